@@ -0,0 +1,135 @@
+"""
+Monthly statement of a pool for record keeping, rendered as a self-contained HTML page that
+browsers print or save as PDF, so that the server needs no PDF toolchain
+"""
+
+import copy
+import datetime
+import html
+import zoneinfo
+from typing import Sequence
+
+from api.formatting import format_money
+from api.types.account_statement import AccountStatement, StatementLine
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.report_snapshot import month_period
+from api.types.transaction import StoredTransaction
+
+STATEMENT_STYLE = """
+body { font-family: sans-serif; font-size: 11pt; margin: 2em; color: #222; }
+h1 { font-size: 16pt; margin-bottom: 0; }
+.period { color: #666; margin-top: 0.25em; }
+table { border-collapse: collapse; width: 100%; margin-top: 1.5em; }
+th, td { padding: 0.3em 0.5em; border-bottom: 1px solid #ddd; text-align: left; }
+td.amount, th.amount { text-align: right; white-space: nowrap; }
+tr.total td { font-weight: bold; border-bottom: none; }
+footer { margin-top: 2em; color: #666; font-size: 9pt; }
+@media print { body { margin: 0; } tr { page-break-inside: avoid; } }
+"""
+
+
+def build_statement(
+    pool: StoredMoneyPool,
+    transactions: Sequence[StoredTransaction],
+    year: int,
+    month: int,
+    tz: zoneinfo.ZoneInfo,
+) -> AccountStatement:
+    """
+    Balances are rolled back from the current one, so the transactions must include all of the
+    pool's ones since the month's start, the later ones included
+    """
+    start, end = month_period(year, month, tz)
+    counted = [t for t in transactions if t.status.is_counted]
+    rolled_back = copy.deepcopy(pool)
+    for t in counted:
+        if t.timestamp.timestamp() >= end.timestamp():
+            rolled_back.update_with_transaction(t.inverted())
+    closing_balance = copy.deepcopy(rolled_back.balance)
+    in_period = sorted(
+        (t for t in counted if start.timestamp() <= t.timestamp.timestamp() < end.timestamp()),
+        key=lambda t: t.timestamp.timestamp(),
+    )
+    for t in in_period:
+        rolled_back.update_with_transaction(t.inverted())
+    opening_balance = copy.deepcopy(rolled_back.balance)
+
+    running = {s.currency: s.amount for s in opening_balance}
+    lines: list[StatementLine] = []
+    for t in in_period:
+        running[t.sum.currency] += t.sum.amount
+        lines.append(
+            StatementLine(
+                transaction_id=t.id,
+                timestamp=t.timestamp,
+                description=t.description,
+                sum=t.sum,
+                balance=MoneySum(amount=running[t.sum.currency], currency=t.sum.currency),
+            )
+        )
+    return AccountStatement(
+        pool_id=pool.id,
+        pool_name=pool.display_name,
+        year=year,
+        month=month,
+        timezone=tz.key,
+        opening_balance=opening_balance,
+        closing_balance=closing_balance,
+        lines=lines,
+    )
+
+
+def render_statement_html(statement: AccountStatement, locale: str) -> str:
+    tz = zoneinfo.ZoneInfo(statement.timezone)
+    start, end = month_period(statement.year, statement.month, tz)
+    last_day = (end - datetime.timedelta(days=1)).date()
+
+    def money(sum: MoneySum) -> str:
+        return html.escape(format_money(sum, locale))
+
+    def balance_rows(label: str, balance: list[MoneySum]) -> list[str]:
+        return [
+            f'<tr class="total"><td colspan="2">{label}</td><td></td>'
+            f'<td class="amount">{money(s)}</td></tr>'
+            for s in balance
+        ]
+
+    rows = balance_rows(f"Opening balance, {start:%Y-%m-%d}", statement.opening_balance)
+    for line in statement.lines:
+        rows.append(
+            f"<tr><td>{line.timestamp.astimezone(tz):%Y-%m-%d}</td>"
+            f"<td>{html.escape(line.description)}</td>"
+            f'<td class="amount">{money(line.sum)}</td>'
+            f'<td class="amount">{money(line.balance)}</td></tr>'
+        )
+    rows.extend(balance_rows(f"Closing balance, {last_day:%Y-%m-%d}", statement.closing_balance))
+
+    title = html.escape(f"{statement.pool_name} statement, {start:%B %Y}")
+    generated_at = statement.generated_at.astimezone(tz)
+    return "\n".join(
+        [
+            "<!DOCTYPE html>",
+            f'<html lang="{html.escape(locale)}">',
+            "<head>",
+            '<meta charset="utf-8">',
+            f"<title>{title}</title>",
+            f"<style>{STATEMENT_STYLE}</style>",
+            "</head>",
+            "<body>",
+            f"<h1>{html.escape(statement.pool_name)}</h1>",
+            f'<p class="period">Statement for {start:%Y-%m-%d} - {last_day:%Y-%m-%d}'
+            f" ({html.escape(statement.timezone)})</p>",
+            "<table>",
+            "<thead><tr><th>Date</th><th>Description</th>"
+            '<th class="amount">Amount</th><th class="amount">Balance</th></tr></thead>',
+            "<tbody>",
+            *rows,
+            "</tbody>",
+            "</table>",
+            f"<footer>{len(statement.lines)} transactions, generated on"
+            f" {generated_at:%Y-%m-%d %H:%M}</footer>",
+            "</body>",
+            "</html>",
+        ]
+    )
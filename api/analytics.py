@@ -0,0 +1,236 @@
+"""
+Insights from the transaction history: forecasts of a pool's balance, unusual spending and
+recurring transactions
+"""
+
+import dataclasses
+import datetime
+import re
+import statistics
+from decimal import Decimal
+from typing import Callable, Sequence
+
+from api.types.allowance import ALLOWANCE_PERIOD, Allowance
+from api.types.api import (
+    AnomalyReason,
+    BalanceProjection,
+    PoolProjectionResponse,
+    RecurringSuggestion,
+    SpendingAnomaly,
+)
+from api.types.datetime import Datetime
+from api.types.ids import MoneyPoolId
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.settings import ReportingPeriod
+from api.types.transaction import StoredTransaction, Transaction, TransactionKind
+
+DEFAULT_LOOKBACK = datetime.timedelta(days=30)
+
+ANOMALY_HISTORY = datetime.timedelta(days=180)
+ANOMALY_Z_SCORE = 3.0
+# fewer past expenses (in the currency, for amounts) are not enough to tell what's unusual
+ANOMALY_MIN_HISTORY = 10
+
+RECURRING_MIN_OCCURRENCES = 3
+RECURRING_MIN_INTERVAL_DAYS = 6  # more frequent repeats are habits rather than schedules
+# deviations from the typical interval and amount that still count as regular
+RECURRING_INTERVAL_TOLERANCE = 0.2
+RECURRING_AMOUNT_TOLERANCE = Decimal("0.1")
+
+
+@dataclasses.dataclass(frozen=True)
+class ScheduledTransaction:
+    pool_id: MoneyPoolId
+    sum: MoneySum
+    timestamp: Datetime
+
+
+def scheduled_allowance_payments(
+    allowance: Allowance, until: datetime.datetime
+) -> list[ScheduledTransaction]:
+    """Both legs of the weekly transfers due before the given time"""
+    scheduled: list[ScheduledTransaction] = []
+    payment_at = allowance.next_payment_at
+    while payment_at < until:
+        amount, currency = allowance.weekly_amount.amount, allowance.weekly_amount.currency
+        scheduled.append(
+            ScheduledTransaction(
+                pool_id=allowance.source_pool_id,
+                sum=MoneySum(amount=-amount, currency=currency),
+                timestamp=payment_at,
+            )
+        )
+        scheduled.append(
+            ScheduledTransaction(
+                pool_id=allowance.pool_id,
+                sum=MoneySum(amount=amount, currency=currency),
+                timestamp=payment_at,
+            )
+        )
+        payment_at += ALLOWANCE_PERIOD
+    return scheduled
+
+
+def month_end(
+    now: datetime.datetime,
+    tz: datetime.tzinfo = datetime.UTC,
+    reporting_period: ReportingPeriod | None = None,  # calendar months by default
+) -> datetime.datetime:
+    return (reporting_period or ReportingPeriod()).containing(now, tz)[1]
+
+
+def project_month_end_balance(
+    pool: StoredMoneyPool,
+    transactions: Sequence[Transaction],
+    scheduled: Sequence[ScheduledTransaction],
+    now: datetime.datetime,
+    lookback: datetime.timedelta = DEFAULT_LOOKBACK,
+    tz: datetime.tzinfo = datetime.UTC,
+    reporting_period: ReportingPeriod | None = None,
+) -> PoolProjectionResponse:
+    """
+    Transactions are the pool's ones, in any order, scheduled ones may be for any pool. Spending
+    over the lookback window (transfers and scheduled transactions aside) is assumed to continue
+    at the same daily rate until the end of the month or the reporting period (in the timezone);
+    each currency is projected separately
+    """
+    end = month_end(now, tz, reporting_period)
+    days_left = Decimal((end - now) / datetime.timedelta(days=1))
+    lookback_days = Decimal(lookback / datetime.timedelta(days=1))
+    recent_expenses = [
+        t
+        for t in transactions
+        if t.kind is TransactionKind.EXPENSE
+        and (now - lookback).timestamp() <= t.timestamp.timestamp() < now.timestamp()
+    ]
+    upcoming = [
+        s
+        for s in scheduled
+        if s.pool_id == pool.id and now.timestamp() <= s.timestamp.timestamp() < end.timestamp()
+    ]
+
+    projections: list[BalanceProjection] = []
+    for balance in pool.balance:
+        currency = balance.currency
+        spent = -sum(
+            (t.sum.amount for t in recent_expenses if t.sum.currency == currency), Decimal(0)
+        )
+        daily_spend = spent / lookback_days
+        scheduled_net = sum(
+            (s.sum.amount for s in upcoming if s.sum.currency == currency), Decimal(0)
+        )
+        projections.append(
+            BalanceProjection(
+                balance=balance,
+                scheduled=MoneySum(amount=scheduled_net, currency=currency),
+                average_daily_spend=MoneySum(amount=daily_spend, currency=currency),
+                projected=MoneySum(
+                    amount=balance.amount + scheduled_net - daily_spend * days_left,
+                    currency=currency,
+                ),
+            )
+        )
+    return PoolProjectionResponse(
+        pool_id=pool.id,
+        projected_until=end,
+        lookback_days=int(lookback_days),
+        projections=projections,
+    )
+
+
+def find_anomalies(
+    history: Sequence[Transaction], transactions: Sequence[StoredTransaction]
+) -> list[SpendingAnomaly]:
+    """
+    Expenses among the transactions that are unusually large (z-score of the amount) or spent on
+    a category (= tag) never seen in the history; the history shouldn't include the transactions
+    """
+    past_expenses = [t for t in history if t.kind is TransactionKind.EXPENSE]
+    known_tags = {tag for t in past_expenses for tag in t.tags}
+    amounts_by_currency: dict[str, list[float]] = {}
+    for t in past_expenses:
+        amounts_by_currency.setdefault(t.sum.currency.code, []).append(float(-t.sum.amount))
+
+    anomalies: list[SpendingAnomaly] = []
+    for t in transactions:
+        if t.kind is not TransactionKind.EXPENSE:
+            continue
+        reasons: list[AnomalyReason] = []
+        z_score: float | None = None
+        amounts = amounts_by_currency.get(t.sum.currency.code, [])
+        if len(amounts) >= ANOMALY_MIN_HISTORY:
+            stdev = statistics.pstdev(amounts)
+            if stdev > 0:
+                z_score = (float(-t.sum.amount) - statistics.mean(amounts)) / stdev
+                if z_score >= ANOMALY_Z_SCORE:
+                    reasons.append(AnomalyReason.LARGE_AMOUNT)
+        if (
+            len(past_expenses) >= ANOMALY_MIN_HISTORY
+            and t.tags
+            and not known_tags.intersection(t.tags)
+        ):
+            reasons.append(AnomalyReason.NEW_CATEGORY)
+        if reasons:
+            anomalies.append(SpendingAnomaly(transaction=t, reasons=reasons, z_score=z_score))
+    return anomalies
+
+
+def recurring_key(description: str) -> str:
+    """Digits and separators aside, e.g. "netflix.com" for "NETFLIX.COM *0123" """
+    return " ".join(re.sub(r"[\d#*]+", " ", description.casefold()).split())
+
+
+def find_recurring(
+    transactions: Sequence[StoredTransaction],
+    description: Callable[[Transaction], str],
+    now: datetime.datetime,
+) -> list[RecurringSuggestion]:
+    """
+    Transactions with the same pool, currency and description (digits aside) repeating at about
+    the same interval with about the same amount, and still active, i.e. not overdue by more than
+    one interval; description gives the plain one, the transactions' may be encrypted
+    """
+    groups: dict[tuple[str, str, str], list[StoredTransaction]] = {}
+    for t in transactions:
+        if t.kind is TransactionKind.TRANSFER:
+            continue
+        key = (t.pool_id, t.sum.currency.code, recurring_key(description(t)))
+        groups.setdefault(key, []).append(t)
+
+    suggestions: list[RecurringSuggestion] = []
+    for group in groups.values():
+        if len(group) < RECURRING_MIN_OCCURRENCES:
+            continue
+        group.sort(key=lambda t: t.timestamp.timestamp())
+        intervals = [
+            (b.timestamp - a.timestamp) / datetime.timedelta(days=1)
+            for a, b in zip(group, group[1:])
+        ]
+        interval = statistics.median(intervals)
+        if interval < RECURRING_MIN_INTERVAL_DAYS or any(
+            abs(i - interval) > interval * RECURRING_INTERVAL_TOLERANCE for i in intervals
+        ):
+            continue
+        amount = statistics.median(t.sum.amount for t in group)
+        tolerance = abs(amount) * RECURRING_AMOUNT_TOLERANCE
+        if any(abs(t.sum.amount - amount) > tolerance for t in group):
+            continue
+        last = group[-1]
+        next_expected_at = last.timestamp + datetime.timedelta(days=interval)
+        if now - next_expected_at > datetime.timedelta(days=interval):
+            continue
+        suggestions.append(
+            RecurringSuggestion(
+                pool_id=last.pool_id,
+                description=description(last),
+                sum=MoneySum(amount=amount, currency=last.sum.currency),
+                interval_days=round(interval),
+                occurrences=len(group),
+                last_at=last.timestamp,
+                next_expected_at=next_expected_at,
+                transaction_ids=[t.id for t in reversed(group)],
+            )
+        )
+    suggestions.sort(key=lambda s: (-s.occurrences, s.next_expected_at.timestamp()))
+    return suggestions
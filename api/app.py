@@ -1,36 +1,276 @@
-import collections
+import asyncio
 import copy
 import datetime
+import hashlib
 import logging
+import secrets
+import urllib.parse
+import uuid
 from contextlib import asynccontextmanager
 from decimal import Decimal
-from typing import Annotated, Iterable, Literal, Sequence
+from pathlib import Path
+from typing import Annotated, Any, Iterable, Literal
 
 import pydantic
-from fastapi import Depends, FastAPI, HTTPException
+from fastapi import (
+    BackgroundTasks,
+    Depends,
+    FastAPI,
+    Header,
+    HTTPException,
+    Query,
+    Request,
+    Response,
+    WebSocket,
+    WebSocketDisconnect,
+)
+from fastapi.exceptions import RequestValidationError
 from fastapi.middleware.cors import CORSMiddleware
-from fastapi.responses import PlainTextResponse
+from fastapi.responses import HTMLResponse, JSONResponse, PlainTextResponse, StreamingResponse
+from fastapi.routing import APIRoute
 
+from api.account_statement import build_statement, render_statement_html
+from api.analytics import (
+    ANOMALY_HISTORY,
+    DEFAULT_LOOKBACK,
+    find_anomalies,
+    find_recurring,
+    month_end,
+    project_month_end_balance,
+    scheduled_allowance_payments,
+)
+from api.audit import AuditedStorage
 from api.auth import Auth
-from api.exchange_rates import ExchangeRates
-from api.storage import Storage, TransactionOrder
+from api.calendar_feed import (
+    allowance_events,
+    month_end_events,
+    recurring_events,
+    render_calendar,
+    scheduled_events,
+)
+from api.challenges import compute_progress
+from api.compression import CompressionConfig, CompressionMiddleware
+from api.digest import (
+    SENDING_INTERVAL,
+    build_digest,
+    digest_end,
+    digest_start,
+    digest_title,
+    render_digest_text,
+)
+from api.events import EventBus
+from api.examples import add_examples_to_schemas, example_for
+from api.exchange_rates import ExchangeRates, RateUnavailable
+from api.formatting import SYMBOLS, format_money
+from api.fx_gains import compute_fx_gains
+from api.goals import SAVING_RATE_WINDOW, compute_goal_progress
+from api.historical_rates import DatedExchangeRates, HistoricalExchangeRates, parse_ecb_csv
+from api.human_dates import HUMAN_DATE_DESCRIPTION, parse_human_span
+from api.limits import RequestLimits, RequestLimitsMiddleware
+from api.live import LiveUpdates, sse_stream
+from api.logs import REQUEST_ID_HEADER, VALID_REQUEST_ID, request_id_var
+from api.notifications import Notifier
+from api.overdraft import compute_overdraft_status
+from api.privacy import MASKED_DESCRIPTION, DescriptionPrivacy
+from api.quick_add import parse_quick_entry
+from api.rebuild import pool_balance_at, rebuild_pool_balance
+from api.reports import (
+    cash_flow,
+    net_worth,
+    spending_by_category,
+    spending_by_payee,
+    sum_transactions,
+    sum_transactions_partially,
+    tag_net_totals,
+)
+from api.service import (
+    DEFAULT_DUPLICATE_WINDOW,
+    DEFAULT_UNDO_WINDOW,
+    MAX_TRANSACTIONS_TO_LOAD,
+    ExpenseService,
+    ServiceError,
+    transfer_legs,
+)
+from api.sharing import share_portions
+from api.statements import (
+    StatementFormat,
+    detect_format,
+    is_imported,
+    ofx_currency,
+    parse_statement,
+    to_transaction,
+)
+from api.static import SpaStaticFiles
+from api.storage import (
+    MAX_SUGGESTION_TRANSACTIONS,
+    IdConflict,
+    Storage,
+    TransactionOrder,
+    VersionConflict,
+    count_suggestions,
+)
+from api.telemetry import Telemetry
+from api.types.allowance import (
+    ALLOWANCE_PERIOD,
+    Allowance,
+    PendingSpend,
+    PendingSpendStatus,
+    StoredAllowance,
+)
 from api.types.api import (
+    AllowanceView,
+    ApplyTemplateRequestBody,
+    BalanceSnapshotReconciliation,
+    BulkTransactionResult,
+    BulkTransactionsRequestBody,
+    CalendarTokenResponse,
+    CashFlowReportResponse,
+    CashWithdrawalRequestBody,
+    CashWithdrawalResponse,
+    CategorizationRuleUpdate,
+    CategorySpendingReportResponse,
+    ChallengeProgress,
+    CloseUnaccountedRequestBody,
+    CounterpartyDebtSummary,
+    CreateAllowanceRequestBody,
+    CreatedTransactionResponse,
+    CreatePoolRequestBody,
+    CreateReportSnapshotRequestBody,
+    CurrencyInfo,
+    DuplicateTransactionResponse,
+    FormattedMoney,
+    FxGainsReportResponse,
+    GoalProgress,
+    GoalUpdate,
+    HistoricalRatesImportResponse,
+    LiveTicketResponse,
     MainApiRouteResponse,
     MoneyPoolAttributesUpdate,
+    OverdraftStatus,
+    PayeeSpendingReportResponse,
+    PoolBalanceResponse,
+    PoolNoteUpdate,
+    PoolNoteView,
+    PoolOrderRequestBody,
+    PoolProjectionResponse,
+    PoolTransferRequestBody,
+    QuickAddRequestBody,
+    RateHistoryResponse,
+    ReconciliationMatchUpdate,
+    ReconciliationWorksheet,
+    ReconciliationWorksheetItem,
+    RecurringSuggestion,
+    RedenominatePoolRequestBody,
     ReportApiRouteResponse,
     ReportPoolSnapshot,
     ReportPoolStats,
-    ReportTagNetTotal,
+    ReportValueChange,
+    RuleApplicationResponse,
+    SensitiveViewRequestBody,
+    SensitiveViewTokenResponse,
+    SettleDebtRequestBody,
+    ShareTransactionRequestBody,
+    SpendingAnomaly,
+    StartReconciliationRequestBody,
+    StatementImportResponse,
     SyncBalanceRequestBody,
+    SyncChangeError,
+    SyncChangeResult,
+    SyncChangeStatus,
+    SyncResponse,
+    SyncUploadRequestBody,
+    SyncUploadResponse,
+    TokenScope,
+    TransactionEdit,
+    TransactionStatusUpdate,
+    TransactionTemplateUpdate,
     TransactionUpdate,
     TransferMoneyRequestBody,
+    UnaccountedSpendingResponse,
+    UndoResponse,
+    UserAccountUpdate,
+    UserAccountView,
+)
+from api.types.audit import AuditEntry
+from api.types.balance_snapshot import BalanceSnapshot, StoredBalanceSnapshot
+from api.types.challenge import Challenge, StoredChallenge
+from api.types.currency import (
+    Currency,
+    CurrencyAdapter,
+    all_currencies,
+    parse_currency,
+    register_currencies,
+)
+from api.types.currency_iso4217 import CurrencyISO4217
+from api.types.debt import Debt, DebtDirection, StoredDebt
+from api.types.digest import Digest, DigestPeriod
+from api.types.events import (
+    BaseEvent,
+    ChallengeCompleted,
+    MonthClosed,
+    PoolUpdated,
+    TransactionCreated,
+    TransactionDeleted,
+    TransactionUpdated,
+)
+from api.types.export import UserDataExport
+from api.types.goal import Goal, StoredGoal
+from api.types.historical_rates import HistoricalRate
+from api.types.ids import (
+    MoneyPoolId,
+    PayeeId,
+    ReconciliationId,
+    ReportSnapshotId,
+    TransactionId,
+    UserId,
 )
-from api.types.currency import Currency, CurrencyAdapter, parse_currency
-from api.types.datetime import Datetime
-from api.types.ids import UserId
 from api.types.money_pool import MoneyPool, StoredMoneyPool
 from api.types.money_sum import MoneySum
-from api.types.transaction import StoredTransaction, Transaction, TransactionFilter
+from api.types.month_close import (
+    MANUAL_ITEMS,
+    ChecklistItem,
+    ChecklistItemStatus,
+    MonthClose,
+    MonthCloseStatus,
+    MonthCloseView,
+)
+from api.types.net_worth import NetWorthSnapshot
+from api.types.note import PoolNote, StoredPoolNote
+from api.types.operation import OperationKind
+from api.types.payee import Payee, StoredPayee
+from api.types.rebuild import RebuildJob, RebuildStatus, RebuildTarget
+from api.types.reconciliation import (
+    Reconciliation,
+    ReconciliationAdjustment,
+    ReconciliationStatus,
+    StoredReconciliation,
+)
+from api.types.redenomination import PoolRedenomination
+from api.types.report_snapshot import (
+    ReportSnapshot,
+    StoredReportSnapshot,
+    diff_reports,
+    month_period,
+)
+from api.types.rule import CategorizationRule, StoredCategorizationRule
+from api.types.settings import Locale, UserSettings
+from api.types.suggestion import Suggestion, SuggestionField
+from api.types.sync import SyncedEntity
+from api.types.telemetry import TelemetryReport
+from api.types.template import StoredTransactionTemplate, TransactionTemplate
+from api.types.transaction import (
+    ALLOWANCE_TAG,
+    DEBT_TAG,
+    FEE_TAG,
+    WITHDRAWAL_TAG,
+    StoredTransaction,
+    Transaction,
+    TransactionFilter,
+    TransactionKind,
+    TransactionSource,
+    TransactionStatus,
+    splits_error,
+)
 
 logger = logging.getLogger(__name__)
 
@@ -40,31 +280,38 @@ ReportPoints = Annotated[int, pydantic.Field(ge=2, le=360)]
 
 Ok = Literal["OK"]
 
-EUR = parse_currency("EUR")
+STATIC_MOUNT_PATH = "/app"
 
+ALLOWANCES_CHECK_INTERVAL_SEC = 60 * 60
 
-async def coerce_to_pool(
-    transaction: Transaction, pool: MoneyPool, exchange_rates: ExchangeRates
-) -> None:
-    if transaction.sum.currency in [sum.currency for sum in pool.balance]:
-        return
-    transaction.original_currency = transaction.sum.currency
-    rate = await exchange_rates.get_rate(
-        base=transaction.original_currency,
-        target=pool.balance[0].currency,
-    )
-    transaction.sum = MoneySum(
-        amount=Decimal(float(transaction.sum.amount) * rate.rate),
-        currency=rate.target,
-    )
+SCHEDULED_TRANSACTIONS_CHECK_INTERVAL_SEC = 60
+
+MAX_SUGGESTIONS = 50
+
+SUGGESTIONS_WINDOW = datetime.timedelta(days=180)
+
+MAX_RATE_HISTORY_PERIOD = datetime.timedelta(days=10 * 366)
+
+# the day's snapshot is overwritten until the day is over, ending up with its closing balances
+NET_WORTH_SNAPSHOT_INTERVAL_SEC = 60 * 60
+
+MAX_NET_WORTH_HISTORY_PERIOD = datetime.timedelta(days=10 * 366)
+
+TELEMETRY_INTERVAL_SEC = 24 * 60 * 60
 
 
 async def pool_total(
     pool: MoneyPool, exchange_rates: ExchangeRates, target_currency: Currency
-) -> tuple[MoneySum, dict[Currency, float]]:
+) -> tuple[MoneySum, dict[Currency, float], list[MoneySum]]:
+    """Balance sums in currencies with unavailable rates are returned as is, not in the total"""
     contributions: dict[Currency, float] = {}
+    unconverted: list[MoneySum] = []
     for sum_ in pool.balance:
-        rate = await exchange_rates.get_rate(base=sum_.currency, target=target_currency)
+        try:
+            rate = await exchange_rates.get_rate(base=sum_.currency, target=target_currency)
+        except RateUnavailable:
+            unconverted.append(sum_)
+            continue
         contributions[sum_.currency] = float(sum_.amount) * rate.rate
     total_amount = sum(p for p in contributions.values())
     total = MoneySum(amount=Decimal(total_amount), currency=target_currency)
@@ -73,33 +320,83 @@ async def pool_total(
         fractions = {c: p / total_amount for c, p in contributions.items()}
     else:
         fractions = {c: 1 / len(contributions) for c in contributions}
-    return total, fractions
+    return total, fractions, unconverted
 
 
-async def sum_transactions(
-    transactions: Iterable[Transaction], exchange_rates: ExchangeRates, target_currency: Currency
-) -> MoneySum:
-    total_amt = 0.0
-    for t in transactions:
-        if target_currency.code == EUR.code and t.amount_eur is not None:
-            total_amt += t.amount_eur
-        else:
-            rate = await exchange_rates.get_rate(base=t.sum.currency, target=target_currency)
-            total_amt += float(t.sum.amount) * rate.rate
-    return MoneySum(
-        amount=Decimal(total_amt),
-        currency=target_currency,
+def last_synced_at(
+    pool_transactions: Iterable[Transaction], currency: Currency
+) -> datetime.datetime | None:
+    """Timestamp of the latest diffuse transaction, i.e. last time the balance matched reality"""
+    return max(
+        (t.timestamp for t in pool_transactions if t.is_diffuse and t.sum.currency == currency),
+        key=lambda dt: dt.timestamp(),
+        default=None,
     )
 
 
-def transactions_per_tag(transactions: Sequence[Transaction]):
-    res: dict[str | None, list[Transaction]] = collections.defaultdict(list)
-    for t in transactions:
-        for tag in t.tags:
-            res[tag].append(t)
-        if not t.tags:
-            res[None].append(t)
-    return res
+def unaccounted_withdrawn_cash(
+    pool_transactions: Iterable[Transaction], currency: Currency
+) -> MoneySum:
+    """
+    Cash withdrawn into the pool since its last diffuse transaction (i.e. last time the balance was
+    synced with reality), less the spending tracked since then
+    """
+    since = last_synced_at(pool_transactions, currency)
+    unaccounted = Decimal(0)
+    for t in pool_transactions:
+        if t.sum.currency != currency:
+            continue
+        if since is not None and t.timestamp.timestamp() <= since.timestamp():
+            continue
+        if WITHDRAWAL_TAG in t.tags and t.sum.amount > 0:
+            unaccounted += t.sum.amount
+        elif t.sum.amount < 0 and t.transfer_id is None:
+            unaccounted += t.sum.amount
+    return MoneySum(amount=max(unaccounted, Decimal(0)), currency=currency)
+
+
+def accept_client_transaction(transaction: Transaction) -> Transaction:
+    """
+    Only transfer routes link transactions with a transfer id, a client-set one would hide an
+    expense from reports
+    """
+    transaction.transfer_id = None
+    return transaction
+
+
+def parse_if_match(if_match: str | None) -> int:
+    """
+    If-Match header carries the entity version the client has seen, possibly as an ETag; it's
+    required on updates, so that a client can't overwrite a change it hasn't seen
+    """
+    if if_match is None:
+        raise HTTPException(status_code=428, detail="If-Match header with entity version required")
+    try:
+        return int(if_match.strip().removeprefix("W/").strip('"'))
+    except ValueError:
+        raise HTTPException(status_code=400, detail="If-Match header must contain entity version")
+
+
+IfMatch = Annotated[str | None, Header()]
+
+
+def list_etag(change_counter: int, request: Request, *variant: object) -> str:
+    """Weak ETag of a list response, changing with the user's data, the query and the variant"""
+    key = ":".join(str(part) for part in (change_counter, request.url.query, *variant))
+    return f'W/"{hashlib.sha256(key.encode()).hexdigest()[:16]}"'
+
+
+def etag_matches(if_none_match: str | None, etag: str) -> bool:
+    """Weak comparison, as If-None-Match calls for"""
+    if if_none_match is None:
+        return False
+    if if_none_match.strip() == "*":
+        return True
+    opaque = etag.removeprefix("W/")
+    return any(tag.strip().removeprefix("W/") == opaque for tag in if_none_match.split(","))
+
+
+IfNoneMatch = Annotated[str | None, Header()]
 
 
 def create_app(
@@ -107,39 +404,299 @@ def create_app(
     auth: Auth,
     exchange_rates: ExchangeRates,
     frontend_origins: list[str] | None = None,
+    cors_allow_methods: list[str] | None = None,
+    cors_allow_headers: list[str] | None = None,
+    static_dir: Path | None = None,
+    privacy: DescriptionPrivacy | None = None,
+    notifier: Notifier | None = None,
+    digest_period: DigestPeriod | None = None,
+    telemetry: Telemetry | None = None,
+    undo_window: datetime.timedelta = DEFAULT_UNDO_WINDOW,
+    # same pool, sum and description within the window is considered a duplicate, zero disables
+    duplicate_window: datetime.timedelta = DEFAULT_DUPLICATE_WINDOW,
+    admin_user_ids: list[UserId] | None = None,
+    event_bus: EventBus | None = None,
+    extra_currencies: list[CurrencyISO4217] | None = None,  # e.g. crypto
+    migrate_on_startup: bool = False,
+    notify_anomalies: bool = False,  # through the notifier, as transactions are added
+    compression: CompressionConfig | None = None,
+    limits: RequestLimits | None = None,
+    grpc_port: int | None = None,  # needs requirements.grpc.txt
 ) -> FastAPI:
+    events = event_bus if event_bus is not None else EventBus()
+    service = ExpenseService(
+        storage,
+        exchange_rates,
+        events,
+        privacy=privacy,
+        undo_window=undo_window,
+        duplicate_window=duplicate_window,
+    )
+    register_currencies(extra_currencies or [])
+
+    if notifier is not None:
+
+        async def notify_challenge_completed(event: ChallengeCompleted) -> None:
+            await notifier.notify(
+                event.user_id,
+                subject=f"Challenge completed: {event.challenge.name}",
+                text=f"Congratulations, you've completed the {event.challenge.name!r} challenge!",
+            )
+
+        events.subscribe(ChallengeCompleted, notify_challenge_completed)
+
+    if notifier is not None and notify_anomalies:
+
+        async def notify_spending_anomaly(event: TransactionCreated) -> None:
+            transaction = event.transaction
+            history = await storage.load_transactions(
+                event.user_id,
+                filter=TransactionFilter(
+                    min_timestamp=transaction.timestamp - ANOMALY_HISTORY,
+                    max_timestamp=transaction.timestamp,
+                ),
+                offset=0,
+                count=MAX_TRANSACTIONS_TO_LOAD,
+                order=TransactionOrder.LATEST,
+            )
+            history = [t for t in history if t.id != transaction.id]
+            locale = (await storage.load_user_settings(event.user_id)).locale
+            for anomaly in find_anomalies(history, [transaction]):
+                reasons = ", ".join(r.value.replace("_", " ") for r in anomaly.reasons)
+                await notifier.notify(
+                    event.user_id,
+                    subject="Unusual spending",
+                    text=(
+                        f"{format_money(transaction.sum, locale)} for "
+                        f"{service.plain_description(transaction)!r}: {reasons}"
+                    ),
+                )
+
+        events.subscribe(TransactionCreated, notify_spending_anomaly)
+
+    live = LiveUpdates()
+    events.subscribe(BaseEvent, live.publish)
+
     @asynccontextmanager
     async def lifespan(_: FastAPI):
         logger.info("Running lifespan methods")
         await storage.initialize()
         logger.info("Storage initialized")
+        if migrate_on_startup:
+            applied = await storage.migrate(dry_run=False)
+            logger.info(f"Applied {len(applied)} storage migrations")
         await auth.initialize()
         logger.info("Auth initialized")
         await exchange_rates.initialize()
         logger.info("Exchange rates initialized")
+        digests_task: asyncio.Task | None = None
+        if notifier is not None and digest_period is not None:
+            digests_task = asyncio.create_task(send_digests_periodically(notifier, digest_period))
+            logger.info(f"Sending {digest_period.value} digests")
+        allowances_task = asyncio.create_task(pay_allowances_periodically())
+        scheduled_task = asyncio.create_task(promote_scheduled_transactions_periodically())
+        net_worth_task = asyncio.create_task(record_net_worth_periodically())
+        telemetry_task: asyncio.Task | None = None
+        if telemetry is not None and telemetry.enabled:
+            telemetry_task = asyncio.create_task(send_telemetry_periodically(telemetry))
+            logger.info(f"Sending telemetry to {telemetry.endpoint}")
+        grpc_server = None
+        if grpc_port is not None:
+            from api.grpc_api import start_grpc_server  # grpcio is an optional dependency
+
+            grpc_server = await start_grpc_server(service, auth, grpc_port)
         yield
-        # logger.info("Nothing to cleanup, bye")
+        if grpc_server is not None:
+            await grpc_server.stop(grace=5)
+        allowances_task.cancel()
+        scheduled_task.cancel()
+        net_worth_task.cancel()
+        if digests_task is not None:
+            digests_task.cancel()
+        if telemetry_task is not None:
+            telemetry_task.cancel()
+        await storage.close()
 
+    add_examples_to_schemas()
     app = FastAPI(title="tiny-expense-tracker-api", lifespan=lifespan)
 
+    if limits is not None:
+        # added first to be inside CORS, for the rejected requests to get its headers too
+        app.add_middleware(RequestLimitsMiddleware, limits=limits)
     if frontend_origins is not None:
         app.add_middleware(
             CORSMiddleware,
             allow_origins=frontend_origins,
             allow_credentials=True,
-            allow_methods=["*"],
-            allow_headers=["*"],
+            allow_methods=cors_allow_methods or ["*"],
+            allow_headers=cors_allow_headers or ["*"],
+            expose_headers=[REQUEST_ID_HEADER, "ETag"],
+        )
+    if compression is not None:
+        app.add_middleware(CompressionMiddleware, config=compression)
+
+    @app.middleware("http")
+    async def assign_request_id(request: Request, call_next):
+        request_id = request.headers.get(REQUEST_ID_HEADER, "")
+        if not VALID_REQUEST_ID.fullmatch(request_id):
+            request_id = uuid.uuid4().hex
+        token = request_id_var.set(request_id)
+        try:
+            response = await call_next(request)
+        finally:
+            request_id_var.reset(token)
+        response.headers[REQUEST_ID_HEADER] = request_id
+        return response
+
+    if telemetry is not None:
+
+        @app.middleware("http")
+        async def record_feature_usage(request: Request, call_next):
+            response = await call_next(request)
+            route = request.scope.get("route")
+            if route is not None:
+                # route template, not the actual path, to avoid reporting ids
+                telemetry.record(f"{request.method} {route.path}")
+            return response
+
+    @app.exception_handler(RateUnavailable)
+    async def rate_unavailable_handler(request: Request, exc: RateUnavailable) -> JSONResponse:
+        return JSONResponse(
+            status_code=503,
+            content={"detail": str(exc), "base": exc.base.code, "target": exc.target.code},
+        )
+
+    @app.exception_handler(ServiceError)
+    async def service_error_handler(request: Request, exc: ServiceError) -> JSONResponse:
+        return JSONResponse(status_code=exc.status_code, content={"detail": str(exc)})
+
+    @app.exception_handler(IdConflict)
+    async def id_conflict_handler(request: Request, exc: IdConflict) -> JSONResponse:
+        return JSONResponse(status_code=409, content={"detail": str(exc)})
+
+    @app.exception_handler(VersionConflict)
+    async def version_conflict_handler(request: Request, exc: VersionConflict) -> JSONResponse:
+        return JSONResponse(
+            status_code=409,
+            content={
+                "detail": "Entity was modified concurrently",
+                "current_version": exc.actual_version,
+            },
         )
 
-    AuthorizedUser = Annotated[UserId, Depends(auth.authorize_request)]
+    async def authorize_enabled(
+        user_id: Annotated[UserId, Depends(auth.authorize_request)],
+    ) -> UserId:
+        if await storage.is_user_disabled(user_id):
+            raise HTTPException(status_code=403, detail="Account is disabled")
+        return user_id
+
+    AuthorizedUser = Annotated[UserId, Depends(authorize_enabled)]
     auth.setup_login_routes(app)
 
+    async def authorize_write(
+        user_id: AuthorizedUser, scope: Annotated[TokenScope, Depends(auth.token_scope)]
+    ) -> UserId:
+        if scope is TokenScope.READ:
+            raise HTTPException(status_code=403, detail="Token is read-only")
+        return user_id
+
+    WritableUser = Annotated[UserId, Depends(authorize_write)]
+
+    async def descriptions_visible(
+        user_id: AuthorizedUser, sensitive_view_token: Annotated[str | None, Header()] = None
+    ) -> bool:
+        return privacy is None or privacy.can_view(user_id, sensitive_view_token)
+
+    DescriptionsVisible = Annotated[bool, Depends(descriptions_visible)]
+
+    async def authorize_admin(user_id: AuthorizedUser) -> UserId:
+        if user_id not in (admin_user_ids or []):
+            raise HTTPException(status_code=403, detail="Admin access required")
+        return user_id
+
+    AdminUser = Annotated[UserId, Depends(authorize_admin)]
+
+    async def resolve_query_dates(
+        user_id: UserId, start: str | None, end: str | None
+    ) -> tuple[datetime.datetime | None, datetime.datetime | None]:
+        """Human-friendly "from" and "to", e.g. "to=yesterday" includes the whole of yesterday"""
+        settings = await storage.load_user_settings(user_id)
+        now = datetime.datetime.now(tz=datetime.UTC)
+        resolved: list[datetime.datetime | None] = []
+        for param, value in (("from", start), ("to", end)):
+            if value is None:
+                resolved.append(None)
+                continue
+            try:
+                span = parse_human_span(value, now, settings.tzinfo, settings.week_start)
+            except ValueError as e:
+                raise RequestValidationError(
+                    [
+                        {
+                            "loc": ("query", param),
+                            "msg": str(e),
+                            "type": "value_error",
+                            "input": value,
+                        }
+                    ]
+                )
+            resolved.append(span[0] if param == "from" else span[1])
+        if any(dt is not None and dt.tzinfo is None for dt in resolved):
+            raise HTTPException(
+                status_code=400, detail="All datetimes must have timezone info specified"
+            )
+        return resolved[0], resolved[1]
+
+    async def report_period(
+        user_id: AuthorizedUser,
+        start: Annotated[
+            str | None, Query(alias="from", description=HUMAN_DATE_DESCRIPTION)
+        ] = None,
+        end: Annotated[str | None, Query(alias="to", description=HUMAN_DATE_DESCRIPTION)] = None,
+        legacy_start: Annotated[
+            str | None, Query(alias="start", deprecated=True, description="Use from instead")
+        ] = None,
+        legacy_end: Annotated[
+            str | None, Query(alias="end", deprecated=True, description="Use to instead")
+        ] = None,
+    ) -> tuple[datetime.datetime, datetime.datetime]:
+        """The period is required, as "from" or as the deprecated "start" of the older routes"""
+        start = start or legacy_start
+        if start is None:
+            raise RequestValidationError(
+                [{"loc": ("query", "from"), "msg": "Field required", "type": "missing"}]
+            )
+        start_dt, end_dt = await resolve_query_dates(user_id, start, end or legacy_end)
+        assert start_dt is not None
+        end_dt = end_dt or datetime.datetime.now(tz=datetime.UTC)
+        if end_dt <= start_dt:
+            raise HTTPException(status_code=400, detail="Period end must be after its start")
+        return start_dt, end_dt
+
+    ReportPeriod = Annotated[tuple[datetime.datetime, datetime.datetime], Depends(report_period)]
+
+    async def listing_period(
+        user_id: AuthorizedUser,
+        start: Annotated[
+            str | None, Query(alias="from", description=HUMAN_DATE_DESCRIPTION)
+        ] = None,
+        end: Annotated[str | None, Query(alias="to", description=HUMAN_DATE_DESCRIPTION)] = None,
+    ) -> tuple[datetime.datetime | None, datetime.datetime | None]:
+        return await resolve_query_dates(user_id, start, end)
+
+    ListingPeriod = Annotated[
+        tuple[datetime.datetime | None, datetime.datetime | None], Depends(listing_period)
+    ]
+
     @app.get("/")
     async def ping() -> dict[str, str]:
         return {"message": "Hi"}
 
     @app.get("/main")
-    async def main_api_route(user_id: AuthorizedUser) -> MainApiRouteResponse:
+    async def main_api_route(
+        user_id: AuthorizedUser, visible: DescriptionsVisible
+    ) -> MainApiRouteResponse:
         pools = await storage.load_pools(user_id)
         last_transactions = await storage.load_transactions(
             user_id,
@@ -150,23 +707,19 @@ def create_app(
         )
         return MainApiRouteResponse(
             pools=pools,
-            last_transactions=last_transactions,
+            last_transactions=service.present_transactions(last_transactions, visible),
+            pinned=await present_notes(
+                user_id, await storage.load_pool_notes(user_id, pool_id=None), visible
+            ),
         )
 
-    @app.get("/report")
-    async def generate_report(
-        user_id: AuthorizedUser,
-        start: Datetime,
-        end: Datetime | None = None,
-        points: ReportPoints = 30,
-        target_currency: str = "EUR",
+    async def compute_report(
+        user_id: UserId,
+        start: datetime.datetime,
+        end: datetime.datetime | None,
+        points: int,
+        target_currency_: Currency,
     ) -> ReportApiRouteResponse:
-        if start.tzinfo is None or (end is not None and end.tzinfo is None):
-            raise HTTPException(
-                status_code=400, detail="All datetimes must have timezone info specified"
-            )
-        MAX_TRANSACTIONS_TO_LOAD = 100_000
-        target_currency_: Currency = CurrencyAdapter.validate_python(target_currency)
         pools = await storage.load_pools(user_id)
         current_pools_by_id = {p.id: p for p in pools}
 
@@ -230,18 +783,23 @@ def create_app(
             "Incorrect number of transaction subsets"
         )
 
+        # transactions are converted at the rates on their dates, balances at the snapshot ones
+        transaction_rates = DatedExchangeRates(exchange_rates, storage)
         snapshots: list[ReportPoolSnapshot] = []
         for dt, pools_at_snapshot, transactions_before_snapshot in zip(
             snapshot_dts, pools_by_id_snapshots, transaction_before_snapshot
         ):
             overall_total = MoneySum(amount=Decimal(0), currency=target_currency_)
             pool_stats: list[ReportPoolStats] = []
+            rates_at_snapshot = HistoricalExchangeRates(storage, exchange_rates, on=dt.date())
             for pool in pools_at_snapshot:
-                pool_total_, fractions = await pool_total(
-                    pool, exchange_rates, target_currency=target_currency_
+                pool_total_, fractions, unconverted = await pool_total(
+                    pool, rates_at_snapshot, target_currency=target_currency_
                 )
                 pool_stats.append(
-                    ReportPoolStats(pool=pool, total=pool_total_, fractions=fractions)
+                    ReportPoolStats(
+                        pool=pool, total=pool_total_, fractions=fractions, unconverted=unconverted
+                    )
                 )
                 overall_total.amount += pool_total_.amount
             snapshots.append(
@@ -249,180 +807,1981 @@ def create_app(
                     timestamp=dt,
                     pool_stats=pool_stats,
                     overall_total=overall_total,
-                    tag_totals_from_prev_snapshot=sorted(
-                        [
-                            ReportTagNetTotal(
-                                tag=tag,
-                                total=await sum_transactions(
-                                    ts,
-                                    exchange_rates=exchange_rates,
-                                    target_currency=target_currency_,
-                                ),
-                            )
-                            for tag, ts in transactions_per_tag(
-                                transactions_before_snapshot
-                            ).items()
-                        ],
-                        key=lambda rtnt: rtnt.total.amount,
+                    tag_totals_from_prev_snapshot=await tag_net_totals(
+                        transactions_before_snapshot, transaction_rates, target_currency_
                     ),
                 )
             )
 
+        counted = [t for t in transactions if t.status.is_counted and t.transfer_id is None]
+        spent, spent_unconverted = await sum_transactions_partially(
+            transactions=(t.inverted() for t in counted if t.sum.amount < 0),
+            exchange_rates=transaction_rates,
+            target_currency=target_currency_,
+        )
+        made, made_unconverted = await sum_transactions_partially(
+            transactions=(t for t in counted if t.sum.amount > 0),
+            exchange_rates=transaction_rates,
+            target_currency=target_currency_,
+        )
         return ReportApiRouteResponse(
             snapshots=snapshots,
-            spent=await sum_transactions(
-                transactions=(t.inverted() for t in transactions if t.sum.amount < 0),
-                exchange_rates=exchange_rates,
-                target_currency=target_currency_,
-            ),
-            made=await sum_transactions(
-                transactions=(t for t in transactions if t.sum.amount > 0),
-                exchange_rates=exchange_rates,
-                target_currency=target_currency_,
-            ),
-            tag_totals=sorted(
-                [
-                    ReportTagNetTotal(
-                        tag=tag,
-                        total=await sum_transactions(
-                            ts, exchange_rates=exchange_rates, target_currency=target_currency_
-                        ),
-                    )
-                    for tag, ts in transactions_per_tag(transactions).items()
-                ],
-                key=lambda rtnt: rtnt.total.amount,
-            ),
+            spent=spent,
+            made=made,
+            tag_totals=await tag_net_totals(transactions, transaction_rates, target_currency_),
+            spent_unconverted=spent_unconverted,
+            made_unconverted=made_unconverted,
         )
 
-    @app.post("/pools")
-    async def create_pool(user_id: AuthorizedUser, new_pool: MoneyPool) -> StoredMoneyPool:
-        return await storage.add_pool(user_id=user_id, new_pool=new_pool)
+    async def currency_or_default(user_id: UserId, target_currency: str | None) -> Currency:
+        if target_currency is not None:
+            try:
+                return CurrencyAdapter.validate_python(target_currency)
+            except pydantic.ValidationError:
+                raise HTTPException(
+                    status_code=400, detail=f"Unknown currency: {target_currency}"
+                )
+        return (await storage.load_user_settings(user_id)).default_currency
 
-    @app.get("/pools")
-    async def get_pools(user_id: AuthorizedUser) -> list[StoredMoneyPool]:
-        return await storage.load_pools(user_id=user_id)
+    @app.get("/report")
+    async def generate_report(
+        user_id: AuthorizedUser,
+        period: ReportPeriod,
+        points: ReportPoints = 30,
+        target_currency: str | None = None,  # the user's default currency if omitted
+    ) -> ReportApiRouteResponse:
+        start, end = period
+        report = await compute_report(
+            user_id,
+            start=start,
+            end=end,
+            points=points,
+            target_currency_=await currency_or_default(user_id, target_currency),
+        )
+        locale = (await storage.load_user_settings(user_id)).locale
+        report.spent_formatted = format_money(report.spent, locale)
+        report.made_formatted = format_money(report.made, locale)
+        return report
 
-    @app.get("/pools/{pool_id}")
-    async def get_pool(user_id: AuthorizedUser, pool_id: str) -> StoredMoneyPool:
-        pool = await storage.load_pool(user_id=user_id, pool_id=pool_id)
-        if pool is None:
-            raise HTTPException(status_code=404, detail="Pool not found")
-        else:
-            return pool
+    @app.get("/currencies")
+    async def get_currencies() -> list[CurrencyInfo]:
+        return [
+            CurrencyInfo(
+                code=c.code,
+                name=c.name,
+                symbol=SYMBOLS.get(c.code),
+                exponent=c.precision,
+                is_iso=c.is_iso,
+            )
+            for c in all_currencies()
+        ]
 
-    @app.put("/pools/{pool_id}", response_class=PlainTextResponse)
-    async def modify_pool(
-        user_id: AuthorizedUser, pool_id: str, update: MoneyPoolAttributesUpdate
-    ) -> Ok:
-        if await storage.set_pool_attributes(user_id, pool_id=pool_id, update=update):
-            return "OK"
-        else:
-            raise HTTPException(status_code=404, detail="Pool not found")
+    @app.get("/format")
+    async def format_money_sum(
+        user_id: AuthorizedUser,
+        amount: Decimal,
+        currency: str | None = None,
+        locale: Locale | None = None,
+    ) -> FormattedMoney:
+        """In the user's default currency and locale unless specified"""
+        money = MoneySum(amount=amount, currency=await currency_or_default(user_id, currency))
+        locale = locale or (await storage.load_user_settings(user_id)).locale
+        return FormattedMoney(sum=money, locale=locale, formatted=format_money(money, locale))
 
-    @app.post("/transactions")
-    async def add_transaction(
-        user_id: AuthorizedUser, transaction: Transaction
-    ) -> StoredTransaction:
-        money_pool = await storage.load_pool(user_id=user_id, pool_id=transaction.pool_id)
-        if money_pool is None:
+    @app.get("/report/categories")
+    async def generate_category_spending_report(
+        user_id: AuthorizedUser,
+        period: ReportPeriod,
+        target_currency: str | None = None,
+    ) -> CategorySpendingReportResponse:
+        start, end_dt = period
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=start - (end_dt - start), max_timestamp=end_dt),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
             raise HTTPException(
-                status_code=400,
-                detail="Transaction is attributed to non-existent money pool",
+                status_code=400, detail="Too many transactions in the requested period"
             )
-        to_eur = await exchange_rates.get_rate(transaction.sum.currency, EUR)
-        transaction.amount_eur = float(transaction.sum.amount) * to_eur.rate
-        await coerce_to_pool(transaction, money_pool, exchange_rates)
-        return await storage.add_transaction(user_id=user_id, transaction=transaction)
+        return await spending_by_category(
+            transactions,
+            exchange_rates=DatedExchangeRates(exchange_rates, storage),
+            start=start,
+            end=end_dt,
+            target_currency=await currency_or_default(user_id, target_currency),
+        )
 
-    @app.get("/transactions")
-    async def get_transactions(
+    @app.get("/report/payees")
+    async def generate_payee_spending_report(
         user_id: AuthorizedUser,
-        offset: Offset = 0,
-        count: Count = 10,
-        order: TransactionOrder = TransactionOrder.LATEST,
-    ) -> list[StoredTransaction]:
-        return await storage.load_transactions(
-            user_id=user_id,
-            filter=None,
-            offset=offset,
-            count=count,
-            order=order,
+        period: ReportPeriod,
+        target_currency: str | None = None,
+    ) -> PayeeSpendingReportResponse:
+        start, end_dt = period
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=start, max_timestamp=end_dt),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise HTTPException(
+                status_code=400, detail="Too many transactions in the requested period"
+            )
+        return await spending_by_payee(
+            transactions,
+            await storage.load_payees(user_id),
+            exchange_rates=DatedExchangeRates(exchange_rates, storage),
+            start=start,
+            end=end_dt,
+            target_currency=await currency_or_default(user_id, target_currency),
         )
 
-    @app.delete("/transactions/{transaction_id}", response_class=PlainTextResponse)
-    async def delete_transaction(user_id: AuthorizedUser, transaction_id: str) -> Ok:
-        if await storage.delete_transaction(user_id=user_id, transaction_id=transaction_id):
-            return "OK"
-        else:
-            raise HTTPException(status_code=404, detail="No such transaction")
-
-    @app.put("/transactions/{transaction_id}", response_class=PlainTextResponse)
-    async def update_transaction(
-        user_id: AuthorizedUser, transaction_id: str, update: TransactionUpdate
-    ) -> Ok:
-        if await storage.update_transaction(
-            user_id=user_id, transaction_id=transaction_id, update=update
-        ):
-            return "OK"
-        else:
-            raise HTTPException(status_code=404, detail="No such transaction")
-
-    @app.post("/transfer", response_class=PlainTextResponse)
-    async def make_transfer(user_id: AuthorizedUser, body: TransferMoneyRequestBody) -> Ok:
-        if body.sum.amount.is_zero():
-            raise HTTPException(status_code=400, detail="Transfer amount can't be zero")
+    @app.get("/report/cashflow")
+    async def generate_cash_flow_report(
+        user_id: AuthorizedUser,
+        period: ReportPeriod,
+        target_currency: str | None = None,
+    ) -> CashFlowReportResponse:
+        start, end_dt = period
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=start, max_timestamp=end_dt),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise HTTPException(
+                status_code=400, detail="Too many transactions in the requested period"
+            )
+        settings = await storage.load_user_settings(user_id)
+        return await cash_flow(
+            transactions,
+            exchange_rates=DatedExchangeRates(exchange_rates, storage),
+            start=start,
+            end=end_dt,
+            target_currency=await currency_or_default(user_id, target_currency),
+            tz=settings.tzinfo,
+            reporting_period=settings.reporting_period,
+        )
 
-        from_pool = await storage.load_pool(user_id=user_id, pool_id=body.from_pool)
-        to_pool = await storage.load_pool(user_id=user_id, pool_id=body.to_pool)
-        if from_pool is None or to_pool is None:
+    async def make_digest(
+        user_id: UserId, period: DigestPeriod, target_currency: Currency | None = None
+    ) -> Digest:
+        """In the user's default currency unless specified"""
+        settings = await storage.load_user_settings(user_id)
+        end = digest_end(
+            period,
+            datetime.datetime.now(tz=datetime.UTC),
+            settings.week_start,
+            settings.tzinfo,
+            settings.reporting_period,
+        )
+        start = digest_start(period, end, settings.tzinfo, settings.reporting_period)
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=start - (end - start)),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
             raise HTTPException(
-                status_code=400,
-                detail="Transfer from/to non-existent pool(s)",
+                status_code=400, detail="Too many transactions in the requested period"
             )
+        return await build_digest(
+            transactions,
+            exchange_rates=DatedExchangeRates(exchange_rates, storage),
+            period=period,
+            end=end,
+            target_currency=target_currency or settings.default_currency,
+            start=start,
+        )
+
+    async def send_digests_periodically(notifier: Notifier, period: DigestPeriod) -> None:
+        interval = SENDING_INTERVAL[period]
+        while True:
+            await asyncio.sleep(interval.total_seconds())
+            for user_id in notifier.user_ids():
+                try:
+                    digest = await make_digest(user_id, period)
+                    if datetime.datetime.now(tz=datetime.UTC) - digest.end >= interval:
+                        continue  # ended before the previous check, so already sent
+                    await notifier.notify(
+                        user_id,
+                        subject=digest_title(digest),
+                        text=render_digest_text(digest),
+                    )
+                except Exception:
+                    logger.exception(f"Error sending digest to user {user_id}")
+
+    def telemetry_report(telemetry: Telemetry) -> TelemetryReport:
+        backend = storage.inner if isinstance(storage, AuditedStorage) else storage
+        return telemetry.report(backend=type(backend).__name__, version=app.version)
+
+    async def send_telemetry_periodically(telemetry: Telemetry) -> None:
+        while True:
+            await asyncio.sleep(TELEMETRY_INTERVAL_SEC)
+            await telemetry.send(telemetry_report(telemetry))
+
+    @app.get("/digest")
+    async def generate_digest(
+        user_id: AuthorizedUser,
+        period: DigestPeriod = DigestPeriod.WEEK,
+        target_currency: str | None = None,
+    ) -> Digest:
+        return await make_digest(
+            user_id, period, target_currency=await currency_or_default(user_id, target_currency)
+        )
+
+    @app.post("/report/snapshots")
+    async def create_report_snapshot(
+        user_id: WritableUser, body: CreateReportSnapshotRequestBody
+    ) -> StoredReportSnapshot:
+        settings = await storage.load_user_settings(user_id)
+        start, end = month_period(body.year, body.month, settings.tzinfo)
+        if end > datetime.datetime.now(tz=datetime.UTC):
+            raise HTTPException(status_code=400, detail="Only past months can be frozen")
+        return await freeze_report(user_id, body)
+
+    async def freeze_report(
+        user_id: UserId, body: CreateReportSnapshotRequestBody
+    ) -> StoredReportSnapshot:
+        settings = await storage.load_user_settings(user_id)
+        start, end = month_period(body.year, body.month, settings.tzinfo)
+        report = await compute_report(
+            user_id,
+            start=start,
+            end=end,
+            points=body.points,
+            target_currency_=body.target_currency,
+        )
+        return await storage.add_report_snapshot(
+            user_id,
+            ReportSnapshot(
+                year=body.year,
+                month=body.month,
+                target_currency=body.target_currency,
+                points=body.points,
+                report=report,
+                timezone=settings.timezone,
+            ),
+        )
+
+    @app.get("/report/snapshots")
+    async def get_report_snapshots(user_id: AuthorizedUser) -> list[StoredReportSnapshot]:
+        return await storage.load_report_snapshots(user_id)
+
+    async def load_report_snapshot(
+        user_id: UserId, snapshot_id: ReportSnapshotId
+    ) -> StoredReportSnapshot:
+        snapshot = await storage.load_report_snapshot(user_id, snapshot_id)
+        if snapshot is None:
+            raise HTTPException(status_code=404, detail="Report snapshot not found")
+        return snapshot
+
+    async def recompute_report_snapshot(
+        user_id: UserId, snapshot: StoredReportSnapshot
+    ) -> ReportApiRouteResponse:
+        start, end = snapshot.period()
+        return await compute_report(
+            user_id,
+            start=start,
+            end=end,
+            points=snapshot.points,
+            target_currency_=snapshot.target_currency,
+        )
+
+    @app.get("/report/snapshots/{snapshot_id}")
+    async def get_report_snapshot(
+        user_id: AuthorizedUser, snapshot_id: str
+    ) -> StoredReportSnapshot:
+        return await load_report_snapshot(user_id, snapshot_id)
+
+    @app.get("/report/snapshots/{snapshot_id}/current")
+    async def get_report_snapshot_recomputed(
+        user_id: AuthorizedUser, snapshot_id: str
+    ) -> ReportApiRouteResponse:
+        snapshot = await load_report_snapshot(user_id, snapshot_id)
+        return await recompute_report_snapshot(user_id, snapshot)
 
-        added = body.sum
-        added.amount = abs(added.amount)
-        deducted = MoneySum(amount=-added.amount, currency=added.currency)
+    @app.get("/report/snapshots/{snapshot_id}/diff")
+    async def get_report_snapshot_diff(
+        user_id: AuthorizedUser, snapshot_id: str
+    ) -> list[ReportValueChange]:
+        snapshot = await load_report_snapshot(user_id, snapshot_id)
+        current = await recompute_report_snapshot(user_id, snapshot)
+        return diff_reports(reported=snapshot.report, current=current)
 
-        descr_suffix = f" {body.description}" if body.description else ""
-        transaction_deduct = Transaction(
-            sum=deducted,
-            pool_id=body.from_pool,
-            # NOTE: not a bug - use the positive amount for display
-            description=f"Transfer {added} to {to_pool.display_name}" + descr_suffix,
-            tags=["moves"],
+    @app.get("/report/fx")
+    async def generate_fx_gains_report(
+        user_id: AuthorizedUser,
+        period: ReportPeriod,
+        target_currency: str | None = None,
+    ) -> FxGainsReportResponse:
+        start, end_dt = period
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=None,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.OLDEST,
         )
-        await coerce_to_pool(transaction_deduct, from_pool, exchange_rates)
-        transaction_add = Transaction(
-            sum=added,
-            pool_id=body.to_pool,
-            description=f"Transfer {added} from {from_pool.display_name}" + descr_suffix,
-            tags=["moves"],
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise HTTPException(
+                status_code=400, detail="Too many transactions to compute FX gains"
+            )
+        return await compute_fx_gains(
+            pools=await storage.load_pools(user_id),
+            transactions=transactions,
+            exchange_rates=DatedExchangeRates(exchange_rates, storage),
+            start=start,
+            end=end_dt,
+            target_currency=await currency_or_default(user_id, target_currency),
         )
-        await coerce_to_pool(transaction_add, to_pool, exchange_rates)
 
-        deduct_transaction = await storage.add_transaction(user_id, transaction_deduct)
+    @app.post("/exchange-rates/history")
+    async def import_historical_rates(
+        _: WritableUser, request: Request
+    ) -> HistoricalRatesImportResponse:
+        """Accepts ECB reference rates CSV as the request body"""
         try:
-            await storage.add_transaction(user_id, transaction_add)
-        except Exception:
-            logger.exception("Error making second transaction, trying to revert the first")
-            try:
-                if await storage.delete_transaction(user_id, transaction_id=deduct_transaction.id):
-                    raise HTTPException(
-                        status_code=503,
-                        detail="Failed to make the transfer, but the state should be consistent",
-                    )
-            except Exception:
-                logger.exception("Error deleting first transaction, the state is inconsistent")
+            days = parse_ecb_csv((await request.body()).decode("utf-8-sig"))
+        except (ValueError, UnicodeDecodeError) as e:
+            raise HTTPException(status_code=400, detail=f"Invalid rates CSV: {e}")
+        if not days:
+            raise HTTPException(status_code=400, detail="No rates found in CSV")
+        await storage.save_historical_rates(days)
+        dates = [d.date for d in days]
+        return HistoricalRatesImportResponse(
+            days_imported=len(days), first_date=min(dates), last_date=max(dates)
+        )
+
+    @app.get("/exchange-rates/history")
+    async def get_rate_history(
+        _: AuthorizedUser,
+        base: str,
+        quote: str,
+        start: Annotated[datetime.date, Query(alias="from")],
+        end: Annotated[datetime.date | None, Query(alias="to")] = None,
+    ) -> RateHistoryResponse:
+        """Recorded and imported daily rates, without the days the rates weren't stored for"""
+        try:
+            base_currency, quote_currency = parse_currency(base), parse_currency(quote)
+        except ValueError as e:
+            raise HTTPException(status_code=400, detail=str(e))
+        end_date = end or datetime.datetime.now(tz=datetime.UTC).date()
+        if end_date < start:
+            raise HTTPException(status_code=400, detail="Period end must not be before its start")
+        if end_date - start > MAX_RATE_HISTORY_PERIOD:
             raise HTTPException(
-                status_code=503,
-                detail="Failed to make the transfer and the state might be inconsisten",
+                status_code=400,
+                detail=f"Period must not be longer than {MAX_RATE_HISTORY_PERIOD.days} days",
             )
-        return "OK"
+        rates: list[HistoricalRate] = []
+        for day in await storage.load_historical_rates_between(start, end_date):
+            rate = day.cross_rate(base_currency.code, quote_currency.code)
+            if rate is not None:
+                rates.append(HistoricalRate(date=day.date, rate=rate))
+        return RateHistoryResponse(base=base_currency, quote=quote_currency, rates=rates)
 
-    @app.post("/sync-balance/{pool_id}", response_class=PlainTextResponse)
+    @app.get("/networth/history")
+    async def get_net_worth_history(
+        user_id: AuthorizedUser,
+        start: Annotated[datetime.date, Query(alias="from")],
+        end: Annotated[datetime.date | None, Query(alias="to")] = None,
+    ) -> list[NetWorthSnapshot]:
+        """
+        Daily snapshots recorded in the background, oldest first; the days before the first pool
+        was created or while the service was down have none
+        """
+        settings = await storage.load_user_settings(user_id)
+        end_date = end or datetime.datetime.now(tz=settings.tzinfo).date()
+        if end_date < start:
+            raise HTTPException(status_code=400, detail="Period end must not be before its start")
+        if end_date - start > MAX_NET_WORTH_HISTORY_PERIOD:
+            raise HTTPException(
+                status_code=400,
+                detail=f"Period must not be longer than {MAX_NET_WORTH_HISTORY_PERIOD.days} days",
+            )
+        return await storage.load_net_worth_snapshots(user_id, start, end_date)
+
+    @app.post("/pools")
+    async def create_pool(
+        user_id: WritableUser, body: CreatePoolRequestBody
+    ) -> StoredMoneyPool:
+        return await service.create_pool(user_id, body.to_money_pool())
+
+    @app.get("/pools")
+    async def get_pools(
+        user_id: AuthorizedUser,
+        request: Request,
+        response: Response,
+        if_none_match: IfNoneMatch = None,
+    ) -> list[StoredMoneyPool]:
+        """Not modified (304) if If-None-Match has the ETag of the previous response"""
+        etag = list_etag(await storage.load_change_counter(user_id), request)
+        if etag_matches(if_none_match, etag):
+            return Response(status_code=304, headers={"ETag": etag})  # type: ignore
+        response.headers["ETag"] = etag
+        return await storage.load_pools(user_id=user_id)
+
+    @app.get("/sync")
+    async def sync(
+        user_id: AuthorizedUser, visible: DescriptionsVisible, since: Offset = 0
+    ) -> SyncResponse:
+        """
+        Pools and transactions changed after the cursor of the previous sync, everything for
+        the initial one (since=0); the same entity may come again in the next sync
+        """
+        # read before the changes, so that the ones made meanwhile are synced again next time
+        cursor = await storage.load_change_counter(user_id)
+        changes = await storage.load_changes(user_id, since)
+        changed_ids = {
+            entity: [c.entity_id for c in changes if c.entity is entity and not c.deleted]
+            for entity in SyncedEntity
+        }
+        pools = [
+            p
+            for p in await storage.load_pools(user_id)
+            if p.id in changed_ids[SyncedEntity.POOL]
+        ]
+        transactions: list[StoredTransaction] = []
+        if changed_ids[SyncedEntity.TRANSACTION]:
+            transactions = await storage.load_transactions(
+                user_id,
+                filter=TransactionFilter(transaction_ids=changed_ids[SyncedEntity.TRANSACTION]),
+                order=TransactionOrder.OLDEST,
+                offset=0,
+                count=len(changed_ids[SyncedEntity.TRANSACTION]),
+            )
+        deleted_ids = {
+            entity: [c.entity_id for c in changes if c.entity is entity and c.deleted]
+            for entity in SyncedEntity
+        }
+        return SyncResponse(
+            cursor=cursor,
+            pools=pools,
+            transactions=service.present_transactions(transactions, visible),
+            deleted_pool_ids=deleted_ids[SyncedEntity.POOL],
+            deleted_transaction_ids=deleted_ids[SyncedEntity.TRANSACTION],
+        )
+
+    @app.post("/sync")
+    async def upload_changes(
+        user_id: WritableUser, visible: DescriptionsVisible, body: SyncUploadRequestBody
+    ) -> SyncUploadResponse:
+        """
+        Applies the changes made on the client while offline. Updates and deletes of
+        transactions changed on the server since their base version are left out as conflicts,
+        for the client to resolve against the server state; if any other change is invalid,
+        nothing is applied. The accepted changes are applied at once, a failure leaves none of
+        them applied
+        """
+        target_ids = [c.transaction_id for c in body.changes if c.transaction_id is not None]
+        # the server state as it would be after the previous changes, to check each one against
+        expected: dict[TransactionId, StoredTransaction] = {}
+        if target_ids:
+            loaded = await storage.load_transactions(
+                user_id,
+                filter=TransactionFilter(transaction_ids=target_ids),
+                order=TransactionOrder.OLDEST,
+                offset=0,
+                count=len(target_ids),
+            )
+            expected = {t.id: t for t in loaded}
+        conflicts: set[str] = set()
+        errors: list[SyncChangeError] = []
+        for change in body.changes:
+            try:
+                if change.transaction is not None:
+                    accept_client_transaction(change.transaction)
+                    await service.prepare_new_transaction(user_id, change.transaction)
+                    continue
+                assert change.transaction_id is not None
+                target = expected.get(change.transaction_id)
+                if target is None or target.version != change.base_version:
+                    conflicts.add(change.client_id)
+                    continue
+                await service.ensure_period_unlocked(user_id, target.pool_id, target.timestamp)
+                if change.update is None:
+                    del expected[target.id]
+                    continue
+                if change.update.timestamp is not None:
+                    await service.ensure_period_unlocked(
+                        user_id, target.pool_id, change.update.timestamp
+                    )
+                if change.update.splits is not None:
+                    error = splits_error(target.sum, change.update.splits)
+                    if error is not None:
+                        raise ServiceError(error)
+                if privacy is not None and change.update.description is not None:
+                    change.update.description = privacy.encrypt(change.update.description)
+                target.version += 1
+            except ServiceError as e:
+                errors.append(SyncChangeError(client_id=change.client_id, error=str(e)))
+        if errors:
+            raise HTTPException(
+                status_code=400, detail=[e.model_dump(mode="json") for e in errors]
+            )
+
+        new = [c.transaction for c in body.changes if c.transaction is not None]
+        while True:
+            edits = [
+                TransactionEdit(
+                    transaction_id=c.transaction_id,
+                    expected_version=c.base_version,
+                    update=c.update,
+                )
+                for c in body.changes
+                if c.transaction_id is not None
+                and c.base_version is not None
+                and c.client_id not in conflicts
+            ]
+            edited_ids = list({e.transaction_id for e in edits})
+            before = {
+                t.id: t
+                for t in await storage.load_transactions(
+                    user_id,
+                    filter=TransactionFilter(transaction_ids=edited_ids),
+                    order=TransactionOrder.OLDEST,
+                    offset=0,
+                    count=len(edited_ids),
+                )
+            }
+            try:
+                # all or nothing
+                created = await storage.apply_transaction_changes(user_id, new, edits)
+                break
+            except VersionConflict as e:
+                # the transaction has changed meanwhile, the rest of the changes are retried
+                conflicting = {
+                    c.client_id
+                    for c in body.changes
+                    if c.transaction_id is not None and c.transaction_id == e.entity_id
+                }
+                if not conflicting - conflicts:
+                    raise
+                conflicts |= conflicting
+        created_by_client_id = {
+            c.client_id: t
+            for c, t in zip((c for c in body.changes if c.transaction is not None), created)
+        }
+        if created:
+            await service.log_operation(user_id, OperationKind.CREATE, created)
+        updated = [before[e.transaction_id] for e in edits if e.update is not None]
+        deleted = [before[e.transaction_id] for e in edits if e.update is None]
+        if updated:
+            await service.log_operation(user_id, OperationKind.UPDATE, updated)
+        if deleted:
+            await service.log_operation(user_id, OperationKind.DELETE, deleted)
+
+        results: list[SyncChangeResult] = []
+        for change in body.changes:
+            if change.client_id in created_by_client_id:
+                transaction: StoredTransaction | None = created_by_client_id[change.client_id]
+            elif change.transaction_id is not None:
+                transaction = await storage.load_transaction(user_id, change.transaction_id)
+            else:
+                transaction = None
+            results.append(
+                SyncChangeResult(
+                    client_id=change.client_id,
+                    status=(
+                        SyncChangeStatus.CONFLICT
+                        if change.client_id in conflicts
+                        else SyncChangeStatus.ACCEPTED
+                    ),
+                    transaction=(
+                        service.present_transactions([transaction], visible)[0]
+                        if transaction is not None
+                        else None
+                    ),
+                )
+            )
+        return SyncUploadResponse(results=results)
+
+    @app.put("/pools/order", response_class=PlainTextResponse)
+    async def set_pools_order(user_id: WritableUser, body: PoolOrderRequestBody) -> Ok:
+        pools = await storage.load_pools(user_id=user_id)
+        if sorted(body.pool_ids) != sorted(p.id for p in pools):
+            raise HTTPException(
+                status_code=400, detail="Pool ids must list all of the user's pools exactly once"
+            )
+        await storage.set_pools_order(user_id, body.pool_ids)
+        return "OK"
+
+    @app.get("/pools/{pool_id}")
+    async def get_pool(user_id: AuthorizedUser, pool_id: str) -> StoredMoneyPool:
+        pool = await storage.load_pool(user_id=user_id, pool_id=pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        else:
+            return pool
+
+    @app.get("/pools/{pool_id}/balance")
+    async def get_pool_balance(user_id: AuthorizedUser, pool_id: str) -> PoolBalanceResponse:
+        """Current balance includes pending transactions, available one only the cleared ones"""
+        pool = await storage.load_pool(user_id=user_id, pool_id=pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        pending = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(pool_ids=[pool_id], status=TransactionStatus.PENDING),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        current = copy.deepcopy(pool.balance)
+        for t in pending:
+            pool.update_with_transaction(t.inverted())
+        return PoolBalanceResponse(
+            current=current,
+            available=pool.balance,
+            pending_transaction_ids=[t.id for t in pending],
+        )
+
+    @app.get("/pools/{pool_id}/scheduled")
+    async def get_scheduled_transactions(
+        user_id: AuthorizedUser, visible: DescriptionsVisible, pool_id: str
+    ) -> list[StoredTransaction]:
+        """Upcoming transactions, soonest first, not yet counted in the balance"""
+        if await storage.load_pool(user_id=user_id, pool_id=pool_id) is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        scheduled = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(pool_ids=[pool_id], status=TransactionStatus.SCHEDULED),
+            order=TransactionOrder.OLDEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        return service.present_transactions(scheduled, visible)
+
+    @app.get("/pools/{pool_id}/statement", response_class=HTMLResponse)
+    async def get_pool_statement(
+        user_id: AuthorizedUser,
+        visible: DescriptionsVisible,
+        pool_id: str,
+        month: Annotated[str, Query(pattern=r"^\d{4}-(0[1-9]|1[0-2])$")],
+        response: Response,
+    ) -> str:
+        """Printable HTML for the month given as YYYY-MM, in the user's timezone"""
+        pool = await storage.load_pool(user_id=user_id, pool_id=pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        settings = await storage.load_user_settings(user_id)
+        year, month_ = (int(part) for part in month.split("-"))
+        start, _ = month_period(year, month_, settings.tzinfo)
+        if start > datetime.datetime.now(tz=datetime.UTC):
+            raise HTTPException(status_code=400, detail="Statement month is in the future")
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(pool_ids=[pool_id], min_timestamp=start),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise HTTPException(
+                status_code=400, detail="Too many transactions since the month's start"
+            )
+        statement = build_statement(
+            pool,
+            service.present_transactions(transactions, visible),
+            year,
+            month_,
+            settings.tzinfo,
+        )
+        filename = f"statement-{pool_id}-{month}.html"
+        response.headers["Content-Disposition"] = f'inline; filename="{filename}"'
+        return render_statement_html(statement, settings.locale)
+
+    @app.put("/pools/{pool_id}", response_class=PlainTextResponse)
+    async def modify_pool(
+        user_id: WritableUser,
+        pool_id: str,
+        update: MoneyPoolAttributesUpdate,
+        if_match: IfMatch = None,
+    ) -> Ok:
+        expected_version = parse_if_match(if_match)
+        if await storage.set_pool_attributes(
+            user_id, pool_id=pool_id, update=update, expected_version=expected_version
+        ):
+            pool = await storage.load_pool(user_id, pool_id)
+            if pool is not None:
+                await events.publish(PoolUpdated(user_id=user_id, pool=pool))
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="Pool not found")
+
+    @app.post("/pools/{pool_id}/redenominate")
+    async def redenominate_pool(
+        user_id: WritableUser, pool_id: MoneyPoolId, body: RedenominatePoolRequestBody
+    ) -> PoolRedenomination:
+        """
+        Changes the pool's currency, e.g. when the account is moved to another one; the conversion
+        is returned with the rates used and recorded in the audit log
+        """
+        redenomination = await service.redenominate_pool(
+            user_id,
+            pool_id,
+            from_currency=body.from_currency,
+            to_currency=body.to_currency,
+            rate=body.rate,
+            convert_transactions=body.convert_transactions,
+        )
+        if redenomination is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        return redenomination
+
+    @app.get("/pools/{pool_id}/overdraft")
+    async def get_overdraft_status(user_id: AuthorizedUser, pool_id: str) -> OverdraftStatus:
+        pool = await storage.load_pool(user_id, pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        status = compute_overdraft_status(
+            pool,
+            transactions=await load_pool_transactions(user_id, pool_id),
+            now=datetime.datetime.now(tz=datetime.UTC),
+        )
+        if status is None:
+            raise HTTPException(status_code=404, detail="Pool has no overdraft facility")
+        return status
+
+    @app.post(
+        "/transactions",
+        responses={202: {"model": PendingSpend}, 409: {"model": DuplicateTransactionResponse}},
+    )
+    async def add_transaction(
+        user_id: WritableUser,
+        visible: DescriptionsVisible,
+        transaction: Transaction,
+        force: bool = False,
+    ) -> CreatedTransactionResponse:
+        """
+        Refuses to add a likely duplicate of a recent transaction, unless forced; less certain
+        issues are returned as warnings alongside the created transaction
+        """
+        accept_client_transaction(transaction)
+        warnings = await service.prepare_new_transaction(user_id, transaction)
+        if not force:
+            duplicate = await service.find_duplicate(user_id, transaction)
+            if duplicate is not None:
+                return JSONResponse(  # type: ignore
+                    status_code=409,
+                    content=DuplicateTransactionResponse(
+                        detail="Transaction looks like a duplicate, pass force=true to add anyway",
+                        existing_id=duplicate.id,
+                    ).model_dump(mode="json"),
+                )
+        for allowance in await storage.load_allowances(user_id):
+            if allowance.needs_approval(transaction):
+                pending_spend = PendingSpend(transaction=transaction)
+                allowance.pending_spends.append(pending_spend)
+                await storage.save_allowance(user_id, allowance)
+                return JSONResponse(  # type: ignore
+                    status_code=202, content=pending_spend.model_dump(mode="json")
+                )
+        [stored] = await service.save_new_transactions(user_id, [transaction])
+        return CreatedTransactionResponse(
+            **service.present_transactions([stored], visible)[0].model_dump(), warnings=warnings
+        )
+
+    @app.post(
+        "/quick",
+        responses={202: {"model": PendingSpend}, 409: {"model": DuplicateTransactionResponse}},
+    )
+    async def quick_add_transaction(
+        user_id: WritableUser,
+        visible: DescriptionsVisible,
+        body: QuickAddRequestBody,
+        force: bool = False,
+    ) -> CreatedTransactionResponse:
+        """Parses a one-line transaction, e.g. "14.99 USD groceries lidl", into the default pool"""
+        settings = await storage.load_user_settings(user_id)
+        if settings.default_pool_id is None:
+            raise HTTPException(status_code=400, detail="Default pool is not set")
+        pool = await storage.load_pool(user_id, settings.default_pool_id)
+        if pool is None or not pool.balance:
+            raise HTTPException(status_code=400, detail="Default pool has no currency")
+        categories = await storage.load_suggestions(
+            user_id,
+            SuggestionField.CATEGORY,
+            query="",
+            since=datetime.datetime.now(tz=datetime.UTC) - SUGGESTIONS_WINDOW,
+            count=MAX_SUGGESTIONS,
+        )
+        try:
+            entry = parse_quick_entry(body.text, [c.value for c in categories])
+        except ValueError as e:
+            raise HTTPException(status_code=400, detail=str(e))
+        transaction = Transaction(
+            sum=MoneySum(amount=entry.amount, currency=entry.currency or pool.balance[0].currency),
+            pool_id=pool.id,
+            description=entry.description,
+            tags=entry.tags,
+        )
+        return await add_transaction(user_id, visible, transaction, force=force)
+
+    @app.post("/transactions/bulk")
+    async def add_transactions_bulk(
+        user_id: WritableUser, visible: DescriptionsVisible, body: BulkTransactionsRequestBody
+    ) -> list[BulkTransactionResult]:
+        errors: list[str | None] = []
+        for transaction in body.transactions:
+            try:
+                await service.prepare_new_transaction(
+                    user_id, accept_client_transaction(transaction)
+                )
+                errors.append(None)
+            except ServiceError as e:
+                errors.append(str(e))
+        if any(errors):
+            raise HTTPException(
+                status_code=400,
+                detail=[
+                    BulkTransactionResult(index=idx, error=error).model_dump(mode="json")
+                    for idx, error in enumerate(errors)
+                ],
+            )
+        stored = await service.save_new_transactions(user_id, body.transactions)
+        stored = service.present_transactions(stored, visible)
+        return [BulkTransactionResult(index=idx, transaction=t) for idx, t in enumerate(stored)]
+
+    @app.post("/pools/{pool_id}/import")
+    async def import_statement(
+        user_id: WritableUser,
+        visible: DescriptionsVisible,
+        pool_id: MoneyPoolId,
+        request: Request,
+        format: StatementFormat | None = None,  # detected from the contents if omitted
+        currency: str | None = None,  # the statement's one or the pool's first if omitted
+    ) -> StatementImportResponse:
+        """Accepts an OFX or QIF bank statement as the request body"""
+        pool = await storage.load_pool(user_id, pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        try:
+            text = (await request.body()).decode("utf-8-sig")
+            format = format or detect_format(text)
+            entries = parse_statement(text, format)
+            statement_currency = parse_currency(
+                currency
+                or (ofx_currency(text) if format is StatementFormat.OFX else None)
+                or pool.balance[0].currency.code
+            )
+        except (ValueError, UnicodeDecodeError) as e:
+            raise HTTPException(status_code=400, detail=f"Invalid statement: {e}")
+        if not entries:
+            return StatementImportResponse(imported=[], skipped=0)
+
+        day = datetime.timedelta(days=1)
+        existing = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(
+                min_timestamp=min(e.timestamp for e in entries) - day,
+                max_timestamp=max(e.timestamp for e in entries) + day,
+                pool_ids=[pool_id],
+            ),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        new_entries = [e for e in entries if not is_imported(e, existing)]
+        # the same FITID repeated within the statement is one transaction
+        new_entries = list({e.fitid or id(e): e for e in new_entries}.values())
+        transactions = [
+            to_transaction(entry, pool_id, statement_currency, format) for entry in new_entries
+        ]
+        for idx, transaction in enumerate(transactions, start=1):
+            try:
+                await service.prepare_new_transaction(user_id, transaction)
+            except ServiceError as e:
+                raise HTTPException(status_code=e.status_code, detail=f"Entry {idx}: {e}")
+        stored = await service.save_new_transactions(user_id, transactions)
+        return StatementImportResponse(
+            imported=service.present_transactions(stored, visible),
+            skipped=len(entries) - len(stored),
+        )
+
+    @app.get("/transactions")
+    async def get_transactions(
+        user_id: AuthorizedUser,
+        visible: DescriptionsVisible,
+        request: Request,
+        response: Response,
+        period: ListingPeriod,
+        offset: Offset = 0,
+        count: Count = 10,
+        order: TransactionOrder = TransactionOrder.LATEST,
+        status: TransactionStatus | None = None,
+        if_none_match: IfNoneMatch = None,
+    ) -> list[StoredTransaction]:
+        """Not modified (304) if If-None-Match has the ETag of the previous response"""
+        # relative dates like "yesterday" move on by themselves, so the resolved ones are keyed
+        etag = list_etag(await storage.load_change_counter(user_id), request, visible, *period)
+        if etag_matches(if_none_match, etag):
+            return Response(status_code=304, headers={"ETag": etag})  # type: ignore
+        response.headers["ETag"] = etag
+        start, end = period
+        transaction_filter = None
+        if status is not None or start is not None or end is not None:
+            transaction_filter = TransactionFilter(
+                status=status, min_timestamp=start, max_timestamp=end
+            )
+        transactions = await storage.load_transactions(
+            user_id=user_id,
+            filter=transaction_filter,
+            offset=offset,
+            count=count,
+            order=order,
+        )
+        return service.present_transactions(transactions, visible)
+
+    @app.get("/transactions/{transaction_id}/source")
+    async def get_transaction_source(
+        user_id: AuthorizedUser, visible: DescriptionsVisible, transaction_id: str
+    ) -> TransactionSource:
+        """What the bank or the imported file actually said about the transaction"""
+        transaction = await storage.load_transaction(user_id, transaction_id)
+        if transaction is None:
+            raise HTTPException(status_code=404, detail="No such transaction")
+        transaction = service.present_transactions([transaction], visible)[0]
+        if transaction.source is None:
+            raise HTTPException(status_code=404, detail="Transaction was not imported")
+        return transaction.source
+
+    @app.delete("/transactions/{transaction_id}", response_class=PlainTextResponse)
+    async def delete_transaction(user_id: WritableUser, transaction_id: str) -> Ok:
+        if await service.delete_transaction(user_id, transaction_id):
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="No such transaction")
+
+    @app.put("/transactions/{transaction_id}", response_class=PlainTextResponse)
+    async def update_transaction(
+        user_id: WritableUser,
+        transaction_id: str,
+        update: TransactionUpdate,
+        if_match: IfMatch = None,
+    ) -> Ok:
+        expected_version = parse_if_match(if_match)
+        transaction = await storage.load_transaction(user_id, transaction_id)
+        if transaction is not None:
+            await service.ensure_period_unlocked(
+                user_id, transaction.pool_id, transaction.timestamp
+            )
+            if update.timestamp is not None:
+                await service.ensure_period_unlocked(
+                    user_id, transaction.pool_id, update.timestamp
+                )
+            if update.splits is not None:
+                error = splits_error(transaction.sum, update.splits)
+                if error is not None:
+                    raise HTTPException(status_code=400, detail=error)
+            if update.payee_id:
+                await ensure_payee_exists(user_id, update.payee_id)
+        if privacy is not None and update.description is not None:
+            update.description = privacy.encrypt(update.description)
+        if await storage.update_transaction(
+            user_id=user_id,
+            transaction_id=transaction_id,
+            update=update,
+            expected_version=expected_version,
+        ):
+            if transaction is not None:
+                await service.log_operation(user_id, OperationKind.UPDATE, [transaction])
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="No such transaction")
+
+    @app.put("/transactions/{transaction_id}/status", response_class=PlainTextResponse)
+    async def update_transaction_status(
+        user_id: WritableUser,
+        transaction_id: str,
+        update: TransactionStatusUpdate,
+        if_match: IfMatch = None,
+    ) -> Ok:
+        """
+        Pending transactions get cleared or voided by the bank, scheduled ones may be cleared
+        or voided ahead of time, the rest are final
+        """
+        expected_version = parse_if_match(if_match)
+        transaction = await storage.load_transaction(user_id, transaction_id)
+        if transaction is None:
+            raise HTTPException(status_code=404, detail="No such transaction")
+        if update.status is transaction.status:
+            return "OK"
+        if update.status is TransactionStatus.SCHEDULED:
+            raise HTTPException(
+                status_code=400, detail="Only future-dated transactions are scheduled"
+            )
+        if transaction.status not in (TransactionStatus.PENDING, TransactionStatus.SCHEDULED):
+            raise HTTPException(
+                status_code=409, detail=f"Transaction is already {transaction.status.value}"
+            )
+        await service.ensure_period_unlocked(user_id, transaction.pool_id, transaction.timestamp)
+        if await storage.update_transaction_status(
+            user_id, transaction_id, update.status, expected_version=expected_version
+        ):
+            await service.publish_transaction_events(user_id, OperationKind.UPDATE, [transaction])
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="No such transaction")
+
+    @app.post("/undo")
+    async def undo_last_operation(
+        user_id: WritableUser, visible: DescriptionsVisible
+    ) -> UndoResponse:
+        """Reverts the latest change to transactions, if it's made within the undo window"""
+        operation = await service.undo_last_operation(user_id)
+        if operation is None:
+            raise HTTPException(status_code=404, detail="Nothing to undo")
+        return UndoResponse(
+            kind=operation.kind,
+            transactions=service.present_transactions(operation.transactions, visible),
+        )
+
+    @app.post("/transfer", response_class=PlainTextResponse)
+    async def make_transfer(user_id: WritableUser, body: TransferMoneyRequestBody) -> Ok:
+        if body.sum.amount.is_zero():
+            raise HTTPException(status_code=400, detail="Transfer amount can't be zero")
+        if body.to_pool == body.from_pool:
+            raise HTTPException(status_code=400, detail="Can't transfer to the same pool")
+
+        from_pool = await storage.load_pool(user_id=user_id, pool_id=body.from_pool)
+        to_pool = await storage.load_pool(user_id=user_id, pool_id=body.to_pool)
+        if from_pool is None or to_pool is None:
+            raise HTTPException(
+                status_code=400,
+                detail="Transfer from/to non-existent pool(s)",
+            )
+        legs = transfer_legs(
+            from_pool,
+            to_pool,
+            MoneySum(amount=abs(body.sum.amount), currency=body.sum.currency),
+            body.description,
+            timestamp=datetime.datetime.now(tz=datetime.UTC),
+            tags=[],
+            received=(
+                MoneySum(amount=abs(body.received.amount), currency=body.received.currency)
+                if body.received is not None
+                else None
+            ),
+        )
+        await service.add_transactions(user_id, legs)
+        return "OK"
+
+    async def load_pool_transactions(
+        user_id: UserId, pool_id: MoneyPoolId
+    ) -> list[StoredTransaction]:
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(pool_ids=[pool_id]),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise HTTPException(status_code=400, detail="Too many transactions in the pool")
+        return transactions
+
+    async def load_transfer_pools(
+        user_id: UserId, from_pool_id: MoneyPoolId, to_pool_id: MoneyPoolId, currency: Currency
+    ) -> tuple[StoredMoneyPool, StoredMoneyPool]:
+        if to_pool_id == from_pool_id:
+            raise HTTPException(status_code=400, detail="Can't transfer to the same pool")
+        from_pool = await storage.load_pool(user_id=user_id, pool_id=from_pool_id)
+        to_pool = await storage.load_pool(user_id=user_id, pool_id=to_pool_id)
+        if from_pool is None or to_pool is None:
+            raise HTTPException(status_code=404, detail="Transfer from/to non-existent pool(s)")
+        for pool in (from_pool, to_pool):
+            if currency not in {ms.currency for ms in pool.balance}:
+                raise HTTPException(
+                    status_code=400,
+                    detail=f"Pool {pool.display_name!r} has no {currency.code} balance",
+                )
+        return from_pool, to_pool
+
+    @app.post("/pools/{pool_id}/transfer")
+    async def transfer_between_pools(
+        user_id: WritableUser,
+        pool_id: MoneyPoolId,
+        body: PoolTransferRequestBody,
+        visible: DescriptionsVisible,
+    ) -> list[StoredTransaction]:
+        from_pool, to_pool = await load_transfer_pools(
+            user_id, pool_id, body.to_pool, body.sum.currency
+        )
+        legs = transfer_legs(
+            from_pool,
+            to_pool,
+            body.sum,
+            body.description,
+            timestamp=datetime.datetime.now(tz=datetime.UTC),
+            tags=[],
+        )
+        stored = await service.add_transactions(user_id, legs)
+        return service.present_transactions(stored, visible)
+
+    @app.post("/pools/{pool_id}/withdrawal")
+    async def withdraw_cash(
+        user_id: WritableUser,
+        pool_id: MoneyPoolId,
+        body: CashWithdrawalRequestBody,
+        visible: DescriptionsVisible,
+    ) -> CashWithdrawalResponse:
+        bank_pool, cash_pool = await load_transfer_pools(
+            user_id, pool_id, body.to_pool, body.sum.currency
+        )
+        now = datetime.datetime.now(tz=datetime.UTC)
+        new_transactions = transfer_legs(
+            bank_pool, cash_pool, body.sum, body.description, timestamp=now, tags=[WITHDRAWAL_TAG]
+        )
+        if body.fee is not None and not body.fee.amount.is_zero():
+            new_transactions.append(
+                Transaction(
+                    sum=MoneySum(amount=-abs(body.fee.amount), currency=body.fee.currency),
+                    pool_id=bank_pool.id,
+                    description=f"Withdrawal fee ({body.sum} to {cash_pool.display_name})",
+                    timestamp=now,
+                    tags=[FEE_TAG],
+                )
+            )
+        stored = await service.add_transactions(user_id, new_transactions)
+
+        return CashWithdrawalResponse(
+            transactions=service.present_transactions(stored, visible),
+            unaccounted=unaccounted_withdrawn_cash(
+                await load_pool_transactions(user_id, cash_pool.id), body.sum.currency
+            ),
+        )
+
+    @app.get("/pools/{pool_id}/projection")
+    async def get_balance_projection(
+        user_id: AuthorizedUser,
+        pool_id: MoneyPoolId,
+        lookback_days: Annotated[int, Query(ge=1, le=365)] = DEFAULT_LOOKBACK.days,
+    ) -> PoolProjectionResponse:
+        """
+        Balance at the end of the month or the reporting period, if spending continues as over
+        the last days
+        """
+        pool = await storage.load_pool(user_id, pool_id)
+        if pool is None:
+            raise HTTPException(404, detail="Pool not found")
+        now = datetime.datetime.now(tz=datetime.UTC)
+        settings = await storage.load_user_settings(user_id)
+        end = month_end(now, settings.tzinfo, settings.reporting_period)
+        scheduled = [
+            s
+            for allowance in await storage.load_allowances(user_id)
+            for s in scheduled_allowance_payments(allowance, until=end)
+        ]
+        return project_month_end_balance(
+            pool,
+            transactions=await load_pool_transactions(user_id, pool_id),
+            scheduled=scheduled,
+            now=now,
+            lookback=datetime.timedelta(days=lookback_days),
+            tz=settings.tzinfo,
+            reporting_period=settings.reporting_period,
+        )
+
+    @app.get("/insights/anomalies")
+    async def get_spending_anomalies(
+        user_id: AuthorizedUser,
+        visible: DescriptionsVisible,
+        days: Annotated[int, Query(ge=1, le=365)] = 30,
+    ) -> list[SpendingAnomaly]:
+        """Unusual expenses of the last days, compared to the history before them"""
+        since = datetime.datetime.now(tz=datetime.UTC) - datetime.timedelta(days=days)
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=since - ANOMALY_HISTORY),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise HTTPException(status_code=400, detail="Too many transactions to analyze")
+        anomalies = find_anomalies(
+            history=[t for t in transactions if t.timestamp.timestamp() < since.timestamp()],
+            transactions=[t for t in transactions if t.timestamp.timestamp() >= since.timestamp()],
+        )
+        service.present_transactions([a.transaction for a in anomalies], visible)
+        return anomalies
+
+    @app.get("/insights/recurring")
+    async def get_recurring_suggestions(
+        user_id: AuthorizedUser,
+        visible: DescriptionsVisible,
+        lookback_days: Annotated[int, Query(ge=1, le=3 * 365)] = 365,
+    ) -> list[RecurringSuggestion]:
+        """Regularly repeating transactions that could be made into templates"""
+        now = datetime.datetime.now(tz=datetime.UTC)
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=now - datetime.timedelta(days=lookback_days)),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise HTTPException(status_code=400, detail="Too many transactions to analyze")
+        suggestions = find_recurring(transactions, service.plain_description, now)
+        if privacy is not None and not visible:
+            for suggestion in suggestions:
+                suggestion.description = MASKED_DESCRIPTION
+        return suggestions
+
+    @app.get("/pools/{pool_id}/unaccounted")
+    async def get_unaccounted_spending(
+        user_id: AuthorizedUser,
+        pool_id: MoneyPoolId,
+        actual: Decimal,
+        currency: str | None = None,
+    ) -> UnaccountedSpendingResponse:
+        pool = await storage.load_pool(user_id, pool_id)
+        if pool is None:
+            raise HTTPException(404, detail="Pool not found")
+        actual_sum = MoneySum(
+            amount=actual,
+            currency=(
+                CurrencyAdapter.validate_python(currency)
+                if currency is not None
+                else pool.balance[0].currency
+            ),
+        )
+        expected = next((ms for ms in pool.balance if ms.currency == actual_sum.currency), None)
+        if expected is None:
+            raise HTTPException(
+                400, detail=f"Pool has no {actual_sum.currency.code} balance to compare with"
+            )
+        pool_transactions = await load_pool_transactions(user_id, pool_id)
+        return UnaccountedSpendingResponse(
+            since=last_synced_at(pool_transactions, actual_sum.currency),
+            expected=expected,
+            actual=actual_sum,
+            gap=MoneySum(amount=expected.amount - actual_sum.amount, currency=expected.currency),
+            unaccounted_withdrawn=unaccounted_withdrawn_cash(
+                pool_transactions, actual_sum.currency
+            ),
+        )
+
+    @app.post("/pools/{pool_id}/unaccounted")
+    async def close_unaccounted_spending(
+        user_id: WritableUser,
+        pool_id: MoneyPoolId,
+        body: CloseUnaccountedRequestBody,
+        visible: DescriptionsVisible,
+    ) -> StoredTransaction:
+        """Records a diffuse transaction bringing the pool's balance to the actual one"""
+        pool = await storage.load_pool(user_id, pool_id)
+        if pool is None:
+            raise HTTPException(404, detail="Pool not found")
+        expected = next((ms for ms in pool.balance if ms.currency == body.actual.currency), None)
+        if expected is None:
+            raise HTTPException(
+                400, detail=f"Pool has no {body.actual.currency.code} balance to close gap in"
+            )
+        delta = body.actual.amount - expected.amount
+        if not delta:
+            raise HTTPException(400, detail="No gap to close, balance matches the actual one")
+        transaction = Transaction(
+            sum=MoneySum(amount=delta, currency=expected.currency),
+            pool_id=pool_id,
+            description=body.description
+            or f"{pool.display_name} unaccounted {expected.amount} -> {body.actual}",
+            is_diffuse=True,
+        )
+        [stored] = await service.add_transactions(user_id, [transaction])
+        return service.present_transactions([stored], visible)[0]
+
+    async def pay_due_allowance(
+        user_id: UserId, allowance: StoredAllowance, now: datetime.datetime
+    ) -> StoredAllowance:
+        """Makes all the weekly transfers due by now, catching up on missed ones"""
+        source_pool, pool = await load_transfer_pools(
+            user_id, allowance.source_pool_id, allowance.pool_id, allowance.weekly_amount.currency
+        )
+        while allowance.next_payment_at.timestamp() <= now.timestamp():
+            legs = transfer_legs(
+                source_pool,
+                pool,
+                allowance.weekly_amount.model_copy(),
+                f"weekly allowance for {allowance.child_name}",
+                timestamp=allowance.next_payment_at,
+                tags=[ALLOWANCE_TAG],
+            )
+            for leg in legs:
+                await service.prepare_new_transaction(user_id, leg)
+            stored = await storage.add_transactions(user_id, legs)
+            await service.publish_transaction_events(user_id, OperationKind.CREATE, stored)
+            allowance.next_payment_at += ALLOWANCE_PERIOD
+            await storage.save_allowance(user_id, allowance)
+        return allowance
+
+    async def promote_scheduled_transactions_periodically() -> None:
+        while True:
+            now = datetime.datetime.now(tz=datetime.UTC)
+            try:
+                due = await storage.load_due_scheduled_transactions(now)
+            except Exception:
+                logger.exception("Error loading due scheduled transactions")
+                due = []
+            for user_id, transaction in due:
+                try:
+                    if await storage.update_transaction_status(
+                        user_id,
+                        transaction.id,
+                        TransactionStatus.CLEARED,
+                        expected_version=transaction.version,
+                    ):
+                        await service.publish_transaction_events(
+                            user_id, OperationKind.UPDATE, [transaction]
+                        )
+                except Exception:
+                    logger.exception(f"Error promoting scheduled transaction {transaction.id}")
+            await asyncio.sleep(SCHEDULED_TRANSACTIONS_CHECK_INTERVAL_SEC)
+
+    async def pay_allowances_periodically() -> None:
+        while True:
+            now = datetime.datetime.now(tz=datetime.UTC)
+            try:
+                due = await storage.load_due_allowances(now)
+            except Exception:
+                logger.exception("Error loading due allowances")
+                due = []
+            for user_id, allowance in due:
+                try:
+                    await pay_due_allowance(user_id, allowance, now)
+                except Exception:
+                    logger.exception(f"Error paying allowance {allowance.id}")
+            await asyncio.sleep(ALLOWANCES_CHECK_INTERVAL_SEC)
+
+    async def record_net_worth(user_id: UserId, now: datetime.datetime) -> NetWorthSnapshot:
+        settings = await storage.load_user_settings(user_id)
+        snapshot = await net_worth(
+            await storage.load_pools(user_id),
+            exchange_rates,
+            target_currency=settings.default_currency,
+            date=now.astimezone(settings.tzinfo).date(),
+        )
+        await storage.save_net_worth_snapshot(user_id, snapshot)
+        return snapshot
+
+    async def record_net_worth_periodically() -> None:
+        while True:
+            now = datetime.datetime.now(tz=datetime.UTC)
+            try:
+                user_ids = await storage.load_user_ids()
+            except Exception:
+                logger.exception("Error loading users to record net worth for")
+                user_ids = []
+            for user_id in user_ids:
+                try:
+                    await record_net_worth(user_id, now)
+                except Exception:
+                    logger.exception(f"Error recording net worth of user {user_id}")
+            await asyncio.sleep(NET_WORTH_SNAPSHOT_INTERVAL_SEC)
+
+    @app.post("/allowances")
+    async def create_allowance(
+        user_id: WritableUser, body: CreateAllowanceRequestBody
+    ) -> StoredAllowance:
+        await load_transfer_pools(
+            user_id, body.source_pool_id, body.pool_id, body.weekly_amount.currency
+        )
+        if body.weekly_amount.amount <= 0:
+            raise HTTPException(status_code=400, detail="Allowance amount must be positive")
+        now = datetime.datetime.now(tz=datetime.UTC)
+        stored = await storage.add_allowance(
+            user_id,
+            Allowance(
+                pool_id=body.pool_id,
+                source_pool_id=body.source_pool_id,
+                child_name=body.child_name,
+                weekly_amount=body.weekly_amount,
+                next_payment_at=body.first_payment_at or now,
+                approval_threshold=body.approval_threshold,
+            ),
+        )
+        return await pay_due_allowance(user_id, stored, now)
+
+    @app.get("/allowances")
+    async def get_allowances(user_id: AuthorizedUser) -> list[StoredAllowance]:
+        return await storage.load_allowances(user_id)
+
+    async def resolve_pending_spend(
+        user_id: UserId, allowance_id: str, spend_id: str, approve: bool
+    ) -> PendingSpend:
+        allowance = await storage.load_allowance(user_id, allowance_id)
+        if allowance is None:
+            raise HTTPException(status_code=404, detail="Allowance not found")
+        spend = allowance.pending_spend(spend_id)
+        if spend is None:
+            raise HTTPException(status_code=404, detail="Pending spend not found")
+        if spend.status is not PendingSpendStatus.PENDING:
+            raise HTTPException(status_code=409, detail=f"Spend is already {spend.status.value}")
+        if approve:
+            await service.ensure_period_unlocked(
+                user_id, spend.transaction.pool_id, spend.transaction.timestamp
+            )
+            stored = await storage.add_transaction(user_id, spend.transaction)
+            await service.publish_transaction_events(user_id, OperationKind.CREATE, [stored])
+            spend.status = PendingSpendStatus.APPROVED
+        else:
+            spend.status = PendingSpendStatus.REJECTED
+        await storage.save_allowance(user_id, allowance)
+        return spend
+
+    @app.post("/allowances/{allowance_id}/spends/{spend_id}/approve")
+    async def approve_spend(
+        user_id: WritableUser, allowance_id: str, spend_id: str
+    ) -> PendingSpend:
+        return await resolve_pending_spend(user_id, allowance_id, spend_id, approve=True)
+
+    @app.post("/allowances/{allowance_id}/spends/{spend_id}/reject")
+    async def reject_spend(
+        user_id: WritableUser, allowance_id: str, spend_id: str
+    ) -> PendingSpend:
+        return await resolve_pending_spend(user_id, allowance_id, spend_id, approve=False)
+
+    @app.get("/allowance-view")
+    async def view_allowance(
+        allowance_token: Annotated[str, Header()],
+    ) -> AllowanceView:
+        """Read-only view of the child's pool, authorized with the allowance's view token"""
+        found = await storage.load_allowance_by_view_token(allowance_token)
+        if found is None:
+            raise HTTPException(status_code=401, detail="Invalid allowance token")
+        user_id, allowance = found
+        pool = await storage.load_pool(user_id, allowance.pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Allowance pool not found")
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(pool_ids=[pool.id]),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=20,
+        )
+        return AllowanceView(
+            child_name=allowance.child_name,
+            balance=pool.balance,
+            weekly_amount=allowance.weekly_amount,
+            next_payment_at=allowance.next_payment_at,
+            last_transactions=service.present_transactions(transactions, visible=False),
+        )
+
+    @app.post("/challenges")
+    async def create_challenge(user_id: WritableUser, challenge: Challenge) -> StoredChallenge:
+        if challenge.end.timestamp() <= challenge.start.timestamp():
+            raise HTTPException(status_code=400, detail="Challenge end must be after its start")
+        if (
+            challenge.pool_id is not None
+            and await storage.load_pool(user_id, challenge.pool_id) is None
+        ):
+            raise HTTPException(status_code=400, detail="Challenge pool does not exist")
+        challenge.completed_at = None
+        return await storage.add_challenge(user_id, challenge)
+
+    @app.get("/challenges")
+    async def get_challenges(user_id: AuthorizedUser) -> list[StoredChallenge]:
+        return await storage.load_challenges(user_id)
+
+    @app.get("/challenges/{challenge_id}/progress")
+    async def get_challenge_progress(
+        user_id: AuthorizedUser, challenge_id: str
+    ) -> ChallengeProgress:
+        challenge = await storage.load_challenge(user_id, challenge_id)
+        if challenge is None:
+            raise HTTPException(status_code=404, detail="Challenge not found")
+        now = datetime.datetime.now(tz=datetime.UTC)
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=challenge.start, max_timestamp=challenge.end),
+            order=TransactionOrder.OLDEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        tz = (await storage.load_user_settings(user_id)).tzinfo
+        progress = compute_progress(challenge, transactions, now, tz)
+        if progress.completed and challenge.completed_at is None:
+            challenge.completed_at = now
+            await storage.save_challenge(user_id, challenge)
+            await events.publish(ChallengeCompleted(user_id=user_id, challenge=challenge))
+        return progress
+
+    async def ensure_goal_pool_exists(user_id: UserId, goal: Goal) -> None:
+        if await storage.load_pool(user_id, goal.pool_id) is None:
+            raise HTTPException(status_code=400, detail="Goal pool does not exist")
+
+    @app.post("/goals")
+    async def create_goal(user_id: WritableUser, goal: Goal) -> StoredGoal:
+        await ensure_goal_pool_exists(user_id, goal)
+        return await storage.add_goal(user_id, goal)
+
+    @app.get("/goals")
+    async def get_goals(user_id: AuthorizedUser) -> list[StoredGoal]:
+        return await storage.load_goals(user_id)
+
+    @app.get("/goals/{goal_id}")
+    async def get_goal(user_id: AuthorizedUser, goal_id: str) -> StoredGoal:
+        goal = await storage.load_goal(user_id, goal_id)
+        if goal is None:
+            raise HTTPException(status_code=404, detail="Goal not found")
+        return goal
+
+    @app.put("/goals/{goal_id}", response_class=PlainTextResponse)
+    async def update_goal(user_id: WritableUser, goal_id: str, update: GoalUpdate) -> Ok:
+        goal = await get_goal(user_id, goal_id)
+        update.apply(goal)
+        await ensure_goal_pool_exists(user_id, goal)
+        await storage.save_goal(user_id, goal)
+        return "OK"
+
+    @app.delete("/goals/{goal_id}", response_class=PlainTextResponse)
+    async def delete_goal(user_id: WritableUser, goal_id: str) -> Ok:
+        if await storage.delete_goal(user_id, goal_id):
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="Goal not found")
+
+    @app.get("/goals/{goal_id}/progress")
+    async def get_goal_progress(user_id: AuthorizedUser, goal_id: str) -> GoalProgress:
+        goal = await get_goal(user_id, goal_id)
+        pool = await storage.load_pool(user_id, goal.pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Goal pool not found")
+        now = datetime.datetime.now(tz=datetime.UTC)
+        recent_transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=now - SAVING_RATE_WINDOW, pool_ids=[pool.id]),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        saved, _, unconverted = await pool_total(
+            pool, exchange_rates, target_currency=goal.target.currency
+        )
+        if unconverted:
+            raise RateUnavailable(unconverted[0].currency, goal.target.currency)
+        return compute_goal_progress(
+            goal,
+            saved=saved,
+            saved_in_window=await sum_transactions(
+                recent_transactions, exchange_rates, target_currency=goal.target.currency
+            ),
+            now=now,
+        )
+
+    @app.post("/debts")
+    async def create_debt(user_id: WritableUser, debt: Debt) -> StoredDebt:
+        if debt.sum.amount <= 0:
+            raise HTTPException(status_code=400, detail="Debt amount must be positive")
+        debt.settled_at = None
+        debt.settlement_transaction_id = None
+        debt.shared_transaction_id = None
+        return await storage.add_debt(user_id, debt)
+
+    @app.get("/debts")
+    async def get_debts(
+        user_id: AuthorizedUser, outstanding_only: bool = False
+    ) -> list[StoredDebt]:
+        debts = await storage.load_debts(user_id)
+        if outstanding_only:
+            debts = [d for d in debts if d.settled_at is None]
+        return debts
+
+    @app.get("/debts/summary")
+    async def get_debts_summary(user_id: AuthorizedUser) -> list[CounterpartyDebtSummary]:
+        balances: dict[str, dict[Currency, Decimal]] = {}
+        for debt in await storage.load_debts(user_id):
+            if debt.settled_at is not None:
+                continue
+            signed = debt.signed_sum()
+            per_currency = balances.setdefault(debt.counterparty, {})
+            per_currency[signed.currency] = (
+                per_currency.get(signed.currency, Decimal(0)) + signed.amount
+            )
+        return [
+            CounterpartyDebtSummary(
+                counterparty=counterparty,
+                balance=[
+                    MoneySum(amount=amount, currency=currency)
+                    for currency, amount in per_currency.items()
+                    if amount
+                ],
+            )
+            for counterparty, per_currency in sorted(balances.items())
+        ]
+
+    @app.post("/debts/{debt_id}/settle")
+    async def settle_debt(
+        user_id: WritableUser,
+        debt_id: str,
+        body: SettleDebtRequestBody,
+        visible: DescriptionsVisible,
+    ) -> StoredTransaction:
+        """Records the money returned (or paid back) as a transaction in the pool"""
+        debt = await storage.load_debt(user_id, debt_id)
+        if debt is None:
+            raise HTTPException(status_code=404, detail="Debt not found")
+        if debt.settled_at is not None:
+            raise HTTPException(status_code=409, detail="Debt is already settled")
+        if debt.direction is DebtDirection.LENT:
+            description = f"{debt.counterparty} returned {debt.sum}"
+        else:
+            description = f"Paid back {debt.sum} to {debt.counterparty}"
+        if debt.description:
+            description += f" ({debt.description})"
+        transaction = Transaction(
+            sum=debt.signed_sum(),
+            pool_id=body.pool_id,
+            description=description,
+            tags=[DEBT_TAG],
+        )
+        [stored] = await service.add_transactions(user_id, [transaction])
+        debt.settled_at = stored.timestamp
+        debt.settlement_transaction_id = stored.id
+        await storage.save_debt(user_id, debt)
+        return service.present_transactions([stored], visible)[0]
+
+    @app.post("/transactions/{transaction_id}/share")
+    async def share_transaction(
+        user_id: WritableUser, transaction_id: str, body: ShareTransactionRequestBody
+    ) -> list[StoredDebt]:
+        """Records what each participant owes the user for their share of the expense"""
+        transaction = await storage.load_transaction(user_id, transaction_id)
+        if transaction is None:
+            raise HTTPException(status_code=404, detail="No such transaction")
+        if transaction.kind is not TransactionKind.EXPENSE:
+            raise HTTPException(status_code=400, detail="Only expenses can be shared")
+        debts = await storage.load_debts(user_id)
+        if any(d.shared_transaction_id == transaction.id for d in debts):
+            raise HTTPException(status_code=409, detail="Transaction is already shared")
+        spent = MoneySum(amount=-transaction.sum.amount, currency=transaction.sum.currency)
+        portions = share_portions(spent, body.own_shares, body.participants)
+        return [
+            await storage.add_debt(
+                user_id,
+                Debt(
+                    counterparty=participant.counterparty,
+                    direction=DebtDirection.LENT,
+                    sum=portion,
+                    shared_transaction_id=transaction.id,
+                ),
+            )
+            for participant, portion in zip(body.participants, portions)
+            if portion.amount > 0
+        ]
+
+    @app.post("/debts/counterparties/{counterparty}/settle")
+    async def settle_up(
+        user_id: WritableUser,
+        counterparty: str,
+        body: SettleDebtRequestBody,
+        visible: DescriptionsVisible,
+    ) -> list[StoredTransaction]:
+        """
+        Settles all outstanding debts with the counterparty, recording one balancing transaction
+        per currency they don't cancel out in
+        """
+        outstanding = [
+            d
+            for d in await storage.load_debts(user_id)
+            if d.counterparty == counterparty and d.settled_at is None
+        ]
+        if not outstanding:
+            raise HTTPException(status_code=404, detail="No outstanding debts with counterparty")
+        balance: dict[Currency, Decimal] = {}
+        for debt in outstanding:
+            signed = debt.signed_sum()
+            balance[signed.currency] = balance.get(signed.currency, Decimal(0)) + signed.amount
+
+        currencies = [currency for currency, amount in balance.items() if amount]
+        transactions = [
+            Transaction(
+                sum=MoneySum(amount=balance[currency], currency=currency),
+                pool_id=body.pool_id,
+                description=f"Settled up with {counterparty}",
+                tags=[DEBT_TAG],
+            )
+            for currency in currencies
+        ]
+        stored = await service.add_transactions(user_id, transactions)
+        # keyed by the debts' currency, the transactions may be converted to the pool's one
+        settlements = dict(zip(currencies, stored))
+
+        now = datetime.datetime.now(tz=datetime.UTC)
+        for debt in outstanding:
+            settlement = settlements.get(debt.sum.currency)
+            debt.settled_at = settlement.timestamp if settlement is not None else now
+            debt.settlement_transaction_id = settlement.id if settlement is not None else None
+            await storage.save_debt(user_id, debt)
+        return service.present_transactions(stored, visible)
+
+    async def ensure_template_pool_exists(user_id: UserId, template: TransactionTemplate) -> None:
+        if await storage.load_pool(user_id, template.pool_id) is None:
+            raise HTTPException(status_code=400, detail="Template pool does not exist")
+
+    @app.post("/templates")
+    async def create_template(
+        user_id: WritableUser, template: TransactionTemplate
+    ) -> StoredTransactionTemplate:
+        await ensure_template_pool_exists(user_id, template)
+        return await storage.add_transaction_template(user_id, template)
+
+    @app.get("/templates")
+    async def get_templates(user_id: AuthorizedUser) -> list[StoredTransactionTemplate]:
+        return await storage.load_transaction_templates(user_id)
+
+    @app.get("/templates/{template_id}")
+    async def get_template(user_id: AuthorizedUser, template_id: str) -> StoredTransactionTemplate:
+        template = await storage.load_transaction_template(user_id, template_id)
+        if template is None:
+            raise HTTPException(status_code=404, detail="Template not found")
+        return template
+
+    @app.put("/templates/{template_id}", response_class=PlainTextResponse)
+    async def update_template(
+        user_id: WritableUser, template_id: str, update: TransactionTemplateUpdate
+    ) -> Ok:
+        template = await get_template(user_id, template_id)
+        update.apply(template)
+        await ensure_template_pool_exists(user_id, template)
+        await storage.save_transaction_template(user_id, template)
+        return "OK"
+
+    @app.delete("/templates/{template_id}", response_class=PlainTextResponse)
+    async def delete_template(user_id: WritableUser, template_id: str) -> Ok:
+        if await storage.delete_transaction_template(user_id, template_id):
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="Template not found")
+
+    @app.post(
+        "/templates/{template_id}/apply",
+        responses={202: {"model": PendingSpend}, 409: {"model": DuplicateTransactionResponse}},
+    )
+    async def apply_template(
+        user_id: WritableUser,
+        visible: DescriptionsVisible,
+        template_id: str,
+        body: ApplyTemplateRequestBody,
+        force: bool = False,
+    ) -> CreatedTransactionResponse:
+        """Creates a transaction from the template, the same way as POST /transactions"""
+        template = await get_template(user_id, template_id)
+        transaction = Transaction(
+            sum=body.sum if body.sum is not None else template.sum,
+            pool_id=template.pool_id,
+            description=(
+                body.description if body.description is not None else template.description
+            ),
+            tags=template.tags,
+        )
+        if body.timestamp is not None:
+            transaction.timestamp = body.timestamp
+        return await add_transaction(user_id, visible, transaction, force)
+
+    async def ensure_rule_pool_exists(user_id: UserId, rule: CategorizationRule) -> None:
+        if rule.pool_id is not None and await storage.load_pool(user_id, rule.pool_id) is None:
+            raise HTTPException(status_code=400, detail="Rule pool does not exist")
+
+    @app.post("/rules")
+    async def create_rule(
+        user_id: WritableUser, rule: CategorizationRule
+    ) -> StoredCategorizationRule:
+        """Applied to new transactions in the order of creation"""
+        await ensure_rule_pool_exists(user_id, rule)
+        return await storage.add_rule(user_id, rule)
+
+    @app.get("/rules")
+    async def get_rules(user_id: AuthorizedUser) -> list[StoredCategorizationRule]:
+        return await storage.load_rules(user_id)
+
+    @app.get("/rules/{rule_id}")
+    async def get_rule(user_id: AuthorizedUser, rule_id: str) -> StoredCategorizationRule:
+        rule = await storage.load_rule(user_id, rule_id)
+        if rule is None:
+            raise HTTPException(status_code=404, detail="Rule not found")
+        return rule
+
+    @app.put("/rules/{rule_id}", response_class=PlainTextResponse)
+    async def update_rule(
+        user_id: WritableUser, rule_id: str, update: CategorizationRuleUpdate
+    ) -> Ok:
+        rule = await get_rule(user_id, rule_id)
+        update.apply(rule)
+        try:
+            rule = StoredCategorizationRule.model_validate(rule.model_dump())
+        except pydantic.ValidationError as e:
+            raise HTTPException(status_code=400, detail=str(e))
+        await ensure_rule_pool_exists(user_id, rule)
+        await storage.save_rule(user_id, rule)
+        return "OK"
+
+    @app.delete("/rules/{rule_id}", response_class=PlainTextResponse)
+    async def delete_rule(user_id: WritableUser, rule_id: str) -> Ok:
+        if await storage.delete_rule(user_id, rule_id):
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="Rule not found")
+
+    @app.post("/rules/{rule_id}/apply")
+    async def apply_rule(user_id: WritableUser, rule_id: str) -> RuleApplicationResponse:
+        """Applies the rule to the existing transactions, can be undone as a single operation"""
+        rule = await get_rule(user_id, rule_id)
+        updated, locked = await service.apply_rule(user_id, rule)
+        return RuleApplicationResponse(
+            updated_transaction_ids=[t.id for t in updated], locked=locked
+        )
+
+    async def ensure_payee_exists(user_id: UserId, payee_id: PayeeId) -> None:
+        if await storage.load_payee(user_id, payee_id) is None:
+            raise HTTPException(status_code=400, detail="Payee does not exist")
+
+    @app.post("/payees")
+    async def create_payee(user_id: WritableUser, payee: Payee) -> StoredPayee:
+        return await storage.add_payee(user_id, payee)
+
+    @app.get("/payees")
+    async def get_payees(user_id: AuthorizedUser) -> list[StoredPayee]:
+        return await storage.load_payees(user_id)
+
+    @app.get("/payees/suggest")
+    async def suggest_payees(
+        user_id: AuthorizedUser,
+        q: Annotated[str, Query(min_length=1)],
+        count: Annotated[int, Query(gt=0, le=MAX_SUGGESTIONS)] = 10,
+    ) -> list[StoredPayee]:
+        """Payees with the names starting with the query first, then the ones containing it"""
+        query = q.casefold()
+        payees = [p for p in await storage.load_payees(user_id) if query in p.name.casefold()]
+        payees.sort(key=lambda p: not p.name.casefold().startswith(query))
+        return payees[:count]
+
+    @app.get("/suggest")
+    async def suggest(
+        user_id: AuthorizedUser,
+        visible: DescriptionsVisible,
+        field: SuggestionField,
+        q: str = "",  # the most frequent values overall if empty
+        count: Annotated[int, Query(gt=0, le=MAX_SUGGESTIONS)] = 10,
+    ) -> list[Suggestion]:
+        """Values from the user's recent transactions, for auto-completion on manual entry"""
+        since = datetime.datetime.now(tz=datetime.UTC) - SUGGESTIONS_WINDOW
+        if privacy is None or field is not SuggestionField.DESCRIPTION:
+            return await storage.load_suggestions(user_id, field, q, since, count)
+        if not visible:
+            return []
+        # descriptions are stored encrypted, matched once decrypted
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=since),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_SUGGESTION_TRANSACTIONS,
+        )
+        transactions = service.present_transactions(transactions, visible)
+        return count_suggestions(transactions, field, q, [], count)
+
+    @app.get("/payees/{payee_id}")
+    async def get_payee(user_id: AuthorizedUser, payee_id: str) -> StoredPayee:
+        payee = await storage.load_payee(user_id, payee_id)
+        if payee is None:
+            raise HTTPException(status_code=404, detail="Payee not found")
+        return payee
+
+    @app.put("/payees/{payee_id}", response_class=PlainTextResponse)
+    async def update_payee(user_id: WritableUser, payee_id: str, payee: Payee) -> Ok:
+        await get_payee(user_id, payee_id)
+        await storage.save_payee(user_id, StoredPayee.from_payee(payee, id=payee_id))
+        return "OK"
+
+    @app.delete("/payees/{payee_id}", response_class=PlainTextResponse)
+    async def delete_payee(user_id: WritableUser, payee_id: str) -> Ok:
+        """The payee is unset on its transactions, which can be undone"""
+        if await storage.load_payee(user_id, payee_id) is None:
+            raise HTTPException(status_code=404, detail="Payee not found")
+        await service.unassign_payee(user_id, payee_id)
+        if not await storage.delete_payee(user_id, payee_id):
+            raise HTTPException(status_code=404, detail="Payee not found")
+        return "OK"
+
+    async def ensure_note_valid(user_id: UserId, note: PoolNote) -> None:
+        if not note.text and note.transaction_id is None:
+            # can only happen on update, new notes are validated by the model
+            raise HTTPException(status_code=400, detail="Note can't be empty")
+        if await storage.load_pool(user_id, note.pool_id) is None:
+            raise HTTPException(status_code=400, detail="Note pool does not exist")
+        if note.transaction_id is not None:
+            transaction = await storage.load_transaction(user_id, note.transaction_id)
+            if transaction is None or transaction.pool_id != note.pool_id:
+                raise HTTPException(
+                    status_code=400, detail="Pinned transaction is not in the pool"
+                )
+
+    async def present_notes(
+        user_id: UserId, notes: list[StoredPoolNote], visible: bool
+    ) -> list[PoolNoteView]:
+        views: list[PoolNoteView] = []
+        for note in notes:
+            transaction = None
+            if note.transaction_id is not None:
+                transaction = await storage.load_transaction(user_id, note.transaction_id)
+            if transaction is not None:
+                transaction = service.present_transactions([transaction], visible)[0]
+            views.append(PoolNoteView(note=note, transaction=transaction))
+        return views
+
+    async def get_note(user_id: UserId, note_id: str) -> StoredPoolNote:
+        note = await storage.load_pool_note(user_id, note_id)
+        if note is None:
+            raise HTTPException(status_code=404, detail="Note not found")
+        return note
+
+    @app.post("/notes")
+    async def create_note(
+        user_id: WritableUser, visible: DescriptionsVisible, note: PoolNote
+    ) -> PoolNoteView:
+        await ensure_note_valid(user_id, note)
+        stored = await storage.add_pool_note(user_id, note)
+        return (await present_notes(user_id, [stored], visible))[0]
+
+    @app.get("/notes")
+    async def get_notes(
+        user_id: AuthorizedUser, visible: DescriptionsVisible, pool_id: MoneyPoolId | None = None
+    ) -> list[PoolNoteView]:
+        notes = await storage.load_pool_notes(user_id, pool_id=pool_id)
+        return await present_notes(user_id, notes, visible)
+
+    @app.put("/notes/{note_id}", response_class=PlainTextResponse)
+    async def update_note(user_id: WritableUser, note_id: str, update: PoolNoteUpdate) -> Ok:
+        note = await get_note(user_id, note_id)
+        update.apply(note)
+        await ensure_note_valid(user_id, note)
+        await storage.save_pool_note(user_id, note)
+        return "OK"
+
+    @app.delete("/notes/{note_id}", response_class=PlainTextResponse)
+    async def delete_note(user_id: WritableUser, note_id: str) -> Ok:
+        if await storage.delete_pool_note(user_id, note_id):
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="Note not found")
+
+    @app.post("/sync-balance/{pool_id}", response_class=PlainTextResponse)
     async def sync_pool_balance(
-        user_id: AuthorizedUser, pool_id: str, body: SyncBalanceRequestBody
+        user_id: WritableUser, pool_id: str, body: SyncBalanceRequestBody
     ) -> Ok:
         pool = await storage.load_pool(user_id, pool_id)
         if pool is None:
@@ -432,39 +2791,749 @@ def create_app(
                 400,
                 detail=f"New amount for every currency in the pool expected ({len(pool.balance)})",
             )
+        now = datetime.datetime.now(tz=datetime.UTC)
+        await service.ensure_period_unlocked(user_id, pool_id, now)
 
-        errors: list[Exception] = []
+        sync_transactions: list[Transaction] = []
         for old_sum, new_amount in zip(pool.balance, body.amounts):
             new_sum = MoneySum(amount=Decimal(new_amount), currency=old_sum.currency)
             delta = new_sum.amount - old_sum.amount
             if not delta:
                 continue
-            try:
-                await storage.add_transaction(
-                    user_id=user_id,
-                    transaction=Transaction(
-                        timestamp=datetime.datetime.now(),
-                        sum=MoneySum(amount=delta, currency=old_sum.currency),
-                        pool_id=pool_id,
-                        description=f"{pool.display_name} synced {old_sum.amount} -> {new_sum.amount} {old_sum.currency}",
-                        is_diffuse=True,
+            sync_transactions.append(
+                Transaction(
+                    timestamp=now,
+                    sum=MoneySum(amount=delta, currency=old_sum.currency),
+                    pool_id=pool_id,
+                    description=(
+                        f"{pool.display_name} synced "
+                        + f"{old_sum.amount} -> {new_sum.amount} {old_sum.currency}"
                     ),
+                    is_diffuse=True,
                 )
-            except Exception as e:
-                logger.exception(f"Error syncing {old_sum} -> {new_sum}")
-                errors.append(e)
+            )
+        try:
+            await service.add_transactions(user_id, sync_transactions)
+        except ServiceError:
+            raise
+        except Exception:
+            logger.exception(f"Error syncing {pool.balance} -> {body.amounts}")
+            raise HTTPException(503, detail="Failed to save transactions")
+        return "OK"
 
-        if errors:
-            if len(errors) == len(pool.balance):
-                raise HTTPException(
-                    503,
-                    detail="Failed to save transactions",
+    @app.post("/pools/{pool_id}/reconciliations")
+    async def start_reconciliation(
+        user_id: WritableUser, pool_id: str, body: StartReconciliationRequestBody
+    ) -> StoredReconciliation:
+        if body.start.tzinfo is None or body.end.tzinfo is None:
+            raise HTTPException(
+                status_code=400, detail="All datetimes must have timezone info specified"
+            )
+        if body.start >= body.end:
+            raise HTTPException(status_code=400, detail="Period start must precede its end")
+        if await storage.load_pool(user_id, pool_id) is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        return await storage.add_reconciliation(
+            user_id,
+            Reconciliation(
+                pool_id=pool_id,
+                start=body.start,
+                end=body.end,
+                statement_balance=body.statement_balance,
+            ),
+        )
+
+    @app.get("/pools/{pool_id}/reconciliations")
+    async def get_reconciliations(
+        user_id: AuthorizedUser, pool_id: str
+    ) -> list[StoredReconciliation]:
+        return await storage.load_reconciliations(user_id, pool_id=pool_id)
+
+    async def load_reconciliation(
+        user_id: UserId, reconciliation_id: ReconciliationId, for_update: bool
+    ) -> StoredReconciliation:
+        reconciliation = await storage.load_reconciliation(user_id, reconciliation_id)
+        if reconciliation is None:
+            raise HTTPException(status_code=404, detail="Reconciliation not found")
+        if for_update and reconciliation.status is ReconciliationStatus.FINISHED:
+            raise HTTPException(status_code=409, detail="Reconciliation is already finished")
+        return reconciliation
+
+    async def load_period_transactions(
+        user_id: UserId, reconciliation: StoredReconciliation
+    ) -> list[StoredTransaction]:
+        return await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(
+                min_timestamp=reconciliation.start,
+                max_timestamp=reconciliation.end,
+                pool_ids=[reconciliation.pool_id],
+            ),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.OLDEST,
+        )
+
+    async def revert_pool_to(
+        user_id: UserId, pool: StoredMoneyPool, timestamp: datetime.datetime
+    ) -> None:
+        transactions_after = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=timestamp, pool_ids=[pool.id]),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        for t in transactions_after:
+            pool.update_with_transaction(t.inverted())
+
+    @app.get("/reconciliations/{reconciliation_id}")
+    async def get_reconciliation_worksheet(
+        user_id: AuthorizedUser, visible: DescriptionsVisible, reconciliation_id: str
+    ) -> ReconciliationWorksheet:
+        reconciliation = await load_reconciliation(user_id, reconciliation_id, for_update=False)
+        pool = await storage.load_pool(user_id, reconciliation.pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+
+        await revert_pool_to(user_id, pool, reconciliation.end)
+
+        discrepancies: list[MoneySum] = []
+        for statement_sum in reconciliation.statement_balance:
+            discrepancy = MoneySum(amount=statement_sum.amount, currency=statement_sum.currency)
+            for computed in pool.balance:
+                if computed.currency == statement_sum.currency:
+                    discrepancy.amount -= computed.amount
+            for adjustment in reconciliation.adjustments:
+                if adjustment.sum.currency == statement_sum.currency:
+                    discrepancy.amount -= adjustment.sum.amount
+            discrepancies.append(discrepancy)
+
+        matched = set(reconciliation.matched_transaction_ids)
+        return ReconciliationWorksheet(
+            reconciliation=reconciliation,
+            items=[
+                ReconciliationWorksheetItem(transaction=t, matched=t.id in matched)
+                for t in service.present_transactions(
+                    await load_period_transactions(user_id, reconciliation), visible
                 )
-            else:
-                raise HTTPException(
-                    503,
-                    detail="Failed to save some transactions, sync might be incomplete",
+            ],
+            computed_balance=pool.balance,
+            discrepancies=discrepancies,
+        )
+
+    @app.put("/reconciliations/{reconciliation_id}/matched", response_class=PlainTextResponse)
+    async def update_reconciliation_matches(
+        user_id: WritableUser, reconciliation_id: str, update: ReconciliationMatchUpdate
+    ) -> Ok:
+        reconciliation = await load_reconciliation(user_id, reconciliation_id, for_update=True)
+        period_transaction_ids = {
+            t.id for t in await load_period_transactions(user_id, reconciliation)
+        }
+        if not set(update.transaction_ids).issubset(period_transaction_ids):
+            raise HTTPException(
+                status_code=400,
+                detail="Only transactions in the reconciled pool and period can be matched",
+            )
+        matched = set(reconciliation.matched_transaction_ids)
+        if update.matched:
+            matched.update(update.transaction_ids)
+        else:
+            matched.difference_update(update.transaction_ids)
+        reconciliation.matched_transaction_ids = sorted(matched)
+        await storage.save_reconciliation(user_id, reconciliation)
+        return "OK"
+
+    @app.post(
+        "/reconciliations/{reconciliation_id}/adjustments", response_class=PlainTextResponse
+    )
+    async def add_reconciliation_adjustment(
+        user_id: WritableUser, reconciliation_id: str, adjustment: ReconciliationAdjustment
+    ) -> Ok:
+        reconciliation = await load_reconciliation(user_id, reconciliation_id, for_update=True)
+        reconciliation.adjustments.append(adjustment)
+        await storage.save_reconciliation(user_id, reconciliation)
+        return "OK"
+
+    @app.post("/reconciliations/{reconciliation_id}/finish", response_class=PlainTextResponse)
+    async def finish_reconciliation(user_id: WritableUser, reconciliation_id: str) -> Ok:
+        reconciliation = await load_reconciliation(user_id, reconciliation_id, for_update=True)
+        reconciliation.status = ReconciliationStatus.FINISHED
+        reconciliation.finished_at = datetime.datetime.now(tz=datetime.UTC)
+        await storage.save_reconciliation(user_id, reconciliation)
+        return "OK"
+
+    @app.post("/balance-snapshots")
+    async def create_balance_snapshot(
+        user_id: WritableUser, snapshot: BalanceSnapshot
+    ) -> StoredBalanceSnapshot:
+        if snapshot.timestamp.tzinfo is None:
+            raise HTTPException(
+                status_code=400, detail="All datetimes must have timezone info specified"
+            )
+        pool = await storage.load_pool(user_id, snapshot.pool_id)
+        if pool is None:
+            raise HTTPException(status_code=400, detail="Pool not found")
+        pool_currencies = {s.currency for s in pool.balance}
+        if any(s.currency not in pool_currencies for s in snapshot.balance):
+            raise HTTPException(
+                status_code=400, detail="Snapshot currencies must be present in the pool"
+            )
+        return await storage.add_balance_snapshot(user_id, snapshot)
+
+    @app.get("/balance-snapshots")
+    async def get_balance_snapshots(
+        user_id: AuthorizedUser, pool_id: MoneyPoolId | None = None
+    ) -> list[StoredBalanceSnapshot]:
+        return await storage.load_balance_snapshots(user_id, pool_id=pool_id)
+
+    @app.delete("/balance-snapshots/{snapshot_id}", response_class=PlainTextResponse)
+    async def delete_balance_snapshot(user_id: WritableUser, snapshot_id: str) -> Ok:
+        if await storage.delete_balance_snapshot(user_id, snapshot_id):
+            return "OK"
+        else:
+            raise HTTPException(status_code=404, detail="Balance snapshot not found")
+
+    @app.get("/balance-snapshots/{snapshot_id}/reconciliation")
+    async def reconcile_balance_snapshot(
+        user_id: AuthorizedUser, snapshot_id: str
+    ) -> BalanceSnapshotReconciliation:
+        snapshot = await storage.load_balance_snapshot(user_id, snapshot_id)
+        if snapshot is None:
+            raise HTTPException(status_code=404, detail="Balance snapshot not found")
+        pool = await storage.load_pool(user_id, snapshot.pool_id)
+        if pool is None:
+            raise HTTPException(status_code=404, detail="Pool not found")
+        await revert_pool_to(user_id, pool, snapshot.timestamp)
+
+        drift: list[MoneySum] = []
+        for snapshot_sum in snapshot.balance:
+            difference = MoneySum(amount=snapshot_sum.amount, currency=snapshot_sum.currency)
+            for computed in pool.balance:
+                if computed.currency == snapshot_sum.currency:
+                    difference.amount -= computed.amount
+            drift.append(difference)
+
+        return BalanceSnapshotReconciliation(
+            snapshot=snapshot,
+            computed_balance=pool.balance,
+            drift=drift,
+            suggested_adjustments=[
+                Transaction(
+                    sum=d,
+                    pool_id=pool.id,
+                    description=f"{pool.display_name} adjusted to the balance snapshot",
+                    # just before the snapshot, so that it is counted towards it
+                    timestamp=snapshot.timestamp - datetime.timedelta(seconds=1),
+                    is_diffuse=True,
                 )
+                for d in drift
+                if d.amount != 0
+            ],
+        )
+
+    async def month_close_checklist(
+        user_id: UserId, month_close: MonthClose
+    ) -> list[ChecklistItemStatus]:
+        start, end = month_close.period()
+
+        def in_month(timestamp: datetime.datetime) -> bool:
+            return start.timestamp() <= timestamp.timestamp() < end.timestamp()
+
+        pending_spends = [
+            s
+            for a in await storage.load_allowances(user_id)
+            for s in a.pending_spends
+            if s.status is PendingSpendStatus.PENDING and in_month(s.transaction.timestamp)
+        ]
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=start, max_timestamp=end),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        reconciliations = await storage.load_reconciliations(user_id, pool_id=None)
+        unreconciled_pool_ids = {
+            t.pool_id
+            for t in transactions
+            if in_month(t.timestamp)
+            and not any(
+                r.status is ReconciliationStatus.FINISHED
+                and r.pool_id == t.pool_id
+                and r.start.timestamp() < end.timestamp()
+                and r.end.timestamp() > start.timestamp()
+                for r in reconciliations
+            )
+        }
+        unreconciled = [
+            p.display_name
+            for p in await storage.load_pools(user_id)
+            if p.id in unreconciled_pool_ids
+        ]
+        return [
+            ChecklistItemStatus(
+                item=ChecklistItem.DRAFTS_REVIEWED,
+                done=not pending_spends,
+                detail=(
+                    f"{len(pending_spends)} spend(s) await approval" if pending_spends else None
+                ),
+            ),
+            ChecklistItemStatus(
+                item=ChecklistItem.RECONCILIATION_DONE,
+                done=not unreconciled,
+                detail=f"Not reconciled: {', '.join(unreconciled)}" if unreconciled else None,
+            ),
+            ChecklistItemStatus(
+                item=ChecklistItem.BUDGETS_REVIEWED,
+                done=ChecklistItem.BUDGETS_REVIEWED in month_close.ticked,
+            ),
+        ]
+
+    async def load_month_close(user_id: UserId, year: int, month: int) -> MonthClose:
+        """The stored one, or a new open one"""
+        month_close = await storage.load_month_close(user_id, year, month)
+        if month_close is None:
+            settings = await storage.load_user_settings(user_id)
+            try:
+                month_close = MonthClose(year=year, month=month, timezone=settings.timezone)
+            except pydantic.ValidationError:
+                raise HTTPException(status_code=404, detail="No such month")
+        return month_close
+
+    @app.get("/month-close")
+    async def get_month_closes(user_id: AuthorizedUser) -> list[MonthClose]:
+        return await storage.load_month_closes(user_id)
+
+    @app.get("/month-close/{year}/{month}")
+    async def get_month_close(user_id: AuthorizedUser, year: int, month: int) -> MonthCloseView:
+        month_close = await load_month_close(user_id, year, month)
+        checklist = await month_close_checklist(user_id, month_close)
+        return MonthCloseView(
+            month_close=month_close,
+            checklist=checklist,
+            can_close=(
+                month_close.status is MonthCloseStatus.OPEN
+                and month_close.period()[1] <= datetime.datetime.now(tz=datetime.UTC)
+                and all(i.done for i in checklist)
+            ),
+        )
+
+    @app.put("/month-close/{year}/{month}/checklist/{item}", response_class=PlainTextResponse)
+    async def tick_month_close_item(
+        user_id: WritableUser, year: int, month: int, item: ChecklistItem, done: bool = True
+    ) -> Ok:
+        if item not in MANUAL_ITEMS:
+            raise HTTPException(status_code=400, detail="The item is checked automatically")
+        month_close = await load_month_close(user_id, year, month)
+        if month_close.status is MonthCloseStatus.CLOSED:
+            raise HTTPException(status_code=409, detail="Month is already closed")
+        ticked = set(month_close.ticked)
+        if done:
+            ticked.add(item)
+        else:
+            ticked.discard(item)
+        month_close.ticked = sorted(ticked)
+        await storage.save_month_close(user_id, month_close)
+        return "OK"
+
+    @app.post("/month-close/{year}/{month}/complete")
+    async def complete_month_close(user_id: WritableUser, year: int, month: int) -> MonthClose:
+        """Locks the month for changes and freezes its report"""
+        month_close = await load_month_close(user_id, year, month)
+        if month_close.status is MonthCloseStatus.CLOSED:
+            raise HTTPException(status_code=409, detail="Month is already closed")
+        if month_close.period()[1] > datetime.datetime.now(tz=datetime.UTC):
+            raise HTTPException(status_code=400, detail="Only past months can be closed")
+        checklist = await month_close_checklist(user_id, month_close)
+        not_done = [i.item for i in checklist if not i.done]
+        if not_done:
+            raise HTTPException(
+                status_code=400, detail=f"Checklist is not complete: {', '.join(not_done)}"
+            )
+        month_close.status = MonthCloseStatus.CLOSED
+        month_close.closed_at = datetime.datetime.now(tz=datetime.UTC)
+        await storage.save_month_close(user_id, month_close)
+        await events.publish(MonthClosed(user_id=user_id, year=year, month=month))
+        return await load_month_close(user_id, year, month)
+
+    async def freeze_closed_month(event: MonthClosed) -> None:
+        settings = await storage.load_user_settings(event.user_id)
+        snapshot = await freeze_report(
+            event.user_id,
+            CreateReportSnapshotRequestBody(
+                year=event.year, month=event.month, target_currency=settings.default_currency
+            ),
+        )
+        month_close = await storage.load_month_close(event.user_id, event.year, event.month)
+        if month_close is not None:
+            month_close.report_snapshot_id = snapshot.id
+            await storage.save_month_close(event.user_id, month_close)
+
+    events.subscribe(MonthClosed, freeze_closed_month)
+
+    @app.get("/settings")
+    async def get_settings(user_id: AuthorizedUser) -> UserSettings:
+        return await storage.load_user_settings(user_id)
+
+    @app.put("/settings", response_class=PlainTextResponse)
+    async def save_settings(user_id: WritableUser, settings: UserSettings) -> Ok:
+        if settings.default_pool_id is not None:
+            if await storage.load_pool(user_id, settings.default_pool_id) is None:
+                raise HTTPException(status_code=400, detail="Default pool does not exist")
+        await storage.save_user_settings(user_id, settings)
+        return "OK"
+
+    def calendar_token_response(token: str) -> CalendarTokenResponse:
+        return CalendarTokenResponse(
+            token=token, feed_path=f"/calendar.ics?{urllib.parse.urlencode({'token': token})}"
+        )
+
+    @app.get("/calendar/token")
+    async def get_calendar_token(user_id: AuthorizedUser) -> CalendarTokenResponse:
+        token = await storage.load_calendar_token(user_id)
+        if token is None:
+            raise HTTPException(status_code=404, detail="Calendar feed is not enabled")
+        return calendar_token_response(token)
+
+    @app.post("/calendar/token")
+    async def issue_calendar_token(user_id: WritableUser) -> CalendarTokenResponse:
+        """Enables the feed or replaces its token, the previously subscribed URL stops working"""
+        token = secrets.token_urlsafe(24)
+        await storage.save_calendar_token(user_id, token)
+        return calendar_token_response(token)
+
+    @app.delete("/calendar/token", response_class=PlainTextResponse)
+    async def revoke_calendar_token(user_id: WritableUser) -> Ok:
+        await storage.save_calendar_token(user_id, None)
+        return "OK"
+
+    @app.get("/calendar.ics")
+    async def get_calendar_feed(token: str) -> Response:
+        """
+        Upcoming events for calendar apps, which can't send auth headers and use the feed token
+        instead; descriptions are masked in privacy mode
+        """
+        user_id = await storage.load_calendar_token_owner(token)
+        if user_id is None:
+            raise HTTPException(status_code=401, detail="Invalid calendar token")
+        if await storage.is_user_disabled(user_id):
+            raise HTTPException(status_code=403, detail="Account is disabled")
+        settings = await storage.load_user_settings(user_id)
+        now = datetime.datetime.now(tz=datetime.UTC)
+        scheduled = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(status=TransactionStatus.SCHEDULED),
+            order=TransactionOrder.OLDEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        recent = await storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(min_timestamp=now - datetime.timedelta(days=365)),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        recurring = find_recurring(recent, service.plain_description, now)
+        if privacy is not None:
+            for suggestion in recurring:
+                suggestion.description = MASKED_DESCRIPTION
+        feed_events = [
+            *scheduled_events(
+                service.present_transactions(scheduled, False), settings.tzinfo, settings.locale
+            ),
+            *recurring_events(recurring, now, settings.tzinfo, settings.locale),
+            *allowance_events(
+                await storage.load_allowances(user_id), now, settings.tzinfo, settings.locale
+            ),
+            *month_end_events(now, settings.tzinfo),
+        ]
+        return Response(
+            content=render_calendar(feed_events, now), media_type="text/calendar; charset=utf-8"
+        )
+
+    @app.get("/me/export")
+    async def export_user_data(
+        user_id: AuthorizedUser, descriptions_visible: DescriptionsVisible, response: Response
+    ) -> UserDataExport:
+        export = await service.export_user_data(user_id, descriptions_visible)
+        filename = f"expenses-{export.exported_at.date().isoformat()}.json"
+        response.headers["Content-Disposition"] = f'attachment; filename="{filename}"'
+        return export
+
+    @app.delete("/me", response_class=PlainTextResponse)
+    async def delete_user_data(user_id: WritableUser, confirm: bool = False) -> Ok:
+        """Irreversibly wipes all of the user's data, export it first"""
+        if not confirm:
+            raise HTTPException(status_code=400, detail="Deleting all data requires confirm=true")
+        await storage.delete_user_data(user_id)
+        logger.info(f"Deleted all data of user {user_id}")
         return "OK"
 
+    @app.get("/audit")
+    async def get_audit_log(
+        user_id: AuthorizedUser,
+        entity_id: str | None = None,
+        offset: Offset = 0,
+        count: Count = 50,
+    ) -> list[AuditEntry]:
+        """Latest changes first, only recorded if the storage is wrapped with AuditedStorage"""
+        return await storage.load_audit_entries(user_id, entity_id, offset=offset, count=count)
+
+    rebuild_jobs: dict[str, RebuildJob] = {}
+
+    async def load_transactions_to_rebuild_from(
+        job: RebuildJob, user_id: UserId
+    ) -> list[StoredTransaction] | None:
+        """None if there are too many of them, which is noted in the job"""
+        transactions = await storage.load_transactions(
+            user_id,
+            filter=None,
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+        )
+        if len(transactions) >= MAX_TRANSACTIONS_TO_LOAD:
+            job.errors.append(f"User {user_id} has too many transactions, skipped")
+            return None
+        return transactions
+
+    async def rebuild_balances(job: RebuildJob) -> None:
+        user_ids = await storage.load_user_ids()
+        job.total = len(user_ids)
+        for user_id in user_ids:
+            transactions = await load_transactions_to_rebuild_from(job, user_id)
+            if transactions is None:
+                job.processed += 1
+                continue
+            for pool in await storage.load_pools(user_id):
+                balance, initial_balance = rebuild_pool_balance(
+                    pool, (t for t in transactions if t.pool_id == pool.id)
+                )
+                if balance == pool.balance and initial_balance == pool.initial_balance:
+                    continue
+                logger.info(
+                    f"Rebuilding pool {pool.id} balance: {[str(s) for s in pool.balance]} -> "
+                    + f"{[str(s) for s in balance]}"
+                )
+                await storage.save_pool_balance(user_id, pool.id, balance, initial_balance)
+                job.changed += 1
+            job.processed += 1
+
+    async def rebuild_net_worth_snapshots(job: RebuildJob) -> None:
+        """
+        Snapshots are rewritten if their balances differ from the ones as of the day's end,
+        converted at the historical rates of the day where they are stored
+        """
+        user_ids = await storage.load_user_ids()
+        job.total = len(user_ids)
+        for user_id in user_ids:
+            transactions = await load_transactions_to_rebuild_from(job, user_id)
+            if transactions is None:
+                job.processed += 1
+                continue
+            settings = await storage.load_user_settings(user_id)
+            pools = await storage.load_pools(user_id)
+            snapshots = await storage.load_net_worth_snapshots(
+                user_id, datetime.date.min, datetime.date.max
+            )
+            for snapshot in snapshots:
+                day_end = datetime.datetime.combine(
+                    snapshot.date + datetime.timedelta(days=1), datetime.time(), settings.tzinfo
+                )
+                pools_then: list[MoneyPool] = []
+                for pool in pools:
+                    balance = pool_balance_at(
+                        pool, [t for t in transactions if t.pool_id == pool.id], day_end
+                    )
+                    if balance is not None:
+                        pools_then.append(pool.model_copy(update={"balance": balance}))
+                rebuilt = await net_worth(
+                    pools_then,
+                    HistoricalExchangeRates(storage, exchange_rates, on=snapshot.date),
+                    target_currency=snapshot.total.currency,
+                    date=snapshot.date,
+                )
+                if rebuilt.balance == snapshot.balance:
+                    continue
+                logger.info(
+                    f"Rebuilding user {user_id} net worth on {snapshot.date}: "
+                    + f"{[str(s) for s in snapshot.balance]} -> "
+                    + f"{[str(s) for s in rebuilt.balance]}"
+                )
+                await storage.save_net_worth_snapshot(user_id, rebuilt)
+                job.changed += 1
+            job.processed += 1
+
+    async def run_rebuild(job: RebuildJob) -> None:
+        logger.info(f"Starting rebuild job {job.id} ({job.what.value})")
+        try:
+            if job.what is RebuildTarget.BALANCES:
+                await rebuild_balances(job)
+            elif job.what is RebuildTarget.SNAPSHOTS:
+                await rebuild_net_worth_snapshots(job)
+            elif job.what is RebuildTarget.INDEXES:
+                job.total = 1
+                await storage.rebuild_indexes()
+                job.processed = 1
+            job.status = RebuildStatus.DONE
+        except Exception as e:
+            logger.exception(f"Rebuild job {job.id} failed")
+            job.errors.append(str(e))
+            job.status = RebuildStatus.FAILED
+        job.finished_at = datetime.datetime.now(tz=datetime.UTC)
+        logger.info(f"Rebuild job {job.id} {job.status.value}, {job.changed} changed")
+
+    @app.post("/admin/rebuild", dependencies=[Depends(authorize_write)])
+    async def start_rebuild(
+        _: AdminUser, what: RebuildTarget, background_tasks: BackgroundTasks
+    ) -> RebuildJob:
+        """
+        Recomputes derived data from the source of truth, for all users; poll the returned job
+        for progress. Writes made while rebuilding balances may be lost, best run in maintenance
+        """
+        if any(j.status is RebuildStatus.RUNNING for j in rebuild_jobs.values()):
+            raise HTTPException(status_code=409, detail="Another rebuild is running")
+        job = RebuildJob(id=str(uuid.uuid4()), what=what)
+        rebuild_jobs[job.id] = job
+        background_tasks.add_task(run_rebuild, job)
+        return job
+
+    @app.get("/admin/rebuild/{job_id}")
+    async def get_rebuild_job(_: AdminUser, job_id: str) -> RebuildJob:
+        job = rebuild_jobs.get(job_id)
+        if job is None:
+            raise HTTPException(status_code=404, detail="Rebuild job not found")
+        return job
+
+    @app.get("/admin/users")
+    async def get_user_accounts(_: AdminUser) -> list[UserAccountView]:
+        """All users owning a pool and all disabled ones, with the amount of data they store"""
+        disabled_user_ids = set(await storage.load_disabled_user_ids())
+        user_ids = sorted(set(await storage.load_user_ids()) | disabled_user_ids)
+        return [
+            UserAccountView(
+                user_id=user_id,
+                is_admin=user_id in (admin_user_ids or []),
+                disabled=user_id in disabled_user_ids,
+                usage=await storage.load_storage_usage(user_id),
+            )
+            for user_id in user_ids
+        ]
+
+    @app.put("/admin/users/{user_id}", dependencies=[Depends(authorize_write)])
+    async def update_user_account(
+        admin_id: AdminUser, user_id: UserId, update: UserAccountUpdate
+    ) -> UserAccountView:
+        """Disabled users are refused on every route, their data is kept"""
+        if update.disabled is not None:
+            if update.disabled and user_id == admin_id:
+                raise HTTPException(status_code=400, detail="Can't disable own account")
+            await storage.set_user_disabled(user_id, update.disabled)
+        return UserAccountView(
+            user_id=user_id,
+            is_admin=user_id in (admin_user_ids or []),
+            disabled=await storage.is_user_disabled(user_id),
+            usage=await storage.load_storage_usage(user_id),
+        )
+
+    @app.get("/meta/examples")
+    async def get_request_examples() -> dict[str, Any]:
+        """Example request bodies, keyed by method and route path, same as in the OpenAPI schema"""
+        examples: dict[str, Any] = {}
+        for route in app.routes:
+            if not isinstance(route, APIRoute) or route.body_field is None:
+                continue
+            example = example_for(route.body_field.type_)
+            if example is None:
+                continue
+            for method in sorted(route.methods & {"POST", "PUT"}):
+                examples[f"{method} {route.path}"] = example
+        return examples
+
+    @app.get("/telemetry/preview")
+    async def preview_telemetry(_: AuthorizedUser) -> TelemetryReport:
+        """Exactly what would be sent on the next telemetry report, if it's enabled"""
+        if telemetry is None:
+            raise HTTPException(status_code=404, detail="Telemetry is not configured")
+        return telemetry_report(telemetry)
+
+    @app.post("/privacy/sensitive-view")
+    async def request_sensitive_view(
+        user_id: WritableUser, body: SensitiveViewRequestBody
+    ) -> SensitiveViewTokenResponse:
+        """
+        Only after proving the identity again: with a two-factor code if it's enabled, otherwise
+        with the start param of a new login
+        """
+        if privacy is None:
+            raise HTTPException(status_code=404, detail="Privacy mode is not enabled")
+        await auth.reauthenticate(user_id, code=body.code, start_param=body.start_param)
+        return SensitiveViewTokenResponse(
+            token=privacy.issue_sensitive_view_token(user_id),
+            expires_in_sec=privacy.sensitive_view_ttl_sec,
+        )
+
+    @app.post("/ws/tickets")
+    async def issue_live_ticket(user_id: AuthorizedUser) -> LiveTicketResponse:
+        return LiveTicketResponse(
+            ticket=live.issue_ticket(user_id), expires_in_sec=live.ticket_ttl_sec
+        )
+
+    def present_event(event: BaseEvent) -> BaseEvent:
+        if privacy is not None and isinstance(
+            event, (TransactionCreated, TransactionUpdated, TransactionDeleted)
+        ):
+            # no way to pass a sensitive view token, same as in lists without one
+            event = event.model_copy(deep=True)
+            privacy.present(event.transaction, visible=False)
+        return event
+
+    @app.websocket("/ws")
+    async def live_updates(websocket: WebSocket, ticket: str) -> None:
+        """Pushes the user's domain events as JSON messages, clients aren't expected to send any"""
+        user_id = live.redeem_ticket(ticket)
+        if user_id is None:
+            await websocket.close(code=1008, reason="Invalid or expired ticket")
+            return
+        await websocket.accept()
+        with live.connect(user_id) as queue:
+
+            async def forward_events() -> None:
+                while True:
+                    _, event = await queue.get()
+                    await websocket.send_text(present_event(event).model_dump_json())
+
+            forwarding = asyncio.create_task(forward_events())
+            try:
+                while True:
+                    await websocket.receive_text()
+            except WebSocketDisconnect:
+                pass
+            finally:
+                forwarding.cancel()
+
+    @app.get("/events", response_class=StreamingResponse)
+    async def stream_events(
+        user_id: AuthorizedUser, last_event_id: Annotated[str | None, Header()] = None
+    ) -> StreamingResponse:
+        """Same events as in /ws, as server-sent events; resumes after the Last-Event-ID"""
+        if last_event_id is not None and not last_event_id.isdigit():
+            raise HTTPException(status_code=400, detail="Invalid Last-Event-ID")
+        return StreamingResponse(
+            sse_stream(
+                live,
+                user_id,
+                last_event_id=int(last_event_id) if last_event_id is not None else None,
+                present=present_event,
+            ),
+            media_type="text/event-stream",
+            headers={"Cache-Control": "no-cache", "X-Accel-Buffering": "no"},
+        )
+
+    if static_dir is not None:
+        # mounted last so that API routes take precedence
+        app.mount(STATIC_MOUNT_PATH, SpaStaticFiles(directory=str(static_dir)), name="frontend")
+
     return app
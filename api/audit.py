@@ -0,0 +1,793 @@
+import datetime
+import logging
+
+import pydantic
+
+from api.logs import request_id_var
+from api.migrations import Migration
+from api.storage import Storage, TransactionOrder
+from api.types.allowance import Allowance, StoredAllowance
+from api.types.api import MoneyPoolAttributesUpdate, TransactionEdit, TransactionUpdate
+from api.types.audit import AuditEntry
+from api.types.balance_snapshot import BalanceSnapshot, StoredBalanceSnapshot
+from api.types.challenge import Challenge, StoredChallenge
+from api.types.debt import Debt, StoredDebt
+from api.types.goal import Goal, StoredGoal
+from api.types.historical_rates import DailyRates
+from api.types.ids import (
+    AllowanceId,
+    BalanceSnapshotId,
+    ChallengeId,
+    DebtId,
+    GoalId,
+    MoneyPoolId,
+    NoteId,
+    PayeeId,
+    ReconciliationId,
+    ReportSnapshotId,
+    RuleId,
+    TemplateId,
+    TransactionId,
+    UserId,
+)
+from api.types.money_pool import MoneyPool, StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.month_close import MonthClose
+from api.types.net_worth import NetWorthSnapshot
+from api.types.note import PoolNote, StoredPoolNote
+from api.types.operation import Operation, StoredOperation
+from api.types.payee import Payee, StoredPayee
+from api.types.reconciliation import Reconciliation, StoredReconciliation
+from api.types.redenomination import PoolRedenomination
+from api.types.report_snapshot import ReportSnapshot, StoredReportSnapshot
+from api.types.rule import CategorizationRule, StoredCategorizationRule
+from api.types.settings import UserSettings
+from api.types.suggestion import Suggestion, SuggestionField
+from api.types.sync import EntityChange
+from api.types.template import StoredTransactionTemplate, TransactionTemplate
+from api.types.transaction import (
+    StoredTransaction,
+    Transaction,
+    TransactionFilter,
+    TransactionStatus,
+)
+from api.types.two_factor import TwoFactor
+
+logger = logging.getLogger(__name__)
+
+
+class AuditedStorage(Storage):
+    """
+    Decorator recording every write made through the wrapped storage in its audit log,
+    with entity snapshots before and after the change
+    """
+
+    def __init__(self, inner: Storage) -> None:
+        self.inner = inner
+
+    async def _record(
+        self,
+        user_id: UserId | None,
+        action: str,
+        entity_type: str,
+        entity_id: str | None,
+        before: pydantic.BaseModel | None = None,
+        after: pydantic.BaseModel | None = None,
+    ) -> None:
+        entry = AuditEntry(
+            user_id=user_id,
+            action=action,
+            entity_type=entity_type,
+            entity_id=entity_id,
+            request_id=request_id_var.get(),
+            before=before.model_dump(mode="json") if before is not None else None,
+            after=after.model_dump(mode="json") if after is not None else None,
+        )
+        try:
+            await self.inner.append_audit_entry(entry)
+        except Exception:
+            # the write itself has already happened, failing the request won't undo it
+            logger.exception(f"Error recording audit entry: {entry}")
+
+    async def initialize(self) -> None:
+        await self.inner.initialize()
+
+    async def close(self) -> None:
+        await self.inner.close()
+
+    async def add_pool(self, user_id: UserId, new_pool: MoneyPool) -> StoredMoneyPool:
+        stored = await self.inner.add_pool(user_id, new_pool)
+        await self._record(user_id, "add_pool", "pool", stored.id, after=stored)
+        return stored
+
+    async def add_balance_to_pool(
+        self, user_id: UserId, pool_id: MoneyPoolId, new_balance: MoneySum
+    ) -> bool:
+        before = await self.inner.load_pool(user_id, pool_id)
+        result = await self.inner.add_balance_to_pool(user_id, pool_id, new_balance)
+        if result:
+            await self._record(
+                user_id,
+                "add_balance_to_pool",
+                "pool",
+                pool_id,
+                before=before,
+                after=await self.inner.load_pool(user_id, pool_id),
+            )
+        return result
+
+    async def set_pool_attributes(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        update: MoneyPoolAttributesUpdate,
+        expected_version: int | None = None,
+    ) -> bool:
+        before = await self.inner.load_pool(user_id, pool_id)
+        result = await self.inner.set_pool_attributes(user_id, pool_id, update, expected_version)
+        if result:
+            await self._record(
+                user_id,
+                "set_pool_attributes",
+                "pool",
+                pool_id,
+                before=before,
+                after=await self.inner.load_pool(user_id, pool_id),
+            )
+        return result
+
+    async def set_pools_order(self, user_id: UserId, pool_ids: list[MoneyPoolId]) -> None:
+        before = {p.id: p for p in await self.inner.load_pools(user_id)}
+        await self.inner.set_pools_order(user_id, pool_ids)
+        for after in await self.inner.load_pools(user_id):
+            pool_before = before.get(after.id)
+            if pool_before is not None and pool_before.sort_order != after.sort_order:
+                await self._record(
+                    user_id, "set_pools_order", "pool", after.id, before=pool_before, after=after
+                )
+
+    async def load_pools(self, user_id: UserId) -> list[StoredMoneyPool]:
+        return await self.inner.load_pools(user_id)
+
+    async def load_pool(self, user_id: UserId, pool_id: MoneyPoolId) -> StoredMoneyPool | None:
+        return await self.inner.load_pool(user_id, pool_id)
+
+    async def save_pool_balance(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        balance: list[MoneySum],
+        initial_balance: list[MoneySum] | None,
+    ) -> bool:
+        before = await self.inner.load_pool(user_id, pool_id)
+        result = await self.inner.save_pool_balance(user_id, pool_id, balance, initial_balance)
+        if result:
+            await self._record(
+                user_id,
+                "save_pool_balance",
+                "pool",
+                pool_id,
+                before=before,
+                after=await self.inner.load_pool(user_id, pool_id),
+            )
+        return result
+
+    async def redenominate_pool(self, user_id: UserId, redenomination: PoolRedenomination) -> bool:
+        before = await self.inner.load_pool(user_id, redenomination.pool_id)
+        transactions_before = [
+            await self.inner.load_transaction(user_id, c.transaction_id)
+            for c in redenomination.transactions
+        ]
+        result = await self.inner.redenominate_pool(user_id, redenomination)
+        if not result:
+            return result
+        # the conversion itself, with its rates
+        await self._record(
+            user_id,
+            "redenominate_pool",
+            "redenomination",
+            redenomination.pool_id,
+            after=redenomination,
+        )
+        await self._record(
+            user_id,
+            "redenominate_pool",
+            "pool",
+            redenomination.pool_id,
+            before=before,
+            after=await self.inner.load_pool(user_id, redenomination.pool_id),
+        )
+        for c, transaction_before in zip(redenomination.transactions, transactions_before):
+            await self._record(
+                user_id,
+                "redenominate_pool",
+                "transaction",
+                c.transaction_id,
+                before=transaction_before,
+                after=await self.inner.load_transaction(user_id, c.transaction_id),
+            )
+        return result
+
+    async def add_transaction(
+        self, user_id: UserId, transaction: Transaction
+    ) -> StoredTransaction:
+        stored = await self.inner.add_transaction(user_id, transaction)
+        await self._record(user_id, "add_transaction", "transaction", stored.id, after=stored)
+        return stored
+
+    async def add_transactions(
+        self, user_id: UserId, transactions: list[Transaction]
+    ) -> list[StoredTransaction]:
+        stored = await self.inner.add_transactions(user_id, transactions)
+        for t in stored:
+            await self._record(user_id, "add_transactions", "transaction", t.id, after=t)
+        return stored
+
+    async def apply_transaction_changes(
+        self, user_id: UserId, new: list[Transaction], edits: list[TransactionEdit]
+    ) -> list[StoredTransaction]:
+        before = {
+            e.transaction_id: await self.inner.load_transaction(user_id, e.transaction_id)
+            for e in edits
+        }
+        added = await self.inner.apply_transaction_changes(user_id, new, edits)
+        action = "apply_transaction_changes"
+        for t in added:
+            await self._record(user_id, action, "transaction", t.id, after=t)
+        for transaction_id, snapshot in before.items():
+            await self._record(
+                user_id,
+                action,
+                "transaction",
+                transaction_id,
+                before=snapshot,
+                after=await self.inner.load_transaction(user_id, transaction_id),
+            )
+        return added
+
+    async def restore_transactions(
+        self, user_id: UserId, transactions: list[StoredTransaction]
+    ) -> None:
+        await self.inner.restore_transactions(user_id, transactions)
+        for t in transactions:
+            await self._record(user_id, "restore_transactions", "transaction", t.id, after=t)
+
+    async def load_transactions(
+        self,
+        user_id: UserId,
+        filter: TransactionFilter | None,
+        order: TransactionOrder,
+        offset: int,
+        count: int,
+    ) -> list[StoredTransaction]:
+        return await self.inner.load_transactions(user_id, filter, order, offset, count)
+
+    async def load_transaction(
+        self, user_id: UserId, transaction_id: TransactionId
+    ) -> StoredTransaction | None:
+        return await self.inner.load_transaction(user_id, transaction_id)
+
+    async def delete_transaction(self, user_id: UserId, transaction_id: TransactionId) -> bool:
+        before = await self.inner.load_transaction(user_id, transaction_id)
+        result = await self.inner.delete_transaction(user_id, transaction_id)
+        if result:
+            await self._record(
+                user_id, "delete_transaction", "transaction", transaction_id, before=before
+            )
+        return result
+
+    async def update_transaction(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        update: TransactionUpdate,
+        expected_version: int | None = None,
+    ) -> bool:
+        before = await self.inner.load_transaction(user_id, transaction_id)
+        result = await self.inner.update_transaction(
+            user_id, transaction_id, update, expected_version
+        )
+        if result:
+            await self._record(
+                user_id,
+                "update_transaction",
+                "transaction",
+                transaction_id,
+                before=before,
+                after=await self.inner.load_transaction(user_id, transaction_id),
+            )
+        return result
+
+    async def load_change_counter(self, user_id: UserId) -> int:
+        return await self.inner.load_change_counter(user_id)
+
+    async def load_changes(self, user_id: UserId, since: int) -> list[EntityChange]:
+        return await self.inner.load_changes(user_id, since)
+
+    async def update_transaction_status(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        status: TransactionStatus,
+        expected_version: int | None = None,
+    ) -> bool:
+        before = await self.inner.load_transaction(user_id, transaction_id)
+        result = await self.inner.update_transaction_status(
+            user_id, transaction_id, status, expected_version
+        )
+        if result:
+            await self._record(
+                user_id,
+                "update_transaction_status",
+                "transaction",
+                transaction_id,
+                before=before,
+                after=await self.inner.load_transaction(user_id, transaction_id),
+            )
+        return result
+
+    async def load_due_scheduled_transactions(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredTransaction]]:
+        return await self.inner.load_due_scheduled_transactions(now)
+
+    async def load_suggestions(
+        self,
+        user_id: UserId,
+        field: SuggestionField,
+        query: str,
+        since: datetime.datetime,
+        count: int,
+    ) -> list[Suggestion]:
+        return await self.inner.load_suggestions(user_id, field, query, since, count)
+
+    async def add_reconciliation(
+        self, user_id: UserId, reconciliation: Reconciliation
+    ) -> StoredReconciliation:
+        stored = await self.inner.add_reconciliation(user_id, reconciliation)
+        await self._record(
+            user_id, "add_reconciliation", "reconciliation", stored.id, after=stored
+        )
+        return stored
+
+    async def load_reconciliations(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredReconciliation]:
+        return await self.inner.load_reconciliations(user_id, pool_id)
+
+    async def load_reconciliation(
+        self, user_id: UserId, reconciliation_id: ReconciliationId
+    ) -> StoredReconciliation | None:
+        return await self.inner.load_reconciliation(user_id, reconciliation_id)
+
+    async def save_reconciliation(
+        self, user_id: UserId, reconciliation: StoredReconciliation
+    ) -> bool:
+        before = await self.inner.load_reconciliation(user_id, reconciliation.id)
+        result = await self.inner.save_reconciliation(user_id, reconciliation)
+        if result:
+            await self._record(
+                user_id,
+                "save_reconciliation",
+                "reconciliation",
+                reconciliation.id,
+                before=before,
+                after=reconciliation,
+            )
+        return result
+
+    async def add_report_snapshot(
+        self, user_id: UserId, snapshot: ReportSnapshot
+    ) -> StoredReportSnapshot:
+        stored = await self.inner.add_report_snapshot(user_id, snapshot)
+        await self._record(
+            user_id, "add_report_snapshot", "report_snapshot", stored.id, after=stored
+        )
+        return stored
+
+    async def load_report_snapshots(self, user_id: UserId) -> list[StoredReportSnapshot]:
+        return await self.inner.load_report_snapshots(user_id)
+
+    async def load_report_snapshot(
+        self, user_id: UserId, snapshot_id: ReportSnapshotId
+    ) -> StoredReportSnapshot | None:
+        return await self.inner.load_report_snapshot(user_id, snapshot_id)
+
+    async def add_allowance(self, user_id: UserId, allowance: Allowance) -> StoredAllowance:
+        stored = await self.inner.add_allowance(user_id, allowance)
+        await self._record(user_id, "add_allowance", "allowance", stored.id, after=stored)
+        return stored
+
+    async def load_allowances(self, user_id: UserId) -> list[StoredAllowance]:
+        return await self.inner.load_allowances(user_id)
+
+    async def load_allowance(
+        self, user_id: UserId, allowance_id: AllowanceId
+    ) -> StoredAllowance | None:
+        return await self.inner.load_allowance(user_id, allowance_id)
+
+    async def save_allowance(self, user_id: UserId, allowance: StoredAllowance) -> bool:
+        before = await self.inner.load_allowance(user_id, allowance.id)
+        result = await self.inner.save_allowance(user_id, allowance)
+        if result:
+            await self._record(
+                user_id,
+                "save_allowance",
+                "allowance",
+                allowance.id,
+                before=before,
+                after=allowance,
+            )
+        return result
+
+    async def load_allowance_by_view_token(
+        self, view_token: str
+    ) -> tuple[UserId, StoredAllowance] | None:
+        return await self.inner.load_allowance_by_view_token(view_token)
+
+    async def load_due_allowances(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredAllowance]]:
+        return await self.inner.load_due_allowances(now)
+
+    async def add_challenge(self, user_id: UserId, challenge: Challenge) -> StoredChallenge:
+        stored = await self.inner.add_challenge(user_id, challenge)
+        await self._record(user_id, "add_challenge", "challenge", stored.id, after=stored)
+        return stored
+
+    async def load_challenges(self, user_id: UserId) -> list[StoredChallenge]:
+        return await self.inner.load_challenges(user_id)
+
+    async def load_challenge(
+        self, user_id: UserId, challenge_id: ChallengeId
+    ) -> StoredChallenge | None:
+        return await self.inner.load_challenge(user_id, challenge_id)
+
+    async def save_challenge(self, user_id: UserId, challenge: StoredChallenge) -> bool:
+        before = await self.inner.load_challenge(user_id, challenge.id)
+        result = await self.inner.save_challenge(user_id, challenge)
+        if result:
+            await self._record(
+                user_id,
+                "save_challenge",
+                "challenge",
+                challenge.id,
+                before=before,
+                after=challenge,
+            )
+        return result
+
+    async def add_goal(self, user_id: UserId, goal: Goal) -> StoredGoal:
+        stored = await self.inner.add_goal(user_id, goal)
+        await self._record(user_id, "add_goal", "goal", stored.id, after=stored)
+        return stored
+
+    async def load_goals(self, user_id: UserId) -> list[StoredGoal]:
+        return await self.inner.load_goals(user_id)
+
+    async def load_goal(self, user_id: UserId, goal_id: GoalId) -> StoredGoal | None:
+        return await self.inner.load_goal(user_id, goal_id)
+
+    async def save_goal(self, user_id: UserId, goal: StoredGoal) -> bool:
+        before = await self.inner.load_goal(user_id, goal.id)
+        result = await self.inner.save_goal(user_id, goal)
+        if result:
+            await self._record(user_id, "save_goal", "goal", goal.id, before=before, after=goal)
+        return result
+
+    async def delete_goal(self, user_id: UserId, goal_id: GoalId) -> bool:
+        before = await self.inner.load_goal(user_id, goal_id)
+        result = await self.inner.delete_goal(user_id, goal_id)
+        if result:
+            await self._record(user_id, "delete_goal", "goal", goal_id, before=before)
+        return result
+
+    async def add_debt(self, user_id: UserId, debt: Debt) -> StoredDebt:
+        stored = await self.inner.add_debt(user_id, debt)
+        await self._record(user_id, "add_debt", "debt", stored.id, after=stored)
+        return stored
+
+    async def load_debts(self, user_id: UserId) -> list[StoredDebt]:
+        return await self.inner.load_debts(user_id)
+
+    async def load_debt(self, user_id: UserId, debt_id: DebtId) -> StoredDebt | None:
+        return await self.inner.load_debt(user_id, debt_id)
+
+    async def save_debt(self, user_id: UserId, debt: StoredDebt) -> bool:
+        before = await self.inner.load_debt(user_id, debt.id)
+        result = await self.inner.save_debt(user_id, debt)
+        if result:
+            await self._record(user_id, "save_debt", "debt", debt.id, before=before, after=debt)
+        return result
+
+    async def add_transaction_template(
+        self, user_id: UserId, template: TransactionTemplate
+    ) -> StoredTransactionTemplate:
+        stored = await self.inner.add_transaction_template(user_id, template)
+        await self._record(
+            user_id, "add_transaction_template", "template", stored.id, after=stored
+        )
+        return stored
+
+    async def load_transaction_templates(
+        self, user_id: UserId
+    ) -> list[StoredTransactionTemplate]:
+        return await self.inner.load_transaction_templates(user_id)
+
+    async def load_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> StoredTransactionTemplate | None:
+        return await self.inner.load_transaction_template(user_id, template_id)
+
+    async def save_transaction_template(
+        self, user_id: UserId, template: StoredTransactionTemplate
+    ) -> bool:
+        before = await self.inner.load_transaction_template(user_id, template.id)
+        result = await self.inner.save_transaction_template(user_id, template)
+        if result:
+            await self._record(
+                user_id,
+                "save_transaction_template",
+                "template",
+                template.id,
+                before=before,
+                after=template,
+            )
+        return result
+
+    async def delete_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> bool:
+        before = await self.inner.load_transaction_template(user_id, template_id)
+        result = await self.inner.delete_transaction_template(user_id, template_id)
+        if result:
+            await self._record(
+                user_id, "delete_transaction_template", "template", template_id, before=before
+            )
+        return result
+
+    async def add_rule(
+        self, user_id: UserId, rule: CategorizationRule
+    ) -> StoredCategorizationRule:
+        stored = await self.inner.add_rule(user_id, rule)
+        await self._record(user_id, "add_rule", "rule", stored.id, after=stored)
+        return stored
+
+    async def load_rules(self, user_id: UserId) -> list[StoredCategorizationRule]:
+        return await self.inner.load_rules(user_id)
+
+    async def load_rule(
+        self, user_id: UserId, rule_id: RuleId
+    ) -> StoredCategorizationRule | None:
+        return await self.inner.load_rule(user_id, rule_id)
+
+    async def save_rule(self, user_id: UserId, rule: StoredCategorizationRule) -> bool:
+        before = await self.inner.load_rule(user_id, rule.id)
+        result = await self.inner.save_rule(user_id, rule)
+        if result:
+            await self._record(user_id, "save_rule", "rule", rule.id, before=before, after=rule)
+        return result
+
+    async def delete_rule(self, user_id: UserId, rule_id: RuleId) -> bool:
+        before = await self.inner.load_rule(user_id, rule_id)
+        result = await self.inner.delete_rule(user_id, rule_id)
+        if result:
+            await self._record(user_id, "delete_rule", "rule", rule_id, before=before)
+        return result
+
+    async def add_payee(self, user_id: UserId, payee: Payee) -> StoredPayee:
+        stored = await self.inner.add_payee(user_id, payee)
+        await self._record(user_id, "add_payee", "payee", stored.id, after=stored)
+        return stored
+
+    async def load_payees(self, user_id: UserId) -> list[StoredPayee]:
+        return await self.inner.load_payees(user_id)
+
+    async def load_payee(self, user_id: UserId, payee_id: PayeeId) -> StoredPayee | None:
+        return await self.inner.load_payee(user_id, payee_id)
+
+    async def save_payee(self, user_id: UserId, payee: StoredPayee) -> bool:
+        before = await self.inner.load_payee(user_id, payee.id)
+        result = await self.inner.save_payee(user_id, payee)
+        if result:
+            await self._record(
+                user_id, "save_payee", "payee", payee.id, before=before, after=payee
+            )
+        return result
+
+    async def delete_payee(self, user_id: UserId, payee_id: PayeeId) -> bool:
+        before = await self.inner.load_payee(user_id, payee_id)
+        result = await self.inner.delete_payee(user_id, payee_id)
+        if result:
+            await self._record(user_id, "delete_payee", "payee", payee_id, before=before)
+        return result
+
+    async def add_pool_note(self, user_id: UserId, note: PoolNote) -> StoredPoolNote:
+        stored = await self.inner.add_pool_note(user_id, note)
+        await self._record(user_id, "add_pool_note", "note", stored.id, after=stored)
+        return stored
+
+    async def load_pool_notes(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredPoolNote]:
+        return await self.inner.load_pool_notes(user_id, pool_id)
+
+    async def load_pool_note(self, user_id: UserId, note_id: NoteId) -> StoredPoolNote | None:
+        return await self.inner.load_pool_note(user_id, note_id)
+
+    async def save_pool_note(self, user_id: UserId, note: StoredPoolNote) -> bool:
+        before = await self.inner.load_pool_note(user_id, note.id)
+        result = await self.inner.save_pool_note(user_id, note)
+        if result:
+            await self._record(
+                user_id, "save_pool_note", "note", note.id, before=before, after=note
+            )
+        return result
+
+    async def delete_pool_note(self, user_id: UserId, note_id: NoteId) -> bool:
+        before = await self.inner.load_pool_note(user_id, note_id)
+        result = await self.inner.delete_pool_note(user_id, note_id)
+        if result:
+            await self._record(user_id, "delete_pool_note", "note", note_id, before=before)
+        return result
+
+    async def add_balance_snapshot(
+        self, user_id: UserId, snapshot: BalanceSnapshot
+    ) -> StoredBalanceSnapshot:
+        stored = await self.inner.add_balance_snapshot(user_id, snapshot)
+        await self._record(
+            user_id, "add_balance_snapshot", "balance_snapshot", stored.id, after=stored
+        )
+        return stored
+
+    async def load_balance_snapshots(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredBalanceSnapshot]:
+        return await self.inner.load_balance_snapshots(user_id, pool_id)
+
+    async def load_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> StoredBalanceSnapshot | None:
+        return await self.inner.load_balance_snapshot(user_id, snapshot_id)
+
+    async def delete_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> bool:
+        before = await self.inner.load_balance_snapshot(user_id, snapshot_id)
+        result = await self.inner.delete_balance_snapshot(user_id, snapshot_id)
+        if result:
+            await self._record(
+                user_id, "delete_balance_snapshot", "balance_snapshot", snapshot_id, before=before
+            )
+        return result
+
+    async def load_user_settings(self, user_id: UserId) -> UserSettings:
+        return await self.inner.load_user_settings(user_id)
+
+    async def save_user_settings(self, user_id: UserId, settings: UserSettings) -> None:
+        before = await self.inner.load_user_settings(user_id)
+        await self.inner.save_user_settings(user_id, settings)
+        await self._record(
+            user_id, "save_user_settings", "settings", user_id, before=before, after=settings
+        )
+
+    async def load_month_closes(self, user_id: UserId) -> list[MonthClose]:
+        return await self.inner.load_month_closes(user_id)
+
+    async def load_month_close(self, user_id: UserId, year: int, month: int) -> MonthClose | None:
+        return await self.inner.load_month_close(user_id, year, month)
+
+    async def save_month_close(self, user_id: UserId, month_close: MonthClose) -> None:
+        before = await self.inner.load_month_close(user_id, month_close.year, month_close.month)
+        await self.inner.save_month_close(user_id, month_close)
+        await self._record(
+            user_id,
+            "save_month_close",
+            "month_close",
+            month_close.key,
+            before=before,
+            after=month_close,
+        )
+
+    # snapshots are derived from the audited balances, recording them would only add noise
+
+    async def save_net_worth_snapshot(self, user_id: UserId, snapshot: NetWorthSnapshot) -> None:
+        await self.inner.save_net_worth_snapshot(user_id, snapshot)
+
+    async def load_net_worth_snapshots(
+        self, user_id: UserId, start: datetime.date, end: datetime.date
+    ) -> list[NetWorthSnapshot]:
+        return await self.inner.load_net_worth_snapshots(user_id, start, end)
+
+    async def save_calendar_token(self, user_id: UserId, token: str | None) -> None:
+        await self.inner.save_calendar_token(user_id, token)
+        # the token itself is a credential and is kept out of the log
+        action = "revoke_calendar_token" if token is None else "issue_calendar_token"
+        await self._record(user_id, action, "calendar_token", user_id)
+
+    async def load_calendar_token(self, user_id: UserId) -> str | None:
+        return await self.inner.load_calendar_token(user_id)
+
+    async def load_calendar_token_owner(self, token: str) -> UserId | None:
+        return await self.inner.load_calendar_token_owner(token)
+
+    async def save_two_factor(self, user_id: UserId, two_factor: TwoFactor | None) -> None:
+        before = await self.inner.load_two_factor(user_id)
+        await self.inner.save_two_factor(user_id, two_factor)
+        # without the secrets; logins only update the used codes and aren't recorded
+        was_enabled = before is not None and before.confirmed
+        is_enabled = two_factor is not None and two_factor.confirmed
+        if was_enabled != is_enabled:
+            action = "enable_two_factor" if is_enabled else "disable_two_factor"
+            await self._record(user_id, action, "two_factor", user_id)
+
+    async def load_two_factor(self, user_id: UserId) -> TwoFactor | None:
+        return await self.inner.load_two_factor(user_id)
+
+    # the undo log is bookkeeping, the changes made on undo are audited as regular writes
+
+    async def log_operation(
+        self, user_id: UserId, operation: Operation, retention: datetime.timedelta
+    ) -> StoredOperation:
+        return await self.inner.log_operation(user_id, operation, retention)
+
+    async def pop_last_operation(
+        self, user_id: UserId, since: datetime.datetime
+    ) -> StoredOperation | None:
+        return await self.inner.pop_last_operation(user_id, since)
+
+    async def append_audit_entry(self, entry: AuditEntry) -> None:
+        await self.inner.append_audit_entry(entry)
+
+    async def load_audit_entries(
+        self, user_id: UserId, entity_id: str | None, offset: int, count: int
+    ) -> list[AuditEntry]:
+        return await self.inner.load_audit_entries(user_id, entity_id, offset, count)
+
+    async def save_historical_rates(self, days: list[DailyRates]) -> None:
+        await self.inner.save_historical_rates(days)
+        if days:
+            # full ECB history is thousands of days, so a single entry per import, no snapshots
+            dates = sorted(day.date for day in days)
+            await self._record(
+                None,
+                "save_historical_rates",
+                "historical_rates",
+                f"{dates[0].isoformat()}/{dates[-1].isoformat()}",
+            )
+
+    async def load_historical_rates(self, on: datetime.date) -> DailyRates | None:
+        return await self.inner.load_historical_rates(on)
+
+    async def load_historical_rates_between(
+        self, start: datetime.date, end: datetime.date
+    ) -> list[DailyRates]:
+        return await self.inner.load_historical_rates_between(start, end)
+
+    async def delete_user_data(self, user_id: UserId) -> None:
+        await self.inner.delete_user_data(user_id)
+        # the user's own audit log is wiped, this entry isn't attributed to them so it survives
+        await self._record(None, "delete_user_data", "user", user_id)
+
+    async def load_user_ids(self) -> list[UserId]:
+        return await self.inner.load_user_ids()
+
+    async def load_storage_usage(self, user_id: UserId) -> dict[str, int]:
+        return await self.inner.load_storage_usage(user_id)
+
+    async def load_disabled_user_ids(self) -> list[UserId]:
+        return await self.inner.load_disabled_user_ids()
+
+    async def is_user_disabled(self, user_id: UserId) -> bool:
+        return await self.inner.is_user_disabled(user_id)
+
+    async def set_user_disabled(self, user_id: UserId, disabled: bool) -> None:
+        await self.inner.set_user_disabled(user_id, disabled)
+        # not attributed to the user, like data deletion
+        await self._record(None, "disable_user" if disabled else "enable_user", "user", user_id)
+
+    async def rebuild_indexes(self) -> None:
+        await self.inner.rebuild_indexes()
+
+    async def migrate(self, dry_run: bool) -> list[Migration]:
+        return await self.inner.migrate(dry_run)
@@ -1,9 +1,12 @@
 import abc
 import asyncio
 import base64
+import dataclasses
+import datetime
 import logging
 import secrets
-from hashlib import md5
+import time
+from hashlib import md5, sha256
 from typing import Annotated, MutableMapping
 
 import fastapi
@@ -14,12 +17,22 @@ from cryptography.hazmat.primitives.asymmetric import padding
 from cryptography.hazmat.primitives.asymmetric.rsa import RSAPublicKey
 from cryptography.hazmat.primitives.serialization import load_pem_public_key
 from fastapi import Header, HTTPException
-from fastapi.responses import PlainTextResponse
+from fastapi.responses import PlainTextResponse, RedirectResponse
 from telebot import AsyncTeleBot
 from telebot import types as tg
 
-from api.types.api import LoginLinkResponse
+from api import totp
+from api.oidc import OidcConfig, OidcError, pkce_pair
+from api.storage import Storage
+from api.types.api import (
+    ApiTokenResponse,
+    AuthSession,
+    LoginLinkResponse,
+    TokenScope,
+    TwoFactorSetupResponse,
+)
 from api.types.ids import UserId
+from api.types.two_factor import TwoFactor
 
 logger = logging.getLogger(__name__)
 
@@ -28,9 +41,21 @@ class Auth(abc.ABC):
     @abc.abstractmethod
     async def authorize_request(self, *args, **kwargs) -> UserId: ...
 
+    async def token_scope(self) -> TokenScope:
+        """Scope of the credentials used for the request; full access unless overridden"""
+        return TokenScope.READ_WRITE
+
     async def initialize(self) -> None:
         pass
 
+    async def reauthenticate(
+        self, user_id: UserId, code: str | None, start_param: str | None
+    ) -> None:
+        """
+        Fresh proof of identity before sensitive actions, raises HTTPException without it; auth
+        without interactive logins has nothing fresher than the credentials of the request itself
+        """
+
     def setup_login_routes(self, app: fastapi.FastAPI) -> None:
         pass
 
@@ -85,9 +110,48 @@ class RSAAuth(Auth):
         raise HTTPException(403, detail="Invalid signature")
 
 
+RECOVERY_CODES_COUNT = 10
+
+
+def hash_recovery_code(code: str) -> str:
+    return sha256(code.encode("utf-8")).hexdigest()
+
+
+@dataclasses.dataclass(frozen=True)
+class OidcLogin:
+    provider: str
+    start_param: str
+    code_verifier: str
+
+
+def check_two_factor(two_factor: TwoFactor, code: str) -> bool:
+    """Consumes recovery codes and TOTP steps, the state must be saved afterwards"""
+    step = totp.matching_step(two_factor.secret, code, time.time())
+    if step is not None and (
+        two_factor.last_used_step is None or step > two_factor.last_used_step
+    ):
+        two_factor.last_used_step = step
+        return True
+    code_hash = hash_recovery_code(code)
+    if code_hash in two_factor.recovery_code_hashes:
+        two_factor.recovery_code_hashes.remove(code_hash)
+        return True
+    return False
+
+
 class TokenAuth(Auth):
-    def __init__(self, server_tokens: list[str], auth_telegram_bot_token: str) -> None:
+    def __init__(
+        self,
+        server_tokens: list[str],
+        auth_telegram_bot_token: str,
+        storage: Storage,  # for two-factor authentication, which must survive restarts
+        read_only_server_tokens: list[str] | None = None,
+        oidc: OidcConfig | None = None,
+    ) -> None:
         self.server_tokens = server_tokens
+        self.storage = storage
+        self.read_only_server_tokens = read_only_server_tokens or []
+        self.oidc = oidc
         self.bot = AsyncTeleBot(token=auth_telegram_bot_token)
         self._bot_user: tg.User | None = None
         # NOTE: inmemory storage for simplicity, doesn't support horizontal scaling
@@ -95,6 +159,12 @@ class TokenAuth(Auth):
             maxsize=4096, ttl=5 * 60
         )
         self._user_id_future_by_access_token: MutableMapping[str, asyncio.Future[str]] = dict()
+        # issued through the API, as opposed to access tokens obtained by logging in
+        self._user_id_by_api_token: dict[str, UserId] = dict()
+        self._session_by_token: dict[str, AuthSession] = dict()
+        self._oidc_login_by_state: MutableMapping[str, OidcLogin] = TTLCache(
+            maxsize=4096, ttl=5 * 60
+        )
 
     @property
     def bot_user(self) -> tg.User:
@@ -111,6 +181,74 @@ class TokenAuth(Auth):
         except asyncio.TimeoutError:
             return None
 
+    def _token_user_id(self, token: str) -> UserId | None:
+        """Without waiting for pending logins"""
+        if user_id := self._user_id_by_api_token.get(token):
+            return user_id
+        fut = self._user_id_future_by_access_token.get(token)
+        if fut is not None and fut.done() and not fut.cancelled():
+            return fut.result()
+        return None
+
+    def _start_session(self, token: str, scope: TokenScope, device_name: str | None) -> None:
+        self._session_by_token[token] = AuthSession(
+            id=secrets.token_urlsafe(nbytes=8),
+            device_name=device_name,
+            scope=scope,
+            created_at=datetime.datetime.now(tz=datetime.UTC),
+        )
+
+    def _start_login(self, device_name: str | None) -> str:
+        """Start param, resolved to the user id by the bot or an identity provider"""
+        start_param = secrets.token_urlsafe(nbytes=16)
+        access_token = secrets.token_urlsafe(nbytes=64)
+        self._access_token_by_bot_start_param[start_param] = access_token
+        self._user_id_future_by_access_token[access_token] = asyncio.Future()
+        self._start_session(access_token, TokenScope.READ_WRITE, device_name)
+        return start_param
+
+    def _finish_login(self, start_param: str, user_id: UserId) -> bool:
+        access_token = self._access_token_by_bot_start_param.get(start_param)
+        if access_token is None:
+            return False
+        user_id_fut = self._user_id_future_by_access_token.get(access_token)
+        if user_id_fut is None or user_id_fut.done():
+            return False
+        user_id_fut.set_result(user_id)
+        return True
+
+    async def _load_two_factor(self, user_id: UserId) -> TwoFactor | None:
+        """Fails closed: without the state it's unknown whether a code is required"""
+        try:
+            return await self.storage.load_two_factor(user_id)
+        except Exception:
+            logger.exception(f"Failed to load two-factor authentication of user {user_id!r}")
+            raise HTTPException(503, detail="Two-factor authentication is unavailable")
+
+    async def reauthenticate(
+        self, user_id: UserId, code: str | None, start_param: str | None
+    ) -> None:
+        """With a two-factor code if it's enabled, otherwise with a new login through the bot"""
+        two_factor = await self._load_two_factor(user_id)
+        if two_factor is not None and two_factor.confirmed:
+            if code is None:
+                raise HTTPException(401, detail="Two-factor code required")
+            if not check_two_factor(two_factor, code):
+                raise HTTPException(403, detail="Invalid two-factor code")
+            await self.storage.save_two_factor(user_id, two_factor)
+            return
+        if start_param is None:
+            raise HTTPException(401, detail="New login required")
+        # start params expire in minutes, popped so that one login confirms one action
+        access_token = self._access_token_by_bot_start_param.pop(start_param, None)
+        if access_token is None or self._token_user_id(access_token) != user_id:
+            raise HTTPException(403, detail="Expired or unfinished login")
+
+    def _revoke(self, token: str) -> None:
+        self._session_by_token.pop(token, None)
+        self._user_id_by_api_token.pop(token, None)
+        self._user_id_future_by_access_token.pop(token, None)
+
     async def initialize(self) -> None:
         self._bot_user = await self.bot.get_me()
         logger.info(f"Initialized bot user: {self.bot_user}")
@@ -120,31 +258,85 @@ class TokenAuth(Auth):
             message_text_parts = message.text_content.split()
             if len(message_text_parts) <= 1:
                 return
-            access_token = self._access_token_by_bot_start_param.get(message_text_parts[1])
-            if access_token is None:
-                return
-            user_id_fut = self._user_id_future_by_access_token.get(access_token)
-            if user_id_fut is None or user_id_fut.done():
-                return
-            user_id_fut.set_result(md5(str(message.from_user.id).encode("utf-8")).hexdigest())
-            await self.bot.reply_to(message, text="OK")
+            user_id = md5(str(message.from_user.id).encode("utf-8")).hexdigest()
+            if self._finish_login(message_text_parts[1], user_id):
+                await self.bot.reply_to(message, text="OK")
 
         asyncio.create_task(self.bot.infinity_polling())
+        if self.oidc is not None:
+            for provider in self.oidc.providers:
+                await provider.discover()
+
+    def _setup_oidc_routes(self, app: fastapi.FastAPI, oidc: OidcConfig) -> None:
+        """
+        Login through an identity provider instead of the bot; the callback redirects to
+        the frontend with the start param to get the access token with, as after the bot login
+        """
+
+        @app.get("/auth/oidc/login")
+        async def login_with_identity_provider(
+            provider: str,
+            device_name: str | None = None,
+            user_agent: Annotated[str | None, Header()] = None,
+        ) -> RedirectResponse:
+            oidc_provider = oidc.provider(provider)
+            if oidc_provider is None:
+                raise HTTPException(404, detail="Unknown identity provider")
+            state = secrets.token_urlsafe(nbytes=16)
+            code_verifier, code_challenge = pkce_pair()
+            self._oidc_login_by_state[state] = OidcLogin(
+                provider=provider,
+                start_param=self._start_login(device_name or user_agent),
+                code_verifier=code_verifier,
+            )
+            try:
+                url = oidc_provider.authorization_url(oidc.redirect_uri, state, code_challenge)
+            except OidcError as e:
+                raise HTTPException(503, detail=str(e))
+            return RedirectResponse(url)
+
+        @app.get("/auth/oidc/callback")
+        async def finish_identity_provider_login(
+            state: str, code: str | None = None, error: str | None = None
+        ) -> RedirectResponse:
+            login = self._oidc_login_by_state.pop(state, None)
+            if login is None:
+                raise HTTPException(404, detail="Expired or non-existent login state")
+            if code is None:
+                raise HTTPException(400, detail=f"Identity provider refused login: {error}")
+            oidc_provider = oidc.provider(login.provider)
+            if oidc_provider is None:
+                raise HTTPException(404, detail="Unknown identity provider")
+            try:
+                subject = await oidc_provider.fetch_subject(
+                    code, oidc.redirect_uri, login.code_verifier
+                )
+            except OidcError as e:
+                logger.warning(f"Failed to log in with {login.provider}: {e}")
+                raise HTTPException(502, detail="Identity provider login failed")
+            if not self._finish_login(login.start_param, oidc_provider.local_user_id(subject)):
+                raise HTTPException(404, detail="Login has expired")
+            return RedirectResponse(f"{oidc.post_login_url}?start_param={login.start_param}")
 
     def setup_login_routes(self, app: fastapi.FastAPI) -> None:
+        AuthorizedUser = Annotated[UserId, fastapi.Depends(self.authorize_request)]
+        CurrentScope = Annotated[TokenScope, fastapi.Depends(self.token_scope)]
+
         @app.get("/auth/login-link")
-        async def request_login_link() -> LoginLinkResponse:
-            start_param = secrets.token_urlsafe(nbytes=16)
-            access_token = secrets.token_urlsafe(nbytes=64)
-            self._access_token_by_bot_start_param[start_param] = access_token
-            self._user_id_future_by_access_token[access_token] = asyncio.Future()
+        async def request_login_link(
+            device_name: str | None = None, user_agent: Annotated[str | None, Header()] = None
+        ) -> LoginLinkResponse:
+            start_param = self._start_login(device_name or user_agent)
             return LoginLinkResponse(
                 url=f"https://t.me/{self.bot_user.username}?start={start_param}",
                 start_param=start_param,
             )
 
         @app.get("/auth/access-token", response_class=PlainTextResponse)
-        async def get_access_token_after_login_to_bot(start_param: str) -> str:
+        async def get_access_token_after_login_to_bot(
+            start_param: str, code: str | None = None
+        ) -> str:
+            """Users with two-factor authentication must pass a TOTP or recovery code"""
             access_token = self._access_token_by_bot_start_param.get(start_param)
             if access_token is None:
                 raise HTTPException(404, "Expired or non-existent bot start param")
@@ -154,18 +346,132 @@ class TokenAuth(Auth):
                 raise HTTPException(
                     202, detail=f"User has not logged in through bot after {timeout} sec"
                 )
-            else:
-                return access_token
+            two_factor = await self._load_two_factor(user_id)
+            if two_factor is not None and two_factor.confirmed:
+                if code is None:
+                    raise HTTPException(401, detail="Two-factor code required")
+                if not check_two_factor(two_factor, code):
+                    raise HTTPException(403, detail="Invalid two-factor code")
+                await self.storage.save_two_factor(user_id, two_factor)
+            return access_token
+
+        if self.oidc is not None:
+            self._setup_oidc_routes(app, self.oidc)
+
+        @app.post("/auth/tokens")
+        async def issue_api_token(
+            user_id: AuthorizedUser,
+            current_scope: CurrentScope,
+            scope: TokenScope = TokenScope.READ,
+            device_name: str | None = None,
+        ) -> ApiTokenResponse:
+            """Additional token for the user, e.g. a read-only one for a dashboard"""
+            if current_scope is TokenScope.READ and scope is not TokenScope.READ:
+                raise HTTPException(403, detail="Read-only token can't issue read-write tokens")
+            api_token = secrets.token_urlsafe(nbytes=64)
+            self._user_id_by_api_token[api_token] = user_id
+            self._start_session(api_token, scope, device_name)
+            return ApiTokenResponse(token=api_token, scope=scope)
+
+        @app.post("/auth/2fa/setup")
+        async def setup_two_factor(
+            user_id: AuthorizedUser, current_scope: CurrentScope
+        ) -> TwoFactorSetupResponse:
+            """Enabled once confirmed with the first code"""
+            if current_scope is TokenScope.READ:
+                raise HTTPException(403, detail="Token is read-only")
+            existing = await self._load_two_factor(user_id)
+            if existing is not None and existing.confirmed:
+                raise HTTPException(409, detail="Two-factor authentication is already enabled")
+            secret = totp.generate_secret()
+            recovery_codes = [secrets.token_hex(5) for _ in range(RECOVERY_CODES_COUNT)]
+            two_factor = TwoFactor(
+                secret=secret,
+                recovery_code_hashes=[hash_recovery_code(c) for c in recovery_codes],
+            )
+            await self.storage.save_two_factor(user_id, two_factor)
+            return TwoFactorSetupResponse(
+                secret=secret,
+                otpauth_uri=totp.provisioning_uri(
+                    secret, account=user_id, issuer="tiny-expense-tracker"
+                ),
+                recovery_codes=recovery_codes,
+            )
+
+        @app.post("/auth/2fa/confirm", response_class=PlainTextResponse)
+        async def confirm_two_factor(
+            user_id: AuthorizedUser, current_scope: CurrentScope, code: str
+        ) -> str:
+            if current_scope is TokenScope.READ:
+                raise HTTPException(403, detail="Token is read-only")
+            two_factor = await self._load_two_factor(user_id)
+            if two_factor is None:
+                raise HTTPException(404, detail="Two-factor authentication is not set up")
+            if not check_two_factor(two_factor, code):
+                raise HTTPException(400, detail="Invalid two-factor code")
+            two_factor.confirmed = True
+            await self.storage.save_two_factor(user_id, two_factor)
+            return "OK"
+
+        @app.delete("/auth/2fa", response_class=PlainTextResponse)
+        async def disable_two_factor(
+            user_id: AuthorizedUser, current_scope: CurrentScope, code: str
+        ) -> str:
+            if current_scope is TokenScope.READ:
+                raise HTTPException(403, detail="Token is read-only")
+            two_factor = await self._load_two_factor(user_id)
+            if two_factor is None:
+                raise HTTPException(404, detail="Two-factor authentication is not set up")
+            if not check_two_factor(two_factor, code):
+                raise HTTPException(400, detail="Invalid two-factor code")
+            await self.storage.save_two_factor(user_id, None)
+            return "OK"
+
+        @app.get("/auth/sessions")
+        async def list_sessions(
+            user_id: AuthorizedUser, token: Annotated[str, Header()]
+        ) -> list[AuthSession]:
+            """Most recently used first; server tokens are not listed"""
+            sessions = [
+                session.model_copy(update={"current": session_token == token})
+                for session_token, session in self._session_by_token.items()
+                if self._token_user_id(session_token) == user_id
+            ]
+            sessions.sort(key=lambda s: (s.last_used_at or s.created_at).timestamp(), reverse=True)
+            return sessions
+
+        @app.delete("/auth/sessions/{session_id}", response_class=PlainTextResponse)
+        async def revoke_session(
+            user_id: AuthorizedUser, current_scope: CurrentScope, session_id: str
+        ) -> str:
+            if current_scope is TokenScope.READ:
+                raise HTTPException(403, detail="Token is read-only")
+            for session_token, session in self._session_by_token.items():
+                if session.id == session_id and self._token_user_id(session_token) == user_id:
+                    self._revoke(session_token)
+                    return "OK"
+            raise HTTPException(404, detail="Session not found")
 
     async def authorize_request(
         self, token: Annotated[str, Header()], user_id: Annotated[str | None, Header()] = None
     ) -> UserId:
-        if any(token == server_token for server_token in self.server_tokens):
+        server_tokens = self.server_tokens + self.read_only_server_tokens
+        if any(token == server_token for server_token in server_tokens):
             if user_id is None:
                 raise HTTPException(400, detail="Valid server token supplied, but no user id")
             logger.info(f"Authorized through server token: {user_id!r}")
             return user_id
-        elif user_id := await self._get_user_id(token, timeout_sec=None):
-            return user_id
-        else:
+        user_id = self._user_id_by_api_token.get(token) or await self._get_user_id(
+            token, timeout_sec=None
+        )
+        if user_id is None:
             raise HTTPException(403, detail="Invalid token")
+        if (session := self._session_by_token.get(token)) is not None:
+            session.last_used_at = datetime.datetime.now(tz=datetime.UTC)
+        return user_id
+
+    async def token_scope(self, token: Annotated[str, Header()]) -> TokenScope:
+        if any(token == server_token for server_token in self.read_only_server_tokens):
+            return TokenScope.READ
+        session = self._session_by_token.get(token)
+        return session.scope if session is not None else TokenScope.READ_WRITE
@@ -0,0 +1,163 @@
+"""
+iCalendar (RFC 5545) feed of the user's upcoming money events for calendar apps to subscribe to:
+scheduled transactions, expected recurring ones, allowance payments and month ends to close
+"""
+
+import dataclasses
+import datetime
+from typing import Iterable, Sequence
+
+from api.formatting import format_money
+from api.types.allowance import ALLOWANCE_PERIOD, StoredAllowance
+from api.types.api import RecurringSuggestion
+from api.types.transaction import StoredTransaction
+
+CALENDAR_HORIZON = datetime.timedelta(days=90)
+
+PRODUCT_ID = "-//tiny-expense-tracker//calendar feed//EN"
+UID_DOMAIN = "tiny-expense-tracker"
+MAX_LINE_OCTETS = 75
+
+
+@dataclasses.dataclass(frozen=True)
+class CalendarEvent:
+    uid: str  # stable across feed refreshes, so that calendar apps update events in place
+    date: datetime.date  # events are all-day, in the user's timezone
+    summary: str
+    description: str = ""
+
+
+def scheduled_events(
+    transactions: Iterable[StoredTransaction], tz: datetime.tzinfo, locale: str
+) -> list[CalendarEvent]:
+    return [
+        CalendarEvent(
+            uid=f"scheduled-{t.id}",
+            date=t.timestamp.astimezone(tz).date(),
+            summary=f"{t.description}: {format_money(t.sum, locale)}",
+            description="Scheduled transaction",
+        )
+        for t in transactions
+    ]
+
+
+def recurring_events(
+    suggestions: Iterable[RecurringSuggestion],
+    now: datetime.datetime,
+    tz: datetime.tzinfo,
+    locale: str,
+) -> list[CalendarEvent]:
+    """Expected occurrences within the horizon, the overdue next one included"""
+    events: list[CalendarEvent] = []
+    for s in suggestions:
+        interval = datetime.timedelta(days=s.interval_days)
+        at = s.next_expected_at
+        while at < now + CALENDAR_HORIZON:
+            date = at.astimezone(tz).date()
+            events.append(
+                CalendarEvent(
+                    # the latest occurrence identifies the series until the next one happens
+                    uid=f"recurring-{s.transaction_ids[0]}-{date:%Y%m%d}",
+                    date=date,
+                    summary=f"{s.description}: {format_money(s.sum, locale)}",
+                    description=(
+                        f"Expected every {s.interval_days} days, seen {s.occurrences} times"
+                    ),
+                )
+            )
+            at += interval
+    return events
+
+
+def allowance_events(
+    allowances: Iterable[StoredAllowance], now: datetime.datetime, tz: datetime.tzinfo, locale: str
+) -> list[CalendarEvent]:
+    events: list[CalendarEvent] = []
+    for a in allowances:
+        at = a.next_payment_at
+        amount = format_money(a.weekly_amount, locale)
+        while at < now + CALENDAR_HORIZON:
+            date = at.astimezone(tz).date()
+            events.append(
+                CalendarEvent(
+                    uid=f"allowance-{a.id}-{date:%Y%m%d}",
+                    date=date,
+                    summary=f"Allowance for {a.child_name}: {amount}",
+                )
+            )
+            at += ALLOWANCE_PERIOD
+    return events
+
+
+def month_end_events(now: datetime.datetime, tz: datetime.tzinfo) -> list[CalendarEvent]:
+    """On the first day of each month, when the previous one can be closed"""
+    events: list[CalendarEvent] = []
+    local_now = now.astimezone(tz)
+    year, month = local_now.year, local_now.month
+    horizon_end = (now + CALENDAR_HORIZON).astimezone(tz).date()
+    while True:
+        closed_year, closed_month = year, month
+        year, month = (year + 1, 1) if month == 12 else (year, month + 1)
+        first_day = datetime.date(year, month, 1)
+        if first_day >= horizon_end:
+            return events
+        events.append(
+            CalendarEvent(
+                uid=f"month-close-{closed_year}-{closed_month:02}",
+                date=first_day,
+                summary=f"Close {datetime.date(closed_year, closed_month, 1):%B %Y}",
+                description="Review the month's transactions and complete its close checklist",
+            )
+        )
+
+
+def escape_text(value: str) -> str:
+    return (
+        value.replace("\\", "\\\\")
+        .replace(";", "\\;")
+        .replace(",", "\\,")
+        .replace("\r\n", "\\n")
+        .replace("\n", "\\n")
+    )
+
+
+def fold(line: str) -> list[str]:
+    """Long content lines are split at 75 octets, continuations starting with a space"""
+    folded: list[str] = []
+    current = ""
+    for char in line:
+        limit = MAX_LINE_OCTETS if not folded else MAX_LINE_OCTETS - 1
+        if len((current + char).encode("utf-8")) > limit:
+            folded.append(current)
+            current = ""
+        current += char
+    folded.append(current)
+    return [folded[0]] + [" " + f for f in folded[1:]]
+
+
+def render_calendar(events: Sequence[CalendarEvent], now: datetime.datetime) -> str:
+    stamp = now.astimezone(datetime.UTC).strftime("%Y%m%dT%H%M%SZ")
+    lines = [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        f"PRODID:{PRODUCT_ID}",
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+        "X-WR-CALNAME:Expenses",
+    ]
+    for event in sorted(events, key=lambda e: (e.date, e.uid)):
+        lines.extend(
+            [
+                "BEGIN:VEVENT",
+                f"UID:{event.uid}@{UID_DOMAIN}",
+                f"DTSTAMP:{stamp}",
+                f"DTSTART;VALUE=DATE:{event.date:%Y%m%d}",
+                f"DTEND;VALUE=DATE:{event.date + datetime.timedelta(days=1):%Y%m%d}",
+                f"SUMMARY:{escape_text(event.summary)}",
+            ]
+        )
+        if event.description:
+            lines.append(f"DESCRIPTION:{escape_text(event.description)}")
+        lines.extend(["TRANSP:TRANSPARENT", "END:VEVENT"])
+    lines.append("END:VCALENDAR")
+    return "".join(folded + "\r\n" for line in lines for folded in fold(line))
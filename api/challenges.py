@@ -0,0 +1,96 @@
+"""
+Savings challenges, a gamification layer: progress is derived from the tracked transactions, so
+challenges need no manual check-ins
+"""
+
+import datetime
+import math
+from decimal import Decimal
+from typing import Sequence
+
+from api.types.api import ChallengeProgress
+from api.types.challenge import Challenge, ChallengeKind, StoredChallenge
+from api.types.transaction import Transaction
+
+FIFTY_TWO_WEEKS = 52
+
+
+def is_spending(t: Transaction) -> bool:
+    return t.sum.amount < 0 and t.transfer_id is None
+
+
+def no_spend_days_progress(
+    challenge: Challenge,
+    transactions: Sequence[Transaction],
+    now: datetime.datetime,
+    tz: datetime.tzinfo,
+) -> tuple[float, float]:
+    """Days are calendar days in the timezone"""
+    assert challenge.target_days is not None
+    end = min(challenge.end, now, key=lambda dt: dt.timestamp())
+    spending_dates = {t.timestamp.astimezone(tz).date() for t in transactions if is_spending(t)}
+    start_date = challenge.start.astimezone(tz).date()
+    end_date = end.astimezone(tz).date()
+    days_elapsed = (end_date - start_date).days + 1
+    no_spend_days = sum(
+        1
+        for offset in range(max(days_elapsed, 0))
+        if start_date + datetime.timedelta(days=offset) not in spending_dates
+    )
+    return no_spend_days, challenge.target_days
+
+
+def fifty_two_weeks_progress(
+    challenge: Challenge, transactions: Sequence[Transaction]
+) -> tuple[float, float]:
+    assert challenge.weekly_step is not None
+    step = challenge.weekly_step
+    saved = sum(
+        (
+            t.sum.amount
+            for t in transactions
+            if t.pool_id == challenge.pool_id
+            and t.sum.currency == step.currency
+            and t.sum.amount > 0
+        ),
+        start=Decimal(0),
+    )
+    total_weeks = FIFTY_TWO_WEEKS * (FIFTY_TWO_WEEKS + 1) // 2
+    return float(saved), float(step.amount * total_weeks)
+
+
+def round_up_sprint_progress(
+    challenge: Challenge, transactions: Sequence[Transaction]
+) -> tuple[float, float]:
+    assert challenge.round_to is not None and challenge.target is not None
+    round_to = challenge.round_to
+    collected = Decimal(0)
+    for t in transactions:
+        if not is_spending(t) or t.sum.currency != challenge.target.currency:
+            continue
+        spent = -t.sum.amount
+        collected += math.ceil(spent / round_to) * round_to - spent
+    return float(collected), float(challenge.target.amount)
+
+
+def compute_progress(
+    challenge: StoredChallenge,
+    transactions: Sequence[Transaction],
+    now: datetime.datetime,
+    tz: datetime.tzinfo = datetime.UTC,
+) -> ChallengeProgress:
+    """Transactions are expected to be filtered to the challenge period"""
+    match challenge.kind:
+        case ChallengeKind.NO_SPEND_DAYS:
+            current, target = no_spend_days_progress(challenge, transactions, now, tz)
+        case ChallengeKind.FIFTY_TWO_WEEKS:
+            current, target = fifty_two_weeks_progress(challenge, transactions)
+        case ChallengeKind.ROUND_UP_SPRINT:
+            current, target = round_up_sprint_progress(challenge, transactions)
+    return ChallengeProgress(
+        challenge_id=challenge.id,
+        current=current,
+        target=target,
+        fraction=min(current / target, 1.0) if target else 1.0,
+        completed=current >= target,
+    )
@@ -0,0 +1,169 @@
+"""
+Client for the HTTP API, for scripts, bots and tests talking to a running server; it only needs
+the request and response types from api.types, not the server dependencies
+
+    async with TetClient("https://tet.example.com", headers={"Authorization": "Bearer ..."}) as c:
+        pool = await c.create_pool(CreatePoolRequestBody(display_name="cash", balance=[...]))
+"""
+
+import datetime
+from typing import Any, Mapping, TypeVar
+
+import httpx
+import pydantic
+
+from api.types.allowance import PendingSpend
+from api.types.api import (
+    CreatedTransactionResponse,
+    CreatePoolRequestBody,
+    MoneyPoolAttributesUpdate,
+    PoolOrderRequestBody,
+    ReportApiRouteResponse,
+    TransactionUpdate,
+    TransferMoneyRequestBody,
+)
+from api.types.ids import MoneyPoolId, TransactionId
+from api.types.money_pool import StoredMoneyPool
+from api.types.settings import UserSettings
+from api.types.transaction import StoredTransaction, Transaction, TransactionOrder
+
+ResponseT = TypeVar("ResponseT")
+
+
+class ApiError(Exception):
+    def __init__(self, status_code: int, detail: Any) -> None:
+        self.status_code = status_code
+        self.detail = detail
+        super().__init__(f"{status_code}: {detail}")
+
+
+class TetClient:
+    def __init__(
+        self,
+        base_url: str,
+        headers: Mapping[str, str] | None = None,
+        transport: httpx.AsyncBaseTransport | None = None,  # e.g. httpx.ASGITransport in tests
+    ) -> None:
+        self.http = httpx.AsyncClient(base_url=base_url, headers=headers, transport=transport)
+
+    async def __aenter__(self) -> "TetClient":
+        return self
+
+    async def __aexit__(self, *exc_info: object) -> None:
+        await self.close()
+
+    async def close(self) -> None:
+        await self.http.aclose()
+
+    async def _request(
+        self,
+        method: str,
+        path: str,
+        body: pydantic.BaseModel | None = None,
+        params: Mapping[str, Any] | None = None,
+        expected_version: int | None = None,
+    ) -> httpx.Response:
+        headers: dict[str, str] = {}
+        if body is not None:
+            headers["Content-Type"] = "application/json"
+        if expected_version is not None:
+            headers["If-Match"] = str(expected_version)
+        response = await self.http.request(
+            method,
+            path,
+            # unset fields are left to the server defaults
+            content=body.model_dump_json(exclude_unset=True) if body is not None else None,
+            headers=headers,
+            params={k: v for k, v in (params or {}).items() if v is not None},
+        )
+        if response.is_error:
+            try:
+                detail = response.json().get("detail")
+            except ValueError:
+                detail = response.text
+            raise ApiError(response.status_code, detail)
+        return response
+
+    def _parse(self, response_type: type[ResponseT], response: httpx.Response) -> ResponseT:
+        return pydantic.TypeAdapter(response_type).validate_json(response.content)
+
+    async def get_pools(self) -> list[StoredMoneyPool]:
+        return self._parse(list[StoredMoneyPool], await self._request("GET", "/pools"))
+
+    async def get_pool(self, pool_id: MoneyPoolId) -> StoredMoneyPool:
+        return self._parse(StoredMoneyPool, await self._request("GET", f"/pools/{pool_id}"))
+
+    async def create_pool(self, body: CreatePoolRequestBody) -> StoredMoneyPool:
+        return self._parse(StoredMoneyPool, await self._request("POST", "/pools", body))
+
+    async def update_pool(
+        self,
+        pool_id: MoneyPoolId,
+        update: MoneyPoolAttributesUpdate,
+        expected_version: int,
+    ) -> None:
+        await self._request("PUT", f"/pools/{pool_id}", update, expected_version=expected_version)
+
+    async def set_pools_order(self, pool_ids: list[MoneyPoolId]) -> None:
+        await self._request("PUT", "/pools/order", PoolOrderRequestBody(pool_ids=pool_ids))
+
+    async def add_transaction(
+        self, transaction: Transaction, force: bool = False
+    ) -> CreatedTransactionResponse | PendingSpend:
+        """Pending spend if the transaction needs an allowance approval"""
+        params = {"force": force}
+        response = await self._request("POST", "/transactions", transaction, params=params)
+        if response.status_code == 202:
+            return self._parse(PendingSpend, response)
+        return self._parse(CreatedTransactionResponse, response)
+
+    async def get_transactions(
+        self,
+        offset: int = 0,
+        count: int = 10,
+        order: TransactionOrder = TransactionOrder.LATEST,
+    ) -> list[StoredTransaction]:
+        response = await self._request(
+            "GET",
+            "/transactions",
+            params={"offset": offset, "count": count, "order": order.value},
+        )
+        return self._parse(list[StoredTransaction], response)
+
+    async def update_transaction(
+        self,
+        transaction_id: TransactionId,
+        update: TransactionUpdate,
+        expected_version: int,
+    ) -> None:
+        await self._request(
+            "PUT", f"/transactions/{transaction_id}", update, expected_version=expected_version
+        )
+
+    async def delete_transaction(self, transaction_id: TransactionId) -> None:
+        await self._request("DELETE", f"/transactions/{transaction_id}")
+
+    async def transfer(self, body: TransferMoneyRequestBody) -> None:
+        await self._request("POST", "/transfer", body)
+
+    async def get_report(
+        self,
+        start: datetime.datetime,
+        end: datetime.datetime | None = None,
+        points: int | None = None,
+        target_currency: str | None = None,
+    ) -> ReportApiRouteResponse:
+        params = {
+            "start": start.isoformat(),
+            "end": end.isoformat() if end is not None else None,
+            "points": points,
+            "target_currency": target_currency,
+        }
+        response = await self._request("GET", "/report", params=params)
+        return self._parse(ReportApiRouteResponse, response)
+
+    async def get_settings(self) -> UserSettings:
+        return self._parse(UserSettings, await self._request("GET", "/settings"))
+
+    async def save_settings(self, settings: UserSettings) -> None:
+        await self._request("PUT", "/settings", settings)
@@ -0,0 +1,205 @@
+"""
+Response compression for the clients that accept it, configured with COMPRESSION_ALGORITHMS in
+the order of preference (e.g. "zstd,br,gzip") and COMPRESSION_MIN_SIZE in bytes; streamed
+responses (e.g. exports) are compressed chunk by chunk
+"""
+
+import dataclasses
+import enum
+import zlib
+from typing import Mapping, Protocol
+
+import brotli
+import zstandard
+from starlette.datastructures import Headers, MutableHeaders
+from starlette.types import ASGIApp, Message, Receive, Scope, Send
+
+DEFAULT_MIN_SIZE = 1024
+
+UNCOMPRESSED_CONTENT_TYPES = ("text/event-stream",)  # live updates must not be buffered
+
+
+class CompressionAlgorithm(enum.StrEnum):
+    GZIP = "gzip"
+    BROTLI = "br"
+    ZSTD = "zstd"
+
+
+@dataclasses.dataclass(frozen=True)
+class CompressionConfig:
+    algorithms: list[CompressionAlgorithm]  # most preferred first
+    min_size: int = DEFAULT_MIN_SIZE  # smaller complete responses are sent as is
+
+
+def compression_config_from_env(env: Mapping[str, str]) -> CompressionConfig | None:
+    if "COMPRESSION_ALGORITHMS" not in env:
+        return None
+    return CompressionConfig(
+        algorithms=[
+            CompressionAlgorithm(a.strip()) for a in env["COMPRESSION_ALGORITHMS"].split(",")
+        ],
+        min_size=int(env.get("COMPRESSION_MIN_SIZE", DEFAULT_MIN_SIZE)),
+    )
+
+
+def negotiate(
+    accept_encoding: str, algorithms: list[CompressionAlgorithm]
+) -> CompressionAlgorithm | None:
+    """The most preferred of the algorithms the client accepts, None to send as is"""
+    accepted: dict[str, float] = {}
+    for item in accept_encoding.split(","):
+        coding, *params = [part.strip() for part in item.split(";")]
+        if not coding:
+            continue
+        quality = 1.0
+        for param in params:
+            name, _, value = param.partition("=")
+            if name.strip() == "q":
+                try:
+                    quality = float(value)
+                except ValueError:
+                    quality = 0.0
+        accepted[coding.lower()] = quality
+    for algorithm in algorithms:
+        if accepted.get(algorithm.value, accepted.get("*", 0.0)) > 0:
+            return algorithm
+    return None
+
+
+class Compressor(Protocol):
+    def compress(self, data: bytes) -> bytes: ...
+
+    def flush(self) -> bytes:
+        """Everything compressed so far, for the client to decompress the chunk it's got"""
+
+    def finish(self) -> bytes: ...
+
+
+class GzipCompressor:
+    def __init__(self) -> None:
+        self.obj = zlib.compressobj(wbits=16 + zlib.MAX_WBITS)
+
+    def compress(self, data: bytes) -> bytes:
+        return self.obj.compress(data)
+
+    def flush(self) -> bytes:
+        return self.obj.flush(zlib.Z_SYNC_FLUSH)
+
+    def finish(self) -> bytes:
+        return self.obj.flush()
+
+
+class BrotliCompressor:
+    def __init__(self) -> None:
+        self.obj = brotli.Compressor()
+
+    def compress(self, data: bytes) -> bytes:
+        return self.obj.process(data)
+
+    def flush(self) -> bytes:
+        return self.obj.flush()
+
+    def finish(self) -> bytes:
+        return self.obj.finish()
+
+
+class ZstdCompressor:
+    def __init__(self) -> None:
+        self.obj = zstandard.ZstdCompressor().compressobj()
+
+    def compress(self, data: bytes) -> bytes:
+        return self.obj.compress(data)
+
+    def flush(self) -> bytes:
+        return self.obj.flush(zstandard.COMPRESSOBJ_FLUSH_BLOCK)
+
+    def finish(self) -> bytes:
+        return self.obj.flush()
+
+
+def new_compressor(algorithm: CompressionAlgorithm) -> Compressor:
+    match algorithm:
+        case CompressionAlgorithm.GZIP:
+            return GzipCompressor()
+        case CompressionAlgorithm.BROTLI:
+            return BrotliCompressor()
+        case CompressionAlgorithm.ZSTD:
+            return ZstdCompressor()
+
+
+class CompressionMiddleware:
+    def __init__(self, app: ASGIApp, config: CompressionConfig) -> None:
+        self.app = app
+        self.config = config
+
+    async def __call__(self, scope: Scope, receive: Receive, send: Send) -> None:
+        if scope["type"] != "http":
+            await self.app(scope, receive, send)
+            return
+        algorithm = negotiate(
+            Headers(scope=scope).get("accept-encoding", ""), self.config.algorithms
+        )
+        if algorithm is None:
+            await self.app(scope, receive, send)
+            return
+        await self.app(scope, receive, CompressingSender(send, algorithm, self.config.min_size))
+
+
+class CompressingSender:
+    """Holds the response start until the first body chunk shows if it's worth compressing"""
+
+    def __init__(self, send: Send, algorithm: CompressionAlgorithm, min_size: int) -> None:
+        self.send = send
+        self.algorithm = algorithm
+        self.min_size = min_size
+        self.start: Message | None = None
+        self.compressor: Compressor | None = None
+        self.passthrough = False
+
+    async def __call__(self, message: Message) -> None:
+        if message["type"] == "http.response.start":
+            headers = Headers(raw=message["headers"])
+            content_type = headers.get("content-type", "")
+            self.passthrough = (
+                message["status"] in (204, 304)
+                or "content-encoding" in headers
+                or content_type.startswith(UNCOMPRESSED_CONTENT_TYPES)
+            )
+            if self.passthrough:
+                await self.send(message)
+            else:
+                self.start = message
+            return
+        if message["type"] != "http.response.body" or self.passthrough:
+            await self.send(message)
+            return
+
+        body: bytes = message.get("body", b"")
+        more_body: bool = message.get("more_body", False)
+        if self.start is not None:
+            start, self.start = self.start, None
+            if not more_body and len(body) < self.min_size:
+                self.passthrough = True
+                await self.send(start)
+                await self.send(message)
+                return
+            self.compressor = new_compressor(self.algorithm)
+            headers = MutableHeaders(raw=start["headers"])
+            headers["Content-Encoding"] = self.algorithm.value
+            headers.add_vary_header("Accept-Encoding")
+            if more_body:
+                del headers["Content-Length"]
+            else:
+                body = self.compressor.compress(body) + self.compressor.finish()
+                headers["Content-Length"] = str(len(body))
+                await self.send(start)
+                await self.send({"type": "http.response.body", "body": body})
+                return
+            await self.send(start)
+
+        assert self.compressor is not None
+        if more_body:
+            body = self.compressor.compress(body) + self.compressor.flush()
+        else:
+            body = self.compressor.compress(body) + self.compressor.finish()
+        await self.send({"type": "http.response.body", "body": body, "more_body": more_body})
@@ -0,0 +1,15 @@
+"""
+Non-ISO currencies, registered on startup if enabled; exponents are the commonly used smallest
+units (satoshi for BTC, wei for ETH)
+"""
+
+from api.types.currency_iso4217 import CurrencyISO4217
+
+CRYPTO_CURRENCIES = [
+    CurrencyISO4217(code="BTC", numeric_code=0, name="Bitcoin", entities=[], precision=8),
+    CurrencyISO4217(code="ETH", numeric_code=0, name="Ether", entities=[], precision=18),
+    CurrencyISO4217(code="LTC", numeric_code=0, name="Litecoin", entities=[], precision=8),
+    CurrencyISO4217(code="SOL", numeric_code=0, name="Solana", entities=[], precision=9),
+    CurrencyISO4217(code="USDT", numeric_code=0, name="Tether", entities=[], precision=6),
+    CurrencyISO4217(code="USDC", numeric_code=0, name="USD Coin", entities=[], precision=6),
+]
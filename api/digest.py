@@ -0,0 +1,119 @@
+"""
+Periodic summary of the user's finances, built once and rendered for any delivery channel (API,
+email, messengers)
+"""
+
+import datetime
+from typing import Sequence
+
+from api.exchange_rates import ExchangeRates
+from api.reports import spending_by_category, sum_transactions
+from api.types.currency import Currency
+from api.types.digest import Digest, DigestPeriod
+from api.types.settings import ReportingPeriod, Weekday
+from api.types.transaction import Transaction
+
+TOP_CATEGORIES_COUNT = 3
+
+PERIOD_DURATION = {
+    DigestPeriod.DAY: datetime.timedelta(days=1),
+    DigestPeriod.WEEK: datetime.timedelta(weeks=1),
+}
+
+# reporting periods end on different days for different users, so monthly digests are due daily
+SENDING_INTERVAL = {**PERIOD_DURATION, DigestPeriod.MONTH: PERIOD_DURATION[DigestPeriod.DAY]}
+
+TITLES = {DigestPeriod.DAY: "Daily", DigestPeriod.WEEK: "Weekly", DigestPeriod.MONTH: "Monthly"}
+
+
+def digest_end(
+    period: DigestPeriod,
+    now: datetime.datetime,
+    week_start: Weekday,
+    tz: datetime.tzinfo = datetime.UTC,
+    reporting_period: ReportingPeriod | None = None,  # calendar months by default
+) -> datetime.datetime:
+    """
+    Weekly digests cover the last complete week, starting at midnight in the timezone on the
+    user's week start day, monthly ones the last complete reporting period
+    """
+    if period is DigestPeriod.MONTH:
+        return (reporting_period or ReportingPeriod()).containing(now, tz)[0]
+    if period is not DigestPeriod.WEEK:
+        return now
+    local = now.astimezone(tz)
+    midnight = local.replace(hour=0, minute=0, second=0, microsecond=0)
+    return midnight - datetime.timedelta(days=(local.weekday() - week_start.number) % 7)
+
+
+def digest_start(
+    period: DigestPeriod,
+    end: datetime.datetime,
+    tz: datetime.tzinfo = datetime.UTC,
+    reporting_period: ReportingPeriod | None = None,
+) -> datetime.datetime:
+    if period is DigestPeriod.MONTH:
+        day_before = end - datetime.timedelta(days=1)
+        return (reporting_period or ReportingPeriod()).containing(day_before, tz)[0]
+    return end - PERIOD_DURATION[period]
+
+
+async def build_digest(
+    transactions: Sequence[Transaction],
+    exchange_rates: ExchangeRates,
+    period: DigestPeriod,
+    end: datetime.datetime,
+    target_currency: Currency,
+    start: datetime.datetime | None = None,  # fixed duration before the end for days and weeks
+) -> Digest:
+    """Transactions must cover the period and the previous one, for category comparison"""
+    if start is None:
+        start = digest_start(period, end)
+    category_report = await spending_by_category(
+        transactions,
+        exchange_rates=exchange_rates,
+        start=start,
+        end=end,
+        target_currency=target_currency,
+    )
+    in_period = [
+        t
+        for t in transactions
+        if start.timestamp() <= t.timestamp.timestamp() < end.timestamp()
+        and t.transfer_id is None
+        and t.status.is_counted
+    ]
+    return Digest(
+        period=period,
+        start=start,
+        end=end,
+        income=await sum_transactions(
+            (t for t in in_period if t.sum.amount > 0), exchange_rates, target_currency
+        ),
+        expenses=category_report.spent,
+        transaction_count=len(in_period),
+        top_categories=category_report.categories[:TOP_CATEGORIES_COUNT],
+    )
+
+
+def digest_title(digest: Digest) -> str:
+    return f"{TITLES[digest.period]} digest, {digest.start:%Y-%m-%d} - {digest.end:%Y-%m-%d}"
+
+
+def render_digest_text(digest: Digest) -> str:
+    lines = [
+        digest_title(digest),
+        "",
+        f"Income: {digest.income}",
+        f"Expenses: {digest.expenses}",
+        f"Transactions: {digest.transaction_count}",
+    ]
+    if digest.top_categories:
+        lines.append("")
+        lines.append("Top categories:")
+        for c in digest.top_categories:
+            line = f"  {c.category or 'untagged'}: {c.spent} ({c.fraction:.0%})"
+            if c.change is not None:
+                line += f", {c.change:+.0%} vs previous {digest.period.value}"
+            lines.append(line)
+    return "\n".join(lines)
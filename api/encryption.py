@@ -0,0 +1,623 @@
+"""
+Encryption at rest of free text fields (transaction descriptions and source records, template
+and debt descriptions, pool notes) and two-factor secrets with per-user keys derived from a server
+master key; applied by a storage decorator, so it works the same for every backend
+"""
+
+import base64
+import datetime
+import logging
+from typing import Any, Callable, TypeVar
+
+import pydantic
+from cryptography.fernet import Fernet, InvalidToken
+from cryptography.hazmat.primitives import hashes
+from cryptography.hazmat.primitives.kdf.hkdf import HKDF
+
+from api.migrations import Migration
+from api.privacy import MASKED_DESCRIPTION
+from api.storage import (
+    MAX_SUGGESTION_TRANSACTIONS,
+    Storage,
+    TransactionOrder,
+    count_suggestions,
+)
+from api.types.allowance import Allowance, StoredAllowance
+from api.types.api import MoneyPoolAttributesUpdate, TransactionEdit, TransactionUpdate
+from api.types.audit import AuditEntry
+from api.types.balance_snapshot import BalanceSnapshot, StoredBalanceSnapshot
+from api.types.challenge import Challenge, StoredChallenge
+from api.types.debt import Debt, StoredDebt
+from api.types.goal import Goal, StoredGoal
+from api.types.historical_rates import DailyRates
+from api.types.ids import (
+    AllowanceId,
+    BalanceSnapshotId,
+    ChallengeId,
+    DebtId,
+    GoalId,
+    MoneyPoolId,
+    NoteId,
+    PayeeId,
+    ReconciliationId,
+    ReportSnapshotId,
+    RuleId,
+    TemplateId,
+    TransactionId,
+    UserId,
+)
+from api.types.money_pool import MoneyPool, StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.month_close import MonthClose
+from api.types.net_worth import NetWorthSnapshot
+from api.types.note import PoolNote, StoredPoolNote
+from api.types.operation import Operation, StoredOperation
+from api.types.payee import Payee, StoredPayee
+from api.types.reconciliation import Reconciliation, StoredReconciliation
+from api.types.redenomination import PoolRedenomination
+from api.types.report_snapshot import ReportSnapshot, StoredReportSnapshot
+from api.types.rule import CategorizationRule, StoredCategorizationRule
+from api.types.sensitive import AT_REST_PREFIX
+from api.types.settings import UserSettings
+from api.types.suggestion import Suggestion, SuggestionField
+from api.types.sync import EntityChange
+from api.types.template import StoredTransactionTemplate, TransactionTemplate
+from api.types.transaction import (
+    StoredTransaction,
+    Transaction,
+    TransactionFilter,
+    TransactionStatus,
+)
+from api.types.two_factor import TwoFactor
+
+logger = logging.getLogger(__name__)
+
+Model = TypeVar("Model", bound=pydantic.BaseModel)
+
+
+class UserKeys:
+    def __init__(self, master_key: bytes) -> None:
+        self.master_key = master_key
+        self._fernet_by_user_id: dict[UserId, Fernet] = {}
+
+    def fernet(self, user_id: UserId) -> Fernet:
+        """HKDF keyed by the user id, a leaked user key doesn't expose the others' data"""
+        if user_id not in self._fernet_by_user_id:
+            key = HKDF(
+                algorithm=hashes.SHA256(),
+                length=32,
+                salt=None,
+                info=f"tiny-expense-tracker at rest {user_id}".encode("utf-8"),
+            ).derive(self.master_key)
+            self._fernet_by_user_id[user_id] = Fernet(base64.urlsafe_b64encode(key))
+        return self._fernet_by_user_id[user_id]
+
+    def encrypt(self, user_id: UserId, value: str) -> str:
+        if not value or value.startswith(AT_REST_PREFIX):
+            return value
+        token = self.fernet(user_id).encrypt(value.encode("utf-8"))
+        return AT_REST_PREFIX + token.decode("ascii")
+
+    def decrypt(self, user_id: UserId, stored: str) -> str:
+        if not stored.startswith(AT_REST_PREFIX):
+            return stored  # stored before the encryption was enabled
+        try:
+            token = stored.removeprefix(AT_REST_PREFIX)
+            return self.fernet(user_id).decrypt(token).decode("utf-8")
+        except InvalidToken:
+            logger.error(f"Can't decrypt user {user_id!r} data, was the master key changed?")
+            return MASKED_DESCRIPTION
+
+
+def with_text_fields(model: Model, codec: Callable[[str], str]) -> Model:
+    """Copy of the model with free text fields passed through the codec"""
+    copy = model.model_copy(deep=True)
+    if isinstance(copy, Transaction):
+        copy.description = codec(copy.description)
+        if copy.source is not None:
+            copy.source.raw = codec(copy.source.raw)
+    elif isinstance(copy, (TransactionTemplate, Debt)):
+        copy.description = codec(copy.description)
+    elif isinstance(copy, PoolNote):
+        copy.text = codec(copy.text)
+    elif isinstance(copy, TransactionUpdate):
+        if copy.description is not None:
+            copy.description = codec(copy.description)
+    elif isinstance(copy, TransactionEdit):
+        if copy.update is not None:
+            copy.update = with_text_fields(copy.update, codec)
+    elif isinstance(copy, Operation):
+        copy.transactions = [with_text_fields(t, codec) for t in copy.transactions]
+    return copy
+
+
+class EncryptedStorage(Storage):
+    """
+    Decorator encrypting free text on the way to the wrapped storage and decrypting it back;
+    wraps the audited storage, so that audit snapshots are encrypted too
+    """
+
+    def __init__(self, inner: Storage, keys: UserKeys) -> None:
+        self.inner = inner
+        self.keys = keys
+
+    def _encode(self, user_id: UserId, model: Model) -> Model:
+        return with_text_fields(model, lambda value: self.keys.encrypt(user_id, value))
+
+    def _decode(self, user_id: UserId, model: Model) -> Model:
+        return with_text_fields(model, lambda value: self.keys.decrypt(user_id, value))
+
+    def _decode_snapshot(
+        self, user_id: UserId, snapshot: dict[str, Any] | None
+    ) -> dict[str, Any] | None:
+        if snapshot is None:
+            return None
+        decoded = {
+            key: self.keys.decrypt(user_id, value) if isinstance(value, str) else value
+            for key, value in snapshot.items()
+        }
+        source = decoded.get("source")
+        if isinstance(source, dict) and isinstance(source.get("raw"), str):
+            decoded["source"] = {**source, "raw": self.keys.decrypt(user_id, source["raw"])}
+        return decoded
+
+    async def initialize(self) -> None:
+        await self.inner.initialize()
+
+    async def close(self) -> None:
+        await self.inner.close()
+
+    async def add_pool(self, user_id: UserId, new_pool: MoneyPool) -> StoredMoneyPool:
+        return await self.inner.add_pool(user_id, new_pool)
+
+    async def add_balance_to_pool(
+        self, user_id: UserId, pool_id: MoneyPoolId, new_balance: MoneySum
+    ) -> bool:
+        return await self.inner.add_balance_to_pool(user_id, pool_id, new_balance)
+
+    async def set_pool_attributes(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        update: MoneyPoolAttributesUpdate,
+        expected_version: int | None = None,
+    ) -> bool:
+        return await self.inner.set_pool_attributes(user_id, pool_id, update, expected_version)
+
+    async def set_pools_order(self, user_id: UserId, pool_ids: list[MoneyPoolId]) -> None:
+        await self.inner.set_pools_order(user_id, pool_ids)
+
+    async def load_pools(self, user_id: UserId) -> list[StoredMoneyPool]:
+        return await self.inner.load_pools(user_id)
+
+    async def load_pool(self, user_id: UserId, pool_id: MoneyPoolId) -> StoredMoneyPool | None:
+        return await self.inner.load_pool(user_id, pool_id)
+
+    async def save_pool_balance(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        balance: list[MoneySum],
+        initial_balance: list[MoneySum] | None,
+    ) -> bool:
+        return await self.inner.save_pool_balance(user_id, pool_id, balance, initial_balance)
+
+    async def redenominate_pool(self, user_id: UserId, redenomination: PoolRedenomination) -> bool:
+        return await self.inner.redenominate_pool(user_id, redenomination)
+
+    async def add_transaction(
+        self, user_id: UserId, transaction: Transaction
+    ) -> StoredTransaction:
+        stored = await self.inner.add_transaction(user_id, self._encode(user_id, transaction))
+        return self._decode(user_id, stored)
+
+    async def add_transactions(
+        self, user_id: UserId, transactions: list[Transaction]
+    ) -> list[StoredTransaction]:
+        stored = await self.inner.add_transactions(
+            user_id, [self._encode(user_id, t) for t in transactions]
+        )
+        return [self._decode(user_id, t) for t in stored]
+
+    async def apply_transaction_changes(
+        self, user_id: UserId, new: list[Transaction], edits: list[TransactionEdit]
+    ) -> list[StoredTransaction]:
+        added = await self.inner.apply_transaction_changes(
+            user_id,
+            [self._encode(user_id, t) for t in new],
+            [self._encode(user_id, e) for e in edits],
+        )
+        return [self._decode(user_id, t) for t in added]
+
+    async def restore_transactions(
+        self, user_id: UserId, transactions: list[StoredTransaction]
+    ) -> None:
+        await self.inner.restore_transactions(
+            user_id, [self._encode(user_id, t) for t in transactions]
+        )
+
+    async def load_transactions(
+        self,
+        user_id: UserId,
+        filter: TransactionFilter | None,
+        order: TransactionOrder,
+        offset: int,
+        count: int,
+    ) -> list[StoredTransaction]:
+        stored = await self.inner.load_transactions(user_id, filter, order, offset, count)
+        return [self._decode(user_id, t) for t in stored]
+
+    async def load_transaction(
+        self, user_id: UserId, transaction_id: TransactionId
+    ) -> StoredTransaction | None:
+        stored = await self.inner.load_transaction(user_id, transaction_id)
+        return self._decode(user_id, stored) if stored is not None else None
+
+    async def delete_transaction(self, user_id: UserId, transaction_id: TransactionId) -> bool:
+        return await self.inner.delete_transaction(user_id, transaction_id)
+
+    async def update_transaction(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        update: TransactionUpdate,
+        expected_version: int | None = None,
+    ) -> bool:
+        return await self.inner.update_transaction(
+            user_id, transaction_id, self._encode(user_id, update), expected_version
+        )
+
+    async def load_change_counter(self, user_id: UserId) -> int:
+        return await self.inner.load_change_counter(user_id)
+
+    async def load_changes(self, user_id: UserId, since: int) -> list[EntityChange]:
+        return await self.inner.load_changes(user_id, since)
+
+    async def update_transaction_status(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        status: TransactionStatus,
+        expected_version: int | None = None,
+    ) -> bool:
+        return await self.inner.update_transaction_status(
+            user_id, transaction_id, status, expected_version
+        )
+
+    async def load_due_scheduled_transactions(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredTransaction]]:
+        due = await self.inner.load_due_scheduled_transactions(now)
+        return [(user_id, self._decode(user_id, t)) for user_id, t in due]
+
+    async def load_suggestions(
+        self,
+        user_id: UserId,
+        field: SuggestionField,
+        query: str,
+        since: datetime.datetime,
+        count: int,
+    ) -> list[Suggestion]:
+        if field is not SuggestionField.DESCRIPTION:
+            return await self.inner.load_suggestions(user_id, field, query, since, count)
+        # descriptions can only be matched once decrypted
+        transactions = await self.load_transactions(
+            user_id,
+            TransactionFilter(min_timestamp=since),
+            TransactionOrder.LATEST,
+            offset=0,
+            count=MAX_SUGGESTION_TRANSACTIONS,
+        )
+        return count_suggestions(transactions, field, query, [], count)
+
+    async def add_reconciliation(
+        self, user_id: UserId, reconciliation: Reconciliation
+    ) -> StoredReconciliation:
+        return await self.inner.add_reconciliation(user_id, reconciliation)
+
+    async def load_reconciliations(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredReconciliation]:
+        return await self.inner.load_reconciliations(user_id, pool_id)
+
+    async def load_reconciliation(
+        self, user_id: UserId, reconciliation_id: ReconciliationId
+    ) -> StoredReconciliation | None:
+        return await self.inner.load_reconciliation(user_id, reconciliation_id)
+
+    async def save_reconciliation(
+        self, user_id: UserId, reconciliation: StoredReconciliation
+    ) -> bool:
+        return await self.inner.save_reconciliation(user_id, reconciliation)
+
+    async def add_report_snapshot(
+        self, user_id: UserId, snapshot: ReportSnapshot
+    ) -> StoredReportSnapshot:
+        return await self.inner.add_report_snapshot(user_id, snapshot)
+
+    async def load_report_snapshots(self, user_id: UserId) -> list[StoredReportSnapshot]:
+        return await self.inner.load_report_snapshots(user_id)
+
+    async def load_report_snapshot(
+        self, user_id: UserId, snapshot_id: ReportSnapshotId
+    ) -> StoredReportSnapshot | None:
+        return await self.inner.load_report_snapshot(user_id, snapshot_id)
+
+    async def add_allowance(self, user_id: UserId, allowance: Allowance) -> StoredAllowance:
+        return await self.inner.add_allowance(user_id, allowance)
+
+    async def load_allowances(self, user_id: UserId) -> list[StoredAllowance]:
+        return await self.inner.load_allowances(user_id)
+
+    async def load_allowance(
+        self, user_id: UserId, allowance_id: AllowanceId
+    ) -> StoredAllowance | None:
+        return await self.inner.load_allowance(user_id, allowance_id)
+
+    async def save_allowance(self, user_id: UserId, allowance: StoredAllowance) -> bool:
+        return await self.inner.save_allowance(user_id, allowance)
+
+    async def load_allowance_by_view_token(
+        self, view_token: str
+    ) -> tuple[UserId, StoredAllowance] | None:
+        return await self.inner.load_allowance_by_view_token(view_token)
+
+    async def load_due_allowances(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredAllowance]]:
+        return await self.inner.load_due_allowances(now)
+
+    async def add_challenge(self, user_id: UserId, challenge: Challenge) -> StoredChallenge:
+        return await self.inner.add_challenge(user_id, challenge)
+
+    async def load_challenges(self, user_id: UserId) -> list[StoredChallenge]:
+        return await self.inner.load_challenges(user_id)
+
+    async def load_challenge(
+        self, user_id: UserId, challenge_id: ChallengeId
+    ) -> StoredChallenge | None:
+        return await self.inner.load_challenge(user_id, challenge_id)
+
+    async def save_challenge(self, user_id: UserId, challenge: StoredChallenge) -> bool:
+        return await self.inner.save_challenge(user_id, challenge)
+
+    async def add_goal(self, user_id: UserId, goal: Goal) -> StoredGoal:
+        return await self.inner.add_goal(user_id, goal)
+
+    async def load_goals(self, user_id: UserId) -> list[StoredGoal]:
+        return await self.inner.load_goals(user_id)
+
+    async def load_goal(self, user_id: UserId, goal_id: GoalId) -> StoredGoal | None:
+        return await self.inner.load_goal(user_id, goal_id)
+
+    async def save_goal(self, user_id: UserId, goal: StoredGoal) -> bool:
+        return await self.inner.save_goal(user_id, goal)
+
+    async def delete_goal(self, user_id: UserId, goal_id: GoalId) -> bool:
+        return await self.inner.delete_goal(user_id, goal_id)
+
+    async def add_debt(self, user_id: UserId, debt: Debt) -> StoredDebt:
+        stored = await self.inner.add_debt(user_id, self._encode(user_id, debt))
+        return self._decode(user_id, stored)
+
+    async def load_debts(self, user_id: UserId) -> list[StoredDebt]:
+        return [self._decode(user_id, d) for d in await self.inner.load_debts(user_id)]
+
+    async def load_debt(self, user_id: UserId, debt_id: DebtId) -> StoredDebt | None:
+        stored = await self.inner.load_debt(user_id, debt_id)
+        return self._decode(user_id, stored) if stored is not None else None
+
+    async def save_debt(self, user_id: UserId, debt: StoredDebt) -> bool:
+        return await self.inner.save_debt(user_id, self._encode(user_id, debt))
+
+    async def add_transaction_template(
+        self, user_id: UserId, template: TransactionTemplate
+    ) -> StoredTransactionTemplate:
+        stored = await self.inner.add_transaction_template(
+            user_id, self._encode(user_id, template)
+        )
+        return self._decode(user_id, stored)
+
+    async def load_transaction_templates(
+        self, user_id: UserId
+    ) -> list[StoredTransactionTemplate]:
+        stored = await self.inner.load_transaction_templates(user_id)
+        return [self._decode(user_id, t) for t in stored]
+
+    async def load_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> StoredTransactionTemplate | None:
+        stored = await self.inner.load_transaction_template(user_id, template_id)
+        return self._decode(user_id, stored) if stored is not None else None
+
+    async def save_transaction_template(
+        self, user_id: UserId, template: StoredTransactionTemplate
+    ) -> bool:
+        return await self.inner.save_transaction_template(
+            user_id, self._encode(user_id, template)
+        )
+
+    async def delete_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> bool:
+        return await self.inner.delete_transaction_template(user_id, template_id)
+
+    async def add_rule(
+        self, user_id: UserId, rule: CategorizationRule
+    ) -> StoredCategorizationRule:
+        return await self.inner.add_rule(user_id, rule)
+
+    async def load_rules(self, user_id: UserId) -> list[StoredCategorizationRule]:
+        return await self.inner.load_rules(user_id)
+
+    async def load_rule(
+        self, user_id: UserId, rule_id: RuleId
+    ) -> StoredCategorizationRule | None:
+        return await self.inner.load_rule(user_id, rule_id)
+
+    async def save_rule(self, user_id: UserId, rule: StoredCategorizationRule) -> bool:
+        return await self.inner.save_rule(user_id, rule)
+
+    async def delete_rule(self, user_id: UserId, rule_id: RuleId) -> bool:
+        return await self.inner.delete_rule(user_id, rule_id)
+
+    async def add_payee(self, user_id: UserId, payee: Payee) -> StoredPayee:
+        return await self.inner.add_payee(user_id, payee)
+
+    async def load_payees(self, user_id: UserId) -> list[StoredPayee]:
+        return await self.inner.load_payees(user_id)
+
+    async def load_payee(self, user_id: UserId, payee_id: PayeeId) -> StoredPayee | None:
+        return await self.inner.load_payee(user_id, payee_id)
+
+    async def save_payee(self, user_id: UserId, payee: StoredPayee) -> bool:
+        return await self.inner.save_payee(user_id, payee)
+
+    async def delete_payee(self, user_id: UserId, payee_id: PayeeId) -> bool:
+        return await self.inner.delete_payee(user_id, payee_id)
+
+    async def add_pool_note(self, user_id: UserId, note: PoolNote) -> StoredPoolNote:
+        stored = await self.inner.add_pool_note(user_id, self._encode(user_id, note))
+        return self._decode(user_id, stored)
+
+    async def load_pool_notes(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredPoolNote]:
+        stored = await self.inner.load_pool_notes(user_id, pool_id)
+        return [self._decode(user_id, n) for n in stored]
+
+    async def load_pool_note(self, user_id: UserId, note_id: NoteId) -> StoredPoolNote | None:
+        stored = await self.inner.load_pool_note(user_id, note_id)
+        return self._decode(user_id, stored) if stored is not None else None
+
+    async def save_pool_note(self, user_id: UserId, note: StoredPoolNote) -> bool:
+        return await self.inner.save_pool_note(user_id, self._encode(user_id, note))
+
+    async def delete_pool_note(self, user_id: UserId, note_id: NoteId) -> bool:
+        return await self.inner.delete_pool_note(user_id, note_id)
+
+    async def add_balance_snapshot(
+        self, user_id: UserId, snapshot: BalanceSnapshot
+    ) -> StoredBalanceSnapshot:
+        return await self.inner.add_balance_snapshot(user_id, snapshot)
+
+    async def load_balance_snapshots(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredBalanceSnapshot]:
+        return await self.inner.load_balance_snapshots(user_id, pool_id)
+
+    async def load_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> StoredBalanceSnapshot | None:
+        return await self.inner.load_balance_snapshot(user_id, snapshot_id)
+
+    async def delete_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> bool:
+        return await self.inner.delete_balance_snapshot(user_id, snapshot_id)
+
+    async def load_user_settings(self, user_id: UserId) -> UserSettings:
+        return await self.inner.load_user_settings(user_id)
+
+    async def save_user_settings(self, user_id: UserId, settings: UserSettings) -> None:
+        await self.inner.save_user_settings(user_id, settings)
+
+    async def load_month_closes(self, user_id: UserId) -> list[MonthClose]:
+        return await self.inner.load_month_closes(user_id)
+
+    async def load_month_close(self, user_id: UserId, year: int, month: int) -> MonthClose | None:
+        return await self.inner.load_month_close(user_id, year, month)
+
+    async def save_month_close(self, user_id: UserId, month_close: MonthClose) -> None:
+        await self.inner.save_month_close(user_id, month_close)
+
+    async def save_net_worth_snapshot(self, user_id: UserId, snapshot: NetWorthSnapshot) -> None:
+        await self.inner.save_net_worth_snapshot(user_id, snapshot)
+
+    async def load_net_worth_snapshots(
+        self, user_id: UserId, start: datetime.date, end: datetime.date
+    ) -> list[NetWorthSnapshot]:
+        return await self.inner.load_net_worth_snapshots(user_id, start, end)
+
+    async def save_calendar_token(self, user_id: UserId, token: str | None) -> None:
+        await self.inner.save_calendar_token(user_id, token)
+
+    async def load_calendar_token(self, user_id: UserId) -> str | None:
+        return await self.inner.load_calendar_token(user_id)
+
+    async def load_calendar_token_owner(self, token: str) -> UserId | None:
+        return await self.inner.load_calendar_token_owner(token)
+
+    async def save_two_factor(self, user_id: UserId, two_factor: TwoFactor | None) -> None:
+        if two_factor is not None:
+            secret = self.keys.encrypt(user_id, two_factor.secret)
+            two_factor = two_factor.model_copy(update={"secret": secret})
+        await self.inner.save_two_factor(user_id, two_factor)
+
+    async def load_two_factor(self, user_id: UserId) -> TwoFactor | None:
+        two_factor = await self.inner.load_two_factor(user_id)
+        if two_factor is not None:
+            # an undecryptable secret matches no codes, only recovery codes work then
+            two_factor.secret = self.keys.decrypt(user_id, two_factor.secret)
+        return two_factor
+
+    async def log_operation(
+        self, user_id: UserId, operation: Operation, retention: datetime.timedelta
+    ) -> StoredOperation:
+        stored = await self.inner.log_operation(
+            user_id, self._encode(user_id, operation), retention
+        )
+        return self._decode(user_id, stored)
+
+    async def pop_last_operation(
+        self, user_id: UserId, since: datetime.datetime
+    ) -> StoredOperation | None:
+        stored = await self.inner.pop_last_operation(user_id, since)
+        return self._decode(user_id, stored) if stored is not None else None
+
+    async def append_audit_entry(self, entry: AuditEntry) -> None:
+        await self.inner.append_audit_entry(entry)
+
+    async def load_audit_entries(
+        self, user_id: UserId, entity_id: str | None, offset: int, count: int
+    ) -> list[AuditEntry]:
+        entries = await self.inner.load_audit_entries(user_id, entity_id, offset, count)
+        for entry in entries:
+            entry.before = self._decode_snapshot(user_id, entry.before)
+            entry.after = self._decode_snapshot(user_id, entry.after)
+        return entries
+
+    async def save_historical_rates(self, days: list[DailyRates]) -> None:
+        await self.inner.save_historical_rates(days)
+
+    async def load_historical_rates(self, on: datetime.date) -> DailyRates | None:
+        return await self.inner.load_historical_rates(on)
+
+    async def load_historical_rates_between(
+        self, start: datetime.date, end: datetime.date
+    ) -> list[DailyRates]:
+        return await self.inner.load_historical_rates_between(start, end)
+
+    async def delete_user_data(self, user_id: UserId) -> None:
+        await self.inner.delete_user_data(user_id)
+
+    async def load_user_ids(self) -> list[UserId]:
+        return await self.inner.load_user_ids()
+
+    async def load_storage_usage(self, user_id: UserId) -> dict[str, int]:
+        return await self.inner.load_storage_usage(user_id)
+
+    async def load_disabled_user_ids(self) -> list[UserId]:
+        return await self.inner.load_disabled_user_ids()
+
+    async def is_user_disabled(self, user_id: UserId) -> bool:
+        return await self.inner.is_user_disabled(user_id)
+
+    async def set_user_disabled(self, user_id: UserId, disabled: bool) -> None:
+        await self.inner.set_user_disabled(user_id, disabled)
+
+    async def rebuild_indexes(self) -> None:
+        await self.inner.rebuild_indexes()
+
+    async def migrate(self, dry_run: bool) -> list[Migration]:
+        return await self.inner.migrate(dry_run)
@@ -0,0 +1,37 @@
+"""In-process pub/sub for domain events, decoupling the features reacting to changes from the
+routes making them"""
+
+import logging
+from typing import Awaitable, Callable, TypeVar
+
+from api.types.events import BaseEvent, DomainEvent
+
+logger = logging.getLogger(__name__)
+
+E = TypeVar("E", bound=BaseEvent)
+
+
+class EventBus:
+    def __init__(self) -> None:
+        self._handlers: list[tuple[type[BaseEvent], Callable[[BaseEvent], Awaitable[None]]]] = []
+
+    @property
+    def has_subscribers(self) -> bool:
+        return bool(self._handlers)
+
+    def subscribe(self, event_type: type[E], handler: Callable[[E], Awaitable[None]]) -> None:
+        """Handlers are called in the order of subscription for every matching event"""
+        self._handlers.append((event_type, handler))  # type: ignore
+
+    async def publish(self, event: DomainEvent) -> None:
+        """
+        Awaits all matching handlers, so they should be quick; handler errors are logged and
+        don't affect the publisher, the change has already been made
+        """
+        for event_type, handler in self._handlers:
+            if not isinstance(event, event_type):
+                continue
+            try:
+                await handler(event)
+            except Exception:
+                logger.exception(f"Error handling {event.type} event in {handler}")
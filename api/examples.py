@@ -0,0 +1,185 @@
+"""
+Canonical request body examples, constructed from the request types themselves so that they are
+validated on import and can't drift from the actual API
+"""
+
+import datetime
+from decimal import Decimal
+from typing import Any
+
+import pydantic
+
+from api.types.api import (
+    ApplyTemplateRequestBody,
+    BulkTransactionsRequestBody,
+    CashWithdrawalRequestBody,
+    CloseUnaccountedRequestBody,
+    CreateAllowanceRequestBody,
+    CreatePoolRequestBody,
+    CreateReportSnapshotRequestBody,
+    GoalUpdate,
+    MoneyPoolAttributesUpdate,
+    PoolNoteUpdate,
+    PoolOrderRequestBody,
+    PoolTransferRequestBody,
+    QuickAddRequestBody,
+    ReconciliationMatchUpdate,
+    RedenominatePoolRequestBody,
+    SensitiveViewRequestBody,
+    SettleDebtRequestBody,
+    StartReconciliationRequestBody,
+    SyncBalanceRequestBody,
+    TransactionTemplateUpdate,
+    TransactionUpdate,
+    TransferMoneyRequestBody,
+)
+from api.types.challenge import Challenge, ChallengeKind
+from api.types.currency import parse_currency
+from api.types.debt import Debt, DebtDirection
+from api.types.goal import Goal
+from api.types.money_pool import OverdraftFacility
+from api.types.money_sum import MoneySum
+from api.types.note import PoolNote
+from api.types.payee import Payee
+from api.types.reconciliation import ReconciliationAdjustment
+from api.types.settings import ReportingPeriod, UserSettings, Weekday
+from api.types.template import TransactionTemplate
+from api.types.transaction import Transaction, TransactionSource
+
+EUR = parse_currency("EUR")
+USD = parse_currency("USD")
+
+POOL_ID = "66d4a1f0c2b9e8a1f0c2b9e8"
+OTHER_POOL_ID = "66d4a1f0c2b9e8a1f0c2b9e9"
+TRANSACTION_ID = "66d4a2b7c2b9e8a1f0c2b9f0"
+TIMESTAMP = datetime.datetime(2024, 9, 1, 12, 30, tzinfo=datetime.UTC)
+
+
+def eur(amount: str) -> MoneySum:
+    return MoneySum(amount=Decimal(amount), currency=EUR)
+
+
+TRANSACTION = Transaction(
+    sum=eur("-4.50"),
+    pool_id=POOL_ID,
+    description="coffee and a croissant",
+    timestamp=TIMESTAMP,
+    tags=["food"],
+)
+
+# one per request body type
+REQUEST_EXAMPLES: list[pydantic.BaseModel] = [
+    CreatePoolRequestBody(
+        display_name="Debit card",
+        balance=[eur("1250.00"), MoneySum(amount=Decimal("40.00"), currency=USD)],
+        icon="credit-card",
+        color_hex="#4a90d9",
+        group="Bank",
+        default_category="misc",
+    ),
+    MoneyPoolAttributesUpdate(
+        display_name="Main debit card",
+        icon="bank",
+        overdraft=OverdraftFacility(limit=eur("500"), interest_free_days=30),
+    ),
+    PoolOrderRequestBody(pool_ids=[OTHER_POOL_ID, POOL_ID]),
+    RedenominatePoolRequestBody(to_currency=USD, rate=1.082, convert_transactions=True),
+    TRANSACTION,
+    BulkTransactionsRequestBody(
+        transactions=[
+            Transaction(
+                sum=eur("-23.90"),
+                pool_id=POOL_ID,
+                description="BOOKSHOP",
+                timestamp=TIMESTAMP,
+                source=TransactionSource(
+                    origin="csv",
+                    raw="01.09.2024;01.09.2024;BOOKSHOP BERLIN;-23,90;EUR",
+                ),
+            )
+        ]
+    ),
+    QuickAddRequestBody(text="14.99 USD groceries lidl"),
+    SensitiveViewRequestBody(code="492039"),
+    TransactionUpdate(description="coffee", tags=["food", "work"]),
+    TransferMoneyRequestBody(
+        from_pool=POOL_ID,
+        to_pool=OTHER_POOL_ID,
+        sum=eur("100"),
+        description="top up",
+        received=MoneySum(amount=Decimal("108.20"), currency=USD),
+    ),
+    PoolTransferRequestBody(to_pool=OTHER_POOL_ID, sum=eur("100"), description="savings"),
+    CashWithdrawalRequestBody(to_pool=OTHER_POOL_ID, sum=eur("200"), fee=eur("2.50")),
+    CloseUnaccountedRequestBody(actual=eur("35.20"), description="small spending"),
+    SyncBalanceRequestBody(amounts=[1180.25, 40.0]),
+    CreateReportSnapshotRequestBody(year=2024, month=8, points=31),
+    StartReconciliationRequestBody(
+        start=datetime.datetime(2024, 8, 1, tzinfo=datetime.UTC),
+        end=datetime.datetime(2024, 9, 1, tzinfo=datetime.UTC),
+        statement_balance=[eur("1180.25")],
+    ),
+    ReconciliationMatchUpdate(transaction_ids=[TRANSACTION_ID]),
+    ReconciliationAdjustment(sum=eur("-0.30"), description="rounding"),
+    CreateAllowanceRequestBody(
+        pool_id=OTHER_POOL_ID,
+        source_pool_id=POOL_ID,
+        child_name="Alice",
+        weekly_amount=eur("10"),
+        approval_threshold=Decimal("5"),
+    ),
+    Challenge(
+        kind=ChallengeKind.NO_SPEND_DAYS,
+        name="September without takeaways",
+        start=datetime.datetime(2024, 9, 1, tzinfo=datetime.UTC),
+        end=datetime.datetime(2024, 10, 1, tzinfo=datetime.UTC),
+        target_days=10,
+    ),
+    Goal(
+        name="New laptop",
+        target=eur("1500"),
+        target_date=datetime.datetime(2025, 3, 1, tzinfo=datetime.UTC),
+        pool_id=OTHER_POOL_ID,
+    ),
+    GoalUpdate(target=eur("1800")),
+    Debt(
+        counterparty="Bob",
+        direction=DebtDirection.LENT,
+        sum=eur("20"),
+        description="concert tickets",
+        created_at=TIMESTAMP,
+    ),
+    SettleDebtRequestBody(pool_id=POOL_ID),
+    TransactionTemplate(
+        name="Lunch", sum=eur("-12"), pool_id=POOL_ID, description="lunch", tags=["food"]
+    ),
+    TransactionTemplateUpdate(sum=eur("-13")),
+    ApplyTemplateRequestBody(description="lunch with colleagues"),
+    Payee(name="Corner Bakery"),
+    PoolNote(pool_id=POOL_ID, text="card expires 09/27"),
+    PoolNoteUpdate(text="pending refund for the headphones", transaction_id=TRANSACTION_ID),
+    UserSettings(
+        default_currency=EUR,
+        locale="en-GB",
+        week_start=Weekday.MONDAY,
+        default_pool_id=POOL_ID,
+        reporting_period=ReportingPeriod(start_day=25),  # pay cycles from the 25th
+    ),
+]
+
+
+def dump_example(example: pydantic.BaseModel) -> Any:
+    return example.model_dump(mode="json", exclude_unset=True)
+
+
+def example_for(model: Any) -> Any | None:
+    for example in REQUEST_EXAMPLES:
+        if type(example) is model:
+            return dump_example(example)
+    return None
+
+
+def add_examples_to_schemas() -> None:
+    """Makes the examples show up in the OpenAPI schema and Swagger UI"""
+    for example in REQUEST_EXAMPLES:
+        type(example).model_config["json_schema_extra"] = {"examples": [dump_example(example)]}
@@ -22,12 +22,26 @@ class ExchangeRate(pydantic.BaseModel):
     updated_on: Datetime
 
 
+class RateUnavailable(Exception):
+    def __init__(self, base: Currency, target: Currency) -> None:
+        self.base = base
+        self.target = target
+        super().__init__(f"Exchange rate {base} -> {target} is unavailable")
+
+
 class ExchangeRates(abc.ABC):
     async def initialize(self) -> None:
         pass
 
     @abc.abstractmethod
-    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate: ...
+    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate:
+        """Raises RateUnavailable if the rate can't be obtained"""
+
+    async def get_rate_on(
+        self, base: Currency, target: Currency, on: datetime.date
+    ) -> ExchangeRate:
+        """The rate as of the date where the history is kept, the current one otherwise"""
+        return await self.get_rate(base, target)
 
 
 class DumbExchangeRates(ExchangeRates):
@@ -135,5 +149,5 @@ class RemoteExchangeRates(ExchangeRates):
             await self.update_exchange_rates(base)
             matches = self.get_cached_rate_matches(base, target)
             if not matches:
-                raise RuntimeError(f"Failed to fetch exchange rate for {base} -> {target}")
+                raise RateUnavailable(base, target)
         return matches[0]
@@ -0,0 +1,99 @@
+"""
+Money formatting for display, so that thin clients don't each reimplement it
+
+Covers the separators and symbol placement of common locales; unknown regional variants fall
+back to the language's conventions, unknown languages to English ones.
+"""
+
+import dataclasses
+
+from api.types.money_sum import MoneySum
+
+NBSP = "\u00a0"
+NARROW_NBSP = "\u202f"
+
+
+@dataclasses.dataclass(frozen=True)
+class NumberConventions:
+    decimal_separator: str
+    group_separator: str
+    symbol_first: bool
+    symbol_spaced: bool
+
+
+ENGLISH = NumberConventions(".", ",", symbol_first=True, symbol_spaced=False)
+CONTINENTAL = NumberConventions(",", ".", symbol_first=False, symbol_spaced=True)
+SPACE_GROUPED = NumberConventions(",", NBSP, symbol_first=False, symbol_spaced=True)
+
+CONVENTIONS = {
+    "en": ENGLISH,
+    "ja": ENGLISH,
+    "zh": ENGLISH,
+    "ko": ENGLISH,
+    "de": CONTINENTAL,
+    "de-CH": NumberConventions(".", "’", symbol_first=True, symbol_spaced=True),
+    "es": CONTINENTAL,
+    "it": CONTINENTAL,
+    "pt": CONTINENTAL,
+    "pt-BR": NumberConventions(",", ".", symbol_first=True, symbol_spaced=True),
+    "nl": NumberConventions(",", ".", symbol_first=True, symbol_spaced=True),
+    "tr": NumberConventions(",", ".", symbol_first=True, symbol_spaced=False),
+    "fr": NumberConventions(",", NARROW_NBSP, symbol_first=False, symbol_spaced=True),
+    "ru": SPACE_GROUPED,
+    "uk": SPACE_GROUPED,
+    "pl": SPACE_GROUPED,
+    "cs": SPACE_GROUPED,
+    "sv": SPACE_GROUPED,
+    "fi": SPACE_GROUPED,
+    "hy": SPACE_GROUPED,
+    "ka": SPACE_GROUPED,
+}
+
+# currencies without a well-known symbol are shown with their code
+SYMBOLS = {
+    "USD": "$",
+    "EUR": "€",
+    "GBP": "£",
+    "JPY": "¥",
+    "CNY": "¥",
+    "INR": "₹",
+    "KRW": "₩",
+    "ILS": "₪",
+    "RUB": "₽",
+    "UAH": "₴",
+    "AMD": "֏",
+    "GEL": "₾",
+    "KZT": "₸",
+    "TRY": "₺",
+    "THB": "฿",
+    "VND": "₫",
+    "PHP": "₱",
+    "NGN": "₦",
+    "PLN": "zł",
+    "BRL": "R$",
+    "CAD": "CA$",
+    "AUD": "A$",
+    "BTC": "₿",
+    "ETH": "Ξ",
+}
+
+
+def conventions_for(locale: str) -> NumberConventions:
+    return CONVENTIONS.get(locale) or CONVENTIONS.get(locale.split("-")[0]) or ENGLISH
+
+
+def format_money(sum: MoneySum, locale: str) -> str:
+    """E.g. "-$1,234.50" for English, "-1.234,50 €" for German"""
+    conventions = conventions_for(locale)
+    number = f"{abs(sum.amount):,.{sum.currency.precision}f}"
+    number = number.translate(
+        str.maketrans({",": conventions.group_separator, ".": conventions.decimal_separator})
+    )
+    symbol = SYMBOLS.get(sum.currency.code)
+    spacing = NBSP if conventions.symbol_spaced or symbol is None else ""
+    symbol = symbol or sum.currency.code
+    if conventions.symbol_first:
+        formatted = symbol + spacing + number
+    else:
+        formatted = number + spacing + symbol
+    return f"-{formatted}" if sum.amount < 0 else formatted
@@ -0,0 +1,157 @@
+"""
+Realized and unrealized FX gains/losses for foreign-currency pool balances, average cost method
+
+Each transaction's value in the target currency on its date serves as its cost basis (for inflows)
+or proceeds (for outflows): in EUR that's its amount_eur, the rate snapshot taken at the time of
+the transaction, otherwise the historical rate on its date. Void and scheduled transactions don't
+move the balance and are left out.
+
+Both gains are for the period: realized on the outflows in it, and unrealized as the change in the
+balance's value over its cost basis between the start and the end of the period, so that their
+sum is the period's total FX gain. Balances in currencies without a rate at the start or the end
+of the period are reported as unconverted instead.
+"""
+
+import dataclasses
+import datetime
+from decimal import Decimal
+from typing import Sequence
+
+from api.exchange_rates import ExchangeRates, RateUnavailable
+from api.types.api import FxGainsReportResponse, FxPoolCurrencyGains
+from api.types.currency import Currency, parse_currency
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction
+
+EUR = parse_currency("EUR")
+
+
+@dataclasses.dataclass
+class Position:
+    units: float
+    cost: float  # in the target currency
+
+    def acquire(self, units: float, cost: float) -> None:
+        self.units += units
+        self.cost += cost
+
+    def dispose(self, units: float, proceeds: float) -> float:
+        """Returns realized gain (negative for loss)"""
+        if self.units <= 0:
+            # no basis to speak of (e.g. overdraft), treat as neutral
+            cost_removed = proceeds
+        else:
+            cost_removed = self.cost / self.units * units
+        self.units -= units
+        self.cost -= cost_removed
+        return proceeds - cost_removed
+
+
+async def compute_fx_gains(
+    pools: Sequence[StoredMoneyPool],
+    transactions: Sequence[StoredTransaction],
+    exchange_rates: ExchangeRates,
+    start: datetime.datetime,
+    end: datetime.datetime,
+    target_currency: Currency = EUR,
+) -> FxGainsReportResponse:
+    def money(amount: float) -> MoneySum:
+        return MoneySum(amount=Decimal(amount), currency=target_currency)
+
+    async def value(t: StoredTransaction) -> float | None:
+        """In the target currency on the transaction's date, None if unknown"""
+        if target_currency == EUR:
+            return t.amount_eur
+        try:
+            rate = await exchange_rates.get_rate_on(
+                base=t.sum.currency, target=target_currency, on=t.timestamp.date()
+            )
+        except RateUnavailable:
+            return None
+        return float(t.sum.amount) * rate.rate
+
+    transactions = sorted(transactions, key=lambda t: t.timestamp.timestamp())
+    per_pool: list[FxPoolCurrencyGains] = []
+    unconverted: list[MoneySum] = []
+    for pool in pools:
+        for balance in pool.balance:
+            currency: Currency = balance.currency
+            if currency == target_currency:
+                continue
+            try:
+                # NOTE: rate as of the end of the period if the stored history covers it,
+                # today's rate otherwise
+                current_rate = (
+                    await exchange_rates.get_rate_on(
+                        base=currency, target=target_currency, on=end.date()
+                    )
+                ).rate
+                start_rate = (
+                    await exchange_rates.get_rate_on(
+                        base=currency, target=target_currency, on=start.date()
+                    )
+                ).rate
+            except RateUnavailable:
+                unconverted.append(balance)
+                continue
+            pool_currency_transactions = [
+                t
+                for t in transactions
+                if t.pool_id == pool.id and t.sum.currency == currency and t.status.is_counted
+            ]
+            values = [await value(t) for t in pool_currency_transactions]
+
+            # balance before the first tracked transaction, its cost basis is taken from the
+            # earliest known rate
+            opening_units = float(balance.amount) - sum(
+                float(t.sum.amount) for t in pool_currency_transactions
+            )
+            opening_rate = next(
+                (
+                    v / float(t.sum.amount)
+                    for t, v in zip(pool_currency_transactions, values)
+                    if v is not None and t.sum.amount
+                ),
+                current_rate,
+            )
+            position = Position(units=opening_units, cost=opening_units * opening_rate)
+
+            start_position: Position | None = None
+            realized = 0.0
+            for t, v in zip(pool_currency_transactions, values):
+                if t.timestamp.timestamp() > end.timestamp():
+                    break
+                if start_position is None and t.timestamp.timestamp() >= start.timestamp():
+                    start_position = dataclasses.replace(position)
+                amount = float(t.sum.amount)
+                if v is None:
+                    v = amount * current_rate
+                if amount >= 0:
+                    position.acquire(amount, v)
+                else:
+                    gain = position.dispose(-amount, -v)
+                    if t.timestamp.timestamp() >= start.timestamp():
+                        realized += gain
+            if start_position is None:  # no transactions in the period
+                start_position = dataclasses.replace(position)
+            unrealized = (position.units * current_rate - position.cost) - (
+                start_position.units * start_rate - start_position.cost
+            )
+
+            per_pool.append(
+                FxPoolCurrencyGains(
+                    pool_id=pool.id,
+                    balance=MoneySum(amount=Decimal(position.units), currency=currency),
+                    cost_basis=money(position.cost),
+                    realized=money(realized),
+                    unrealized=money(unrealized),
+                )
+            )
+
+    return FxGainsReportResponse(
+        per_pool=per_pool,
+        realized=money(sum(float(g.realized.amount) for g in per_pool)),
+        unrealized=money(sum(float(g.unrealized.amount) for g in per_pool)),
+        unconverted=unconverted,
+    )
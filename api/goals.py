@@ -0,0 +1,51 @@
+"""Savings goal progress, projected linearly from the recent saving rate"""
+
+import datetime
+from decimal import Decimal
+
+from api.types.api import GoalProgress
+from api.types.goal import StoredGoal
+from api.types.money_sum import MoneySum
+
+SAVING_RATE_WINDOW = datetime.timedelta(days=90)
+DAYS_PER_MONTH = Decimal("30.4375")
+
+
+def compute_goal_progress(
+    goal: StoredGoal,
+    saved: MoneySum,
+    saved_in_window: MoneySum,
+    now: datetime.datetime,
+) -> GoalProgress:
+    """
+    saved is the pool's current total and saved_in_window is its net change over the last
+    SAVING_RATE_WINDOW, both in goal's currency
+    """
+    currency = goal.target.currency
+    daily_rate = saved_in_window.amount / SAVING_RATE_WINDOW.days
+    days_left = Decimal(max(goal.target_date.timestamp() - now.timestamp(), 0) / 86400)
+    projected = saved.amount + daily_rate * days_left
+    remaining = goal.target.amount - saved.amount
+
+    projected_completion: datetime.datetime | None
+    if remaining <= 0:
+        projected_completion = now
+    elif daily_rate > 0:
+        projected_completion = now + datetime.timedelta(days=float(remaining / daily_rate))
+    else:
+        projected_completion = None
+
+    return GoalProgress(
+        goal_id=goal.id,
+        saved=saved,
+        target=goal.target,
+        fraction=(
+            float(min(saved.amount / goal.target.amount, Decimal(1)))
+            if goal.target.amount
+            else 1.0
+        ),
+        monthly_saving_rate=MoneySum(amount=daily_rate * DAYS_PER_MONTH, currency=currency),
+        projected_at_target_date=MoneySum(amount=projected, currency=currency),
+        on_track=projected >= goal.target.amount,
+        projected_completion=projected_completion,
+    )
@@ -0,0 +1,216 @@
+"""
+Optional gRPC interface to the pool and transaction operations, for clients preferring protobuf
+contracts; needs the packages from requirements.grpc.txt. The messages and the service are loaded
+from expense_tracker.proto at runtime, so there are no generated modules to keep in sync.
+
+Metadata carries the same credentials as the HTTP headers (e.g. "token" and "user-id"), checked
+by the same auth. The port is plaintext, TLS is up to a proxy in front of it
+"""
+
+import datetime
+import functools
+import inspect
+import logging
+from decimal import Decimal, InvalidOperation
+from typing import Any, Awaitable, Callable, Mapping, TypeVar
+
+import grpc  # type: ignore
+import pydantic
+from fastapi import HTTPException
+
+from api.auth import Auth
+from api.exchange_rates import RateUnavailable
+from api.service import ExpenseService, ServiceError
+from api.types.api import TokenScope
+from api.types.ids import UserId
+from api.types.money_pool import MoneyPool, StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction, Transaction, TransactionOrder
+
+logger = logging.getLogger(__name__)
+
+# resolved against sys.path, i.e. the project root
+protos, services = grpc.protos_and_services("api/expense_tracker.proto")  # type: ignore
+
+DEFAULT_COUNT = 10
+MAX_COUNT = 200  # as in the HTTP API
+
+GRPC_STATUS_BY_HTTP_STATUS = {
+    400: grpc.StatusCode.INVALID_ARGUMENT,
+    401: grpc.StatusCode.UNAUTHENTICATED,
+    403: grpc.StatusCode.PERMISSION_DENIED,
+    404: grpc.StatusCode.NOT_FOUND,
+    409: grpc.StatusCode.FAILED_PRECONDITION,
+    429: grpc.StatusCode.RESOURCE_EXHAUSTED,
+    503: grpc.StatusCode.UNAVAILABLE,
+}
+
+Response = TypeVar("Response")
+Rpc = Callable[..., Awaitable[Response]]
+
+
+def auth_arguments(method: Callable[..., Any], metadata: Mapping[str, str]) -> dict[str, str]:
+    """Header parameters of the auth method from the metadata, keyed by the header names"""
+    arguments: dict[str, str] = {}
+    for name, parameter in inspect.signature(method).parameters.items():
+        header = name.replace("_", "-")
+        if header in metadata:
+            arguments[name] = metadata[header]
+        elif parameter.default is inspect.Parameter.empty:
+            raise HTTPException(status_code=401, detail=f"Missing {header!r} metadata")
+    return arguments
+
+
+def money_sum(message: Any) -> MoneySum:
+    try:
+        return MoneySum(amount=Decimal(message.amount), currency=message.currency)
+    except InvalidOperation:
+        raise HTTPException(status_code=400, detail=f"Invalid amount: {message.amount!r}")
+
+
+def money_sum_message(sum_: MoneySum) -> Any:
+    return protos.MoneySum(amount=str(sum_.amount), currency=sum_.currency.code)
+
+
+def pool_message(pool: StoredMoneyPool) -> Any:
+    return protos.Pool(
+        id=pool.id,
+        display_name=pool.display_name,
+        balance=[money_sum_message(s) for s in pool.balance],
+        version=pool.version,
+    )
+
+
+def transaction_message(transaction: StoredTransaction) -> Any:
+    return protos.Transaction(
+        id=transaction.id,
+        pool_id=transaction.pool_id,
+        sum=money_sum_message(transaction.sum),
+        description=transaction.description,
+        timestamp=transaction.timestamp.timestamp(),
+        tags=transaction.tags,
+        version=transaction.version,
+    )
+
+
+def rpc(method: Rpc[Response]) -> Rpc[Response]:
+    """Reports the errors the HTTP API turns into responses with the matching status codes"""
+
+    @functools.wraps(method)
+    async def wrapper(self: "ExpenseTrackerServicer", request: Any, context: Any) -> Response:
+        try:
+            return await method(self, request, context)
+        except HTTPException as e:
+            status_code, detail = e.status_code, str(e.detail)
+        except ServiceError as e:
+            status_code, detail = e.status_code, str(e)
+        except pydantic.ValidationError as e:
+            status_code, detail = 400, str(e)
+        except RateUnavailable as e:
+            status_code, detail = 503, str(e)
+        code = GRPC_STATUS_BY_HTTP_STATUS.get(status_code, grpc.StatusCode.UNKNOWN)
+        await context.abort(code, detail)
+        raise AssertionError("abort raises")
+
+    return wrapper
+
+
+class ExpenseTrackerServicer:
+    def __init__(self, service: ExpenseService, auth: Auth) -> None:
+        self.service = service
+        self.auth = auth
+
+    async def authorize(self, context: Any, write: bool = False) -> UserId:
+        metadata = {key: value for key, value in context.invocation_metadata()}
+        user_id = await self.auth.authorize_request(
+            **auth_arguments(self.auth.authorize_request, metadata)
+        )
+        if await self.service.storage.is_user_disabled(user_id):
+            raise HTTPException(status_code=403, detail="Account is disabled")
+        if write:
+            scope = await self.auth.token_scope(**auth_arguments(self.auth.token_scope, metadata))
+            if scope is TokenScope.READ:
+                raise HTTPException(status_code=403, detail="Token is read-only")
+        return user_id
+
+    def descriptions_visible(self, user_id: UserId, context: Any) -> bool:
+        privacy = self.service.privacy
+        if privacy is None:
+            return True
+        metadata = {key: value for key, value in context.invocation_metadata()}
+        return privacy.can_view(user_id, metadata.get("sensitive-view-token"))
+
+    @rpc
+    async def ListPools(self, request: Any, context: Any) -> Any:
+        user_id = await self.authorize(context)
+        pools = await self.service.storage.load_pools(user_id)
+        return protos.ListPoolsResponse(pools=[pool_message(p) for p in pools])
+
+    @rpc
+    async def CreatePool(self, request: Any, context: Any) -> Any:
+        user_id = await self.authorize(context, write=True)
+        pool = await self.service.create_pool(
+            user_id,
+            MoneyPool(
+                display_name=request.display_name,
+                balance=[money_sum(s) for s in request.balance],
+            ),
+        )
+        return pool_message(pool)
+
+    @rpc
+    async def AddTransaction(self, request: Any, context: Any) -> Any:
+        """Without the duplicate check and the allowance approvals of the HTTP route"""
+        user_id = await self.authorize(context, write=True)
+        transaction = Transaction(
+            sum=money_sum(request.sum),
+            pool_id=request.pool_id,
+            description=request.description,
+            timestamp=(
+                datetime.datetime.fromtimestamp(request.timestamp, tz=datetime.UTC)
+                if request.HasField("timestamp")
+                else datetime.datetime.now(tz=datetime.UTC)
+            ),
+            tags=list(request.tags),
+        )
+        stored, _ = await self.service.add_transaction(user_id, transaction)
+        [presented] = self.service.present_transactions(
+            [stored], self.descriptions_visible(user_id, context)
+        )
+        return transaction_message(presented)
+
+    @rpc
+    async def ListTransactions(self, request: Any, context: Any) -> Any:
+        user_id = await self.authorize(context)
+        count = request.count or DEFAULT_COUNT
+        if request.offset < 0 or not 0 < count <= MAX_COUNT:
+            raise HTTPException(status_code=400, detail="Invalid offset or count")
+        transactions = await self.service.storage.load_transactions(
+            user_id,
+            filter=None,
+            order=TransactionOrder.LATEST,
+            offset=request.offset,
+            count=count,
+        )
+        presented = self.service.present_transactions(
+            transactions, self.descriptions_visible(user_id, context)
+        )
+        return protos.ListTransactionsResponse(
+            transactions=[transaction_message(t) for t in presented]
+        )
+
+    @rpc
+    async def DeleteTransaction(self, request: Any, context: Any) -> Any:
+        user_id = await self.authorize(context, write=True)
+        if not await self.service.delete_transaction(user_id, request.id):
+            raise HTTPException(status_code=404, detail="No such transaction")
+        return protos.DeleteTransactionResponse()
+
+
+async def start_grpc_server(service: ExpenseService, auth: Auth, port: int) -> grpc.aio.Server:
+    server = grpc.aio.server()
+    services.add_ExpenseTrackerServicer_to_server(ExpenseTrackerServicer(service, auth), server)
+    server.add_insecure_port(f"[::]:{port}")
+    await server.start()
+    logger.info(f"Serving gRPC on port {port}")
+    return server
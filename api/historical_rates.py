@@ -0,0 +1,158 @@
+"""
+Historical exchange rates, recorded daily as the rate provider is used; imported from ECB reference
+rates CSV (eurofxref-hist.csv) to backfill dates before the instance started recording them
+"""
+
+import csv
+import datetime
+import io
+import logging
+
+from api.exchange_rates import ExchangeRate, ExchangeRates, RateUnavailable
+from api.storage import Storage
+from api.types.currency import Currency, parse_currency
+from api.types.historical_rates import DailyRates
+
+logger = logging.getLogger(__name__)
+
+EUR = parse_currency("EUR")
+
+
+def parse_ecb_csv(text: str) -> list[DailyRates]:
+    """
+    Expects a header row "Date,USD,JPY,..." followed by one row per day; missing values are
+    marked with N/A or left empty
+    """
+    reader = csv.reader(io.StringIO(text.strip()))
+    try:
+        header = [column.strip() for column in next(reader)]
+    except StopIteration:
+        raise ValueError("Empty CSV")
+    if not header or header[0].lower() != "date":
+        raise ValueError("First column must be Date")
+    currencies = header[1:]
+
+    days: list[DailyRates] = []
+    for line_no, row in enumerate(reader, start=2):
+        if not row or not row[0].strip():
+            continue
+        try:
+            date = datetime.date.fromisoformat(row[0].strip())
+        except ValueError:
+            raise ValueError(f"Line {line_no}: invalid date {row[0]!r}")
+        rates: dict[str, float] = {}
+        for code, value in zip(currencies, row[1:]):
+            value = value.strip()
+            if not code or not value or value.upper() == "N/A":
+                continue
+            try:
+                rates[code.upper()] = float(value)
+            except ValueError:
+                raise ValueError(f"Line {line_no}: invalid {code} rate {value!r}")
+        days.append(DailyRates(date=date, rates=rates))
+    return days
+
+
+class HistoricalExchangeRates(ExchangeRates):
+    """Rates as of the given date where imported history covers it, current ones otherwise"""
+
+    def __init__(self, storage: Storage, fallback: ExchangeRates, on: datetime.date) -> None:
+        self.storage = storage
+        self.fallback = fallback
+        self.on = on
+        self._daily_rates: DailyRates | None = None
+        self._loaded = False
+
+    async def _load(self) -> DailyRates | None:
+        if not self._loaded:
+            self._daily_rates = await self.storage.load_historical_rates(self.on)
+            self._loaded = True
+        return self._daily_rates
+
+    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate:
+        daily = await self._load()
+        if daily is not None:
+            rate = daily.cross_rate(base.code, target.code)
+            if rate is not None:
+                return ExchangeRate(
+                    base=base,
+                    target=target,
+                    rate=rate,
+                    updated_on=datetime.datetime.combine(
+                        daily.date, datetime.time(), tzinfo=datetime.UTC
+                    ),
+                )
+        return await self.fallback.get_rate(base, target)
+
+
+class DatedExchangeRates(ExchangeRates):
+    """
+    Current rates from the provider, past ones from the stored history where it covers the date;
+    made per request, like HistoricalExchangeRates, as the loaded days are kept
+    """
+
+    def __init__(self, inner: ExchangeRates, storage: Storage) -> None:
+        self.inner = inner
+        self.storage = storage
+        self._historical: dict[datetime.date, HistoricalExchangeRates] = {}
+
+    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate:
+        return await self.inner.get_rate(base, target)
+
+    async def get_rate_on(
+        self, base: Currency, target: Currency, on: datetime.date
+    ) -> ExchangeRate:
+        if on >= datetime.datetime.now(tz=datetime.UTC).date():
+            return await self.inner.get_rate(base, target)
+        if on not in self._historical:
+            self._historical[on] = HistoricalExchangeRates(self.storage, self.inner, on=on)
+        return await self._historical[on].get_rate(base, target)
+
+
+class RecordedExchangeRates(ExchangeRates):
+    """
+    Provider's rates recorded in storage once a day per currency, for past dates looked up there
+    (alongside the imported history) instead of using the current ones
+    """
+
+    def __init__(self, inner: ExchangeRates, storage: Storage) -> None:
+        self.inner = inner
+        self.storage = storage
+        self._recorded: set[tuple[datetime.date, str]] = set()
+
+    async def initialize(self) -> None:
+        await self.inner.initialize()
+
+    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate:
+        rate = await self.inner.get_rate(base, target)
+        for currency in (base, target):
+            if currency != EUR:
+                try:
+                    await self._record(currency)
+                except Exception:
+                    # the rate is there, failing the conversion won't help
+                    logger.exception(f"Error recording {currency.code} exchange rate")
+        return rate
+
+    async def get_rate_on(
+        self, base: Currency, target: Currency, on: datetime.date
+    ) -> ExchangeRate:
+        if on >= datetime.datetime.now(tz=datetime.UTC).date():
+            return await self.get_rate(base, target)
+        return await HistoricalExchangeRates(self.storage, self, on=on).get_rate(base, target)
+
+    async def _record(self, currency: Currency) -> None:
+        today = datetime.datetime.now(tz=datetime.UTC).date()
+        if (today, currency.code) in self._recorded:
+            return
+        try:
+            eur_rate = await self.inner.get_rate(EUR, currency)
+        except RateUnavailable:
+            return
+        day = eur_rate.updated_on.date()
+        stored = await self.storage.load_historical_rates(day)
+        rates = dict(stored.rates) if stored is not None and stored.date == day else {}
+        if rates.get(currency.code) != eur_rate.rate:
+            rates[currency.code] = eur_rate.rate
+            await self.storage.save_historical_rates([DailyRates(date=day, rates=rates)])
+        self._recorded.add((today, currency.code))
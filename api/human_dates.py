@@ -0,0 +1,95 @@
+"""
+Human-friendly dates in query parameters, e.g. ?from=last+monday&to=yesterday, resolved in the
+user's timezone; ISO datetimes and UNIX timestamps are accepted as before
+"""
+
+import datetime
+import re
+
+from api.types.datetime import parse_datetime
+from api.types.settings import Weekday
+
+RELATIVE_DAYS = {"today": 0, "yesterday": -1, "tomorrow": 1}
+WEEKDAYS = {w.value for w in Weekday}
+PERIODS = ("week", "month", "year")
+AGO_PATTERN = re.compile(r"^(?P<count>\d+) (?P<unit>day|week|month|year)s? ago$")
+EXAMPLES = "'today', 'yesterday', 'last monday', 'this month', '3 days ago'"
+HUMAN_DATE_DESCRIPTION = f"ISO datetime, UNIX timestamp or e.g. {EXAMPLES}, in the user's timezone"
+
+
+def add_months(date: datetime.date, months: int) -> datetime.date:
+    """Clamped to the month's last day, e.g. a month before March 31 is the end of February"""
+    year, month = divmod(date.year * 12 + date.month - 1 + months, 12)
+    month += 1
+    next_month = datetime.date(year + month // 12, month % 12 + 1, 1)
+    last_day = (next_month - datetime.timedelta(days=1)).day
+    return datetime.date(year, month, min(date.day, last_day))
+
+
+def week_start_of(date: datetime.date, week_start: Weekday) -> datetime.date:
+    return date - datetime.timedelta(days=(date.weekday() - week_start.number) % 7)
+
+
+def period_start(date: datetime.date, unit: str, week_start: Weekday) -> datetime.date:
+    match unit:
+        case "week":
+            return week_start_of(date, week_start)
+        case "month":
+            return date.replace(day=1)
+        case "year":
+            return date.replace(month=1, day=1)
+    return date
+
+
+def shift(date: datetime.date, unit: str, count: int) -> datetime.date:
+    match unit:
+        case "week":
+            return date + datetime.timedelta(weeks=count)
+        case "month":
+            return add_months(date, count)
+        case "year":
+            return add_months(date, 12 * count)
+    return date + datetime.timedelta(days=count)
+
+
+def parse_human_span(
+    text: str, now: datetime.datetime, tz: datetime.tzinfo, week_start: Weekday
+) -> tuple[datetime.datetime, datetime.datetime]:
+    """
+    Period the text refers to, e.g. the whole day for "yesterday", so that it's used from its start
+    in "from" and up to its end in "to"; exact datetimes are empty periods. Raises ValueError
+    """
+    phrase = " ".join(text.casefold().split())
+    words = phrase.split(" ")
+    today = now.astimezone(tz).date()
+    unit = "day"
+    if phrase == "now":
+        return now, now
+    elif phrase in RELATIVE_DAYS:
+        first_day = today + datetime.timedelta(days=RELATIVE_DAYS[phrase])
+    elif match := AGO_PATTERN.match(phrase):
+        first_day = shift(today, match["unit"], -int(match["count"]))
+    elif len(words) == 2 and words[0] in ("last", "this") and words[1] in WEEKDAYS:
+        day = Weekday(words[1])
+        if words[0] == "last":
+            first_day = today - datetime.timedelta(days=(today.weekday() - day.number - 1) % 7 + 1)
+        else:
+            offset = (day.number - week_start.number) % 7
+            first_day = week_start_of(today, week_start) + datetime.timedelta(days=offset)
+    elif len(words) == 2 and words[0] in ("last", "this") and words[1] in PERIODS:
+        unit = words[1]
+        first_day = period_start(today, unit, week_start)
+        if words[0] == "last":
+            first_day = shift(first_day, unit, -1)
+    else:
+        try:
+            exact = parse_datetime(text)
+        except (ValueError, OverflowError, OSError):
+            raise ValueError(
+                f"Unknown date {text!r}, expected an ISO datetime, a UNIX timestamp or e.g. "
+                + EXAMPLES
+            )
+        return exact, exact
+    start = datetime.datetime.combine(first_day, datetime.time(), tzinfo=tz)
+    end = datetime.datetime.combine(shift(first_day, unit, 1), datetime.time(), tzinfo=tz)
+    return start, end
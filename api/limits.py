@@ -0,0 +1,113 @@
+"""
+Request body size limit and per-request timeout, configured with MAX_REQUEST_BODY_SIZE in bytes
+and REQUEST_TIMEOUT_SEC; the timeout covers producing the response, not streaming it, so that
+exports and live updates are not cut off
+"""
+
+import asyncio
+import dataclasses
+import json
+from typing import Mapping
+
+from starlette.datastructures import Headers
+from starlette.exceptions import HTTPException
+from starlette.types import ASGIApp, Message, Receive, Scope, Send
+
+DEFAULT_MAX_BODY_SIZE = 10 * 1024 * 1024  # large statement imports still fit
+DEFAULT_TIMEOUT_SEC = 30.0
+
+
+@dataclasses.dataclass(frozen=True)
+class RequestLimits:
+    max_body_size: int = DEFAULT_MAX_BODY_SIZE
+    timeout_sec: float = DEFAULT_TIMEOUT_SEC
+
+
+def request_limits_from_env(env: Mapping[str, str]) -> RequestLimits:
+    return RequestLimits(
+        max_body_size=int(env.get("MAX_REQUEST_BODY_SIZE", DEFAULT_MAX_BODY_SIZE)),
+        timeout_sec=float(env.get("REQUEST_TIMEOUT_SEC", DEFAULT_TIMEOUT_SEC)),
+    )
+
+
+class BodyTooLarge(HTTPException):
+    """Raised while reading the body, for the app to respond as with its own HTTP errors"""
+
+    def __init__(self, max_body_size: int) -> None:
+        super().__init__(status_code=413, detail=f"Request body exceeds {max_body_size} bytes")
+
+
+async def send_error(send: Send, status_code: int, detail: str) -> None:
+    body = json.dumps({"detail": detail}).encode("utf-8")
+    await send(
+        {
+            "type": "http.response.start",
+            "status": status_code,
+            "headers": [
+                (b"content-type", b"application/json"),
+                (b"content-length", str(len(body)).encode("ascii")),
+            ],
+        }
+    )
+    await send({"type": "http.response.body", "body": body})
+
+
+class RequestLimitsMiddleware:
+    def __init__(self, app: ASGIApp, limits: RequestLimits) -> None:
+        self.app = app
+        self.limits = limits
+
+    async def __call__(self, scope: Scope, receive: Receive, send: Send) -> None:
+        if scope["type"] != "http":
+            await self.app(scope, receive, send)
+            return
+        content_length = Headers(scope=scope).get("content-length")
+        if content_length is not None and content_length.isdigit():
+            if int(content_length) > self.limits.max_body_size:
+                await send_error(send, 413, BodyTooLarge(self.limits.max_body_size).detail)
+                return
+
+        received = 0
+
+        async def limited_receive() -> Message:
+            # chunked bodies have no length upfront, counting as they are read
+            nonlocal received
+            message = await receive()
+            if message["type"] == "http.request":
+                received += len(message.get("body", b""))
+                if received > self.limits.max_body_size:
+                    raise BodyTooLarge(self.limits.max_body_size)
+            return message
+
+        response_started = asyncio.Event()
+
+        async def tracking_send(message: Message) -> None:
+            if message["type"] == "http.response.start":
+                response_started.set()
+            await send(message)
+
+        async def run_app() -> None:
+            await self.app(scope, limited_receive, tracking_send)
+
+        task = asyncio.create_task(run_app())
+        started = asyncio.create_task(response_started.wait())
+        try:
+            await asyncio.wait(
+                [task, started],
+                timeout=self.limits.timeout_sec,
+                return_when=asyncio.FIRST_COMPLETED,
+            )
+        except asyncio.CancelledError:
+            task.cancel()
+            raise
+        finally:
+            started.cancel()
+        if not task.done() and not response_started.is_set():
+            task.cancel()
+            try:
+                await task
+            except asyncio.CancelledError:
+                pass
+            await send_error(send, 408, "Request timed out")
+            return
+        await task
@@ -0,0 +1,113 @@
+"""
+Fan-out of domain events to the user's live connections (WebSocket or server-sent events), so
+that multiple devices stay in sync without polling
+"""
+
+import asyncio
+import logging
+import secrets
+import time
+from collections import deque
+from contextlib import contextmanager
+from typing import AsyncIterator, Callable, Iterator, MutableMapping
+
+from cachetools import TTLCache  # type: ignore
+
+from api.types.events import BaseEvent
+from api.types.ids import UserId
+
+logger = logging.getLogger(__name__)
+
+# events are dropped for connections lagging behind by that many
+MAX_QUEUED_EVENTS = 100
+
+# per user, for resuming server-sent event streams after reconnects
+REPLAY_BUFFER_SIZE = 100
+
+SSE_KEEPALIVE_SEC = 15
+
+LiveEvent = tuple[int, BaseEvent]  # id, event
+
+
+class LiveUpdates:
+    def __init__(self, ticket_ttl_sec: float = 60) -> None:
+        self.ticket_ttl_sec = ticket_ttl_sec
+        # browsers can't set auth headers on WebSocket connections, so clients authorize
+        # through a regular request first and connect with a one-time ticket
+        self._user_id_by_ticket: MutableMapping[str, UserId] = TTLCache(
+            maxsize=4096, ttl=ticket_ttl_sec
+        )
+        self._queues_by_user_id: dict[UserId, list[asyncio.Queue[LiveEvent]]] = {}
+        self._recent_by_user_id: dict[UserId, deque[LiveEvent]] = {}
+        # starting from the current time so that ids keep growing across restarts
+        self._last_event_id = time.time_ns() // 1000
+
+    def issue_ticket(self, user_id: UserId) -> str:
+        ticket = secrets.token_urlsafe(nbytes=32)
+        self._user_id_by_ticket[ticket] = user_id
+        return ticket
+
+    def redeem_ticket(self, ticket: str) -> UserId | None:
+        return self._user_id_by_ticket.pop(ticket, None)
+
+    def connection_count(self, user_id: UserId) -> int:
+        return len(self._queues_by_user_id.get(user_id, []))
+
+    @contextmanager
+    def connect(self, user_id: UserId) -> Iterator["asyncio.Queue[LiveEvent]"]:
+        queue: asyncio.Queue[LiveEvent] = asyncio.Queue(maxsize=MAX_QUEUED_EVENTS)
+        self._queues_by_user_id.setdefault(user_id, []).append(queue)
+        try:
+            yield queue
+        finally:
+            queues = self._queues_by_user_id[user_id]
+            queues.remove(queue)
+            if not queues:
+                del self._queues_by_user_id[user_id]
+
+    def recent(self, user_id: UserId, after_id: int) -> list[LiveEvent]:
+        return [e for e in self._recent_by_user_id.get(user_id, []) if e[0] > after_id]
+
+    async def publish(self, event: BaseEvent) -> None:
+        self._last_event_id += 1
+        live_event = (self._last_event_id, event)
+        recent = self._recent_by_user_id.setdefault(
+            event.user_id, deque(maxlen=REPLAY_BUFFER_SIZE)
+        )
+        recent.append(live_event)
+        for queue in self._queues_by_user_id.get(event.user_id, []):
+            try:
+                queue.put_nowait(live_event)
+            except asyncio.QueueFull:
+                logger.warning(f"Live connection of {event.user_id!r} lags, dropping events")
+
+
+async def sse_stream(
+    live: LiveUpdates,
+    user_id: UserId,
+    last_event_id: int | None,
+    present: Callable[[BaseEvent], BaseEvent],
+) -> AsyncIterator[str]:
+    """
+    Server-sent events, resuming after the last event the client has received if it's still
+    in the replay buffer
+    """
+
+    def message(live_event: LiveEvent) -> str:
+        id, event = live_event
+        return f"id: {id}\ndata: {present(event).model_dump_json()}\n\n"
+
+    with live.connect(user_id) as queue:
+        last_sent_id = last_event_id or 0
+        if last_event_id is not None:
+            for live_event in live.recent(user_id, after_id=last_event_id):
+                yield message(live_event)
+                last_sent_id = live_event[0]
+        while True:
+            try:
+                live_event = await asyncio.wait_for(queue.get(), timeout=SSE_KEEPALIVE_SEC)
+            except TimeoutError:
+                yield ": keepalive\n\n"
+                continue
+            if live_event[0] > last_sent_id:  # might have been replayed already
+                yield message(live_event)
@@ -0,0 +1,27 @@
+"""
+Versioned changes of stored data, applied once each in the order of versions; the storage
+records applied versions and runs them on startup (if enabled) or with `python cli.py migrate`
+"""
+
+import dataclasses
+from typing import Awaitable, Callable, Iterable, Sequence
+
+
+@dataclasses.dataclass(frozen=True)
+class Migration:
+    version: int  # never reused or reordered once released
+    name: str
+    apply: Callable[[], Awaitable[None]]  # must be safe to rerun if interrupted
+
+    def __str__(self) -> str:
+        return f"{self.version:04}: {self.name}"
+
+
+def pending_migrations(
+    migrations: Sequence[Migration], applied_versions: Iterable[int]
+) -> list[Migration]:
+    versions = [m.version for m in migrations]
+    if len(set(versions)) != len(versions):
+        raise ValueError(f"Duplicate migration versions: {versions}")
+    applied = set(applied_versions)
+    return sorted((m for m in migrations if m.version not in applied), key=lambda m: m.version)
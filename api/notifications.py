@@ -0,0 +1,76 @@
+import abc
+import asyncio
+import logging
+import smtplib
+from email.message import EmailMessage
+
+from api.types.ids import UserId
+
+logger = logging.getLogger(__name__)
+
+
+class Notifier(abc.ABC):
+    @abc.abstractmethod
+    def user_ids(self) -> list[UserId]:
+        """Users reachable through the channel"""
+
+    @abc.abstractmethod
+    async def notify(self, user_id: UserId, subject: str, text: str) -> bool:
+        """Returns False if the user can't be reached through the channel"""
+
+
+class EmailNotifier(Notifier):
+    def __init__(
+        self,
+        smtp_host: str,
+        smtp_port: int,
+        sender: str,
+        recipients: dict[UserId, str],
+        username: str | None = None,
+        password: str | None = None,
+        starttls: bool = True,
+    ) -> None:
+        self.smtp_host = smtp_host
+        self.smtp_port = smtp_port
+        self.sender = sender
+        self.recipients = recipients
+        self.username = username
+        self.password = password
+        self.starttls = starttls
+
+    def user_ids(self) -> list[UserId]:
+        return list(self.recipients.keys())
+
+    def _send(self, message: EmailMessage) -> None:
+        with smtplib.SMTP(self.smtp_host, self.smtp_port, timeout=30) as smtp:
+            if self.starttls:
+                smtp.starttls()
+            if self.username is not None and self.password is not None:
+                smtp.login(self.username, self.password)
+            smtp.send_message(message)
+
+    async def notify(self, user_id: UserId, subject: str, text: str) -> bool:
+        address = self.recipients.get(user_id)
+        if address is None:
+            return False
+        message = EmailMessage()
+        message["From"] = self.sender
+        message["To"] = address
+        message["Subject"] = subject
+        message.set_content(text)
+        logger.info(f"Sending email {subject!r} to user {user_id}")
+        await asyncio.to_thread(self._send, message)
+        return True
+
+
+def parse_email_recipients(raw: str) -> dict[UserId, str]:
+    """Comma-separated user_id:address pairs"""
+    recipients: dict[UserId, str] = {}
+    for pair in raw.split(","):
+        if not pair.strip():
+            continue
+        user_id, _, address = pair.strip().partition(":")
+        if not address:
+            raise ValueError(f"Expected user_id:address pair, got {pair!r}")
+        recipients[user_id] = address
+    return recipients
@@ -0,0 +1,168 @@
+"""
+Login through OAuth2 / OpenID Connect identity providers (Google, GitHub, self-hosted IdPs) as an
+alternative to the Telegram bot; configured with OIDC_* environment variables (or .env file)
+"""
+
+import base64
+import dataclasses
+import logging
+import secrets
+import urllib.parse
+from hashlib import md5, sha256
+from typing import Mapping
+
+import aiohttp
+
+from api.types.ids import UserId
+
+logger = logging.getLogger(__name__)
+
+GOOGLE_ISSUER = "https://accounts.google.com"
+
+
+class OidcError(Exception):
+    pass
+
+
+@dataclasses.dataclass
+class OidcProvider:
+    name: str
+    client_id: str
+    client_secret: str
+    issuer: str | None = None  # endpoints are discovered from it if not set explicitly
+    authorization_endpoint: str | None = None
+    token_endpoint: str | None = None
+    userinfo_endpoint: str | None = None
+    scopes: list[str] = dataclasses.field(default_factory=lambda: ["openid"])
+    subject_claim: str = "sub"  # in the user info
+    # external subject -> local user, e.g. to keep the data of a user who used the bot before
+    linked_users: dict[str, UserId] = dataclasses.field(default_factory=dict)
+
+    async def discover(self) -> None:
+        if self.issuer is None or self.authorization_endpoint is not None:
+            return
+        url = self.issuer.rstrip("/") + "/.well-known/openid-configuration"
+        async with aiohttp.ClientSession() as session:
+            async with session.get(url) as resp:
+                resp.raise_for_status()
+                config = await resp.json()
+        self.authorization_endpoint = config["authorization_endpoint"]
+        self.token_endpoint = config["token_endpoint"]
+        self.userinfo_endpoint = config["userinfo_endpoint"]
+        logger.info(f"Discovered {self.name} OIDC endpoints from {url}")
+
+    def local_user_id(self, subject: str) -> UserId:
+        if subject in self.linked_users:
+            return self.linked_users[subject]
+        return md5(f"{self.name}:{subject}".encode("utf-8")).hexdigest()
+
+    def authorization_url(self, redirect_uri: str, state: str, code_challenge: str) -> str:
+        if self.authorization_endpoint is None:
+            raise OidcError(f"{self.name} endpoints are not discovered yet")
+        query = urllib.parse.urlencode(
+            {
+                "response_type": "code",
+                "client_id": self.client_id,
+                "redirect_uri": redirect_uri,
+                "scope": " ".join(self.scopes),
+                "state": state,
+                "code_challenge": code_challenge,
+                "code_challenge_method": "S256",
+            }
+        )
+        return f"{self.authorization_endpoint}?{query}"
+
+    async def fetch_subject(self, code: str, redirect_uri: str, code_verifier: str) -> str:
+        """Exchanges the authorization code for an access token to get the user info with"""
+        if self.token_endpoint is None or self.userinfo_endpoint is None:
+            raise OidcError(f"{self.name} endpoints are not discovered yet")
+        async with aiohttp.ClientSession(headers={"Accept": "application/json"}) as session:
+            token_request = {
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": redirect_uri,
+                "client_id": self.client_id,
+                "client_secret": self.client_secret,
+                "code_verifier": code_verifier,
+            }
+            async with session.post(self.token_endpoint, data=token_request) as resp:
+                token_response = await resp.json()
+            access_token = token_response.get("access_token")
+            if access_token is None:
+                raise OidcError(f"No access token from {self.name}: {token_response}")
+            auth_header = {"Authorization": f"Bearer {access_token}"}
+            async with session.get(self.userinfo_endpoint, headers=auth_header) as resp:
+                userinfo = await resp.json()
+        subject = userinfo.get(self.subject_claim)
+        if subject is None:
+            raise OidcError(f"No {self.subject_claim!r} in {self.name} user info")
+        return str(subject)
+
+
+def github(client_id: str, client_secret: str) -> OidcProvider:
+    """Plain OAuth2, without OIDC discovery"""
+    return OidcProvider(
+        name="github",
+        client_id=client_id,
+        client_secret=client_secret,
+        authorization_endpoint="https://github.com/login/oauth/authorize",
+        token_endpoint="https://github.com/login/oauth/access_token",
+        userinfo_endpoint="https://api.github.com/user",
+        scopes=["read:user"],
+        subject_claim="id",
+    )
+
+
+@dataclasses.dataclass
+class OidcConfig:
+    providers: list[OidcProvider]
+    redirect_uri: str  # public URL of the callback route
+    # frontend page finishing the login with the start param, as after logging in with the bot
+    post_login_url: str
+
+    def provider(self, name: str) -> OidcProvider | None:
+        return next((p for p in self.providers if p.name == name), None)
+
+
+def pkce_pair() -> tuple[str, str]:
+    """Code verifier and its S256 challenge"""
+    verifier = secrets.token_urlsafe(48)
+    challenge = base64.urlsafe_b64encode(sha256(verifier.encode("ascii")).digest())
+    return verifier, challenge.decode("ascii").rstrip("=")
+
+
+def parse_linked_users(value: str) -> dict[str, UserId]:
+    """Comma-separated subject=user_id pairs"""
+    pairs = (item.split("=", maxsplit=1) for item in value.split(",") if item.strip())
+    return {subject.strip(): user_id.strip() for subject, user_id in pairs}
+
+
+def oidc_config_from_env(env: Mapping[str, str]) -> OidcConfig | None:
+    """
+    OIDC_PROVIDERS=google,github,corp with OIDC_<NAME>_CLIENT_ID, OIDC_<NAME>_CLIENT_SECRET,
+    OIDC_<NAME>_ISSUER (except for Google and GitHub) and optional OIDC_<NAME>_LINKED_USERS
+    """
+    if not env.get("OIDC_PROVIDERS"):
+        return None
+    providers: list[OidcProvider] = []
+    for name in env["OIDC_PROVIDERS"].split(","):
+        prefix = f"OIDC_{name.upper()}_"
+        client_id = env[prefix + "CLIENT_ID"]
+        client_secret = env[prefix + "CLIENT_SECRET"]
+        if name == "github":
+            provider = github(client_id, client_secret)
+        else:
+            provider = OidcProvider(
+                name=name,
+                client_id=client_id,
+                client_secret=client_secret,
+                issuer=GOOGLE_ISSUER if name == "google" else env[prefix + "ISSUER"],
+                scopes=["openid", "email"] if name == "google" else ["openid"],
+            )
+        provider.linked_users = parse_linked_users(env.get(prefix + "LINKED_USERS", ""))
+        providers.append(provider)
+    return OidcConfig(
+        providers=providers,
+        redirect_uri=env["OIDC_REDIRECT_URI"],
+        post_login_url=env["OIDC_POST_LOGIN_URL"],
+    )
@@ -0,0 +1,70 @@
+"""Overdraft status of a pool: when its balance went negative and what it is going to cost"""
+
+import datetime
+from decimal import Decimal
+from typing import Sequence
+
+from api.types.api import OverdraftStatus
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+PROJECTION_HORIZON = datetime.timedelta(days=30)
+
+
+def compute_overdraft_status(
+    pool: StoredMoneyPool,
+    transactions: Sequence[Transaction],
+    now: datetime.datetime,
+) -> OverdraftStatus | None:
+    """Transactions are the pool's ones, in any order; None if the pool has no overdraft"""
+    facility = pool.overdraft
+    if facility is None:
+        return None
+    currency = facility.limit.currency
+    balance = next(
+        (ms for ms in pool.balance if ms.currency == currency),
+        MoneySum(amount=Decimal(0), currency=currency),
+    )
+
+    # walking back in time to find when the balance went negative
+    overdrawn_since: datetime.datetime | None = None
+    if balance.amount < 0:
+        amount = balance.amount
+        overdrawn_since = now
+        for t in sorted(transactions, key=lambda t: t.timestamp.timestamp(), reverse=True):
+            if t.sum.currency != currency:
+                continue
+            if amount >= 0:
+                break
+            overdrawn_since = t.timestamp
+            amount -= t.sum.amount
+
+    interest_free_until: datetime.datetime | None = None
+    projected_until = now + PROJECTION_HORIZON
+    projected_fee = Decimal(0)
+    if overdrawn_since is not None:
+        interest_free_until = overdrawn_since + datetime.timedelta(
+            days=facility.interest_free_days
+        )
+        charged_seconds = max(
+            projected_until.timestamp() - max(interest_free_until.timestamp(), now.timestamp()),
+            0,
+        )
+        projected_fee = (
+            -balance.amount
+            * Decimal(facility.annual_interest_rate)
+            * Decimal(charged_seconds / datetime.timedelta(days=365).total_seconds())
+        )
+
+    return OverdraftStatus(
+        pool_id=pool.id,
+        facility=facility,
+        balance=balance,
+        in_overdraft=balance.amount < 0,
+        available=MoneySum(amount=balance.amount + facility.limit.amount, currency=currency),
+        overdrawn_since=overdrawn_since,
+        interest_free_until=interest_free_until,
+        projected_fee=MoneySum(amount=projected_fee, currency=currency),
+        projected_until=projected_until,
+    )
@@ -0,0 +1,65 @@
+import secrets
+from typing import MutableMapping
+
+from cachetools import TTLCache  # type: ignore
+from cryptography.fernet import Fernet, InvalidToken
+
+from api.types.ids import UserId
+from api.types.sensitive import PRIVACY_PREFIX
+from api.types.transaction import Transaction
+
+ENCRYPTED_PREFIX = PRIVACY_PREFIX
+MASKED_DESCRIPTION = "•••"
+
+
+class DescriptionPrivacy:
+    """
+    Transaction descriptions are stored encrypted and returned masked, unless the request carries
+    a sensitive view token issued to the same user within the last few minutes, after they proved
+    their identity again
+    """
+
+    def __init__(self, key: bytes, sensitive_view_ttl_sec: float = 5 * 60) -> None:
+        self.fernet = Fernet(key)
+        self.sensitive_view_ttl_sec = sensitive_view_ttl_sec
+        self._user_id_by_sensitive_view_token: MutableMapping[str, UserId] = TTLCache(
+            maxsize=4096, ttl=sensitive_view_ttl_sec
+        )
+
+    def encrypt(self, description: str) -> str:
+        if description.startswith(ENCRYPTED_PREFIX):
+            return description
+        return ENCRYPTED_PREFIX + self.fernet.encrypt(description.encode("utf-8")).decode("ascii")
+
+    def decrypt(self, stored: str) -> str:
+        if not stored.startswith(ENCRYPTED_PREFIX):
+            return stored  # stored before privacy mode was enabled
+        try:
+            return self.fernet.decrypt(stored.removeprefix(ENCRYPTED_PREFIX)).decode("utf-8")
+        except InvalidToken:
+            return MASKED_DESCRIPTION
+
+    def issue_sensitive_view_token(self, user_id: UserId) -> str:
+        token = secrets.token_urlsafe(nbytes=32)
+        self._user_id_by_sensitive_view_token[token] = user_id
+        return token
+
+    def can_view(self, user_id: UserId, token: str | None) -> bool:
+        if token is None:
+            return False
+        return self._user_id_by_sensitive_view_token.get(token) == user_id
+
+    def protect(self, transaction: Transaction) -> None:
+        transaction.description = self.encrypt(transaction.description)
+        if transaction.source is not None:
+            # bank records include the description and often more
+            transaction.source.raw = self.encrypt(transaction.source.raw)
+
+    def present(self, transaction: Transaction, visible: bool) -> None:
+        transaction.description = (
+            self.decrypt(transaction.description) if visible else MASKED_DESCRIPTION
+        )
+        if transaction.source is not None:
+            transaction.source.raw = (
+                self.decrypt(transaction.source.raw) if visible else MASKED_DESCRIPTION
+            )
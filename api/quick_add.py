@@ -0,0 +1,98 @@
+"""
+One-line transactions for clients that can only send a string, e.g. IFTTT applets and phone
+shortcuts: "14.99 USD groceries lidl", "$5 coffee", "+1200 salary #work"
+"""
+
+import dataclasses
+import re
+from decimal import Decimal
+from typing import Collection
+
+from api.formatting import SYMBOLS
+from api.types.currency import parse_currency
+from api.types.currency_iso4217 import CurrencyISO4217
+
+AMOUNT_PATTERN = re.compile(
+    r"^(?P<sign>[+-]?)(?P<prefix>[^\d\s+-]*)(?P<number>\d+(?:[.,]\d+)?)(?P<suffix>[^\d\s]*)$"
+)
+
+# symbols shared by several currencies (e.g. ¥) are ambiguous and not recognized
+SYMBOL_CODES = {
+    symbol: code
+    for code, symbol in SYMBOLS.items()
+    if list(SYMBOLS.values()).count(symbol) == 1
+}
+
+
+@dataclasses.dataclass(frozen=True)
+class QuickEntry:
+    amount: Decimal  # negative for expenses
+    currency: CurrencyISO4217 | None  # None for the pool's
+    tags: list[str]
+    description: str
+
+
+def currency_of(word: str, attached: bool) -> CurrencyISO4217 | None:
+    """
+    Symbol or code attached to the amount, or a separate code next to it; lowercase separate
+    words are only taken for the common currencies, so that e.g. "all" stays a word
+    """
+    if word in SYMBOL_CODES:
+        return parse_currency(SYMBOL_CODES[word])
+    if len(word) != 3 or not word.isalpha():
+        return None
+    if not attached and not word.isupper() and word.upper() not in SYMBOLS:
+        return None
+    try:
+        return parse_currency(word)
+    except ValueError:
+        return None
+
+
+def parse_quick_entry(text: str, categories: Collection[str]) -> QuickEntry:
+    """
+    The first number is the amount, spent unless it starts with "+"; "#words" are tags, otherwise
+    the first word matching one of the user's categories is; the rest is the description
+    """
+    words = text.split()
+    amount: Decimal | None = None
+    currency: CurrencyISO4217 | None = None
+    rest: list[str] = []
+    for idx, word in enumerate(words):
+        match = AMOUNT_PATTERN.match(word) if amount is None else None
+        if match is None or (match["prefix"] and match["suffix"]):
+            rest.append(word)
+            continue
+        affix = match["prefix"] or match["suffix"]
+        if affix:
+            currency = currency_of(affix, attached=True)
+            if currency is None:
+                rest.append(word)  # e.g. "7eleven"
+                continue
+        number = Decimal(match["number"].replace(",", "."))
+        amount = number if match["sign"] == "+" else -number
+        if currency is None and rest:
+            currency = currency_of(rest[-1], attached=False)
+            if currency is not None:
+                rest.pop()
+        if currency is None and idx + 1 < len(words):
+            currency = currency_of(words[idx + 1], attached=False)
+            if currency is not None:
+                words[idx + 1] = ""  # consumed
+    rest = [w for w in rest if w]
+    if amount is None:
+        raise ValueError("No amount found")
+    if amount.is_zero():
+        raise ValueError("Amount must not be zero")
+
+    tags = [w.removeprefix("#") for w in rest if w.startswith("#") and len(w) > 1]
+    rest = [w for w in rest if not w.startswith("#")]
+    if not tags:
+        by_key = {c.casefold(): c for c in categories}
+        for idx, word in enumerate(rest):
+            if word.casefold() in by_key:
+                tags = [by_key[word.casefold()]]
+                del rest[idx]
+                break
+    description = " ".join(rest) or " ".join(tags)
+    return QuickEntry(amount=amount, currency=currency, tags=tags, description=description)
@@ -0,0 +1,57 @@
+"""Recomputing derived data from the source-of-truth transactions, for recovery after bugs"""
+
+import datetime
+from decimal import Decimal
+from typing import Iterable, Sequence
+
+from api.types.currency import Currency
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+
+def rebuild_pool_balance(
+    pool: MoneyPool, transactions: Iterable[Transaction]
+) -> tuple[list[MoneySum], list[MoneySum]]:
+    """
+    Returns balance and initial balance. If the pool has no initial balance recorded,
+    the current balance is trusted and the initial one is backfilled from it
+    """
+    totals: dict[Currency, Decimal] = {}
+    for t in transactions:
+        if not t.status.is_counted:
+            continue
+        totals[t.sum.currency] = totals.get(t.sum.currency, Decimal(0)) + t.sum.amount
+
+    if pool.initial_balance is None:
+        initial_balance = [
+            MoneySum(amount=s.amount - totals.get(s.currency, Decimal(0)), currency=s.currency)
+            for s in pool.balance
+        ]
+        return [s.model_copy() for s in pool.balance], initial_balance
+
+    balance = [
+        MoneySum(amount=s.amount + totals.pop(s.currency, Decimal(0)), currency=s.currency)
+        for s in pool.initial_balance
+    ]
+    # transactions can't be in a currency missing from the pool, but just in case
+    balance.extend(MoneySum(amount=amount, currency=c) for c, amount in totals.items())
+    return balance, [s.model_copy() for s in pool.initial_balance]
+
+
+def pool_balance_at(
+    pool: MoneyPool, transactions: Sequence[Transaction], moment: datetime.datetime
+) -> list[MoneySum] | None:
+    """
+    Balance right before the moment, from the initial balance (backfilled if missing). Pools
+    don't record their creation time, so one without transactions before the moment is taken
+    to not exist yet and None is returned
+    """
+    before = [t for t in transactions if t.timestamp < moment]
+    if not before:
+        return None
+    _, initial_balance = rebuild_pool_balance(pool, transactions)
+    balance, _ = rebuild_pool_balance(
+        pool.model_copy(update={"initial_balance": initial_balance}), before
+    )
+    return balance
@@ -0,0 +1,318 @@
+"""Aggregations over transactions and pool balances used by the report routes"""
+
+import collections
+import datetime
+from decimal import Decimal
+from typing import Iterable, Sequence
+
+from api.exchange_rates import ExchangeRates, RateUnavailable
+from api.types.api import (
+    CashFlowMonth,
+    CashFlowReportResponse,
+    CategorySpending,
+    CategorySpendingReportResponse,
+    ForeignSpending,
+    PayeeSpending,
+    PayeeSpendingReportResponse,
+    ReportTagNetTotal,
+)
+from api.types.currency import Currency
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.net_worth import NetWorthSnapshot
+from api.types.payee import StoredPayee
+from api.types.settings import ReportingPeriod
+from api.types.transaction import Transaction, TransactionKind
+
+DIFFUSE_CATEGORY = "diffuse"
+
+
+async def sum_transactions(
+    transactions: Iterable[Transaction], exchange_rates: ExchangeRates, target_currency: Currency
+) -> MoneySum:
+    total, unconverted = await sum_transactions_partially(
+        transactions, exchange_rates, target_currency
+    )
+    if unconverted:
+        raise RateUnavailable(unconverted[0].currency, target_currency)
+    return total
+
+
+async def sum_transactions_partially(
+    transactions: Iterable[Transaction], exchange_rates: ExchangeRates, target_currency: Currency
+) -> tuple[MoneySum, list[MoneySum]]:
+    """
+    Total of the transactions that can be converted to the target currency (at the rates on their
+    dates, where the history is kept) and per-currency sums of the ones that can't because
+    the exchange rate is unavailable
+    """
+    total_amt = 0.0
+    unconverted: dict[Currency, Decimal] = {}
+    rates: dict[tuple[Currency, datetime.date], float] = {}
+    for t in transactions:
+        if target_currency.code == "EUR" and t.amount_eur is not None:
+            total_amt += t.amount_eur
+            continue
+        key = (t.sum.currency, t.timestamp.date())
+        if key not in rates:
+            try:
+                rate = await exchange_rates.get_rate_on(
+                    base=t.sum.currency, target=target_currency, on=t.timestamp.date()
+                )
+            except RateUnavailable:
+                currency = t.sum.currency
+                unconverted[currency] = unconverted.get(currency, Decimal(0)) + t.sum.amount
+                continue
+            rates[key] = rate.rate
+        total_amt += float(t.sum.amount) * rates[key]
+    total = MoneySum(
+        amount=Decimal(total_amt),
+        currency=target_currency,
+    )
+    return total, [MoneySum(amount=amount, currency=c) for c, amount in unconverted.items()]
+
+
+async def tag_net_totals(
+    transactions: Sequence[Transaction], exchange_rates: ExchangeRates, target_currency: Currency
+) -> list[ReportTagNetTotal]:
+    totals: list[ReportTagNetTotal] = []
+    counted = [t for t in transactions if t.status.is_counted]
+    for tag, ts in transactions_per_tag(counted).items():
+        total, unconverted = await sum_transactions_partially(ts, exchange_rates, target_currency)
+        totals.append(ReportTagNetTotal(tag=tag, total=total, unconverted=unconverted))
+    return sorted(totals, key=lambda rtnt: rtnt.total.amount)
+
+
+def transactions_per_tag(transactions: Sequence[Transaction]):
+    """Split transactions count towards their split categories instead of their tags"""
+    res: dict[str | None, list[Transaction]] = collections.defaultdict(list)
+    for t in transactions:
+        for portion in t.split_portions():
+            for tag in portion.tags:
+                res[tag].append(portion)
+            if not portion.tags:
+                res[None].append(portion)
+    return res
+
+
+def spending_per_category(expenses: Sequence[Transaction]) -> dict[str | None, list[Transaction]]:
+    res = transactions_per_tag([t for t in expenses if not t.is_diffuse])
+    diffuse = [t for t in expenses if t.is_diffuse]
+    if diffuse:
+        res[DIFFUSE_CATEGORY] = diffuse
+    return res
+
+
+async def spending_by_category(
+    transactions: Sequence[Transaction],
+    exchange_rates: ExchangeRates,
+    start: datetime.datetime,
+    end: datetime.datetime,
+    target_currency: Currency,
+) -> CategorySpendingReportResponse:
+    """
+    Spending per category (= tag, None for untagged) in the [start, end) period, compared to the
+    previous period of the same length. Transactions must cover both periods. A transaction with
+    several tags counts towards each of them, so fractions may add up to more than 1, and a split
+    one counts its portions towards their categories. Diffuse spending is a category of its own.
+    """
+    previous_start = start - (end - start)
+
+    def expenses(from_: datetime.datetime, to: datetime.datetime) -> list[Transaction]:
+        return [
+            t
+            for t in transactions
+            if t.sum.amount < 0
+            and t.transfer_id is None
+            and t.status.is_counted
+            and from_.timestamp() <= t.timestamp.timestamp() < to.timestamp()
+        ]
+
+    async def spent(ts: Iterable[Transaction]) -> MoneySum:
+        total = await sum_transactions(ts, exchange_rates, target_currency)
+        return MoneySum(amount=-total.amount, currency=target_currency)
+
+    current_expenses = expenses(start, end)
+    previous_expenses = expenses(previous_start, start)
+    current_per_tag = spending_per_category(current_expenses)
+    previous_per_tag = spending_per_category(previous_expenses)
+
+    total_spent = await spent(current_expenses)
+    categories: list[CategorySpending] = []
+    for category in current_per_tag.keys() | previous_per_tag.keys():
+        category_spent = await spent(current_per_tag.get(category, []))
+        previous_spent = await spent(previous_per_tag.get(category, []))
+        categories.append(
+            CategorySpending(
+                category=category,
+                spent=category_spent,
+                fraction=(
+                    float(category_spent.amount / total_spent.amount)
+                    if total_spent.amount
+                    else 0.0
+                ),
+                previous_spent=previous_spent,
+                change=(
+                    float((category_spent.amount - previous_spent.amount) / previous_spent.amount)
+                    if previous_spent.amount
+                    else None
+                ),
+            )
+        )
+    categories.sort(key=lambda c: (-c.spent.amount, c.category is not None, c.category or ""))
+
+    paid_abroad: dict[Currency, list[Transaction]] = collections.defaultdict(list)
+    for t in current_expenses:
+        original = t.original_sum()
+        if original is not None:
+            paid_abroad[original.currency].append(t)
+    foreign_spending = [
+        ForeignSpending(
+            paid=MoneySum(
+                amount=-sum(t.original_amount or Decimal(0) for t in ts), currency=currency
+            ),
+            charged=await spent(ts),
+        )
+        for currency, ts in sorted(paid_abroad.items(), key=lambda item: item[0].code)
+    ]
+
+    return CategorySpendingReportResponse(
+        previous_start=previous_start,
+        previous_end=start,
+        spent=total_spent,
+        previous_spent=await spent(previous_expenses),
+        categories=categories,
+        foreign_spending=foreign_spending,
+    )
+
+
+async def spending_by_payee(
+    transactions: Sequence[Transaction],
+    payees: Sequence[StoredPayee],
+    exchange_rates: ExchangeRates,
+    start: datetime.datetime,
+    end: datetime.datetime,
+    target_currency: Currency,
+) -> PayeeSpendingReportResponse:
+    """
+    Spending per payee in the [start, end) period, the largest first; transactions of a deleted
+    payee are reported under its id with no name
+    """
+    names = {p.id: p.name for p in payees}
+    expenses = [
+        t
+        for t in transactions
+        if t.sum.amount < 0
+        and t.transfer_id is None
+        and t.status.is_counted
+        and start.timestamp() <= t.timestamp.timestamp() < end.timestamp()
+    ]
+    per_payee: dict[str | None, list[Transaction]] = collections.defaultdict(list)
+    for t in expenses:
+        per_payee[t.payee_id].append(t)
+
+    async def spent(ts: Iterable[Transaction]) -> MoneySum:
+        total = await sum_transactions(ts, exchange_rates, target_currency)
+        return MoneySum(amount=-total.amount, currency=target_currency)
+
+    total_spent = await spent(expenses)
+    report: list[PayeeSpending] = []
+    for payee_id, ts in per_payee.items():
+        payee_spent = await spent(ts)
+        report.append(
+            PayeeSpending(
+                payee_id=payee_id,
+                name=names.get(payee_id) if payee_id is not None else None,
+                spent=payee_spent,
+                fraction=(
+                    float(payee_spent.amount / total_spent.amount) if total_spent.amount else 0.0
+                ),
+                transaction_count=len(ts),
+            )
+        )
+    report.sort(key=lambda p: (-p.spent.amount, p.payee_id is None, p.name or ""))
+    return PayeeSpendingReportResponse(spent=total_spent, payees=report)
+
+
+async def cash_flow(
+    transactions: Sequence[Transaction],
+    exchange_rates: ExchangeRates,
+    start: datetime.datetime,
+    end: datetime.datetime,
+    target_currency: Currency,
+    tz: datetime.tzinfo = datetime.UTC,
+    reporting_period: ReportingPeriod | None = None,  # calendar months by default
+) -> CashFlowReportResponse:
+    """
+    Inflows, outflows and net per reporting period (in the timezone) in the [start, end) period,
+    the first and the last ones being partial; transfers between the user's pools are neither
+    """
+    in_period = [
+        t
+        for t in transactions
+        if t.kind is not TransactionKind.TRANSFER
+        and t.status.is_counted
+        and start.timestamp() <= t.timestamp.timestamp() < end.timestamp()
+    ]
+
+    async def flows(ts: Sequence[Transaction]) -> tuple[MoneySum, MoneySum, MoneySum]:
+        inflow = await sum_transactions(
+            [t for t in ts if t.kind is TransactionKind.INCOME], exchange_rates, target_currency
+        )
+        outflow = await sum_transactions(
+            [t for t in ts if t.kind is TransactionKind.EXPENSE], exchange_rates, target_currency
+        )
+        return (
+            inflow,
+            MoneySum(amount=-outflow.amount, currency=target_currency),
+            MoneySum(amount=inflow.amount + outflow.amount, currency=target_currency),
+        )
+
+    months: list[CashFlowMonth] = []
+    periods = (reporting_period or ReportingPeriod()).covering(start, end, tz)
+    for period_start, period_end in periods:
+        inflow, outflow, net = await flows(
+            [
+                t
+                for t in in_period
+                if period_start.timestamp() <= t.timestamp.timestamp() < period_end.timestamp()
+            ]
+        )
+        months.append(
+            CashFlowMonth(
+                month=period_start.strftime("%Y-%m"),
+                start=period_start,
+                end=period_end,
+                inflow=inflow,
+                outflow=outflow,
+                net=net,
+            )
+        )
+
+    inflow, outflow, net = await flows(in_period)
+    return CashFlowReportResponse(inflow=inflow, outflow=outflow, net=net, months=months)
+
+
+async def net_worth(
+    pools: Iterable[MoneyPool],
+    exchange_rates: ExchangeRates,
+    target_currency: Currency,
+    date: datetime.date,
+) -> NetWorthSnapshot:
+    """Pool balances summed per currency and converted at the current rates where available"""
+    per_currency: dict[Currency, Decimal] = {}
+    for pool in pools:
+        for sum_ in pool.balance:
+            per_currency[sum_.currency] = per_currency.get(sum_.currency, Decimal(0)) + sum_.amount
+    balance = [MoneySum(amount=amount, currency=c) for c, amount in per_currency.items()]
+    total = MoneySum(amount=Decimal(0), currency=target_currency)
+    unconverted: list[MoneySum] = []
+    for sum_ in balance:
+        try:
+            rate = await exchange_rates.get_rate(base=sum_.currency, target=target_currency)
+        except RateUnavailable:
+            unconverted.append(sum_)
+            continue
+        total.amount += sum_.amount * Decimal(rate.rate)
+    total.round_for_currency()
+    return NetWorthSnapshot(date=date, balance=balance, total=total, unconverted=unconverted)
@@ -0,0 +1,32 @@
+"""Applying the user's categorization rules to transactions"""
+
+from typing import Sequence
+
+from api.types.api import TransactionUpdate
+from api.types.rule import CategorizationRule
+from api.types.transaction import Transaction
+
+
+def categorize(
+    rules: Sequence[CategorizationRule], transaction: Transaction, description: str
+) -> TransactionUpdate | None:
+    """
+    Changes made by the matching rules, None if there are none; rules apply in order, the first
+    matching one sets the merchant and each adds its category
+    """
+    merchant: str | None = None
+    tags = list(transaction.tags)
+    for rule in rules:
+        if not rule.matches(transaction, description):
+            continue
+        if merchant is None and rule.merchant is not None:
+            merchant = rule.merchant
+        if rule.category is not None and rule.category not in tags:
+            tags.append(rule.category)
+    update = TransactionUpdate(
+        description=merchant if merchant is not None and merchant != description else None,
+        tags=tags if tags != transaction.tags else None,
+    )
+    if update.description is None and update.tags is None:
+        return None
+    return update
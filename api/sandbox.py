@@ -0,0 +1,142 @@
+"""
+Sandbox API for docs playground: same routes backed by a throwaway in-memory storage with sample
+data, no auth, rate-limited per client and reset periodically. Mounted under /sandbox.
+
+Starlette doesn't run lifespans of mounted apps, so nothing from create_app's lifespan runs for
+the sandbox: its storage and auth need no initialization, the exchange rates are shared with and
+initialized by the main app, and the periodic jobs (allowances, digests etc.) are not wanted here
+"""
+
+import collections
+import datetime
+import logging
+import time
+from decimal import Decimal
+from typing import MutableMapping
+
+from cachetools import TTLCache  # type: ignore
+from fastapi import FastAPI, HTTPException, Request
+
+from api.app import create_app
+from api.auth import Auth
+from api.exchange_rates import ExchangeRates
+from api.iso4217 import CURRENCIES
+from api.storage import InmemoryStorage
+from api.types.ids import UserId
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+logger = logging.getLogger(__name__)
+
+SANDBOX_MOUNT_PATH = "/sandbox"
+SANDBOX_USER_ID = "sandbox"
+
+
+async def seed_sample_data(storage: InmemoryStorage) -> None:
+    now = datetime.datetime.now(tz=datetime.UTC)
+    card = await storage.add_pool(
+        SANDBOX_USER_ID,
+        MoneyPool(
+            display_name="Debit card",
+            balance=[MoneySum(amount=Decimal(1500), currency=CURRENCIES["EUR"])],
+            display_color="#3b82f6",
+        ),
+    )
+    cash = await storage.add_pool(
+        SANDBOX_USER_ID,
+        MoneyPool(
+            display_name="Cash",
+            balance=[
+                MoneySum(amount=Decimal(200), currency=CURRENCIES["EUR"]),
+                MoneySum(amount=Decimal(50), currency=CURRENCIES["USD"]),
+            ],
+        ),
+    )
+    samples = [
+        (card.id, -54.3, "EUR", "Groceries", ["food"], 1),
+        (card.id, -12.0, "EUR", "Cinema", ["fun"], 3),
+        (card.id, 2100.0, "EUR", "Salary", ["salary"], 5),
+        (card.id, -800.0, "EUR", "Rent", ["housing"], 6),
+        (cash.id, -4.5, "EUR", "Coffee", ["food"], 2),
+        (cash.id, -20.0, "USD", "Souvenirs", ["travel"], 10),
+    ]
+    for pool_id, amount, currency, description, tags, days_ago in samples:
+        await storage.add_transaction(
+            SANDBOX_USER_ID,
+            Transaction(
+                sum=MoneySum(amount=Decimal(amount), currency=CURRENCIES[currency]),
+                pool_id=pool_id,
+                description=description,
+                timestamp=now - datetime.timedelta(days=days_ago),
+                amount_eur=amount if currency == "EUR" else None,
+                tags=tags,
+            ),
+        )
+
+
+class SandboxStorage(InmemoryStorage):
+    def __init__(self, reset_interval_sec: float) -> None:
+        super().__init__()
+        self.reset_interval_sec = reset_interval_sec
+        self._last_reset: float | None = None
+
+    async def reset_if_stale(self) -> None:
+        now = time.time()
+        if self._last_reset is not None and now - self._last_reset < self.reset_interval_sec:
+            return
+        logger.info("Resetting sandbox storage")
+        self.clear()
+        await seed_sample_data(self)
+        self._last_reset = now
+
+
+class RateLimiter:
+    """
+    Sliding window limiter, in-memory, per key; keys without requests in the window expire, and
+    the least recent ones are dropped beyond max_keys
+    """
+
+    def __init__(self, max_requests: int, window_sec: float, max_keys: int = 10_000) -> None:
+        self.max_requests = max_requests
+        self.window_sec = window_sec
+        self._request_times: MutableMapping[str, collections.deque[float]] = TTLCache(
+            maxsize=max_keys, ttl=window_sec
+        )
+
+    def allow(self, key: str) -> bool:
+        now = time.time()
+        times = self._request_times.get(key, collections.deque())
+        while times and now - times[0] > self.window_sec:
+            times.popleft()
+        if len(times) >= self.max_requests:
+            return False
+        times.append(now)
+        self._request_times[key] = times  # restarts the expiration
+        return True
+
+
+class SandboxAuth(Auth):
+    def __init__(self, storage: SandboxStorage, requests_per_minute: int) -> None:
+        self.storage = storage
+        self.rate_limiter = RateLimiter(max_requests=requests_per_minute, window_sec=60)
+
+    async def authorize_request(self, request: Request) -> UserId:
+        client = request.client.host if request.client else "unknown"
+        if not self.rate_limiter.allow(client):
+            raise HTTPException(429, detail="Too many sandbox requests, try again later")
+        await self.storage.reset_if_stale()
+        return SANDBOX_USER_ID
+
+
+def create_sandbox_app(
+    exchange_rates: ExchangeRates,
+    reset_interval_sec: float = 15 * 60,
+    requests_per_minute: int = 60,
+) -> FastAPI:
+    storage = SandboxStorage(reset_interval_sec=reset_interval_sec)
+    return create_app(
+        storage=storage,
+        auth=SandboxAuth(storage, requests_per_minute=requests_per_minute),
+        exchange_rates=exchange_rates,
+    )
@@ -0,0 +1,649 @@
+"""
+Domain logic shared by the interfaces (HTTP API, bot, CLI): validation, currency conversion,
+period locks, undo log and domain events; interfaces handle authorization and presentation
+"""
+
+import copy
+import datetime
+import logging
+import uuid
+from decimal import Decimal
+
+from api.events import EventBus
+from api.exchange_rates import ExchangeRates, RateUnavailable
+from api.historical_rates import HistoricalExchangeRates
+from api.privacy import DescriptionPrivacy
+from api.rules import categorize
+from api.storage import Storage, TransactionOrder
+from api.types.api import IssueSeverity, TransactionEdit, TransactionUpdate, ValidationIssue
+from api.types.currency import Currency, parse_currency
+from api.types.events import (
+    PoolBalanceChanged,
+    PoolCreated,
+    TransactionCreated,
+    TransactionDeleted,
+    TransactionUpdated,
+)
+from api.types.export import UserDataExport
+from api.types.ids import MoneyPoolId, PayeeId, UserId
+from api.types.money_pool import MoneyPool, StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.operation import Operation, OperationKind
+from api.types.redenomination import PoolRedenomination, TransactionConversion
+from api.types.rule import CategorizationRule
+from api.types.transaction import (
+    TRANSFER_TAG,
+    StoredTransaction,
+    Transaction,
+    TransactionFilter,
+    TransactionSplit,
+    TransactionStatus,
+)
+from api.validation import POSSIBLE_DUPLICATE_WINDOW, RECENT_WINDOW, validate_transaction
+
+logger = logging.getLogger(__name__)
+
+MAX_TRANSACTIONS_TO_LOAD = 100_000
+
+DEFAULT_UNDO_WINDOW = datetime.timedelta(minutes=5)
+
+DEFAULT_DUPLICATE_WINDOW = datetime.timedelta(minutes=2)
+
+EUR = parse_currency("EUR")
+
+
+class ServiceError(Exception):
+    status_code = 400  # for HTTP interfaces
+
+
+class InvalidTransaction(ServiceError):
+    def __init__(self, issues: list[ValidationIssue]) -> None:
+        self.issues = issues
+        super().__init__("; ".join(i.message for i in issues))
+
+
+class PeriodLocked(ServiceError):
+    status_code = 409
+
+
+class TooManyTransactions(ServiceError):
+    pass
+
+
+async def coerce_to_pool(
+    transaction: Transaction, pool: MoneyPool, exchange_rates: ExchangeRates
+) -> None:
+    if transaction.sum.currency in [sum.currency for sum in pool.balance]:
+        return
+    transaction.original_currency = transaction.sum.currency
+    transaction.original_amount = transaction.sum.amount
+    rate = await exchange_rates.get_rate(
+        base=transaction.original_currency,
+        target=pool.balance[0].currency,
+    )
+    original_amount = transaction.sum.amount
+    transaction.sum = MoneySum(
+        amount=Decimal(float(transaction.sum.amount) * rate.rate),
+        currency=rate.target,
+    )
+    convert_splits(transaction.splits, original_amount, transaction.sum)
+
+
+def convert_splits(
+    splits: list[TransactionSplit], original_amount: Decimal, converted: MoneySum
+) -> None:
+    """Proportionally, the rounding remainder goes to the last split"""
+    if not splits:
+        return
+    for split in splits:
+        split.amount = round(
+            converted.amount * split.amount / original_amount,
+            ndigits=converted.currency.precision,
+        )
+    converted_amount = sum(split.amount for split in splits[:-1])
+    splits[-1].amount = converted.amount - converted_amount
+
+
+def transfer_legs(
+    from_pool: StoredMoneyPool,
+    to_pool: StoredMoneyPool,
+    sum_: MoneySum,
+    description: str,
+    timestamp: datetime.datetime,
+    tags: list[str],
+    received: MoneySum | None = None,
+) -> list[Transaction]:
+    """
+    Debit and credit transactions linked with a common transfer id; the credit one is for the
+    received sum if it's given, e.g. for pools in different currencies
+    """
+    if sum_.amount <= 0:
+        raise ServiceError("Transfer amount must be positive")
+    transfer_id = uuid.uuid4().hex
+    descr_suffix = f" {description}" if description else ""
+    return [
+        Transaction(
+            sum=MoneySum(amount=-sum_.amount, currency=sum_.currency),
+            pool_id=from_pool.id,
+            description=f"Transfer {sum_} to {to_pool.display_name}" + descr_suffix,
+            timestamp=timestamp,
+            tags=[TRANSFER_TAG, *tags],
+            transfer_id=transfer_id,
+        ),
+        Transaction(
+            sum=received if received is not None else sum_,
+            pool_id=to_pool.id,
+            description=f"Transfer {sum_} from {from_pool.display_name}" + descr_suffix,
+            timestamp=timestamp,
+            tags=[TRANSFER_TAG, *tags],
+            transfer_id=transfer_id,
+        ),
+    ]
+
+
+def redenominated_sums(
+    sums: list[MoneySum],
+    from_currency: Currency,
+    to_currency: Currency,
+    moved: Decimal,
+    remaining: Decimal | None,
+) -> list[MoneySum]:
+    """
+    Sums with the moved amount added to the to-currency one, which takes the from-currency one's
+    place if there's none yet; the from-currency sum is kept with the remaining amount, if any
+    """
+    has_target = any(s.currency == to_currency for s in sums)
+    result: list[MoneySum] = []
+    for s in sums:
+        if s.currency == to_currency:
+            result.append(MoneySum(amount=s.amount + moved, currency=to_currency))
+        elif s.currency == from_currency:
+            if not has_target:
+                result.append(MoneySum(amount=moved, currency=to_currency))
+                has_target = True
+            if remaining is not None:
+                result.append(MoneySum(amount=remaining, currency=from_currency))
+        else:
+            result.append(s.model_copy())
+    if not has_target:
+        result.append(MoneySum(amount=moved, currency=to_currency))
+    return result
+
+
+class ExpenseService:
+    def __init__(
+        self,
+        storage: Storage,
+        exchange_rates: ExchangeRates,
+        events: EventBus,
+        privacy: DescriptionPrivacy | None,
+        undo_window: datetime.timedelta,
+        duplicate_window: datetime.timedelta,
+    ) -> None:
+        self.storage = storage
+        self.exchange_rates = exchange_rates
+        self.events = events
+        self.privacy = privacy
+        self.undo_window = undo_window
+        # same pool, sum and description within the window is considered a duplicate, zero disables
+        self.duplicate_window = duplicate_window
+
+    def protect_description(self, transaction: Transaction) -> None:
+        if self.privacy is not None:
+            self.privacy.protect(transaction)
+
+    def present_transactions(
+        self, transactions: list[StoredTransaction], visible: bool
+    ) -> list[StoredTransaction]:
+        if self.privacy is not None:
+            for t in transactions:
+                self.privacy.present(t, visible)
+        return transactions
+
+    def plain_description(self, transaction: Transaction) -> str:
+        if self.privacy is not None:
+            return self.privacy.decrypt(transaction.description)
+        return transaction.description
+
+    async def ensure_period_unlocked(
+        self, user_id: UserId, pool_id: MoneyPoolId, timestamp: datetime.datetime
+    ) -> None:
+        for r in await self.storage.load_reconciliations(user_id, pool_id=pool_id):
+            if r.locks(pool_id, timestamp):
+                raise PeriodLocked(f"Period is locked by reconciliation {r.id}")
+        for mc in await self.storage.load_month_closes(user_id):
+            if mc.locks(timestamp):
+                raise PeriodLocked(f"Month {mc.key} is closed")
+
+    async def prepare_new_transaction(
+        self, user_id: UserId, transaction: Transaction
+    ) -> list[ValidationIssue]:
+        """
+        Validates new transaction, converts it to the pool's currency, fills amount_eur and
+        schedules it if it's future-dated; returns non-blocking validation issues
+        """
+        transaction.version = 0  # new transactions start from the first version, whatever is sent
+        money_pool = await self.storage.load_pool(user_id=user_id, pool_id=transaction.pool_id)
+        recent: list[StoredTransaction] = []
+        if money_pool is not None:
+            recent = await self.storage.load_transactions(
+                user_id,
+                filter=TransactionFilter(
+                    min_timestamp=transaction.timestamp - RECENT_WINDOW,
+                    max_timestamp=transaction.timestamp + POSSIBLE_DUPLICATE_WINDOW,
+                    pool_ids=[transaction.pool_id],
+                ),
+                offset=0,
+                count=MAX_TRANSACTIONS_TO_LOAD,
+                order=TransactionOrder.LATEST,
+            )
+        issues = validate_transaction(transaction, money_pool, recent)
+        if (
+            transaction.payee_id is not None
+            and await self.storage.load_payee(user_id, transaction.payee_id) is None
+        ):
+            issues.append(
+                ValidationIssue(
+                    code="unknown_payee",
+                    severity=IssueSeverity.ERROR,
+                    message="Transaction is attributed to non-existent payee",
+                )
+            )
+        errors = [i for i in issues if i.severity is IssueSeverity.ERROR]
+        if errors or money_pool is None:
+            raise InvalidTransaction(errors)
+        await self.ensure_period_unlocked(user_id, transaction.pool_id, transaction.timestamp)
+        try:
+            to_eur = await self.exchange_rates.get_rate(transaction.sum.currency, EUR)
+            transaction.amount_eur = float(transaction.sum.amount) * to_eur.rate
+        except RateUnavailable:
+            # reports fall back to converting the sum at the report time
+            transaction.amount_eur = None
+        await coerce_to_pool(transaction, money_pool, self.exchange_rates)
+        if transaction.status in (TransactionStatus.CLEARED, TransactionStatus.SCHEDULED):
+            is_future = transaction.timestamp > datetime.datetime.now(tz=datetime.UTC)
+            transaction.status = (
+                TransactionStatus.SCHEDULED if is_future else TransactionStatus.CLEARED
+            )
+        update = categorize(
+            await self.storage.load_rules(user_id), transaction, transaction.description
+        )
+        if update is not None:
+            update.apply(transaction)
+        if money_pool.default_category and not transaction.tags and not transaction.splits:
+            transaction.tags = [money_pool.default_category]
+        self.protect_description(transaction)
+        return issues
+
+    async def find_duplicate(
+        self, user_id: UserId, transaction: Transaction
+    ) -> StoredTransaction | None:
+        """Transaction must be prepared, i.e. converted to the pool's currency"""
+        if not self.duplicate_window:
+            return None
+        candidates = await self.storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(
+                min_timestamp=transaction.timestamp - self.duplicate_window,
+                max_timestamp=transaction.timestamp + self.duplicate_window,
+                pool_ids=[transaction.pool_id],
+            ),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        description = self.plain_description(transaction)
+        for candidate in candidates:
+            if (
+                candidate.sum == transaction.sum
+                and self.plain_description(candidate) == description
+            ):
+                return candidate
+        return None
+
+    async def publish_transaction_events(
+        self, user_id: UserId, kind: OperationKind, transactions: list[StoredTransaction]
+    ) -> None:
+        """Transactions as created, before the update or as deleted"""
+        if not self.events.has_subscribers:
+            return
+        rebalanced: list[MoneyPoolId] = []
+        for t in transactions:
+            match kind:
+                case OperationKind.CREATE:
+                    await self.events.publish(TransactionCreated(user_id=user_id, transaction=t))
+                    rebalanced.append(t.pool_id)
+                case OperationKind.UPDATE:
+                    updated = await self.storage.load_transaction(user_id, t.id)
+                    if updated is not None:
+                        await self.events.publish(
+                            TransactionUpdated(user_id=user_id, transaction=updated)
+                        )
+                        # sums and pools can't be updated, only the status affects the balance
+                        if updated.status.is_counted != t.status.is_counted:
+                            rebalanced.append(t.pool_id)
+                case OperationKind.DELETE:
+                    await self.events.publish(TransactionDeleted(user_id=user_id, transaction=t))
+                    rebalanced.append(t.pool_id)
+        for pool_id in dict.fromkeys(rebalanced):
+            pool = await self.storage.load_pool(user_id, pool_id)
+            if pool is not None:
+                await self.events.publish(
+                    PoolBalanceChanged(user_id=user_id, pool_id=pool_id, balance=pool.balance)
+                )
+
+    async def log_operation(
+        self, user_id: UserId, kind: OperationKind, transactions: list[StoredTransaction]
+    ) -> None:
+        """
+        Logs the operation for undo and publishes it to the event bus;
+        must be called before presenting the transactions, to log the stored descriptions
+        """
+        await self.storage.log_operation(
+            user_id, Operation(kind=kind, transactions=transactions), retention=self.undo_window
+        )
+        await self.publish_transaction_events(user_id, kind, transactions)
+
+    async def create_pool(self, user_id: UserId, new_pool: MoneyPool) -> StoredMoneyPool:
+        """Pool id is generated by the storage, new pools go last"""
+        new_pool.initial_balance = copy.deepcopy(new_pool.balance)
+        pools = await self.storage.load_pools(user_id=user_id)
+        new_pool.sort_order = max((p.sort_order for p in pools), default=-1) + 1
+        stored = await self.storage.add_pool(user_id=user_id, new_pool=new_pool)
+        await self.events.publish(PoolCreated(user_id=user_id, pool=stored))
+        return stored
+
+    async def save_new_transactions(
+        self, user_id: UserId, transactions: list[Transaction]
+    ) -> list[StoredTransaction]:
+        """Stores prepared transactions all or nothing and logs them as a single operation"""
+        if not transactions:
+            return []
+        stored = await self.storage.add_transactions(user_id, transactions)
+        await self.log_operation(user_id, OperationKind.CREATE, stored)
+        return stored
+
+    async def add_transaction(
+        self, user_id: UserId, transaction: Transaction
+    ) -> tuple[StoredTransaction, list[ValidationIssue]]:
+        """Prepares, stores and logs a transaction, without duplicate or approval checks"""
+        warnings = await self.prepare_new_transaction(user_id, transaction)
+        [stored] = await self.save_new_transactions(user_id, [transaction])
+        return stored, warnings
+
+    async def add_transactions(
+        self, user_id: UserId, transactions: list[Transaction]
+    ) -> list[StoredTransaction]:
+        """Prepares the transactions, then stores and logs them together, e.g. transfer legs"""
+        for t in transactions:
+            await self.prepare_new_transaction(user_id, t)
+        return await self.save_new_transactions(user_id, transactions)
+
+    async def delete_transaction(self, user_id: UserId, transaction_id: str) -> bool:
+        """False if there's no such transaction"""
+        transaction = await self.storage.load_transaction(user_id, transaction_id)
+        if transaction is None:
+            return False
+        await self.ensure_period_unlocked(user_id, transaction.pool_id, transaction.timestamp)
+        if not await self.storage.delete_transaction(user_id, transaction_id=transaction_id):
+            return False
+        await self.log_operation(user_id, OperationKind.DELETE, [transaction])
+        return True
+
+    async def undo_last_operation(self, user_id: UserId) -> Operation | None:
+        """
+        Reverts the latest change to transactions made within the undo window; returns its kind
+        with the affected transactions as they are now (or were, for the deleted ones)
+        """
+        operation = await self.storage.pop_last_operation(
+            user_id, since=datetime.datetime.now(tz=datetime.UTC) - self.undo_window
+        )
+        if operation is None:
+            return None
+        for t in operation.transactions:
+            await self.ensure_period_unlocked(user_id, t.pool_id, t.timestamp)
+        match operation.kind:
+            case OperationKind.CREATE:
+                for t in operation.transactions:
+                    await self.storage.delete_transaction(user_id, t.id)
+                affected = operation.transactions
+                await self.publish_transaction_events(user_id, OperationKind.DELETE, affected)
+            case OperationKind.UPDATE:
+                affected = []
+                for t in operation.transactions:
+                    await self.storage.update_transaction(
+                        user_id,
+                        t.id,
+                        TransactionUpdate(
+                            description=t.description,
+                            timestamp=t.timestamp,
+                            tags=t.tags,
+                            splits=t.splits,
+                            payee_id=t.payee_id or "",
+                        ),
+                    )
+                    await self.storage.update_transaction_status(user_id, t.id, t.status)
+                    restored = await self.storage.load_transaction(user_id, t.id)
+                    if restored is not None:
+                        affected.append(restored)
+                await self.publish_transaction_events(
+                    user_id, OperationKind.UPDATE, operation.transactions
+                )
+            case OperationKind.DELETE:
+                await self.storage.restore_transactions(user_id, operation.transactions)
+                affected = operation.transactions
+                await self.publish_transaction_events(user_id, OperationKind.CREATE, affected)
+        return Operation(kind=operation.kind, transactions=affected)
+
+    async def unassign_payee(self, user_id: UserId, payee_id: PayeeId) -> list[StoredTransaction]:
+        """
+        Unsets the payee on its transactions as a single operation, all or nothing; raises
+        VersionConflict if one of them has changed meanwhile
+        """
+        transactions = await self.storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(payee_ids=[payee_id]),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise TooManyTransactions("Too many transactions to unassign the payee from")
+        if not transactions:
+            return []
+        edits = [
+            TransactionEdit(
+                transaction_id=t.id,
+                expected_version=t.version,
+                update=TransactionUpdate(payee_id=""),
+            )
+            for t in transactions
+        ]
+        await self.storage.apply_transaction_changes(user_id, new=[], edits=edits)
+        await self.log_operation(user_id, OperationKind.UPDATE, transactions)
+        return transactions
+
+    async def apply_rule(
+        self, user_id: UserId, rule: CategorizationRule
+    ) -> tuple[list[StoredTransaction], int]:
+        """
+        Applies the rule to the existing transactions as a single operation (to undo at once);
+        returns updated transactions and the number of matching ones skipped in locked periods
+        """
+        transactions = await self.storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(pool_ids=[rule.pool_id] if rule.pool_id else None),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise TooManyTransactions("Too many transactions to apply the rule to")
+        updated: list[StoredTransaction] = []
+        locked = 0
+        for t in transactions:
+            update = categorize([rule], t, self.plain_description(t))
+            if update is None:
+                continue
+            try:
+                await self.ensure_period_unlocked(user_id, t.pool_id, t.timestamp)
+            except PeriodLocked:
+                locked += 1
+                continue
+            if self.privacy is not None and update.description is not None:
+                update.description = self.privacy.encrypt(update.description)
+            if await self.storage.update_transaction(user_id, t.id, update):
+                updated.append(t)
+        if updated:
+            await self.log_operation(user_id, OperationKind.UPDATE, updated)
+        return updated, locked
+
+    async def redenominate_pool(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        from_currency: Currency | None,
+        to_currency: Currency,
+        rate: float | None,
+        convert_transactions: bool,
+    ) -> PoolRedenomination | None:
+        """
+        Moves the pool's balance in one currency (the main one by default) to another one at
+        the given or the current rate; the transactions are converted at the given rate or
+        the historical one on their dates, otherwise they stay in the old currency, which is kept
+        with zero balance for them; None if there's no such pool
+        """
+        pool = await self.storage.load_pool(user_id, pool_id)
+        if pool is None:
+            return None
+        if from_currency is None:
+            if not pool.balance:
+                raise ServiceError("Pool has no balance to convert")
+            from_currency = pool.balance[0].currency
+        if from_currency == to_currency:
+            raise ServiceError("Pool is already in this currency")
+        balance = next((s for s in pool.balance if s.currency == from_currency), None)
+        if balance is None:
+            raise ServiceError(f"Pool has no balance in {from_currency.code}")
+        await self.ensure_period_unlocked(user_id, pool.id, datetime.datetime.now(tz=datetime.UTC))
+        fixed_rate = rate
+        if rate is None:
+            rate = (await self.exchange_rates.get_rate(from_currency, to_currency)).rate
+
+        transactions = await self.storage.load_transactions(
+            user_id,
+            filter=TransactionFilter(pool_ids=[pool.id]),
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.OLDEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise TooManyTransactions("Too many transactions to convert the pool")
+        transactions = [t for t in transactions if t.sum.currency == from_currency]
+        counted_total = sum(
+            (t.sum.amount for t in transactions if t.status.is_counted), start=Decimal(0)
+        )
+
+        def convert(amount: Decimal, rate: float) -> Decimal:
+            return MoneySum(amount=Decimal(float(amount) * rate), currency=to_currency).amount
+
+        conversions: list[TransactionConversion] = []
+        converted_total = Decimal(0)
+        if convert_transactions:
+            for t in transactions:
+                await self.ensure_period_unlocked(user_id, t.pool_id, t.timestamp)
+                transaction_rate = fixed_rate
+                if transaction_rate is None:
+                    historical = HistoricalExchangeRates(
+                        self.storage, self.exchange_rates, on=t.timestamp.date()
+                    )
+                    transaction_rate = (await historical.get_rate(from_currency, to_currency)).rate
+                converted_sum = MoneySum(
+                    amount=convert(t.sum.amount, transaction_rate), currency=to_currency
+                )
+                splits = copy.deepcopy(t.splits)
+                convert_splits(splits, t.sum.amount, converted_sum)
+                conversions.append(
+                    TransactionConversion(
+                        transaction_id=t.id,
+                        rate=transaction_rate,
+                        sum=converted_sum,
+                        splits=splits,
+                        original_currency=t.original_currency or from_currency,
+                        original_amount=(
+                            t.original_amount if t.original_currency else t.sum.amount
+                        ),
+                    )
+                )
+                if t.status.is_counted:
+                    converted_total += converted_sum.amount
+            # the part of the balance not coming from the transactions is converted at the rate
+            opening = convert(balance.amount - counted_total, rate)
+            moved, remaining = opening + converted_total, None
+            initial_moved, initial_remaining = opening, None
+        else:
+            moved = convert(balance.amount, rate)
+            # the transactions left in the old currency must still be deletable
+            remaining = Decimal(0) if transactions else None
+            initial_moved, initial_remaining = moved, -counted_total if transactions else None
+
+        redenomination = PoolRedenomination(
+            pool_id=pool.id,
+            from_currency=from_currency,
+            to_currency=to_currency,
+            rate=rate,
+            balance=redenominated_sums(
+                pool.balance, from_currency, to_currency, moved, remaining
+            ),
+            initial_balance=(
+                redenominated_sums(
+                    pool.initial_balance,
+                    from_currency,
+                    to_currency,
+                    initial_moved,
+                    initial_remaining,
+                )
+                if pool.initial_balance is not None
+                else None
+            ),
+            transactions=conversions,
+        )
+        if not await self.storage.redenominate_pool(user_id, redenomination):
+            return None
+        if self.events.has_subscribers:
+            await self.events.publish(
+                PoolBalanceChanged(
+                    user_id=user_id, pool_id=pool.id, balance=redenomination.balance
+                )
+            )
+        return redenomination
+
+    async def export_user_data(
+        self, user_id: UserId, descriptions_visible: bool
+    ) -> UserDataExport:
+        transactions = await self.storage.load_transactions(
+            user_id,
+            filter=None,
+            offset=0,
+            count=MAX_TRANSACTIONS_TO_LOAD,
+            order=TransactionOrder.LATEST,
+        )
+        if len(transactions) == MAX_TRANSACTIONS_TO_LOAD:
+            raise TooManyTransactions("Too many transactions to export")
+        return UserDataExport(
+            user_id=user_id,
+            pools=await self.storage.load_pools(user_id),
+            transactions=self.present_transactions(transactions, descriptions_visible),
+            reconciliations=await self.storage.load_reconciliations(user_id, pool_id=None),
+            report_snapshots=await self.storage.load_report_snapshots(user_id),
+            allowances=await self.storage.load_allowances(user_id),
+            challenges=await self.storage.load_challenges(user_id),
+            goals=await self.storage.load_goals(user_id),
+            debts=await self.storage.load_debts(user_id),
+            templates=await self.storage.load_transaction_templates(user_id),
+            rules=await self.storage.load_rules(user_id),
+            notes=await self.storage.load_pool_notes(user_id, pool_id=None),
+            balance_snapshots=await self.storage.load_balance_snapshots(user_id, pool_id=None),
+            settings=await self.storage.load_user_settings(user_id),
+            month_closes=await self.storage.load_month_closes(user_id),
+        )
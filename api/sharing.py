@@ -0,0 +1,24 @@
+"""Splitting a shared expense between the user and the counterparties who owe their shares"""
+
+from decimal import Decimal
+from typing import Sequence
+
+from api.types.api import ExpenseShare
+from api.types.money_sum import MoneySum
+
+
+def share_portions(
+    total: MoneySum, own_shares: Decimal, participants: Sequence[ExpenseShare]
+) -> list[MoneySum]:
+    """
+    Portion of the positive total owed by each participant, proportional to the shares; the user
+    absorbs the rounding remainder, or the last participant if the user has no share
+    """
+    all_shares = own_shares + sum(p.shares for p in participants)
+    portions = [
+        MoneySum(amount=total.amount * p.shares / all_shares, currency=total.currency)
+        for p in participants
+    ]
+    if own_shares.is_zero():
+        portions[-1].amount = total.amount - sum(p.amount for p in portions[:-1])
+    return portions
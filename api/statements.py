@@ -0,0 +1,173 @@
+"""
+Bank statement files (OFX, QIF) parsed into transactions for a pool, skipping the entries that
+were already imported or entered by hand
+"""
+
+import dataclasses
+import datetime
+import enum
+import re
+from decimal import Decimal, InvalidOperation
+from typing import Sequence
+
+from api.types.currency import Currency
+from api.types.ids import MoneyPoolId
+from api.types.money_sum import MoneySum
+from api.types.transaction import MAX_SOURCE_LENGTH, Transaction, TransactionSource
+
+
+class StatementFormat(enum.StrEnum):
+    OFX = "ofx"
+    QIF = "qif"
+
+
+@dataclasses.dataclass(frozen=True)
+class StatementEntry:
+    timestamp: datetime.datetime
+    amount: Decimal
+    description: str
+    fitid: str | None  # bank's transaction id, OFX only
+    raw: str
+
+
+def detect_format(text: str) -> StatementFormat:
+    if "OFXHEADER" in text[:1024] or "<OFX>" in text.upper():
+        return StatementFormat.OFX
+    if text.lstrip().startswith("!"):
+        return StatementFormat.QIF
+    raise ValueError("Unknown statement format, expected OFX or QIF")
+
+
+OFX_TRANSACTION_RE = re.compile(
+    r"<STMTTRN>(.*?)(?=</STMTTRN>|<STMTTRN>|</BANKTRANLIST>|\Z)", re.DOTALL | re.IGNORECASE
+)
+OFX_FIELD_RE = re.compile(r"<([A-Z0-9.]+)>([^<\r\n]*)", re.IGNORECASE)
+OFX_DATE_RE = re.compile(r"^(\d{8})(\d{6})?(?:\.\d+)?(?:\[([+-]?\d+(?:\.\d+)?)(?::\w+)?\])?")
+
+
+def parse_ofx_date(value: str) -> datetime.datetime:
+    """E.g. 20240131, 20240131120000 or 20240131120000.000[-5:EST]"""
+    match = OFX_DATE_RE.match(value.strip())
+    if match is None:
+        raise ValueError(f"invalid date {value!r}")
+    date, time, offset_hours = match.groups()
+    timestamp = datetime.datetime.strptime(date + (time or "120000"), "%Y%m%d%H%M%S")
+    offset = datetime.timedelta(hours=float(offset_hours or 0))
+    return timestamp.replace(tzinfo=datetime.timezone(offset))
+
+
+def ofx_currency(text: str) -> str | None:
+    match = re.search(r"<CURDEF>([A-Z]{3})", text, re.IGNORECASE)
+    return match.group(1).upper() if match else None
+
+
+def parse_ofx(text: str) -> list[StatementEntry]:
+    """Both SGML (OFX 1.x, closing tags optional) and XML (OFX 2.x) flavors"""
+    entries: list[StatementEntry] = []
+    for idx, match in enumerate(OFX_TRANSACTION_RE.finditer(text), start=1):
+        block = match.group(1)
+        fields = {tag.upper(): value.strip() for tag, value in OFX_FIELD_RE.findall(block)}
+        try:
+            timestamp = parse_ofx_date(fields["DTPOSTED"])
+            amount = Decimal(fields["TRNAMT"].replace(",", "."))
+        except (KeyError, ValueError, InvalidOperation) as e:
+            raise ValueError(f"Transaction {idx}: missing or invalid field: {e}")
+        entries.append(
+            StatementEntry(
+                timestamp=timestamp,
+                amount=amount,
+                description=fields.get("NAME") or fields.get("MEMO") or "",
+                fitid=fields.get("FITID") or None,
+                raw=block.strip(),
+            )
+        )
+    return entries
+
+
+def parse_qif_date(value: str) -> datetime.datetime:
+    """
+    E.g. 12/31/2024, 12/31'24 (US month first), 31.12.2024 (day first) or 2024-12-31;
+    QIF has no time, so it's noon UTC
+    """
+    parts = re.split(r"[/.'-]", value.strip().replace(" ", ""))
+    if len(parts) != 3 or not all(p.isdigit() for p in parts):
+        raise ValueError(f"invalid date {value!r}")
+    if len(parts[0]) == 4:
+        year, month, day = parts
+    elif "." in value:
+        day, month, year = parts
+    else:
+        month, day, year = parts
+    full_year = int(year) + 2000 if len(year) <= 2 else int(year)
+    return datetime.datetime(full_year, int(month), int(day), 12, tzinfo=datetime.UTC)
+
+
+def parse_qif(text: str) -> list[StatementEntry]:
+    """Records of one-letter-prefixed lines separated by ^, the header line is skipped"""
+    entries: list[StatementEntry] = []
+    record: list[str] = []
+    for line_no, line in enumerate(text.splitlines(), start=1):
+        line = line.strip()
+        if not line or line.startswith("!"):
+            continue
+        if line != "^":
+            record.append(line)
+            continue
+        fields = {r[0]: r[1:].strip() for r in reversed(record)}  # first occurrence wins
+        try:
+            timestamp = parse_qif_date(fields["D"])
+            amount = Decimal((fields.get("T") or fields["U"]).replace(",", ""))
+        except (KeyError, ValueError, InvalidOperation) as e:
+            raise ValueError(f"Record ending on line {line_no}: missing or invalid field: {e}")
+        entries.append(
+            StatementEntry(
+                timestamp=timestamp,
+                amount=amount,
+                description=fields.get("P") or fields.get("M") or "",
+                fitid=None,
+                raw="\n".join(record),
+            )
+        )
+        record = []
+    return entries
+
+
+def parse_statement(text: str, format: StatementFormat) -> list[StatementEntry]:
+    match format:
+        case StatementFormat.OFX:
+            return parse_ofx(text)
+        case StatementFormat.QIF:
+            return parse_qif(text)
+
+
+def is_imported(entry: StatementEntry, existing: Sequence[Transaction]) -> bool:
+    """
+    Matched by FITID against earlier imports, by date and amount against transactions entered
+    by hand or imported without one
+    """
+    for t in existing:
+        external_id = t.source.external_id if t.source is not None else None
+        if entry.fitid is not None and external_id is not None:
+            if external_id == entry.fitid:
+                return True
+        elif (
+            t.timestamp.astimezone(datetime.UTC).date()
+            == entry.timestamp.astimezone(datetime.UTC).date()
+            and t.sum.amount == entry.amount
+        ):
+            return True
+    return False
+
+
+def to_transaction(
+    entry: StatementEntry, pool_id: MoneyPoolId, currency: Currency, format: StatementFormat
+) -> Transaction:
+    return Transaction(
+        sum=MoneySum(amount=entry.amount, currency=currency),
+        pool_id=pool_id,
+        description=entry.description,
+        timestamp=entry.timestamp,
+        source=TransactionSource(
+            origin=format.value, raw=entry.raw[:MAX_SOURCE_LENGTH], external_id=entry.fitid
+        ),
+    )
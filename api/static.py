@@ -0,0 +1,24 @@
+from starlette.exceptions import HTTPException
+from starlette.responses import Response
+from starlette.staticfiles import StaticFiles
+from starlette.types import Scope
+
+INDEX_HTML = "index.html"
+
+
+class SpaStaticFiles(StaticFiles):
+    """Serves files from a directory, falling back to index.html for client-side routes"""
+
+    def __init__(self, directory: str) -> None:
+        super().__init__(directory=directory, html=True)
+
+    async def get_response(self, path: str, scope: Scope) -> Response:
+        try:
+            response = await super().get_response(path, scope)
+        except HTTPException as e:
+            if e.status_code != 404:
+                raise
+        else:
+            if response.status_code != 404:
+                return response
+        return await super().get_response(INDEX_HTML, scope)
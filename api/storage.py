@@ -1,10 +1,18 @@
 import abc
+import asyncio
+import bisect
+import collections
 import copy
-import enum
+import datetime
+import functools
+import inspect
 import logging
+import os
+import re
 import time
 import uuid
-from typing import Annotated, Any
+from pathlib import Path
+from typing import Annotated, Any, Awaitable, Callable, Iterable, TypeVar, get_type_hints
 
 import fastapi
 import pydantic
@@ -14,36 +22,135 @@ from motor.motor_asyncio import (
     AsyncIOMotorClientSession,
     AsyncIOMotorCollection,
 )
-
-from api.types.api import MoneyPoolAttributesUpdate, TransactionUpdate
-from api.types.ids import MoneyPoolId, TransactionId, UserId
+from pydantic_core import to_jsonable_python
+from pymongo import ReplaceOne, ReturnDocument, UpdateOne
+from pymongo.errors import DuplicateKeyError
+
+from api.migrations import Migration, pending_migrations
+from api.types.allowance import Allowance, StoredAllowance
+from api.types.api import MoneyPoolAttributesUpdate, TransactionEdit, TransactionUpdate
+from api.types.audit import AuditEntry
+from api.types.balance_snapshot import BalanceSnapshot, StoredBalanceSnapshot
+from api.types.challenge import Challenge, StoredChallenge
+from api.types.debt import Debt, StoredDebt
+from api.types.goal import Goal, StoredGoal
+from api.types.historical_rates import DailyRates
+from api.types.ids import (
+    AllowanceId,
+    BalanceSnapshotId,
+    ChallengeId,
+    DebtId,
+    GoalId,
+    MoneyPoolId,
+    NoteId,
+    OperationId,
+    PayeeId,
+    ReconciliationId,
+    ReportSnapshotId,
+    RuleId,
+    TemplateId,
+    TransactionId,
+    UserId,
+)
 from api.types.money_pool import MoneyPool, StoredMoneyPool
 from api.types.money_sum import MoneySum
-from api.types.transaction import StoredTransaction, Transaction, TransactionFilter
-
-
-class TransactionOrder(enum.Enum):
-    LATEST = "latest"
-    OLDEST = "oldest"
-    LARGEST = "largest"
-    LARGEST_NEGATIVE = "largest_negative"
-
-    def key(self, tran: StoredTransaction) -> float:
-        match self:
-            case TransactionOrder.LATEST:
-                return tran.timestamp.timestamp()
-            case TransactionOrder.OLDEST:
-                return -tran.timestamp.timestamp()
-            case TransactionOrder.LARGEST:
-                return -float(tran.sum.amount)
-            case TransactionOrder.LARGEST_NEGATIVE:
-                return float(tran.sum.amount)
+from api.types.month_close import MonthClose
+from api.types.net_worth import NetWorthSnapshot
+from api.types.note import PoolNote, StoredPoolNote
+from api.types.operation import Operation, StoredOperation
+from api.types.payee import Payee, StoredPayee
+from api.types.reconciliation import Reconciliation, StoredReconciliation
+from api.types.redenomination import PoolRedenomination
+from api.types.report_snapshot import ReportSnapshot, StoredReportSnapshot
+from api.types.rule import CategorizationRule, StoredCategorizationRule
+from api.types.settings import UserSettings
+from api.types.suggestion import Suggestion, SuggestionField
+from api.types.sync import EntityChange, SyncedEntity
+from api.types.template import StoredTransactionTemplate, TransactionTemplate
+from api.types.transaction import (
+    StoredTransaction,
+    Transaction,
+    TransactionFilter,
+    TransactionKind,
+    TransactionOrder,
+    TransactionStatus,
+)
+from api.types.two_factor import TwoFactor
+from api.wal import WalRecord, WriteAheadLog
+
+
+logger = logging.getLogger(__name__)
+
+HISTORICAL_RATES_MAX_GAP_DAYS = 7
+
+# for suggestions computed from loaded transactions, e.g. over encrypted descriptions
+MAX_SUGGESTION_TRANSACTIONS = 5000
+
+MAX_REPORT_SNAPSHOTS = 1000
+
+
+class StorageError(Exception):
+    pass
+
+
+class VersionConflict(StorageError):
+    def __init__(
+        self, expected_version: int, actual_version: int | None, entity_id: str | None = None
+    ) -> None:
+        self.expected_version = expected_version
+        self.actual_version = actual_version
+        self.entity_id = entity_id
+        super().__init__(f"Version mismatch: expected {expected_version}, got {actual_version}")
+
+
+class IdConflict(StorageError):
+    def __init__(self, entity_type: str, id: str) -> None:
+        self.entity_type = entity_type
+        self.id = id
+        super().__init__(f"{entity_type.capitalize()} with id {id} already exists")
+
+
+def count_suggestions(
+    transactions: Iterable[Transaction],
+    field: SuggestionField,
+    query: str,
+    payees: Iterable[StoredPayee],
+    count: int,
+) -> list[Suggestion]:
+    """
+    The field's values containing the query case-insensitively, the most frequent first and the
+    most recently used among equally frequent ones; payees are matched by name
+    """
+    query = query.casefold()
+    payee_names = {p.id: p.name for p in payees if query in p.name.casefold()}
+    counts: collections.Counter[str] = collections.Counter()
+    last_used: dict[str, float] = {}
+    for t in transactions:
+        match field:
+            case SuggestionField.DESCRIPTION:
+                values = [t.description] if query in t.description.casefold() else []
+            case SuggestionField.CATEGORY:
+                values = [tag for tag in t.tags if query in tag.casefold()]
+            case SuggestionField.PAYEE:
+                values = [t.payee_id] if t.payee_id in payee_names else []
+        for value in values:
+            if not value:
+                continue
+            counts[value] += 1
+            last_used[value] = max(last_used.get(value, 0.0), t.timestamp.timestamp())
+    ranked = sorted(counts, key=lambda v: (-counts[v], -last_used[v]))[:count]
+    if field is SuggestionField.PAYEE:
+        return [Suggestion(value=payee_names[v], payee_id=v, count=counts[v]) for v in ranked]
+    return [Suggestion(value=v, count=counts[v]) for v in ranked]
 
 
 class Storage(abc.ABC):
     async def initialize(self) -> None:
         pass
 
+    async def close(self) -> None:
+        """On shutdown"""
+
     @abc.abstractmethod
     async def add_pool(self, user_id: UserId, new_pool: MoneyPool) -> StoredMoneyPool: ...
 
@@ -54,20 +161,76 @@ class Storage(abc.ABC):
 
     @abc.abstractmethod
     async def set_pool_attributes(
-        self, user_id: UserId, pool_id: MoneyPoolId, update: MoneyPoolAttributesUpdate
-    ) -> bool: ...
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        update: MoneyPoolAttributesUpdate,
+        expected_version: int | None = None,
+    ) -> bool:
+        """Raises VersionConflict if expected_version is given and doesn't match"""
+
+    @abc.abstractmethod
+    async def set_pools_order(self, user_id: UserId, pool_ids: list[MoneyPoolId]) -> None: ...
 
     @abc.abstractmethod
-    async def load_pools(self, user_id: UserId) -> list[StoredMoneyPool]: ...
+    async def load_pools(self, user_id: UserId) -> list[StoredMoneyPool]:
+        """In the user-defined order"""
 
     @abc.abstractmethod
     async def load_pool(self, user_id: UserId, pool_id: MoneyPoolId) -> StoredMoneyPool | None: ...
 
+    @abc.abstractmethod
+    async def save_pool_balance(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        balance: list[MoneySum],
+        initial_balance: list[MoneySum] | None,
+    ) -> bool:
+        """Overwrites the balance, only for rebuilding it from transactions"""
+
+    @abc.abstractmethod
+    async def redenominate_pool(self, user_id: UserId, redenomination: PoolRedenomination) -> bool:
+        """Sets the pool's new balances and the converted transactions' amounts at once"""
+
     @abc.abstractmethod
     async def add_transaction(
         self, user_id: str, transaction: Transaction
     ) -> StoredTransaction: ...
 
+    @abc.abstractmethod
+    async def load_change_counter(self, user_id: UserId) -> int:
+        """Grows on every change to the user's pools and transactions, for conditional requests"""
+
+    @abc.abstractmethod
+    async def load_changes(self, user_id: UserId, since: int) -> list[EntityChange]:
+        """Latest changes to the user's pools and transactions made after the given counter"""
+
+    async def add_transactions(
+        self, user_id: UserId, transactions: list[Transaction]
+    ) -> list[StoredTransaction]:
+        """Backends may override this to insert the batch more efficiently"""
+        return [await self.add_transaction(user_id, t) for t in transactions]
+
+    @abc.abstractmethod
+    async def apply_transaction_changes(
+        self, user_id: UserId, new: list[Transaction], edits: list[TransactionEdit]
+    ) -> list[StoredTransaction]:
+        """
+        Adds the new transactions and then applies the edits in order, all or nothing; raises
+        VersionConflict with the transaction id if an edited one is gone or of another version.
+        Returns the added transactions
+        """
+
+    @abc.abstractmethod
+    async def restore_transactions(
+        self, user_id: UserId, transactions: list[StoredTransaction]
+    ) -> None:
+        """
+        Adds previously deleted transactions back, keeping their ids;
+        raises IdConflict without restoring anything if any of the ids is taken
+        """
+
     @abc.abstractmethod
     async def load_transactions(
         self,
@@ -76,29 +239,613 @@ class Storage(abc.ABC):
         order: TransactionOrder,
         offset: int,
         count: int,
-    ) -> list[StoredTransaction]: ...
+    ) -> list[StoredTransaction]:
+        """Transactions equal in the order (e.g. with the same timestamp) go in the order added"""
 
     @abc.abstractmethod
     async def delete_transaction(self, user_id: UserId, transaction_id: TransactionId) -> bool: ...
 
     @abc.abstractmethod
     async def update_transaction(
-        self, user_id: UserId, transaction_id: TransactionId, update: TransactionUpdate
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        update: TransactionUpdate,
+        expected_version: int | None = None,
+    ) -> bool:
+        """Raises VersionConflict if expected_version is given and doesn't match"""
+
+    @abc.abstractmethod
+    async def update_transaction_status(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        status: TransactionStatus,
+        expected_version: int | None = None,
+    ) -> bool:
+        """
+        Voiding or scheduling reverts the transaction's effect on the pool balance, the opposite
+        reapplies it; raises VersionConflict if expected_version is given and doesn't match
+        """
+
+    @abc.abstractmethod
+    async def load_due_scheduled_transactions(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredTransaction]]:
+        """Scheduled transactions of all users that are due by now"""
+
+    @abc.abstractmethod
+    async def load_suggestions(
+        self,
+        user_id: UserId,
+        field: SuggestionField,
+        query: str,
+        since: datetime.datetime,
+        count: int,
+    ) -> list[Suggestion]:
+        """Values of the field in the user's transactions since the time, as count_suggestions"""
+
+    async def load_transaction(
+        self, user_id: UserId, transaction_id: TransactionId
+    ) -> StoredTransaction | None:
+        found = await self.load_transactions(
+            user_id,
+            filter=TransactionFilter(transaction_ids=[transaction_id]),
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=1,
+        )
+        return found[0] if found else None
+
+    @abc.abstractmethod
+    async def add_reconciliation(
+        self, user_id: UserId, reconciliation: Reconciliation
+    ) -> StoredReconciliation: ...
+
+    @abc.abstractmethod
+    async def load_reconciliations(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredReconciliation]: ...
+
+    @abc.abstractmethod
+    async def load_reconciliation(
+        self, user_id: UserId, reconciliation_id: ReconciliationId
+    ) -> StoredReconciliation | None: ...
+
+    @abc.abstractmethod
+    async def save_reconciliation(
+        self, user_id: UserId, reconciliation: StoredReconciliation
+    ) -> bool: ...
+
+    @abc.abstractmethod
+    async def add_report_snapshot(
+        self, user_id: UserId, snapshot: ReportSnapshot
+    ) -> StoredReportSnapshot: ...
+
+    @abc.abstractmethod
+    async def load_report_snapshots(self, user_id: UserId) -> list[StoredReportSnapshot]:
+        """Newest first by creation time, at most MAX_REPORT_SNAPSHOTS"""
+
+    @abc.abstractmethod
+    async def load_report_snapshot(
+        self, user_id: UserId, snapshot_id: ReportSnapshotId
+    ) -> StoredReportSnapshot | None: ...
+
+    @abc.abstractmethod
+    async def add_allowance(self, user_id: UserId, allowance: Allowance) -> StoredAllowance: ...
+
+    @abc.abstractmethod
+    async def load_allowances(self, user_id: UserId) -> list[StoredAllowance]: ...
+
+    @abc.abstractmethod
+    async def load_allowance(
+        self, user_id: UserId, allowance_id: AllowanceId
+    ) -> StoredAllowance | None: ...
+
+    @abc.abstractmethod
+    async def save_allowance(self, user_id: UserId, allowance: StoredAllowance) -> bool: ...
+
+    @abc.abstractmethod
+    async def load_allowance_by_view_token(
+        self, view_token: str
+    ) -> tuple[UserId, StoredAllowance] | None: ...
+
+    @abc.abstractmethod
+    async def load_due_allowances(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredAllowance]]:
+        """Allowances of all users with payment due"""
+
+    @abc.abstractmethod
+    async def add_challenge(self, user_id: UserId, challenge: Challenge) -> StoredChallenge: ...
+
+    @abc.abstractmethod
+    async def load_challenges(self, user_id: UserId) -> list[StoredChallenge]: ...
+
+    @abc.abstractmethod
+    async def load_challenge(
+        self, user_id: UserId, challenge_id: ChallengeId
+    ) -> StoredChallenge | None: ...
+
+    @abc.abstractmethod
+    async def save_challenge(self, user_id: UserId, challenge: StoredChallenge) -> bool: ...
+
+    @abc.abstractmethod
+    async def add_goal(self, user_id: UserId, goal: Goal) -> StoredGoal: ...
+
+    @abc.abstractmethod
+    async def load_goals(self, user_id: UserId) -> list[StoredGoal]: ...
+
+    @abc.abstractmethod
+    async def load_goal(self, user_id: UserId, goal_id: GoalId) -> StoredGoal | None: ...
+
+    @abc.abstractmethod
+    async def save_goal(self, user_id: UserId, goal: StoredGoal) -> bool: ...
+
+    @abc.abstractmethod
+    async def delete_goal(self, user_id: UserId, goal_id: GoalId) -> bool: ...
+
+    @abc.abstractmethod
+    async def add_debt(self, user_id: UserId, debt: Debt) -> StoredDebt: ...
+
+    @abc.abstractmethod
+    async def load_debts(self, user_id: UserId) -> list[StoredDebt]: ...
+
+    @abc.abstractmethod
+    async def load_debt(self, user_id: UserId, debt_id: DebtId) -> StoredDebt | None: ...
+
+    @abc.abstractmethod
+    async def save_debt(self, user_id: UserId, debt: StoredDebt) -> bool: ...
+
+    @abc.abstractmethod
+    async def add_transaction_template(
+        self, user_id: UserId, template: TransactionTemplate
+    ) -> StoredTransactionTemplate: ...
+
+    @abc.abstractmethod
+    async def load_transaction_templates(
+        self, user_id: UserId
+    ) -> list[StoredTransactionTemplate]: ...
+
+    @abc.abstractmethod
+    async def load_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> StoredTransactionTemplate | None: ...
+
+    @abc.abstractmethod
+    async def save_transaction_template(
+        self, user_id: UserId, template: StoredTransactionTemplate
+    ) -> bool: ...
+
+    @abc.abstractmethod
+    async def delete_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> bool: ...
+
+    @abc.abstractmethod
+    async def add_rule(
+        self, user_id: UserId, rule: CategorizationRule
+    ) -> StoredCategorizationRule: ...
+
+    @abc.abstractmethod
+    async def load_rules(self, user_id: UserId) -> list[StoredCategorizationRule]:
+        """In the order of creation"""
+
+    @abc.abstractmethod
+    async def load_rule(
+        self, user_id: UserId, rule_id: RuleId
+    ) -> StoredCategorizationRule | None: ...
+
+    @abc.abstractmethod
+    async def save_rule(self, user_id: UserId, rule: StoredCategorizationRule) -> bool: ...
+
+    @abc.abstractmethod
+    async def delete_rule(self, user_id: UserId, rule_id: RuleId) -> bool: ...
+
+    @abc.abstractmethod
+    async def add_payee(self, user_id: UserId, payee: Payee) -> StoredPayee: ...
+
+    @abc.abstractmethod
+    async def load_payees(self, user_id: UserId) -> list[StoredPayee]:
+        """Ordered by name"""
+
+    @abc.abstractmethod
+    async def load_payee(self, user_id: UserId, payee_id: PayeeId) -> StoredPayee | None: ...
+
+    @abc.abstractmethod
+    async def save_payee(self, user_id: UserId, payee: StoredPayee) -> bool: ...
+
+    @abc.abstractmethod
+    async def delete_payee(self, user_id: UserId, payee_id: PayeeId) -> bool:
+        """Transactions keep the id, the caller is to unset it"""
+
+    @abc.abstractmethod
+    async def add_pool_note(self, user_id: UserId, note: PoolNote) -> StoredPoolNote: ...
+
+    @abc.abstractmethod
+    async def load_pool_notes(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredPoolNote]: ...
+
+    @abc.abstractmethod
+    async def load_pool_note(self, user_id: UserId, note_id: NoteId) -> StoredPoolNote | None: ...
+
+    @abc.abstractmethod
+    async def save_pool_note(self, user_id: UserId, note: StoredPoolNote) -> bool: ...
+
+    @abc.abstractmethod
+    async def delete_pool_note(self, user_id: UserId, note_id: NoteId) -> bool: ...
+
+    @abc.abstractmethod
+    async def add_balance_snapshot(
+        self, user_id: UserId, snapshot: BalanceSnapshot
+    ) -> StoredBalanceSnapshot: ...
+
+    @abc.abstractmethod
+    async def load_balance_snapshots(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredBalanceSnapshot]:
+        """Oldest first"""
+
+    @abc.abstractmethod
+    async def load_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> StoredBalanceSnapshot | None: ...
+
+    @abc.abstractmethod
+    async def delete_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
     ) -> bool: ...
 
+    @abc.abstractmethod
+    async def load_user_settings(self, user_id: UserId) -> UserSettings:
+        """Defaults if the user hasn't saved any"""
+
+    @abc.abstractmethod
+    async def save_user_settings(self, user_id: UserId, settings: UserSettings) -> None: ...
+
+    @abc.abstractmethod
+    async def load_month_closes(self, user_id: UserId) -> list[MonthClose]:
+        """Only the months the user has started closing"""
+
+    @abc.abstractmethod
+    async def load_month_close(
+        self, user_id: UserId, year: int, month: int
+    ) -> MonthClose | None: ...
+
+    @abc.abstractmethod
+    async def save_month_close(self, user_id: UserId, month_close: MonthClose) -> None: ...
+
+    @abc.abstractmethod
+    async def save_net_worth_snapshot(self, user_id: UserId, snapshot: NetWorthSnapshot) -> None:
+        """Replaces the snapshot for the same date, if any"""
+
+    @abc.abstractmethod
+    async def load_net_worth_snapshots(
+        self, user_id: UserId, start: datetime.date, end: datetime.date
+    ) -> list[NetWorthSnapshot]:
+        """Snapshots dated within [start; end], oldest first"""
+
+    @abc.abstractmethod
+    async def save_calendar_token(self, user_id: UserId, token: str | None) -> None:
+        """Replaces the user's calendar feed token, None revokes it"""
+
+    @abc.abstractmethod
+    async def load_calendar_token(self, user_id: UserId) -> str | None: ...
+
+    @abc.abstractmethod
+    async def load_calendar_token_owner(self, token: str) -> UserId | None: ...
+
+    @abc.abstractmethod
+    async def save_two_factor(self, user_id: UserId, two_factor: TwoFactor | None) -> None:
+        """Replaces the user's two-factor authentication state, None disables it"""
+
+    @abc.abstractmethod
+    async def load_two_factor(self, user_id: UserId) -> TwoFactor | None: ...
+
+    @abc.abstractmethod
+    async def log_operation(
+        self, user_id: UserId, operation: Operation, retention: datetime.timedelta
+    ) -> StoredOperation:
+        """Operations logged more than the retention period before the new one are dropped"""
+
+    @abc.abstractmethod
+    async def pop_last_operation(
+        self, user_id: UserId, since: datetime.datetime
+    ) -> StoredOperation | None:
+        """Removes and returns the latest operation, if it's logged after the given time"""
+
+    @abc.abstractmethod
+    async def append_audit_entry(self, entry: AuditEntry) -> None: ...
+
+    @abc.abstractmethod
+    async def load_audit_entries(
+        self, user_id: UserId, entity_id: str | None, offset: int, count: int
+    ) -> list[AuditEntry]:
+        """Latest first"""
+
+    @abc.abstractmethod
+    async def save_historical_rates(self, days: list[DailyRates]) -> None:
+        """Shared by all users, overwrites the days already stored"""
+
+    @abc.abstractmethod
+    async def load_historical_rates(self, on: datetime.date) -> DailyRates | None:
+        """Latest rates on or shortly before the date (no rates on weekends and holidays)"""
+
+    @abc.abstractmethod
+    async def load_historical_rates_between(
+        self, start: datetime.date, end: datetime.date
+    ) -> list[DailyRates]:
+        """Days stored in the range, both ends included, oldest first"""
+
+    @abc.abstractmethod
+    async def delete_user_data(self, user_id: UserId) -> None:
+        """Wipes everything owned by the user, including their undo and audit logs"""
+
+    @abc.abstractmethod
+    async def load_user_ids(self) -> list[UserId]:
+        """All users owning at least one pool"""
+
+    @abc.abstractmethod
+    async def load_storage_usage(self, user_id: UserId) -> dict[str, int]:
+        """Number of stored entities owned by the user, per kind (transactions, pools, ...)"""
+
+    @abc.abstractmethod
+    async def load_disabled_user_ids(self) -> list[UserId]: ...
+
+    async def is_user_disabled(self, user_id: UserId) -> bool:
+        """Checked on every request, backends may override this to query a single user"""
+        return user_id in await self.load_disabled_user_ids()
+
+    @abc.abstractmethod
+    async def set_user_disabled(self, user_id: UserId, disabled: bool) -> None: ...
+
+    async def rebuild_indexes(self) -> None:
+        pass
+
+    async def migrate(self, dry_run: bool) -> list[Migration]:
+        """Applies pending data migrations (just lists them on dry run) and returns them"""
+        return []
+
+
+class InmemorySnapshot(pydantic.BaseModel):
+    """Contents of the in-memory storage, saved to a file to survive restarts"""
+
+    transactions: dict[UserId, list[StoredTransaction]] = pydantic.Field(default_factory=dict)
+    pools: dict[UserId, list[StoredMoneyPool]] = pydantic.Field(default_factory=dict)
+    reconciliations: dict[UserId, list[StoredReconciliation]] = pydantic.Field(
+        default_factory=dict
+    )
+    report_snapshots: dict[UserId, list[StoredReportSnapshot]] = pydantic.Field(
+        default_factory=dict
+    )
+    allowances: dict[UserId, list[StoredAllowance]] = pydantic.Field(default_factory=dict)
+    challenges: dict[UserId, list[StoredChallenge]] = pydantic.Field(default_factory=dict)
+    goals: dict[UserId, list[StoredGoal]] = pydantic.Field(default_factory=dict)
+    debts: dict[UserId, list[StoredDebt]] = pydantic.Field(default_factory=dict)
+    transaction_templates: dict[UserId, list[StoredTransactionTemplate]] = pydantic.Field(
+        default_factory=dict
+    )
+    categorization_rules: dict[UserId, list[StoredCategorizationRule]] = pydantic.Field(
+        default_factory=dict
+    )
+    payees: dict[UserId, list[StoredPayee]] = pydantic.Field(default_factory=dict)
+    pool_notes: dict[UserId, list[StoredPoolNote]] = pydantic.Field(default_factory=dict)
+    balance_snapshots: dict[UserId, list[StoredBalanceSnapshot]] = pydantic.Field(
+        default_factory=dict
+    )
+    user_settings: dict[UserId, UserSettings] = pydantic.Field(default_factory=dict)
+    month_closes: dict[UserId, list[MonthClose]] = pydantic.Field(default_factory=dict)
+    net_worth_snapshots: dict[UserId, list[NetWorthSnapshot]] = pydantic.Field(
+        default_factory=dict
+    )
+    calendar_tokens: dict[UserId, str] = pydantic.Field(default_factory=dict)
+    two_factors: dict[UserId, TwoFactor] = pydantic.Field(default_factory=dict)
+    operations: dict[UserId, list[StoredOperation]] = pydantic.Field(default_factory=dict)
+    change_counters: dict[UserId, int] = pydantic.Field(default_factory=dict)
+    changes: dict[UserId, list[EntityChange]] = pydantic.Field(default_factory=dict)
+    audit_entries: list[AuditEntry] = pydantic.Field(default_factory=list)
+    historical_rates: list[DailyRates] = pydantic.Field(default_factory=list)
+    disabled_user_ids: list[UserId] = pydantic.Field(default_factory=list)
+    wal_seq: int = 0  # last write-ahead log record included
+
+
+DEFAULT_SNAPSHOT_INTERVAL_SEC = 60
+
+Mutation = TypeVar("Mutation", bound=Callable[..., Awaitable[Any]])
+
+
+def logged_mutation(method: Mutation) -> Mutation:
+    """Records the call in the in-memory storage's write-ahead log once it succeeds"""
+    signature = inspect.signature(method)
+
+    @functools.wraps(method)
+    async def wrapper(self: "InmemoryStorage", *args: Any, **kwargs: Any) -> Any:
+        # nested calls are replayed as a part of the outer one; no concurrent calls here, the
+        # in-memory methods don't actually suspend
+        if self.wal is None or self._generated_ids is not None:
+            return await method(self, *args, **kwargs)
+        bound = signature.bind(self, *args, **kwargs)
+        bound.apply_defaults()
+        # serialized before the call, which may modify the arguments
+        arguments = {
+            name: to_jsonable_python(value)
+            for name, value in bound.arguments.items()
+            if name != "self"
+        }
+        self._generated_ids = []
+        try:
+            result = await method(self, *args, **kwargs)
+            self.wal.append(method.__name__, arguments, self._generated_ids)
+        finally:
+            self._generated_ids = None
+        return result
+
+    return wrapper  # type: ignore
+
 
 class InmemoryStorage(Storage):
-    """Lacks synchronization, only for testing purposes"""
+    """
+    For testing purposes and small dev/self-hosted instances; with a snapshot file the contents
+    are saved periodically and on shutdown, and loaded on startup.
 
-    def __init__(self) -> None:
+    Lacks synchronization and needs none: the data is kept in per-user maps and the methods never
+    suspend on the event loop, so each call is atomic and no call waits for another. Keep it this
+    way, an await on anything that actually suspends (I/O, sleeps) inside a method breaks it
+    """
+
+    def __init__(
+        self,
+        snapshot_path: Path | None = None,
+        snapshot_interval_sec: float = DEFAULT_SNAPSHOT_INTERVAL_SEC,
+        wal_path: Path | None = None,
+    ) -> None:
+        self.snapshot_path = snapshot_path
+        self.snapshot_interval_sec = snapshot_interval_sec
+        self._snapshot_task: asyncio.Task | None = None
+        self.wal = WriteAheadLog(wal_path) if wal_path is not None else None
+        self._generated_ids: list[str] | None = None  # by the mutation being logged
+        self._replayed_ids: collections.deque[str] = collections.deque()
+        self.clear()
+
+    def clear(self) -> None:
+        """Drops all users' data in memory, the snapshot file and the WAL are left as they are"""
         self._user_transactions: dict[UserId, list[StoredTransaction]] = {}
         self._user_pools: dict[UserId, list[StoredMoneyPool]] = {}
+        self._user_reconciliations: dict[UserId, list[StoredReconciliation]] = {}
+        self._user_report_snapshots: dict[UserId, list[StoredReportSnapshot]] = {}
+        self._user_allowances: dict[UserId, list[StoredAllowance]] = {}
+        self._user_challenges: dict[UserId, list[StoredChallenge]] = {}
+        self._user_goals: dict[UserId, list[StoredGoal]] = {}
+        self._user_debts: dict[UserId, list[StoredDebt]] = {}
+        self._user_templates: dict[UserId, list[StoredTransactionTemplate]] = {}
+        self._user_rules: dict[UserId, list[StoredCategorizationRule]] = {}
+        self._user_payees: dict[UserId, list[StoredPayee]] = {}
+        self._user_notes: dict[UserId, list[StoredPoolNote]] = {}
+        self._user_balance_snapshots: dict[UserId, list[StoredBalanceSnapshot]] = {}
+        self._user_settings: dict[UserId, UserSettings] = {}
+        self._user_month_closes: dict[UserId, list[MonthClose]] = {}
+        self._user_net_worth_snapshots: dict[UserId, list[NetWorthSnapshot]] = {}
+        self._user_calendar_tokens: dict[UserId, str] = {}
+        self._user_two_factors: dict[UserId, TwoFactor] = {}
+        self._user_operations: dict[UserId, list[StoredOperation]] = {}
+        self._user_change_counters: dict[UserId, int] = {}
+        self._user_changes: dict[UserId, list[EntityChange]] = {}
+        self._audit_entries: list[AuditEntry] = []
+        self._historical_rates: dict[datetime.date, DailyRates] = {}
+        self._disabled_user_ids: set[UserId] = set()
+
+    async def initialize(self) -> None:
+        wal_seq = 0
+        if self.snapshot_path is not None and self.snapshot_path.exists():
+            snapshot = InmemorySnapshot.model_validate_json(self.snapshot_path.read_bytes())
+            self.restore_snapshot(snapshot)
+            wal_seq = snapshot.wal_seq
+            logger.info(f"Loaded in-memory storage snapshot from {self.snapshot_path}")
+        if self.wal is not None:
+            records = [r for r in self.wal.read() if r.seq > wal_seq]
+            for record in records:
+                await self._replay(record)
+            self.wal.last_seq = max(self.wal.last_seq, wal_seq)
+            logger.info(f"Replayed {len(records)} mutations from {self.wal.path}")
+        if self.snapshot_path is not None:
+            self._snapshot_task = asyncio.create_task(self._save_snapshots_periodically())
+
+    async def close(self) -> None:
+        if self._snapshot_task is not None:
+            self._snapshot_task.cancel()
+            self._snapshot_task = None
+        await self.save_snapshot()
+        if self.wal is not None:
+            self.wal.close()
+
+    async def _replay(self, record: WalRecord) -> None:
+        method = getattr(type(self), record.method).__wrapped__
+        hints = get_type_hints(method)
+        arguments = {
+            name: pydantic.TypeAdapter(hints[name]).validate_python(value)
+            for name, value in record.args.items()
+        }
+        self._replayed_ids = collections.deque(record.ids)
+        await method(self, **arguments)
+
+    def _new_id(self) -> str:
+        if self._replayed_ids:
+            return self._replayed_ids.popleft()
+        new_id = str(uuid.uuid4())
+        if self._generated_ids is not None:
+            self._generated_ids.append(new_id)
+        return new_id
+
+    def snapshot(self) -> InmemorySnapshot:
+        return InmemorySnapshot(
+            **self._all_user_entities(),
+            audit_entries=self._audit_entries,
+            historical_rates=list(self._historical_rates.values()),
+            disabled_user_ids=sorted(self._disabled_user_ids),
+            wal_seq=self.wal.last_seq if self.wal is not None else 0,
+        )
+
+    def restore_snapshot(self, snapshot: InmemorySnapshot) -> None:
+        for name, user_entities in self._all_user_entities().items():
+            user_entities.clear()
+            user_entities.update(getattr(snapshot, name))
+        self._audit_entries = snapshot.audit_entries
+        self._historical_rates = {day.date: day for day in snapshot.historical_rates}
+        self._disabled_user_ids = set(snapshot.disabled_user_ids)
+
+    async def save_snapshot(self) -> None:
+        if self.snapshot_path is None:
+            return
+        # serialized at once, so that concurrent requests don't change the data midway
+        snapshot = self.snapshot()
+        data = snapshot.model_dump_json()
+        await asyncio.to_thread(self._write_snapshot, self.snapshot_path, data)
+        if self.wal is not None:
+            self.wal.truncate(through_seq=snapshot.wal_seq)
+
+    @staticmethod
+    def _write_snapshot(path: Path, data: str) -> None:
+        # the previous snapshot stays intact if the process dies while writing
+        tmp_path = path.with_name(path.name + ".tmp")
+        tmp_path.write_text(data)
+        os.replace(tmp_path, path)
+
+    async def _save_snapshots_periodically(self) -> None:
+        while True:
+            await asyncio.sleep(self.snapshot_interval_sec)
+            try:
+                await self.save_snapshot()
+            except Exception:
+                logger.exception("Error saving in-memory storage snapshot")
+
+    def _record_change(
+        self, user_id: UserId, entity: SyncedEntity, entity_id: str, deleted: bool = False
+    ) -> None:
+        seq = self._user_change_counters.get(user_id, 0) + 1
+        self._user_change_counters[user_id] = seq
+        changes = [
+            c
+            for c in self._user_changes.get(user_id, [])
+            if (c.entity, c.entity_id) != (entity, entity_id)
+        ]
+        changes.append(EntityChange(seq=seq, entity=entity, entity_id=entity_id, deleted=deleted))
+        self._user_changes[user_id] = changes
 
+    async def load_change_counter(self, user_id: UserId) -> int:
+        return self._user_change_counters.get(user_id, 0)
+
+    async def load_changes(self, user_id: UserId, since: int) -> list[EntityChange]:
+        return copy.deepcopy([c for c in self._user_changes.get(user_id, []) if c.seq > since])
+
+    @logged_mutation
     async def add_pool(self, user_id: UserId, new_pool: MoneyPool) -> StoredMoneyPool:
-        stored_pool = StoredMoneyPool.from_money_pool(new_pool, id=str(uuid.uuid4()))
+        stored_pool = StoredMoneyPool.from_money_pool(new_pool, id=self._new_id())
         self._user_pools.setdefault(user_id, []).append(stored_pool)
+        self._record_change(user_id, SyncedEntity.POOL, stored_pool.id)
         return copy.deepcopy(stored_pool)
 
+    @logged_mutation
     async def add_balance_to_pool(
         self, user_id: UserId, pool_id: UserId, new_balance: MoneySum
     ) -> bool:
@@ -107,26 +854,51 @@ class InmemoryStorage(Storage):
             return False
         if new_balance.currency not in [s.currency for s in p.balance]:
             p.balance.append(new_balance)
+            if p.initial_balance is not None:
+                p.initial_balance.append(copy.deepcopy(new_balance))
+            self._record_change(user_id, SyncedEntity.POOL, pool_id)
             return True
         else:
             raise ValueError(f"Balance already has currency {new_balance.currency.code}")
 
+    @logged_mutation
     async def set_pool_attributes(
-        self, user_id: UserId, pool_id: MoneyPoolId, update: MoneyPoolAttributesUpdate
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        update: MoneyPoolAttributesUpdate,
+        expected_version: int | None = None,
     ) -> bool:
         p = await self._load_pool_internal(user_id, pool_id)
         if p is None:
             return False
+        if expected_version is not None and p.version != expected_version:
+            raise VersionConflict(expected_version, p.version)
+        p.version += 1
         p.is_visible = update.is_visible or p.is_visible
         p.display_name = update.display_name or p.display_name
         p.display_color = update.display_color or p.display_color
+        p.icon = update.icon or p.icon
+        p.color_hex = update.color_hex or p.color_hex
+        p.overdraft = update.overdraft or p.overdraft
+        p.group = update.group or p.group
+        p.default_category = update.default_category or p.default_category
+        self._record_change(user_id, SyncedEntity.POOL, pool_id)
         return True
 
+    @logged_mutation
+    async def set_pools_order(self, user_id: UserId, pool_ids: list[MoneyPoolId]) -> None:
+        sort_orders = {pool_id: idx for idx, pool_id in enumerate(pool_ids)}
+        for p in await self._load_pools_internal(user_id):
+            p.sort_order = sort_orders.get(p.id, p.sort_order)
+            self._record_change(user_id, SyncedEntity.POOL, p.id)
+
     async def _load_pools_internal(self, user_id: UserId) -> list[StoredMoneyPool]:
         return self._user_pools.get(user_id, [])
 
     async def load_pools(self, user_id: UserId) -> list[StoredMoneyPool]:
-        return copy.deepcopy(await self._load_pools_internal(user_id))
+        pools = await self._load_pools_internal(user_id)
+        return copy.deepcopy(sorted(pools, key=lambda p: p.sort_order))
 
     async def _load_pool_internal(
         self, user_id: UserId, pool_id: MoneyPoolId
@@ -137,16 +909,106 @@ class InmemoryStorage(Storage):
     async def load_pool(self, user_id: UserId, pool_id: MoneyPoolId) -> StoredMoneyPool | None:
         return copy.deepcopy(await self._load_pool_internal(user_id, pool_id))
 
+    @logged_mutation
+    async def save_pool_balance(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        balance: list[MoneySum],
+        initial_balance: list[MoneySum] | None,
+    ) -> bool:
+        p = await self._load_pool_internal(user_id, pool_id)
+        if p is None:
+            return False
+        p.balance = copy.deepcopy(balance)
+        p.initial_balance = copy.deepcopy(initial_balance)
+        self._record_change(user_id, SyncedEntity.POOL, pool_id)
+        return True
+
+    @logged_mutation
+    async def redenominate_pool(self, user_id: UserId, redenomination: PoolRedenomination) -> bool:
+        p = await self._load_pool_internal(user_id, redenomination.pool_id)
+        if p is None:
+            return False
+        p.balance = copy.deepcopy(redenomination.balance)
+        p.initial_balance = copy.deepcopy(redenomination.initial_balance)
+        for conversion in redenomination.transactions:
+            res = self._lookup_transaction(user_id, conversion.transaction_id)
+            if res is None:
+                continue
+            _, t = res
+            t.sum = conversion.sum.model_copy()
+            t.splits = copy.deepcopy(conversion.splits)
+            t.original_currency = conversion.original_currency
+            t.original_amount = conversion.original_amount
+            t.version += 1
+            self._record_change(user_id, SyncedEntity.TRANSACTION, t.id)
+        self._record_change(user_id, SyncedEntity.POOL, p.id)
+        return True
+
+    @logged_mutation
     async def add_transaction(self, user_id: str, transaction: Transaction) -> StoredTransaction:
         pool = await self._load_pool_internal(user_id, transaction.pool_id)
         if pool is None:
             raise ValueError("Transaction attributed to non-existent pool")
         pool.update_with_transaction(transaction)
-        stored = StoredTransaction.from_transaction(transaction, id=str(uuid.uuid4()))
-        self._user_transactions.setdefault(user_id, []).append(stored)
-        self._user_transactions[user_id].sort(key=lambda t: t.timestamp)
+        stored = StoredTransaction.from_transaction(transaction, id=self._new_id())
+        self._insert_transaction(user_id, stored)
+        self._record_change(user_id, SyncedEntity.POOL, pool.id)
+        self._record_change(user_id, SyncedEntity.TRANSACTION, stored.id)
         return copy.deepcopy(stored)
 
+    def _insert_transaction(self, user_id: UserId, transaction: StoredTransaction) -> None:
+        """Keeps the user's transactions sorted by timestamp, after the ones with the same"""
+        bisect.insort(
+            self._user_transactions.setdefault(user_id, []),
+            transaction,
+            key=lambda t: t.timestamp,
+        )
+
+    @logged_mutation
+    async def restore_transactions(
+        self, user_id: UserId, transactions: list[StoredTransaction]
+    ) -> None:
+        existing_ids = {t.id for t in self._user_transactions.get(user_id, [])}
+        for transaction in transactions:
+            if transaction.id in existing_ids:
+                raise IdConflict("transaction", transaction.id)
+        for transaction in transactions:
+            pool = await self._load_pool_internal(user_id, transaction.pool_id)
+            if pool is None:
+                raise ValueError("Transaction attributed to non-existent pool")
+            pool.update_with_transaction(transaction)
+            self._user_transactions.setdefault(user_id, []).append(copy.deepcopy(transaction))
+            self._record_change(user_id, SyncedEntity.POOL, pool.id)
+            self._record_change(user_id, SyncedEntity.TRANSACTION, transaction.id)
+        self._user_transactions.get(user_id, []).sort(key=lambda t: t.timestamp)
+
+    @logged_mutation
+    async def apply_transaction_changes(
+        self, user_id: UserId, new: list[Transaction], edits: list[TransactionEdit]
+    ) -> list[StoredTransaction]:
+        # everything is checked upfront, nothing can fail once the changes are being made
+        for transaction in new:
+            if await self._load_pool_internal(user_id, transaction.pool_id) is None:
+                raise ValueError("Transaction attributed to non-existent pool")
+        versions: dict[TransactionId, int | None] = {}
+        for edit in edits:
+            if edit.transaction_id not in versions:
+                res = self._lookup_transaction(user_id, edit.transaction_id)
+                versions[edit.transaction_id] = res[1].version if res is not None else None
+            version = versions[edit.transaction_id]
+            if version != edit.expected_version:
+                raise VersionConflict(edit.expected_version, version, edit.transaction_id)
+            versions[edit.transaction_id] = version + 1 if edit.update is not None else None
+        added = [await self.add_transaction(user_id, t) for t in new]
+        for edit in edits:
+            if edit.update is not None:
+                await self.update_transaction(user_id, edit.transaction_id, edit.update)
+            else:
+                await self.delete_transaction(user_id, edit.transaction_id)
+        return added
+
     async def load_transactions(
         self,
         user_id: UserId,
@@ -158,10 +1020,10 @@ class InmemoryStorage(Storage):
         transactions = self._user_transactions.get(user_id, [])
         if filter is not None:
             transactions = [t for t in transactions if filter.matches(t)]
-        transactions.sort(key=order.key, reverse=True)  # reverse True to get "most fitting" last
-        end = len(transactions) - offset
-        start = end - count - 1
-        return copy.deepcopy(transactions[start:end])
+        # not in place, the stored list stays sorted by timestamp; stable sort keeps the ties in
+        # the order they were added
+        transactions = sorted(transactions, key=order.key, reverse=True)  # "most fitting" first
+        return copy.deepcopy(transactions[offset : offset + count])
 
     def _lookup_transaction(
         self, user_id: UserId, transaction_id: TransactionId
@@ -174,6 +1036,7 @@ class InmemoryStorage(Storage):
             return None
         return matching_transactions[0]
 
+    @logged_mutation
     async def delete_transaction(self, user_id: UserId, transaction_id: TransactionId) -> bool:
         res = self._lookup_transaction(user_id, transaction_id)
         if res is None:
@@ -185,25 +1048,659 @@ class InmemoryStorage(Storage):
         assert pool is not None
         pool.update_with_transaction(deleted)
         self._user_transactions[user_id].pop(deleted_idx)
+        self._record_change(user_id, SyncedEntity.POOL, pool.id)
+        self._record_change(user_id, SyncedEntity.TRANSACTION, transaction_id, deleted=True)
         return True
 
+    @logged_mutation
     async def update_transaction(
-        self, user_id: UserId, transaction_id: TransactionId, update: TransactionUpdate
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        update: TransactionUpdate,
+        expected_version: int | None = None,
     ) -> bool:
         res = self._lookup_transaction(user_id, transaction_id)
         if res is None:
             return False
         modified_idx, modified = res
+        if expected_version is not None and modified.version != expected_version:
+            raise VersionConflict(expected_version, modified.version)
         modified = copy.deepcopy(modified)
         update.apply(modified)
-        self._user_transactions[user_id][modified_idx] = modified
+        modified.version += 1
+        if modified.timestamp == self._user_transactions[user_id][modified_idx].timestamp:
+            self._user_transactions[user_id][modified_idx] = modified
+        else:
+            self._user_transactions[user_id].pop(modified_idx)
+            self._insert_transaction(user_id, modified)
+        self._record_change(user_id, SyncedEntity.TRANSACTION, transaction_id)
         return True
 
+    @logged_mutation
+    async def update_transaction_status(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        status: TransactionStatus,
+        expected_version: int | None = None,
+    ) -> bool:
+        res = self._lookup_transaction(user_id, transaction_id)
+        if res is None:
+            return False
+        _, modified = res
+        if expected_version is not None and modified.version != expected_version:
+            raise VersionConflict(expected_version, modified.version)
+        if modified.status.is_counted != status.is_counted:
+            pool = await self._load_pool_internal(user_id, modified.pool_id)
+            assert pool is not None
+            effect = modified.model_copy(update={"status": TransactionStatus.CLEARED})
+            pool.update_with_transaction(effect if status.is_counted else effect.inverted())
+            self._record_change(user_id, SyncedEntity.POOL, pool.id)
+        modified.status = status
+        modified.version += 1
+        self._record_change(user_id, SyncedEntity.TRANSACTION, transaction_id)
+        return True
 
-def validate_object_id(v: Any) -> str:
-    if isinstance(v, ObjectId):
-        return str(v)
-    else:
+    async def load_due_scheduled_transactions(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredTransaction]]:
+        return [
+            (user_id, copy.deepcopy(t))
+            for user_id, transactions in self._user_transactions.items()
+            for t in transactions
+            if t.status is TransactionStatus.SCHEDULED
+            and t.timestamp.timestamp() <= now.timestamp()
+        ]
+
+    async def load_suggestions(
+        self,
+        user_id: UserId,
+        field: SuggestionField,
+        query: str,
+        since: datetime.datetime,
+        count: int,
+    ) -> list[Suggestion]:
+        transactions = self._user_transactions.get(user_id, [])
+        first_idx = bisect.bisect_left(
+            transactions, since.timestamp(), key=lambda t: t.timestamp.timestamp()
+        )
+        return count_suggestions(
+            transactions[first_idx:], field, query, self._user_payees.get(user_id, []), count
+        )
+
+    @logged_mutation
+    async def add_reconciliation(
+        self, user_id: UserId, reconciliation: Reconciliation
+    ) -> StoredReconciliation:
+        stored = StoredReconciliation.from_reconciliation(reconciliation, id=self._new_id())
+        self._user_reconciliations.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_reconciliations(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredReconciliation]:
+        return copy.deepcopy(
+            [
+                r
+                for r in self._user_reconciliations.get(user_id, [])
+                if pool_id is None or r.pool_id == pool_id
+            ]
+        )
+
+    async def load_reconciliation(
+        self, user_id: UserId, reconciliation_id: ReconciliationId
+    ) -> StoredReconciliation | None:
+        for r in self._user_reconciliations.get(user_id, []):
+            if r.id == reconciliation_id:
+                return copy.deepcopy(r)
+        return None
+
+    @logged_mutation
+    async def save_reconciliation(
+        self, user_id: UserId, reconciliation: StoredReconciliation
+    ) -> bool:
+        user_reconciliations = self._user_reconciliations.get(user_id, [])
+        for idx, r in enumerate(user_reconciliations):
+            if r.id == reconciliation.id:
+                user_reconciliations[idx] = copy.deepcopy(reconciliation)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_report_snapshot(
+        self, user_id: UserId, snapshot: ReportSnapshot
+    ) -> StoredReportSnapshot:
+        stored = StoredReportSnapshot.from_report_snapshot(snapshot, id=self._new_id())
+        self._user_report_snapshots.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_report_snapshots(self, user_id: UserId) -> list[StoredReportSnapshot]:
+        # stable sort on the reversed list for the later added to go first on equal times
+        snapshots = sorted(
+            reversed(self._user_report_snapshots.get(user_id, [])),
+            key=lambda rs: rs.created_at,
+            reverse=True,
+        )
+        return copy.deepcopy(snapshots[:MAX_REPORT_SNAPSHOTS])
+
+    async def load_report_snapshot(
+        self, user_id: UserId, snapshot_id: ReportSnapshotId
+    ) -> StoredReportSnapshot | None:
+        for rs in self._user_report_snapshots.get(user_id, []):
+            if rs.id == snapshot_id:
+                return copy.deepcopy(rs)
+        return None
+
+    @logged_mutation
+    async def add_allowance(self, user_id: UserId, allowance: Allowance) -> StoredAllowance:
+        stored = StoredAllowance.from_allowance(allowance, id=self._new_id())
+        self._user_allowances.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_allowances(self, user_id: UserId) -> list[StoredAllowance]:
+        return copy.deepcopy(self._user_allowances.get(user_id, []))
+
+    async def load_allowance(
+        self, user_id: UserId, allowance_id: AllowanceId
+    ) -> StoredAllowance | None:
+        for a in self._user_allowances.get(user_id, []):
+            if a.id == allowance_id:
+                return copy.deepcopy(a)
+        return None
+
+    @logged_mutation
+    async def save_allowance(self, user_id: UserId, allowance: StoredAllowance) -> bool:
+        user_allowances = self._user_allowances.get(user_id, [])
+        for idx, a in enumerate(user_allowances):
+            if a.id == allowance.id:
+                user_allowances[idx] = copy.deepcopy(allowance)
+                return True
+        return False
+
+    async def load_allowance_by_view_token(
+        self, view_token: str
+    ) -> tuple[UserId, StoredAllowance] | None:
+        for user_id, allowances in self._user_allowances.items():
+            for a in allowances:
+                if a.view_token == view_token:
+                    return user_id, copy.deepcopy(a)
+        return None
+
+    async def load_due_allowances(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredAllowance]]:
+        return [
+            (user_id, copy.deepcopy(a))
+            for user_id, allowances in self._user_allowances.items()
+            for a in allowances
+            if a.next_payment_at.timestamp() <= now.timestamp()
+        ]
+
+    @logged_mutation
+    async def add_challenge(self, user_id: UserId, challenge: Challenge) -> StoredChallenge:
+        stored = StoredChallenge.from_challenge(challenge, id=self._new_id())
+        self._user_challenges.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_challenges(self, user_id: UserId) -> list[StoredChallenge]:
+        return copy.deepcopy(self._user_challenges.get(user_id, []))
+
+    async def load_challenge(
+        self, user_id: UserId, challenge_id: ChallengeId
+    ) -> StoredChallenge | None:
+        for c in self._user_challenges.get(user_id, []):
+            if c.id == challenge_id:
+                return copy.deepcopy(c)
+        return None
+
+    @logged_mutation
+    async def save_challenge(self, user_id: UserId, challenge: StoredChallenge) -> bool:
+        user_challenges = self._user_challenges.get(user_id, [])
+        for idx, c in enumerate(user_challenges):
+            if c.id == challenge.id:
+                user_challenges[idx] = copy.deepcopy(challenge)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_goal(self, user_id: UserId, goal: Goal) -> StoredGoal:
+        stored = StoredGoal.from_goal(goal, id=self._new_id())
+        self._user_goals.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_goals(self, user_id: UserId) -> list[StoredGoal]:
+        return copy.deepcopy(self._user_goals.get(user_id, []))
+
+    async def load_goal(self, user_id: UserId, goal_id: GoalId) -> StoredGoal | None:
+        for g in self._user_goals.get(user_id, []):
+            if g.id == goal_id:
+                return copy.deepcopy(g)
+        return None
+
+    @logged_mutation
+    async def save_goal(self, user_id: UserId, goal: StoredGoal) -> bool:
+        user_goals = self._user_goals.get(user_id, [])
+        for idx, g in enumerate(user_goals):
+            if g.id == goal.id:
+                user_goals[idx] = copy.deepcopy(goal)
+                return True
+        return False
+
+    @logged_mutation
+    async def delete_goal(self, user_id: UserId, goal_id: GoalId) -> bool:
+        user_goals = self._user_goals.get(user_id, [])
+        for idx, g in enumerate(user_goals):
+            if g.id == goal_id:
+                user_goals.pop(idx)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_debt(self, user_id: UserId, debt: Debt) -> StoredDebt:
+        stored = StoredDebt.from_debt(debt, id=self._new_id())
+        self._user_debts.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_debts(self, user_id: UserId) -> list[StoredDebt]:
+        return copy.deepcopy(self._user_debts.get(user_id, []))
+
+    async def load_debt(self, user_id: UserId, debt_id: DebtId) -> StoredDebt | None:
+        for d in self._user_debts.get(user_id, []):
+            if d.id == debt_id:
+                return copy.deepcopy(d)
+        return None
+
+    @logged_mutation
+    async def save_debt(self, user_id: UserId, debt: StoredDebt) -> bool:
+        user_debts = self._user_debts.get(user_id, [])
+        for idx, d in enumerate(user_debts):
+            if d.id == debt.id:
+                user_debts[idx] = copy.deepcopy(debt)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_transaction_template(
+        self, user_id: UserId, template: TransactionTemplate
+    ) -> StoredTransactionTemplate:
+        stored = StoredTransactionTemplate.from_template(template, id=self._new_id())
+        self._user_templates.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_transaction_templates(
+        self, user_id: UserId
+    ) -> list[StoredTransactionTemplate]:
+        return copy.deepcopy(self._user_templates.get(user_id, []))
+
+    async def load_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> StoredTransactionTemplate | None:
+        for t in self._user_templates.get(user_id, []):
+            if t.id == template_id:
+                return copy.deepcopy(t)
+        return None
+
+    @logged_mutation
+    async def save_transaction_template(
+        self, user_id: UserId, template: StoredTransactionTemplate
+    ) -> bool:
+        user_templates = self._user_templates.get(user_id, [])
+        for idx, t in enumerate(user_templates):
+            if t.id == template.id:
+                user_templates[idx] = copy.deepcopy(template)
+                return True
+        return False
+
+    @logged_mutation
+    async def delete_transaction_template(self, user_id: UserId, template_id: TemplateId) -> bool:
+        user_templates = self._user_templates.get(user_id, [])
+        for idx, t in enumerate(user_templates):
+            if t.id == template_id:
+                user_templates.pop(idx)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_rule(
+        self, user_id: UserId, rule: CategorizationRule
+    ) -> StoredCategorizationRule:
+        stored = StoredCategorizationRule.from_rule(rule, id=self._new_id())
+        self._user_rules.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_rules(self, user_id: UserId) -> list[StoredCategorizationRule]:
+        return copy.deepcopy(self._user_rules.get(user_id, []))
+
+    async def load_rule(
+        self, user_id: UserId, rule_id: RuleId
+    ) -> StoredCategorizationRule | None:
+        for r in self._user_rules.get(user_id, []):
+            if r.id == rule_id:
+                return copy.deepcopy(r)
+        return None
+
+    @logged_mutation
+    async def save_rule(self, user_id: UserId, rule: StoredCategorizationRule) -> bool:
+        user_rules = self._user_rules.get(user_id, [])
+        for idx, r in enumerate(user_rules):
+            if r.id == rule.id:
+                user_rules[idx] = copy.deepcopy(rule)
+                return True
+        return False
+
+    @logged_mutation
+    async def delete_rule(self, user_id: UserId, rule_id: RuleId) -> bool:
+        user_rules = self._user_rules.get(user_id, [])
+        for idx, r in enumerate(user_rules):
+            if r.id == rule_id:
+                user_rules.pop(idx)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_payee(self, user_id: UserId, payee: Payee) -> StoredPayee:
+        stored = StoredPayee.from_payee(payee, id=self._new_id())
+        self._user_payees.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_payees(self, user_id: UserId) -> list[StoredPayee]:
+        payees = self._user_payees.get(user_id, [])
+        return copy.deepcopy(sorted(payees, key=lambda p: p.name))
+
+    async def load_payee(self, user_id: UserId, payee_id: PayeeId) -> StoredPayee | None:
+        for p in self._user_payees.get(user_id, []):
+            if p.id == payee_id:
+                return copy.deepcopy(p)
+        return None
+
+    @logged_mutation
+    async def save_payee(self, user_id: UserId, payee: StoredPayee) -> bool:
+        user_payees = self._user_payees.get(user_id, [])
+        for idx, p in enumerate(user_payees):
+            if p.id == payee.id:
+                user_payees[idx] = copy.deepcopy(payee)
+                return True
+        return False
+
+    @logged_mutation
+    async def delete_payee(self, user_id: UserId, payee_id: PayeeId) -> bool:
+        user_payees = self._user_payees.get(user_id, [])
+        for idx, p in enumerate(user_payees):
+            if p.id == payee_id:
+                user_payees.pop(idx)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_pool_note(self, user_id: UserId, note: PoolNote) -> StoredPoolNote:
+        stored = StoredPoolNote.from_note(note, id=self._new_id())
+        self._user_notes.setdefault(user_id, []).append(stored)
+        return copy.deepcopy(stored)
+
+    async def load_pool_notes(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredPoolNote]:
+        return copy.deepcopy(
+            [
+                n
+                for n in self._user_notes.get(user_id, [])
+                if pool_id is None or n.pool_id == pool_id
+            ]
+        )
+
+    async def load_pool_note(self, user_id: UserId, note_id: NoteId) -> StoredPoolNote | None:
+        for n in self._user_notes.get(user_id, []):
+            if n.id == note_id:
+                return copy.deepcopy(n)
+        return None
+
+    @logged_mutation
+    async def save_pool_note(self, user_id: UserId, note: StoredPoolNote) -> bool:
+        user_notes = self._user_notes.get(user_id, [])
+        for idx, n in enumerate(user_notes):
+            if n.id == note.id:
+                user_notes[idx] = copy.deepcopy(note)
+                return True
+        return False
+
+    @logged_mutation
+    async def delete_pool_note(self, user_id: UserId, note_id: NoteId) -> bool:
+        user_notes = self._user_notes.get(user_id, [])
+        for idx, n in enumerate(user_notes):
+            if n.id == note_id:
+                user_notes.pop(idx)
+                return True
+        return False
+
+    @logged_mutation
+    async def add_balance_snapshot(
+        self, user_id: UserId, snapshot: BalanceSnapshot
+    ) -> StoredBalanceSnapshot:
+        stored = StoredBalanceSnapshot.from_balance_snapshot(snapshot, id=self._new_id())
+        user_snapshots = self._user_balance_snapshots.setdefault(user_id, [])
+        bisect.insort(user_snapshots, stored, key=lambda s: s.timestamp)
+        return copy.deepcopy(stored)
+
+    async def load_balance_snapshots(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredBalanceSnapshot]:
+        return copy.deepcopy(
+            [
+                s
+                for s in self._user_balance_snapshots.get(user_id, [])
+                if pool_id is None or s.pool_id == pool_id
+            ]
+        )
+
+    async def load_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> StoredBalanceSnapshot | None:
+        for s in self._user_balance_snapshots.get(user_id, []):
+            if s.id == snapshot_id:
+                return copy.deepcopy(s)
+        return None
+
+    @logged_mutation
+    async def delete_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> bool:
+        user_snapshots = self._user_balance_snapshots.get(user_id, [])
+        for idx, s in enumerate(user_snapshots):
+            if s.id == snapshot_id:
+                user_snapshots.pop(idx)
+                return True
+        return False
+
+    async def load_user_settings(self, user_id: UserId) -> UserSettings:
+        return copy.deepcopy(self._user_settings.get(user_id, UserSettings()))
+
+    @logged_mutation
+    async def save_user_settings(self, user_id: UserId, settings: UserSettings) -> None:
+        self._user_settings[user_id] = copy.deepcopy(settings)
+
+    async def load_month_closes(self, user_id: UserId) -> list[MonthClose]:
+        return copy.deepcopy(self._user_month_closes.get(user_id, []))
+
+    async def load_month_close(self, user_id: UserId, year: int, month: int) -> MonthClose | None:
+        for mc in self._user_month_closes.get(user_id, []):
+            if mc.year == year and mc.month == month:
+                return copy.deepcopy(mc)
+        return None
+
+    @logged_mutation
+    async def save_month_close(self, user_id: UserId, month_close: MonthClose) -> None:
+        user_month_closes = self._user_month_closes.setdefault(user_id, [])
+        for idx, mc in enumerate(user_month_closes):
+            if mc.key == month_close.key:
+                user_month_closes[idx] = copy.deepcopy(month_close)
+                return
+        user_month_closes.append(copy.deepcopy(month_close))
+
+    @logged_mutation
+    async def save_net_worth_snapshot(self, user_id: UserId, snapshot: NetWorthSnapshot) -> None:
+        user_snapshots = self._user_net_worth_snapshots.setdefault(user_id, [])
+        for idx, s in enumerate(user_snapshots):
+            if s.date == snapshot.date:
+                user_snapshots[idx] = copy.deepcopy(snapshot)
+                return
+        user_snapshots.append(copy.deepcopy(snapshot))
+        user_snapshots.sort(key=lambda s: s.date)
+
+    async def load_net_worth_snapshots(
+        self, user_id: UserId, start: datetime.date, end: datetime.date
+    ) -> list[NetWorthSnapshot]:
+        return [
+            copy.deepcopy(s)
+            for s in self._user_net_worth_snapshots.get(user_id, [])
+            if start <= s.date <= end
+        ]
+
+    @logged_mutation
+    async def save_calendar_token(self, user_id: UserId, token: str | None) -> None:
+        if token is None:
+            self._user_calendar_tokens.pop(user_id, None)
+        else:
+            self._user_calendar_tokens[user_id] = token
+
+    async def load_calendar_token(self, user_id: UserId) -> str | None:
+        return self._user_calendar_tokens.get(user_id)
+
+    async def load_calendar_token_owner(self, token: str) -> UserId | None:
+        for user_id, user_token in self._user_calendar_tokens.items():
+            if user_token == token:
+                return user_id
+        return None
+
+    @logged_mutation
+    async def save_two_factor(self, user_id: UserId, two_factor: TwoFactor | None) -> None:
+        if two_factor is None:
+            self._user_two_factors.pop(user_id, None)
+        else:
+            self._user_two_factors[user_id] = copy.deepcopy(two_factor)
+
+    async def load_two_factor(self, user_id: UserId) -> TwoFactor | None:
+        return copy.deepcopy(self._user_two_factors.get(user_id))
+
+    @logged_mutation
+    async def log_operation(
+        self, user_id: UserId, operation: Operation, retention: datetime.timedelta
+    ) -> StoredOperation:
+        stored = StoredOperation.from_operation(operation, id=self._new_id())
+        user_operations = [
+            o
+            for o in self._user_operations.get(user_id, [])
+            if o.timestamp >= operation.timestamp - retention
+        ]
+        user_operations.append(stored)
+        self._user_operations[user_id] = user_operations
+        return copy.deepcopy(stored)
+
+    @logged_mutation
+    async def pop_last_operation(
+        self, user_id: UserId, since: datetime.datetime
+    ) -> StoredOperation | None:
+        user_operations = self._user_operations.get(user_id, [])
+        if not user_operations or user_operations[-1].timestamp < since:
+            return None
+        return user_operations.pop()
+
+    @logged_mutation
+    async def append_audit_entry(self, entry: AuditEntry) -> None:
+        self._audit_entries.append(copy.deepcopy(entry))
+
+    async def load_audit_entries(
+        self, user_id: UserId, entity_id: str | None, offset: int, count: int
+    ) -> list[AuditEntry]:
+        entries = [
+            e
+            for e in reversed(self._audit_entries)
+            if e.user_id == user_id and (entity_id is None or e.entity_id == entity_id)
+        ]
+        return copy.deepcopy(entries[offset : offset + count])
+
+    @logged_mutation
+    async def save_historical_rates(self, days: list[DailyRates]) -> None:
+        for day in days:
+            self._historical_rates[day.date] = copy.deepcopy(day)
+
+    async def load_historical_rates(self, on: datetime.date) -> DailyRates | None:
+        for days_before in range(HISTORICAL_RATES_MAX_GAP_DAYS + 1):
+            day = self._historical_rates.get(on - datetime.timedelta(days=days_before))
+            if day is not None:
+                return copy.deepcopy(day)
+        return None
+
+    async def load_historical_rates_between(
+        self, start: datetime.date, end: datetime.date
+    ) -> list[DailyRates]:
+        return copy.deepcopy(
+            sorted(
+                (day for day in self._historical_rates.values() if start <= day.date <= end),
+                key=lambda day: day.date,
+            )
+        )
+
+    def _all_user_entities(self) -> dict[str, dict[UserId, Any]]:
+        """Keyed by the names of the MongoDB collections, for the same storage usage reports"""
+        return {
+            "transactions": self._user_transactions,
+            "pools": self._user_pools,
+            "reconciliations": self._user_reconciliations,
+            "report_snapshots": self._user_report_snapshots,
+            "allowances": self._user_allowances,
+            "challenges": self._user_challenges,
+            "goals": self._user_goals,
+            "debts": self._user_debts,
+            "transaction_templates": self._user_templates,
+            "categorization_rules": self._user_rules,
+            "payees": self._user_payees,
+            "pool_notes": self._user_notes,
+            "balance_snapshots": self._user_balance_snapshots,
+            "user_settings": self._user_settings,
+            "month_closes": self._user_month_closes,
+            "net_worth_snapshots": self._user_net_worth_snapshots,
+            "calendar_tokens": self._user_calendar_tokens,
+            "two_factors": self._user_two_factors,
+            "operations": self._user_operations,
+            "change_counters": self._user_change_counters,
+            "changes": self._user_changes,
+        }
+
+    @logged_mutation
+    async def delete_user_data(self, user_id: UserId) -> None:
+        for user_entities in self._all_user_entities().values():
+            user_entities.pop(user_id, None)
+        self._audit_entries = [e for e in self._audit_entries if e.user_id != user_id]
+
+    async def load_user_ids(self) -> list[UserId]:
+        return [user_id for user_id, pools in self._user_pools.items() if pools]
+
+    async def load_storage_usage(self, user_id: UserId) -> dict[str, int]:
+        usage: dict[str, int] = {}
+        for name, user_entities in self._all_user_entities().items():
+            entities = user_entities.get(user_id)
+            if entities is None:
+                usage[name] = 0
+            else:
+                usage[name] = len(entities) if isinstance(entities, list) else 1
+        return usage
+
+    async def load_disabled_user_ids(self) -> list[UserId]:
+        return sorted(self._disabled_user_ids)
+
+    @logged_mutation
+    async def set_user_disabled(self, user_id: UserId, disabled: bool) -> None:
+        if disabled:
+            self._disabled_user_ids.add(user_id)
+        else:
+            self._disabled_user_ids.discard(user_id)
+
+
+def validate_object_id(v: Any) -> str:
+    if isinstance(v, ObjectId):
+        return str(v)
+    else:
         raise ValueError("Must be an instance of bson.ObjectId class")
 
 
@@ -242,6 +1739,181 @@ class OwnedTransaction(MongoStoredModel):
         return StoredTransaction.from_transaction(self.transaction, id=self.id)
 
 
+class OwnedReconciliation(MongoStoredModel):
+    reconciliation: Reconciliation
+    owner: UserId
+
+    def to_stored(self) -> StoredReconciliation:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedReconciliation (no id attr) "
+                + "to StoredReconciliation"
+            )
+        return StoredReconciliation.from_reconciliation(self.reconciliation, id=self.id)
+
+
+class OwnedReportSnapshot(MongoStoredModel):
+    snapshot: ReportSnapshot
+    owner: UserId
+
+    def to_stored(self) -> StoredReportSnapshot:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedReportSnapshot (no id attr) "
+                + "to StoredReportSnapshot"
+            )
+        return StoredReportSnapshot.from_report_snapshot(self.snapshot, id=self.id)
+
+
+class OwnedAllowance(MongoStoredModel):
+    allowance: Allowance
+    owner: UserId
+
+    def to_stored(self) -> StoredAllowance:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedAllowance (no id attr) to StoredAllowance"
+            )
+        return StoredAllowance.from_allowance(self.allowance, id=self.id)
+
+
+class OwnedChallenge(MongoStoredModel):
+    challenge: Challenge
+    owner: UserId
+
+    def to_stored(self) -> StoredChallenge:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedChallenge (no id attr) to StoredChallenge"
+            )
+        return StoredChallenge.from_challenge(self.challenge, id=self.id)
+
+
+class OwnedGoal(MongoStoredModel):
+    goal: Goal
+    owner: UserId
+
+    def to_stored(self) -> StoredGoal:
+        if self.id is None:
+            raise ValueError("Attempt to convert non-stored OwnedGoal (no id attr) to StoredGoal")
+        return StoredGoal.from_goal(self.goal, id=self.id)
+
+
+class OwnedDebt(MongoStoredModel):
+    debt: Debt
+    owner: UserId
+
+    def to_stored(self) -> StoredDebt:
+        if self.id is None:
+            raise ValueError("Attempt to convert non-stored OwnedDebt (no id attr) to StoredDebt")
+        return StoredDebt.from_debt(self.debt, id=self.id)
+
+
+class OwnedPoolNote(MongoStoredModel):
+    note: PoolNote
+    owner: UserId
+
+    def to_stored(self) -> StoredPoolNote:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedPoolNote (no id attr) to StoredPoolNote"
+            )
+        return StoredPoolNote.from_note(self.note, id=self.id)
+
+
+class OwnedBalanceSnapshot(MongoStoredModel):
+    snapshot: BalanceSnapshot
+    owner: UserId
+
+    def to_stored(self) -> StoredBalanceSnapshot:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedBalanceSnapshot (no id attr) to "
+                + "StoredBalanceSnapshot"
+            )
+        return StoredBalanceSnapshot.from_balance_snapshot(self.snapshot, id=self.id)
+
+
+class OwnedTransactionTemplate(MongoStoredModel):
+    template: TransactionTemplate
+    owner: UserId
+
+    def to_stored(self) -> StoredTransactionTemplate:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedTransactionTemplate (no id attr) "
+                + "to StoredTransactionTemplate"
+            )
+        return StoredTransactionTemplate.from_template(self.template, id=self.id)
+
+
+class OwnedCategorizationRule(MongoStoredModel):
+    rule: CategorizationRule
+    owner: UserId
+
+    def to_stored(self) -> StoredCategorizationRule:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedCategorizationRule (no id attr) "
+                + "to StoredCategorizationRule"
+            )
+        return StoredCategorizationRule.from_rule(self.rule, id=self.id)
+
+
+class OwnedPayee(MongoStoredModel):
+    payee: Payee
+    owner: UserId
+
+    def to_stored(self) -> StoredPayee:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedPayee (no id attr) to StoredPayee"
+            )
+        return StoredPayee.from_payee(self.payee, id=self.id)
+
+
+class OwnedOperation(MongoStoredModel):
+    operation: Operation
+    owner: UserId
+
+    def to_stored(self) -> StoredOperation:
+        if self.id is None:
+            raise ValueError(
+                "Attempt to convert non-stored OwnedOperation (no id attr) to StoredOperation"
+            )
+        return StoredOperation.from_operation(self.operation, id=self.id)
+
+
+class OwnedUserSettings(MongoStoredModel):
+    settings: UserSettings
+    owner: UserId
+
+
+class OwnedMonthClose(MongoStoredModel):
+    month_close: MonthClose
+    owner: UserId
+
+
+class OwnedNetWorthSnapshot(MongoStoredModel):
+    snapshot: NetWorthSnapshot
+    owner: UserId
+
+
+class OwnedCalendarToken(MongoStoredModel):
+    token: str
+    owner: UserId
+
+
+class OwnedTwoFactor(MongoStoredModel):
+    two_factor: TwoFactor
+    owner: UserId
+
+
+class OwnedEntityChange(MongoStoredModel):
+    change: EntityChange
+    owner: UserId
+
+
 class MongoDbStorage(Storage):
     def __init__(self, url: str) -> None:
         self.client: AsyncIOMotorClient = AsyncIOMotorClient(url)
@@ -249,6 +1921,29 @@ class MongoDbStorage(Storage):
         db = "tiny-expense-tracker"
         self.transactions_coll: AsyncIOMotorCollection = self.client[db].transactions
         self.pools_coll: AsyncIOMotorCollection = self.client[db].pools
+        self.reconciliations_coll: AsyncIOMotorCollection = self.client[db].reconciliations
+        self.report_snapshots_coll: AsyncIOMotorCollection = self.client[db].report_snapshots
+        self.allowances_coll: AsyncIOMotorCollection = self.client[db].allowances
+        self.challenges_coll: AsyncIOMotorCollection = self.client[db].challenges
+        self.goals_coll: AsyncIOMotorCollection = self.client[db].goals
+        self.debts_coll: AsyncIOMotorCollection = self.client[db].debts
+        self.templates_coll: AsyncIOMotorCollection = self.client[db].transaction_templates
+        self.rules_coll: AsyncIOMotorCollection = self.client[db].categorization_rules
+        self.payees_coll: AsyncIOMotorCollection = self.client[db].payees
+        self.notes_coll: AsyncIOMotorCollection = self.client[db].pool_notes
+        self.balance_snapshots_coll: AsyncIOMotorCollection = self.client[db].balance_snapshots
+        self.settings_coll: AsyncIOMotorCollection = self.client[db].user_settings
+        self.month_closes_coll: AsyncIOMotorCollection = self.client[db].month_closes
+        self.net_worth_coll: AsyncIOMotorCollection = self.client[db].net_worth_snapshots
+        self.calendar_tokens_coll: AsyncIOMotorCollection = self.client[db].calendar_tokens
+        self.two_factors_coll: AsyncIOMotorCollection = self.client[db].two_factors
+        self.operations_coll: AsyncIOMotorCollection = self.client[db].operations
+        self.change_counters_coll: AsyncIOMotorCollection = self.client[db].change_counters
+        self.changes_coll: AsyncIOMotorCollection = self.client[db].changes
+        self.audit_coll: AsyncIOMotorCollection = self.client[db].audit
+        self.historical_rates_coll: AsyncIOMotorCollection = self.client[db].historical_rates
+        self.disabled_users_coll: AsyncIOMotorCollection = self.client[db].disabled_users
+        self.migrations_coll: AsyncIOMotorCollection = self.client[db].migrations
 
     async def initialize(self) -> None:
         start = time.time()
@@ -257,10 +1952,43 @@ class MongoDbStorage(Storage):
         # self.logger.info(f"transactions: {await self.transactions_coll.count_documents({})}")
         # self.logger.info(f"pools: {await self.pools_coll.count_documents({})}")
 
+    async def _record_change(
+        self,
+        user_id: UserId,
+        entity: SyncedEntity,
+        entity_id: str,
+        deleted: bool = False,
+        session: AsyncIOMotorClientSession | None = None,
+    ) -> None:
+        counter = await self.change_counters_coll.find_one_and_update(
+            {"owner": user_id},
+            {"$inc": {"counter": 1}},
+            upsert=True,
+            return_document=ReturnDocument.AFTER,
+            session=session,
+        )
+        await self.changes_coll.update_one(
+            {"owner": user_id, "change.entity": entity.value, "change.entity_id": entity_id},
+            {"$set": {"change.seq": counter["counter"], "change.deleted": deleted}},
+            upsert=True,
+            session=session,
+        )
+
+    async def load_change_counter(self, user_id: UserId) -> int:
+        doc = await self.change_counters_coll.find_one({"owner": user_id})
+        return doc["counter"] if doc is not None else 0
+
+    async def load_changes(self, user_id: UserId, since: int) -> list[EntityChange]:
+        docs = await self.changes_coll.find(
+            {"owner": user_id, "change.seq": {"$gt": since}}
+        ).sort("change.seq", 1).to_list(length=None)
+        return [OwnedEntityChange.model_validate(d).change for d in docs]
+
     async def add_pool(self, user_id: UserId, new_pool: MoneyPool) -> StoredMoneyPool:
         result = await self.pools_coll.insert_one(
             OwnedPool(pool=new_pool, owner=user_id).model_dump(mode="json")
         )
+        await self._record_change(user_id, SyncedEntity.POOL, str(result.inserted_id))
         return StoredMoneyPool.from_money_pool(new_pool, id=str(result.inserted_id))
 
     def _pool_filter(self, user_id: UserId, pool_id: MoneyPoolId) -> dict[str, Any]:
@@ -281,10 +2009,80 @@ class MongoDbStorage(Storage):
         return await self._load_pool_internal(user_id, pool_id, session=None)
 
     async def load_pools(self, user_id: UserId) -> list[StoredMoneyPool]:
-        cursor = self.pools_coll.find({"owner": user_id})
+        # pools created before ordering was introduced have no sort order and go first
+        cursor = self.pools_coll.find({"owner": user_id}).sort(
+            [("pool.sort_order", 1), ("_id", 1)]
+        )
         docs = await cursor.to_list(length=1000)
         return [OwnedPool.model_validate(d).to_stored() for d in docs]
 
+    async def save_pool_balance(
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        balance: list[MoneySum],
+        initial_balance: list[MoneySum] | None,
+    ) -> bool:
+        result = await self.pools_coll.update_one(
+            self._pool_filter(user_id, pool_id),
+            {
+                "$set": {
+                    "pool.balance": [s.model_dump(mode="json") for s in balance],
+                    "pool.initial_balance": (
+                        [s.model_dump(mode="json") for s in initial_balance]
+                        if initial_balance is not None
+                        else None
+                    ),
+                }
+            },
+        )
+        await self._record_change(user_id, SyncedEntity.POOL, pool_id)
+        return result.matched_count == 1
+
+    async def redenominate_pool(self, user_id: UserId, redenomination: PoolRedenomination) -> bool:
+        async def internal(session: AsyncIOMotorClientSession) -> bool:
+            result = await self.pools_coll.update_one(
+                self._pool_filter(user_id, redenomination.pool_id),
+                {
+                    "$set": {
+                        "pool.balance": [
+                            s.model_dump(mode="json") for s in redenomination.balance
+                        ],
+                        "pool.initial_balance": (
+                            [s.model_dump(mode="json") for s in redenomination.initial_balance]
+                            if redenomination.initial_balance is not None
+                            else None
+                        ),
+                    }
+                },
+                session=session,
+            )
+            if result.matched_count == 0:
+                return False
+            for conversion in redenomination.transactions:
+                converted = conversion.model_dump(mode="json")
+                await self.transactions_coll.update_one(
+                    self._transaction_filter(user_id, conversion.transaction_id),
+                    {
+                        "$set": {
+                            f"transaction.{field}": converted[field]
+                            for field in ("sum", "splits", "original_currency", "original_amount")
+                        },
+                        "$inc": {"transaction.version": 1},
+                    },
+                    session=session,
+                )
+                await self._record_change(
+                    user_id, SyncedEntity.TRANSACTION, conversion.transaction_id, session=session
+                )
+            await self._record_change(
+                user_id, SyncedEntity.POOL, redenomination.pool_id, session=session
+            )
+            return True
+
+        async with await self.client.start_session() as session:
+            return await session.with_transaction(internal)
+
     async def add_balance_to_pool(
         self, user_id: UserId, pool_id: UserId, new_balance: MoneySum
     ) -> bool:
@@ -292,27 +2090,69 @@ class MongoDbStorage(Storage):
             self._pool_filter(user_id, pool_id),
             {"$push": {"pool.balance": new_balance.model_dump(mode="json")}},
         )
+        await self.pools_coll.update_one(
+            {**self._pool_filter(user_id, pool_id), "pool.initial_balance": {"$type": "array"}},
+            {"$push": {"pool.initial_balance": new_balance.model_dump(mode="json")}},
+        )
+        await self._record_change(user_id, SyncedEntity.POOL, pool_id)
         return result.modified_count == 1
 
     async def set_pool_attributes(
-        self, user_id: UserId, pool_id: MoneyPoolId, update: MoneyPoolAttributesUpdate
+        self,
+        user_id: UserId,
+        pool_id: MoneyPoolId,
+        update: MoneyPoolAttributesUpdate,
+        expected_version: int | None = None,
     ) -> bool:
-        result = await self.pools_coll.update_one(
-            self._pool_filter(user_id, pool_id),
-            {
-                "$set": {
-                    path: new_value
-                    for path, new_value in (
-                        ("pool.is_visible", update.is_visible),
-                        ("pool.display_name", update.display_name),
-                        ("pool.display_color", update.display_color),
-                    )
-                    if new_value is not None
-                }
-            },
-        )
+        filter = self._pool_filter(user_id, pool_id)
+        if expected_version is not None:
+            filter["pool.version"] = self._version_query(expected_version)
+        mongo_update: dict[str, Any] = {"$inc": {"pool.version": 1}}
+        mongo_set = {
+            path: new_value
+            for path, new_value in (
+                ("pool.is_visible", update.is_visible),
+                ("pool.display_name", update.display_name),
+                ("pool.display_color", update.display_color),
+                ("pool.icon", update.icon),
+                ("pool.color_hex", update.color_hex),
+                (
+                    "pool.overdraft",
+                    update.overdraft.model_dump(mode="json") if update.overdraft else None,
+                ),
+                ("pool.group", update.group),
+                ("pool.default_category", update.default_category),
+            )
+            if new_value is not None
+        }
+        if mongo_set:
+            mongo_update["$set"] = mongo_set
+        result = await self.pools_coll.update_one(filter, mongo_update)
+        if result.matched_count == 0 and expected_version is not None:
+            current = await self.load_pool(user_id, pool_id)
+            if current is not None:
+                raise VersionConflict(expected_version, current.version)
+        await self._record_change(user_id, SyncedEntity.POOL, pool_id)
         return result.modified_count == 1
 
+    async def set_pools_order(self, user_id: UserId, pool_ids: list[MoneyPoolId]) -> None:
+        if not pool_ids:
+            return
+        await self.pools_coll.bulk_write(
+            [
+                UpdateOne(self._pool_filter(user_id, pool_id), {"$set": {"pool.sort_order": idx}})
+                for idx, pool_id in enumerate(pool_ids)
+            ]
+        )
+        for pool_id in pool_ids:
+            await self._record_change(user_id, SyncedEntity.POOL, pool_id)
+
+    def _version_query(self, expected_version: int) -> Any:
+        if expected_version == 0:
+            # documents stored before versioning was introduced have no version field
+            return {"$in": [0, None]}
+        return expected_version
+
     async def _update_pool_internal(
         self,
         user_id: UserId,
@@ -329,24 +2169,103 @@ class MongoDbStorage(Storage):
         await self.pools_coll.update_one(
             self._pool_filter(user_id, transaction.pool_id), {"$set": mongo_set}, session=session
         )
+        await self._record_change(user_id, SyncedEntity.POOL, transaction.pool_id, session=session)
+
+    async def _add_transaction_internal(
+        self, user_id: UserId, transaction: Transaction, session: AsyncIOMotorClientSession
+    ) -> StoredTransaction:
+        pool = await self._load_pool_internal(user_id, transaction.pool_id, session=session)
+        if pool is None:
+            raise ValueError("Attempt to add transaction to a non-existing pool")
+        await self._update_pool_internal(user_id, pool, transaction, session=session)
+        result = await self.transactions_coll.insert_one(
+            OwnedTransaction(transaction=transaction, owner=user_id).model_dump(mode="json"),
+            session=session,
+        )
+        await self._record_change(
+            user_id, SyncedEntity.TRANSACTION, str(result.inserted_id), session=session
+        )
+        return StoredTransaction.from_transaction(transaction, id=str(result.inserted_id))
 
     async def add_transaction(
         self, user_id: UserId, transaction: Transaction
     ) -> StoredTransaction:
         async def internal(session: AsyncIOMotorClientSession) -> StoredTransaction:
-            pool = await self._load_pool_internal(user_id, transaction.pool_id, session=session)
-            if pool is None:
-                raise ValueError("Attempt to add transaction to a non-existing pool")
-            await self._update_pool_internal(user_id, pool, transaction, session=session)
-            result = await self.transactions_coll.insert_one(
-                OwnedTransaction(transaction=transaction, owner=user_id).model_dump(mode="json"),
-                session=session,
-            )
-            return StoredTransaction.from_transaction(transaction, id=str(result.inserted_id))
+            return await self._add_transaction_internal(user_id, transaction, session)
+
+        async with await self.client.start_session() as session:
+            return await session.with_transaction(internal)
+
+    async def add_transactions(
+        self, user_id: UserId, transactions: list[Transaction]
+    ) -> list[StoredTransaction]:
+        async def internal(session: AsyncIOMotorClientSession) -> list[StoredTransaction]:
+            return [
+                await self._add_transaction_internal(user_id, t, session) for t in transactions
+            ]
 
+        # all or nothing
         async with await self.client.start_session() as session:
             return await session.with_transaction(internal)
 
+    async def apply_transaction_changes(
+        self, user_id: UserId, new: list[Transaction], edits: list[TransactionEdit]
+    ) -> list[StoredTransaction]:
+        async def internal(session: AsyncIOMotorClientSession) -> list[StoredTransaction]:
+            added = [await self._add_transaction_internal(user_id, t, session) for t in new]
+            for edit in edits:
+                owned = await self._load_transaction_internal(
+                    user_id, edit.transaction_id, session=session
+                )
+                version = owned.transaction.version if owned is not None else None
+                if version != edit.expected_version:
+                    # aborts the transaction
+                    raise VersionConflict(edit.expected_version, version, edit.transaction_id)
+                if edit.update is None:
+                    await self._delete_transaction_internal(
+                        user_id, edit.transaction_id, session
+                    )
+                    continue
+                await self.transactions_coll.update_one(
+                    self._transaction_filter(user_id, edit.transaction_id),
+                    self._transaction_update_doc(edit.update),
+                    session=session,
+                )
+                await self._record_change(
+                    user_id, SyncedEntity.TRANSACTION, edit.transaction_id, session=session
+                )
+            return added
+
+        async with await self.client.start_session() as session:
+            return await session.with_transaction(internal)
+
+    async def restore_transactions(
+        self, user_id: UserId, transactions: list[StoredTransaction]
+    ) -> None:
+        async def internal(session: AsyncIOMotorClientSession) -> None:
+            for stored in transactions:
+                transaction = Transaction.model_validate(stored.model_dump(exclude={"id"}))
+                pool = await self._load_pool_internal(
+                    user_id, transaction.pool_id, session=session
+                )
+                if pool is None:
+                    raise ValueError("Attempt to restore transaction to a non-existing pool")
+                await self._update_pool_internal(user_id, pool, transaction, session=session)
+                doc = OwnedTransaction(transaction=transaction, owner=user_id).model_dump(
+                    mode="json"
+                )
+                doc["_id"] = ObjectId(stored.id)
+                try:
+                    await self.transactions_coll.insert_one(doc, session=session)
+                except DuplicateKeyError:
+                    raise IdConflict("transaction", stored.id)
+                await self._record_change(
+                    user_id, SyncedEntity.TRANSACTION, stored.id, session=session
+                )
+
+        async with await self.client.start_session() as session:
+            await session.with_transaction(internal)
+
     async def load_transactions(
         self,
         user_id: UserId,
@@ -374,6 +2293,13 @@ class MongoDbStorage(Storage):
                 # query["transaction.tags"] = {"$exists": False}
             if filter.is_diffuse is not None:
                 query["transaction.is_diffuse"] = filter.is_diffuse
+            if filter.status is TransactionStatus.CLEARED:
+                # documents stored before statuses were introduced have no status field
+                query["transaction.status"] = {"$in": [filter.status.value, None]}
+            elif filter.status is not None:
+                query["transaction.status"] = filter.status.value
+            if filter.payee_ids:
+                query["transaction.payee_id"] = {"$in": filter.payee_ids}
 
         match order:
             case TransactionOrder.LATEST:
@@ -387,7 +2313,7 @@ class MongoDbStorage(Storage):
 
         docs = (
             await self.transactions_coll.find(query)
-            .sort(sort_key, sort_dir)
+            .sort([(sort_key, sort_dir), ("_id", 1)])  # ids grow with insertion
             .skip(offset)
             .to_list(length=count)
         )
@@ -410,33 +2336,40 @@ class MongoDbStorage(Storage):
         )
         return OwnedTransaction.model_validate(raw) if raw else None
 
+    async def _delete_transaction_internal(
+        self, user_id: UserId, transaction_id: TransactionId, session: AsyncIOMotorClientSession
+    ) -> bool:
+        to_be_deleted = await self._load_transaction_internal(
+            user_id, transaction_id, session=session
+        )
+        if to_be_deleted is None:
+            return False
+        result = await self.transactions_coll.delete_one(
+            self._transaction_filter(user_id, transaction_id), session=session
+        )
+        if result.deleted_count == 0:
+            return False
+        inverse_transaction = to_be_deleted.transaction.inverted()
+        pool = await self._load_pool_internal(
+            user_id, inverse_transaction.pool_id, session=session
+        )
+        if pool is None:
+            return False
+        await self._update_pool_internal(user_id, pool, inverse_transaction, session=session)
+        await self._record_change(
+            user_id, SyncedEntity.TRANSACTION, transaction_id, deleted=True, session=session
+        )
+        return True
+
     async def delete_transaction(self, user_id: UserId, transaction_id: MoneyPoolId) -> bool:
         async def internal(session: AsyncIOMotorClientSession) -> bool:
-            to_be_deleted = await self._load_transaction_internal(
-                user_id, transaction_id, session=session
-            )
-            if to_be_deleted is None:
-                return False
-            result = await self.transactions_coll.delete_one(
-                self._transaction_filter(user_id, transaction_id), session=session
-            )
-            if result.deleted_count == 0:
-                return False
-            inverse_transaction = to_be_deleted.transaction.inverted()
-            pool = await self._load_pool_internal(
-                user_id, inverse_transaction.pool_id, session=session
-            )
-            if pool is None:
-                return False
-            await self._update_pool_internal(user_id, pool, inverse_transaction, session=session)
-            return True
+            return await self._delete_transaction_internal(user_id, transaction_id, session)
 
         async with await self.client.start_session() as session:
             return await session.with_transaction(internal)
 
-    async def update_transaction(
-        self, user_id: UserId, transaction_id: TransactionId, update: TransactionUpdate
-    ) -> bool:
+    @staticmethod
+    def _transaction_update_doc(update: TransactionUpdate) -> dict[str, Any]:
         update_doc: dict[str, Any] = {}
         if update.description is not None:
             update_doc["transaction.description"] = update.description
@@ -444,8 +2377,889 @@ class MongoDbStorage(Storage):
             update_doc["transaction.timestamp"] = update.timestamp.timestamp()
         if update.tags is not None:
             update_doc["transaction.tags"] = update.tags
+        if update.splits is not None:
+            update_doc["transaction.splits"] = [s.model_dump(mode="json") for s in update.splits]
+        if update.payee_id is not None:
+            update_doc["transaction.payee_id"] = update.payee_id or None
+        mongo_update: dict[str, Any] = {"$inc": {"transaction.version": 1}}
+        if update_doc:
+            mongo_update["$set"] = update_doc
+        return mongo_update
+
+    async def update_transaction(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        update: TransactionUpdate,
+        expected_version: int | None = None,
+    ) -> bool:
+        filter = self._transaction_filter(user_id, transaction_id)
+        if expected_version is not None:
+            filter["transaction.version"] = self._version_query(expected_version)
         res = await self.transactions_coll.update_one(
-            filter=self._transaction_filter(user_id, transaction_id),
-            update={"$set": update_doc},
+            filter=filter, update=self._transaction_update_doc(update)
         )
+        if res.matched_count == 0 and expected_version is not None:
+            current = await self.load_transaction(user_id, transaction_id)
+            if current is not None:
+                raise VersionConflict(expected_version, current.version)
+        await self._record_change(user_id, SyncedEntity.TRANSACTION, transaction_id)
         return res.modified_count == 1
+
+    async def update_transaction_status(
+        self,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        status: TransactionStatus,
+        expected_version: int | None = None,
+    ) -> bool:
+        filter = self._transaction_filter(user_id, transaction_id)
+
+        async def internal(session: AsyncIOMotorClientSession) -> bool:
+            doc = await self.transactions_coll.find_one(filter, session=session)
+            if doc is None:
+                return False
+            transaction = OwnedTransaction.model_validate(doc).to_stored()
+            if expected_version is not None and transaction.version != expected_version:
+                raise VersionConflict(expected_version, transaction.version)
+            if transaction.status.is_counted != status.is_counted:
+                pool = await self._load_pool_internal(
+                    user_id, transaction.pool_id, session=session
+                )
+                effect = transaction.model_copy(update={"status": TransactionStatus.CLEARED})
+                if pool is not None:
+                    await self._update_pool_internal(
+                        user_id,
+                        pool,
+                        effect if status.is_counted else effect.inverted(),
+                        session=session,
+                    )
+            await self.transactions_coll.update_one(
+                filter,
+                {"$set": {"transaction.status": status.value}, "$inc": {"transaction.version": 1}},
+                session=session,
+            )
+            await self._record_change(
+                user_id, SyncedEntity.TRANSACTION, transaction_id, session=session
+            )
+            return True
+
+        async with await self.client.start_session() as session:
+            return await session.with_transaction(internal)
+
+    async def load_due_scheduled_transactions(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredTransaction]]:
+        docs = await self.transactions_coll.find(
+            {
+                "transaction.status": TransactionStatus.SCHEDULED.value,
+                "transaction.timestamp": {"$lte": now.timestamp()},
+            }
+        ).to_list(length=None)
+        owned = [OwnedTransaction.model_validate(d) for d in docs]
+        return [(o.owner, o.to_stored()) for o in owned]
+
+    async def load_suggestions(
+        self,
+        user_id: UserId,
+        field: SuggestionField,
+        query: str,
+        since: datetime.datetime,
+        count: int,
+    ) -> list[Suggestion]:
+        match: dict[str, Any] = {
+            "owner": user_id,
+            "transaction.timestamp": {"$gte": since.timestamp()},
+        }
+        pattern = {"$regex": re.escape(query), "$options": "i"}
+        payee_names: dict[PayeeId, str] = {}
+        pipeline: list[dict[str, Any]] = []
+        match field:
+            case SuggestionField.DESCRIPTION:
+                path = "transaction.description"
+                match[path] = pattern
+                pipeline.append({"$match": match})
+            case SuggestionField.CATEGORY:
+                path = "transaction.tags"
+                pipeline.append({"$match": match})
+                pipeline.append({"$unwind": f"${path}"})
+                pipeline.append({"$match": {path: pattern}})
+            case SuggestionField.PAYEE:
+                payee_names = {
+                    p.id: p.name
+                    for p in await self.load_payees(user_id)
+                    if query.casefold() in p.name.casefold()
+                }
+                path = "transaction.payee_id"
+                match[path] = {"$in": list(payee_names)}
+                pipeline.append({"$match": match})
+        pipeline.extend(
+            [
+                {
+                    "$group": {
+                        "_id": f"${path}",
+                        "count": {"$sum": 1},
+                        "last_used": {"$max": "$transaction.timestamp"},
+                    }
+                },
+                {"$match": {"_id": {"$nin": ["", None]}}},
+                {"$sort": {"count": -1, "last_used": -1}},
+                {"$limit": count},
+            ]
+        )
+        docs = await self.transactions_coll.aggregate(pipeline).to_list(length=None)
+        if field is SuggestionField.PAYEE:
+            return [
+                Suggestion(value=payee_names[d["_id"]], payee_id=d["_id"], count=d["count"])
+                for d in docs
+            ]
+        return [Suggestion(value=d["_id"], count=d["count"]) for d in docs]
+
+    def _reconciliation_filter(
+        self, user_id: UserId, reconciliation_id: ReconciliationId
+    ) -> dict[str, Any]:
+        if not ObjectId.is_valid(reconciliation_id):
+            raise fastapi.HTTPException(404, "Invalid reconciliation id")
+        return {"_id": ObjectId(reconciliation_id), "owner": user_id}
+
+    async def add_reconciliation(
+        self, user_id: UserId, reconciliation: Reconciliation
+    ) -> StoredReconciliation:
+        result = await self.reconciliations_coll.insert_one(
+            OwnedReconciliation(reconciliation=reconciliation, owner=user_id).model_dump(
+                mode="json"
+            )
+        )
+        return StoredReconciliation.from_reconciliation(
+            reconciliation, id=str(result.inserted_id)
+        )
+
+    async def load_reconciliations(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredReconciliation]:
+        query: dict[str, Any] = {"owner": user_id}
+        if pool_id is not None:
+            query["reconciliation.pool_id"] = pool_id
+        docs = await self.reconciliations_coll.find(query).to_list(length=1000)
+        return [OwnedReconciliation.model_validate(d).to_stored() for d in docs]
+
+    async def load_reconciliation(
+        self, user_id: UserId, reconciliation_id: ReconciliationId
+    ) -> StoredReconciliation | None:
+        doc = await self.reconciliations_coll.find_one(
+            self._reconciliation_filter(user_id, reconciliation_id)
+        )
+        if doc is None:
+            return None
+        return OwnedReconciliation.model_validate(doc).to_stored()
+
+    async def save_reconciliation(
+        self, user_id: UserId, reconciliation: StoredReconciliation
+    ) -> bool:
+        result = await self.reconciliations_coll.replace_one(
+            self._reconciliation_filter(user_id, reconciliation.id),
+            OwnedReconciliation(
+                reconciliation=Reconciliation.model_validate(
+                    reconciliation.model_dump(exclude={"id"})
+                ),
+                owner=user_id,
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    async def add_report_snapshot(
+        self, user_id: UserId, snapshot: ReportSnapshot
+    ) -> StoredReportSnapshot:
+        result = await self.report_snapshots_coll.insert_one(
+            OwnedReportSnapshot(snapshot=snapshot, owner=user_id).model_dump(mode="json")
+        )
+        return StoredReportSnapshot.from_report_snapshot(snapshot, id=str(result.inserted_id))
+
+    async def load_report_snapshots(self, user_id: UserId) -> list[StoredReportSnapshot]:
+        docs = (
+            await self.report_snapshots_coll.find({"owner": user_id})
+            .sort([("snapshot.created_at", -1), ("_id", -1)])
+            .to_list(length=MAX_REPORT_SNAPSHOTS)
+        )
+        return [OwnedReportSnapshot.model_validate(d).to_stored() for d in docs]
+
+    async def load_report_snapshot(
+        self, user_id: UserId, snapshot_id: ReportSnapshotId
+    ) -> StoredReportSnapshot | None:
+        if not ObjectId.is_valid(snapshot_id):
+            return None
+        doc = await self.report_snapshots_coll.find_one(
+            {"_id": ObjectId(snapshot_id), "owner": user_id}
+        )
+        if doc is None:
+            return None
+        return OwnedReportSnapshot.model_validate(doc).to_stored()
+
+    def _allowance_filter(self, user_id: UserId, allowance_id: AllowanceId) -> dict[str, Any]:
+        if not ObjectId.is_valid(allowance_id):
+            raise fastapi.HTTPException(404, "Invalid allowance id")
+        return {"_id": ObjectId(allowance_id), "owner": user_id}
+
+    async def add_allowance(self, user_id: UserId, allowance: Allowance) -> StoredAllowance:
+        result = await self.allowances_coll.insert_one(
+            OwnedAllowance(allowance=allowance, owner=user_id).model_dump(mode="json")
+        )
+        return StoredAllowance.from_allowance(allowance, id=str(result.inserted_id))
+
+    async def load_allowances(self, user_id: UserId) -> list[StoredAllowance]:
+        docs = await self.allowances_coll.find({"owner": user_id}).to_list(length=1000)
+        return [OwnedAllowance.model_validate(d).to_stored() for d in docs]
+
+    async def load_allowance(
+        self, user_id: UserId, allowance_id: AllowanceId
+    ) -> StoredAllowance | None:
+        doc = await self.allowances_coll.find_one(self._allowance_filter(user_id, allowance_id))
+        if doc is None:
+            return None
+        return OwnedAllowance.model_validate(doc).to_stored()
+
+    async def save_allowance(self, user_id: UserId, allowance: StoredAllowance) -> bool:
+        result = await self.allowances_coll.replace_one(
+            self._allowance_filter(user_id, allowance.id),
+            OwnedAllowance(
+                allowance=Allowance.model_validate(allowance.model_dump(exclude={"id"})),
+                owner=user_id,
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    async def load_allowance_by_view_token(
+        self, view_token: str
+    ) -> tuple[UserId, StoredAllowance] | None:
+        doc = await self.allowances_coll.find_one({"allowance.view_token": view_token})
+        if doc is None:
+            return None
+        owned = OwnedAllowance.model_validate(doc)
+        return owned.owner, owned.to_stored()
+
+    async def load_due_allowances(
+        self, now: datetime.datetime
+    ) -> list[tuple[UserId, StoredAllowance]]:
+        docs = await self.allowances_coll.find(
+            {"allowance.next_payment_at": {"$lte": now.timestamp()}}
+        ).to_list(length=None)
+        owned = [OwnedAllowance.model_validate(d) for d in docs]
+        return [(o.owner, o.to_stored()) for o in owned]
+
+    def _challenge_filter(self, user_id: UserId, challenge_id: ChallengeId) -> dict[str, Any]:
+        if not ObjectId.is_valid(challenge_id):
+            raise fastapi.HTTPException(404, "Invalid challenge id")
+        return {"_id": ObjectId(challenge_id), "owner": user_id}
+
+    async def add_challenge(self, user_id: UserId, challenge: Challenge) -> StoredChallenge:
+        result = await self.challenges_coll.insert_one(
+            OwnedChallenge(challenge=challenge, owner=user_id).model_dump(mode="json")
+        )
+        return StoredChallenge.from_challenge(challenge, id=str(result.inserted_id))
+
+    async def load_challenges(self, user_id: UserId) -> list[StoredChallenge]:
+        docs = await self.challenges_coll.find({"owner": user_id}).to_list(length=1000)
+        return [OwnedChallenge.model_validate(d).to_stored() for d in docs]
+
+    async def load_challenge(
+        self, user_id: UserId, challenge_id: ChallengeId
+    ) -> StoredChallenge | None:
+        doc = await self.challenges_coll.find_one(self._challenge_filter(user_id, challenge_id))
+        if doc is None:
+            return None
+        return OwnedChallenge.model_validate(doc).to_stored()
+
+    async def save_challenge(self, user_id: UserId, challenge: StoredChallenge) -> bool:
+        result = await self.challenges_coll.replace_one(
+            self._challenge_filter(user_id, challenge.id),
+            OwnedChallenge(
+                challenge=Challenge.model_validate(challenge.model_dump(exclude={"id"})),
+                owner=user_id,
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    def _goal_filter(self, user_id: UserId, goal_id: GoalId) -> dict[str, Any]:
+        if not ObjectId.is_valid(goal_id):
+            raise fastapi.HTTPException(404, "Invalid goal id")
+        return {"_id": ObjectId(goal_id), "owner": user_id}
+
+    async def add_goal(self, user_id: UserId, goal: Goal) -> StoredGoal:
+        result = await self.goals_coll.insert_one(
+            OwnedGoal(goal=goal, owner=user_id).model_dump(mode="json")
+        )
+        return StoredGoal.from_goal(goal, id=str(result.inserted_id))
+
+    async def load_goals(self, user_id: UserId) -> list[StoredGoal]:
+        docs = await self.goals_coll.find({"owner": user_id}).to_list(length=1000)
+        return [OwnedGoal.model_validate(d).to_stored() for d in docs]
+
+    async def load_goal(self, user_id: UserId, goal_id: GoalId) -> StoredGoal | None:
+        doc = await self.goals_coll.find_one(self._goal_filter(user_id, goal_id))
+        if doc is None:
+            return None
+        return OwnedGoal.model_validate(doc).to_stored()
+
+    async def save_goal(self, user_id: UserId, goal: StoredGoal) -> bool:
+        result = await self.goals_coll.replace_one(
+            self._goal_filter(user_id, goal.id),
+            OwnedGoal(
+                goal=Goal.model_validate(goal.model_dump(exclude={"id"})), owner=user_id
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    async def delete_goal(self, user_id: UserId, goal_id: GoalId) -> bool:
+        result = await self.goals_coll.delete_one(self._goal_filter(user_id, goal_id))
+        return result.deleted_count == 1
+
+    def _debt_filter(self, user_id: UserId, debt_id: DebtId) -> dict[str, Any]:
+        if not ObjectId.is_valid(debt_id):
+            raise fastapi.HTTPException(404, "Invalid debt id")
+        return {"_id": ObjectId(debt_id), "owner": user_id}
+
+    async def add_debt(self, user_id: UserId, debt: Debt) -> StoredDebt:
+        result = await self.debts_coll.insert_one(
+            OwnedDebt(debt=debt, owner=user_id).model_dump(mode="json")
+        )
+        return StoredDebt.from_debt(debt, id=str(result.inserted_id))
+
+    async def load_debts(self, user_id: UserId) -> list[StoredDebt]:
+        docs = (
+            await self.debts_coll.find({"owner": user_id})
+            .sort("debt.created_at", -1)
+            .to_list(length=None)
+        )
+        return [OwnedDebt.model_validate(d).to_stored() for d in docs]
+
+    async def load_debt(self, user_id: UserId, debt_id: DebtId) -> StoredDebt | None:
+        doc = await self.debts_coll.find_one(self._debt_filter(user_id, debt_id))
+        if doc is None:
+            return None
+        return OwnedDebt.model_validate(doc).to_stored()
+
+    async def save_debt(self, user_id: UserId, debt: StoredDebt) -> bool:
+        result = await self.debts_coll.replace_one(
+            self._debt_filter(user_id, debt.id),
+            OwnedDebt(
+                debt=Debt.model_validate(debt.model_dump(exclude={"id"})), owner=user_id
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    def _template_filter(self, user_id: UserId, template_id: TemplateId) -> dict[str, Any]:
+        if not ObjectId.is_valid(template_id):
+            raise fastapi.HTTPException(404, "Invalid template id")
+        return {"_id": ObjectId(template_id), "owner": user_id}
+
+    async def add_transaction_template(
+        self, user_id: UserId, template: TransactionTemplate
+    ) -> StoredTransactionTemplate:
+        result = await self.templates_coll.insert_one(
+            OwnedTransactionTemplate(template=template, owner=user_id).model_dump(mode="json")
+        )
+        return StoredTransactionTemplate.from_template(template, id=str(result.inserted_id))
+
+    async def load_transaction_templates(
+        self, user_id: UserId
+    ) -> list[StoredTransactionTemplate]:
+        docs = await self.templates_coll.find({"owner": user_id}).to_list(length=None)
+        return [OwnedTransactionTemplate.model_validate(d).to_stored() for d in docs]
+
+    async def load_transaction_template(
+        self, user_id: UserId, template_id: TemplateId
+    ) -> StoredTransactionTemplate | None:
+        doc = await self.templates_coll.find_one(self._template_filter(user_id, template_id))
+        if doc is None:
+            return None
+        return OwnedTransactionTemplate.model_validate(doc).to_stored()
+
+    async def save_transaction_template(
+        self, user_id: UserId, template: StoredTransactionTemplate
+    ) -> bool:
+        result = await self.templates_coll.replace_one(
+            self._template_filter(user_id, template.id),
+            OwnedTransactionTemplate(
+                template=TransactionTemplate.model_validate(template.model_dump(exclude={"id"})),
+                owner=user_id,
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    async def delete_transaction_template(self, user_id: UserId, template_id: TemplateId) -> bool:
+        result = await self.templates_coll.delete_one(self._template_filter(user_id, template_id))
+        return result.deleted_count == 1
+
+    def _rule_filter(self, user_id: UserId, rule_id: RuleId) -> dict[str, Any]:
+        if not ObjectId.is_valid(rule_id):
+            raise fastapi.HTTPException(404, "Invalid rule id")
+        return {"_id": ObjectId(rule_id), "owner": user_id}
+
+    async def add_rule(
+        self, user_id: UserId, rule: CategorizationRule
+    ) -> StoredCategorizationRule:
+        result = await self.rules_coll.insert_one(
+            OwnedCategorizationRule(rule=rule, owner=user_id).model_dump(mode="json")
+        )
+        return StoredCategorizationRule.from_rule(rule, id=str(result.inserted_id))
+
+    async def load_rules(self, user_id: UserId) -> list[StoredCategorizationRule]:
+        # object ids grow with the creation time
+        docs = await self.rules_coll.find({"owner": user_id}).sort("_id", 1).to_list(length=None)
+        return [OwnedCategorizationRule.model_validate(d).to_stored() for d in docs]
+
+    async def load_rule(
+        self, user_id: UserId, rule_id: RuleId
+    ) -> StoredCategorizationRule | None:
+        doc = await self.rules_coll.find_one(self._rule_filter(user_id, rule_id))
+        if doc is None:
+            return None
+        return OwnedCategorizationRule.model_validate(doc).to_stored()
+
+    async def save_rule(self, user_id: UserId, rule: StoredCategorizationRule) -> bool:
+        result = await self.rules_coll.replace_one(
+            self._rule_filter(user_id, rule.id),
+            OwnedCategorizationRule(
+                rule=CategorizationRule.model_validate(rule.model_dump(exclude={"id"})),
+                owner=user_id,
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    async def delete_rule(self, user_id: UserId, rule_id: RuleId) -> bool:
+        result = await self.rules_coll.delete_one(self._rule_filter(user_id, rule_id))
+        return result.deleted_count == 1
+
+    def _payee_filter(self, user_id: UserId, payee_id: PayeeId) -> dict[str, Any]:
+        if not ObjectId.is_valid(payee_id):
+            raise fastapi.HTTPException(404, "Invalid payee id")
+        return {"_id": ObjectId(payee_id), "owner": user_id}
+
+    async def add_payee(self, user_id: UserId, payee: Payee) -> StoredPayee:
+        result = await self.payees_coll.insert_one(
+            OwnedPayee(payee=payee, owner=user_id).model_dump(mode="json")
+        )
+        return StoredPayee.from_payee(payee, id=str(result.inserted_id))
+
+    async def load_payees(self, user_id: UserId) -> list[StoredPayee]:
+        docs = (
+            await self.payees_coll.find({"owner": user_id}).sort("payee.name", 1).to_list(None)
+        )
+        return [OwnedPayee.model_validate(d).to_stored() for d in docs]
+
+    async def load_payee(self, user_id: UserId, payee_id: PayeeId) -> StoredPayee | None:
+        doc = await self.payees_coll.find_one(self._payee_filter(user_id, payee_id))
+        if doc is None:
+            return None
+        return OwnedPayee.model_validate(doc).to_stored()
+
+    async def save_payee(self, user_id: UserId, payee: StoredPayee) -> bool:
+        result = await self.payees_coll.replace_one(
+            self._payee_filter(user_id, payee.id),
+            OwnedPayee(
+                payee=Payee.model_validate(payee.model_dump(exclude={"id"})), owner=user_id
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    async def delete_payee(self, user_id: UserId, payee_id: PayeeId) -> bool:
+        result = await self.payees_coll.delete_one(self._payee_filter(user_id, payee_id))
+        return result.deleted_count == 1
+
+    def _note_filter(self, user_id: UserId, note_id: NoteId) -> dict[str, Any]:
+        if not ObjectId.is_valid(note_id):
+            raise fastapi.HTTPException(404, "Invalid note id")
+        return {"_id": ObjectId(note_id), "owner": user_id}
+
+    async def add_pool_note(self, user_id: UserId, note: PoolNote) -> StoredPoolNote:
+        result = await self.notes_coll.insert_one(
+            OwnedPoolNote(note=note, owner=user_id).model_dump(mode="json")
+        )
+        return StoredPoolNote.from_note(note, id=str(result.inserted_id))
+
+    async def load_pool_notes(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredPoolNote]:
+        query: dict[str, Any] = {"owner": user_id}
+        if pool_id is not None:
+            query["note.pool_id"] = pool_id
+        docs = await self.notes_coll.find(query).to_list(length=None)
+        return [OwnedPoolNote.model_validate(d).to_stored() for d in docs]
+
+    async def load_pool_note(self, user_id: UserId, note_id: NoteId) -> StoredPoolNote | None:
+        doc = await self.notes_coll.find_one(self._note_filter(user_id, note_id))
+        if doc is None:
+            return None
+        return OwnedPoolNote.model_validate(doc).to_stored()
+
+    async def save_pool_note(self, user_id: UserId, note: StoredPoolNote) -> bool:
+        result = await self.notes_coll.replace_one(
+            self._note_filter(user_id, note.id),
+            OwnedPoolNote(
+                note=PoolNote.model_validate(note.model_dump(exclude={"id"})), owner=user_id
+            ).model_dump(mode="json"),
+        )
+        return result.matched_count == 1
+
+    async def delete_pool_note(self, user_id: UserId, note_id: NoteId) -> bool:
+        result = await self.notes_coll.delete_one(self._note_filter(user_id, note_id))
+        return result.deleted_count == 1
+
+    def _balance_snapshot_filter(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> dict[str, Any]:
+        if not ObjectId.is_valid(snapshot_id):
+            raise fastapi.HTTPException(404, "Invalid balance snapshot id")
+        return {"_id": ObjectId(snapshot_id), "owner": user_id}
+
+    async def add_balance_snapshot(
+        self, user_id: UserId, snapshot: BalanceSnapshot
+    ) -> StoredBalanceSnapshot:
+        result = await self.balance_snapshots_coll.insert_one(
+            OwnedBalanceSnapshot(snapshot=snapshot, owner=user_id).model_dump(mode="json")
+        )
+        return StoredBalanceSnapshot.from_balance_snapshot(snapshot, id=str(result.inserted_id))
+
+    async def load_balance_snapshots(
+        self, user_id: UserId, pool_id: MoneyPoolId | None
+    ) -> list[StoredBalanceSnapshot]:
+        query: dict[str, Any] = {"owner": user_id}
+        if pool_id is not None:
+            query["snapshot.pool_id"] = pool_id
+        docs = (
+            await self.balance_snapshots_coll.find(query)
+            .sort([("snapshot.timestamp", 1), ("_id", 1)])
+            .to_list(length=None)
+        )
+        return [OwnedBalanceSnapshot.model_validate(d).to_stored() for d in docs]
+
+    async def load_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> StoredBalanceSnapshot | None:
+        doc = await self.balance_snapshots_coll.find_one(
+            self._balance_snapshot_filter(user_id, snapshot_id)
+        )
+        if doc is None:
+            return None
+        return OwnedBalanceSnapshot.model_validate(doc).to_stored()
+
+    async def delete_balance_snapshot(
+        self, user_id: UserId, snapshot_id: BalanceSnapshotId
+    ) -> bool:
+        result = await self.balance_snapshots_coll.delete_one(
+            self._balance_snapshot_filter(user_id, snapshot_id)
+        )
+        return result.deleted_count == 1
+
+    async def load_user_settings(self, user_id: UserId) -> UserSettings:
+        doc = await self.settings_coll.find_one({"owner": user_id})
+        if doc is None:
+            return UserSettings()
+        return OwnedUserSettings.model_validate(doc).settings
+
+    async def save_user_settings(self, user_id: UserId, settings: UserSettings) -> None:
+        await self.settings_coll.replace_one(
+            {"owner": user_id},
+            OwnedUserSettings(settings=settings, owner=user_id).model_dump(mode="json"),
+            upsert=True,
+        )
+
+    async def load_month_closes(self, user_id: UserId) -> list[MonthClose]:
+        docs = await self.month_closes_coll.find({"owner": user_id}).to_list(length=None)
+        return [OwnedMonthClose.model_validate(d).month_close for d in docs]
+
+    async def load_month_close(self, user_id: UserId, year: int, month: int) -> MonthClose | None:
+        doc = await self.month_closes_coll.find_one(
+            {"owner": user_id, "month_close.year": year, "month_close.month": month}
+        )
+        if doc is None:
+            return None
+        return OwnedMonthClose.model_validate(doc).month_close
+
+    async def save_month_close(self, user_id: UserId, month_close: MonthClose) -> None:
+        await self.month_closes_coll.replace_one(
+            {
+                "owner": user_id,
+                "month_close.year": month_close.year,
+                "month_close.month": month_close.month,
+            },
+            OwnedMonthClose(month_close=month_close, owner=user_id).model_dump(mode="json"),
+            upsert=True,
+        )
+
+    async def save_net_worth_snapshot(self, user_id: UserId, snapshot: NetWorthSnapshot) -> None:
+        await self.net_worth_coll.replace_one(
+            {"owner": user_id, "snapshot.date": snapshot.date.isoformat()},
+            OwnedNetWorthSnapshot(snapshot=snapshot, owner=user_id).model_dump(mode="json"),
+            upsert=True,
+        )
+
+    async def load_net_worth_snapshots(
+        self, user_id: UserId, start: datetime.date, end: datetime.date
+    ) -> list[NetWorthSnapshot]:
+        docs = (
+            await self.net_worth_coll.find(
+                {
+                    "owner": user_id,
+                    "snapshot.date": {"$gte": start.isoformat(), "$lte": end.isoformat()},
+                }
+            )
+            .sort("snapshot.date", 1)
+            .to_list(length=None)
+        )
+        return [OwnedNetWorthSnapshot.model_validate(d).snapshot for d in docs]
+
+    async def save_calendar_token(self, user_id: UserId, token: str | None) -> None:
+        if token is None:
+            await self.calendar_tokens_coll.delete_one({"owner": user_id})
+            return
+        await self.calendar_tokens_coll.replace_one(
+            {"owner": user_id},
+            OwnedCalendarToken(token=token, owner=user_id).model_dump(mode="json"),
+            upsert=True,
+        )
+
+    async def load_calendar_token(self, user_id: UserId) -> str | None:
+        doc = await self.calendar_tokens_coll.find_one({"owner": user_id})
+        if doc is None:
+            return None
+        return OwnedCalendarToken.model_validate(doc).token
+
+    async def load_calendar_token_owner(self, token: str) -> UserId | None:
+        doc = await self.calendar_tokens_coll.find_one({"token": token})
+        if doc is None:
+            return None
+        return OwnedCalendarToken.model_validate(doc).owner
+
+    async def save_two_factor(self, user_id: UserId, two_factor: TwoFactor | None) -> None:
+        if two_factor is None:
+            await self.two_factors_coll.delete_one({"owner": user_id})
+            return
+        await self.two_factors_coll.replace_one(
+            {"owner": user_id},
+            OwnedTwoFactor(two_factor=two_factor, owner=user_id).model_dump(mode="json"),
+            upsert=True,
+        )
+
+    async def load_two_factor(self, user_id: UserId) -> TwoFactor | None:
+        doc = await self.two_factors_coll.find_one({"owner": user_id})
+        if doc is None:
+            return None
+        return OwnedTwoFactor.model_validate(doc).two_factor
+
+    async def log_operation(
+        self, user_id: UserId, operation: Operation, retention: datetime.timedelta
+    ) -> StoredOperation:
+        await self.operations_coll.delete_many(
+            {
+                "owner": user_id,
+                "operation.timestamp": {"$lt": (operation.timestamp - retention).timestamp()},
+            }
+        )
+        result = await self.operations_coll.insert_one(
+            OwnedOperation(operation=operation, owner=user_id).model_dump(mode="json")
+        )
+        return StoredOperation.from_operation(operation, id=str(result.inserted_id))
+
+    async def pop_last_operation(
+        self, user_id: UserId, since: datetime.datetime
+    ) -> StoredOperation | None:
+        doc = await self.operations_coll.find_one_and_delete(
+            {"owner": user_id, "operation.timestamp": {"$gte": since.timestamp()}},
+            sort=[("operation.timestamp", -1)],
+        )
+        if doc is None:
+            return None
+        return OwnedOperation.model_validate(doc).to_stored()
+
+    async def append_audit_entry(self, entry: AuditEntry) -> None:
+        await self.audit_coll.insert_one(entry.model_dump(mode="json"))
+
+    async def load_audit_entries(
+        self, user_id: UserId, entity_id: str | None, offset: int, count: int
+    ) -> list[AuditEntry]:
+        query: dict[str, Any] = {"user_id": user_id}
+        if entity_id is not None:
+            query["entity_id"] = entity_id
+        docs = (
+            await self.audit_coll.find(query)
+            .sort("timestamp", -1)
+            .skip(offset)
+            .to_list(length=count)
+        )
+        return [AuditEntry.model_validate(d) for d in docs]
+
+    async def save_historical_rates(self, days: list[DailyRates]) -> None:
+        if not days:
+            return
+        await self.historical_rates_coll.bulk_write(
+            [
+                ReplaceOne(
+                    {"date": day.date.isoformat()}, day.model_dump(mode="json"), upsert=True
+                )
+                for day in days
+            ]
+        )
+
+    async def load_historical_rates(self, on: datetime.date) -> DailyRates | None:
+        earliest = on - datetime.timedelta(days=HISTORICAL_RATES_MAX_GAP_DAYS)
+        doc = await self.historical_rates_coll.find_one(
+            {"date": {"$lte": on.isoformat(), "$gte": earliest.isoformat()}},
+            sort=[("date", -1)],
+        )
+        if doc is None:
+            return None
+        return DailyRates.model_validate(doc)
+
+    async def load_historical_rates_between(
+        self, start: datetime.date, end: datetime.date
+    ) -> list[DailyRates]:
+        docs = (
+            await self.historical_rates_coll.find(
+                {"date": {"$gte": start.isoformat(), "$lte": end.isoformat()}}
+            )
+            .sort("date", 1)
+            .to_list(length=None)
+        )
+        return [DailyRates.model_validate(d) for d in docs]
+
+    def _owned_collections(self) -> list[AsyncIOMotorCollection]:
+        """Collections of documents with an owner field"""
+        return [
+            self.transactions_coll,
+            self.pools_coll,
+            self.reconciliations_coll,
+            self.report_snapshots_coll,
+            self.allowances_coll,
+            self.challenges_coll,
+            self.goals_coll,
+            self.debts_coll,
+            self.templates_coll,
+            self.rules_coll,
+            self.payees_coll,
+            self.notes_coll,
+            self.balance_snapshots_coll,
+            self.settings_coll,
+            self.month_closes_coll,
+            self.net_worth_coll,
+            self.calendar_tokens_coll,
+            self.two_factors_coll,
+            self.operations_coll,
+            self.change_counters_coll,
+            self.changes_coll,
+        ]
+
+    async def delete_user_data(self, user_id: UserId) -> None:
+        for coll in self._owned_collections():
+            result = await coll.delete_many({"owner": user_id})
+            self.logger.info(f"Deleted {result.deleted_count} docs from {coll.name}")
+        await self.audit_coll.delete_many({"user_id": user_id})
+
+    async def load_user_ids(self) -> list[UserId]:
+        return await self.pools_coll.distinct("owner")
+
+    async def load_storage_usage(self, user_id: UserId) -> dict[str, int]:
+        return {
+            coll.name: await coll.count_documents({"owner": user_id})
+            for coll in self._owned_collections()
+        }
+
+    async def load_disabled_user_ids(self) -> list[UserId]:
+        return await self.disabled_users_coll.distinct("user_id")
+
+    async def is_user_disabled(self, user_id: UserId) -> bool:
+        return await self.disabled_users_coll.find_one({"user_id": user_id}) is not None
+
+    async def set_user_disabled(self, user_id: UserId, disabled: bool) -> None:
+        if disabled:
+            await self.disabled_users_coll.replace_one(
+                {"user_id": user_id},
+                {"user_id": user_id, "disabled_at": datetime.datetime.now(tz=datetime.UTC)},
+                upsert=True,
+            )
+        else:
+            await self.disabled_users_coll.delete_one({"user_id": user_id})
+
+    async def rebuild_indexes(self) -> None:
+        indexes: list[tuple[AsyncIOMotorCollection, list[tuple[str, int]]]] = [
+            (self.pools_coll, [("owner", 1)]),
+            (self.transactions_coll, [("owner", 1), ("transaction.timestamp", -1)]),
+            (self.transactions_coll, [("owner", 1), ("transaction.pool_id", 1)]),
+            (self.transactions_coll, [("transaction.status", 1), ("transaction.timestamp", 1)]),
+            (self.operations_coll, [("owner", 1), ("operation.timestamp", -1)]),
+            (self.audit_coll, [("user_id", 1), ("timestamp", -1)]),
+            (self.notes_coll, [("owner", 1), ("note.pool_id", 1)]),
+            (self.balance_snapshots_coll, [("owner", 1), ("snapshot.pool_id", 1)]),
+            (self.rules_coll, [("owner", 1)]),
+            (self.payees_coll, [("owner", 1), ("payee.name", 1)]),
+            (self.transactions_coll, [("owner", 1), ("transaction.payee_id", 1)]),
+            (self.settings_coll, [("owner", 1)]),
+            (self.month_closes_coll, [("owner", 1)]),
+            (self.net_worth_coll, [("owner", 1), ("snapshot.date", 1)]),
+            (self.calendar_tokens_coll, [("owner", 1)]),
+            (self.calendar_tokens_coll, [("token", 1)]),
+            (self.two_factors_coll, [("owner", 1)]),
+            (self.change_counters_coll, [("owner", 1)]),
+            (self.changes_coll, [("owner", 1), ("change.seq", 1)]),
+            (self.changes_coll, [("owner", 1), ("change.entity", 1), ("change.entity_id", 1)]),
+            (self.historical_rates_coll, [("date", -1)]),
+            (self.disabled_users_coll, [("user_id", 1)]),
+        ]
+        for coll in {id(coll): coll for coll, _ in indexes}.values():
+            await coll.drop_indexes()
+        for coll, keys in indexes:
+            await coll.create_index(keys)
+            self.logger.info(f"Index on {coll.name} {keys} created")
+
+    async def _add_missing_tags(self) -> None:
+        # transactions stored before tags were introduced aren't matched by untagged filters
+        result = await self.transactions_coll.update_many(
+            {"transaction.tags": {"$exists": False}}, {"$set": {"transaction.tags": []}}
+        )
+        self.logger.info(f"Added empty tags to {result.modified_count} transactions")
+
+    async def _store_transaction_kinds(self) -> None:
+        # same as Transaction.kind, for transactions stored before it was introduced
+        kind = {
+            "$switch": {
+                "branches": [
+                    {
+                        "case": {"$ne": [{"$ifNull": ["$transaction.transfer_id", None]}, None]},
+                        "then": TransactionKind.TRANSFER.value,
+                    },
+                    {
+                        "case": {"$lt": [{"$toDecimal": "$transaction.sum.amount"}, 0]},
+                        "then": TransactionKind.EXPENSE.value,
+                    },
+                ],
+                "default": TransactionKind.INCOME.value,
+            }
+        }
+        result = await self.transactions_coll.update_many(
+            {"transaction.kind": {"$exists": False}}, [{"$set": {"transaction.kind": kind}}]
+        )
+        self.logger.info(f"Stored kinds of {result.modified_count} transactions")
+
+    async def migrate(self, dry_run: bool) -> list[Migration]:
+        migrations = [
+            Migration(1, "create indexes", self.rebuild_indexes),
+            Migration(2, "add missing transaction tags", self._add_missing_tags),
+            Migration(3, "store transaction kinds", self._store_transaction_kinds),
+        ]
+        applied = [doc["version"] async for doc in self.migrations_coll.find()]
+        pending = pending_migrations(migrations, applied)
+        if dry_run:
+            return pending
+        for migration in pending:
+            self.logger.info(f"Applying migration {migration}")
+            start = time.time()
+            await migration.apply()
+            await self.migrations_coll.insert_one(
+                {
+                    "version": migration.version,
+                    "name": migration.name,
+                    "applied_at": datetime.datetime.now(tz=datetime.UTC),
+                }
+            )
+            self.logger.info(f"Migration {migration} applied in {time.time() - start:.2} sec")
+        return pending
@@ -0,0 +1,58 @@
+import datetime
+import logging
+
+import aiohttp
+
+from api.types.telemetry import TelemetryReport
+
+logger = logging.getLogger(__name__)
+
+
+class Telemetry:
+    """
+    Opt-in usage statistics for self-hosted instances: counts are always collected in memory so
+    that the report can be previewed, but only sent if the endpoint is configured
+    """
+
+    def __init__(self, endpoint: str | None) -> None:
+        self.endpoint = endpoint
+        self._feature_usage: dict[str, int] = {}
+        self._since = datetime.datetime.now(tz=datetime.UTC)
+
+    @property
+    def enabled(self) -> bool:
+        return self.endpoint is not None
+
+    def record(self, feature: str) -> None:
+        self._feature_usage[feature] = self._feature_usage.get(feature, 0) + 1
+
+    def report(self, backend: str, version: str) -> TelemetryReport:
+        return TelemetryReport(
+            version=version,
+            backend=backend,
+            since=self._since,
+            feature_usage=dict(sorted(self._feature_usage.items())),
+        )
+
+    async def send(self, report: TelemetryReport) -> bool:
+        """Posts the report and resets the counters, returns False if it wasn't sent"""
+        if self.endpoint is None:
+            return False
+        try:
+            await self._post(self.endpoint, report.model_dump_json())
+        except Exception:
+            logger.exception("Error sending telemetry report")
+            return False
+        for feature, count in report.feature_usage.items():
+            self._feature_usage[feature] -= count
+            if not self._feature_usage[feature]:
+                self._feature_usage.pop(feature)
+        self._since = datetime.datetime.now(tz=datetime.UTC)
+        return True
+
+    async def _post(self, endpoint: str, payload: str) -> None:
+        async with aiohttp.ClientSession() as session:
+            async with session.post(
+                endpoint, data=payload, headers={"Content-Type": "application/json"}
+            ) as resp:
+                resp.raise_for_status()
@@ -0,0 +1,60 @@
+"""
+TLS termination by the server itself, for small deployments without a reverse proxy; configured
+with TLS_CERT_FILE and TLS_KEY_FILE, renewed certificates (e.g. by certbot) are picked up without
+a restart
+"""
+
+import asyncio
+import dataclasses
+import logging
+import ssl
+from pathlib import Path
+from typing import Mapping
+
+logger = logging.getLogger(__name__)
+
+RELOAD_CHECK_INTERVAL_SEC = 60
+
+
+@dataclasses.dataclass(frozen=True)
+class TlsConfig:
+    cert_file: Path  # PEM, with the intermediate certificates if any
+    key_file: Path
+
+    def modified_at(self) -> tuple[float, float]:
+        return self.cert_file.stat().st_mtime, self.key_file.stat().st_mtime
+
+
+def tls_config_from_env(env: Mapping[str, str]) -> TlsConfig | None:
+    if "TLS_CERT_FILE" not in env:
+        return None
+    return TlsConfig(cert_file=Path(env["TLS_CERT_FILE"]), key_file=Path(env["TLS_KEY_FILE"]))
+
+
+def reload_if_changed(
+    context: ssl.SSLContext, tls: TlsConfig, loaded_at: tuple[float, float]
+) -> tuple[float, float]:
+    """
+    Loads the changed files into the context used by the server, new connections get the new
+    certificate; returns modification times of the loaded files
+    """
+    try:
+        modified_at = tls.modified_at()
+        if modified_at == loaded_at:
+            return loaded_at
+        context.load_cert_chain(tls.cert_file, tls.key_file)
+    except (OSError, ssl.SSLError):
+        # e.g. the certificate is renewed but the key isn't written yet, retried on next check
+        logger.exception(f"Error reloading TLS certificate from {tls.cert_file}")
+        return loaded_at
+    logger.info(f"Reloaded TLS certificate from {tls.cert_file}")
+    return modified_at
+
+
+async def reload_periodically(
+    context: ssl.SSLContext, tls: TlsConfig, interval_sec: float = RELOAD_CHECK_INTERVAL_SEC
+) -> None:
+    loaded_at = tls.modified_at()
+    while True:
+        await asyncio.sleep(interval_sec)
+        loaded_at = reload_if_changed(context, tls, loaded_at)
@@ -0,0 +1,47 @@
+"""Time-based one-time passwords (RFC 6238), as generated by authenticator apps"""
+
+import base64
+import hashlib
+import hmac
+import secrets
+import struct
+import urllib.parse
+
+DIGITS = 6
+PERIOD_SEC = 30
+# codes from adjacent periods are accepted too, to tolerate clock skew
+ALLOWED_DRIFT_STEPS = 1
+
+
+def generate_secret() -> str:
+    return base64.b32encode(secrets.token_bytes(20)).decode("ascii")
+
+
+def time_step(timestamp: float) -> int:
+    return int(timestamp // PERIOD_SEC)
+
+
+def code_at(secret: str, step: int) -> str:
+    key = base64.b32decode(secret, casefold=True)
+    digest = hmac.new(key, struct.pack(">Q", step), hashlib.sha1).digest()
+    offset = digest[-1] & 0x0F
+    value = struct.unpack(">I", digest[offset : offset + 4])[0] & 0x7FFFFFFF
+    return str(value % 10**DIGITS).zfill(DIGITS)
+
+
+def matching_step(secret: str, code: str, timestamp: float) -> int | None:
+    """Time step the code was generated for, None if it's invalid"""
+    step = time_step(timestamp)
+    for candidate in range(step - ALLOWED_DRIFT_STEPS, step + ALLOWED_DRIFT_STEPS + 1):
+        if hmac.compare_digest(code_at(secret, candidate), code):
+            return candidate
+    return None
+
+
+def provisioning_uri(secret: str, account: str, issuer: str) -> str:
+    """For QR codes scanned by authenticator apps"""
+    label = urllib.parse.quote(f"{issuer}:{account}")
+    query = urllib.parse.urlencode(
+        {"secret": secret, "issuer": issuer, "digits": DIGITS, "period": PERIOD_SEC}
+    )
+    return f"otpauth://totp/{label}?{query}"
@@ -0,0 +1,226 @@
+"""
+Terminal interface for browsing pools, adding expenses and viewing the recent transactions,
+against a running server through the API client or directly against the storage
+"""
+
+import abc
+import asyncio
+import curses
+from decimal import Decimal, InvalidOperation
+from typing import Awaitable, TypeVar
+
+from api.client import ApiError, TetClient
+from api.formatting import format_money
+from api.service import ExpenseService, ServiceError
+from api.types.allowance import PendingSpend
+from api.types.ids import UserId
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction, Transaction, TransactionOrder
+
+RECENT_TRANSACTIONS = 15
+HELP = "up/down: select pool   a: add expense   r: refresh   q: quit"
+
+T = TypeVar("T")
+
+
+class TuiError(Exception):
+    pass
+
+
+class TuiBackend(abc.ABC):
+    @abc.abstractmethod
+    async def load_pools(self) -> list[StoredMoneyPool]: ...
+
+    @abc.abstractmethod
+    async def load_recent_transactions(self) -> list[StoredTransaction]: ...
+
+    @abc.abstractmethod
+    async def load_locale(self) -> str: ...
+
+    @abc.abstractmethod
+    async def add_transaction(self, transaction: Transaction) -> None:
+        """Raises TuiError if the transaction is rejected"""
+
+
+class ClientBackend(TuiBackend):
+    def __init__(self, client: TetClient) -> None:
+        self.client = client
+
+    async def load_pools(self) -> list[StoredMoneyPool]:
+        return await self.client.get_pools()
+
+    async def load_recent_transactions(self) -> list[StoredTransaction]:
+        return await self.client.get_transactions(count=RECENT_TRANSACTIONS)
+
+    async def load_locale(self) -> str:
+        return (await self.client.get_settings()).locale
+
+    async def add_transaction(self, transaction: Transaction) -> None:
+        try:
+            created = await self.client.add_transaction(transaction)
+        except ApiError as e:
+            raise TuiError(str(e.detail))
+        if isinstance(created, PendingSpend):
+            raise TuiError("Added, but the spend is pending approval")
+
+
+class StorageBackend(TuiBackend):
+    def __init__(self, service: ExpenseService, user_id: UserId, descriptions: bool) -> None:
+        self.service = service
+        self.user_id = user_id
+        self.descriptions = descriptions  # decrypted if privacy mode is on
+
+    async def load_pools(self) -> list[StoredMoneyPool]:
+        return await self.service.storage.load_pools(self.user_id)
+
+    async def load_recent_transactions(self) -> list[StoredTransaction]:
+        transactions = await self.service.storage.load_transactions(
+            self.user_id,
+            filter=None,
+            order=TransactionOrder.LATEST,
+            offset=0,
+            count=RECENT_TRANSACTIONS,
+        )
+        return self.service.present_transactions(transactions, self.descriptions)
+
+    async def load_locale(self) -> str:
+        return (await self.service.storage.load_user_settings(self.user_id)).locale
+
+    async def add_transaction(self, transaction: Transaction) -> None:
+        try:
+            await self.service.add_transaction(self.user_id, transaction)
+        except ServiceError as e:
+            raise TuiError(str(e))
+
+
+def parse_expense(pool: StoredMoneyPool, amount: str, description: str) -> Transaction:
+    """Spent amount, positive, with either decimal separator"""
+    try:
+        value = Decimal(amount.strip().replace(",", "."))
+    except InvalidOperation:
+        raise TuiError(f"Invalid amount: {amount!r}")
+    if not value.is_finite() or value <= 0:
+        raise TuiError("Amount must be positive")
+    if not pool.balance:
+        raise TuiError(f"Pool {pool.display_name!r} has no currency")
+    return Transaction(
+        sum=MoneySum(amount=-value, currency=pool.balance[0].currency),
+        pool_id=pool.id,
+        description=description.strip(),
+    )
+
+
+def pool_line(pool: StoredMoneyPool, locale: str) -> str:
+    balance = ", ".join(format_money(s, locale) for s in pool.balance)
+    return f"{pool.display_name}  {balance}"
+
+
+def transaction_line(
+    transaction: StoredTransaction, pools: list[StoredMoneyPool], locale: str
+) -> str:
+    pool_names = {p.id: p.display_name for p in pools}
+    columns = [
+        transaction.timestamp.astimezone().strftime("%Y-%m-%d %H:%M"),
+        f"{format_money(transaction.sum, locale):>12}",
+        pool_names.get(transaction.pool_id, "?"),
+        transaction.description,
+    ]
+    original = transaction.original_sum()
+    if original is not None:
+        columns.append(f"({format_money(original, locale)})")
+    return "  ".join(columns)
+
+
+class Tui:
+    """Synchronous curses loop, running the backend calls on the given event loop"""
+
+    def __init__(self, backend: TuiBackend, loop: asyncio.AbstractEventLoop) -> None:
+        self.backend = backend
+        self.loop = loop
+        self.locale = "en"
+        self.pools: list[StoredMoneyPool] = []
+        self.transactions: list[StoredTransaction] = []
+        self.selected = 0
+        self.status = ""
+
+    def run(self, screen: "curses.window") -> None:
+        curses.curs_set(0)
+        self.refresh()
+        while True:
+            self.draw(screen)
+            key = screen.getch()
+            if key in (ord("q"), 27):
+                return
+            elif key == curses.KEY_UP:
+                self.selected = max(self.selected - 1, 0)
+            elif key == curses.KEY_DOWN:
+                self.selected = min(self.selected + 1, max(len(self.pools) - 1, 0))
+            elif key == ord("r"):
+                self.refresh()
+            elif key == ord("a"):
+                self.add_expense(screen)
+
+    def call(self, coro: Awaitable[T]) -> T:
+        return self.loop.run_until_complete(coro)
+
+    def refresh(self) -> None:
+        try:
+            self.locale = self.call(self.backend.load_locale())
+            self.pools = self.call(self.backend.load_pools())
+            self.transactions = self.call(self.backend.load_recent_transactions())
+        except Exception as e:
+            self.status = f"Failed to load: {e}"
+            return
+        self.selected = min(self.selected, max(len(self.pools) - 1, 0))
+        self.status = ""
+
+    def add_expense(self, screen: "curses.window") -> None:
+        if not self.pools:
+            self.status = "No pools to add to"
+            return
+        pool = self.pools[self.selected]
+        amount = self.prompt(screen, f"Spent from {pool.display_name}: ")
+        if not amount:
+            return
+        description = self.prompt(screen, "Description: ")
+        try:
+            self.call(self.backend.add_transaction(parse_expense(pool, amount, description)))
+            self.refresh()
+            self.status = "Added"
+        except TuiError as e:
+            self.refresh()
+            self.status = str(e)
+
+    def prompt(self, screen: "curses.window", label: str) -> str:
+        height, width = screen.getmaxyx()
+        screen.move(height - 1, 0)
+        screen.clrtoeol()
+        screen.addnstr(height - 1, 0, label, width - 1)
+        curses.echo()
+        curses.curs_set(1)
+        try:
+            value = screen.getstr(height - 1, len(label), max(width - len(label) - 1, 1))
+        finally:
+            curses.noecho()
+            curses.curs_set(0)
+        return value.decode("utf-8", errors="replace")
+
+    def draw(self, screen: "curses.window") -> None:
+        screen.erase()
+        height, width = screen.getmaxyx()
+
+        def line(row: int, text: str, attr: int = curses.A_NORMAL) -> None:
+            if row < height - 1:
+                screen.addnstr(row, 0, text, width - 1, attr)
+
+        line(0, "Pools", curses.A_BOLD)
+        for idx, pool in enumerate(self.pools):
+            attr = curses.A_REVERSE if idx == self.selected else curses.A_NORMAL
+            line(1 + idx, pool_line(pool, self.locale), attr)
+        row = len(self.pools) + 2
+        line(row, "Recent transactions", curses.A_BOLD)
+        for idx, transaction in enumerate(self.transactions):
+            line(row + 1 + idx, transaction_line(transaction, self.pools, self.locale))
+        screen.addnstr(height - 1, 0, self.status or HELP, width - 1, curses.A_DIM)
+        screen.refresh()
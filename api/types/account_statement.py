@@ -0,0 +1,31 @@
+import datetime
+
+import pydantic
+
+from api.types.datetime import Datetime, Timezone
+from api.types.ids import MoneyPoolId, TransactionId
+from api.types.money_sum import MoneySum
+
+
+class StatementLine(pydantic.BaseModel):
+    transaction_id: TransactionId
+    timestamp: Datetime
+    description: str
+    sum: MoneySum
+    balance: MoneySum  # in the transaction's currency, right after it
+
+
+class AccountStatement(pydantic.BaseModel):
+    """Pool's counted transactions in a calendar month, between its opening and closing balances"""
+
+    pool_id: MoneyPoolId
+    pool_name: str
+    year: int
+    month: int = pydantic.Field(ge=1, le=12)
+    timezone: Timezone = "UTC"
+    opening_balance: list[MoneySum]
+    closing_balance: list[MoneySum]
+    lines: list[StatementLine]  # oldest first
+    generated_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
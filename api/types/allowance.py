@@ -0,0 +1,64 @@
+import datetime
+import enum
+import secrets
+import uuid
+from decimal import Decimal
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import AllowanceId, MoneyPoolId
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+ALLOWANCE_PERIOD = datetime.timedelta(weeks=1)
+
+
+class PendingSpendStatus(enum.Enum):
+    PENDING = "pending"
+    APPROVED = "approved"
+    REJECTED = "rejected"
+
+
+class PendingSpend(pydantic.BaseModel):
+    id: str = pydantic.Field(default_factory=lambda: uuid.uuid4().hex)
+    transaction: Transaction
+    requested_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+    status: PendingSpendStatus = PendingSpendStatus.PENDING
+
+
+class Allowance(pydantic.BaseModel):
+    """Pool managed for a child, topped up weekly from the parent's pool"""
+
+    pool_id: MoneyPoolId
+    source_pool_id: MoneyPoolId
+    child_name: str
+    weekly_amount: MoneySum
+    next_payment_at: Datetime
+
+    # spends above the threshold wait for parent's approval, None means no approval needed
+    approval_threshold: Decimal | None = None
+    pending_spends: list[PendingSpend] = pydantic.Field(default_factory=list)
+
+    # gives the child read-only access to the pool
+    view_token: str = pydantic.Field(default_factory=lambda: secrets.token_urlsafe(24))
+
+    def needs_approval(self, transaction: Transaction) -> bool:
+        return (
+            self.approval_threshold is not None
+            and transaction.pool_id == self.pool_id
+            and -transaction.sum.amount > self.approval_threshold
+        )
+
+    def pending_spend(self, spend_id: str) -> PendingSpend | None:
+        return next((s for s in self.pending_spends if s.id == spend_id), None)
+
+
+class StoredAllowance(Allowance):
+    id: AllowanceId
+
+    @classmethod
+    def from_allowance(cls, a: Allowance, id: AllowanceId) -> "StoredAllowance":
+        return StoredAllowance(id=id, **a.model_dump())
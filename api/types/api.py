@@ -1,17 +1,78 @@
+import datetime
+import enum
+from decimal import Decimal
+from typing import Self
+
 import pydantic
 
+from api.types.balance_snapshot import StoredBalanceSnapshot
 from api.types.currency import Currency
 from api.types.datetime import Datetime
-from api.types.ids import MoneyPoolId
-from api.types.money_pool import StoredMoneyPool
+from api.types.goal import Goal
+from api.types.historical_rates import HistoricalRate
+from api.types.ids import ChallengeId, GoalId, MoneyPoolId, PayeeId, TransactionId, UserId
+from api.types.money_pool import (
+    ColorHex,
+    MoneyPool,
+    OverdraftFacility,
+    PoolIcon,
+    StoredMoneyPool,
+)
 from api.types.money_sum import MoneySum
-from api.types.transaction import StoredTransaction
+from api.types.note import MAX_NOTE_LENGTH, PoolNote, StoredPoolNote
+from api.types.operation import OperationKind
+from api.types.reconciliation import StoredReconciliation
+from api.types.rule import CategorizationRule, RuleMatch
+from api.types.template import TransactionTemplate
+from api.types.transaction import (
+    StoredTransaction,
+    Transaction,
+    TransactionSplit,
+    TransactionStatus,
+)
+
+MAX_BULK_TRANSACTIONS = 500
+
+
+class CreatePoolRequestBody(pydantic.BaseModel):
+    """Client-settable pool fields, the id and the bookkeeping ones are set on the server"""
+
+    display_name: str
+    balance: list[MoneySum]
+    is_visible: bool = True
+    display_color: str | None = None
+    icon: PoolIcon | None = None
+    color_hex: ColorHex | None = None
+    overdraft: OverdraftFacility | None = None
+    group: str | None = None
+    default_category: str | None = None
+
+    def to_money_pool(self) -> MoneyPool:
+        return MoneyPool.model_validate(self.model_dump())
 
 
 class MoneyPoolAttributesUpdate(pydantic.BaseModel):
     is_visible: bool | None = None
     display_name: str | None = None
     display_color: str | None = None
+    icon: PoolIcon | None = None
+    color_hex: ColorHex | None = None
+    overdraft: OverdraftFacility | None = None
+    group: str | None = None
+    default_category: str | None = None
+
+
+class RedenominatePoolRequestBody(pydantic.BaseModel):
+    to_currency: Currency
+    from_currency: Currency | None = None  # the pool's main currency if omitted
+    # amount in to_currency per unit of from_currency, the current rate if omitted
+    rate: float | None = pydantic.Field(default=None, gt=0)
+    # at the rate if given, otherwise at the historical rates on the transactions' dates
+    convert_transactions: bool = False
+
+
+class PoolOrderRequestBody(pydantic.BaseModel):
+    pool_ids: list[MoneyPoolId]  # all of the user's pools, in the desired order
 
 
 class SyncBalanceRequestBody(pydantic.BaseModel):
@@ -23,22 +84,106 @@ class TransferMoneyRequestBody(pydantic.BaseModel):
     to_pool: MoneyPoolId
     sum: MoneySum
     description: str
+    # amount received, for pools in different currencies; converted at the current rate if omitted
+    received: MoneySum | None = None
+
+
+class PoolTransferRequestBody(pydantic.BaseModel):
+    to_pool: MoneyPoolId
+    sum: MoneySum
+    description: str = ""
+
+
+class CashWithdrawalRequestBody(pydantic.BaseModel):
+    to_pool: MoneyPoolId
+    sum: MoneySum
+    fee: MoneySum | None = None
+    description: str = ""
+
+
+class CashWithdrawalResponse(pydantic.BaseModel):
+    transactions: list[StoredTransaction]
+    # withdrawn cash not yet covered by tracked or diffuse spending
+    unaccounted: MoneySum
+
+
+class UnaccountedSpendingResponse(pydantic.BaseModel):
+    since: Datetime | None  # last time the balance was synced, None if never
+    expected: MoneySum  # according to tracked transactions
+    actual: MoneySum
+    gap: MoneySum  # positive when there's less money than expected
+    unaccounted_withdrawn: MoneySum
+
+
+class CloseUnaccountedRequestBody(pydantic.BaseModel):
+    actual: MoneySum
+    description: str = ""
+
+
+class IssueSeverity(enum.StrEnum):
+    ERROR = "error"  # blocks creation
+    WARNING = "warning"  # returned alongside the result, for the user to confirm
+
+
+class ValidationIssue(pydantic.BaseModel):
+    code: str  # machine-readable, e.g. "large_amount"
+    severity: IssueSeverity
+    message: str
+
+
+class CreatedTransactionResponse(StoredTransaction):
+    warnings: list[ValidationIssue] = pydantic.Field(default_factory=list)
+
+
+class DuplicateTransactionResponse(pydantic.BaseModel):
+    detail: str
+    existing_id: TransactionId
+
+
+class BulkTransactionsRequestBody(pydantic.BaseModel):
+    transactions: list[Transaction] = pydantic.Field(
+        min_length=1, max_length=MAX_BULK_TRANSACTIONS
+    )
+
+
+class QuickAddRequestBody(pydantic.BaseModel):
+    text: str = pydantic.Field(min_length=1, max_length=500)  # e.g. "14.99 USD groceries lidl"
+
+
+class BulkTransactionResult(pydantic.BaseModel):
+    index: int
+    transaction: StoredTransaction | None = None
+    error: str | None = None
+
+
+class StatementImportResponse(pydantic.BaseModel):
+    imported: list[StoredTransaction]
+    skipped: int  # entries that were already imported or entered by hand
+
+
+class PoolNoteView(pydantic.BaseModel):
+    note: StoredPoolNote
+    transaction: StoredTransaction | None  # None if no transaction is pinned or it's deleted
 
 
 class MainApiRouteResponse(pydantic.BaseModel):
     pools: list[StoredMoneyPool]
     last_transactions: list[StoredTransaction]
+    pinned: list[PoolNoteView] = pydantic.Field(default_factory=list)
 
 
 class ReportPoolStats(pydantic.BaseModel):
     pool: StoredMoneyPool
     total: MoneySum
     fractions: dict[Currency, float]
+    # balance in currencies with unavailable exchange rates, not included in the total
+    unconverted: list[MoneySum] = pydantic.Field(default_factory=list)
 
 
 class ReportTagNetTotal(pydantic.BaseModel):
     tag: str | None
     total: MoneySum
+    unconverted: list[MoneySum] = pydantic.Field(default_factory=list)
 
 
 class ReportPoolSnapshot(pydantic.BaseModel):
@@ -53,6 +198,324 @@ class ReportApiRouteResponse(pydantic.BaseModel):
     spent: MoneySum
     made: MoneySum
     tag_totals: list[ReportTagNetTotal]
+    spent_unconverted: list[MoneySum] = pydantic.Field(default_factory=list)
+    made_unconverted: list[MoneySum] = pydantic.Field(default_factory=list)
+    # for display in the user's locale, not frozen in report snapshots
+    spent_formatted: str | None = None
+    made_formatted: str | None = None
+
+
+class CurrencyInfo(pydantic.BaseModel):
+    code: str
+    name: str
+    symbol: str | None  # None if shown with the code
+    exponent: int  # number of digits after the decimal point
+    is_iso: bool
+
+
+class FormattedMoney(pydantic.BaseModel):
+    sum: MoneySum
+    locale: str
+    formatted: str
+
+
+class CreateReportSnapshotRequestBody(pydantic.BaseModel):
+    year: int
+    month: int = pydantic.Field(ge=1, le=12)
+    points: int = pydantic.Field(ge=2, le=360, default=30)
+    target_currency: Currency = pydantic.Field(default="EUR", validate_default=True)
+
+
+class ReportValueChange(pydantic.BaseModel):
+    metric: str
+    tag: str | None = None  # for tag totals
+    reported: MoneySum | None
+    current: MoneySum | None
+
+
+class FxPoolCurrencyGains(pydantic.BaseModel):
+    pool_id: MoneyPoolId
+    balance: MoneySum  # foreign currency balance at the end of the period
+    cost_basis: MoneySum  # of the balance at the end of the period
+    realized: MoneySum  # on the outflows in the period
+    unrealized: MoneySum  # change over the period, on the balance held
+
+
+class FxGainsReportResponse(pydantic.BaseModel):
+    per_pool: list[FxPoolCurrencyGains]
+    realized: MoneySum
+    unrealized: MoneySum
+    # foreign currency balances with unavailable exchange rates, not included in the gains
+    unconverted: list[MoneySum] = pydantic.Field(default_factory=list)
+
+
+class BalanceProjection(pydantic.BaseModel):
+    balance: MoneySum  # current
+    scheduled: MoneySum  # net of the scheduled transactions until the end of the month
+    average_daily_spend: MoneySum  # over the lookback window
+    projected: MoneySum
+
+
+class PoolProjectionResponse(pydantic.BaseModel):
+    pool_id: MoneyPoolId
+    projected_until: Datetime  # end of the current month
+    lookback_days: int
+    projections: list[BalanceProjection]  # per currency in the pool's balance
+
+
+class AnomalyReason(enum.StrEnum):
+    LARGE_AMOUNT = "large_amount"  # compared to the expenses in the same currency
+    NEW_CATEGORY = "new_category"  # none of the transaction's tags were spent on before
+
+
+class SpendingAnomaly(pydantic.BaseModel):
+    transaction: StoredTransaction
+    reasons: list[AnomalyReason]
+    z_score: float | None  # of the amount, None if there's too little history to compare
+
+
+class RecurringSuggestion(pydantic.BaseModel):
+    """Transactions repeating at a regular interval, e.g. a subscription, to make a template of"""
+
+    pool_id: MoneyPoolId
+    description: str  # of the latest occurrence
+    sum: MoneySum  # median of the occurrences
+    interval_days: int
+    occurrences: int
+    last_at: Datetime
+    next_expected_at: Datetime
+    transaction_ids: list[TransactionId]  # latest first
+
+
+class CategorySpending(pydantic.BaseModel):
+    category: str | None
+    spent: MoneySum
+    fraction: float  # of the total spent in the period
+    previous_spent: MoneySum
+    change: float | None  # relative to the previous period, None if nothing was spent then
+
+
+class ForeignSpending(pydantic.BaseModel):
+    paid: MoneySum  # in the original currency
+    charged: MoneySum  # converted to the report currency
+
+
+class CategorySpendingReportResponse(pydantic.BaseModel):
+    previous_start: Datetime
+    previous_end: Datetime
+    spent: MoneySum
+    previous_spent: MoneySum
+    categories: list[CategorySpending]
+    # expenses recorded with the original amount, per original currency
+    foreign_spending: list[ForeignSpending] = pydantic.Field(default_factory=list)
+
+
+class PayeeSpending(pydantic.BaseModel):
+    payee_id: PayeeId | None  # None for the transactions without a payee
+    name: str | None
+    spent: MoneySum
+    fraction: float  # of the total spent in the period
+    transaction_count: int
+
+
+class PayeeSpendingReportResponse(pydantic.BaseModel):
+    spent: MoneySum
+    payees: list[PayeeSpending]
+
+
+class CashFlowMonth(pydantic.BaseModel):
+    """Calendar month or the user's reporting period, e.g. a pay cycle from the 25th to the 24th"""
+
+    month: str  # YYYY-MM the period starts in, in the user's timezone
+    start: Datetime
+    end: Datetime
+    inflow: MoneySum
+    outflow: MoneySum  # positive
+    net: MoneySum
+
+
+class CashFlowReportResponse(pydantic.BaseModel):
+    inflow: MoneySum
+    outflow: MoneySum
+    net: MoneySum
+    months: list[CashFlowMonth]
+
+
+class CreateAllowanceRequestBody(pydantic.BaseModel):
+    pool_id: MoneyPoolId
+    source_pool_id: MoneyPoolId
+    child_name: str
+    weekly_amount: MoneySum
+    approval_threshold: Decimal | None = None
+    first_payment_at: Datetime | None = None  # now by default
+
+
+class AllowanceView(pydantic.BaseModel):
+    child_name: str
+    balance: list[MoneySum]
+    weekly_amount: MoneySum
+    next_payment_at: Datetime
+    last_transactions: list[StoredTransaction]
+
+
+class ChallengeProgress(pydantic.BaseModel):
+    challenge_id: ChallengeId
+    current: float
+    target: float
+    fraction: float
+    completed: bool
+
+
+class GoalProgress(pydantic.BaseModel):
+    goal_id: GoalId
+    saved: MoneySum
+    target: MoneySum
+    fraction: float
+    monthly_saving_rate: MoneySum  # over the recent months
+    projected_at_target_date: MoneySum
+    on_track: bool
+    projected_completion: Datetime | None  # None if savings don't grow
+
+
+class GoalUpdate(pydantic.BaseModel):
+    name: str | None = None
+    target: MoneySum | None = None
+    target_date: Datetime | None = None
+    pool_id: MoneyPoolId | None = None
+
+    def apply(self, goal: Goal) -> None:
+        if self.name is not None:
+            goal.name = self.name
+        if self.target is not None:
+            goal.target = self.target
+        if self.target_date is not None:
+            goal.target_date = self.target_date
+        if self.pool_id is not None:
+            goal.pool_id = self.pool_id
+
+
+class SettleDebtRequestBody(pydantic.BaseModel):
+    pool_id: MoneyPoolId  # the money is returned to / paid from this pool
+
+
+class ExpenseShare(pydantic.BaseModel):
+    counterparty: str = pydantic.Field(min_length=1)
+    shares: Decimal = pydantic.Field(gt=0)  # e.g. percentages or equal parts
+
+
+class ShareTransactionRequestBody(pydantic.BaseModel):
+    participants: list[ExpenseShare] = pydantic.Field(min_length=1)
+    own_shares: Decimal = pydantic.Field(default=Decimal(1), ge=0)
+
+    @pydantic.model_validator(mode="after")
+    def unique_counterparties(self) -> Self:
+        counterparties = [p.counterparty for p in self.participants]
+        if len(counterparties) != len(set(counterparties)):
+            raise ValueError("each counterparty can take part only once")
+        return self
+
+
+class CounterpartyDebtSummary(pydantic.BaseModel):
+    counterparty: str
+    # outstanding balance per currency, positive if the counterparty owes the user
+    balance: list[MoneySum]
+
+
+class OverdraftStatus(pydantic.BaseModel):
+    pool_id: MoneyPoolId
+    facility: OverdraftFacility
+    balance: MoneySum
+    in_overdraft: bool
+    available: MoneySum  # including the overdraft limit
+    overdrawn_since: Datetime | None
+    interest_free_until: Datetime | None
+    # interest accrued by the end of the projection horizon if the balance stays as is
+    projected_fee: MoneySum
+    projected_until: Datetime
+
+
+class HistoricalRatesImportResponse(pydantic.BaseModel):
+    days_imported: int
+    first_date: datetime.date
+    last_date: datetime.date
+
+
+class RateHistoryResponse(pydantic.BaseModel):
+    base: Currency
+    quote: Currency
+    rates: list[HistoricalRate]  # amount in quote per 1 base, only the days with both stored
+
+
+class TransactionTemplateUpdate(pydantic.BaseModel):
+    name: str | None = None
+    sum: MoneySum | None = None
+    pool_id: MoneyPoolId | None = None
+    description: str | None = None
+    tags: list[str] | None = None
+
+    def apply(self, template: TransactionTemplate) -> None:
+        if self.name is not None:
+            template.name = self.name
+        if self.sum is not None:
+            template.sum = self.sum
+        if self.pool_id is not None:
+            template.pool_id = self.pool_id
+        if self.description is not None:
+            template.description = self.description
+        if self.tags is not None:
+            template.tags = self.tags
+
+
+class CategorizationRuleUpdate(pydantic.BaseModel):
+    pattern: str | None = pydantic.Field(default=None, min_length=1)
+    match: RuleMatch | None = None
+    case_sensitive: bool | None = None
+    category: str | None = None
+    merchant: str | None = None
+    pool_id: MoneyPoolId | None = None
+
+    def apply(self, rule: CategorizationRule) -> None:
+        if self.pattern is not None:
+            rule.pattern = self.pattern
+        if self.match is not None:
+            rule.match = self.match
+        if self.case_sensitive is not None:
+            rule.case_sensitive = self.case_sensitive
+        if self.category is not None:
+            rule.category = self.category
+        if self.merchant is not None:
+            rule.merchant = self.merchant
+        if self.pool_id is not None:
+            rule.pool_id = self.pool_id
+
+
+class RuleApplicationResponse(pydantic.BaseModel):
+    updated_transaction_ids: list[TransactionId]
+    locked: int  # matching transactions left as is because their period is locked
+
+
+class ApplyTemplateRequestBody(pydantic.BaseModel):
+    # overrides for the template's defaults
+    sum: MoneySum | None = None
+    description: str | None = None
+    timestamp: Datetime | None = None
+
+
+class PoolNoteUpdate(pydantic.BaseModel):
+    text: str | None = pydantic.Field(default=None, max_length=MAX_NOTE_LENGTH)
+    transaction_id: TransactionId | None = None
+
+    def apply(self, note: PoolNote) -> None:
+        if self.text is not None:
+            note.text = self.text
+        if self.transaction_id is not None:
+            note.transaction_id = self.transaction_id
+
+
+class UndoResponse(pydantic.BaseModel):
+    kind: OperationKind  # of the undone operation
+    # deleted if the operation created them, restored otherwise
+    transactions: list[StoredTransaction]
 
 
 class LoginLinkResponse(pydantic.BaseModel):
@@ -60,15 +523,201 @@ class LoginLinkResponse(pydantic.BaseModel):
     start_param: str
 
 
+class TokenScope(enum.StrEnum):
+    READ = "read"  # e.g. for dashboards and widgets
+    READ_WRITE = "read_write"
+
+
+class ApiTokenResponse(pydantic.BaseModel):
+    token: str
+    scope: TokenScope
+
+
+class TwoFactorSetupResponse(pydantic.BaseModel):
+    secret: str  # base32, for manual entry
+    otpauth_uri: str
+    recovery_codes: list[str]  # single use, shown only once
+
+
+class AuthSession(pydantic.BaseModel):
+    """Login or issued token, identified by an id rather than the token itself"""
+
+    id: str
+    device_name: str | None
+    scope: TokenScope
+    created_at: Datetime
+    last_used_at: Datetime | None = None
+    current: bool = False  # used for the request
+
+
+class SensitiveViewRequestBody(pydantic.BaseModel):
+    """Fresh proof of identity, which of the two depends on the auth, see Auth.reauthenticate"""
+
+    code: str | None = None  # TOTP or recovery code
+    start_param: str | None = None  # of a login finished within the last minutes
+
+
+class SensitiveViewTokenResponse(pydantic.BaseModel):
+    token: str
+    expires_in_sec: float
+
+
+class LiveTicketResponse(pydantic.BaseModel):
+    ticket: str  # single use
+    expires_in_sec: float
+
+
+class CalendarTokenResponse(pydantic.BaseModel):
+    token: str
+    feed_path: str  # relative to the API root, for calendar apps to subscribe to
+
+
+class UserAccountView(pydantic.BaseModel):
+    user_id: UserId
+    is_admin: bool
+    disabled: bool
+    usage: dict[str, int]  # stored entities per kind, e.g. {"transactions": 120, "pools": 3}
+
+
+class UserAccountUpdate(pydantic.BaseModel):
+    disabled: bool | None = None
+
+
 class TransactionUpdate(pydantic.BaseModel):
     description: str | None = None
     timestamp: Datetime | None = None
     tags: list[str] | None = None
+    splits: list[TransactionSplit] | None = None  # empty to unsplit
+    payee_id: PayeeId | None = None  # empty to unset
 
-    def apply(self, tran: StoredTransaction) -> None:
+    def apply(self, tran: Transaction) -> None:
         if self.description is not None:
             tran.description = self.description
         if self.tags is not None:
             tran.tags = self.tags
+        if self.splits is not None:
+            tran.splits = self.splits
+        if self.payee_id is not None:
+            tran.payee_id = self.payee_id or None
         if self.timestamp is not None:
             tran.timestamp = self.timestamp
+
+
+class TransactionEdit(pydantic.BaseModel):
+    """Update or, without one, deletion of a transaction the client has seen at the version"""
+
+    transaction_id: TransactionId
+    expected_version: int
+    update: TransactionUpdate | None = None
+
+
+class TransactionStatusUpdate(pydantic.BaseModel):
+    status: TransactionStatus
+
+
+class PoolBalanceResponse(pydantic.BaseModel):
+    current: list[MoneySum]  # including pending transactions
+    available: list[MoneySum]  # cleared transactions only
+    pending_transaction_ids: list[TransactionId]
+
+
+class SyncResponse(pydantic.BaseModel):
+    cursor: int  # to pass as since on the next sync
+    pools: list[StoredMoneyPool]  # created or updated
+    transactions: list[StoredTransaction]
+    deleted_pool_ids: list[MoneyPoolId]
+    deleted_transaction_ids: list[TransactionId]
+
+
+class ClientChange(pydantic.BaseModel):
+    """Transaction change made on the client while offline"""
+
+    client_id: str  # the client's own id of the change, echoed in the result
+    kind: OperationKind
+    transaction: Transaction | None = None  # to create
+    transaction_id: TransactionId | None = None  # to update or delete
+    base_version: int | None = None  # of the transaction the client updated or deleted
+    update: TransactionUpdate | None = None
+
+    @pydantic.model_validator(mode="after")
+    def fields_match_kind(self) -> Self:
+        match self.kind:
+            case OperationKind.CREATE:
+                if self.transaction is None:
+                    raise ValueError("created transaction is required")
+                return self
+            case OperationKind.UPDATE:
+                if self.update is None:
+                    raise ValueError("transaction update is required")
+        if self.transaction is not None:
+            raise ValueError("only created transactions are sent whole")
+        if self.transaction_id is None or self.base_version is None:
+            raise ValueError("transaction id and base version are required")
+        return self
+
+
+class SyncUploadRequestBody(pydantic.BaseModel):
+    changes: list[ClientChange] = pydantic.Field(min_length=1, max_length=MAX_BULK_TRANSACTIONS)
+
+    @pydantic.model_validator(mode="after")
+    def unique_client_ids(self) -> Self:
+        client_ids = [c.client_id for c in self.changes]
+        if len(client_ids) != len(set(client_ids)):
+            raise ValueError("client ids must be unique")
+        return self
+
+
+class SyncChangeStatus(enum.StrEnum):
+    ACCEPTED = "accepted"
+    CONFLICT = "conflict"  # the transaction changed on the server since the base version
+
+
+class SyncChangeResult(pydantic.BaseModel):
+    client_id: str
+    status: SyncChangeStatus
+    # as stored once accepted, the server's one on conflict; None if deleted
+    transaction: StoredTransaction | None = None
+
+
+class SyncChangeError(pydantic.BaseModel):
+    client_id: str
+    error: str
+
+
+class SyncUploadResponse(pydantic.BaseModel):
+    results: list[SyncChangeResult]  # in the order of the changes
+
+
+class StartReconciliationRequestBody(pydantic.BaseModel):
+    start: Datetime
+    end: Datetime
+    statement_balance: list[MoneySum] = pydantic.Field(default_factory=list)
+
+
+class ReconciliationMatchUpdate(pydantic.BaseModel):
+    transaction_ids: list[TransactionId]
+    matched: bool = True
+
+
+class ReconciliationWorksheetItem(pydantic.BaseModel):
+    transaction: StoredTransaction
+    matched: bool
+
+
+class ReconciliationWorksheet(pydantic.BaseModel):
+    reconciliation: StoredReconciliation
+    items: list[ReconciliationWorksheetItem]
+    # pool balance at the end of the period, as tracked
+    computed_balance: list[MoneySum]
+    # statement balance minus computed balance minus recorded adjustments, per currency
+    discrepancies: list[MoneySum]
+
+
+class BalanceSnapshotReconciliation(pydantic.BaseModel):
+    snapshot: StoredBalanceSnapshot
+    # pool balance at the snapshot time, as tracked
+    computed_balance: list[MoneySum]
+    # snapshot balance minus computed balance, per snapshot currency
+    drift: list[MoneySum]
+    # to bring the tracked balance in line with the snapshot, one per drifted currency
+    suggested_adjustments: list[Transaction]
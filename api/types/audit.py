@@ -0,0 +1,21 @@
+import datetime
+from typing import Any
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import UserId
+
+
+class AuditEntry(pydantic.BaseModel):
+    user_id: UserId | None  # None for shared data, like historical exchange rates
+    action: str  # storage method name, e.g. "update_transaction"
+    entity_type: str
+    entity_id: str | None
+    timestamp: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+    request_id: str | None = None
+    # entity snapshots, None before creation and after deletion
+    before: dict[str, Any] | None = None
+    after: dict[str, Any] | None = None
@@ -0,0 +1,38 @@
+import datetime
+from typing import Self
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import BalanceSnapshotId, MoneyPoolId
+from api.types.money_sum import MoneySum
+
+
+class BalanceSnapshot(pydantic.BaseModel):
+    """Real balance of the pool at some moment, e.g. as shown by the bank app or an ATM receipt"""
+
+    pool_id: MoneyPoolId
+    timestamp: Datetime
+    balance: list[MoneySum]  # one sum per currency, those not listed aren't compared
+    created_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+
+    @pydantic.model_validator(mode="after")
+    def one_sum_per_currency(self) -> Self:
+        if not self.balance:
+            raise ValueError("balance snapshot must have at least one sum")
+        currencies = [s.currency.code for s in self.balance]
+        if len(currencies) != len(set(currencies)):
+            raise ValueError("balance snapshot must have one sum per currency")
+        return self
+
+
+class StoredBalanceSnapshot(BalanceSnapshot):
+    id: BalanceSnapshotId
+
+    @classmethod
+    def from_balance_snapshot(
+        cls, s: BalanceSnapshot, id: BalanceSnapshotId
+    ) -> "StoredBalanceSnapshot":
+        return StoredBalanceSnapshot(id=id, **s.model_dump())
@@ -0,0 +1,54 @@
+import enum
+from decimal import Decimal
+from typing import Self
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import ChallengeId, MoneyPoolId
+from api.types.money_sum import MoneySum
+
+
+class ChallengeKind(enum.Enum):
+    # at least target_days days in the period without any spending
+    NO_SPEND_DAYS = "no_spend_days"
+    # saving weekly_step * N into the pool during the N-th week, 52 weeks
+    FIFTY_TWO_WEEKS = "fifty_two_weeks"
+    # rounding every spending up to round_to and collecting the differences until the target
+    ROUND_UP_SPRINT = "round_up_sprint"
+
+
+class Challenge(pydantic.BaseModel):
+    kind: ChallengeKind
+    name: str
+    start: Datetime
+    end: Datetime
+
+    # kind-specific parameters
+    target_days: int | None = None
+    pool_id: MoneyPoolId | None = None
+    weekly_step: MoneySum | None = None
+    round_to: Decimal | None = None
+    target: MoneySum | None = None
+
+    completed_at: Datetime | None = None
+
+    @pydantic.model_validator(mode="after")
+    def kind_parameters_present(self) -> Self:
+        required = {
+            ChallengeKind.NO_SPEND_DAYS: ["target_days"],
+            ChallengeKind.FIFTY_TWO_WEEKS: ["pool_id", "weekly_step"],
+            ChallengeKind.ROUND_UP_SPRINT: ["round_to", "target"],
+        }[self.kind]
+        missing = [field for field in required if getattr(self, field) is None]
+        if missing:
+            raise ValueError(f"{self.kind.value} challenge requires {', '.join(missing)}")
+        return self
+
+
+class StoredChallenge(Challenge):
+    id: ChallengeId
+
+    @classmethod
+    def from_challenge(cls, c: Challenge, id: ChallengeId) -> "StoredChallenge":
+        return StoredChallenge(id=id, **c.model_dump())
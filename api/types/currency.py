@@ -1,4 +1,4 @@
-from typing import Annotated, Any
+from typing import Annotated, Any, Iterable
 
 import pydantic
 
@@ -6,14 +6,31 @@ from api.iso4217 import CURRENCIES
 from api.types.currency_iso4217 import CurrencyISO4217
 
 
+# non-ISO currencies enabled on startup; stored data using them can't be loaded without them
+CUSTOM_CURRENCIES: dict[str, CurrencyISO4217] = {}
+
+
+def register_currencies(currencies: Iterable[CurrencyISO4217]) -> None:
+    for c in currencies:
+        if c.code in CURRENCIES:
+            raise ValueError(f"{c.code} is an ISO 4217 currency")
+        CUSTOM_CURRENCIES[c.code] = c
+
+
+def all_currencies() -> list[CurrencyISO4217]:
+    return sorted([*CURRENCIES.values(), *CUSTOM_CURRENCIES.values()], key=lambda c: c.code)
+
+
 def parse_currency(v: Any) -> CurrencyISO4217:
     if isinstance(v, CurrencyISO4217):
         return v
     if isinstance(v, str):
         v = v.upper()
-        if v not in CURRENCIES:
-            raise ValueError(f"not a valid ISO 4217 code: {v}")
-        return CURRENCIES[v]
+        if v in CURRENCIES:
+            return CURRENCIES[v]
+        if v in CUSTOM_CURRENCIES:
+            return CUSTOM_CURRENCIES[v]
+        raise ValueError(f"not a valid ISO 4217 or registered currency code: {v}")
     else:
         raise TypeError("currency value must be a string containing three-letter ISO2417 code")
 
@@ -4,14 +4,18 @@ from typing import Any
 
 @dataclass
 class CurrencyISO4217:
-    """See iso4217.py for a list of instances of this class"""
+    """See iso4217.py for a list of instances of this class, crypto_currencies.py for non-ISO ones"""
 
-    code: str  # 3-letter ISO code, uppercase
-    numeric_code: int
+    code: str  # 3-letter ISO code, uppercase; non-ISO codes may be longer
+    numeric_code: int  # 0 for non-ISO currencies
     name: str
     entities: list[str]  # countries etc
     precision: int
 
+    @property
+    def is_iso(self) -> bool:
+        return self.numeric_code != 0
+
     def __eq__(self, other: Any) -> bool:
         if isinstance(other, CurrencyISO4217):
             return self.code == other.code
@@ -1,4 +1,5 @@
 import datetime as dt
+import zoneinfo
 from typing import Annotated, Any
 
 import pydantic
@@ -24,3 +25,15 @@ Datetime = Annotated[
     pydantic.PlainSerializer(lambda dt: dt.timestamp(), return_type=float),
     pydantic.WithJsonSchema({"type": "number"}),
 ]
+
+
+def parse_timezone(v: str) -> str:
+    try:
+        zoneinfo.ZoneInfo(v)
+    except (zoneinfo.ZoneInfoNotFoundError, ValueError):
+        raise ValueError(f"unknown timezone {v!r}")
+    return v
+
+
+# IANA timezone name, e.g. "Europe/Berlin"
+Timezone = Annotated[str, pydantic.AfterValidator(parse_timezone)]
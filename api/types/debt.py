@@ -0,0 +1,42 @@
+import datetime
+import enum
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import DebtId, TransactionId
+from api.types.money_sum import MoneySum
+
+
+class DebtDirection(enum.Enum):
+    LENT = "lent"  # counterparty owes the user
+    BORROWED = "borrowed"  # user owes the counterparty
+
+
+class Debt(pydantic.BaseModel):
+    counterparty: str
+    direction: DebtDirection
+    sum: MoneySum
+    description: str = ""
+    created_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+
+    settled_at: Datetime | None = None
+    settlement_transaction_id: TransactionId | None = None
+
+    # the shared expense the counterparty owes a portion of
+    shared_transaction_id: TransactionId | None = None
+
+    def signed_sum(self) -> MoneySum:
+        """Positive if the counterparty owes the user"""
+        sign = 1 if self.direction is DebtDirection.LENT else -1
+        return MoneySum(amount=sign * self.sum.amount, currency=self.sum.currency)
+
+
+class StoredDebt(Debt):
+    id: DebtId
+
+    @classmethod
+    def from_debt(cls, d: Debt, id: DebtId) -> "StoredDebt":
+        return StoredDebt(id=id, **d.model_dump())
@@ -0,0 +1,23 @@
+import enum
+
+import pydantic
+
+from api.types.api import CategorySpending
+from api.types.datetime import Datetime
+from api.types.money_sum import MoneySum
+
+
+class DigestPeriod(enum.StrEnum):
+    DAY = "day"
+    WEEK = "week"
+    MONTH = "month"  # the user's reporting period, e.g. a pay cycle
+
+
+class Digest(pydantic.BaseModel):
+    period: DigestPeriod
+    start: Datetime
+    end: Datetime
+    income: MoneySum
+    expenses: MoneySum
+    transaction_count: int
+    top_categories: list[CategorySpending]
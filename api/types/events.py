@@ -0,0 +1,76 @@
+import datetime
+from typing import Annotated, Literal
+
+import pydantic
+
+from api.types.challenge import StoredChallenge
+from api.types.datetime import Datetime
+from api.types.ids import MoneyPoolId, UserId
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction
+
+
+class BaseEvent(pydantic.BaseModel):
+    user_id: UserId
+    timestamp: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+
+
+# transactions are as stored, i.e. with descriptions encrypted if privacy mode is on
+
+
+class TransactionCreated(BaseEvent):
+    type: Literal["transaction_created"] = "transaction_created"
+    transaction: StoredTransaction
+
+
+class TransactionUpdated(BaseEvent):
+    type: Literal["transaction_updated"] = "transaction_updated"
+    transaction: StoredTransaction  # after the update
+
+
+class TransactionDeleted(BaseEvent):
+    type: Literal["transaction_deleted"] = "transaction_deleted"
+    transaction: StoredTransaction
+
+
+class PoolCreated(BaseEvent):
+    type: Literal["pool_created"] = "pool_created"
+    pool: StoredMoneyPool
+
+
+class PoolUpdated(BaseEvent):
+    type: Literal["pool_updated"] = "pool_updated"
+    pool: StoredMoneyPool  # after the update
+
+
+class PoolBalanceChanged(BaseEvent):
+    type: Literal["pool_balance_changed"] = "pool_balance_changed"
+    pool_id: MoneyPoolId
+    balance: list[MoneySum]
+
+
+class ChallengeCompleted(BaseEvent):
+    type: Literal["challenge_completed"] = "challenge_completed"
+    challenge: StoredChallenge
+
+
+class MonthClosed(BaseEvent):
+    type: Literal["month_closed"] = "month_closed"
+    year: int
+    month: int
+
+
+DomainEvent = Annotated[
+    TransactionCreated
+    | TransactionUpdated
+    | TransactionDeleted
+    | PoolCreated
+    | PoolUpdated
+    | PoolBalanceChanged
+    | ChallengeCompleted
+    | MonthClosed,
+    pydantic.Field(discriminator="type"),
+]
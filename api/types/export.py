@@ -0,0 +1,43 @@
+import datetime
+
+import pydantic
+
+from api.types.allowance import StoredAllowance
+from api.types.balance_snapshot import StoredBalanceSnapshot
+from api.types.challenge import StoredChallenge
+from api.types.datetime import Datetime
+from api.types.debt import StoredDebt
+from api.types.goal import StoredGoal
+from api.types.ids import UserId
+from api.types.money_pool import StoredMoneyPool
+from api.types.month_close import MonthClose
+from api.types.note import StoredPoolNote
+from api.types.reconciliation import StoredReconciliation
+from api.types.report_snapshot import StoredReportSnapshot
+from api.types.rule import StoredCategorizationRule
+from api.types.settings import UserSettings
+from api.types.template import StoredTransactionTemplate
+from api.types.transaction import StoredTransaction
+
+
+class UserDataExport(pydantic.BaseModel):
+    """Everything stored for the user, categories are transaction tags"""
+
+    user_id: UserId
+    exported_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+    pools: list[StoredMoneyPool]
+    transactions: list[StoredTransaction]  # latest first
+    reconciliations: list[StoredReconciliation]
+    report_snapshots: list[StoredReportSnapshot]
+    allowances: list[StoredAllowance]
+    challenges: list[StoredChallenge]
+    goals: list[StoredGoal]
+    debts: list[StoredDebt]
+    templates: list[StoredTransactionTemplate]
+    rules: list[StoredCategorizationRule]
+    notes: list[StoredPoolNote]
+    balance_snapshots: list[StoredBalanceSnapshot] = []
+    settings: UserSettings
+    month_closes: list[MonthClose]
@@ -0,0 +1,20 @@
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import GoalId, MoneyPoolId
+from api.types.money_sum import MoneySum
+
+
+class Goal(pydantic.BaseModel):
+    name: str
+    target: MoneySum
+    target_date: Datetime
+    pool_id: MoneyPoolId  # savings are tracked as this pool's total
+
+
+class StoredGoal(Goal):
+    id: GoalId
+
+    @classmethod
+    def from_goal(cls, g: Goal, id: GoalId) -> "StoredGoal":
+        return StoredGoal(id=id, **g.model_dump())
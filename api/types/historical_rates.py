@@ -0,0 +1,22 @@
+import datetime
+
+import pydantic
+
+
+class DailyRates(pydantic.BaseModel):
+    """Reference rates for a day, as units of currency per 1 EUR (ECB convention)"""
+
+    date: datetime.date
+    rates: dict[str, float]
+
+    def cross_rate(self, base: str, quote: str) -> float | None:
+        """Amount in quote per 1 base, None if either currency is missing on this day"""
+        eur_rates = {"EUR": 1.0, **self.rates}
+        if base not in eur_rates or quote not in eur_rates:
+            return None
+        return eur_rates[quote] / eur_rates[base]
+
+
+class HistoricalRate(pydantic.BaseModel):
+    date: datetime.date
+    rate: float
@@ -1,3 +1,16 @@
 UserId = str
 MoneyPoolId = str
 TransactionId = str
+ReconciliationId = str
+ReportSnapshotId = str
+TransferId = str
+AllowanceId = str
+ChallengeId = str
+GoalId = str
+DebtId = str
+TemplateId = str
+OperationId = str
+NoteId = str
+RuleId = str
+BalanceSnapshotId = str
+PayeeId = str
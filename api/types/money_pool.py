@@ -1,4 +1,5 @@
 import datetime
+from typing import Annotated
 
 import pydantic
 
@@ -8,6 +9,20 @@ from api.types.money_sum import MoneySum
 from api.types.transaction import Transaction
 
 
+# icon name from the frontend's icon set, e.g. "credit-card"
+PoolIcon = Annotated[
+    str, pydantic.StringConstraints(pattern=r"^[a-z0-9]+(-[a-z0-9]+)*$", max_length=32)
+]
+
+ColorHex = Annotated[str, pydantic.StringConstraints(pattern=r"^#[0-9a-fA-F]{6}$", to_lower=True)]
+
+
+class OverdraftFacility(pydantic.BaseModel):
+    limit: MoneySum  # positive, the pool's balance in the currency may go down to -limit
+    interest_free_days: int = 0  # since the balance went negative
+    annual_interest_rate: float = 0.0  # charged after the interest-free window
+
+
 class MoneyPool(pydantic.BaseModel):
     display_name: str
     balance: list[MoneySum]
@@ -16,6 +31,19 @@ class MoneyPool(pydantic.BaseModel):
     is_visible: bool = True
     last_updated: Datetime | None = None
     display_color: str | None = None  # css color for frontend
+    icon: PoolIcon | None = None
+    color_hex: ColorHex | None = None  # e.g. "#4a90d9"
+    overdraft: OverdraftFacility | None = None
+    sort_order: int = 0  # position in the user-defined order of pools
+    group: str | None = None  # for clients to render pools under a common header
+    default_category: str | None = None  # tag for the transactions added without any
+
+    # balance at creation, the current one can be rebuilt from it and the pool's transactions;
+    # None for pools created before it was recorded
+    initial_balance: list[MoneySum] | None = None
+
+    # incremented on every attribute update, used for optimistic concurrency control
+    version: int = 0
 
     def update_with_transaction(self, transaction: Transaction) -> tuple[int, MoneySum]:
         matching = [
@@ -28,6 +56,8 @@ class MoneyPool(pydantic.BaseModel):
                 "Transaction is in currency not present in the pool, apply exchange rates first"
             )
         updated_sum_idx, updated_sum = matching[0]
+        if not transaction.status.is_counted:
+            return updated_sum_idx, updated_sum
         updated_sum.amount += transaction.sum.amount
         self.last_updated = datetime.datetime.now(tz=datetime.UTC)
         return updated_sum_idx, updated_sum
@@ -1,9 +1,9 @@
 from decimal import Decimal
-from typing import Self
+from typing import Any, Self
 
 import pydantic
 
-from api.types.currency import Currency
+from api.types.currency import Currency, parse_currency
 
 
 class MoneySum(pydantic.BaseModel):
@@ -16,6 +16,34 @@ class MoneySum(pydantic.BaseModel):
     def round_for_currency(self) -> None:
         self.amount = round(self.amount, ndigits=self.currency.precision)
 
+    @pydantic.model_validator(mode="before")
+    @classmethod
+    def accept_amount_formats(cls, data: Any) -> Any:
+        """
+        Amount may be sent as a decimal string, a float or an integer; alternatively,
+        amount_minor is an integer number of minor units (e.g. cents), scaled by currency exponent
+        """
+        if not isinstance(data, dict):
+            return data
+        data = dict(data)
+        if "amount_minor" in data:
+            if "amount" in data:
+                raise ValueError("either amount or amount_minor expected, not both")
+            amount_minor = data.pop("amount_minor")
+            if isinstance(amount_minor, bool) or not isinstance(amount_minor, int):
+                raise ValueError("amount_minor must be an integer number of minor units")
+            try:
+                currency = parse_currency(data.get("currency"))
+            except TypeError:
+                return data  # reported as invalid currency
+            data["amount"] = Decimal(amount_minor).scaleb(-currency.precision)
+        elif isinstance(data.get("amount"), float):
+            # shortest repr avoids binary float artifacts, e.g. 2.675 -> 2.67499999...
+            data["amount"] = Decimal(repr(data["amount"]))
+        elif isinstance(data.get("amount"), str):
+            data["amount"] = data["amount"].strip()
+        return data
+
     @pydantic.model_validator(mode="after")
     def amount_has_correct_precision(self) -> Self:
         self.round_for_currency()
@@ -0,0 +1,62 @@
+import datetime
+import enum
+import zoneinfo
+
+import pydantic
+
+from api.types.datetime import Datetime, Timezone
+from api.types.ids import ReportSnapshotId
+from api.types.report_snapshot import month_period
+
+
+class ChecklistItem(enum.StrEnum):
+    DRAFTS_REVIEWED = "drafts_reviewed"  # no spends in the month await approval
+    RECONCILIATION_DONE = "reconciliation_done"  # every pool used in the month is reconciled
+    BUDGETS_REVIEWED = "budgets_reviewed"  # ticked by the user
+
+
+# the rest are checked against the data
+MANUAL_ITEMS = {ChecklistItem.BUDGETS_REVIEWED}
+
+
+class MonthCloseStatus(enum.StrEnum):
+    OPEN = "open"
+    CLOSED = "closed"
+
+
+class MonthClose(pydantic.BaseModel):
+    """Month-end checklist; closing the month locks it and freezes its report"""
+
+    year: int
+    month: int = pydantic.Field(ge=1, le=12)
+    ticked: list[ChecklistItem] = pydantic.Field(default_factory=list)  # manual items only
+    status: MonthCloseStatus = MonthCloseStatus.OPEN
+    closed_at: Datetime | None = None
+    report_snapshot_id: ReportSnapshotId | None = None  # frozen on closing
+    timezone: Timezone = "UTC"  # the user's when the month close is started
+
+    def period(self) -> tuple[datetime.datetime, datetime.datetime]:
+        return month_period(self.year, self.month, zoneinfo.ZoneInfo(self.timezone))
+
+    @property
+    def key(self) -> str:
+        return f"{self.year}-{self.month:02}"
+
+    def locks(self, timestamp: Datetime) -> bool:
+        start, end = self.period()
+        return (
+            self.status is MonthCloseStatus.CLOSED
+            and start.timestamp() <= timestamp.timestamp() < end.timestamp()
+        )
+
+
+class ChecklistItemStatus(pydantic.BaseModel):
+    item: ChecklistItem
+    done: bool
+    detail: str | None = None  # what's left to do
+
+
+class MonthCloseView(pydantic.BaseModel):
+    month_close: MonthClose
+    checklist: list[ChecklistItemStatus]
+    can_close: bool
@@ -0,0 +1,18 @@
+import datetime
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.money_sum import MoneySum
+
+
+class NetWorthSnapshot(pydantic.BaseModel):
+    """Total balance of all the user's pools on a day, overwritten until the day is over"""
+
+    date: datetime.date  # in the user's timezone
+    balance: list[MoneySum]  # summed over the pools, one sum per currency
+    total: MoneySum  # in the user's default currency at the time
+    unconverted: list[MoneySum]  # sums in currencies with unavailable rates, not in the total
+    recorded_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
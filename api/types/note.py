@@ -0,0 +1,35 @@
+import datetime
+from typing import Annotated, Self
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import MoneyPoolId, NoteId, TransactionId
+from api.types.sensitive import plain_max_length
+
+MAX_NOTE_LENGTH = 200
+
+
+class PoolNote(pydantic.BaseModel):
+    """Pinned to the pool, e.g. "card expires 09/25" or a pending refund transaction"""
+
+    pool_id: MoneyPoolId
+    text: Annotated[str, plain_max_length(MAX_NOTE_LENGTH)] = ""
+    transaction_id: TransactionId | None = None  # pinned transaction, must be in the pool
+    created_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+
+    @pydantic.model_validator(mode="after")
+    def not_empty(self) -> Self:
+        if not self.text and self.transaction_id is None:
+            raise ValueError("note must have a text or a pinned transaction")
+        return self
+
+
+class StoredPoolNote(PoolNote):
+    id: NoteId
+
+    @classmethod
+    def from_note(cls, n: PoolNote, id: NoteId) -> "StoredPoolNote":
+        return StoredPoolNote(id=id, **n.model_dump())
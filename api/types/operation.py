@@ -0,0 +1,33 @@
+import datetime
+import enum
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import OperationId
+from api.types.transaction import StoredTransaction
+
+
+class OperationKind(enum.StrEnum):
+    CREATE = "create"
+    UPDATE = "update"
+    DELETE = "delete"
+
+
+class Operation(pydantic.BaseModel):
+    """A recent mutation, recorded so that it can be undone"""
+
+    kind: OperationKind
+    timestamp: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+    # created transactions, transactions before the update or deleted transactions
+    transactions: list[StoredTransaction]
+
+
+class StoredOperation(Operation):
+    id: OperationId
+
+    @classmethod
+    def from_operation(cls, o: Operation, id: OperationId) -> "StoredOperation":
+        return StoredOperation(id=id, **o.model_dump())
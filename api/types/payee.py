@@ -0,0 +1,17 @@
+import pydantic
+
+from api.types.ids import PayeeId
+
+
+class Payee(pydantic.BaseModel):
+    """Merchant or person the user pays or gets paid by, referenced by the transactions"""
+
+    name: str = pydantic.Field(min_length=1)
+
+
+class StoredPayee(Payee):
+    id: PayeeId
+
+    @classmethod
+    def from_payee(cls, p: Payee, id: PayeeId) -> "StoredPayee":
+        return StoredPayee(id=id, **p.model_dump())
@@ -0,0 +1,35 @@
+import datetime
+import enum
+
+import pydantic
+
+from api.types.datetime import Datetime
+
+
+class RebuildTarget(enum.StrEnum):
+    BALANCES = "balances"  # pool balances, from initial balances and transactions
+    SNAPSHOTS = "snapshots"  # daily net worth snapshots, from the pool balances as of the day
+    INDEXES = "indexes"  # storage indexes
+
+
+class RebuildStatus(enum.StrEnum):
+    RUNNING = "running"
+    DONE = "done"
+    FAILED = "failed"
+
+
+class RebuildJob(pydantic.BaseModel):
+    id: str
+    what: RebuildTarget
+    status: RebuildStatus = RebuildStatus.RUNNING
+    started_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+    finished_at: Datetime | None = None
+
+    # progress, in units depending on the target (users for balances and snapshots)
+    processed: int = 0
+    total: int | None = None  # None until known
+
+    changed: int = 0  # e.g. pools or snapshots with balance differing from the rebuilt one
+    errors: list[str] = pydantic.Field(default_factory=list)
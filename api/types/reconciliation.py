@@ -0,0 +1,51 @@
+import enum
+
+import pydantic
+
+from api.types.datetime import Datetime
+from api.types.ids import MoneyPoolId, ReconciliationId, TransactionId
+from api.types.money_sum import MoneySum
+
+
+class ReconciliationStatus(enum.Enum):
+    IN_PROGRESS = "in_progress"
+    FINISHED = "finished"
+
+
+class ReconciliationAdjustment(pydantic.BaseModel):
+    """Unexplained difference between the statement and the tracked transactions"""
+
+    sum: MoneySum
+    description: str
+
+
+class Reconciliation(pydantic.BaseModel):
+    pool_id: MoneyPoolId
+    start: Datetime
+    end: Datetime
+
+    # closing balance as stated by the bank, one sum per currency
+    statement_balance: list[MoneySum] = pydantic.Field(default_factory=list)
+
+    matched_transaction_ids: list[TransactionId] = pydantic.Field(default_factory=list)
+    adjustments: list[ReconciliationAdjustment] = pydantic.Field(default_factory=list)
+    status: ReconciliationStatus = ReconciliationStatus.IN_PROGRESS
+    finished_at: Datetime | None = None
+
+    def locks(self, pool_id: MoneyPoolId, timestamp: Datetime) -> bool:
+        return (
+            self.status is ReconciliationStatus.FINISHED
+            and self.pool_id == pool_id
+            # comparing as UNIX timestamps to tolerate naive datetimes
+            and self.start.timestamp() <= timestamp.timestamp() <= self.end.timestamp()
+        )
+
+
+class StoredReconciliation(Reconciliation):
+    id: ReconciliationId
+
+    @classmethod
+    def from_reconciliation(
+        cls, r: Reconciliation, id: ReconciliationId
+    ) -> "StoredReconciliation":
+        return StoredReconciliation(id=id, **r.model_dump())
@@ -0,0 +1,37 @@
+import datetime
+from decimal import Decimal
+
+import pydantic
+
+from api.types.currency import Currency
+from api.types.datetime import Datetime
+from api.types.ids import MoneyPoolId, TransactionId
+from api.types.money_sum import MoneySum
+from api.types.transaction import TransactionSplit
+
+
+class TransactionConversion(pydantic.BaseModel):
+    """New amounts of a transaction converted to the pool's new currency"""
+
+    transaction_id: TransactionId
+    rate: float
+    sum: MoneySum
+    splits: list[TransactionSplit]
+    # the amount before conversion is kept as the original one, unless there already was one
+    original_currency: Currency | None
+    original_amount: Decimal | None
+
+
+class PoolRedenomination(pydantic.BaseModel):
+    """Change of the pool's currency, applied at once and kept in the audit log with its rates"""
+
+    pool_id: MoneyPoolId
+    from_currency: Currency
+    to_currency: Currency
+    rate: float  # for the balance not coming from the converted transactions
+    balance: list[MoneySum]
+    initial_balance: list[MoneySum] | None
+    transactions: list[TransactionConversion] = pydantic.Field(default_factory=list)
+    timestamp: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
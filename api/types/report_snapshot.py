@@ -0,0 +1,85 @@
+import datetime
+import zoneinfo
+
+import pydantic
+
+from api.types.api import ReportApiRouteResponse, ReportValueChange
+from api.types.currency import Currency
+from api.types.datetime import Datetime, Timezone
+from api.types.ids import ReportSnapshotId
+from api.types.money_sum import MoneySum
+
+
+class ReportSnapshot(pydantic.BaseModel):
+    """Monthly report frozen at the time of creation, unaffected by later data edits"""
+
+    year: int
+    month: int = pydantic.Field(ge=1, le=12)
+    target_currency: Currency
+    points: int
+    created_at: Datetime = pydantic.Field(
+        default_factory=lambda: datetime.datetime.now(tz=datetime.UTC)
+    )
+    report: ReportApiRouteResponse
+    timezone: Timezone = "UTC"  # the user's at the time of creation, sets the month boundaries
+
+    def period(self) -> tuple[datetime.datetime, datetime.datetime]:
+        return month_period(self.year, self.month, zoneinfo.ZoneInfo(self.timezone))
+
+
+class StoredReportSnapshot(ReportSnapshot):
+    id: ReportSnapshotId
+
+    @classmethod
+    def from_report_snapshot(
+        cls, rs: ReportSnapshot, id: ReportSnapshotId
+    ) -> "StoredReportSnapshot":
+        return StoredReportSnapshot(id=id, **rs.model_dump())
+
+
+def month_period(
+    year: int, month: int, tz: datetime.tzinfo = datetime.UTC
+) -> tuple[datetime.datetime, datetime.datetime]:
+    start = datetime.datetime(year=year, month=month, day=1, tzinfo=tz)
+    if month == 12:
+        end = start.replace(year=year + 1, month=1)
+    else:
+        end = start.replace(month=month + 1)
+    return start, end
+
+
+def diff_reports(
+    reported: ReportApiRouteResponse, current: ReportApiRouteResponse
+) -> list[ReportValueChange]:
+    def changed(a: MoneySum | None, b: MoneySum | None) -> bool:
+        if a is None or b is None:
+            return a is not b
+        return a.currency != b.currency or a.amount != b.amount
+
+    candidates = [
+        ReportValueChange(metric="spent", reported=reported.spent, current=current.spent),
+        ReportValueChange(metric="made", reported=reported.made, current=current.made),
+        ReportValueChange(
+            metric="overall_total_at_end",
+            reported=reported.snapshots[0].overall_total,
+            current=current.snapshots[0].overall_total,
+        ),
+        ReportValueChange(
+            metric="overall_total_at_start",
+            reported=reported.snapshots[-1].overall_total,
+            current=current.snapshots[-1].overall_total,
+        ),
+    ]
+    reported_tag_totals = {tt.tag: tt.total for tt in reported.tag_totals}
+    current_tag_totals = {tt.tag: tt.total for tt in current.tag_totals}
+    all_tags = reported_tag_totals.keys() | current_tag_totals.keys()
+    for tag in sorted(all_tags, key=lambda t: (t is not None, t or "")):
+        candidates.append(
+            ReportValueChange(
+                metric="tag_total",
+                tag=tag,
+                reported=reported_tag_totals.get(tag),
+                current=current_tag_totals.get(tag),
+            )
+        )
+    return [c for c in candidates if changed(c.reported, c.current)]
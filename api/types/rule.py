@@ -0,0 +1,64 @@
+import enum
+import re
+from typing import Self
+
+import pydantic
+
+from api.types.ids import MoneyPoolId, RuleId
+from api.types.transaction import Transaction
+
+
+class RuleMatch(enum.StrEnum):
+    CONTAINS = "contains"
+    STARTS_WITH = "starts_with"
+    REGEX = "regex"
+
+
+class CategorizationRule(pydantic.BaseModel):
+    """
+    Normalizes the merchant and categorizes matching transactions, e.g. description containing
+    "LIDL" -> "Lidl" with "groceries" category (= tag)
+    """
+
+    pattern: str = pydantic.Field(min_length=1)
+    match: RuleMatch = RuleMatch.CONTAINS
+    case_sensitive: bool = False
+    category: str | None = None  # added to the transaction's tags
+    merchant: str | None = None  # replaces the description
+    pool_id: MoneyPoolId | None = None  # only transactions in the pool if set
+
+    @pydantic.model_validator(mode="after")
+    def valid(self) -> Self:
+        if self.category is None and self.merchant is None:
+            raise ValueError("rule must set a category or a merchant")
+        if self.match is RuleMatch.REGEX:
+            try:
+                re.compile(self.pattern)
+            except re.error as e:
+                raise ValueError(f"invalid regex: {e}")
+        return self
+
+    def matches(self, transaction: Transaction, description: str) -> bool:
+        """Description is the plain one, the transaction's may be encrypted"""
+        if transaction.transfer_id is not None:
+            return False
+        if self.pool_id is not None and transaction.pool_id != self.pool_id:
+            return False
+        pattern = self.pattern if self.case_sensitive else self.pattern.casefold()
+        text = description if self.case_sensitive else description.casefold()
+        match self.match:
+            case RuleMatch.CONTAINS:
+                return pattern in text
+            case RuleMatch.STARTS_WITH:
+                return text.startswith(pattern)
+            case RuleMatch.REGEX:
+                flags = 0 if self.case_sensitive else re.IGNORECASE
+                return re.search(self.pattern, description, flags) is not None
+
+
+class StoredCategorizationRule(CategorizationRule):
+    id: RuleId
+
+    @classmethod
+    def from_rule(cls, r: CategorizationRule, id: RuleId) -> "StoredCategorizationRule":
+        return StoredCategorizationRule(id=id, **r.model_dump())
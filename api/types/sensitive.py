@@ -0,0 +1,22 @@
+"""
+Free text which may be stored encrypted, by description privacy or encryption at rest; length
+limits apply to the plain text, ciphertext is longer
+"""
+
+import pydantic
+
+PRIVACY_PREFIX = "enc:v1:"
+AT_REST_PREFIX = "rest:v1:"
+
+
+def is_encrypted(value: str) -> bool:
+    return value.startswith((PRIVACY_PREFIX, AT_REST_PREFIX))
+
+
+def plain_max_length(max_length: int) -> pydantic.AfterValidator:
+    def validate(value: str) -> str:
+        if not is_encrypted(value) and len(value) > max_length:
+            raise ValueError(f"should have at most {max_length} characters")
+        return value
+
+    return pydantic.AfterValidator(validate)
@@ -0,0 +1,109 @@
+import calendar
+import datetime
+import enum
+import zoneinfo
+from typing import Annotated, Self
+
+import pydantic
+
+from api.types.currency import Currency
+from api.types.datetime import Timezone
+from api.types.ids import MoneyPoolId
+
+# BCP 47 language tag subset, e.g. "en" or "en-GB"
+Locale = Annotated[str, pydantic.StringConstraints(pattern=r"^[a-z]{2,3}(-[A-Z]{2})?$")]
+
+
+class Weekday(enum.StrEnum):
+    MONDAY = "monday"
+    TUESDAY = "tuesday"
+    WEDNESDAY = "wednesday"
+    THURSDAY = "thursday"
+    FRIDAY = "friday"
+    SATURDAY = "saturday"
+    SUNDAY = "sunday"
+
+    @property
+    def number(self) -> int:
+        """As in datetime.date.weekday(), Monday is 0"""
+        return list(Weekday).index(self)
+
+
+class ReportingPeriod(pydantic.BaseModel):
+    """
+    Budgeting period, the calendar month by default; pay cycles either start on the same day each
+    month (on the last day in shorter months) or every few weeks from an anchor date
+    """
+
+    start_day: int = pydantic.Field(default=1, ge=1, le=31)
+    every_weeks: int | None = pydantic.Field(default=None, ge=1, le=8)
+    anchor: datetime.date | None = None  # start of any of the cycles, with every_weeks
+
+    @pydantic.model_validator(mode="after")
+    def monthly_or_weekly(self) -> Self:
+        if (self.every_weeks is None) != (self.anchor is None):
+            raise ValueError("every_weeks and anchor must be set together")
+        if self.every_weeks is not None and self.start_day != 1:
+            raise ValueError("start_day is only used for monthly periods")
+        return self
+
+    def first_day(self, date: datetime.date) -> datetime.date:
+        """Of the period the date is in"""
+        if self.every_weeks is not None and self.anchor is not None:
+            length = 7 * self.every_weeks
+            cycles = (date - self.anchor).days // length
+            return self.anchor + datetime.timedelta(days=cycles * length)
+        start = self.month_start_day(date.year, date.month)
+        if date >= start:
+            return start
+        year, month = (date.year - 1, 12) if date.month == 1 else (date.year, date.month - 1)
+        return self.month_start_day(year, month)
+
+    def next_first_day(self, first_day: datetime.date) -> datetime.date:
+        if self.every_weeks is not None:
+            return first_day + datetime.timedelta(weeks=self.every_weeks)
+        year, month = first_day.year, first_day.month
+        year, month = (year + 1, 1) if month == 12 else (year, month + 1)
+        return self.month_start_day(year, month)
+
+    def month_start_day(self, year: int, month: int) -> datetime.date:
+        return datetime.date(year, month, min(self.start_day, calendar.monthrange(year, month)[1]))
+
+    def containing(
+        self, moment: datetime.datetime, tz: datetime.tzinfo = datetime.UTC
+    ) -> tuple[datetime.datetime, datetime.datetime]:
+        """Starting and ending at midnight in the timezone"""
+        first_day = self.first_day(moment.astimezone(tz).date())
+        return (
+            datetime.datetime.combine(first_day, datetime.time(), tzinfo=tz),
+            datetime.datetime.combine(self.next_first_day(first_day), datetime.time(), tzinfo=tz),
+        )
+
+    def covering(
+        self, start: datetime.datetime, end: datetime.datetime, tz: datetime.tzinfo = datetime.UTC
+    ) -> list[tuple[datetime.datetime, datetime.datetime]]:
+        """Consecutive periods from the one containing the start to the one containing the end"""
+        periods = [self.containing(start, tz)]
+        while periods[-1][1] < end:
+            periods.append(self.containing(periods[-1][1], tz))
+        return periods
+
+
+class UserSettings(pydantic.BaseModel):
+    """
+    Defaults for reports and digests; the locale is only stored for clients, the timezone sets day
+    and month boundaries for the period-based aggregation. The reporting period applies to cash
+    flow, monthly digests and projections; month closes, statements and frozen reports stay
+    calendar months
+    """
+
+    default_currency: Currency = pydantic.Field(default="EUR", validate_default=True)
+    locale: Locale = "en"
+    week_start: Weekday = Weekday.MONDAY
+    default_pool_id: MoneyPoolId | None = None
+    timezone: Timezone = "UTC"
+    reporting_period: ReportingPeriod = pydantic.Field(default_factory=ReportingPeriod)
+
+    @property
+    def tzinfo(self) -> zoneinfo.ZoneInfo:
+        return zoneinfo.ZoneInfo(self.timezone)
@@ -0,0 +1,17 @@
+import enum
+
+import pydantic
+
+from api.types.ids import PayeeId
+
+
+class SuggestionField(enum.StrEnum):
+    DESCRIPTION = "description"
+    PAYEE = "payee"
+    CATEGORY = "category"  # = tag
+
+
+class Suggestion(pydantic.BaseModel):
+    value: str  # description, payee name or category
+    payee_id: PayeeId | None = None  # for payee suggestions
+    count: int  # of the recent transactions with the value
@@ -0,0 +1,17 @@
+import enum
+
+import pydantic
+
+
+class SyncedEntity(enum.StrEnum):
+    POOL = "pool"
+    TRANSACTION = "transaction"
+
+
+class EntityChange(pydantic.BaseModel):
+    """Latest change to one of the user's entities; the earlier ones are overwritten"""
+
+    seq: int  # the user's change counter right after the change
+    entity: SyncedEntity
+    entity_id: str
+    deleted: bool = False
@@ -0,0 +1,13 @@
+import pydantic
+
+from api.types.datetime import Datetime
+
+
+class TelemetryReport(pydantic.BaseModel):
+    """Everything the instance reports, no user ids, amounts, descriptions or pool names"""
+
+    version: str
+    backend: str  # storage implementation
+    since: Datetime
+    # number of requests per route, e.g. "POST /transactions"
+    feature_usage: dict[str, int]
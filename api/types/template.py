@@ -0,0 +1,22 @@
+import pydantic
+
+from api.types.ids import MoneyPoolId, TemplateId
+from api.types.money_sum import MoneySum
+
+
+class TransactionTemplate(pydantic.BaseModel):
+    name: str
+    sum: MoneySum  # default amount, can be overridden when applying the template
+    pool_id: MoneyPoolId
+    description: str = ""
+    tags: list[str] = pydantic.Field(default_factory=list)
+
+
+class StoredTransactionTemplate(TransactionTemplate):
+    id: TemplateId
+
+    @classmethod
+    def from_template(
+        cls, t: TransactionTemplate, id: TemplateId
+    ) -> "StoredTransactionTemplate":
+        return StoredTransactionTemplate(id=id, **t.model_dump())
@@ -1,12 +1,70 @@
 import copy
 import datetime
+import enum
+from decimal import Decimal
+from typing import Annotated, Self
 
 import pydantic
 
 from api.types.currency import Currency
 from api.types.datetime import Datetime
-from api.types.ids import MoneyPoolId, TransactionId
+from api.types.ids import MoneyPoolId, PayeeId, TransactionId, TransferId
 from api.types.money_sum import MoneySum
+from api.types.sensitive import plain_max_length
+
+TRANSFER_TAG = "transfer"
+WITHDRAWAL_TAG = "withdrawal"
+FEE_TAG = "fees"
+ALLOWANCE_TAG = "allowance"
+DEBT_TAG = "debts"
+
+MAX_SOURCE_LENGTH = 4096
+
+
+class TransactionKind(enum.StrEnum):
+    INCOME = "income"
+    EXPENSE = "expense"
+    TRANSFER = "transfer"  # a leg of a pool-to-pool transfer, neither income nor expense
+
+
+class TransactionStatus(enum.StrEnum):
+    PENDING = "pending"  # authorized, not yet settled by the bank
+    CLEARED = "cleared"
+    VOID = "void"  # never settled, counted neither in the balance nor in the reports
+    SCHEDULED = "scheduled"  # future-dated, counted once due
+
+    @property
+    def is_counted(self) -> bool:
+        """In the pool balance and in the reports"""
+        return self not in (TransactionStatus.VOID, TransactionStatus.SCHEDULED)
+
+
+class TransactionSource(pydantic.BaseModel):
+    """Raw record an imported transaction was parsed from, kept verbatim for traceability"""
+
+    origin: str  # importer or connector, e.g. "csv" or the bank's name
+    raw: Annotated[str, plain_max_length(MAX_SOURCE_LENGTH)]  # e.g. statement CSV line
+    external_id: str | None = None  # e.g. OFX FITID, to skip the transaction when reimported
+
+
+class TransactionSplit(pydantic.BaseModel):
+    """Portion of the transaction attributed to a category, signed like the transaction sum"""
+
+    category: str = pydantic.Field(min_length=1)
+    amount: Decimal
+
+
+def splits_error(total: MoneySum, splits: list[TransactionSplit]) -> str | None:
+    if not splits:
+        return None
+    categories = [s.category for s in splits]
+    if len(categories) != len(set(categories)):
+        return "Split categories must be unique"
+    if any(s.amount.is_zero() or (s.amount < 0) != (total.amount < 0) for s in splits):
+        return "Split amounts must be non-zero and have the sign of the transaction sum"
+    if sum(s.amount for s in splits) != total.amount:
+        return f"Split amounts must add up to the transaction sum {total}"
+    return None
 
 
 class Transaction(pydantic.BaseModel):
@@ -23,14 +81,88 @@ class Transaction(pydantic.BaseModel):
     # diffuse = a transaction implying any number of actual transactions too small to be tracked
     is_diffuse: bool = False
 
-    # for transactions made not in pool's currency
+    # for transactions made not in pool's currency; the amount is the one actually paid, while the
+    # sum is what the pool was charged; None amount is for backwards compatibility
     original_currency: Currency | None = None
+    original_amount: Decimal | None = None
 
     tags: list[str] = pydantic.Field(default_factory=list)
 
+    # merchant or person on the other side, for reports and auto-completion
+    payee_id: PayeeId | None = None
+
+    # shared by debit and credit legs of a pool-to-pool transfer, reports don't count them as
+    # spending or income
+    transfer_id: TransferId | None = None
+
+    # for transactions coming from imports and connectors
+    source: TransactionSource | None = None
+
+    # category portions adding up to the sum, replacing tags in category reports when present
+    splits: list[TransactionSplit] = pydantic.Field(default_factory=list)
+
+    # pending transactions count towards the current pool balance but not the available one
+    status: TransactionStatus = TransactionStatus.CLEARED
+
+    # incremented on every update, used for optimistic concurrency control
+    version: int = 0
+
+    @pydantic.computed_field  # type: ignore[prop-decorator]
+    @property
+    def kind(self) -> TransactionKind:
+        """Stored along with the transaction for queries, derived from the sum and transfer id"""
+        if self.transfer_id is not None:
+            return TransactionKind.TRANSFER
+        return TransactionKind.EXPENSE if self.sum.amount < 0 else TransactionKind.INCOME
+
+    @pydantic.model_validator(mode="after")
+    def original_amount_has_currency(self) -> Self:
+        if self.original_amount is None:
+            return self
+        if self.original_currency is None:
+            raise ValueError("original amount requires original currency")
+        if (self.original_amount < 0) != (self.sum.amount < 0):
+            raise ValueError("original amount must have the sign of the sum")
+        return self
+
+    @pydantic.model_validator(mode="after")
+    def splits_add_up(self) -> Self:
+        error = splits_error(self.sum, self.splits)
+        if error is not None:
+            raise ValueError(error)
+        return self
+
+    def original_sum(self) -> MoneySum | None:
+        if self.original_currency is None or self.original_amount is None:
+            return None
+        return MoneySum(amount=self.original_amount, currency=self.original_currency)
+
+    def split_portions(self) -> list["Transaction"]:
+        """The transaction itself if it's not split, otherwise one per split category"""
+        if not self.splits:
+            return [self]
+        portions: list[Transaction] = []
+        for split in self.splits:
+            portion = self.model_copy(deep=True)
+            portion.sum.amount = split.amount
+            if not self.sum.amount.is_zero():
+                fraction = split.amount / self.sum.amount
+                if self.amount_eur is not None:
+                    portion.amount_eur = self.amount_eur * float(fraction)
+                if self.original_amount is not None:
+                    portion.original_amount = self.original_amount * fraction
+            portion.tags = [split.category]
+            portion.splits = []
+            portions.append(portion)
+        return portions
+
     def inverted(self) -> "Transaction":
         res = copy.deepcopy(self)
         res.sum.amount = -res.sum.amount
+        for split in res.splits:
+            split.amount = -split.amount
+        if res.original_amount is not None:
+            res.original_amount = -res.original_amount
         return res
 
 
@@ -49,6 +181,8 @@ class TransactionFilter(pydantic.BaseModel):
     transaction_ids: list[TransactionId] | None = None
     untagged_only: bool = False
     is_diffuse: bool | None = None
+    status: TransactionStatus | None = None
+    payee_ids: list[PayeeId] | None = None
 
     @classmethod
     def empty(cls) -> "TransactionFilter":
@@ -67,4 +201,27 @@ class TransactionFilter(pydantic.BaseModel):
             return False
         if self.is_diffuse is not None and t.is_diffuse != self.is_diffuse:
             return False
+        if self.status is not None and t.status is not self.status:
+            return False
+        if self.payee_ids is not None and t.payee_id not in self.payee_ids:
+            return False
         return True
+
+
+class TransactionOrder(enum.Enum):
+    LATEST = "latest"
+    OLDEST = "oldest"
+    LARGEST = "largest"
+    LARGEST_NEGATIVE = "largest_negative"
+
+    def key(self, tran: StoredTransaction) -> float:
+        """Greater for the transactions going first"""
+        match self:
+            case TransactionOrder.LATEST:
+                return tran.timestamp.timestamp()
+            case TransactionOrder.OLDEST:
+                return -tran.timestamp.timestamp()
+            case TransactionOrder.LARGEST:
+                return float(tran.sum.amount)
+            case TransactionOrder.LARGEST_NEGATIVE:
+                return -float(tran.sum.amount)
@@ -0,0 +1,10 @@
+import pydantic
+
+
+class TwoFactor(pydantic.BaseModel):
+    """TOTP two-factor authentication of a user, persisted so that it survives restarts"""
+
+    secret: str
+    recovery_code_hashes: list[str]
+    confirmed: bool = False  # by entering the first code, so that a failed setup can't lock out
+    last_used_step: int | None = None  # codes can't be reused
@@ -0,0 +1,68 @@
+"""
+Checks of new transactions: errors block creation, warnings are returned alongside the created
+transaction so that clients can ask the user for confirmation
+"""
+
+import datetime
+from typing import Sequence
+
+from api.types.api import IssueSeverity, ValidationIssue
+from api.types.money_pool import MoneyPool
+from api.types.transaction import Transaction
+
+# compared to the median amount of the pool's recent transactions in the same currency
+LARGE_AMOUNT_FACTOR = 10
+LARGE_AMOUNT_MIN_SAMPLES = 5
+
+POSSIBLE_DUPLICATE_WINDOW = datetime.timedelta(days=1)
+
+# how far back to look for the pool's recent transactions
+RECENT_WINDOW = datetime.timedelta(days=90)
+
+
+def validate_transaction(
+    transaction: Transaction, pool: MoneyPool | None, recent: Sequence[Transaction]
+) -> list[ValidationIssue]:
+    """Recent transactions are the pool's ones around the transaction's time, in any order"""
+    if pool is None:
+        return [
+            ValidationIssue(
+                code="unknown_pool",
+                severity=IssueSeverity.ERROR,
+                message="Transaction is attributed to non-existent money pool",
+            )
+        ]
+
+    issues: list[ValidationIssue] = []
+    same_currency = [t for t in recent if t.sum.currency == transaction.sum.currency]
+
+    amounts = sorted(abs(t.sum.amount) for t in same_currency if not t.sum.amount.is_zero())
+    if len(amounts) >= LARGE_AMOUNT_MIN_SAMPLES:
+        median = amounts[len(amounts) // 2]
+        if abs(transaction.sum.amount) > LARGE_AMOUNT_FACTOR * median:
+            issues.append(
+                ValidationIssue(
+                    code="large_amount",
+                    severity=IssueSeverity.WARNING,
+                    message=(
+                        f"{transaction.sum} is more than {LARGE_AMOUNT_FACTOR} times "
+                        + f"the pool's typical {median} {transaction.sum.currency.code}"
+                    ),
+                )
+            )
+
+    for t in same_currency:
+        if (
+            t.sum.amount == transaction.sum.amount
+            and abs(t.timestamp - transaction.timestamp) <= POSSIBLE_DUPLICATE_WINDOW
+        ):
+            issues.append(
+                ValidationIssue(
+                    code="possible_duplicate",
+                    severity=IssueSeverity.WARNING,
+                    message=f"The same amount was recorded at {t.timestamp.isoformat()}",
+                )
+            )
+            break
+
+    return issues
@@ -0,0 +1,75 @@
+"""
+Append-only log of in-memory storage mutations, as JSON lines replayed on startup; also readable
+as a record of what was changed when. Ids generated by the storage are logged and reused on
+replay, timestamps it sets itself (like pools' last update) are not
+"""
+
+import datetime
+import logging
+import os
+from pathlib import Path
+from typing import Any, TextIO
+
+import pydantic
+
+from api.types.datetime import Datetime
+
+logger = logging.getLogger(__name__)
+
+
+class WalRecord(pydantic.BaseModel):
+    seq: int
+    at: Datetime = pydantic.Field(default_factory=lambda: datetime.datetime.now(tz=datetime.UTC))
+    method: str  # storage method name, e.g. "add_transaction"
+    args: dict[str, Any]
+    ids: list[str] = pydantic.Field(default_factory=list)  # generated by the method
+
+
+class WriteAheadLog:
+    """Each record is flushed to disk before the mutation is reported done"""
+
+    def __init__(self, path: Path) -> None:
+        self.path = path
+        self.last_seq = 0
+        self._file: TextIO | None = None
+
+    def read(self) -> list[WalRecord]:
+        if not self.path.exists():
+            return []
+        records: list[WalRecord] = []
+        for line in self.path.read_text().splitlines():
+            try:
+                records.append(WalRecord.model_validate_json(line))
+            except pydantic.ValidationError:
+                # the process died while writing the last record, the mutation wasn't reported
+                logger.warning(f"Dropping incomplete record from {self.path}: {line[:100]!r}")
+                self._rewrite(records)
+                break
+        if records:
+            self.last_seq = max(self.last_seq, records[-1].seq)
+        return records
+
+    def append(self, method: str, args: dict[str, Any], ids: list[str]) -> WalRecord:
+        record = WalRecord(seq=self.last_seq + 1, method=method, args=args, ids=ids)
+        if self._file is None:
+            self._file = self.path.open("a")
+        self._file.write(record.model_dump_json() + "\n")
+        self._file.flush()
+        os.fsync(self._file.fileno())
+        self.last_seq = record.seq
+        return record
+
+    def truncate(self, through_seq: int) -> None:
+        """Drops the records already persisted otherwise, e.g. in a snapshot"""
+        self._rewrite([r for r in self.read() if r.seq > through_seq])
+
+    def close(self) -> None:
+        if self._file is not None:
+            self._file.close()
+            self._file = None
+
+    def _rewrite(self, records: list[WalRecord]) -> None:
+        self.close()
+        tmp_path = self.path.with_name(self.path.name + ".tmp")
+        tmp_path.write_text("".join(r.model_dump_json() + "\n" for r in records))
+        os.replace(tmp_path, self.path)
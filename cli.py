@@ -0,0 +1,298 @@
+"""
+Instance management without the HTTP API, configured with the same environment as main.py
+
+    python cli.py serve [--host HOST] [--port PORT | --uds PATH] [--tls-cert FILE --tls-key FILE]
+    python cli.py export --user USER_ID [--output FILE] [--descriptions]
+    python cli.py create-user --user USER_ID [--currency EUR] [--locale en] [--pool-name cash]
+    python cli.py migrate [--dry-run]
+    python cli.py tui [--url URL [--token TOKEN]] [--user USER_ID] [--descriptions]
+"""
+
+import argparse
+import asyncio
+import dataclasses
+import logging
+import os
+import sys
+from decimal import Decimal
+from pathlib import Path
+from typing import Mapping
+
+import pydantic
+from dotenv import load_dotenv
+
+from api.audit import AuditedStorage
+from api.crypto_currencies import CRYPTO_CURRENCIES
+from api.encryption import EncryptedStorage, UserKeys
+from api.events import EventBus
+from api.exchange_rates import DumbExchangeRates
+from api.logs import setup_logging
+from api.migrations import Migration
+from api.privacy import DescriptionPrivacy
+from api.service import (
+    DEFAULT_DUPLICATE_WINDOW,
+    DEFAULT_UNDO_WINDOW,
+    ExpenseService,
+    ServiceError,
+)
+from api.storage import MongoDbStorage, Storage
+from api.tls import TlsConfig, reload_periodically, tls_config_from_env
+from api.types.currency import Currency, parse_currency, register_currencies
+from api.types.ids import UserId
+from api.types.money_pool import MoneyPool, StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.settings import UserSettings
+
+logger = logging.getLogger(__name__)
+
+SYSTEMD_LISTEN_FDS_START = 3
+
+
+class CommandError(Exception):
+    pass
+
+
+def make_service(storage: Storage) -> ExpenseService:
+    return ExpenseService(
+        storage,
+        # commands don't convert currencies
+        exchange_rates=DumbExchangeRates(),
+        events=EventBus(),
+        privacy=(
+            DescriptionPrivacy(key=os.environ["DESCRIPTION_PRIVACY_KEY"].encode("ascii"))
+            if "DESCRIPTION_PRIVACY_KEY" in os.environ
+            else None
+        ),
+        undo_window=DEFAULT_UNDO_WINDOW,
+        duplicate_window=DEFAULT_DUPLICATE_WINDOW,
+    )
+
+
+async def export_user(service: ExpenseService, user_id: UserId, descriptions: bool) -> str:
+    export = await service.export_user_data(user_id, descriptions_visible=descriptions)
+    return export.model_dump_json(indent=2)
+
+
+async def create_user(
+    service: ExpenseService, user_id: UserId, currency: Currency, locale: str, pool_name: str
+) -> StoredMoneyPool:
+    """
+    Users are implicit, identified by the auth; this sets up a new user's settings and
+    the first pool so that the clients have something to start with
+    """
+    if await service.storage.load_pools(user_id):
+        raise CommandError(f"User {user_id!r} already has pools")
+    try:
+        settings = UserSettings(default_currency=currency, locale=locale)
+    except pydantic.ValidationError as e:
+        raise CommandError(str(e))
+    pool = await service.create_pool(
+        user_id,
+        MoneyPool(
+            display_name=pool_name, balance=[MoneySum(amount=Decimal(0), currency=currency)]
+        ),
+    )
+    settings.default_pool_id = pool.id
+    await service.storage.save_user_settings(user_id, settings)
+    return pool
+
+
+async def migrate(storage: Storage, dry_run: bool) -> list[Migration]:
+    return await storage.migrate(dry_run)
+
+
+@dataclasses.dataclass(frozen=True)
+class Listener:
+    host: str = "127.0.0.1"
+    port: int = 8000
+    uds: Path | None = None  # Unix socket instead of TCP, e.g. behind nginx
+    fd: int | None = None  # already bound socket, from systemd socket activation
+
+    def __str__(self) -> str:
+        if self.fd is not None:
+            return f"inherited socket (fd {self.fd})"
+        if self.uds is not None:
+            return f"unix:{self.uds}"
+        return f"{self.host}:{self.port}"
+
+
+def listener_from_args(args: argparse.Namespace, env: Mapping[str, str]) -> Listener:
+    if env.get("LISTEN_PID") == str(os.getpid()) and env.get("LISTEN_FDS"):
+        return Listener(fd=SYSTEMD_LISTEN_FDS_START)
+    uds = args.uds or env.get("UNIX_SOCKET")
+    if uds:
+        return Listener(uds=Path(uds))
+    return Listener(host=args.host, port=args.port)
+
+
+async def serve(listener: Listener, tls: TlsConfig | None) -> None:
+    import uvicorn
+
+    config = uvicorn.Config(
+        "main:app",
+        host=listener.host,
+        port=listener.port,
+        uds=str(listener.uds) if listener.uds is not None else None,
+        fd=listener.fd,
+        ssl_certfile=tls.cert_file if tls is not None else None,
+        ssl_keyfile=tls.key_file if tls is not None else None,
+    )
+    logger.info(f"Listening on {listener}{' with TLS' if tls is not None else ''}")
+    config.load()  # creates the SSL context to reload renewed certificates into
+    server = uvicorn.Server(config)
+    if tls is None or config.ssl is None:
+        await server.serve()
+        return
+    reloader = asyncio.create_task(reload_periodically(config.ssl, tls))
+    try:
+        await server.serve()
+    finally:
+        reloader.cancel()
+
+
+def parse_args(argv: list[str]) -> argparse.Namespace:
+    parser = argparse.ArgumentParser(prog="tet", description="tiny-expense-tracker admin")
+    commands = parser.add_subparsers(dest="command", required=True)
+
+    serve_parser = commands.add_parser("serve", help="run the API server")
+    serve_parser.add_argument("--host", default="127.0.0.1")
+    serve_parser.add_argument("--port", type=int, default=8000)
+    serve_parser.add_argument(
+        "--uds", type=Path, help="Unix socket to listen on instead of TCP, UNIX_SOCKET by default"
+    )
+    serve_parser.add_argument(
+        "--tls-cert", type=Path, help="PEM certificate chain, TLS_CERT_FILE by default"
+    )
+    serve_parser.add_argument("--tls-key", type=Path, help="PEM key, TLS_KEY_FILE by default")
+
+    export_parser = commands.add_parser("export", help="dump all of the user's data as JSON")
+    export_parser.add_argument("--user", required=True)
+    export_parser.add_argument("--output", type=Path, help="file to write, stdout by default")
+    export_parser.add_argument(
+        "--descriptions", action="store_true", help="decrypt descriptions if privacy mode is on"
+    )
+
+    create_user_parser = commands.add_parser("create-user", help="set up a new user")
+    create_user_parser.add_argument("--user", required=True)
+    create_user_parser.add_argument("--currency", type=parse_currency, default="EUR")
+    create_user_parser.add_argument("--locale", default="en")
+    create_user_parser.add_argument("--pool-name", default="cash")
+
+    migrate_parser = commands.add_parser("migrate", help="apply pending storage migrations")
+    migrate_parser.add_argument(
+        "--dry-run", action="store_true", help="only list the pending migrations"
+    )
+
+    tui_parser = commands.add_parser(
+        "tui", help="browse pools and add expenses in the terminal, on a server or the storage"
+    )
+    tui_parser.add_argument("--url", help="server to connect to, the storage is used if omitted")
+    tui_parser.add_argument(
+        "--token",
+        default=os.environ.get("TET_API_TOKEN"),
+        help="API or server token, TET_API_TOKEN by default",
+    )
+    tui_parser.add_argument("--user", help="required for the storage and with a server token")
+    tui_parser.add_argument(
+        "--descriptions", action="store_true", help="decrypt descriptions if privacy mode is on"
+    )
+    args = parser.parse_args(argv)
+    if args.command == "serve" and (args.tls_cert is None) != (args.tls_key is None):
+        parser.error("--tls-cert and --tls-key must be given together")
+    if args.command == "tui" and args.url is None and args.user is None:
+        parser.error("--user is required without --url")
+    return args
+
+
+def make_storage() -> Storage:
+    storage: Storage = AuditedStorage(MongoDbStorage(url=os.environ["MONGODB_URL"]))
+    if "STORAGE_ENCRYPTION_KEY" in os.environ:
+        storage = EncryptedStorage(
+            storage, UserKeys(master_key=os.environ["STORAGE_ENCRYPTION_KEY"].encode("ascii"))
+        )
+    return storage
+
+
+def run_tui(args: argparse.Namespace) -> None:
+    import curses
+
+    from api.client import TetClient
+    from api.tui import ClientBackend, StorageBackend, Tui, TuiBackend
+
+    # curses is synchronous, backend calls are run on this loop one by one
+    loop = asyncio.new_event_loop()
+    backend: TuiBackend
+    client: TetClient | None = None
+    storage: Storage | None = None
+    if args.url is not None:
+        headers = {"token": args.token} if args.token else {}
+        if args.user is not None:
+            headers["user-id"] = args.user
+        client = TetClient(args.url, headers=headers)
+        backend = ClientBackend(client)
+    else:
+        storage = make_storage()
+        loop.run_until_complete(storage.initialize())
+        backend = StorageBackend(make_service(storage), args.user, args.descriptions)
+    try:
+        curses.wrapper(Tui(backend, loop).run)
+    finally:
+        if client is not None:
+            loop.run_until_complete(client.close())
+        if storage is not None:
+            loop.run_until_complete(storage.close())
+        loop.close()
+
+
+async def run(args: argparse.Namespace) -> None:
+    storage = make_storage()
+    await storage.initialize()
+    service = make_service(storage)
+    match args.command:
+        case "export":
+            output = await export_user(service, args.user, args.descriptions)
+            if args.output is not None:
+                args.output.write_text(output)
+                logger.info(f"Exported to {args.output}")
+            else:
+                print(output)
+        case "create-user":
+            pool = await create_user(
+                service, args.user, args.currency, args.locale, args.pool_name
+            )
+            logger.info(f"Created user {args.user!r} with pool {pool.id}")
+        case "migrate":
+            migrations = await migrate(storage, args.dry_run)
+            if not migrations:
+                logger.info("Storage is up to date")
+            for migration in migrations:
+                print(f"{'pending' if args.dry_run else 'applied'} {migration}")
+
+
+def main(argv: list[str]) -> int:
+    load_dotenv()
+    setup_logging(json_format=os.environ.get("LOG_FORMAT") == "json")
+    if os.environ.get("CRYPTO_CURRENCIES"):
+        register_currencies(CRYPTO_CURRENCIES)
+    args = parse_args(argv)
+    if args.command == "serve":
+        tls = (
+            TlsConfig(cert_file=args.tls_cert, key_file=args.tls_key)
+            if args.tls_cert is not None
+            else tls_config_from_env(os.environ)
+        )
+        asyncio.run(serve(listener_from_args(args, os.environ), tls))
+        return 0
+    if args.command == "tui":
+        run_tui(args)
+        return 0
+    try:
+        asyncio.run(run(args))
+    except (CommandError, ServiceError) as e:
+        logger.error(str(e))
+        return 1
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main(sys.argv[1:]))
@@ -1,18 +1,16 @@
-import logging
 import os
+from pathlib import Path
 
 from dotenv import load_dotenv
 
 from api.app import create_app
 from api.auth import NoAuth
 from api.exchange_rates import DumbExchangeRates
+from api.logs import setup_logging
 from api.storage import MongoDbStorage
 
 load_dotenv()
-logging.basicConfig(
-    level=logging.INFO,
-    format="%(levelname)-10s%(asctime)s %(name)s: %(message)s",
-)
+setup_logging(json_format=os.environ.get("LOG_FORMAT") == "json")
 
 app = create_app(
     storage=MongoDbStorage(url=os.environ["MONGODB_URL"]),
@@ -23,4 +21,5 @@ app = create_app(
     auth=NoAuth(),
     exchange_rates=DumbExchangeRates(),
     frontend_origins=["http://127.0.0.1:5500"],
+    static_dir=Path(os.environ["SERVE_STATIC"]) if "SERVE_STATIC" in os.environ else None,
 )
@@ -1,29 +1,130 @@
-import logging
+import datetime
 import os
 from pathlib import Path
 
 from dotenv import load_dotenv
 
 from api.app import create_app
+from api.audit import AuditedStorage
 from api.auth import TokenAuth
+from api.compression import compression_config_from_env
+from api.crypto_currencies import CRYPTO_CURRENCIES
+from api.encryption import EncryptedStorage, UserKeys
 from api.exchange_rates import RemoteExchangeRates
-from api.storage import MongoDbStorage
+from api.historical_rates import RecordedExchangeRates
+from api.limits import request_limits_from_env
+from api.logs import setup_logging
+from api.notifications import EmailNotifier, parse_email_recipients
+from api.oidc import oidc_config_from_env
+from api.privacy import DescriptionPrivacy
+from api.sandbox import SANDBOX_MOUNT_PATH, create_sandbox_app
+from api.service import DEFAULT_DUPLICATE_WINDOW, DEFAULT_UNDO_WINDOW
+from api.storage import (
+    DEFAULT_SNAPSHOT_INTERVAL_SEC,
+    InmemoryStorage,
+    MongoDbStorage,
+    Storage,
+)
+from api.telemetry import Telemetry
+from api.types.digest import DigestPeriod
 
 load_dotenv()
-logging.basicConfig(
-    level=logging.INFO,
-    format="%(levelname)-10s%(asctime)s %(name)s: %(message)s",
+setup_logging(json_format=os.environ.get("LOG_FORMAT") == "json")
+
+exchange_rates = RemoteExchangeRates(
+    api_url=os.environ["EXCHANGE_RATES_API_URL"],
+    cache_file_path=Path(__file__).parent / ".exchange-rates.json",
+)
+
+storage: Storage = AuditedStorage(
+    # without a database, for small instances
+    InmemoryStorage(
+        snapshot_path=(
+            Path(os.environ["INMEMORY_SNAPSHOT_FILE"])
+            if "INMEMORY_SNAPSHOT_FILE" in os.environ
+            else None
+        ),
+        snapshot_interval_sec=(
+            float(os.environ["INMEMORY_SNAPSHOT_INTERVAL_SEC"])
+            if "INMEMORY_SNAPSHOT_INTERVAL_SEC" in os.environ
+            else DEFAULT_SNAPSHOT_INTERVAL_SEC
+        ),
+        wal_path=(
+            Path(os.environ["INMEMORY_WAL_FILE"]) if "INMEMORY_WAL_FILE" in os.environ else None
+        ),
+    )
+    if "INMEMORY_SNAPSHOT_FILE" in os.environ or "INMEMORY_WAL_FILE" in os.environ
+    else MongoDbStorage(url=os.environ["MONGODB_URL"])
 )
+if "STORAGE_ENCRYPTION_KEY" in os.environ:
+    storage = EncryptedStorage(
+        storage, UserKeys(master_key=os.environ["STORAGE_ENCRYPTION_KEY"].encode("ascii"))
+    )
 
 app = create_app(
-    storage=MongoDbStorage(url=os.environ["MONGODB_URL"]),
+    storage=storage,
     auth=TokenAuth(
         server_tokens=os.environ["STATIC_TOKENS"].split(","),
         auth_telegram_bot_token=os.environ["AUTH_TGBOT_TOKEN"],
+        storage=storage,
+        read_only_server_tokens=(
+            os.environ["READ_ONLY_STATIC_TOKENS"].split(",")
+            if "READ_ONLY_STATIC_TOKENS" in os.environ
+            else None
+        ),
+        oidc=oidc_config_from_env(os.environ),
     ),
-    exchange_rates=RemoteExchangeRates(
-        api_url=os.environ["EXCHANGE_RATES_API_URL"],
-        cache_file_path=Path(__file__).parent / ".exchange-rates.json",
-    ),
+    exchange_rates=RecordedExchangeRates(exchange_rates, storage),
     frontend_origins=os.environ["FRONTEND_ORIGINS"].split(","),
+    cors_allow_methods=(
+        os.environ["CORS_ALLOW_METHODS"].split(",") if "CORS_ALLOW_METHODS" in os.environ else None
+    ),
+    cors_allow_headers=(
+        os.environ["CORS_ALLOW_HEADERS"].split(",") if "CORS_ALLOW_HEADERS" in os.environ else None
+    ),
+    static_dir=Path(os.environ["SERVE_STATIC"]) if "SERVE_STATIC" in os.environ else None,
+    privacy=(
+        DescriptionPrivacy(key=os.environ["DESCRIPTION_PRIVACY_KEY"].encode("ascii"))
+        if "DESCRIPTION_PRIVACY_KEY" in os.environ
+        else None
+    ),
+    notifier=(
+        EmailNotifier(
+            smtp_host=os.environ["SMTP_HOST"],
+            smtp_port=int(os.environ.get("SMTP_PORT", "587")),
+            sender=os.environ["SMTP_SENDER"],
+            recipients=parse_email_recipients(os.environ.get("EMAIL_RECIPIENTS", "")),
+            username=os.environ.get("SMTP_USERNAME"),
+            password=os.environ.get("SMTP_PASSWORD"),
+        )
+        if "SMTP_HOST" in os.environ
+        else None
+    ),
+    digest_period=(
+        DigestPeriod(os.environ["DIGEST_PERIOD"]) if "DIGEST_PERIOD" in os.environ else None
+    ),
+    # opt-in: usage is only reported if the endpoint is set, preview at /telemetry/preview
+    telemetry=Telemetry(endpoint=os.environ.get("TELEMETRY_ENDPOINT")),
+    undo_window=(
+        datetime.timedelta(seconds=float(os.environ["UNDO_WINDOW_SEC"]))
+        if "UNDO_WINDOW_SEC" in os.environ
+        else DEFAULT_UNDO_WINDOW
+    ),
+    duplicate_window=(
+        datetime.timedelta(seconds=float(os.environ["DUPLICATE_WINDOW_SEC"]))
+        if "DUPLICATE_WINDOW_SEC" in os.environ
+        else DEFAULT_DUPLICATE_WINDOW
+    ),
+    admin_user_ids=(
+        os.environ["ADMIN_USER_IDS"].split(",") if "ADMIN_USER_IDS" in os.environ else None
+    ),
+    extra_currencies=CRYPTO_CURRENCIES if os.environ.get("CRYPTO_CURRENCIES") else None,
+    migrate_on_startup=bool(os.environ.get("MIGRATE_ON_STARTUP")),
+    notify_anomalies=bool(os.environ.get("NOTIFY_ANOMALIES")),
+    compression=compression_config_from_env(os.environ),
+    limits=request_limits_from_env(os.environ),
+    grpc_port=int(os.environ["GRPC_PORT"]) if "GRPC_PORT" in os.environ else None,
 )
+
+if os.environ.get("SANDBOX"):
+    app.mount(SANDBOX_MOUNT_PATH, create_sandbox_app(exchange_rates=exchange_rates))
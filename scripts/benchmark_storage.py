@@ -0,0 +1,154 @@
+"""
+Timings of adding and loading transactions with large histories, to catch performance
+regressions in the storage backends
+
+    PYTHONPATH=. python scripts/benchmark_storage.py --sizes 10000,100000,1000000
+    PYTHONPATH=. python scripts/benchmark_storage.py --backend mongodb  # MONGODB_URL from env
+"""
+
+import argparse
+import asyncio
+import datetime
+import os
+import random
+import statistics
+import time
+import uuid
+from decimal import Decimal
+from typing import Awaitable, Callable
+
+from bson import ObjectId
+from dotenv import load_dotenv
+
+from api.storage import InmemoryStorage, MongoDbStorage, Storage, TransactionOrder
+from api.types.ids import MoneyPoolId
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction, Transaction, TransactionFilter
+
+load_dotenv()
+
+USER_ID = "benchmark-user"
+POOLS = 5
+PAGE_SIZE = 50
+HISTORY = datetime.timedelta(days=5 * 365)
+
+
+def random_transaction(pool_ids: list[MoneyPoolId], now: datetime.datetime) -> Transaction:
+    return Transaction(
+        sum=MoneySum(amount=Decimal(random.randint(-20000, 5000)) / 100, currency="EUR"),
+        pool_id=random.choice(pool_ids),
+        description=f"benchmark {random.randint(0, 1000)}",
+        timestamp=now - HISTORY * random.random(),
+        is_diffuse=random.random() < 0.1,
+        tags=[] if random.random() < 0.3 else [random.choice(["food", "rent", "travel"])],
+    )
+
+
+def new_id(storage: Storage) -> str:
+    # MongoDB ids must be ObjectIds
+    return str(ObjectId()) if isinstance(storage, MongoDbStorage) else str(uuid.uuid4())
+
+
+async def populate(storage: Storage, size: int) -> tuple[list[MoneyPoolId], list[str]]:
+    await storage.delete_user_data(USER_ID)
+    pool_ids: list[MoneyPoolId] = []
+    for idx in range(POOLS):
+        pool = await storage.add_pool(
+            USER_ID,
+            MoneyPool(
+                display_name=f"pool {idx}",
+                balance=[MoneySum(amount=Decimal(0), currency="EUR")],
+            ),
+        )
+        pool_ids.append(pool.id)
+    now = datetime.datetime.now(tz=datetime.UTC)
+    transaction_ids: list[str] = []
+    batch_size = 10_000
+    for start in range(0, size, batch_size):
+        # restored in batches, adding one by one takes too long for the large sizes
+        batch = [
+            StoredTransaction.from_transaction(
+                random_transaction(pool_ids, now), id=new_id(storage)
+            )
+            for _ in range(min(batch_size, size - start))
+        ]
+        await storage.restore_transactions(USER_ID, batch)
+        transaction_ids.extend(t.id for t in batch)
+    return pool_ids, transaction_ids
+
+
+async def measure(repeat: int, call: Callable[[], Awaitable[object]]) -> float:
+    """Median duration of a call, in ms"""
+    durations: list[float] = []
+    for _ in range(repeat):
+        start = time.perf_counter()
+        await call()
+        durations.append((time.perf_counter() - start) * 1000)
+    return statistics.median(durations)
+
+
+async def benchmark(storage: Storage, size: int, repeat: int) -> dict[str, float]:
+    pool_ids, transaction_ids = await populate(storage, size)
+    now = datetime.datetime.now(tz=datetime.UTC)
+
+    def load(filter: TransactionFilter | None, order: TransactionOrder) -> Awaitable[object]:
+        return storage.load_transactions(USER_ID, filter, order, offset=0, count=PAGE_SIZE)
+
+    cases: dict[str, Callable[[], Awaitable[object]]] = {
+        "add_transaction": lambda: storage.add_transaction(
+            USER_ID, random_transaction(pool_ids, now)
+        ),
+        "load latest": lambda: load(None, TransactionOrder.LATEST),
+        "load oldest": lambda: load(None, TransactionOrder.OLDEST),
+        "load largest": lambda: load(None, TransactionOrder.LARGEST),
+        "load last 30 days": lambda: load(
+            TransactionFilter(min_timestamp=now - datetime.timedelta(days=30)),
+            TransactionOrder.LATEST,
+        ),
+        "load single pool": lambda: load(
+            TransactionFilter(pool_ids=pool_ids[:1]), TransactionOrder.LATEST
+        ),
+        "load untagged": lambda: load(
+            TransactionFilter(untagged_only=True), TransactionOrder.LATEST
+        ),
+        "load diffuse": lambda: load(TransactionFilter(is_diffuse=True), TransactionOrder.LATEST),
+        "load by ids": lambda: load(
+            TransactionFilter(transaction_ids=random.sample(transaction_ids, PAGE_SIZE)),
+            TransactionOrder.LATEST,
+        ),
+    }
+    return {name: await measure(repeat, call) for name, call in cases.items()}
+
+
+async def main(backend: str, sizes: list[int], repeat: int) -> None:
+    storage: Storage = (
+        MongoDbStorage(url=os.environ["MONGODB_URL"])
+        if backend == "mongodb"
+        else InmemoryStorage()
+    )
+    await storage.initialize()
+    results = {size: await benchmark(storage, size, repeat) for size in sizes}
+    await storage.delete_user_data(USER_ID)
+
+    print(f"median ms per call, {backend}, {repeat} calls each")
+    print(f"{'':<20}" + "".join(f"{size:>12,}" for size in sizes))
+    for name in results[sizes[0]]:
+        print(f"{name:<20}" + "".join(f"{results[size][name]:>12.3f}" for size in sizes))
+
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--backend", choices=["inmemory", "mongodb"], default="inmemory")
+    parser.add_argument(
+        "--sizes",
+        type=lambda value: [int(s) for s in value.split(",")],
+        default=[10_000, 100_000],
+        help="numbers of stored transactions, comma-separated",
+    )
+    parser.add_argument("--repeat", type=int, default=20)
+    parser.add_argument("--seed", type=int, default=0)
+    args = parser.parse_args()
+
+    random.seed(args.seed)
+    asyncio.run(main(args.backend, args.sizes, args.repeat))
@@ -2,7 +2,7 @@ import pytest
 from fastapi.testclient import TestClient
 
 from api.app import create_app
-from api.auth import NoAuth
+from api.auth import DumbSecretHeaderAuth, NoAuth
 from api.exchange_rates import DumbExchangeRates
 from api.storage import InmemoryStorage
 
@@ -15,3 +15,14 @@ def client() -> TestClient:
         exchange_rates=DumbExchangeRates(),
     )
     return TestClient(app)
+
+
+@pytest.fixture
+def secret_client() -> TestClient:
+    """Authorized with a header, requests without it must be rejected"""
+    app = create_app(
+        storage=InmemoryStorage(),
+        auth=DumbSecretHeaderAuth(),
+        exchange_rates=DumbExchangeRates(),
+    )
+    return TestClient(app, headers={"secret": "secret"})
@@ -0,0 +1,85 @@
+"""
+In-memory storage failing or slowing down chosen operations, to test how the callers handle
+storage outages
+"""
+
+import asyncio
+import collections
+import dataclasses
+import functools
+import inspect
+from typing import Any
+
+from api.storage import InmemoryStorage, Storage, StorageError
+
+
+class StorageUnavailable(StorageError):
+    pass
+
+
+@dataclasses.dataclass
+class Fault:
+    error: Exception | None
+    delay_sec: float
+    remaining_calls: int | None  # None until healed
+    passing_calls: int = 0  # before the fault kicks in
+
+
+class FaultyStorage(InmemoryStorage):
+    """
+    Faults apply to the calls made by the storage itself too, e.g. load_transaction loads
+    through load_transactions
+    """
+
+    def __init__(self) -> None:
+        super().__init__()
+        self.faults: dict[str, Fault] = {}
+        self.calls: collections.Counter[str] = collections.Counter()
+
+    def fail(
+        self,
+        method: str,
+        error: Exception | None = None,
+        times: int | None = None,
+        after: int = 0,
+    ) -> None:
+        error = error or StorageUnavailable(f"{method} failed")
+        self._inject(method, Fault(error, delay_sec=0, remaining_calls=times, passing_calls=after))
+
+    def delay(self, method: str, delay_sec: float, times: int | None = None) -> None:
+        self._inject(method, Fault(None, delay_sec, remaining_calls=times))
+
+    def heal(self, method: str | None = None) -> None:
+        if method is None:
+            self.faults.clear()
+        else:
+            self.faults.pop(method, None)
+
+    def _inject(self, method: str, fault: Fault) -> None:
+        if not inspect.iscoroutinefunction(getattr(Storage, method, None)):
+            raise ValueError(f"No storage operation {method!r}")
+        self.faults[method] = fault
+
+    def __getattribute__(self, name: str) -> Any:
+        attr = super().__getattribute__(name)
+        if name.startswith("_") or not inspect.iscoroutinefunction(attr):
+            return attr
+
+        @functools.wraps(attr)
+        async def with_faults(*args: Any, **kwargs: Any) -> Any:
+            self.calls[name] += 1
+            fault = self.faults.get(name)
+            if fault is not None and fault.passing_calls > 0:
+                fault.passing_calls -= 1
+            elif fault is not None:
+                if fault.remaining_calls is not None:
+                    fault.remaining_calls -= 1
+                    if fault.remaining_calls == 0:
+                        del self.faults[name]
+                if fault.delay_sec:
+                    await asyncio.sleep(fault.delay_sec)
+                if fault.error is not None:
+                    raise fault.error
+            return await attr(*args, **kwargs)
+
+        return with_faults
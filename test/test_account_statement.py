@@ -0,0 +1,103 @@
+import datetime
+import zoneinfo
+from decimal import Decimal
+
+from fastapi.testclient import TestClient
+
+from api.account_statement import build_statement, render_statement_html
+from api.formatting import NBSP
+from api.iso4217 import CURRENCIES
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction
+
+BERLIN = zoneinfo.ZoneInfo("Europe/Berlin")
+
+
+def test_build_statement() -> None:
+    eur = CURRENCIES["EUR"]
+    usd = CURRENCIES["USD"]
+
+    def transaction(
+        amount: int, day: datetime.datetime, currency=eur, **kwargs
+    ) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{day:%m%d%H}",
+            sum=MoneySum(amount=Decimal(amount), currency=currency),
+            pool_id="pool",
+            description=f"spent {amount}",
+            timestamp=day,
+            **kwargs,
+        )
+
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="Card <main>",
+        balance=[
+            MoneySum(amount=Decimal(1000), currency=eur),
+            MoneySum(amount=Decimal(50), currency=usd),
+        ],
+    )
+    transactions = [
+        transaction(-100, datetime.datetime(2024, 10, 2, tzinfo=BERLIN)),  # after the month
+        transaction(-30, datetime.datetime(2024, 9, 30, 23, 30, tzinfo=BERLIN)),
+        transaction(-10, datetime.datetime(2024, 9, 15, tzinfo=BERLIN), currency=usd),
+        transaction(-5, datetime.datetime(2024, 9, 10, tzinfo=BERLIN), status="void"),
+        transaction(200, datetime.datetime(2024, 9, 1, 0, 30, tzinfo=BERLIN)),
+    ]
+
+    statement = build_statement(pool, transactions, 2024, 9, BERLIN)
+    assert [(s.amount, s.currency.code) for s in statement.closing_balance] == [
+        (Decimal(1100), "EUR"),
+        (Decimal(50), "USD"),
+    ]
+    assert [(s.amount, s.currency.code) for s in statement.opening_balance] == [
+        (Decimal(930), "EUR"),
+        (Decimal(60), "USD"),
+    ]
+    assert [(line.sum.amount, line.balance.amount) for line in statement.lines] == [
+        (Decimal(200), Decimal(1130)),
+        (Decimal(-10), Decimal(50)),
+        (Decimal(-30), Decimal(1100)),
+    ]
+
+    page = render_statement_html(statement, locale="de")
+    assert "<h1>Card &lt;main&gt;</h1>" in page
+    assert "Opening balance, 2024-09-01" in page
+    assert "Closing balance, 2024-09-30" in page
+    assert f"1.130,00{NBSP}€" in page
+    assert f"-10,00{NBSP}$" in page
+    assert "3 transactions" in page
+
+
+def test_statement_api(client: TestClient) -> None:
+    response = client.post(
+        "/pools", json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]}
+    )
+    pool_id = response.json()["id"]
+    response = client.post(
+        "/transactions",
+        json={
+            "timestamp": "2024-09-10T12:00:00+00:00",
+            "sum": {"amount": -30, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "groceries",
+        },
+        params={"force": True},
+    )
+    assert response.status_code == 200
+
+    response = client.get(f"/pools/{pool_id}/statement", params={"month": "2024-09"})
+    assert response.status_code == 200
+    assert response.headers["Content-Type"].startswith("text/html")
+    disposition = response.headers["Content-Disposition"]
+    assert disposition == f'inline; filename="statement-{pool_id}-2024-09.html"'
+    assert "groceries" in response.text
+    assert "€100.00" in response.text and "€70.00" in response.text
+
+    def status(pool_id: str, month: str) -> int:
+        return client.get(f"/pools/{pool_id}/statement", params={"month": month}).status_code
+
+    assert status(pool_id, "2024-13") == 422
+    assert status(pool_id, "2999-01") == 400
+    assert status("missing", "2024-09") == 404
@@ -0,0 +1,118 @@
+import datetime
+from decimal import Decimal
+
+from api.analytics import (
+    find_anomalies,
+    find_recurring,
+    project_month_end_balance,
+    scheduled_allowance_payments,
+)
+from api.iso4217 import CURRENCIES
+from api.types.allowance import Allowance
+from api.types.api import AnomalyReason
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction
+
+EUR = CURRENCIES["EUR"]
+
+
+def test_project_month_end_balance() -> None:
+    now = datetime.datetime(year=2024, month=9, day=20, tzinfo=datetime.UTC)
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="debit",
+        balance=[MoneySum(amount=Decimal(1000), currency=EUR)],
+    )
+
+    def transaction(amount: int, days_ago: int, **kwargs) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{days_ago}",
+            sum=MoneySum(amount=Decimal(amount), currency=EUR),
+            pool_id="pool",
+            description="",
+            timestamp=now - datetime.timedelta(days=days_ago),
+            **kwargs,
+        )
+
+    transactions = [
+        transaction(-100, 2),
+        transaction(-200, 9),
+        transaction(2000, 5),  # income isn't extrapolated
+        transaction(-500, 3, transfer_id="t"),
+        transaction(-1000, 11),  # outside the lookback window
+    ]
+    allowance = Allowance(
+        pool_id="kid",
+        source_pool_id="pool",
+        child_name="kid",
+        weekly_amount=MoneySum(amount=Decimal(10), currency=EUR),
+        next_payment_at=now + datetime.timedelta(days=1),
+    )
+    scheduled = scheduled_allowance_payments(allowance, until=now + datetime.timedelta(days=10))
+    assert len(scheduled) == 4
+
+    projection = project_month_end_balance(
+        pool, transactions, scheduled, now=now, lookback=datetime.timedelta(days=10)
+    )
+    assert projection.projected_until == datetime.datetime(2024, 10, 1, tzinfo=datetime.UTC)
+    assert projection.lookback_days == 10
+    [eur] = projection.projections
+    assert eur.average_daily_spend.amount == 30
+    assert eur.scheduled.amount == -20
+    assert eur.projected.amount == 1000 - 20 - 30 * 11
+
+
+def test_find_anomalies() -> None:
+    now = datetime.datetime(year=2024, month=9, day=20, tzinfo=datetime.UTC)
+
+    def transaction(amount: int, tags: list[str], **kwargs) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{tags}",
+            sum=MoneySum(amount=Decimal(amount), currency=EUR),
+            pool_id="pool",
+            description="",
+            timestamp=now,
+            tags=tags,
+            **kwargs,
+        )
+
+    history = [transaction(-amount, ["food"]) for amount in range(10, 30, 2)]
+    usual = transaction(-25, ["food"])
+    large = transaction(-200, ["food"])
+    new_category = transaction(-15, ["travel"])
+    large_transfer = transaction(-200, [], transfer_id="t")
+    anomalies = find_anomalies(history, [usual, large, new_category, large_transfer])
+    assert [(a.transaction.id, a.reasons) for a in anomalies] == [
+        (large.id, [AnomalyReason.LARGE_AMOUNT]),
+        (new_category.id, [AnomalyReason.NEW_CATEGORY]),
+    ]
+    assert find_anomalies(history[:5], [large, new_category]) == []
+
+
+def test_find_recurring() -> None:
+    now = datetime.datetime(year=2024, month=9, day=20, tzinfo=datetime.UTC)
+
+    def transaction(amount: str, days_ago: int, description: str) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{description}-{days_ago}",
+            sum=MoneySum(amount=Decimal(amount), currency=EUR),
+            pool_id="pool",
+            description=description,
+            timestamp=now - datetime.timedelta(days=days_ago),
+        )
+
+    transactions = [
+        *(transaction("-12.99", days, f"NETFLIX.COM {days}") for days in (5, 35, 66, 96)),
+        *(transaction("-3", days, "coffee") for days in (1, 2, 3, 4)),  # too frequent
+        *(transaction("-50", days, "gym") for days in (100, 130, 160)),  # stopped
+        *(transaction(amount, days, "groceries") for amount, days in [("-20", 7), ("-90", 14)]),
+        *(transaction("-30", days, "irregular") for days in (10, 17, 40)),
+    ]
+    [netflix] = find_recurring(transactions, lambda t: t.description, now)
+    assert netflix.description == "NETFLIX.COM 5"
+    assert netflix.sum.amount == Decimal("-12.99")
+    assert netflix.interval_days == 30
+    assert netflix.occurrences == 4
+    assert netflix.next_expected_at == now + datetime.timedelta(days=25)
+    assert netflix.transaction_ids[0] == "NETFLIX.COM 5-5"
@@ -1,8 +1,38 @@
+import asyncio
 import datetime
-from test.utils import MASKED_ID, RECENT_TIMESTAMP, mask_ids, mask_recent_timestamps
+import zoneinfo
+from decimal import Decimal
+from pathlib import Path
+from test.faulty_storage import FaultyStorage
+from test.utils import (
+    MASKED_ID,
+    RECENT_TIMESTAMP,
+    NoGbpExchangeRates,
+    mask_ids,
+    mask_recent_timestamps,
+)
 
+import pytest
+from fastapi import WebSocketDisconnect
+from fastapi.routing import APIRoute
 from fastapi.testclient import TestClient
 
+from api.app import create_app
+from api.auth import NoAuth
+from api.crypto_currencies import CRYPTO_CURRENCIES
+from api.events import EventBus
+from api.exchange_rates import DumbExchangeRates
+from api.storage import InmemoryStorage
+from api.types.events import (
+    BaseEvent,
+    PoolBalanceChanged,
+    PoolCreated,
+    PoolUpdated,
+    TransactionUpdated,
+)
+from api.types.money_sum import MoneySum
+from api.types.net_worth import NetWorthSnapshot
+
 
 def test_api(client: TestClient) -> None:
     response = client.get("/pools")
@@ -38,6 +68,17 @@ def test_basic_flow(client: TestClient) -> None:
         "display_color": "red",
         "id": pool_id,
         "is_visible": True,
+        "version": 0,
+        "overdraft": None,
+        "icon": None,
+        "color_hex": None,
+        "sort_order": 0,
+        "group": None,
+        "default_category": None,
+        "initial_balance": [
+            {"amount": "0.00", "currency": "USD"},
+            {"amount": "10.00", "currency": "EUR"},
+        ],
         "last_updated": None,
     }
 
@@ -66,6 +107,17 @@ def test_basic_flow(client: TestClient) -> None:
             "display_color": "red",
             "id": pool_id,
             "is_visible": True,
+            "version": 0,
+            "overdraft": None,
+            "icon": None,
+            "color_hex": None,
+            "sort_order": 0,
+            "group": None,
+            "default_category": None,
+            "initial_balance": [
+                {"amount": "0.00", "currency": "USD"},
+                {"amount": "10.00", "currency": "EUR"},
+            ],
             "last_updated": RECENT_TIMESTAMP,
         }
     ]
@@ -105,6 +157,14 @@ def test_currency_coercion(client: TestClient) -> None:
             "balance": [{"amount": "200.00", "currency": "USD"}],
             "display_color": None,
             "is_visible": True,
+            "version": 0,
+            "overdraft": None,
+            "icon": None,
+            "color_hex": None,
+            "sort_order": 0,
+            "group": None,
+            "default_category": None,
+            "initial_balance": [{"amount": "300.00", "currency": "USD"}],
             "last_updated": RECENT_TIMESTAMP,
         }
     ]
@@ -120,8 +180,16 @@ def test_currency_coercion(client: TestClient) -> None:
             },
             "description": "payment in Armenian Drams",
             "is_diffuse": False,
+            "transfer_id": None,
+            "source": None,
+            "payee_id": None,
+            "splits": [],
+            "status": "cleared",
+            "version": 0,
+            "kind": "expense",
             "pool_id": pool_id,
             "original_currency": "AMD",
+            "original_amount": "-100.00",
             "id": MASKED_ID,
             "tags": [],
         },
@@ -164,6 +232,18 @@ def test_sync_balance(client: TestClient) -> None:
             ],
             "display_color": None,
             "is_visible": True,
+            "version": 0,
+            "overdraft": None,
+            "icon": None,
+            "color_hex": None,
+            "sort_order": 0,
+            "group": None,
+            "default_category": None,
+            "initial_balance": [
+                {"amount": "300.00", "currency": "USD"},
+                {"amount": "500.00", "currency": "GEL"},
+                {"amount": "50.00", "currency": "EUR"},
+            ],
             "last_updated": RECENT_TIMESTAMP,
         }
     ]
@@ -174,7 +254,15 @@ def test_sync_balance(client: TestClient) -> None:
         {
             "description": "my money synced 300.00 -> 290.00 USD",
             "is_diffuse": True,
+            "transfer_id": None,
+            "source": None,
+            "payee_id": None,
+            "splits": [],
+            "status": "cleared",
+            "version": 0,
+            "kind": "expense",
             "original_currency": None,
+            "original_amount": None,
             "pool_id": pool_id,
             "sum": {
                 "amount": "-10.00",
@@ -187,7 +275,15 @@ def test_sync_balance(client: TestClient) -> None:
         {
             "description": "my money synced 500.00 -> 490.50 GEL",
             "is_diffuse": True,
+            "transfer_id": None,
+            "source": None,
+            "payee_id": None,
+            "splits": [],
+            "status": "cleared",
+            "version": 0,
+            "kind": "expense",
             "original_currency": None,
+            "original_amount": None,
             "pool_id": pool_id,
             "sum": {
                 "amount": "-9.50",
@@ -200,7 +296,15 @@ def test_sync_balance(client: TestClient) -> None:
         {
             "description": "my money synced 50.00 -> 0.00 EUR",
             "is_diffuse": True,
+            "transfer_id": None,
+            "source": None,
+            "payee_id": None,
+            "splits": [],
+            "status": "cleared",
+            "version": 0,
+            "kind": "expense",
             "original_currency": None,
+            "original_amount": None,
             "pool_id": pool_id,
             "sum": {
                 "amount": "-50.00",
@@ -249,6 +353,14 @@ def test_transfer_between_pools(client: TestClient) -> None:
             "balance": [{"amount": "200.00", "currency": "USD"}],
             "display_color": None,
             "is_visible": True,
+            "version": 0,
+            "overdraft": None,
+            "icon": None,
+            "color_hex": None,
+            "sort_order": 0,
+            "group": None,
+            "default_category": None,
+            "initial_balance": [{"amount": "300.00", "currency": "USD"}],
             "last_updated": RECENT_TIMESTAMP,
         },
         {
@@ -257,35 +369,72 @@ def test_transfer_between_pools(client: TestClient) -> None:
             "balance": [{"amount": "100.00", "currency": "USD"}],
             "display_color": None,
             "is_visible": True,
+            "version": 0,
+            "overdraft": None,
+            "icon": None,
+            "color_hex": None,
+            "sort_order": 1,
+            "group": None,
+            "default_category": None,
+            "initial_balance": [{"amount": "0.00", "currency": "USD"}],
             "last_updated": RECENT_TIMESTAMP,
         },
     ]
 
     response = client.get("/transactions")
     assert response.status_code == 200
-    assert mask_ids(mask_recent_timestamps(response.json())) == [
+    transactions = response.json()
+    transfer_ids = {t.pop("transfer_id") for t in transactions}
+    assert len(transfer_ids) == 1 and None not in transfer_ids
+    assert mask_ids(mask_recent_timestamps(transactions)) == [
         {
             "sum": {"amount": "-100.00", "currency": "USD"},
             "pool_id": pool1_id,
-            "description": "Transfer 100.00 USD to cash (got some cash)",
+            "description": "Transfer 100.00 USD to cash got some cash",
             "timestamp": RECENT_TIMESTAMP,
             "is_diffuse": False,
+            "source": None,
+            "payee_id": None,
+            "splits": [],
+            "status": "cleared",
+            "version": 0,
+            "kind": "transfer",
             "original_currency": None,
+            "original_amount": None,
             "id": MASKED_ID,
-            "tags": [],
+            "tags": ["transfer"],
         },
         {
             "sum": {"amount": "100.00", "currency": "USD"},
             "pool_id": pool2_id,
-            "description": "Transfer 100.00 USD from debit card (got some cash)",
+            "description": "Transfer 100.00 USD from debit card got some cash",
             "timestamp": RECENT_TIMESTAMP,
             "is_diffuse": False,
+            "source": None,
+            "payee_id": None,
+            "splits": [],
+            "status": "cleared",
+            "version": 0,
+            "kind": "transfer",
             "original_currency": None,
+            "original_amount": None,
             "id": MASKED_ID,
-            "tags": [],
+            "tags": ["transfer"],
         },
     ]
 
+    response = client.post(
+        "/transfer",
+        json={
+            "from_pool": pool1_id,
+            "to_pool": pool1_id,
+            "sum": {"amount": 100, "currency": "USD"},
+            "description": "",
+        },
+    )
+    assert response.status_code == 400
+    assert len(client.get("/transactions").json()) == 2
+
 
 def test_report(client: TestClient) -> None:
     response = client.post(
@@ -312,12 +461,22 @@ def test_report(client: TestClient) -> None:
     response = client.get(
         "/report",
         params={
-            "start": start.isoformat(),
-            "end": end.isoformat(),
+            "from": start.isoformat(),
+            "to": end.isoformat(),
             "points": 3,
         },
     )
     assert response.status_code == 200
+    # the older parameter names are still accepted
+    legacy = client.get(
+        "/report",
+        params={"start": start.isoformat(), "end": end.isoformat(), "points": 3},
+    )
+    assert legacy.json() == response.json()
+    assert client.get("/report", params={"to": end.isoformat()}).status_code == 422
+    fx = client.get("/report/fx", params={"from": start.isoformat(), "to": end.isoformat()})
+    assert fx.status_code == 200
+    assert [g["pool_id"] for g in fx.json()["per_pool"]] == [pool_id]
     assert mask_recent_timestamps(response.json()) == {
         "snapshots": [
             {
@@ -328,12 +487,21 @@ def test_report(client: TestClient) -> None:
                             "display_name": "debit",
                             "balance": [{"amount": "240.00", "currency": "USD"}],
                             "is_visible": True,
+                            "version": 0,
+                            "overdraft": None,
+                            "icon": None,
+                            "color_hex": None,
+                            "sort_order": 0,
+                            "group": None,
+                            "default_category": None,
+                            "initial_balance": [{"amount": "300.00", "currency": "USD"}],
                             "last_updated": RECENT_TIMESTAMP,
                             "display_color": None,
                             "id": pool_id,
                         },
                         "total": {"amount": "240.00", "currency": "EUR"},
                         "fractions": {"USD": 1.0},
+                        "unconverted": [],
                     }
                 ],
                 "overall_total": {"amount": "240.00", "currency": "EUR"},
@@ -346,12 +514,21 @@ def test_report(client: TestClient) -> None:
                             "display_name": "debit",
                             "balance": [{"amount": "140.00", "currency": "USD"}],
                             "is_visible": True,
+                            "version": 0,
+                            "overdraft": None,
+                            "icon": None,
+                            "color_hex": None,
+                            "sort_order": 0,
+                            "group": None,
+                            "default_category": None,
+                            "initial_balance": [{"amount": "300.00", "currency": "USD"}],
                             "last_updated": RECENT_TIMESTAMP,
                             "display_color": None,
                             "id": pool_id,
                         },
                         "total": {"amount": "140.00", "currency": "EUR"},
                         "fractions": {"USD": 1.0},
+                        "unconverted": [],
                     }
                 ],
                 "overall_total": {"amount": "140.00", "currency": "EUR"},
@@ -364,12 +541,21 @@ def test_report(client: TestClient) -> None:
                             "display_name": "debit",
                             "balance": [{"amount": "300.00", "currency": "USD"}],
                             "is_visible": True,
+                            "version": 0,
+                            "overdraft": None,
+                            "icon": None,
+                            "color_hex": None,
+                            "sort_order": 0,
+                            "group": None,
+                            "default_category": None,
+                            "initial_balance": [{"amount": "300.00", "currency": "USD"}],
                             "last_updated": RECENT_TIMESTAMP,
                             "display_color": None,
                             "id": pool_id,
                         },
                         "total": {"amount": "300.00", "currency": "EUR"},
                         "fractions": {"USD": 1.0},
+                        "unconverted": [],
                     }
                 ],
                 "overall_total": {"amount": "300.00", "currency": "EUR"},
@@ -377,6 +563,10 @@ def test_report(client: TestClient) -> None:
         ],
         "spent": {"amount": "210.00", "currency": "EUR"},
         "made": {"amount": "150.00", "currency": "EUR"},
+        "spent_formatted": "€210.00",
+        "made_formatted": "€150.00",
+        "spent_unconverted": [],
+        "made_unconverted": [],
         "tag_totals": [
             {
                 "total": {
@@ -384,6 +574,7 @@ def test_report(client: TestClient) -> None:
                     "currency": "EUR",
                 },
                 "tag": None,
+                "unconverted": [],
             },
         ],
     }
@@ -435,12 +626,21 @@ def test_net_per_tag(client: TestClient) -> None:
                             "display_name": "example",
                             "balance": [{"amount": "155.00", "currency": "USD"}],
                             "is_visible": True,
+                            "version": 0,
+                            "overdraft": None,
+                            "icon": None,
+                            "color_hex": None,
+                            "sort_order": 0,
+                            "group": None,
+                            "default_category": None,
+                            "initial_balance": [{"amount": "300.00", "currency": "USD"}],
                             "last_updated": RECENT_TIMESTAMP,
                             "display_color": None,
                             "id": pool_id,
                         },
                         "total": {"amount": "155.00", "currency": "EUR"},
                         "fractions": {"USD": 1.0},
+                        "unconverted": [],
                     }
                 ],
                 "overall_total": {"amount": "155.00", "currency": "EUR"},
@@ -453,12 +653,21 @@ def test_net_per_tag(client: TestClient) -> None:
                             "display_name": "example",
                             "balance": [{"amount": "300.00", "currency": "USD"}],
                             "is_visible": True,
+                            "version": 0,
+                            "overdraft": None,
+                            "icon": None,
+                            "color_hex": None,
+                            "sort_order": 0,
+                            "group": None,
+                            "default_category": None,
+                            "initial_balance": [{"amount": "300.00", "currency": "USD"}],
                             "last_updated": RECENT_TIMESTAMP,
                             "display_color": None,
                             "id": pool_id,
                         },
                         "total": {"amount": "300.00", "currency": "EUR"},
                         "fractions": {"USD": 1.0},
+                        "unconverted": [],
                     }
                 ],
                 "overall_total": {"amount": "300.00", "currency": "EUR"},
@@ -466,6 +675,10 @@ def test_net_per_tag(client: TestClient) -> None:
         ],
         "spent": {"amount": "145.00", "currency": "EUR"},
         "made": {"amount": "0.00", "currency": "EUR"},
+        "spent_formatted": "€145.00",
+        "made_formatted": "€0.00",
+        "spent_unconverted": [],
+        "made_unconverted": [],
         "tag_totals": [
             {
                 "tag": None,
@@ -473,6 +686,7 @@ def test_net_per_tag(client: TestClient) -> None:
                     "amount": "-100.00",
                     "currency": "EUR",
                 },
+                "unconverted": [],
             },
             {
                 "tag": "test",
@@ -480,6 +694,7 @@ def test_net_per_tag(client: TestClient) -> None:
                     "amount": "-30.00",
                     "currency": "EUR",
                 },
+                "unconverted": [],
             },
             {
                 "tag": "another",
@@ -487,6 +702,7 @@ def test_net_per_tag(client: TestClient) -> None:
                     "amount": "-15.00",
                     "currency": "EUR",
                 },
+                "unconverted": [],
             },
         ],
     }
@@ -514,6 +730,7 @@ def test_update_transaction(client: TestClient) -> None:
                 "pool_id": pool_id,
                 "description": "one",
             },
+            params={"force": True},
         )
         assert response.status_code == 200
 
@@ -530,6 +747,14 @@ def test_update_transaction(client: TestClient) -> None:
             "display_color": None,
             "id": pool_id,
             "is_visible": True,
+            "version": 0,
+            "overdraft": None,
+            "icon": None,
+            "color_hex": None,
+            "sort_order": 0,
+            "group": None,
+            "default_category": None,
+            "initial_balance": [{"amount": "100.00", "currency": "EUR"}],
             "last_updated": RECENT_TIMESTAMP,
         }
     ]
@@ -542,6 +767,7 @@ def test_update_transaction(client: TestClient) -> None:
     response = client.put(
         f"/transactions/{updated_tran_id}",
         json={"description": "updated", "tags": ["updated", "tags"]},
+        headers={"If-Match": "0"},
     )
     assert response.status_code == 200
 
@@ -552,7 +778,15 @@ def test_update_transaction(client: TestClient) -> None:
         "description": "updated",
         "id": updated_tran_id,
         "is_diffuse": False,
+        "transfer_id": None,
+        "source": None,
+        "payee_id": None,
+        "splits": [],
+        "status": "cleared",
+        "version": 1,
+        "kind": "expense",
         "original_currency": None,
+        "original_amount": None,
         "pool_id": pool_id,
         "sum": {
             "amount": "-10.00",
@@ -564,3 +798,2022 @@ def test_update_transaction(client: TestClient) -> None:
         ],
         "timestamp": "<recent timestamp>",
     }
+
+
+def test_update_with_stale_version(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "p1", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+    assert response.json()["version"] == 0
+
+    response = client.put(
+        f"/pools/{pool_id}", json={"display_name": "p2"}, headers={"if-match": "0"}
+    )
+    assert response.status_code == 200
+
+    response = client.put(
+        f"/pools/{pool_id}", json={"display_name": "p3"}, headers={"if-match": "0"}
+    )
+    assert response.status_code == 409
+    assert response.json()["current_version"] == 1
+
+    response = client.get(f"/pools/{pool_id}")
+    assert response.status_code == 200
+    assert response.json()["display_name"] == "p2"
+
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -10, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "one",
+            "version": 7,
+        },
+    )
+    assert response.status_code == 200
+    transaction_id = response.json()["id"]
+    assert response.json()["version"] == 0
+
+    response = client.put(f"/transactions/{transaction_id}", json={"description": "two"})
+    assert response.status_code == 428
+    response = client.put(f"/transactions/{transaction_id}/status", json={"status": "void"})
+    assert response.status_code == 428
+
+    response = client.put(
+        f"/transactions/{transaction_id}",
+        json={"description": "two"},
+        headers={"if-match": '"0"'},
+    )
+    assert response.status_code == 200
+
+    response = client.put(
+        f"/transactions/{transaction_id}",
+        json={"description": "three"},
+        headers={"if-match": '"0"'},
+    )
+    assert response.status_code == 409
+
+    response = client.put(
+        f"/transactions/{transaction_id}",
+        json={"description": "three"},
+        headers={"if-match": "abc"},
+    )
+    assert response.status_code == 400
+
+
+def test_reconciliation(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "bank", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    end = start + datetime.timedelta(days=10)
+    transaction_ids: list[str] = []
+    for amount, days in ((-10, 1), (-20, 2), (-5, 15)):
+        response = client.post(
+            "/transactions",
+            json={
+                "timestamp": (start + datetime.timedelta(days=days)).timestamp(),
+                "sum": {"amount": amount, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": "whatever",
+            },
+        )
+        assert response.status_code == 200
+        transaction_ids.append(response.json()["id"])
+
+    response = client.post(
+        f"/pools/{pool_id}/reconciliations",
+        json={
+            "start": start.isoformat(),
+            "end": end.isoformat(),
+            "statement_balance": [{"amount": 68, "currency": "EUR"}],
+        },
+    )
+    assert response.status_code == 200
+    reconciliation_id = response.json()["id"]
+
+    response = client.put(
+        f"/reconciliations/{reconciliation_id}/matched",
+        json={"transaction_ids": [transaction_ids[2]]},
+    )
+    assert response.status_code == 400
+
+    response = client.put(
+        f"/reconciliations/{reconciliation_id}/matched",
+        json={"transaction_ids": [transaction_ids[0]]},
+    )
+    assert response.status_code == 200
+
+    response = client.get(f"/reconciliations/{reconciliation_id}")
+    assert response.status_code == 200
+    worksheet = response.json()
+    assert [(i["transaction"]["id"], i["matched"]) for i in worksheet["items"]] == [
+        (transaction_ids[0], True),
+        (transaction_ids[1], False),
+    ]
+    assert worksheet["computed_balance"] == [{"amount": "70.00", "currency": "EUR"}]
+    assert worksheet["discrepancies"] == [{"amount": "-2.00", "currency": "EUR"}]
+
+    response = client.post(
+        f"/reconciliations/{reconciliation_id}/adjustments",
+        json={"sum": {"amount": -2, "currency": "EUR"}, "description": "bank fee"},
+    )
+    assert response.status_code == 200
+
+    response = client.get(f"/reconciliations/{reconciliation_id}")
+    assert response.status_code == 200
+    assert response.json()["discrepancies"] == [{"amount": "0.00", "currency": "EUR"}]
+
+    response = client.post(f"/reconciliations/{reconciliation_id}/finish")
+    assert response.status_code == 200
+
+    response = client.post(
+        "/transactions",
+        json={
+            "timestamp": (start + datetime.timedelta(days=3)).timestamp(),
+            "sum": {"amount": -1, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "late addition",
+        },
+    )
+    assert response.status_code == 409
+
+    response = client.delete(f"/transactions/{transaction_ids[1]}")
+    assert response.status_code == 409
+
+    response = client.delete(f"/transactions/{transaction_ids[2]}")
+    assert response.status_code == 200
+
+
+def test_balance_snapshots(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "bank", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    snapshot_time = datetime.datetime(year=2024, month=9, day=10, tzinfo=datetime.UTC)
+    for amount, days in ((-10, -5), (-20, -2), (-5, 3)):
+        response = client.post(
+            "/transactions",
+            json={
+                "timestamp": (snapshot_time + datetime.timedelta(days=days)).timestamp(),
+                "sum": {"amount": amount, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": "whatever",
+            },
+        )
+        assert response.status_code == 200
+
+    snapshot = {
+        "pool_id": pool_id,
+        "timestamp": snapshot_time.isoformat(),
+        "balance": [{"amount": 65, "currency": "EUR"}],
+    }
+    response = client.post("/balance-snapshots", json={**snapshot, "pool_id": "missing"})
+    assert response.status_code == 400
+    response = client.post(
+        "/balance-snapshots", json={**snapshot, "balance": [{"amount": 1, "currency": "USD"}]}
+    )
+    assert response.status_code == 400
+    response = client.post("/balance-snapshots", json={**snapshot, "balance": []})
+    assert response.status_code == 422
+
+    response = client.post("/balance-snapshots", json=snapshot)
+    assert response.status_code == 200
+    snapshot_id = response.json()["id"]
+    response = client.get("/balance-snapshots", params={"pool_id": pool_id})
+    assert [s["id"] for s in response.json()] == [snapshot_id]
+
+    response = client.get(f"/balance-snapshots/{snapshot_id}/reconciliation")
+    assert response.status_code == 200
+    reconciliation = response.json()
+    assert reconciliation["computed_balance"] == [{"amount": "70.00", "currency": "EUR"}]
+    assert reconciliation["drift"] == [{"amount": "-5.00", "currency": "EUR"}]
+    [adjustment] = reconciliation["suggested_adjustments"]
+    assert adjustment["sum"] == {"amount": "-5.00", "currency": "EUR"}
+    assert adjustment["is_diffuse"] is True
+
+    response = client.post("/transactions", json=adjustment)
+    assert response.status_code == 200
+    response = client.get(f"/balance-snapshots/{snapshot_id}/reconciliation")
+    assert response.json()["drift"] == [{"amount": "0.00", "currency": "EUR"}]
+    assert response.json()["suggested_adjustments"] == []
+    response = client.get(f"/pools/{pool_id}")
+    assert response.json()["balance"] == [{"amount": "60.00", "currency": "EUR"}]
+
+    response = client.delete(f"/balance-snapshots/{snapshot_id}")
+    assert response.status_code == 200
+    response = client.delete(f"/balance-snapshots/{snapshot_id}")
+    assert response.status_code == 404
+    assert client.get(f"/balance-snapshots/{snapshot_id}/reconciliation").status_code == 404
+
+
+
+def test_request_id(client: TestClient) -> None:
+    response = client.get("/")
+    assert response.status_code == 200
+    assert response.headers["x-request-id"]
+
+    response = client.get("/", headers={"x-request-id": "my-request"})
+    assert response.status_code == 200
+    assert response.headers["x-request-id"] == "my-request"
+
+    for invalid in ("my request", "x" * 129, 'id"forged'):
+        response = client.get("/", headers={"x-request-id": invalid})
+        assert response.status_code == 200
+        assert response.headers["x-request-id"] not in (invalid, "")
+
+
+def test_cors_preflight() -> None:
+    app = create_app(
+        storage=InmemoryStorage(),
+        auth=NoAuth(),
+        exchange_rates=DumbExchangeRates(),
+        frontend_origins=["https://example.com"],
+        cors_allow_methods=["GET", "POST"],
+    )
+    client = TestClient(app)
+
+    response = client.options(
+        "/transactions",
+        headers={
+            "origin": "https://example.com",
+            "access-control-request-method": "POST",
+        },
+    )
+    assert response.status_code == 200
+    assert response.headers["access-control-allow-origin"] == "https://example.com"
+
+    response = client.options(
+        "/transactions",
+        headers={
+            "origin": "https://example.com",
+            "access-control-request-method": "DELETE",
+        },
+    )
+    assert response.status_code == 400
+
+    response = client.options(
+        "/transactions",
+        headers={
+            "origin": "https://evil.com",
+            "access-control-request-method": "GET",
+        },
+    )
+    assert response.status_code == 400
+
+
+def test_report_snapshot_diff(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "debit", "balance": [{"amount": 300, "currency": "USD"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+
+    def make_transaction(amount: float, days: int) -> None:
+        response = client.post(
+            "/transactions",
+            json={
+                "timestamp": (start + datetime.timedelta(days=days)).timestamp(),
+                "sum": {"amount": amount, "currency": "USD"},
+                "pool_id": pool_id,
+                "description": "whatever",
+            },
+        )
+        assert response.status_code == 200
+
+    make_transaction(-100, 4)
+
+    response = client.post(
+        "/report/snapshots",
+        json={"year": 2024, "month": 9, "points": 2, "target_currency": "USD"},
+    )
+    assert response.status_code == 200
+    snapshot_id = response.json()["id"]
+    assert response.json()["report"]["spent"] == {"amount": "100.00", "currency": "USD"}
+
+    response = client.get(f"/report/snapshots/{snapshot_id}/diff")
+    assert response.status_code == 200
+    assert response.json() == []
+
+    make_transaction(-50, 5)
+
+    response = client.get(f"/report/snapshots/{snapshot_id}")
+    assert response.status_code == 200
+    assert response.json()["report"]["spent"] == {"amount": "100.00", "currency": "USD"}
+
+    response = client.get(f"/report/snapshots/{snapshot_id}/current")
+    assert response.status_code == 200
+    assert response.json()["spent"] == {"amount": "150.00", "currency": "USD"}
+
+    response = client.get(f"/report/snapshots/{snapshot_id}/diff")
+    assert response.status_code == 200
+    assert response.json() == [
+        {
+            "metric": "spent",
+            "tag": None,
+            "reported": {"amount": "100.00", "currency": "USD"},
+            "current": {"amount": "150.00", "currency": "USD"},
+        },
+        {
+            "metric": "overall_total_at_end",
+            "tag": None,
+            "reported": {"amount": "200.00", "currency": "USD"},
+            "current": {"amount": "150.00", "currency": "USD"},
+        },
+        {
+            "metric": "tag_total",
+            "tag": None,
+            "reported": {"amount": "-100.00", "currency": "USD"},
+            "current": {"amount": "-150.00", "currency": "USD"},
+        },
+    ]
+
+    response = client.post("/report/snapshots", json={"year": 2999, "month": 1})
+    assert response.status_code == 400
+
+
+def test_static_frontend(tmp_path: Path) -> None:
+    (tmp_path / "index.html").write_text("<html>index</html>")
+    (tmp_path / "app.js").write_text("console.log('hi')")
+    app = create_app(
+        storage=InmemoryStorage(),
+        auth=NoAuth(),
+        exchange_rates=DumbExchangeRates(),
+        static_dir=tmp_path,
+    )
+    client = TestClient(app)
+
+    response = client.get("/app/app.js")
+    assert response.status_code == 200
+    assert response.text == "console.log('hi')"
+
+    response = client.get("/app/")
+    assert response.status_code == 200
+    assert response.text == "<html>index</html>"
+
+    response = client.get("/app/pools/123")
+    assert response.status_code == 200
+    assert response.text == "<html>index</html>"
+
+    response = client.get("/pools")
+    assert response.status_code == 200
+    assert response.json() == []
+
+
+def test_bulk_transactions(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "p", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    def transaction(amount: float, pool_id: str) -> dict:
+        return {
+            "sum": {"amount": amount, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "imported",
+        }
+
+    response = client.post(
+        "/transactions/bulk",
+        json={"transactions": [transaction(-10, pool_id), transaction(-20, "missing-pool")]},
+    )
+    assert response.status_code == 400
+    assert response.json()["detail"] == [
+        {"index": 0, "transaction": None, "error": None},
+        {
+            "index": 1,
+            "transaction": None,
+            "error": "Transaction is attributed to non-existent money pool",
+        },
+    ]
+
+    response = client.get(f"/pools/{pool_id}")
+    assert response.json()["balance"] == [{"amount": "100.00", "currency": "EUR"}]
+
+    response = client.post(
+        "/transactions/bulk",
+        json={"transactions": [transaction(-10, pool_id), transaction(-20, pool_id)]},
+    )
+    assert response.status_code == 200
+    results = response.json()
+    assert [r["index"] for r in results] == [0, 1]
+    assert [r["transaction"]["sum"]["amount"] for r in results] == ["-10.00", "-20.00"]
+
+    response = client.get(f"/pools/{pool_id}")
+    assert response.json()["balance"] == [{"amount": "70.00", "currency": "EUR"}]
+
+
+def test_pool_transfer(client: TestClient) -> None:
+    pool_ids = []
+    for name, amount in [("card", 100), ("cash", 0)]:
+        response = client.post(
+            "/pools",
+            json={"display_name": name, "balance": [{"amount": amount, "currency": "EUR"}]},
+        )
+        assert response.status_code == 200
+        pool_ids.append(response.json()["id"])
+    card_id, cash_id = pool_ids
+
+    response = client.post(
+        f"/pools/{card_id}/transfer",
+        json={"to_pool": cash_id, "sum": {"amount": 30, "currency": "USD"}},
+    )
+    assert response.status_code == 400
+
+    response = client.post(
+        f"/pools/{card_id}/transfer",
+        json={"to_pool": cash_id, "sum": {"amount": 30, "currency": "EUR"}},
+    )
+    assert response.status_code == 200
+    debit, credit = response.json()
+    assert debit["pool_id"] == card_id
+    assert debit["sum"] == {"amount": "-30.00", "currency": "EUR"}
+    assert credit["pool_id"] == cash_id
+    assert credit["sum"] == {"amount": "30.00", "currency": "EUR"}
+    assert debit["transfer_id"] is not None
+    assert debit["transfer_id"] == credit["transfer_id"]
+    assert debit["tags"] == credit["tags"] == ["transfer"]
+
+    assert client.get(f"/pools/{card_id}").json()["balance"][0]["amount"] == "70.00"
+    assert client.get(f"/pools/{cash_id}").json()["balance"][0]["amount"] == "30.00"
+
+    response = client.get("/report", params={"start": "2020-01-01T00:00:00Z"})
+    assert response.status_code == 200
+    assert response.json()["spent"]["amount"] == "0.00"
+    assert response.json()["made"]["amount"] == "0.00"
+
+    # clients can't pass an expense off as a transfer
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -10, "currency": "EUR"},
+            "pool_id": card_id,
+            "description": "groceries",
+            "transfer_id": debit["transfer_id"],
+        },
+    )
+    assert response.status_code == 200
+    assert response.json()["transfer_id"] is None
+    assert response.json()["kind"] == "expense"
+    response = client.get("/report", params={"start": "2020-01-01T00:00:00Z"})
+    assert response.json()["spent"]["amount"] == "10.00"
+
+
+def test_cash_withdrawal(client: TestClient) -> None:
+    pool_ids = []
+    for name, amount in [("card", 500), ("cash", 0)]:
+        response = client.post(
+            "/pools",
+            json={"display_name": name, "balance": [{"amount": amount, "currency": "EUR"}]},
+        )
+        assert response.status_code == 200
+        pool_ids.append(response.json()["id"])
+    card_id, cash_id = pool_ids
+
+    response = client.post(
+        f"/pools/{card_id}/withdrawal",
+        json={
+            "to_pool": cash_id,
+            "sum": {"amount": 100, "currency": "EUR"},
+            "fee": {"amount": 2.5, "currency": "EUR"},
+        },
+    )
+    assert response.status_code == 200
+    assert [t["tags"] for t in response.json()["transactions"]] == [
+        ["transfer", "withdrawal"],
+        ["transfer", "withdrawal"],
+        ["fees"],
+    ]
+    assert response.json()["unaccounted"] == {"amount": "100.00", "currency": "EUR"}
+    assert client.get(f"/pools/{card_id}").json()["balance"][0]["amount"] == "397.50"
+    assert client.get(f"/pools/{cash_id}").json()["balance"][0]["amount"] == "100.00"
+
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -30, "currency": "EUR"},
+            "pool_id": cash_id,
+            "description": "lunch",
+        },
+    )
+    assert response.status_code == 200
+
+    response = client.post(
+        f"/pools/{card_id}/withdrawal",
+        json={"to_pool": cash_id, "sum": {"amount": 50, "currency": "EUR"}},
+    )
+    assert response.status_code == 200
+    assert len(response.json()["transactions"]) == 2
+    assert response.json()["unaccounted"] == {"amount": "120.00", "currency": "EUR"}
+
+    response = client.post(f"/sync-balance/{cash_id}", json={"amounts": [25]})
+    assert response.status_code == 200
+
+    response = client.post(
+        f"/pools/{card_id}/withdrawal",
+        json={"to_pool": cash_id, "sum": {"amount": 10, "currency": "EUR"}},
+    )
+    assert response.status_code == 200
+    assert response.json()["unaccounted"] == {"amount": "10.00", "currency": "EUR"}
+
+
+def test_unaccounted_spending(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    response = client.get(f"/pools/{pool_id}/unaccounted", params={"actual": 65})
+    assert response.status_code == 200
+    assert response.json() == {
+        "since": None,
+        "expected": {"amount": "100.00", "currency": "EUR"},
+        "actual": {"amount": "65.00", "currency": "EUR"},
+        "gap": {"amount": "35.00", "currency": "EUR"},
+        "unaccounted_withdrawn": {"amount": "0.00", "currency": "EUR"},
+    }
+
+    response = client.post(
+        f"/pools/{pool_id}/unaccounted",
+        json={"actual": {"amount": 65, "currency": "EUR"}},
+    )
+    assert response.status_code == 200
+    assert response.json()["sum"] == {"amount": "-35.00", "currency": "EUR"}
+    assert response.json()["is_diffuse"] is True
+
+    response = client.get(f"/pools/{pool_id}/unaccounted", params={"actual": 65})
+    assert response.status_code == 200
+    assert mask_recent_timestamps(response.json())["since"] == RECENT_TIMESTAMP
+    assert response.json()["gap"] == {"amount": "0.00", "currency": "EUR"}
+
+    response = client.post(
+        f"/pools/{pool_id}/unaccounted",
+        json={"actual": {"amount": 65, "currency": "EUR"}},
+    )
+    assert response.status_code == 400
+
+
+def test_allowance(client: TestClient) -> None:
+    pool_ids = []
+    for name, amount in [("parent", 100), ("kid", 0)]:
+        response = client.post(
+            "/pools",
+            json={"display_name": name, "balance": [{"amount": amount, "currency": "EUR"}]},
+        )
+        assert response.status_code == 200
+        pool_ids.append(response.json()["id"])
+    parent_id, kid_id = pool_ids
+
+    response = client.post(
+        "/allowances",
+        json={
+            "pool_id": kid_id,
+            "source_pool_id": parent_id,
+            "child_name": "Kid",
+            "weekly_amount": {"amount": 10, "currency": "EUR"},
+            "approval_threshold": 5,
+        },
+    )
+    assert response.status_code == 200
+    allowance = response.json()
+    assert allowance["next_payment_at"] > datetime.datetime.now().timestamp() + 6 * 86400
+    assert client.get(f"/pools/{parent_id}").json()["balance"][0]["amount"] == "90.00"
+
+    response = client.get("/allowance-view", headers={"allowance-token": "wrong"})
+    assert response.status_code == 401
+    response = client.get(
+        "/allowance-view", headers={"allowance-token": allowance["view_token"]}
+    )
+    assert response.status_code == 200
+    assert response.json()["child_name"] == "Kid"
+    assert response.json()["balance"] == [{"amount": "10.00", "currency": "EUR"}]
+
+    def spend(amount: float):
+        return client.post(
+            "/transactions",
+            json={
+                "sum": {"amount": -amount, "currency": "EUR"},
+                "pool_id": kid_id,
+                "description": "candies",
+            },
+        )
+
+    assert spend(3).status_code == 200
+    response = spend(7)
+    assert response.status_code == 202
+    spend_id = response.json()["id"]
+    assert response.json()["status"] == "pending"
+    assert client.get(f"/pools/{kid_id}").json()["balance"][0]["amount"] == "7.00"
+
+    url = f"/allowances/{allowance['id']}/spends/{spend_id}/approve"
+    response = client.post(url)
+    assert response.status_code == 200
+    assert response.json()["status"] == "approved"
+    assert client.get(f"/pools/{kid_id}").json()["balance"][0]["amount"] == "0.00"
+    assert client.post(url).status_code == 409
+
+
+def test_goals(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "savings", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    goal = {
+        "name": "bike",
+        "target": {"amount": 1000, "currency": "EUR"},
+        "target_date": (datetime.datetime.now() + datetime.timedelta(days=365)).timestamp(),
+        "pool_id": pool_id,
+    }
+    assert client.post("/goals", json={**goal, "pool_id": "missing"}).status_code == 400
+    response = client.post("/goals", json=goal)
+    assert response.status_code == 200
+    goal_id = response.json()["id"]
+
+    response = client.put(f"/goals/{goal_id}", json={"name": "new bike"})
+    assert response.status_code == 200
+    assert [g["name"] for g in client.get("/goals").json()] == ["new bike"]
+
+    response = client.get(f"/goals/{goal_id}/progress")
+    assert response.status_code == 200
+    assert response.json()["saved"] == {"amount": "100.00", "currency": "EUR"}
+    assert response.json()["fraction"] == 0.1
+    assert response.json()["on_track"] is False
+
+    assert client.delete(f"/goals/{goal_id}").status_code == 200
+    assert client.get(f"/goals/{goal_id}").status_code == 404
+
+
+def test_debts(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    debt_ids = []
+    for counterparty, direction, amount in [
+        ("Alice", "lent", 30),
+        ("Alice", "borrowed", 10),
+        ("Bob", "borrowed", 20),
+    ]:
+        response = client.post(
+            "/debts",
+            json={
+                "counterparty": counterparty,
+                "direction": direction,
+                "sum": {"amount": amount, "currency": "EUR"},
+            },
+        )
+        assert response.status_code == 200
+        debt_ids.append(response.json()["id"])
+
+    response = client.get("/debts/summary")
+    assert response.json() == [
+        {"counterparty": "Alice", "balance": [{"amount": "20.00", "currency": "EUR"}]},
+        {"counterparty": "Bob", "balance": [{"amount": "-20.00", "currency": "EUR"}]},
+    ]
+
+    response = client.post(f"/debts/{debt_ids[0]}/settle", json={"pool_id": pool_id})
+    assert response.status_code == 200
+    assert response.json()["sum"] == {"amount": "30.00", "currency": "EUR"}
+    assert response.json()["description"] == "Alice returned 30.00 EUR"
+    assert client.get(f"/pools/{pool_id}").json()["balance"][0]["amount"] == "130.00"
+    assert (
+        client.post(f"/debts/{debt_ids[0]}/settle", json={"pool_id": pool_id}).status_code
+        == 409
+    )
+
+    response = client.get("/debts/summary")
+    assert response.json()[0] == {
+        "counterparty": "Alice",
+        "balance": [{"amount": "-10.00", "currency": "EUR"}],
+    }
+    assert len(client.get("/debts", params={"outstanding_only": True}).json()) == 2
+
+
+def test_shared_expenses(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -90, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "dinner",
+        },
+    )
+    transaction_id = response.json()["id"]
+
+    response = client.post(
+        f"/transactions/{transaction_id}/share",
+        json={
+            "participants": [
+                {"counterparty": "Alice", "shares": 1},
+                {"counterparty": "Bob", "shares": 1},
+            ]
+        },
+    )
+    assert response.status_code == 200
+    assert [(d["counterparty"], d["sum"]["amount"]) for d in response.json()] == [
+        ("Alice", "30.00"),
+        ("Bob", "30.00"),
+    ]
+    assert all(d["shared_transaction_id"] == transaction_id for d in response.json())
+    response = client.post(
+        f"/transactions/{transaction_id}/share",
+        json={"participants": [{"counterparty": "Alice", "shares": 1}]},
+    )
+    assert response.status_code == 409
+    response = client.post(
+        "/transactions/missing/share",
+        json={"participants": [{"counterparty": "Alice", "shares": 1}]},
+    )
+    assert response.status_code == 404
+    response = client.post(
+        f"/transactions/{transaction_id}/share",
+        json={"participants": [{"counterparty": "Alice", "shares": 0}]},
+    )
+    assert response.status_code == 422
+
+    response = client.post(
+        "/debts",
+        json={
+            "counterparty": "Alice",
+            "direction": "borrowed",
+            "sum": {"amount": 10, "currency": "EUR"},
+        },
+    )
+    assert response.status_code == 200
+
+    response = client.post("/debts/counterparties/Alice/settle", json={"pool_id": pool_id})
+    assert response.status_code == 200
+    [settlement] = response.json()
+    assert settlement["sum"] == {"amount": "20.00", "currency": "EUR"}
+    assert settlement["description"] == "Settled up with Alice"
+    assert client.get(f"/pools/{pool_id}").json()["balance"][0]["amount"] == "30.00"
+    response = client.get("/debts/summary")
+    assert response.json() == [
+        {"counterparty": "Bob", "balance": [{"amount": "30.00", "currency": "EUR"}]}
+    ]
+    response = client.post("/debts/counterparties/Alice/settle", json={"pool_id": pool_id})
+    assert response.status_code == 404
+
+
+def test_transaction_templates(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    template = {
+        "name": "coffee",
+        "sum": {"amount": -3, "currency": "EUR"},
+        "pool_id": pool_id,
+        "description": "coffee",
+        "tags": ["food"],
+    }
+    assert client.post("/templates", json={**template, "pool_id": "missing"}).status_code == 400
+    response = client.post("/templates", json=template)
+    assert response.status_code == 200
+    template_id = response.json()["id"]
+
+    response = client.put(f"/templates/{template_id}", json={"description": "flat white"})
+    assert response.status_code == 200
+    assert [t["description"] for t in client.get("/templates").json()] == ["flat white"]
+
+    response = client.post(f"/templates/{template_id}/apply", json={})
+    assert response.status_code == 200
+    assert response.json()["sum"] == {"amount": "-3.00", "currency": "EUR"}
+    assert response.json()["description"] == "flat white"
+    assert response.json()["tags"] == ["food"]
+
+    response = client.post(
+        f"/templates/{template_id}/apply", json={"sum": {"amount": -4.5, "currency": "EUR"}}
+    )
+    assert response.status_code == 200
+    assert response.json()["sum"] == {"amount": "-4.50", "currency": "EUR"}
+    assert client.get(f"/pools/{pool_id}").json()["balance"][0]["amount"] == "92.50"
+
+    assert client.delete(f"/templates/{template_id}").status_code == 200
+    assert client.post(f"/templates/{template_id}/apply", json={}).status_code == 404
+
+
+def test_statement_import(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    qif = "!Type:Bank\nD2024-01-31\nT-12.50\nPLIDL\n^\nD2024-02-01\nT-20\nPRent\n^\n"
+    client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -20, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "rent",
+            "timestamp": datetime.datetime(2024, 2, 1, 9, tzinfo=datetime.UTC).timestamp(),
+        },
+    )
+
+    response = client.post(f"/pools/{pool_id}/import", content=qif)
+    assert response.status_code == 200
+    assert [t["description"] for t in response.json()["imported"]] == ["LIDL"]
+    assert response.json()["skipped"] == 1
+    assert response.json()["imported"][0]["source"]["origin"] == "qif"
+
+    response = client.post(f"/pools/{pool_id}/import", content=qif)
+    assert response.json() == {"imported": [], "skipped": 2}
+    assert client.post(f"/pools/{pool_id}/import", content="garbage").status_code == 400
+
+
+def test_categorization_rules(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    transaction = {"sum": {"amount": -20, "currency": "EUR"}, "pool_id": pool_id}
+    response = client.post("/transactions", json={**transaction, "description": "LIDL 1234"})
+    assert response.status_code == 200
+    old_id = response.json()["id"]
+
+    assert client.post("/rules", json={"pattern": "lidl"}).status_code == 422
+    response = client.post("/rules", json={"pattern": "lidl", "category": "groceries"})
+    assert response.status_code == 200
+    rule_id = response.json()["id"]
+    response = client.put(f"/rules/{rule_id}", json={"merchant": "Lidl"})
+    assert response.status_code == 200
+
+    response = client.post("/transactions", json={**transaction, "description": "lidl berlin"})
+    assert response.status_code == 200
+    assert response.json()["description"] == "Lidl"
+    assert response.json()["tags"] == ["groceries"]
+
+    response = client.post(f"/rules/{rule_id}/apply")
+    assert response.status_code == 200
+    assert response.json() == {"updated_transaction_ids": [old_id], "locked": 0}
+    assert [t["tags"] for t in client.get("/transactions").json()] == [
+        ["groceries"],
+        ["groceries"],
+    ]
+
+    assert client.delete(f"/rules/{rule_id}").status_code == 200
+    assert client.get("/rules").json() == []
+
+
+def test_payees(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    payee_ids = {}
+    for name in ("Corner Bakery", "Bakery Express", "Bookshop"):
+        response = client.post("/payees", json={"name": name})
+        assert response.status_code == 200
+        payee_ids[name] = response.json()["id"]
+    assert client.post("/payees", json={"name": ""}).status_code == 422
+
+    def suggested(q: str) -> list[str]:
+        response = client.get("/payees/suggest", params={"q": q})
+        assert response.status_code == 200
+        return [p["name"] for p in response.json()]
+
+    assert suggested("bak") == ["Bakery Express", "Corner Bakery"]
+    assert suggested("B") == ["Bakery Express", "Bookshop", "Corner Bakery"]
+    assert suggested("cafe") == []
+    assert client.get("/payees/suggest", params={"q": ""}).status_code == 422
+
+    def add(amount: int, payee: str | None) -> dict:
+        response = client.post(
+            "/transactions",
+            json={
+                "sum": {"amount": amount, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": "x",
+                "payee_id": payee_ids[payee] if payee is not None else None,
+            },
+            params={"force": True},
+        )
+        assert response.status_code == 200
+        return response.json()
+
+    bread = add(-3, "Corner Bakery")
+    assert bread["payee_id"] == payee_ids["Corner Bakery"]
+    add(-5, "Corner Bakery")
+    add(-20, "Bookshop")
+    untagged = add(-1, None)
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -1, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "x",
+            "payee_id": "missing",
+        },
+    )
+    assert response.status_code == 400
+
+    response = client.put(
+        f"/transactions/{untagged['id']}",
+        json={"payee_id": payee_ids["Bakery Express"]},
+        headers={"If-Match": "0"},
+    )
+    assert response.status_code == 200
+    response = client.put(
+        f"/transactions/{untagged['id']}", json={"payee_id": "missing"}, headers={"If-Match": "1"}
+    )
+    assert response.status_code == 400
+
+    response = client.get("/report/payees", params={"from": "2000-01-01T00:00:00Z"})
+    assert response.status_code == 200
+    report = response.json()
+    assert report["spent"]["amount"] == "29.00"
+    payees = [(p["name"], p["spent"]["amount"], p["transaction_count"]) for p in report["payees"]]
+    assert payees == [
+        ("Bookshop", "20.00", 1),
+        ("Corner Bakery", "8.00", 2),
+        ("Bakery Express", "1.00", 1),
+    ]
+    response = client.get(
+        "/report/payees", params={"from": "2000-01-01T00:00:00Z", "target_currency": "XXXX"}
+    )
+    assert response.status_code == 400
+
+    bakery_id = payee_ids["Corner Bakery"]
+    response = client.put(f"/payees/{bakery_id}", json={"name": "Bakery on the Corner"})
+    assert response.status_code == 200
+    assert client.get(f"/payees/{bakery_id}").json()["name"] == "Bakery on the Corner"
+    assert client.delete(f"/payees/{bakery_id}").status_code == 200
+    assert client.get(f"/payees/{bakery_id}").status_code == 404
+    assert [p["name"] for p in client.get("/payees").json()] == ["Bakery Express", "Bookshop"]
+    transactions = client.get("/transactions").json()
+    assert [t["payee_id"] for t in transactions if t["id"] == bread["id"]] == [None]
+    response = client.post("/undo")
+    assert response.json()["kind"] == "update"
+    assert len(response.json()["transactions"]) == 2
+
+
+def test_suggestions(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    for description, tags in [("coffee", ["food"]), ("Coffee beans", ["food"]), ("coffee", [])]:
+        response = client.post(
+            "/transactions",
+            json={
+                "sum": {"amount": -1, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": description,
+                "tags": tags,
+            },
+            params={"force": True},
+        )
+        assert response.status_code == 200
+
+    response = client.get("/suggest", params={"field": "description", "q": "COF"})
+    assert response.status_code == 200
+    assert response.json() == [
+        {"value": "coffee", "payee_id": None, "count": 2},
+        {"value": "Coffee beans", "payee_id": None, "count": 1},
+    ]
+    response = client.get("/suggest", params={"field": "category", "count": 1})
+    assert [s["value"] for s in response.json()] == ["food"]
+    assert client.get("/suggest", params={"field": "merchant"}).status_code == 422
+
+
+def test_undo(client: TestClient) -> None:
+    assert client.post("/undo").status_code == 404
+
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    def balance() -> str:
+        return client.get(f"/pools/{pool_id}").json()["balance"][0]["amount"]
+
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -10, "currency": "EUR"}, "pool_id": pool_id, "description": "x"},
+    )
+    assert response.status_code == 200
+    transaction_id = response.json()["id"]
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -1000, "currency": "EUR"}, "pool_id": pool_id, "description": "y"},
+    )
+    assert response.status_code == 200
+    assert balance() == "-910.00"
+
+    response = client.post("/undo")
+    assert response.status_code == 200
+    assert response.json()["kind"] == "create"
+    assert [t["description"] for t in response.json()["transactions"]] == ["y"]
+    assert balance() == "90.00"
+
+    response = client.put(
+        f"/transactions/{transaction_id}", json={"description": "typo"}, headers={"If-Match": "0"}
+    )
+    assert response.status_code == 200
+    response = client.post("/undo")
+    assert response.status_code == 200
+    assert response.json()["kind"] == "update"
+    assert response.json()["transactions"][0]["description"] == "x"
+
+    assert client.delete(f"/transactions/{transaction_id}").status_code == 200
+    assert balance() == "100.00"
+    response = client.post("/undo")
+    assert response.status_code == 200
+    assert response.json()["kind"] == "delete"
+    assert response.json()["transactions"][0]["id"] == transaction_id
+    assert balance() == "90.00"
+
+    assert client.post("/undo").status_code == 200  # the first transaction creation
+    assert balance() == "100.00"
+    assert client.post("/undo").status_code == 404
+
+
+def test_unavailable_exchange_rate() -> None:
+    client = TestClient(
+        create_app(
+            storage=InmemoryStorage(), auth=NoAuth(), exchange_rates=NoGbpExchangeRates()
+        )
+    )
+    pool_ids = []
+    for currency in ("EUR", "GBP"):
+        response = client.post(
+            "/pools",
+            json={"display_name": currency, "balance": [{"amount": 100, "currency": currency}]},
+        )
+        assert response.status_code == 200
+        pool_ids.append(response.json()["id"])
+    eur_pool_id, gbp_pool_id = pool_ids
+
+    start = datetime.datetime.now(tz=datetime.UTC) - datetime.timedelta(days=1)
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -10, "currency": "GBP"},
+            "pool_id": gbp_pool_id,
+            "description": "tea",
+        },
+    )
+    assert response.status_code == 200
+    assert response.json()["amount_eur"] is None
+
+    response = client.get("/report", params={"start": start.isoformat(), "points": 2})
+    assert response.status_code == 200
+    report = response.json()
+    pool_stats = report["snapshots"][0]["pool_stats"]
+    assert [s["unconverted"] for s in pool_stats] == [
+        [],
+        [{"amount": "90.00", "currency": "GBP"}],
+    ]
+    assert report["snapshots"][0]["overall_total"] == {"amount": "100.00", "currency": "EUR"}
+    assert report["spent"] == {"amount": "0.00", "currency": "EUR"}
+    assert report["spent_unconverted"] == [{"amount": "10.00", "currency": "GBP"}]
+
+    transfer = {
+        "from_pool": eur_pool_id,
+        "to_pool": gbp_pool_id,
+        "sum": {"amount": 20, "currency": "EUR"},
+        "description": "",
+    }
+    response = client.post("/transfer", json=transfer)
+    assert response.status_code == 503
+    assert response.json()["base"] == "EUR"
+    response = client.post(
+        "/transfer", json={**transfer, "received": {"amount": 17, "currency": "GBP"}}
+    )
+    assert response.status_code == 200
+    assert client.get(f"/pools/{gbp_pool_id}").json()["balance"][0]["amount"] == "107.00"
+
+
+def test_storage_outage() -> None:
+    storage = FaultyStorage()
+    client = TestClient(
+        create_app(storage=storage, auth=NoAuth(), exchange_rates=DumbExchangeRates()),
+        raise_server_exceptions=False,
+    )
+    pool_ids = []
+    for name in ("card", "cash"):
+        response = client.post(
+            "/pools",
+            json={"display_name": name, "balance": [{"amount": 100, "currency": "EUR"}]},
+        )
+        assert response.status_code == 200
+        pool_ids.append(response.json()["id"])
+    card_id, cash_id = pool_ids
+
+    storage.fail("load_pools")
+    assert client.get("/pools").status_code == 500
+    storage.heal()
+    assert client.get("/pools").status_code == 200
+
+    def balances() -> list[str]:
+        return [client.get(f"/pools/{p}").json()["balance"][0]["amount"] for p in pool_ids]
+
+    transfer = {
+        "from_pool": card_id,
+        "to_pool": cash_id,
+        "sum": {"amount": 20, "currency": "EUR"},
+        "description": "",
+    }
+    # both legs of the transfer are added at once or not at all
+    storage.fail("add_transactions", times=1)
+    assert client.post("/transfer", json=transfer).status_code == 500
+    assert balances() == ["100.00", "100.00"]
+    assert client.get("/transactions").json() == []
+
+    storage.delay("add_transactions", delay_sec=0.05, times=1)
+    assert client.post("/transfer", json=transfer).status_code == 200
+    assert balances() == ["80.00", "120.00"]
+
+    # offline changes are applied at once or not at all too
+    [credit] = [t for t in client.get("/transactions").json() if t["pool_id"] == cash_id]
+    tea = {"sum": {"amount": -5, "currency": "EUR"}, "pool_id": card_id, "description": "tea"}
+    changes = [
+        {"client_id": "a", "kind": "create", "transaction": tea},
+        {"client_id": "b", "kind": "delete", "transaction_id": credit["id"], "base_version": 0},
+    ]
+    storage.fail("apply_transaction_changes", times=1)
+    assert client.post("/sync", json={"changes": changes}).status_code == 500
+    assert balances() == ["80.00", "120.00"]
+    assert client.post("/sync", json={"changes": changes}).status_code == 200
+    assert balances() == ["75.00", "100.00"]
+    assert storage.faults == {}
+
+
+def test_admin_rebuild(client: TestClient) -> None:
+    assert client.post("/admin/rebuild", params={"what": "balances"}).status_code == 403
+
+    storage = InmemoryStorage()
+    client = TestClient(
+        create_app(
+            storage=storage,
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+            admin_user_ids=["no-auth"],
+        )
+    )
+    response = client.post(
+        "/pools",
+        json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -30, "currency": "EUR"}, "pool_id": pool_id, "description": ""},
+    )
+    assert response.status_code == 200
+    pool = asyncio.run(storage.load_pool("no-auth", pool_id))
+    assert pool is not None
+    pool.balance[0].amount = Decimal(42)
+    asyncio.run(storage.save_pool_balance("no-auth", pool_id, pool.balance, pool.initial_balance))
+
+    assert client.post("/admin/rebuild", params={"what": "projections"}).status_code == 422
+    response = client.post("/admin/rebuild", params={"what": "balances"})
+    assert response.status_code == 200
+    job_id = response.json()["id"]
+    job = client.get(f"/admin/rebuild/{job_id}").json()
+    assert job["status"] == "done"
+    assert (job["processed"], job["total"], job["changed"], job["errors"]) == (1, 1, 1, [])
+    assert client.get(f"/pools/{pool_id}").json()["balance"] == [
+        {"amount": "70.00", "currency": "EUR"}
+    ]
+
+    # recorded while the balance was off
+    today = datetime.datetime.now(tz=datetime.UTC).date()
+    drifted = MoneySum(amount=Decimal(42), currency="EUR")
+    asyncio.run(
+        storage.save_net_worth_snapshot(
+            "no-auth",
+            NetWorthSnapshot(date=today, balance=[drifted], total=drifted, unconverted=[]),
+        )
+    )
+    response = client.post("/admin/rebuild", params={"what": "snapshots"})
+    assert response.status_code == 200
+    job = client.get(f"/admin/rebuild/{response.json()['id']}").json()
+    assert (job["status"], job["processed"], job["changed"]) == ("done", 1, 1)
+    [snapshot] = asyncio.run(storage.load_net_worth_snapshots("no-auth", today, today))
+    assert [str(s) for s in snapshot.balance] == ["70.00 EUR"]
+    assert str(snapshot.total) == "70.00 EUR"
+
+    response = client.post("/admin/rebuild", params={"what": "indexes"})
+    assert response.status_code == 200
+    assert client.get(f"/admin/rebuild/{response.json()['id']}").json()["status"] == "done"
+    assert client.get("/admin/rebuild/unknown").status_code == 404
+
+
+def test_admin_users(client: TestClient) -> None:
+    assert client.get("/admin/users").status_code == 403
+
+    storage = InmemoryStorage()
+    client = TestClient(
+        create_app(
+            storage=storage,
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+            admin_user_ids=["no-auth"],
+        )
+    )
+    response = client.post(
+        "/pools",
+        json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    for _ in range(2):
+        response = client.post(
+            "/transactions",
+            json={"sum": {"amount": -5, "currency": "EUR"}, "pool_id": pool_id, "description": ""},
+        )
+        assert response.status_code == 200
+
+    response = client.get("/admin/users")
+    assert response.status_code == 200
+    [account] = response.json()
+    assert (account["user_id"], account["is_admin"], account["disabled"]) == (
+        "no-auth",
+        True,
+        False,
+    )
+    assert (account["usage"]["pools"], account["usage"]["transactions"]) == (1, 2)
+
+    response = client.put("/admin/users/no-auth", json={"disabled": True})
+    assert response.status_code == 400
+    response = client.put("/admin/users/other", json={"disabled": True})
+    assert response.status_code == 200
+    assert response.json()["disabled"] is True
+    assert [a["user_id"] for a in client.get("/admin/users").json()] == ["no-auth", "other"]
+    response = client.put("/admin/users/other", json={"disabled": False})
+    assert response.json()["disabled"] is False
+
+    asyncio.run(storage.set_user_disabled("no-auth", True))
+    response = client.get("/pools")
+    assert response.status_code == 403
+    assert response.json() == {"detail": "Account is disabled"}
+
+
+def test_export_and_delete_user_data(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -3, "currency": "EUR"}, "pool_id": pool_id, "description": "tea"},
+    )
+    assert response.status_code == 200
+
+    response = client.get("/me/export")
+    assert response.status_code == 200
+    assert response.headers["Content-Disposition"].startswith("attachment")
+    export = response.json()
+    assert export["user_id"] == "no-auth"
+    assert [p["id"] for p in export["pools"]] == [pool_id]
+    assert [t["description"] for t in export["transactions"]] == ["tea"]
+    assert export["goals"] == []
+
+    assert client.delete("/me").status_code == 400
+    assert client.get("/pools").json() != []
+    response = client.delete("/me", params={"confirm": True})
+    assert response.status_code == 200
+    assert client.get("/pools").json() == []
+    export = client.get("/me/export").json()
+    assert export["pools"] == []
+    assert export["transactions"] == []
+
+
+def test_pool_order(client: TestClient) -> None:
+    pool_ids = []
+    for name in ("card", "cash", "savings"):
+        response = client.post(
+            "/pools",
+            json={"display_name": name, "balance": [{"amount": 100, "currency": "EUR"}]},
+        )
+        assert response.status_code == 200
+        pool_ids.append(response.json()["id"])
+    card_id, cash_id, savings_id = pool_ids
+
+    response = client.put(
+        f"/pools/{savings_id}", json={"group": "long term"}, headers={"If-Match": "0"}
+    )
+    assert response.status_code == 200
+
+    response = client.put("/pools/order", json={"pool_ids": [savings_id, card_id]})
+    assert response.status_code == 400
+    response = client.put("/pools/order", json={"pool_ids": [savings_id, card_id, cash_id]})
+    assert response.status_code == 200
+
+    pools = client.get("/pools").json()
+    assert [(p["display_name"], p["sort_order"], p["group"]) for p in pools] == [
+        ("savings", 0, "long term"),
+        ("card", 1, None),
+        ("cash", 2, None),
+    ]
+
+
+def test_request_examples(client: TestClient) -> None:
+    response = client.get("/meta/examples")
+    assert response.status_code == 200
+    examples = response.json()
+    assert examples["POST /pools"]["display_name"] == "Debit card"
+    assert examples["PUT /transactions/{transaction_id}"] == {
+        "description": "coffee",
+        "tags": ["food", "work"],
+    }
+
+    routes_with_body = {
+        f"{method} {route.path}"
+        for route in client.app.routes  # type: ignore
+        if isinstance(route, APIRoute) and route.body_field is not None
+        for method in route.methods & {"POST", "PUT"}
+    }
+    assert routes_with_body <= set(examples)
+
+    schema = client.get("/openapi.json").json()
+    pool_schema = schema["components"]["schemas"]["CreatePoolRequestBody"]
+    assert pool_schema["examples"] == [examples["POST /pools"]]
+
+
+def test_pool_icon_and_color(client: TestClient) -> None:
+    pool = {"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]}
+    for invalid in ({"icon": "Credit Card"}, {"color_hex": "red"}, {"color_hex": "#12345"}):
+        assert client.post("/pools", json={**pool, **invalid}).status_code == 422
+
+    response = client.post("/pools", json={**pool, "icon": "wallet", "color_hex": "#4A90D9"})
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+    assert response.json()["color_hex"] == "#4a90d9"
+
+    assert client.put(f"/pools/{pool_id}", json={"color_hex": "#xyzxyz"}).status_code == 422
+    response = client.put(
+        f"/pools/{pool_id}", json={"icon": "piggy-bank"}, headers={"If-Match": "0"}
+    )
+    assert response.status_code == 200
+    response = client.get(f"/pools/{pool_id}")
+    assert (response.json()["icon"], response.json()["color_hex"]) == ("piggy-bank", "#4a90d9")
+
+
+def test_domain_events() -> None:
+    event_bus = EventBus()
+    received: list[BaseEvent] = []
+
+    async def on_event(event: BaseEvent) -> None:
+        received.append(event)
+
+    event_bus.subscribe(BaseEvent, on_event)
+    client = TestClient(
+        create_app(
+            storage=InmemoryStorage(),
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+            event_bus=event_bus,
+        )
+    )
+    response = client.post(
+        "/pools",
+        json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -30, "currency": "EUR"}, "pool_id": pool_id, "description": ""},
+    )
+    transaction_id = response.json()["id"]
+    if_match = {"If-Match": "0"}
+    response = client.put(
+        f"/transactions/{transaction_id}", json={"tags": ["food"]}, headers=if_match
+    )
+    assert response.is_success
+    assert client.post("/undo").is_success
+    response = client.put(f"/pools/{pool_id}", json={"display_name": "wallet"}, headers=if_match)
+    assert response.is_success
+    assert client.post(f"/sync-balance/{pool_id}", json={"amounts": [65]}).is_success
+
+    assert [e.type for e in received] == [  # type: ignore
+        "pool_created",
+        "transaction_created",
+        "pool_balance_changed",
+        "transaction_updated",
+        "transaction_updated",
+        "pool_updated",
+        "transaction_created",
+        "pool_balance_changed",
+    ]
+    assert isinstance(received[0], PoolCreated)
+    assert received[0].pool.id == pool_id
+    assert isinstance(received[2], PoolBalanceChanged)
+    assert received[2].balance[0].amount == Decimal(70)
+    assert isinstance(received[3], TransactionUpdated)
+    assert received[3].transaction.tags == ["food"]
+    assert isinstance(received[4], TransactionUpdated)
+    assert received[4].transaction.tags == []
+    assert isinstance(received[5], PoolUpdated)
+    assert received[5].pool.display_name == "wallet"
+    assert isinstance(received[7], PoolBalanceChanged)
+    assert received[7].balance[0].amount == Decimal(65)
+
+
+def test_pool_notes(client: TestClient) -> None:
+    pool_ids = []
+    for name in ("card", "cash"):
+        response = client.post(
+            "/pools",
+            json={"display_name": name, "balance": [{"amount": 100, "currency": "EUR"}]},
+        )
+        pool_ids.append(response.json()["id"])
+    card_id, cash_id = pool_ids
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -50, "currency": "EUR"}, "pool_id": card_id, "description": "hat"},
+    )
+    transaction_id = response.json()["id"]
+
+    assert client.post("/notes", json={"pool_id": card_id}).status_code == 422
+    assert client.post("/notes", json={"pool_id": card_id, "text": "x" * 201}).status_code == 422
+    response = client.post("/notes", json={"pool_id": cash_id, "transaction_id": transaction_id})
+    assert response.status_code == 400
+
+    response = client.post("/notes", json={"pool_id": card_id, "text": "card expires 09/27"})
+    assert response.status_code == 200
+    note_id = response.json()["note"]["id"]
+    assert response.json()["transaction"] is None
+    response = client.put(
+        f"/notes/{note_id}", json={"text": "refund pending", "transaction_id": transaction_id}
+    )
+    assert response.status_code == 200
+
+    pinned = client.get("/main").json()["pinned"]
+    assert [(p["note"]["text"], p["transaction"]["description"]) for p in pinned] == [
+        ("refund pending", "hat")
+    ]
+    assert client.get("/notes", params={"pool_id": cash_id}).json() == []
+
+    assert client.delete(f"/notes/{note_id}").status_code == 200
+    assert client.delete(f"/notes/{note_id}").status_code == 404
+    assert client.get("/notes").json() == []
+
+
+def test_pool_ids_generated_on_server_and_id_conflicts() -> None:
+    storage = InmemoryStorage()
+    client = TestClient(
+        create_app(storage=storage, auth=NoAuth(), exchange_rates=DumbExchangeRates())
+    )
+    response = client.post(
+        "/pools",
+        json={
+            "id": "my-id",
+            "display_name": "cash",
+            "balance": [{"amount": 100, "currency": "EUR"}],
+            "sort_order": 10,
+            "version": 5,
+        },
+    )
+    assert response.status_code == 200
+    pool = response.json()
+    assert pool["id"] != "my-id"
+    assert (pool["sort_order"], pool["version"]) == (0, 0)
+
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -1, "currency": "EUR"}, "pool_id": pool["id"], "description": ""},
+    )
+    transaction = asyncio.run(storage.load_transaction("no-auth", response.json()["id"]))
+    assert transaction is not None
+    assert client.delete(f"/transactions/{transaction.id}").status_code == 200
+    # e.g. restored concurrently
+    asyncio.run(storage.restore_transactions("no-auth", [transaction]))
+    response = client.post("/undo")
+    assert response.status_code == 409
+    assert transaction.id in response.json()["detail"]
+    assert client.get(f"/pools/{pool['id']}").json()["balance"][0]["amount"] == "99.00"
+
+
+def test_duplicate_transactions(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    now = datetime.datetime.now(tz=datetime.UTC)
+    transaction = {
+        "sum": {"amount": -4.5, "currency": "EUR"},
+        "pool_id": pool_id,
+        "description": "coffee",
+        "timestamp": now.timestamp(),
+    }
+    response = client.post("/transactions", json=transaction)
+    assert response.status_code == 200
+    existing_id = response.json()["id"]
+
+    later = now + datetime.timedelta(seconds=30)
+    response = client.post("/transactions", json={**transaction, "timestamp": later.timestamp()})
+    assert response.status_code == 409
+    assert response.json()["existing_id"] == existing_id
+
+    much_later = now + datetime.timedelta(minutes=10)
+    for not_duplicate in (
+        {"timestamp": much_later.timestamp()},
+        {"description": "tea"},
+        {"sum": {"amount": -4, "currency": "EUR"}},
+    ):
+        response = client.post("/transactions", json={**transaction, **not_duplicate})
+        assert response.status_code == 200
+
+    response = client.post("/transactions", json=transaction, params={"force": True})
+    assert response.status_code == 200
+    assert client.get(f"/pools/{pool_id}").json()["balance"][0]["amount"] == "78.00"
+
+
+def test_transaction_validation_warnings(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 1000, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    now = datetime.datetime.now(tz=datetime.UTC)
+
+    def add(amount: float, days_ago: float, description: str) -> dict:
+        response = client.post(
+            "/transactions",
+            json={
+                "sum": {"amount": amount, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": description,
+                "timestamp": (now - datetime.timedelta(days=days_ago)).timestamp(),
+            },
+        )
+        assert response.status_code == 200
+        return response.json()
+
+    for days_ago, amount in enumerate([-5, -8, -4, -12, -6]):
+        assert add(amount, days_ago + 2, f"groceries {days_ago}")["warnings"] == []
+
+    created = add(-300, 0, "new phone")
+    assert created["description"] == "new phone"
+    assert [w["code"] for w in created["warnings"]] == ["large_amount"]
+    assert all(w["severity"] == "warning" for w in created["warnings"])
+
+    created = add(-5, 1.5, "groceries again")
+    assert [w["code"] for w in created["warnings"]] == ["possible_duplicate"]
+
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -5, "currency": "EUR"},
+            "pool_id": "no-such-pool",
+            "description": "",
+        },
+    )
+    assert response.status_code == 400
+    assert response.json() == {"detail": "Transaction is attributed to non-existent money pool"}
+
+
+def test_user_settings(client: TestClient) -> None:
+    assert client.get("/settings").json() == {
+        "default_currency": "EUR",
+        "locale": "en",
+        "week_start": "monday",
+        "default_pool_id": None,
+        "timezone": "UTC",
+        "reporting_period": {"start_day": 1, "every_weeks": None, "anchor": None},
+    }
+    assert client.get("/digest").json()["expenses"]["currency"] == "EUR"
+
+    pool_id = client.post("/pools", json={"display_name": "card", "balance": []}).json()["id"]
+    settings = {
+        "default_currency": "USD",
+        "locale": "en-GB",
+        "week_start": "sunday",
+        "default_pool_id": "no-such-pool",
+        "timezone": "America/New_York",
+        "reporting_period": {"start_day": 25, "every_weeks": None, "anchor": None},
+    }
+    response = client.put("/settings", json=settings)
+    assert response.status_code == 400
+    response = client.put("/settings", json={**settings, "locale": "English"})
+    assert response.status_code == 422
+    response = client.put("/settings", json={**settings, "timezone": "Mars/Olympus_Mons"})
+    assert response.status_code == 422
+    response = client.put("/settings", json={**settings, "reporting_period": {"every_weeks": 4}})
+    assert response.status_code == 422
+
+    settings["default_pool_id"] = pool_id
+    response = client.put("/settings", json=settings)
+    assert response.status_code == 200
+    assert client.get("/settings").json() == settings
+
+    response = client.get("/report", params={"start": "2020-01-01T00:00:00Z", "points": 2})
+    assert response.json()["spent"]["currency"] == "USD"
+    digest = client.get("/digest").json()
+    assert digest["expenses"]["currency"] == "USD"
+    digest_end = datetime.datetime.fromtimestamp(
+        digest["end"], tz=zoneinfo.ZoneInfo("America/New_York")
+    )
+    assert (digest_end.weekday(), digest_end.hour) == (6, 0)
+    digest = client.get("/digest", params={"period": "month"}).json()
+    digest_end = datetime.datetime.fromtimestamp(
+        digest["end"], tz=zoneinfo.ZoneInfo("America/New_York")
+    )
+    assert (digest_end.day, digest_end.hour) == (25, 0)
+    assert client.get("/digest", params={"target_currency": "GBP"}).json()["expenses"] == {
+        "amount": "0.00",
+        "currency": "GBP",
+    }
+
+
+def test_transaction_source(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    source = {"origin": "csv", "raw": "01.09.2024;BOOKSHOP BERLIN;-23,90;EUR"}
+    response = client.post(
+        "/transactions/bulk",
+        json={
+            "transactions": [
+                {
+                    "sum": {"amount": -23.9, "currency": "EUR"},
+                    "pool_id": pool_id,
+                    "description": "books",
+                    "source": source,
+                },
+                {
+                    "sum": {"amount": -5, "currency": "EUR"},
+                    "pool_id": pool_id,
+                    "description": "coffee",
+                },
+            ]
+        },
+    )
+    assert response.status_code == 200
+    imported_id, manual_id = [r["transaction"]["id"] for r in response.json()]
+
+    response = client.get(f"/transactions/{imported_id}/source")
+    assert response.status_code == 200
+    assert response.json() == source
+
+    # edits don't touch what the bank said
+    client.put(
+        f"/transactions/{imported_id}",
+        json={"description": "birthday present"},
+        headers={"If-Match": "0"},
+    )
+    assert client.get(f"/transactions/{imported_id}/source").json() == source
+
+    assert client.get(f"/transactions/{manual_id}/source").status_code == 404
+
+
+def test_transaction_splits(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    transaction = {
+        "sum": {"amount": -50, "currency": "EUR"},
+        "pool_id": pool_id,
+        "description": "supermarket",
+    }
+    for splits in (
+        [{"category": "food", "amount": -30}, {"category": "household", "amount": -10}],
+        [{"category": "food", "amount": -60}, {"category": "household", "amount": 10}],
+        [{"category": "food", "amount": -25}, {"category": "food", "amount": -25}],
+        [{"category": "", "amount": -50}],
+    ):
+        response = client.post("/transactions", json={**transaction, "splits": splits})
+        assert response.status_code == 422
+
+    splits = [{"category": "food", "amount": -35}, {"category": "household", "amount": -15}]
+    response = client.post("/transactions", json={**transaction, "splits": splits})
+    assert response.status_code == 200
+    transaction_id = response.json()["id"]
+    [stored] = client.get("/transactions").json()
+    assert stored["splits"] == [
+        {"category": "food", "amount": "-35"},
+        {"category": "household", "amount": "-15"},
+    ]
+
+    response = client.put(
+        f"/transactions/{transaction_id}",
+        json={"splits": [{"category": "food", "amount": -40}]},
+        headers={"If-Match": "0"},
+    )
+    assert response.status_code == 400
+    response = client.put(
+        f"/transactions/{transaction_id}",
+        json={"splits": [{"category": "food", "amount": -45}, {"category": "fun", "amount": -5}]},
+        headers={"If-Match": "0"},
+    )
+    assert response.status_code == 200
+    assert [s["category"] for s in client.get("/transactions").json()[0]["splits"]] == [
+        "food",
+        "fun",
+    ]
+    response = client.put(
+        f"/transactions/{transaction_id}", json={"splits": []}, headers={"If-Match": "1"}
+    )
+    assert response.status_code == 200
+    assert client.get("/transactions").json()[0]["splits"] == []
+    assert client.get("/transactions/no-such-id/source").status_code == 404
+
+
+def test_transaction_status(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    transaction_ids = []
+    for amount, status in ((-30, "pending"), (-20, "pending"), (-10, None)):
+        transaction = {
+            "sum": {"amount": amount, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "hotel",
+        }
+        if status is not None:
+            transaction["status"] = status
+        response = client.post("/transactions", json=transaction)
+        assert response.status_code == 200
+        transaction_ids.append(response.json()["id"])
+    hold_id, deposit_id, coffee_id = transaction_ids
+
+    def set_status(transaction_id: str, status: str) -> int:
+        response = client.put(
+            f"/transactions/{transaction_id}/status",
+            json={"status": status},
+            headers={"If-Match": "0"},
+        )
+        return response.status_code
+
+    response = client.get(f"/pools/{pool_id}/balance")
+    assert response.status_code == 200
+    assert response.json()["current"] == [{"amount": "40.00", "currency": "EUR"}]
+    assert response.json()["available"] == [{"amount": "90.00", "currency": "EUR"}]
+    assert set(response.json()["pending_transaction_ids"]) == {hold_id, deposit_id}
+
+    assert set_status(hold_id, "cleared") == 200
+    assert set_status(deposit_id, "void") == 200
+    response = client.get(f"/pools/{pool_id}/balance")
+    assert response.json()["current"] == [{"amount": "60.00", "currency": "EUR"}]
+    assert response.json()["available"] == [{"amount": "60.00", "currency": "EUR"}]
+    assert response.json()["pending_transaction_ids"] == []
+    response = client.get("/transactions", params={"status": "void"})
+    assert [t["id"] for t in response.json()] == [deposit_id]
+    response = client.get("/report", params={"start": "2020-01-01T00:00:00Z"})
+    assert response.status_code == 200
+    assert response.json()["spent"]["amount"] == "40.00"
+
+    for transaction_id in (hold_id, deposit_id, coffee_id):
+        assert set_status(transaction_id, "pending") == 409
+    assert set_status(coffee_id, "cleared") == 200
+    assert set_status(coffee_id, "settled") == 422
+    assert set_status("no-such-id", "void") == 404
+    assert client.get("/pools/no-such-id/balance").status_code == 404
+
+
+def test_scheduled_transactions(client: TestClient) -> None:
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    now = datetime.datetime.now(tz=datetime.UTC)
+    transaction_ids = []
+    for days, description in ((7, "rent"), (3, "insurance"), (-1, "coffee")):
+        response = client.post(
+            "/transactions",
+            json={
+                "timestamp": (now + datetime.timedelta(days=days)).timestamp(),
+                "sum": {"amount": -10, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": description,
+            },
+        )
+        assert response.status_code == 200
+        transaction_ids.append(response.json()["id"])
+    rent_id, insurance_id, coffee_id = transaction_ids
+
+    def set_status(transaction_id: str, status: str) -> int:
+        response = client.put(
+            f"/transactions/{transaction_id}/status",
+            json={"status": status},
+            headers={"If-Match": "0"},
+        )
+        return response.status_code
+
+    response = client.get(f"/pools/{pool_id}")
+    assert response.json()["balance"] == [{"amount": "90.00", "currency": "EUR"}]
+    response = client.get(f"/pools/{pool_id}/scheduled")
+    assert response.status_code == 200
+    assert [t["id"] for t in response.json()] == [insurance_id, rent_id]
+    assert {t["status"] for t in response.json()} == {"scheduled"}
+
+    assert set_status(coffee_id, "scheduled") == 400
+    assert set_status(insurance_id, "cleared") == 200
+    assert set_status(rent_id, "void") == 200
+    response = client.get(f"/pools/{pool_id}")
+    assert response.json()["balance"] == [{"amount": "80.00", "currency": "EUR"}]
+    assert client.get(f"/pools/{pool_id}/scheduled").json() == []
+    assert client.get("/pools/no-such-id/scheduled").status_code == 404
+
+
+def test_format_money(client: TestClient) -> None:
+    response = client.get("/format", params={"amount": "-1234.5"})
+    assert response.status_code == 200
+    assert response.json() == {
+        "sum": {"amount": "-1234.50", "currency": "EUR"},
+        "locale": "en",
+        "formatted": "-€1,234.50",
+    }
+
+    response = client.get("/format", params={"amount": "1500", "currency": "JPY", "locale": "ja"})
+    assert response.json()["formatted"] == "¥1,500"
+
+    client.put("/settings", json={"default_currency": "EUR", "locale": "de-AT"})
+    response = client.get("/format", params={"amount": "1234.5"})
+    assert response.json()["formatted"] == "1.234,50\u00a0€"
+    response = client.get("/report", params={"start": "2020-01-01T00:00:00Z", "points": 2})
+    assert response.json()["spent_formatted"] == "0,00\u00a0€"
+
+    assert client.get("/format", params={"amount": "1", "locale": "German"}).status_code == 422
+
+
+def test_month_close(client: TestClient) -> None:
+    pool_ids = []
+    for name in ("bank", "cash"):
+        response = client.post(
+            "/pools", json={"display_name": name, "balance": [{"amount": 100, "currency": "EUR"}]}
+        )
+        pool_ids.append(response.json()["id"])
+    bank_id, cash_id = pool_ids
+
+    def add_transaction(pool_id: str, day: int) -> int:
+        return client.post(
+            "/transactions",
+            json={
+                "timestamp": datetime.datetime(2024, 1, day, tzinfo=datetime.UTC).timestamp(),
+                "sum": {"amount": -10, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": "whatever",
+            },
+        ).status_code
+
+    assert add_transaction(bank_id, 10) == 200
+
+    response = client.get("/month-close/2024/1")
+    assert response.status_code == 200
+    assert response.json() == {
+        "month_close": {
+            "year": 2024,
+            "month": 1,
+            "ticked": [],
+            "status": "open",
+            "closed_at": None,
+            "report_snapshot_id": None,
+        },
+        "checklist": [
+            {"item": "drafts_reviewed", "done": True, "detail": None},
+            {"item": "reconciliation_done", "done": False, "detail": "Not reconciled: bank"},
+            {"item": "budgets_reviewed", "done": False, "detail": None},
+        ],
+        "can_close": False,
+    }
+    response = client.post("/month-close/2024/1/complete")
+    assert response.status_code == 400
+    assert response.json()["detail"] == (
+        "Checklist is not complete: reconciliation_done, budgets_reviewed"
+    )
+
+    response = client.post(
+        f"/pools/{bank_id}/reconciliations",
+        json={"start": "2024-01-01T00:00:00Z", "end": "2024-02-01T00:00:00Z"},
+    )
+    reconciliation_id = response.json()["id"]
+    client.post(f"/reconciliations/{reconciliation_id}/finish")
+    assert client.put("/month-close/2024/1/checklist/drafts_reviewed").status_code == 400
+    assert client.put("/month-close/2024/1/checklist/budgets_reviewed").status_code == 200
+    assert client.get("/month-close/2024/1").json()["can_close"] is True
+
+    response = client.post("/month-close/2024/1/complete")
+    assert response.status_code == 200
+    assert response.json()["status"] == "closed"
+    snapshot_id = response.json()["report_snapshot_id"]
+    assert [s["id"] for s in client.get("/report/snapshots").json()] == [snapshot_id]
+    assert [mc["year"] for mc in client.get("/month-close").json()] == [2024]
+
+    # the whole month is locked, not only the reconciled pool
+    assert add_transaction(cash_id, 20) == 409
+    assert client.post("/month-close/2024/1/complete").status_code == 409
+    assert client.put("/month-close/2024/1/checklist/budgets_reviewed").status_code == 409
+
+    now = datetime.datetime.now(tz=datetime.UTC)
+    assert client.post(f"/month-close/{now.year}/{now.month}/complete").status_code == 400
+    assert client.get("/month-close/2024/13").status_code == 404
+
+
+def test_currencies() -> None:
+    client = TestClient(
+        create_app(
+            storage=InmemoryStorage(),
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+            extra_currencies=CRYPTO_CURRENCIES,
+        )
+    )
+    currencies = {c["code"]: c for c in client.get("/currencies").json()}
+    assert currencies["EUR"] == {
+        "code": "EUR",
+        "name": "Euro",
+        "symbol": "€",
+        "exponent": 2,
+        "is_iso": True,
+    }
+    assert currencies["BTC"] == {
+        "code": "BTC",
+        "name": "Bitcoin",
+        "symbol": "₿",
+        "exponent": 8,
+        "is_iso": False,
+    }
+    assert currencies["JPY"]["exponent"] == 0
+
+    response = client.post(
+        "/pools",
+        json={"display_name": "wallet", "balance": [{"amount": "0.123456789", "currency": "BTC"}]},
+    )
+    assert response.status_code == 200
+    assert response.json()["balance"] == [{"amount": "0.12345679", "currency": "BTC"}]
+
+
+def test_live_updates() -> None:
+    with TestClient(
+        create_app(storage=InmemoryStorage(), auth=NoAuth(), exchange_rates=DumbExchangeRates())
+    ) as client:
+        response = client.post(
+            "/pools",
+            json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]},
+        )
+        pool_id = response.json()["id"]
+        ticket = client.post("/ws/tickets").json()["ticket"]
+
+        with client.websocket_connect(f"/ws?ticket={ticket}") as websocket:
+            response = client.post(
+                "/transactions",
+                json={
+                    "sum": {"amount": -30, "currency": "EUR"},
+                    "pool_id": pool_id,
+                    "description": "",
+                },
+            )
+            transaction_id = response.json()["id"]
+
+            event = websocket.receive_json()
+            assert event["type"] == "transaction_created"
+            assert event["user_id"] == "no-auth"
+            assert event["transaction"]["id"] == transaction_id
+            event = websocket.receive_json()
+            assert event["type"] == "pool_balance_changed"
+            assert event["balance"] == [{"amount": "70.00", "currency": "EUR"}]
+
+        # tickets are single use
+        with pytest.raises(WebSocketDisconnect):
+            with client.websocket_connect(f"/ws?ticket={ticket}") as websocket:
+                websocket.receive_json()
+
+    response = client.get("/events", headers={"Last-Event-ID": "abc"})
+    assert response.status_code == 400
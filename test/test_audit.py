@@ -0,0 +1,131 @@
+import asyncio
+from decimal import Decimal
+
+from fastapi.testclient import TestClient
+
+from api.app import create_app
+from api.audit import AuditedStorage
+from api.auth import NoAuth
+from api.exchange_rates import DumbExchangeRates
+from api.storage import InmemoryStorage
+from api.types.api import TransactionUpdate
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+
+def test_audited_storage() -> None:
+    storage = AuditedStorage(InmemoryStorage())
+
+    async def scenario() -> None:
+        pool = await storage.add_pool(
+            "user",
+            MoneyPool(
+                display_name="card", balance=[MoneySum(amount=Decimal(100), currency="EUR")]
+            ),
+        )
+        transaction = await storage.add_transaction(
+            "user",
+            Transaction(
+                sum=MoneySum(amount=Decimal(-10), currency="EUR"),
+                pool_id=pool.id,
+                description="lunch",
+            ),
+        )
+        assert await storage.update_transaction(
+            "user", transaction.id, TransactionUpdate(description="dinner")
+        )
+        assert await storage.delete_transaction("user", transaction.id)
+        assert not await storage.delete_transaction("user", transaction.id)
+
+        entries = await storage.load_audit_entries(
+            "user", entity_id=transaction.id, offset=0, count=10
+        )
+        assert [e.action for e in entries] == [
+            "delete_transaction",
+            "update_transaction",
+            "add_transaction",
+        ]
+        deleted, updated, added = entries
+        assert deleted.before is not None and deleted.after is None
+        assert updated.before is not None and updated.before["description"] == "lunch"
+        assert updated.after is not None and updated.after["description"] == "dinner"
+        assert added.before is None and added.after is not None
+
+        assert len(await storage.load_audit_entries("user", None, offset=0, count=10)) == 4
+        assert await storage.load_audit_entries("other", None, offset=0, count=10) == []
+
+    asyncio.run(scenario())
+
+
+def test_audit_api() -> None:
+    client = TestClient(
+        create_app(
+            storage=AuditedStorage(InmemoryStorage()),
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+        )
+    )
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+    response = client.put(
+        f"/pools/{pool_id}", json={"display_name": "debit card"}, headers={"If-Match": "0"}
+    )
+    assert response.status_code == 200
+
+    response = client.get("/audit", params={"entity_id": pool_id})
+    assert response.status_code == 200
+    entries = response.json()
+    assert [e["action"] for e in entries] == ["set_pool_attributes", "add_pool"]
+    assert entries[0]["before"]["display_name"] == "card"
+    assert entries[0]["after"]["display_name"] == "debit card"
+    assert entries[0]["request_id"] is not None
+
+
+def test_pool_redenomination_is_audited() -> None:
+    client = TestClient(
+        create_app(
+            storage=AuditedStorage(InmemoryStorage()),
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+        )
+    )
+    response = client.post(
+        "/pools",
+        json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    pool_id = response.json()["id"]
+    response = client.post(
+        "/transactions",
+        json={"sum": {"amount": -10, "currency": "EUR"}, "pool_id": pool_id, "description": "tea"},
+        params={"force": True},
+    )
+    transaction_id = response.json()["id"]
+
+    body = {"to_currency": "USD", "rate": 2, "convert_transactions": True}
+    assert client.post("/pools/missing/redenominate", json=body).status_code == 404
+    same_currency = {"to_currency": "EUR"}
+    assert client.post(f"/pools/{pool_id}/redenominate", json=same_currency).status_code == 400
+    response = client.post(f"/pools/{pool_id}/redenominate", json=body)
+    assert response.status_code == 200
+    redenomination = response.json()
+    assert redenomination["balance"] == [{"amount": "180.00", "currency": "USD"}]
+    [conversion] = redenomination["transactions"]
+    assert conversion["transaction_id"] == transaction_id
+    assert conversion["sum"] == {"amount": "-20.00", "currency": "USD"}
+    assert client.get(f"/pools/{pool_id}").json()["balance"] == redenomination["balance"]
+
+    entries = client.get("/audit", params={"entity_id": pool_id}).json()
+    assert [(e["action"], e["entity_type"]) for e in entries[:2]] == [
+        ("redenominate_pool", "pool"),
+        ("redenominate_pool", "redenomination"),
+    ]
+    assert entries[0]["before"]["balance"] == [{"amount": "90.00", "currency": "EUR"}]
+    assert entries[1]["after"]["rate"] == 2.0
+    [entry, *_] = client.get("/audit", params={"entity_id": transaction_id}).json()
+    assert entry["action"] == "redenominate_pool"
+    assert entry["after"]["original_amount"] == "-10.00"
@@ -1,4 +1,8 @@
+import asyncio
 import base64
+import time
+import urllib.parse
+from test.faulty_storage import FaultyStorage
 
 import pytest
 from cryptography.hazmat.primitives import hashes, serialization
@@ -6,9 +10,11 @@ from cryptography.hazmat.primitives.asymmetric import padding
 from cryptography.hazmat.primitives.asymmetric.rsa import RSAPrivateKey, generate_private_key
 from fastapi.testclient import TestClient
 
+from api import totp
 from api.app import create_app
-from api.auth import RSAAuth
+from api.auth import RSAAuth, TokenAuth, check_two_factor
 from api.exchange_rates import DumbExchangeRates
+from api.oidc import OidcConfig, OidcProvider
 from api.storage import InmemoryStorage
 
 
@@ -72,3 +78,181 @@ def test_rsa_auth(client_with_rsa_auth: tuple[bytes, TestClient]):
     resp = client.get("/pools", headers={"user-id": "Another user", "signature": signature})
     assert resp.status_code == 403
     assert resp.json() == {"detail": "Invalid signature"}
+
+
+def test_read_only_tokens() -> None:
+    storage = InmemoryStorage()
+    auth = TokenAuth(
+        server_tokens=["rw-token"],
+        auth_telegram_bot_token="123:fake",
+        storage=storage,
+        read_only_server_tokens=["ro-token"],
+    )
+    client = TestClient(create_app(storage=storage, auth=auth, exchange_rates=DumbExchangeRates()))
+    pool = {"display_name": "cash", "balance": [{"amount": 10, "currency": "EUR"}]}
+
+    read_only = {"token": "ro-token", "user-id": "user"}
+    assert client.get("/pools", headers=read_only).status_code == 200
+    resp = client.post("/pools", headers=read_only, json=pool)
+    assert resp.status_code == 403
+    assert resp.json() == {"detail": "Token is read-only"}
+    read_write = {"token": "rw-token", "user-id": "user"}
+    assert client.post("/pools", headers=read_write, json=pool).is_success
+
+    resp = client.post("/auth/tokens", headers=read_write)
+    assert resp.status_code == 200
+    assert resp.json()["scope"] == "read"
+    widget = {"token": resp.json()["token"]}
+    resp = client.get("/pools", headers=widget)
+    assert resp.status_code == 200
+    assert [p["display_name"] for p in resp.json()] == ["cash"]
+    assert client.post("/pools", headers=widget, json=pool).status_code == 403
+    resp = client.post("/auth/tokens", headers=widget, params={"scope": "read_write"})
+    assert resp.status_code == 403
+
+
+def test_auth_sessions() -> None:
+    storage = InmemoryStorage()
+    auth = TokenAuth(
+        server_tokens=["server-token"], auth_telegram_bot_token="123:fake", storage=storage
+    )
+    client = TestClient(create_app(storage=storage, auth=auth, exchange_rates=DumbExchangeRates()))
+    server = {"token": "server-token", "user-id": "user"}
+    tokens = {}
+    for device_name in ("laptop", "phone"):
+        params = {"scope": "read_write", "device_name": device_name}
+        resp = client.post("/auth/tokens", headers=server, params=params)
+        tokens[device_name] = resp.json()["token"]
+    client.post("/auth/tokens", headers={"token": "server-token", "user-id": "someone else"})
+
+    assert client.get("/pools", headers={"token": tokens["phone"]}).status_code == 200
+    resp = client.get("/auth/sessions", headers={"token": tokens["laptop"]})
+    assert resp.status_code == 200
+    sessions = resp.json()
+    assert [(s["device_name"], s["current"]) for s in sessions] == [
+        ("laptop", True),
+        ("phone", False),
+    ]
+    assert sessions[0]["last_used_at"] is not None
+
+    phone_session_id = sessions[1]["id"]
+    resp = client.delete(f"/auth/sessions/{phone_session_id}", headers={"token": tokens["laptop"]})
+    assert resp.status_code == 200
+    assert client.get("/pools", headers={"token": tokens["phone"]}).status_code == 403
+    resp = client.delete(f"/auth/sessions/{phone_session_id}", headers={"token": tokens["laptop"]})
+    assert resp.status_code == 404
+
+
+def test_two_factor_setup() -> None:
+    storage = InmemoryStorage()
+    auth = TokenAuth(
+        server_tokens=["server-token"],
+        auth_telegram_bot_token="123:fake",
+        storage=storage,
+        read_only_server_tokens=["ro-token"],
+    )
+    client = TestClient(create_app(storage=storage, auth=auth, exchange_rates=DumbExchangeRates()))
+    headers = {"token": "server-token", "user-id": "user"}
+
+    resp = client.post("/auth/2fa/setup", headers=headers)
+    assert resp.status_code == 200
+    setup = resp.json()
+    assert setup["otpauth_uri"].startswith("otpauth://totp/")
+    assert len(setup["recovery_codes"]) == 10
+
+    resp = client.post("/auth/2fa/confirm", headers=headers, params={"code": "000000"})
+    assert resp.status_code == 400
+    code = totp.code_at(setup["secret"], totp.time_step(time.time()))
+    read_only = {"token": "ro-token", "user-id": "user"}
+    resp = client.post("/auth/2fa/confirm", headers=read_only, params={"code": code})
+    assert resp.status_code == 403
+    resp = client.post("/auth/2fa/confirm", headers=headers, params={"code": code})
+    assert resp.status_code == 200
+    assert client.post("/auth/2fa/setup", headers=headers).status_code == 409
+
+    two_factor = asyncio.run(storage.load_two_factor("user"))
+    assert two_factor is not None and two_factor.confirmed
+    assert not check_two_factor(two_factor, code)  # already used
+    recovery_code = setup["recovery_codes"][0]
+    assert check_two_factor(two_factor, recovery_code)
+    assert not check_two_factor(two_factor, recovery_code)
+
+    recovery_code = setup["recovery_codes"][1]
+    resp = client.delete("/auth/2fa", headers=headers, params={"code": recovery_code})
+    assert resp.status_code == 200
+    assert client.post("/auth/2fa/setup", headers=headers).status_code == 200
+
+
+def test_two_factor_login_after_restart() -> None:
+    storage = FaultyStorage()
+    auth = TokenAuth(
+        server_tokens=["server-token"], auth_telegram_bot_token="123:fake", storage=storage
+    )
+    client = TestClient(create_app(storage=storage, auth=auth, exchange_rates=DumbExchangeRates()))
+    headers = {"token": "server-token", "user-id": "user"}
+    setup = client.post("/auth/2fa/setup", headers=headers).json()
+    code = totp.code_at(setup["secret"], totp.time_step(time.time()))
+    assert client.post("/auth/2fa/confirm", headers=headers, params={"code": code}).is_success
+
+    restarted = TokenAuth(
+        server_tokens=["server-token"], auth_telegram_bot_token="123:fake", storage=storage
+    )
+    client = TestClient(
+        create_app(storage=storage, auth=restarted, exchange_rates=DumbExchangeRates())
+    )
+
+    async def log_in_through_bot() -> str:
+        start_param = restarted._start_login(device_name=None)
+        assert restarted._finish_login(start_param, "user")
+        return start_param
+
+    start_param = asyncio.run(log_in_through_bot())
+    resp = client.get("/auth/access-token", params={"start_param": start_param})
+    assert resp.status_code == 401
+    assert resp.json() == {"detail": "Two-factor code required"}
+
+    storage.fail("load_two_factor")
+    resp = client.get("/auth/access-token", params={"start_param": start_param, "code": code})
+    assert resp.status_code == 503
+    storage.heal()
+    resp = client.get("/auth/access-token", params={"start_param": start_param, "code": code})
+    assert resp.status_code == 403  # already used to confirm
+    params = {"start_param": start_param, "code": setup["recovery_codes"][0]}
+    resp = client.get("/auth/access-token", params=params)
+    assert resp.status_code == 200
+    assert client.get("/pools", headers={"token": resp.text}).status_code == 200
+    two_factor = asyncio.run(storage.load_two_factor("user"))
+    assert two_factor is not None and len(two_factor.recovery_code_hashes) == 9
+
+
+def test_oidc_login_redirect() -> None:
+    provider = OidcProvider(
+        name="corp",
+        client_id="client",
+        client_secret="secret",
+        authorization_endpoint="https://idp.example.com/authorize",
+    )
+    auth = TokenAuth(
+        server_tokens=[],
+        auth_telegram_bot_token="123:fake",
+        storage=InmemoryStorage(),
+        oidc=OidcConfig(
+            providers=[provider],
+            redirect_uri="https://api.example.com/auth/oidc/callback",
+            post_login_url="https://app.example.com/login",
+        ),
+    )
+    client = TestClient(
+        create_app(storage=InmemoryStorage(), auth=auth, exchange_rates=DumbExchangeRates())
+    )
+    resp = client.get("/auth/oidc/login", params={"provider": "other"}, follow_redirects=False)
+    assert resp.status_code == 404
+    resp = client.get("/auth/oidc/login", params={"provider": "corp"}, follow_redirects=False)
+    assert resp.status_code == 307
+    assert resp.headers["location"].startswith("https://idp.example.com/authorize?")
+    state = urllib.parse.parse_qs(urllib.parse.urlparse(resp.headers["location"]).query)["state"]
+
+    resp = client.get("/auth/oidc/callback", params={"state": "unknown", "code": "code"})
+    assert resp.status_code == 404
+    resp = client.get("/auth/oidc/callback", params={"state": state[0], "error": "denied"})
+    assert resp.status_code == 400
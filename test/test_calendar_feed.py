@@ -0,0 +1,126 @@
+import datetime
+import zoneinfo
+from decimal import Decimal
+
+from fastapi.testclient import TestClient
+
+from api.calendar_feed import (
+    CalendarEvent,
+    allowance_events,
+    fold,
+    month_end_events,
+    recurring_events,
+    render_calendar,
+)
+from api.iso4217 import CURRENCIES
+from api.types.allowance import StoredAllowance
+from api.types.api import RecurringSuggestion
+from api.types.money_sum import MoneySum
+
+NOW = datetime.datetime(2024, 9, 20, 12, tzinfo=datetime.UTC)
+TOKYO = zoneinfo.ZoneInfo("Asia/Tokyo")
+
+
+def test_events() -> None:
+    eur = CURRENCIES["EUR"]
+    subscription = RecurringSuggestion(
+        pool_id="pool",
+        description="netflix",
+        sum=MoneySum(amount=Decimal(-12), currency=eur),
+        interval_days=30,
+        occurrences=4,
+        last_at=NOW - datetime.timedelta(days=25),
+        next_expected_at=NOW + datetime.timedelta(days=5),
+        transaction_ids=["t4", "t3", "t2", "t1"],
+    )
+    events = recurring_events([subscription], NOW, TOKYO, "en")
+    assert [e.date for e in events] == [
+        datetime.date(2024, 9, 25),
+        datetime.date(2024, 10, 25),
+        datetime.date(2024, 11, 24),
+    ]
+    assert events[0].summary == "netflix: -€12.00"
+    assert events[0].uid == "recurring-t4-20240925"
+
+    allowance = StoredAllowance(
+        id="a1",
+        pool_id="kid",
+        source_pool_id="parent",
+        child_name="Sam",
+        weekly_amount=MoneySum(amount=Decimal(5), currency=eur),
+        next_payment_at=NOW + datetime.timedelta(days=1),
+    )
+    events = allowance_events([allowance], NOW, datetime.UTC, "en")
+    assert len(events) == 13
+    assert events[0].summary == "Allowance for Sam: €5.00"
+
+    events = month_end_events(NOW, datetime.UTC)
+    assert [(e.date, e.summary) for e in events] == [
+        (datetime.date(2024, 10, 1), "Close September 2024"),
+        (datetime.date(2024, 11, 1), "Close October 2024"),
+        (datetime.date(2024, 12, 1), "Close November 2024"),
+    ]
+
+
+def test_render_calendar() -> None:
+    event = CalendarEvent(
+        uid="scheduled-1",
+        date=datetime.date(2024, 10, 1),
+        summary="rent, flat; october: €900.00",
+        description="x" * 100,
+    )
+    text = render_calendar([event], NOW)
+    lines = text.split("\r\n")
+    assert lines[0] == "BEGIN:VCALENDAR"
+    assert lines[-2:] == ["END:VCALENDAR", ""]
+    assert "UID:scheduled-1@tiny-expense-tracker" in lines
+    assert "DTSTAMP:20240920T120000Z" in lines
+    assert "DTSTART;VALUE=DATE:20241001" in lines
+    assert "DTEND;VALUE=DATE:20241002" in lines
+    assert "SUMMARY:rent\\, flat\\; october: €900.00" in lines
+    assert all(len(line.encode("utf-8")) <= 75 for line in lines)
+    description = [line for line in lines if line.startswith("DESCRIPTION:")][0]
+    assert description + lines[lines.index(description) + 1][1:] == "DESCRIPTION:" + "x" * 100
+
+    assert fold("short") == ["short"]
+    assert [len(line.encode("utf-8")) for line in fold("€" * 30)] == [75, 16]
+
+
+def test_calendar_api(client: TestClient) -> None:
+    assert client.get("/calendar/token").status_code == 404
+    assert client.get("/calendar.ics", params={"token": "guess"}).status_code == 401
+
+    response = client.post("/calendar/token")
+    assert response.status_code == 200
+    token = response.json()["token"]
+    assert response.json()["feed_path"] == f"/calendar.ics?token={token}"
+    assert client.get("/calendar/token").json()["token"] == token
+
+    pool_id = client.post(
+        "/pools", json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]}
+    ).json()["id"]
+    in_a_week = datetime.datetime.now(tz=datetime.UTC) + datetime.timedelta(days=7)
+    response = client.post(
+        "/transactions",
+        json={
+            "timestamp": in_a_week.isoformat(),
+            "sum": {"amount": -900, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "rent",  # scheduled, being future-dated
+        },
+        params={"force": True},
+    )
+    assert response.status_code == 200
+
+    response = client.get("/calendar.ics", params={"token": token})
+    assert response.status_code == 200
+    assert response.headers["Content-Type"].startswith("text/calendar")
+    assert "SUMMARY:rent: -€900.00" in response.text.split("\r\n")
+    assert f"DTSTART;VALUE=DATE:{in_a_week:%Y%m%d}" in response.text
+
+    old_token = token
+    token = client.post("/calendar/token").json()["token"]
+    assert client.get("/calendar.ics", params={"token": old_token}).status_code == 401
+    assert client.get("/calendar.ics", params={"token": token}).status_code == 200
+    assert client.delete("/calendar/token").status_code == 200
+    assert client.get("/calendar.ics", params={"token": token}).status_code == 401
@@ -0,0 +1,88 @@
+import datetime
+from decimal import Decimal
+
+import pydantic
+import pytest
+
+from api.challenges import compute_progress
+from api.iso4217 import CURRENCIES
+from api.types.challenge import ChallengeKind, StoredChallenge
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction
+
+EUR = CURRENCIES["EUR"]
+START = datetime.datetime(year=2024, month=9, day=2, tzinfo=datetime.UTC)
+
+
+def transaction(amount: str, days: float, pool_id: str = "card") -> StoredTransaction:
+    return StoredTransaction(
+        id=f"{amount}-{days}",
+        sum=MoneySum(amount=Decimal(amount), currency=EUR),
+        pool_id=pool_id,
+        description="",
+        timestamp=START + datetime.timedelta(days=days),
+    )
+
+
+def test_no_spend_days() -> None:
+    challenge = StoredChallenge(
+        id="c",
+        kind=ChallengeKind.NO_SPEND_DAYS,
+        name="no spend",
+        start=START,
+        end=START + datetime.timedelta(days=30),
+        target_days=3,
+    )
+    transactions = [transaction("-5", 0.5), transaction("-5", 1.5), transaction("100", 2.5)]
+
+    progress = compute_progress(challenge, transactions, now=START + datetime.timedelta(days=3))
+    assert (progress.current, progress.target, progress.completed) == (2, 3, False)
+
+    progress = compute_progress(challenge, transactions, now=START + datetime.timedelta(days=4))
+    assert progress.completed
+
+
+def test_fifty_two_weeks() -> None:
+    challenge = StoredChallenge(
+        id="c",
+        kind=ChallengeKind.FIFTY_TWO_WEEKS,
+        name="52 weeks",
+        start=START,
+        end=START + datetime.timedelta(weeks=52),
+        pool_id="savings",
+        weekly_step=MoneySum(amount=Decimal(1), currency=EUR),
+    )
+    transactions = [
+        transaction("1", 1, pool_id="savings"),
+        transaction("2", 8, pool_id="savings"),
+        transaction("50", 9),
+    ]
+    progress = compute_progress(challenge, transactions, now=START + datetime.timedelta(days=10))
+    assert (progress.current, progress.target, progress.completed) == (3, 1378, False)
+
+
+def test_round_up_sprint() -> None:
+    challenge = StoredChallenge(
+        id="c",
+        kind=ChallengeKind.ROUND_UP_SPRINT,
+        name="round ups",
+        start=START,
+        end=START + datetime.timedelta(days=14),
+        round_to=Decimal(1),
+        target=MoneySum(amount=Decimal(1), currency=EUR),
+    )
+    transactions = [transaction("-4.30", 1), transaction("-2.60", 2)]
+    progress = compute_progress(challenge, transactions, now=START + datetime.timedelta(days=3))
+    assert progress.current == pytest.approx(1.1)
+    assert progress.completed
+
+
+def test_challenge_parameters_required() -> None:
+    with pytest.raises(pydantic.ValidationError):
+        StoredChallenge(
+            id="c",
+            kind=ChallengeKind.ROUND_UP_SPRINT,
+            name="round ups",
+            start=START,
+            end=START + datetime.timedelta(days=14),
+        )
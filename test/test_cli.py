@@ -0,0 +1,73 @@
+import asyncio
+import json
+import os
+from pathlib import Path
+
+import pytest
+
+from api.storage import InmemoryStorage
+from api.types.currency import parse_currency
+from cli import (
+    CommandError,
+    Listener,
+    create_user,
+    export_user,
+    listener_from_args,
+    make_service,
+    parse_args,
+)
+
+
+def test_create_and_export_user() -> None:
+    service = make_service(InmemoryStorage())
+
+    async def scenario() -> None:
+        pool = await create_user(service, "user", parse_currency("USD"), "en-US", "wallet")
+        settings = await service.storage.load_user_settings("user")
+        assert settings.default_currency.code == "USD"
+        assert settings.default_pool_id == pool.id
+
+        with pytest.raises(CommandError):
+            await create_user(service, "user", parse_currency("EUR"), "en", "cash")
+
+        export = json.loads(await export_user(service, "user", descriptions=False))
+        assert export["user_id"] == "user"
+        assert [p["display_name"] for p in export["pools"]] == ["wallet"]
+
+    asyncio.run(scenario())
+
+
+def test_parse_args() -> None:
+    args = parse_args(["create-user", "--user", "user", "--currency", "gel"])
+    assert args.command == "create-user"
+    assert args.currency.code == "GEL"
+    assert args.pool_name == "cash"
+    with pytest.raises(SystemExit):
+        parse_args(["create-user", "--user", "user", "--currency", "XYZ"])
+
+    args = parse_args(["serve", "--tls-cert", "cert.pem", "--tls-key", "key.pem"])
+    assert (args.tls_cert.name, args.tls_key.name) == ("cert.pem", "key.pem")
+    with pytest.raises(SystemExit):
+        parse_args(["serve", "--tls-cert", "cert.pem"])
+
+    args = parse_args(["tui", "--url", "https://tet.example.com", "--token", "t"])
+    assert (args.url, args.token, args.user) == ("https://tet.example.com", "t", None)
+    assert parse_args(["tui", "--user", "user"]).url is None
+    with pytest.raises(SystemExit):
+        parse_args(["tui"])
+
+
+def test_listener_from_args() -> None:
+    args = parse_args(["serve", "--port", "9000"])
+    assert listener_from_args(args, {}) == Listener(host="127.0.0.1", port=9000)
+    assert str(listener_from_args(args, {})) == "127.0.0.1:9000"
+
+    listener = listener_from_args(args, {"UNIX_SOCKET": "/run/tet.sock"})
+    assert listener == Listener(uds=Path("/run/tet.sock"))
+    assert str(listener) == "unix:/run/tet.sock"
+    args = parse_args(["serve", "--uds", "/tmp/tet.sock"])
+    assert listener_from_args(args, {"UNIX_SOCKET": "/run/tet.sock"}).uds == Path("/tmp/tet.sock")
+
+    socket_activation = {"LISTEN_PID": str(os.getpid()), "LISTEN_FDS": "1"}
+    assert listener_from_args(args, socket_activation) == Listener(fd=3)
+    assert listener_from_args(args, {**socket_activation, "LISTEN_PID": "1"}).fd is None
@@ -0,0 +1,82 @@
+import asyncio
+import datetime
+from test.utils import eur
+
+import httpx
+import pytest
+
+from api.app import create_app
+from api.auth import NoAuth
+from api.client import ApiError, TetClient
+from api.exchange_rates import DumbExchangeRates
+from api.storage import InmemoryStorage
+from api.types.api import (
+    CreatedTransactionResponse,
+    CreatePoolRequestBody,
+    MoneyPoolAttributesUpdate,
+    TransactionUpdate,
+    TransferMoneyRequestBody,
+)
+from api.types.settings import UserSettings
+from api.types.transaction import Transaction, TransactionOrder
+
+
+def make_client() -> TetClient:
+    app = create_app(
+        storage=InmemoryStorage(), auth=NoAuth(), exchange_rates=DumbExchangeRates()
+    )
+    return TetClient("http://test", transport=httpx.ASGITransport(app=app))
+
+
+def test_client() -> None:
+    async def scenario() -> None:
+        async with make_client() as client:
+            assert await client.get_pools() == []
+            card = await client.create_pool(
+                CreatePoolRequestBody(display_name="card", balance=[eur(100)])
+            )
+            cash = await client.create_pool(
+                CreatePoolRequestBody(display_name="cash", balance=[eur(0)])
+            )
+            await client.update_pool(
+                card.id, MoneyPoolAttributesUpdate(display_name="debit"), expected_version=0
+            )
+            assert (await client.get_pool(card.id)).display_name == "debit"
+            with pytest.raises(ApiError) as error:
+                await client.update_pool(
+                    card.id, MoneyPoolAttributesUpdate(group="daily"), expected_version=0
+                )
+            assert error.value.status_code == 409
+
+            created = await client.add_transaction(
+                Transaction(
+                    sum=eur(-12), pool_id=card.id, description="lunch", tags=["food"]
+                )
+            )
+            assert isinstance(created, CreatedTransactionResponse)
+            await client.update_transaction(
+                created.id, TransactionUpdate(description="dinner"), expected_version=0
+            )
+            await client.transfer(
+                TransferMoneyRequestBody(
+                    from_pool=card.id, to_pool=cash.id, sum=eur(30), description=""
+                )
+            )
+            [first, *_] = await client.get_transactions(order=TransactionOrder.OLDEST)
+            assert (first.id, first.description, first.version) == (created.id, "dinner", 1)
+            assert [p.balance for p in await client.get_pools()] == [[eur(58)], [eur(30)]]
+
+            await client.delete_transaction(created.id)
+            with pytest.raises(ApiError) as error:
+                await client.delete_transaction(created.id)
+            assert (error.value.status_code, error.value.detail) == (404, "No such transaction")
+
+            await client.save_settings(UserSettings(default_pool_id=cash.id))
+            assert (await client.get_settings()).default_pool_id == cash.id
+
+            report = await client.get_report(
+                start=datetime.datetime.now(tz=datetime.UTC) - datetime.timedelta(days=1)
+            )
+            assert report.spent == eur(0)
+
+    asyncio.run(scenario())
@@ -0,0 +1,77 @@
+import brotli
+from fastapi.testclient import TestClient
+
+from api.app import create_app
+from api.auth import NoAuth
+from api.compression import (
+    CompressionAlgorithm,
+    CompressionConfig,
+    compression_config_from_env,
+    negotiate,
+)
+from api.exchange_rates import DumbExchangeRates
+from api.storage import InmemoryStorage
+
+ALL = [CompressionAlgorithm.ZSTD, CompressionAlgorithm.BROTLI, CompressionAlgorithm.GZIP]
+
+
+def test_compression_config_from_env() -> None:
+    assert compression_config_from_env({}) is None
+    config = compression_config_from_env(
+        {"COMPRESSION_ALGORITHMS": "br, gzip", "COMPRESSION_MIN_SIZE": "256"}
+    )
+    assert config == CompressionConfig(
+        algorithms=[CompressionAlgorithm.BROTLI, CompressionAlgorithm.GZIP], min_size=256
+    )
+
+
+def test_negotiate() -> None:
+    assert negotiate("", ALL) is None
+    assert negotiate("identity", ALL) is None
+    assert negotiate("gzip, br", ALL) == CompressionAlgorithm.BROTLI
+    assert negotiate("gzip, br;q=0", ALL) == CompressionAlgorithm.GZIP
+    assert negotiate("GZIP;q=0.5", ALL) == CompressionAlgorithm.GZIP
+    assert negotiate("*", ALL) == CompressionAlgorithm.ZSTD
+    assert negotiate("*;q=0, gzip", ALL) == CompressionAlgorithm.GZIP
+    assert negotiate("br", [CompressionAlgorithm.GZIP]) is None
+
+
+def test_compressed_responses() -> None:
+    app = create_app(
+        storage=InmemoryStorage(),
+        auth=NoAuth(),
+        exchange_rates=DumbExchangeRates(),
+        compression=CompressionConfig(algorithms=ALL, min_size=512),
+    )
+    client = TestClient(app)
+    response = client.post(
+        "/pools", json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]}
+    )
+    pool_id = response.json()["id"]
+    for idx in range(20):
+        transaction = {
+            "sum": {"amount": -1 - idx, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": f"coffee #{idx}",
+        }
+        response = client.post("/transactions", json=transaction, params={"force": True})
+        assert response.status_code == 200
+
+    small = client.get("/pools", headers={"Accept-Encoding": "gzip"})
+    assert "Content-Encoding" not in small.headers
+
+    plain = client.get("/transactions", headers={"Accept-Encoding": "identity"})
+    assert "Content-Encoding" not in plain.headers
+    assert len(plain.content) > 512
+
+    response = client.get("/transactions", headers={"Accept-Encoding": "gzip"})
+    assert response.headers["Content-Encoding"] == "gzip"
+    assert "Accept-Encoding" in response.headers["Vary"]
+    assert response.json() == plain.json()
+
+    # the client decodes the body itself, checking the raw stream instead
+    with client.stream("GET", "/transactions", headers={"Accept-Encoding": "br"}) as response:
+        assert response.headers["Content-Encoding"] == "br"
+        raw = b"".join(response.iter_raw())
+    assert brotli.decompress(raw) == plain.content
+    assert len(raw) < len(plain.content)
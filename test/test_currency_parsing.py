@@ -1,9 +1,12 @@
 from decimal import Decimal
 from typing import Any
 
+import pydantic
 import pytest
 
 from api.iso4217 import CURRENCIES
+from api.types.currency import CUSTOM_CURRENCIES, all_currencies, register_currencies
+from api.types.currency_iso4217 import CurrencyISO4217
 from api.types.money_sum import MoneySum
 
 
@@ -28,7 +31,56 @@ from api.types.money_sum import MoneySum
             MoneySum(amount=Decimal("3"), currency=CURRENCIES["VND"]),
             id="rounding appropriate to the currency",
         ),
+        pytest.param(
+            {"amount": " 14.30 ", "currency": "EUR"},
+            MoneySum(amount=Decimal("14.30"), currency=CURRENCIES["EUR"]),
+            id="amount as string",
+        ),
+        pytest.param(
+            {"amount_minor": 1430, "currency": "EUR"},
+            MoneySum(amount=Decimal("14.30"), currency=CURRENCIES["EUR"]),
+            id="amount in minor units",
+        ),
+        pytest.param(
+            {"amount_minor": 1430, "currency": "JPY"},
+            MoneySum(amount=Decimal("1430"), currency=CURRENCIES["JPY"]),
+            id="amount in minor units for currency without them",
+        ),
+        pytest.param(
+            {"amount": 2.675, "currency": "USD"},
+            MoneySum(amount=Decimal("2.68"), currency=CURRENCIES["USD"]),
+            id="no float artifacts",
+        ),
     ],
 )
 def test_sum_parsing(raw: dict[str, Any], expected_money_sum: MoneySum):
     assert MoneySum.model_validate(raw) == expected_money_sum
+
+
+@pytest.mark.parametrize(
+    "raw",
+    [
+        pytest.param({"amount": 1, "amount_minor": 100, "currency": "EUR"}),
+        pytest.param({"amount_minor": 14.3, "currency": "EUR"}),
+        pytest.param({"amount_minor": "1430", "currency": "EUR"}),
+    ],
+)
+def test_sum_parsing_errors(raw: dict[str, Any]):
+    with pytest.raises(pydantic.ValidationError):
+        MoneySum.model_validate(raw)
+
+
+def test_custom_currencies() -> None:
+    token = CurrencyISO4217(code="TEST", numeric_code=0, name="Token", entities=[], precision=4)
+    raw = {"amount": "0.123456", "currency": "test"}
+    with pytest.raises(pydantic.ValidationError):
+        MoneySum.model_validate(raw)
+    with pytest.raises(ValueError):
+        register_currencies([CurrencyISO4217(**{**token.__dict__, "code": "EUR"})])
+
+    register_currencies([token])
+    try:
+        assert MoneySum.model_validate(raw) == MoneySum(amount=Decimal("0.1235"), currency=token)
+        assert token in all_currencies()
+    finally:
+        CUSTOM_CURRENCIES.pop(token.code)
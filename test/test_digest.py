@@ -0,0 +1,115 @@
+import asyncio
+import datetime
+import zoneinfo
+from decimal import Decimal
+
+from api.digest import build_digest, digest_end, digest_start, digest_title, render_digest_text
+from api.exchange_rates import DumbExchangeRates
+from api.iso4217 import CURRENCIES
+from api.types.digest import DigestPeriod
+from api.types.money_sum import MoneySum
+from api.types.settings import ReportingPeriod, Weekday
+from api.types.transaction import StoredTransaction
+
+
+def test_digest() -> None:
+    eur = CURRENCIES["EUR"]
+    end = datetime.datetime(year=2024, month=9, day=8, tzinfo=datetime.UTC)
+
+    def transaction(amount: float, days_ago: int, tags: list[str]) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{days_ago}",
+            sum=MoneySum(amount=Decimal(amount), currency=eur),
+            pool_id="pool",
+            description="",
+            timestamp=end - datetime.timedelta(days=days_ago),
+            amount_eur=amount,
+            tags=tags,
+        )
+
+    transactions = [
+        transaction(-40, 10, ["food"]),
+        transaction(-50, 1, ["food"]),
+        transaction(-30, 2, ["fun"]),
+        transaction(-20, 3, ["fun"]),
+        transaction(200, 4, ["salary"]),
+    ]
+
+    digest = asyncio.run(
+        build_digest(
+            transactions,
+            exchange_rates=DumbExchangeRates(),
+            period=DigestPeriod.WEEK,
+            end=end,
+            target_currency=eur,
+        )
+    )
+
+    assert digest.income.amount == Decimal(200)
+    assert digest.expenses.amount == Decimal(100)
+    assert digest.transaction_count == 4
+    assert render_digest_text(digest) == "\n".join(
+        [
+            "Weekly digest, 2024-09-01 - 2024-09-08",
+            "",
+            "Income: 200.00 EUR",
+            "Expenses: 100.00 EUR",
+            "Transactions: 4",
+            "",
+            "Top categories:",
+            "  food: 50.00 EUR (50%), +25% vs previous week",
+            "  fun: 50.00 EUR (50%)",
+        ]
+    )
+
+
+def test_digest_end() -> None:
+    # wednesday
+    now = datetime.datetime(year=2024, month=9, day=11, hour=15, tzinfo=datetime.UTC)
+
+    assert digest_end(DigestPeriod.DAY, now, Weekday.MONDAY) == now
+    assert digest_end(DigestPeriod.WEEK, now, Weekday.MONDAY) == datetime.datetime(
+        year=2024, month=9, day=9, tzinfo=datetime.UTC
+    )
+    assert digest_end(DigestPeriod.WEEK, now, Weekday.WEDNESDAY) == datetime.datetime(
+        year=2024, month=9, day=11, tzinfo=datetime.UTC
+    )
+    assert digest_end(DigestPeriod.WEEK, now, Weekday.THURSDAY) == datetime.datetime(
+        year=2024, month=9, day=5, tzinfo=datetime.UTC
+    )
+    # already thursday in Tokyo
+    tokyo = zoneinfo.ZoneInfo("Asia/Tokyo")
+    assert digest_end(DigestPeriod.WEEK, now, Weekday.THURSDAY, tokyo) == datetime.datetime(
+        year=2024, month=9, day=12, tzinfo=tokyo
+    )
+    assert digest_end(DigestPeriod.WEEK, now, Weekday.MONDAY, tokyo) == datetime.datetime(
+        year=2024, month=9, day=9, tzinfo=tokyo
+    )
+
+
+def test_monthly_digest_period() -> None:
+    now = datetime.datetime(year=2024, month=9, day=11, hour=15, tzinfo=datetime.UTC)
+    payday = ReportingPeriod(start_day=25)
+
+    end = digest_end(DigestPeriod.MONTH, now, Weekday.MONDAY)
+    assert end == datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    assert digest_start(DigestPeriod.MONTH, end) == datetime.datetime(
+        year=2024, month=8, day=1, tzinfo=datetime.UTC
+    )
+
+    end = digest_end(DigestPeriod.MONTH, now, Weekday.MONDAY, reporting_period=payday)
+    assert end == datetime.datetime(year=2024, month=8, day=25, tzinfo=datetime.UTC)
+    start = digest_start(DigestPeriod.MONTH, end, reporting_period=payday)
+    assert start == datetime.datetime(year=2024, month=7, day=25, tzinfo=datetime.UTC)
+
+    digest = asyncio.run(
+        build_digest(
+            [],
+            exchange_rates=DumbExchangeRates(),
+            period=DigestPeriod.MONTH,
+            end=end,
+            target_currency=CURRENCIES["EUR"],
+            start=start,
+        )
+    )
+    assert digest_title(digest) == "Monthly digest, 2024-07-25 - 2024-08-25"
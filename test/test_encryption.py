@@ -0,0 +1,99 @@
+import asyncio
+import datetime
+from decimal import Decimal
+
+from api.audit import AuditedStorage
+from api.encryption import EncryptedStorage, UserKeys
+from api.privacy import MASKED_DESCRIPTION
+from api.storage import InmemoryStorage, TransactionOrder
+from api.types.api import TransactionUpdate
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.note import MAX_NOTE_LENGTH, PoolNote
+from api.types.operation import Operation, OperationKind
+from api.types.sensitive import AT_REST_PREFIX
+from api.types.suggestion import Suggestion, SuggestionField
+from api.types.transaction import Transaction, TransactionSource
+
+
+def test_user_keys() -> None:
+    keys = UserKeys(master_key=b"master")
+    encrypted = keys.encrypt("alice", "rent")
+    assert encrypted.startswith(AT_REST_PREFIX)
+    assert keys.encrypt("alice", encrypted) == encrypted
+    assert keys.decrypt("alice", encrypted) == "rent"
+    assert keys.decrypt("bob", encrypted) == MASKED_DESCRIPTION
+    assert keys.decrypt("alice", "stored before") == "stored before"
+    assert keys.encrypt("alice", "") == ""
+    assert UserKeys(master_key=b"master").decrypt("alice", encrypted) == "rent"
+    assert UserKeys(master_key=b"other").decrypt("alice", encrypted) == MASKED_DESCRIPTION
+
+
+def test_encrypted_storage() -> None:
+    inner = InmemoryStorage()
+    storage = EncryptedStorage(AuditedStorage(inner), UserKeys(master_key=b"master"))
+
+    async def scenario() -> None:
+        pool = await storage.add_pool(
+            "user",
+            MoneyPool(display_name="card", balance=[MoneySum(amount=Decimal(0), currency="EUR")]),
+        )
+        transaction = Transaction(
+            sum=MoneySum(amount=Decimal(-10), currency="EUR"),
+            pool_id=pool.id,
+            description="pharmacy",
+            source=TransactionSource(origin="csv", raw="2024-01-01;pharmacy;-10"),
+        )
+        stored = await storage.add_transaction("user", transaction)
+        assert stored.description == "pharmacy"
+        assert transaction.description == "pharmacy"  # the argument is not modified
+
+        [raw] = inner._user_transactions["user"]
+        assert raw.description.startswith(AT_REST_PREFIX)
+        assert raw.source is not None and raw.source.raw.startswith(AT_REST_PREFIX)
+        loaded = await storage.load_transaction("user", stored.id)
+        assert loaded is not None
+        assert loaded.description == "pharmacy"
+        assert loaded.source is not None and loaded.source.raw == "2024-01-01;pharmacy;-10"
+
+        await storage.update_transaction(
+            "user", stored.id, TransactionUpdate(description="doctor")
+        )
+        assert inner._user_transactions["user"][0].description.startswith(AT_REST_PREFIX)
+        [loaded] = await storage.load_transactions("user", None, TransactionOrder.LATEST, 0, 10)
+        assert loaded.description == "doctor"
+        since = datetime.datetime.now(tz=datetime.UTC) - datetime.timedelta(days=1)
+        suggestions = await storage.load_suggestions(
+            "user", SuggestionField.DESCRIPTION, "doc", since, count=5
+        )
+        assert suggestions == [Suggestion(value="doctor", count=1)]
+
+        # audit snapshots are encrypted, decrypted on loading
+        entries = await storage.load_audit_entries("user", stored.id, 0, 10)
+        assert [e.after["description"] for e in entries if e.after] == ["doctor", "pharmacy"]
+        raw_entries = await inner.load_audit_entries("user", stored.id, 0, 10)
+        assert all(
+            e.after["description"].startswith(AT_REST_PREFIX) for e in raw_entries if e.after
+        )
+
+        note = await storage.add_pool_note(
+            "user", PoolNote(pool_id=pool.id, text="x" * MAX_NOTE_LENGTH)
+        )
+        assert inner._user_notes["user"][0].text.startswith(AT_REST_PREFIX)
+        assert [n.text for n in await storage.load_pool_notes("user", pool.id)] == [note.text]
+
+        await storage.log_operation(
+            "user",
+            Operation(kind=OperationKind.DELETE, transactions=[loaded]),
+            datetime.timedelta(minutes=5),
+        )
+        assert inner._user_operations["user"][0].transactions[0].description.startswith(
+            AT_REST_PREFIX
+        )
+        operation = await storage.pop_last_operation(
+            "user", datetime.datetime.now(tz=datetime.UTC) - datetime.timedelta(minutes=1)
+        )
+        assert operation is not None
+        assert operation.transactions[0].description == "doctor"
+
+    asyncio.run(scenario())
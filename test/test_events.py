@@ -0,0 +1,51 @@
+import asyncio
+from decimal import Decimal
+
+from api.events import EventBus
+from api.iso4217 import CURRENCIES
+from api.types.events import BaseEvent, PoolBalanceChanged, TransactionDeleted
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction
+
+
+def test_event_bus() -> None:
+    bus = EventBus()
+    assert not bus.has_subscribers
+    received: list[tuple[str, BaseEvent]] = []
+
+    async def on_any(event: BaseEvent) -> None:
+        received.append(("any", event))
+
+    async def on_balance_changed(event: PoolBalanceChanged) -> None:
+        received.append(("balance", event))
+
+    async def failing(event: BaseEvent) -> None:
+        raise RuntimeError("oops")
+
+    bus.subscribe(BaseEvent, failing)
+    bus.subscribe(BaseEvent, on_any)
+    bus.subscribe(PoolBalanceChanged, on_balance_changed)
+    assert bus.has_subscribers
+
+    balance_changed = PoolBalanceChanged(
+        user_id="user",
+        pool_id="pool",
+        balance=[MoneySum(amount=Decimal(10), currency=CURRENCIES["EUR"])],
+    )
+    deleted = TransactionDeleted(
+        user_id="user",
+        transaction=StoredTransaction(
+            id="t",
+            sum=MoneySum(amount=Decimal(-5), currency=CURRENCIES["EUR"]),
+            pool_id="pool",
+            description="",
+        ),
+    )
+    asyncio.run(bus.publish(balance_changed))
+    asyncio.run(bus.publish(deleted))
+
+    assert received == [
+        ("any", balance_changed),
+        ("balance", balance_changed),
+        ("any", deleted),
+    ]
@@ -0,0 +1,21 @@
+from decimal import Decimal
+
+from api.formatting import NARROW_NBSP, NBSP, format_money
+from api.iso4217 import CURRENCIES
+from api.types.money_sum import MoneySum
+
+
+def money(amount: str, currency: str) -> MoneySum:
+    return MoneySum(amount=Decimal(amount), currency=CURRENCIES[currency])
+
+
+def test_format_money() -> None:
+    assert format_money(money("1234.5", "USD"), "en") == "$1,234.50"
+    assert format_money(money("-1234.5", "USD"), "en-GB") == "-$1,234.50"
+    assert format_money(money("1234.5", "EUR"), "de") == f"1.234,50{NBSP}€"
+    assert format_money(money("-1234567.5", "EUR"), "fr") == (
+        f"-1{NARROW_NBSP}234{NARROW_NBSP}567,50{NBSP}€"
+    )
+    assert format_money(money("1500", "JPY"), "ja") == "¥1,500"
+    assert format_money(money("12.345", "BHD"), "en") == f"BHD{NBSP}12.345"
+    assert format_money(money("0", "EUR"), "xx") == "€0.00"
@@ -0,0 +1,182 @@
+import asyncio
+import datetime
+from decimal import Decimal
+from test.utils import DatedUsdRates, FixedExchangeRates, NoGbpExchangeRates
+
+from api.fx_gains import compute_fx_gains
+from api.iso4217 import CURRENCIES
+from api.types.money_pool import StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction, TransactionStatus
+
+
+def test_fx_gains() -> None:
+    usd = CURRENCIES["USD"]
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="dollars",
+        balance=[MoneySum(amount=Decimal(50), currency=usd)],
+    )
+    transactions = [
+        StoredTransaction(
+            id="bought",
+            sum=MoneySum(amount=Decimal(100), currency=usd),
+            pool_id="pool",
+            description="bought dollars at 0.9",
+            timestamp=start + datetime.timedelta(days=1),
+            amount_eur=90.0,
+        ),
+        StoredTransaction(
+            id="spent",
+            sum=MoneySum(amount=Decimal(-50), currency=usd),
+            pool_id="pool",
+            description="spent dollars at 1.0",
+            timestamp=start + datetime.timedelta(days=2),
+            amount_eur=-50.0,
+        ),
+    ]
+
+    report = asyncio.run(
+        compute_fx_gains(
+            pools=[pool],
+            transactions=transactions,
+            exchange_rates=FixedExchangeRates({"USD": 1 / 1.1}),
+            start=start,
+            end=start + datetime.timedelta(days=10),
+        )
+    )
+
+    assert len(report.per_pool) == 1
+    assert report.per_pool[0].balance.amount == Decimal(50)
+    assert report.per_pool[0].cost_basis.amount == Decimal(45)
+    assert report.realized.amount == Decimal(5)
+    assert report.unrealized.amount == Decimal(10)
+
+
+def test_fx_gains_over_period() -> None:
+    usd = CURRENCIES["USD"]
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="dollars",
+        balance=[MoneySum(amount=Decimal(50), currency=usd)],
+    )
+    transactions = [
+        StoredTransaction(
+            id="bought",
+            sum=MoneySum(amount=Decimal(100), currency=usd),
+            pool_id="pool",
+            description="bought dollars at 0.9 before the period",
+            timestamp=start - datetime.timedelta(days=5),
+            amount_eur=90.0,
+        ),
+        StoredTransaction(
+            id="spent",
+            sum=MoneySum(amount=Decimal(-50), currency=usd),
+            pool_id="pool",
+            description="spent dollars at 1.0",
+            timestamp=start + datetime.timedelta(days=2),
+            amount_eur=-50.0,
+        ),
+    ]
+
+    report = asyncio.run(
+        compute_fx_gains(
+            pools=[pool],
+            transactions=transactions,
+            exchange_rates=FixedExchangeRates({"USD": 1 / 1.1}),
+            start=start,
+            end=start + datetime.timedelta(days=10),
+        )
+    )
+
+    # worth 110 EUR at the start, 50 EUR + 50 USD worth 55 EUR at the end
+    assert report.per_pool[0].cost_basis.amount == Decimal(45)
+    assert report.realized.amount == Decimal(5)
+    assert report.unrealized.amount == Decimal(-10)
+
+
+def test_fx_gains_in_target_currency() -> None:
+    usd, gbp = CURRENCIES["USD"], CURRENCIES["GBP"]
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="dollars",
+        balance=[MoneySum(amount=Decimal(100), currency=usd)],
+    )
+    transactions = [
+        StoredTransaction(
+            id="bought",
+            sum=MoneySum(amount=Decimal(100), currency=usd),
+            pool_id="pool",
+            description="bought dollars for 80 GBP",
+            timestamp=start + datetime.timedelta(days=1),
+            amount_eur=95.0,  # not used for other currencies
+        ),
+    ]
+
+    report = asyncio.run(
+        compute_fx_gains(
+            pools=[pool],
+            transactions=transactions,
+            exchange_rates=DatedUsdRates(),
+            start=start,
+            end=start + datetime.timedelta(days=60),
+            target_currency=gbp,
+        )
+    )
+
+    [gains] = report.per_pool
+    assert gains.cost_basis == MoneySum(amount=Decimal(80), currency=gbp)
+    assert report.realized.amount == Decimal(0)
+    assert report.unrealized == MoneySum(amount=Decimal(-5), currency=gbp)
+
+
+def test_fx_gains_skip_void_transactions_and_unavailable_rates() -> None:
+    usd, gbp = CURRENCIES["USD"], CURRENCIES["GBP"]
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="travel",
+        balance=[
+            MoneySum(amount=Decimal(100), currency=usd),
+            MoneySum(amount=Decimal(10), currency=gbp),
+        ],
+    )
+    transactions = [
+        StoredTransaction(
+            id="bought",
+            sum=MoneySum(amount=Decimal(100), currency=usd),
+            pool_id="pool",
+            description="bought dollars at 0.9",
+            timestamp=start + datetime.timedelta(days=1),
+            amount_eur=90.0,
+        ),
+        StoredTransaction(
+            id="voided",
+            sum=MoneySum(amount=Decimal(-50), currency=usd),
+            pool_id="pool",
+            description="card payment that never went through",
+            timestamp=start + datetime.timedelta(days=2),
+            amount_eur=-50.0,
+            status=TransactionStatus.VOID,
+        ),
+    ]
+
+    report = asyncio.run(
+        compute_fx_gains(
+            pools=[pool],
+            transactions=transactions,
+            exchange_rates=NoGbpExchangeRates(),
+            start=start,
+            end=start + datetime.timedelta(days=10),
+        )
+    )
+
+    [gains] = report.per_pool
+    assert gains.balance.currency == usd
+    assert gains.cost_basis.amount == Decimal(90)
+    assert report.realized.amount == Decimal(0)
+    assert report.unrealized.amount == Decimal(10)
+    assert report.unconverted == [MoneySum(amount=Decimal(10), currency=gbp)]
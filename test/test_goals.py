@@ -0,0 +1,30 @@
+import datetime
+from decimal import Decimal
+from test.utils import eur
+
+from api.goals import compute_goal_progress
+from api.types.goal import StoredGoal
+
+NOW = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+
+
+def test_goal_progress() -> None:
+    goal = StoredGoal(
+        id="goal",
+        name="bike",
+        target=eur(1000),
+        target_date=NOW + datetime.timedelta(days=180),
+        pool_id="savings",
+    )
+
+    progress = compute_goal_progress(goal, saved=eur(400), saved_in_window=eur(270), now=NOW)
+    assert progress.fraction == 0.4
+    assert progress.projected_at_target_date.amount == Decimal(940)
+    assert not progress.on_track
+    assert progress.projected_completion == NOW + datetime.timedelta(days=200)
+
+    progress = compute_goal_progress(goal, saved=eur(400), saved_in_window=eur(450), now=NOW)
+    assert progress.on_track
+
+    progress = compute_goal_progress(goal, saved=eur(400), saved_in_window=eur(-10), now=NOW)
+    assert progress.projected_completion is None
@@ -0,0 +1,99 @@
+import asyncio
+import socket
+from decimal import Decimal
+
+import pytest
+
+from api.auth import DumbSecretHeaderAuth
+from api.events import EventBus
+from api.exchange_rates import DumbExchangeRates
+from api.service import DEFAULT_DUPLICATE_WINDOW, DEFAULT_UNDO_WINDOW, ExpenseService
+from api.storage import InmemoryStorage
+
+grpc = pytest.importorskip("grpc")
+
+
+def free_port() -> int:
+    with socket.socket() as s:
+        s.bind(("localhost", 0))
+        return s.getsockname()[1]
+
+
+async def grpc_roundtrip() -> None:
+    from api.grpc_api import protos, services, start_grpc_server
+
+    service = ExpenseService(
+        InmemoryStorage(),
+        DumbExchangeRates(),
+        EventBus(),
+        privacy=None,
+        undo_window=DEFAULT_UNDO_WINDOW,
+        duplicate_window=DEFAULT_DUPLICATE_WINDOW,
+    )
+    port = free_port()
+    server = await start_grpc_server(service, DumbSecretHeaderAuth(), port)
+    try:
+        async with grpc.aio.insecure_channel(f"localhost:{port}") as channel:
+            stub = services.ExpenseTrackerStub(channel)
+            metadata = (("secret", "secret"),)
+
+            with pytest.raises(grpc.aio.AioRpcError) as e:
+                await stub.ListPools(protos.ListPoolsRequest())
+            assert e.value.code() is grpc.StatusCode.UNAUTHENTICATED
+
+            pool = await stub.CreatePool(
+                protos.CreatePoolRequest(
+                    display_name="cash",
+                    balance=[protos.MoneySum(amount="100", currency="EUR")],
+                ),
+                metadata=metadata,
+            )
+            assert pool.display_name == "cash"
+            response = await stub.ListPools(protos.ListPoolsRequest(), metadata=metadata)
+            assert [p.id for p in response.pools] == [pool.id]
+
+            for description, amount in [("coffee", "-3.5"), ("salary", "1000")]:
+                transaction = await stub.AddTransaction(
+                    protos.AddTransactionRequest(
+                        pool_id=pool.id,
+                        sum=protos.MoneySum(amount=amount, currency="EUR"),
+                        description=description,
+                    ),
+                    metadata=metadata,
+                )
+            assert transaction.description == "salary"
+
+            response = await stub.ListTransactions(
+                protos.ListTransactionsRequest(), metadata=metadata
+            )
+            assert [t.description for t in response.transactions] == ["salary", "coffee"]
+            [pool] = (await stub.ListPools(protos.ListPoolsRequest(), metadata=metadata)).pools
+            assert [(Decimal(s.amount), s.currency) for s in pool.balance] == [
+                (Decimal("1096.5"), "EUR")
+            ]
+
+            with pytest.raises(grpc.aio.AioRpcError) as e:
+                await stub.AddTransaction(
+                    protos.AddTransactionRequest(
+                        pool_id="no-such-pool",
+                        sum=protos.MoneySum(amount="1", currency="EUR"),
+                        description="lost",
+                    ),
+                    metadata=metadata,
+                )
+            assert e.value.code() is grpc.StatusCode.INVALID_ARGUMENT
+
+            await stub.DeleteTransaction(
+                protos.DeleteTransactionRequest(id=transaction.id), metadata=metadata
+            )
+            with pytest.raises(grpc.aio.AioRpcError) as e:
+                await stub.DeleteTransaction(
+                    protos.DeleteTransactionRequest(id=transaction.id), metadata=metadata
+                )
+            assert e.value.code() is grpc.StatusCode.NOT_FOUND
+    finally:
+        await server.stop(grace=None)
+
+
+def test_grpc_api() -> None:
+    asyncio.run(grpc_roundtrip())
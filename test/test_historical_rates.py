@@ -0,0 +1,116 @@
+import asyncio
+import datetime
+from decimal import Decimal
+from test.utils import FixedExchangeRates
+
+import pytest
+from fastapi.testclient import TestClient
+
+from api.app import create_app
+from api.auth import NoAuth
+from api.exchange_rates import DumbExchangeRates
+from api.historical_rates import HistoricalExchangeRates, RecordedExchangeRates, parse_ecb_csv
+from api.iso4217 import CURRENCIES
+from api.reports import sum_transactions
+from api.storage import InmemoryStorage
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+ECB_CSV = """Date,USD,JPY,CYP,
+2024-09-03,1.1037,160.88,N/A,
+2024-09-02,1.1058,161.25,N/A,
+"""
+
+
+def test_parse_ecb_csv() -> None:
+    days = parse_ecb_csv(ECB_CSV)
+    assert [d.date for d in days] == [datetime.date(2024, 9, 3), datetime.date(2024, 9, 2)]
+    assert days[0].rates == {"USD": 1.1037, "JPY": 160.88}
+
+
+@pytest.mark.parametrize(
+    "text",
+    [
+        pytest.param("", id="empty"),
+        pytest.param("USD,JPY\n1.1,160", id="no date column"),
+        pytest.param("Date,USD\n03.09.2024,1.1", id="invalid date"),
+        pytest.param("Date,USD\n2024-09-03,one", id="invalid rate"),
+    ],
+)
+def test_parse_ecb_csv_errors(text: str) -> None:
+    with pytest.raises(ValueError):
+        parse_ecb_csv(text)
+
+
+def test_historical_exchange_rates() -> None:
+    storage = InmemoryStorage()
+    asyncio.run(storage.save_historical_rates(parse_ecb_csv(ECB_CSV)))
+    usd, jpy = CURRENCIES["USD"], CURRENCIES["JPY"]
+
+    # weekend, falling back to the last business day
+    rates = HistoricalExchangeRates(storage, DumbExchangeRates(), on=datetime.date(2024, 9, 8))
+    rate = asyncio.run(rates.get_rate(usd, jpy))
+    assert rate.rate == pytest.approx(160.88 / 1.1037)
+    assert rate.updated_on.date() == datetime.date(2024, 9, 3)
+
+    # before the imported history
+    rates = HistoricalExchangeRates(storage, DumbExchangeRates(), on=datetime.date(2020, 1, 1))
+    assert asyncio.run(rates.get_rate(usd, jpy)).rate == 1.0
+
+
+def test_recorded_exchange_rates() -> None:
+    storage = InmemoryStorage()
+    asyncio.run(storage.save_historical_rates(parse_ecb_csv(ECB_CSV)))
+    provider = FixedExchangeRates({"USD": 1.2, "JPY": 150.0})
+    rates = RecordedExchangeRates(provider, storage)
+    usd, jpy = CURRENCIES["USD"], CURRENCIES["JPY"]
+    today = datetime.datetime.now(tz=datetime.UTC).date()
+
+    assert asyncio.run(rates.get_rate(usd, jpy)).rate == pytest.approx(125.0)
+    recorded = asyncio.run(storage.load_historical_rates(today))
+    assert recorded is not None and recorded.rates == {"USD": 1.2, "JPY": 150.0}
+    # recorded once a day, not on every conversion
+    provider.eur_rates = {"USD": 1.3, "JPY": 150.0}
+    asyncio.run(rates.get_rate(usd, jpy))
+    assert asyncio.run(storage.load_historical_rates(today)) == recorded
+
+    past = asyncio.run(rates.get_rate_on(usd, jpy, on=datetime.date(2024, 9, 2)))
+    assert past.rate == pytest.approx(161.25 / 1.1058)
+    transactions = [
+        Transaction(
+            sum=MoneySum(amount=Decimal(-10), currency=usd),
+            pool_id="pool",
+            description="books",
+            timestamp=datetime.datetime(2024, 9, 2, 12, tzinfo=datetime.UTC),
+        ),
+        Transaction(
+            sum=MoneySum(amount=Decimal(-10), currency=usd),
+            pool_id="pool",
+            description="books",
+        ),
+    ]
+    total = asyncio.run(sum_transactions(transactions, rates, jpy))
+    assert total.amount == round(Decimal(-10 * 161.25 / 1.1058 - 10 * 150 / 1.3))
+
+
+def test_rate_history_api() -> None:
+    client = TestClient(
+        create_app(storage=InmemoryStorage(), auth=NoAuth(), exchange_rates=DumbExchangeRates())
+    )
+    response = client.post("/exchange-rates/history", content=ECB_CSV)
+    assert response.status_code == 200
+
+    params = {"base": "USD", "quote": "JPY", "from": "2024-09-01", "to": "2024-09-30"}
+    response = client.get("/exchange-rates/history", params=params)
+    assert response.status_code == 200
+    history = response.json()
+    assert (history["base"], history["quote"]) == ("USD", "JPY")
+    assert [r["date"] for r in history["rates"]] == ["2024-09-02", "2024-09-03"]
+    assert history["rates"][0]["rate"] == pytest.approx(161.25 / 1.1058)
+
+    response = client.get("/exchange-rates/history", params={**params, "quote": "GBP"})
+    assert response.json()["rates"] == []
+    response = client.get("/exchange-rates/history", params={**params, "quote": "XXXX"})
+    assert response.status_code == 400
+    response = client.get("/exchange-rates/history", params={**params, "to": "2024-08-01"})
+    assert response.status_code == 400
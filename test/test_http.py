@@ -0,0 +1,316 @@
+"""
+End-to-end flows through the HTTP API on in-memory storage, one request after another as
+a client would make them
+"""
+
+import datetime
+
+from fastapi.testclient import TestClient
+
+NOW = datetime.datetime(2024, 6, 15, 12, tzinfo=datetime.UTC)
+
+
+def create_pool(client: TestClient, name: str, amount: int = 100) -> str:
+    response = client.post(
+        "/pools",
+        json={"display_name": name, "balance": [{"amount": amount, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    return response.json()["id"]
+
+
+def add_transaction(
+    client: TestClient, pool_id: str, amount: int, description: str, days_ago: int = 0
+) -> str:
+    response = client.post(
+        "/transactions",
+        json={
+            "timestamp": (NOW - datetime.timedelta(days=days_ago)).isoformat(),
+            "sum": {"amount": amount, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": description,
+        },
+        params={"force": True},
+    )
+    assert response.status_code == 200
+    return response.json()["id"]
+
+
+def balance(client: TestClient, pool_id: str) -> str:
+    return client.get(f"/pools/{pool_id}").json()["balance"][0]["amount"]
+
+
+def test_auth(secret_client: TestClient) -> None:
+    anonymous = TestClient(secret_client.app)
+    assert anonymous.get("/pools").status_code == 403
+    assert anonymous.post("/pools", json={}).status_code == 403
+    assert secret_client.get("/pools", headers={"secret": "guess"}).status_code == 403
+
+    pool_id = create_pool(secret_client, "cash")
+    assert anonymous.get(f"/pools/{pool_id}").status_code == 403
+    assert secret_client.get(f"/pools/{pool_id}").status_code == 200
+
+
+def test_pool_crud(secret_client: TestClient) -> None:
+    client = secret_client
+    cash_id = create_pool(client, "cash")
+    card_id = create_pool(client, "card", amount=500)
+    assert [p["id"] for p in client.get("/pools").json()] == [cash_id, card_id]
+
+    response = client.put(f"/pools/{cash_id}", json={"display_name": "wallet"})
+    assert response.status_code == 428
+    response = client.put(
+        f"/pools/{cash_id}", json={"display_name": "wallet"}, headers={"If-Match": "0"}
+    )
+    assert response.status_code == 200
+    assert response.text == "OK"
+    pool = client.get(f"/pools/{cash_id}").json()
+    assert pool["display_name"] == "wallet"
+    assert pool["version"] == 1
+
+    response = client.put(
+        f"/pools/{cash_id}", json={"display_name": "stale"}, headers={"If-Match": '"0"'}
+    )
+    assert response.status_code == 409
+    response = client.put(
+        f"/pools/{cash_id}", json={"display_name": "stale"}, headers={"If-Match": "latest"}
+    )
+    assert response.status_code == 400
+    assert client.get(f"/pools/{cash_id}").json()["display_name"] == "wallet"
+
+    response = client.put("/pools/order", json={"pool_ids": [card_id, cash_id]})
+    assert response.status_code == 200
+    assert [p["id"] for p in client.get("/pools").json()] == [card_id, cash_id]
+    response = client.put("/pools/order", json={"pool_ids": [card_id]})
+    assert response.status_code == 400
+
+    assert client.get("/pools/missing").status_code == 404
+    response = client.put("/pools/missing", json={"display_name": "x"}, headers={"If-Match": "0"})
+    assert response.status_code == 404
+
+
+def test_pool_validation(secret_client: TestClient) -> None:
+    client = secret_client
+    no_name = {"balance": [{"amount": 1, "currency": "EUR"}]}
+    assert client.post("/pools", json=no_name).status_code == 422
+    unknown_currency = {"display_name": "x", "balance": [{"amount": 1, "currency": "XXXX"}]}
+    assert client.post("/pools", json=unknown_currency).status_code == 422
+    assert client.post("/pools", content="not json").status_code == 422
+    assert client.get("/pools").json() == []
+
+
+def test_transaction_crud(secret_client: TestClient) -> None:
+    client = secret_client
+    pool_id = create_pool(client, "cash")
+    transaction_id = add_transaction(client, pool_id, -30, "groceries")
+    assert balance(client, pool_id) == "70.00"
+
+    response = client.put(
+        f"/transactions/{transaction_id}",
+        json={"description": "market", "tags": ["food"]},
+        headers={"If-Match": "0"},
+    )
+    assert response.status_code == 200
+    [transaction] = client.get("/transactions").json()
+    assert transaction == {
+        "id": transaction_id,
+        "pool_id": pool_id,
+        "sum": {"amount": "-30.00", "currency": "EUR"},
+        "description": "market",
+        "tags": ["food"],
+        "timestamp": NOW.timestamp(),
+        "is_diffuse": False,
+        "transfer_id": None,
+        "source": None,
+        "payee_id": None,
+        "splits": [],
+        "status": "cleared",
+        "version": 1,
+        "kind": "expense",
+        "original_currency": None,
+        "original_amount": None,
+    }
+
+    response = client.put(
+        f"/transactions/{transaction_id}", json={"tags": []}, headers={"If-Match": "0"}
+    )
+    assert response.status_code == 409
+
+    assert client.delete(f"/transactions/{transaction_id}").status_code == 200
+    assert client.get("/transactions").json() == []
+    assert balance(client, pool_id) == "100.00"
+
+    assert client.delete(f"/transactions/{transaction_id}").status_code == 404
+    response = client.put("/transactions/missing", json={"tags": []}, headers={"If-Match": "0"})
+    assert response.status_code == 404
+    assert client.get("/transactions/missing/source").status_code == 404
+
+
+def test_transaction_validation(secret_client: TestClient) -> None:
+    client = secret_client
+    pool_id = create_pool(client, "cash")
+    transaction = {
+        "timestamp": NOW.isoformat(),
+        "sum": {"amount": -10, "currency": "EUR"},
+        "pool_id": pool_id,
+        "description": "coffee",
+    }
+    response = client.post("/transactions", json={**transaction, "pool_id": "missing"})
+    assert response.status_code == 400
+    no_sum = {k: v for k, v in transaction.items() if k != "sum"}
+    assert client.post("/transactions", json=no_sum).status_code == 422
+    response = client.post("/transactions", json={**transaction, "timestamp": "yesterday"})
+    assert response.status_code == 422
+    assert client.get("/transactions").json() == []
+    assert balance(client, pool_id) == "100.00"
+
+
+def test_transaction_listing(secret_client: TestClient) -> None:
+    client = secret_client
+    pool_id = create_pool(client, "cash", amount=1000)
+    add_transaction(client, pool_id, -5, "coffee", days_ago=2)
+    add_transaction(client, pool_id, -300, "rent", days_ago=10)
+    add_transaction(client, pool_id, 50, "refund", days_ago=1)
+
+    def descriptions(**params: object) -> list[str]:
+        response = client.get("/transactions", params=params)
+        assert response.status_code == 200
+        return [t["description"] for t in response.json()]
+
+    assert descriptions() == ["refund", "coffee", "rent"]
+    assert descriptions(order="latest") == ["refund", "coffee", "rent"]
+    assert descriptions(order="oldest") == ["rent", "coffee", "refund"]
+    assert descriptions(order="largest") == ["refund", "coffee", "rent"]
+    assert descriptions(order="largest_negative") == ["rent", "coffee", "refund"]
+    assert descriptions(offset=3) == []
+
+    assert client.get("/transactions", params={"count": 0}).status_code == 422
+    assert client.get("/transactions", params={"count": 201}).status_code == 422
+    assert client.get("/transactions", params={"offset": -1}).status_code == 422
+    assert client.get("/transactions", params={"order": "random"}).status_code == 422
+
+
+def test_conditional_listing(secret_client: TestClient) -> None:
+    client = secret_client
+    pool_id = create_pool(client, "cash")
+    add_transaction(client, pool_id, -5, "coffee", days_ago=1)
+
+    for path in ("/pools", "/transactions"):
+        response = client.get(path)
+        etag = response.headers["ETag"]
+        response = client.get(path, headers={"If-None-Match": etag})
+        assert response.status_code == 304
+        assert response.headers["ETag"] == etag
+        assert response.content == b""
+        response = client.get(path, headers={"If-None-Match": f'"other", {etag}'})
+        assert response.status_code == 304
+        assert client.get(path, headers={"If-None-Match": '"other"'}).status_code == 200
+
+    etag = client.get("/transactions").headers["ETag"]
+    response = client.get("/transactions", params={"count": 1}, headers={"If-None-Match": etag})
+    assert response.status_code == 200
+    add_transaction(client, pool_id, -7, "tea")
+    response = client.get("/transactions", headers={"If-None-Match": etag})
+    assert response.status_code == 200
+    assert [t["description"] for t in response.json()] == ["tea", "coffee"]
+    assert response.headers["ETag"] != etag
+
+
+def test_sync(secret_client: TestClient) -> None:
+    client = secret_client
+    pool_id = create_pool(client, "cash")
+    coffee_id = add_transaction(client, pool_id, -5, "coffee")
+
+    response = client.get("/sync")
+    assert response.status_code == 200
+    initial = response.json()
+    assert [p["id"] for p in initial["pools"]] == [pool_id]
+    assert [t["id"] for t in initial["transactions"]] == [coffee_id]
+    assert initial["deleted_transaction_ids"] == []
+    empty = client.get("/sync", params={"since": initial["cursor"]}).json()
+    assert (empty["pools"], empty["transactions"]) == ([], [])
+    assert empty["cursor"] == initial["cursor"]
+
+    tea_id = add_transaction(client, pool_id, -3, "tea")
+    assert client.delete(f"/transactions/{coffee_id}").status_code == 200
+    delta = client.get("/sync", params={"since": initial["cursor"]}).json()
+    assert delta["cursor"] > initial["cursor"]
+    assert [p["balance"][0]["amount"] for p in delta["pools"]] == ["97.00"]
+    assert [t["id"] for t in delta["transactions"]] == [tea_id]
+    assert delta["deleted_transaction_ids"] == [coffee_id]
+    assert client.get("/sync", params={"since": -1}).status_code == 422
+
+
+def test_sync_upload(secret_client: TestClient) -> None:
+    client = secret_client
+    pool_id = create_pool(client, "cash")
+    coffee_id = add_transaction(client, pool_id, -5, "coffee", days_ago=2)
+    cake_id = add_transaction(client, pool_id, -4, "cake", days_ago=1)
+    tea = {
+        "timestamp": NOW.isoformat(),
+        "sum": {"amount": -3, "currency": "EUR"},
+        "pool_id": pool_id,
+        "description": "tea",
+    }
+
+    response = client.post(
+        "/sync",
+        json={
+            "changes": [
+                {"client_id": "a", "kind": "create", "transaction": tea},
+                {"client_id": "b", "kind": "create", "transaction": {**tea, "pool_id": "missing"}},
+            ]
+        },
+    )
+    assert response.status_code == 400
+    assert [e["client_id"] for e in response.json()["detail"]] == ["b"]
+    assert len(client.get("/transactions").json()) == 2
+
+    response = client.post(
+        "/sync",
+        json={
+            "changes": [
+                {"client_id": "a", "kind": "create", "transaction": tea},
+                {
+                    "client_id": "b",
+                    "kind": "update",
+                    "transaction_id": coffee_id,
+                    "base_version": 0,
+                    "update": {"description": "espresso"},
+                },
+                {"client_id": "c", "kind": "delete", "transaction_id": cake_id, "base_version": 0},
+                {
+                    "client_id": "d",
+                    "kind": "update",
+                    "transaction_id": coffee_id,
+                    "base_version": 0,  # made before the "b" one
+                    "update": {"description": "latte"},
+                },
+            ]
+        },
+    )
+    assert response.status_code == 200
+    results = response.json()["results"]
+    assert [(r["client_id"], r["status"]) for r in results] == [
+        ("a", "accepted"),
+        ("b", "accepted"),
+        ("c", "accepted"),
+        ("d", "conflict"),
+    ]
+    assert results[0]["transaction"]["description"] == "tea"
+    assert results[2]["transaction"] is None
+    assert (results[3]["transaction"]["description"], results[3]["transaction"]["version"]) == (
+        "espresso",
+        1,
+    )
+    assert [t["description"] for t in client.get("/transactions").json()] == ["tea", "espresso"]
+    assert balance(client, pool_id) == "92.00"
+
+    response = client.post(
+        "/sync",
+        json={"changes": [{"client_id": "a", "kind": "delete", "transaction_id": cake_id}]},
+    )
+    assert response.status_code == 422
+    duplicate = {"client_id": "a", "kind": "create", "transaction": tea}
+    response = client.post("/sync", json={"changes": [duplicate, duplicate]})
+    assert response.status_code == 422
@@ -0,0 +1,89 @@
+import datetime
+import zoneinfo
+
+import pytest
+from fastapi.testclient import TestClient
+
+from api.human_dates import add_months, parse_human_span
+from api.types.settings import Weekday
+
+NOW = datetime.datetime(2024, 9, 18, 12, tzinfo=datetime.UTC)  # Wednesday
+UTC = datetime.UTC
+
+
+def day(month: int, day: int, tz: datetime.tzinfo = UTC) -> datetime.datetime:
+    return datetime.datetime(2024, month, day, tzinfo=tz)
+
+
+@pytest.mark.parametrize(
+    "text,expected",
+    [
+        ("now", (NOW, NOW)),
+        ("yesterday", (day(9, 17), day(9, 18))),
+        ("Today", (day(9, 18), day(9, 19))),
+        ("last monday", (day(9, 16), day(9, 17))),
+        ("last wednesday", (day(9, 11), day(9, 12))),
+        ("this sunday", (day(9, 22), day(9, 23))),
+        ("this week", (day(9, 16), day(9, 23))),
+        ("  Last   Month ", (day(8, 1), day(9, 1))),
+        ("last year", (datetime.datetime(2023, 1, 1, tzinfo=UTC), day(1, 1))),
+        ("3 days ago", (day(9, 15), day(9, 16))),
+        ("1 week ago", (day(9, 11), day(9, 12))),
+        ("2024-09-01T10:00:00+00:00", (day(9, 1) + datetime.timedelta(hours=10),) * 2),
+        ("1725148800", (day(9, 1),) * 2),
+    ],
+)
+def test_parse_human_span(
+    text: str, expected: tuple[datetime.datetime, datetime.datetime]
+) -> None:
+    assert parse_human_span(text, NOW, UTC, Weekday.MONDAY) == expected
+
+
+def test_parse_human_span_settings() -> None:
+    tokyo = zoneinfo.ZoneInfo("Asia/Tokyo")
+    late_evening = datetime.datetime(2024, 9, 18, 20, tzinfo=UTC)  # Thursday in Tokyo
+    assert parse_human_span("today", late_evening, tokyo, Weekday.MONDAY) == (
+        day(9, 19, tokyo),
+        day(9, 20, tokyo),
+    )
+    assert parse_human_span("this week", NOW, UTC, Weekday.SUNDAY) == (day(9, 15), day(9, 22))
+    assert add_months(datetime.date(2024, 3, 31), -1) == datetime.date(2024, 2, 29)
+    assert add_months(datetime.date(2024, 12, 15), 1) == datetime.date(2025, 1, 15)
+
+
+@pytest.mark.parametrize("text", ["next friday", "last", "someday", "3 fortnights ago"])
+def test_parse_human_span_errors(text: str) -> None:
+    with pytest.raises(ValueError):
+        parse_human_span(text, NOW, UTC, Weekday.MONDAY)
+
+
+def test_human_dates_api(client: TestClient) -> None:
+    pool_id = client.post(
+        "/pools", json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]}
+    ).json()["id"]
+    now = datetime.datetime.now(tz=datetime.UTC)
+    for days_ago in (0, 3):
+        response = client.post(
+            "/transactions",
+            json={
+                "timestamp": (now - datetime.timedelta(days=days_ago)).isoformat(),
+                "sum": {"amount": -1, "currency": "EUR"},
+                "pool_id": pool_id,
+                "description": f"{days_ago} days ago",
+            },
+        )
+        assert response.status_code == 200
+
+    response = client.get("/transactions", params={"from": "yesterday"})
+    assert [t["description"] for t in response.json()] == ["0 days ago"]
+    response = client.get("/transactions", params={"from": "last month", "to": "yesterday"})
+    assert [t["description"] for t in response.json()] == ["3 days ago"]
+
+    response = client.get("/report/cashflow", params={"from": "this year"})
+    assert response.status_code == 200
+
+    response = client.get("/report/cashflow", params={"from": "last blue moon"})
+    assert response.status_code == 422
+    assert response.json()["detail"][0]["loc"] == ["query", "from"]
+    response = client.get("/report/payees", params={"from": "today", "to": "last week"})
+    assert response.status_code == 400
@@ -0,0 +1,57 @@
+import asyncio
+
+from fastapi.testclient import TestClient
+from starlette.responses import PlainTextResponse
+from starlette.types import Receive, Scope, Send
+
+from api.app import create_app
+from api.auth import NoAuth
+from api.exchange_rates import DumbExchangeRates
+from api.limits import RequestLimits, RequestLimitsMiddleware, request_limits_from_env
+from api.storage import InmemoryStorage
+
+
+def test_request_limits_from_env() -> None:
+    assert request_limits_from_env({}) == RequestLimits()
+    limits = request_limits_from_env({"MAX_REQUEST_BODY_SIZE": "100", "REQUEST_TIMEOUT_SEC": "5"})
+    assert limits == RequestLimits(max_body_size=100, timeout_sec=5.0)
+
+
+def test_body_size_limit() -> None:
+    app = create_app(
+        storage=InmemoryStorage(),
+        auth=NoAuth(),
+        exchange_rates=DumbExchangeRates(),
+        limits=RequestLimits(max_body_size=200),
+    )
+    client = TestClient(app)
+    response = client.post(
+        "/pools", json={"display_name": "cash", "balance": [{"amount": 100, "currency": "EUR"}]}
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    response = client.post("/pools", json={"display_name": "x" * 300, "balance": []})
+    assert response.status_code == 413
+    assert response.json() == {"detail": "Request body exceeds 200 bytes"}
+
+    def chunked(size: int):
+        yield b"!Type:Bank\n"
+        yield b"D01/02/2024\nT-1.00\nPcoffee\n^\n" * (size // 30)
+
+    response = client.post(f"/pools/{pool_id}/import", content=chunked(1000))
+    assert response.status_code == 413
+    assert response.json()["detail"] == "Request body exceeds 200 bytes"
+    assert client.get("/transactions").json() == []
+
+
+def test_timeout() -> None:
+    async def slow_app(scope: Scope, receive: Receive, send: Send) -> None:
+        await asyncio.sleep(float(scope["path"].strip("/")))
+        await PlainTextResponse("OK")(scope, receive, send)
+
+    client = TestClient(RequestLimitsMiddleware(slow_app, RequestLimits(timeout_sec=0.2)))
+    assert client.get("/0").text == "OK"
+    response = client.get("/5")
+    assert response.status_code == 408
+    assert response.json() == {"detail": "Request timed out"}
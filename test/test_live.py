@@ -0,0 +1,31 @@
+import asyncio
+import json
+
+from api.live import LiveUpdates, sse_stream
+from api.types.events import MonthClosed
+
+
+def test_sse_stream_resumes_after_last_event_id() -> None:
+    live = LiveUpdates()
+
+    async def run() -> list[str]:
+        for month in (1, 2, 3):
+            await live.publish(MonthClosed(user_id="user", year=2024, month=month))
+        await live.publish(MonthClosed(user_id="other", year=2024, month=1))
+        first_id = live.recent("user", after_id=0)[0][0]
+
+        stream = sse_stream(live, "user", last_event_id=first_id, present=lambda e: e)
+        messages = [await anext(stream), await anext(stream)]
+        assert live.connection_count("user") == 1
+        await live.publish(MonthClosed(user_id="user", year=2024, month=4))
+        messages.append(await anext(stream))
+        await stream.aclose()
+        assert live.connection_count("user") == 0
+        return messages
+
+    messages = asyncio.run(run())
+
+    ids = [int(m.split("\n")[0].removeprefix("id: ")) for m in messages]
+    assert ids == sorted(ids)
+    months = [json.loads(m.split("\n")[1].removeprefix("data: "))["month"] for m in messages]
+    assert months == [2, 3, 4]
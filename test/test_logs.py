@@ -0,0 +1,27 @@
+import json
+import logging
+
+from api.logs import JsonFormatter, RequestIdFilter, request_id_var
+
+
+def test_json_log_format() -> None:
+    record = logging.LogRecord(
+        name="test",
+        level=logging.INFO,
+        pathname=__file__,
+        lineno=1,
+        msg="hello %s",
+        args=("world",),
+        exc_info=None,
+    )
+    token = request_id_var.set("abc")
+    try:
+        RequestIdFilter().filter(record)
+    finally:
+        request_id_var.reset(token)
+
+    entry = json.loads(JsonFormatter().format(record))
+    assert entry["level"] == "INFO"
+    assert entry["logger"] == "test"
+    assert entry["message"] == "hello world"
+    assert entry["request_id"] == "abc"
@@ -0,0 +1,30 @@
+import asyncio
+
+import pytest
+
+from api.migrations import Migration, pending_migrations
+
+
+def test_pending_migrations() -> None:
+    applied: list[str] = []
+
+    def make_migration(version: int, name: str) -> Migration:
+        async def apply() -> None:
+            applied.append(name)
+
+        return Migration(version, name, apply)
+
+    migrations = [
+        make_migration(3, "third"),
+        make_migration(1, "first"),
+        make_migration(2, "second"),
+    ]
+    pending = pending_migrations(migrations, applied_versions=[1])
+    assert [str(m) for m in pending] == ["0002: second", "0003: third"]
+    for migration in pending:
+        asyncio.run(migration.apply())
+    assert applied == ["second", "third"]
+
+    assert pending_migrations(migrations, applied_versions=[1, 2, 3]) == []
+    with pytest.raises(ValueError):
+        pending_migrations([*migrations, make_migration(2, "again")], applied_versions=[])
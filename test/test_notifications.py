@@ -0,0 +1,39 @@
+import asyncio
+from email.message import EmailMessage
+
+import pytest
+
+from api.notifications import EmailNotifier, parse_email_recipients
+
+
+class CapturingEmailNotifier(EmailNotifier):
+    def __init__(self, **kwargs) -> None:
+        super().__init__(**kwargs)
+        self.sent: list[EmailMessage] = []
+
+    def _send(self, message: EmailMessage) -> None:
+        self.sent.append(message)
+
+
+def test_email_notifier() -> None:
+    notifier = CapturingEmailNotifier(
+        smtp_host="localhost",
+        smtp_port=25,
+        sender="tracker@example.com",
+        recipients=parse_email_recipients("alice:alice@example.com, bob:bob@example.com"),
+    )
+    assert notifier.user_ids() == ["alice", "bob"]
+
+    assert asyncio.run(notifier.notify("alice", subject="Digest", text="Spent: 10.00 EUR"))
+    assert not asyncio.run(notifier.notify("eve", subject="Digest", text="Spent: 10.00 EUR"))
+
+    assert len(notifier.sent) == 1
+    message = notifier.sent[0]
+    assert message["To"] == "alice@example.com"
+    assert message["Subject"] == "Digest"
+    assert message.get_content().strip() == "Spent: 10.00 EUR"
+
+
+def test_parse_email_recipients_error() -> None:
+    with pytest.raises(ValueError):
+        parse_email_recipients("alice")
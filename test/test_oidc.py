@@ -0,0 +1,60 @@
+import base64
+import urllib.parse
+from hashlib import sha256
+
+from api.oidc import OidcProvider, oidc_config_from_env, pkce_pair
+
+
+def test_pkce_pair() -> None:
+    verifier, challenge = pkce_pair()
+    expected = base64.urlsafe_b64encode(sha256(verifier.encode("ascii")).digest())
+    assert challenge == expected.decode("ascii").rstrip("=")
+
+
+def test_authorization_url() -> None:
+    provider = OidcProvider(
+        name="corp",
+        client_id="client",
+        client_secret="secret",
+        authorization_endpoint="https://idp.example.com/authorize",
+    )
+    url = provider.authorization_url("https://api.example.com/cb", "state", "challenge")
+    parsed = urllib.parse.urlparse(url)
+    assert parsed.netloc == "idp.example.com"
+    assert urllib.parse.parse_qs(parsed.query) == {
+        "response_type": ["code"],
+        "client_id": ["client"],
+        "redirect_uri": ["https://api.example.com/cb"],
+        "scope": ["openid"],
+        "state": ["state"],
+        "code_challenge": ["challenge"],
+        "code_challenge_method": ["S256"],
+    }
+
+
+def test_oidc_config_from_env() -> None:
+    assert oidc_config_from_env({}) is None
+    config = oidc_config_from_env(
+        {
+            "OIDC_PROVIDERS": "github,corp",
+            "OIDC_GITHUB_CLIENT_ID": "gh-id",
+            "OIDC_GITHUB_CLIENT_SECRET": "gh-secret",
+            "OIDC_GITHUB_LINKED_USERS": "42=old-user",
+            "OIDC_CORP_CLIENT_ID": "corp-id",
+            "OIDC_CORP_CLIENT_SECRET": "corp-secret",
+            "OIDC_CORP_ISSUER": "https://idp.example.com",
+            "OIDC_REDIRECT_URI": "https://api.example.com/auth/oidc/callback",
+            "OIDC_POST_LOGIN_URL": "https://app.example.com/login",
+        }
+    )
+    assert config is not None
+    github = config.provider("github")
+    corp = config.provider("corp")
+    assert github is not None and corp is not None
+    assert github.subject_claim == "id"
+    assert corp.issuer == "https://idp.example.com"
+    assert corp.authorization_endpoint is None  # discovered on initialization
+
+    assert github.local_user_id("42") == "old-user"
+    assert github.local_user_id("43") != corp.local_user_id("43")
+    assert github.local_user_id("43") == github.local_user_id("43")
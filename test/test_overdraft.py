@@ -0,0 +1,53 @@
+import datetime
+from test.utils import eur
+
+from api.overdraft import compute_overdraft_status
+from api.types.money_pool import OverdraftFacility, StoredMoneyPool
+from api.types.transaction import StoredTransaction
+
+NOW = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+
+
+def test_overdraft_status() -> None:
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="bank",
+        balance=[eur("-365")],
+        overdraft=OverdraftFacility(
+            limit=eur("1000"), interest_free_days=20, annual_interest_rate=0.1
+        ),
+    )
+    transactions = [
+        StoredTransaction(
+            id=str(days_ago),
+            sum=eur(amount),
+            pool_id="pool",
+            description="",
+            timestamp=NOW - datetime.timedelta(days=days_ago),
+        )
+        for amount, days_ago in [("-400", 5), ("100", 30), ("-65", 2)]
+    ]
+
+    status = compute_overdraft_status(pool, transactions, now=NOW)
+
+    assert status is not None
+    assert status.in_overdraft
+    assert status.available == eur("635")
+    assert status.overdrawn_since == NOW - datetime.timedelta(days=5)
+    assert status.interest_free_until == NOW + datetime.timedelta(days=15)
+    # charged for 15 out of 30 projected days
+    assert status.projected_fee == eur("1.50")
+
+
+def test_no_overdraft() -> None:
+    pool = StoredMoneyPool(
+        id="pool",
+        display_name="bank",
+        balance=[eur("100")],
+        overdraft=OverdraftFacility(limit=eur("1000")),
+    )
+    status = compute_overdraft_status(pool, [], now=NOW)
+    assert status is not None
+    assert not status.in_overdraft
+    assert status.overdrawn_since is None
+    assert status.projected_fee == eur("0")
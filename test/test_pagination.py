@@ -0,0 +1,161 @@
+import asyncio
+import datetime
+from decimal import Decimal
+
+from hypothesis import given, settings
+from hypothesis import strategies as st
+
+from api.storage import InmemoryStorage, TransactionOrder
+from api.types.ids import MoneyPoolId
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction, Transaction, TransactionFilter
+
+START = datetime.datetime(2024, 1, 1, tzinfo=datetime.UTC)
+POOLS = 3
+
+# few distinct days and amounts, so that there are plenty of ties in every order
+timestamps = st.integers(min_value=0, max_value=10).map(
+    lambda day: START + datetime.timedelta(days=day)
+)
+transactions = st.builds(
+    lambda timestamp, amount, pool_idx, tagged, is_diffuse: (
+        pool_idx,
+        Transaction(
+            sum=MoneySum(amount=Decimal(amount), currency="EUR"),
+            pool_id="",  # set when the pools are created
+            description="",
+            timestamp=timestamp,
+            tags=["tag"] if tagged else [],
+            is_diffuse=is_diffuse,
+        ),
+    ),
+    timestamp=timestamps,
+    amount=st.integers(min_value=-5, max_value=5),
+    pool_idx=st.integers(min_value=0, max_value=POOLS - 1),
+    tagged=st.booleans(),
+    is_diffuse=st.booleans(),
+)
+filters = st.builds(
+    lambda min_timestamp, max_timestamp, pool_idxs, untagged_only, is_diffuse: (
+        pool_idxs,
+        TransactionFilter(
+            min_timestamp=min_timestamp,
+            max_timestamp=max_timestamp,
+            untagged_only=untagged_only,
+            is_diffuse=is_diffuse,
+        ),
+    ),
+    min_timestamp=st.none() | timestamps,
+    max_timestamp=st.none() | timestamps,
+    pool_idxs=st.none() | st.lists(st.integers(min_value=0, max_value=POOLS - 1), unique=True),
+    untagged_only=st.booleans(),
+    is_diffuse=st.none() | st.booleans(),
+)
+
+
+async def populate(
+    storage: InmemoryStorage, new_transactions: list[tuple[int, Transaction]]
+) -> tuple[list[MoneyPoolId], list[StoredTransaction]]:
+    pool_ids: list[MoneyPoolId] = []
+    for idx in range(POOLS):
+        pool = await storage.add_pool(
+            "user",
+            MoneyPool(
+                display_name=f"pool {idx}", balance=[MoneySum(amount=Decimal(0), currency="EUR")]
+            ),
+        )
+        pool_ids.append(pool.id)
+    added: list[StoredTransaction] = []
+    for pool_idx, transaction in new_transactions:
+        transaction = transaction.model_copy(update={"pool_id": pool_ids[pool_idx]})
+        added.append(await storage.add_transaction("user", transaction))
+    return pool_ids, added
+
+
+def in_order(
+    transactions: list[StoredTransaction], order: TransactionOrder
+) -> list[StoredTransaction]:
+    """The order spelled out independently of the storage, ties are left as they are"""
+    match order:
+        case TransactionOrder.LATEST:
+            return sorted(transactions, key=lambda t: t.timestamp, reverse=True)
+        case TransactionOrder.OLDEST:
+            return sorted(transactions, key=lambda t: t.timestamp)
+        case TransactionOrder.LARGEST:
+            return sorted(transactions, key=lambda t: t.sum.amount, reverse=True)
+        case TransactionOrder.LARGEST_NEGATIVE:
+            return sorted(transactions, key=lambda t: t.sum.amount)
+
+
+@settings(max_examples=200, deadline=None)
+@given(
+    new_transactions=st.lists(transactions, max_size=30),
+    filter=st.none() | filters,
+    order=st.sampled_from(TransactionOrder),
+    page_size=st.integers(min_value=1, max_value=12),
+)
+def test_pages_cover_matching_transactions_in_order(
+    new_transactions: list[tuple[int, Transaction]],
+    filter: tuple[list[int] | None, TransactionFilter] | None,
+    order: TransactionOrder,
+    page_size: int,
+) -> None:
+    storage = InmemoryStorage()
+
+    async def scenario() -> None:
+        pool_ids, added = await populate(storage, new_transactions)
+        transaction_filter: TransactionFilter | None = None
+        if filter is not None:
+            pool_idxs, transaction_filter = filter
+            if pool_idxs is not None:
+                transaction_filter = transaction_filter.model_copy(
+                    update={"pool_ids": [pool_ids[idx] for idx in pool_idxs]}
+                )
+
+        pages: list[list[StoredTransaction]] = []
+        while page := await storage.load_transactions(
+            "user", transaction_filter, order, offset=page_size * len(pages), count=page_size
+        ):
+            pages.append(page)
+        assert all(len(page) == page_size for page in pages[:-1])
+
+        loaded = [t for page in pages for t in page]
+        ids = [t.id for t in loaded]
+        assert len(ids) == len(set(ids)), "pages overlap"
+        matching = [
+            t for t in added if transaction_filter is None or transaction_filter.matches(t)
+        ]
+        assert set(ids) == {t.id for t in matching}
+        # the ties go in the stored order: by timestamp, then as added
+        stored = sorted(matching, key=lambda t: t.timestamp)
+        assert ids == [t.id for t in in_order(stored, order)]
+
+    asyncio.run(scenario())
+
+
+@settings(max_examples=200, deadline=None)
+@given(
+    new_transactions=st.lists(transactions, max_size=30),
+    order=st.sampled_from(TransactionOrder),
+    offset=st.integers(min_value=0, max_value=40),
+    count=st.integers(min_value=1, max_value=40),
+)
+def test_page_is_slice_of_full_listing(
+    new_transactions: list[tuple[int, Transaction]],
+    order: TransactionOrder,
+    offset: int,
+    count: int,
+) -> None:
+    storage = InmemoryStorage()
+
+    async def scenario() -> None:
+        await populate(storage, new_transactions)
+        full = await storage.load_transactions(
+            "user", None, order, offset=0, count=len(new_transactions) + 1
+        )
+        assert len(full) == len(new_transactions)
+        page = await storage.load_transactions("user", None, order, offset=offset, count=count)
+        assert page == full[offset : offset + count]
+
+    asyncio.run(scenario())
@@ -0,0 +1,115 @@
+import asyncio
+import time
+
+from cryptography.fernet import Fernet
+from fastapi.testclient import TestClient
+
+from api import totp
+from api.app import create_app
+from api.auth import NoAuth, TokenAuth
+from api.exchange_rates import DumbExchangeRates
+from api.privacy import ENCRYPTED_PREFIX, MASKED_DESCRIPTION, DescriptionPrivacy
+from api.storage import InmemoryStorage
+
+
+def test_description_privacy() -> None:
+    storage = InmemoryStorage()
+    client = TestClient(
+        create_app(
+            storage=storage,
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+            privacy=DescriptionPrivacy(key=Fernet.generate_key()),
+        )
+    )
+
+    response = client.post(
+        "/pools",
+        json={"display_name": "p", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -10, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "gift for Alice",
+        },
+    )
+    assert response.status_code == 200
+    assert response.json()["description"] == MASKED_DESCRIPTION
+
+    stored = storage._user_transactions["no-auth"][0]
+    assert stored.description.startswith(ENCRYPTED_PREFIX)
+    assert "Alice" not in stored.description
+
+    response = client.get("/transactions")
+    assert response.status_code == 200
+    assert [t["description"] for t in response.json()] == [MASKED_DESCRIPTION]
+
+    response = client.post("/privacy/sensitive-view", json={})
+    assert response.status_code == 200
+    token = response.json()["token"]
+
+    response = client.get("/transactions", headers={"sensitive-view-token": token})
+    assert response.status_code == 200
+    assert [t["description"] for t in response.json()] == ["gift for Alice"]
+
+    response = client.get("/transactions", headers={"sensitive-view-token": "forged"})
+    assert response.status_code == 200
+    assert [t["description"] for t in response.json()] == [MASKED_DESCRIPTION]
+
+
+def test_sensitive_view_requires_reauthentication() -> None:
+    storage = InmemoryStorage()
+    auth = TokenAuth(
+        server_tokens=["server-token"],
+        auth_telegram_bot_token="123:fake",
+        storage=storage,
+        read_only_server_tokens=["ro-token"],
+    )
+    client = TestClient(
+        create_app(
+            storage=storage,
+            auth=auth,
+            exchange_rates=DumbExchangeRates(),
+            privacy=DescriptionPrivacy(key=Fernet.generate_key()),
+        )
+    )
+    headers = {"token": "server-token", "user-id": "user"}
+
+    async def log_in_through_bot(user_id: str) -> str:
+        start_param = auth._start_login(device_name=None)
+        assert auth._finish_login(start_param, user_id)
+        return start_param
+
+    read_only = {"token": "ro-token", "user-id": "user"}
+    assert client.post("/privacy/sensitive-view", headers=read_only, json={}).status_code == 403
+    response = client.post("/privacy/sensitive-view", headers=headers, json={})
+    assert response.status_code == 401
+    assert response.json() == {"detail": "New login required"}
+
+    body = {"start_param": asyncio.run(log_in_through_bot("someone else"))}
+    response = client.post("/privacy/sensitive-view", headers=headers, json=body)
+    assert response.status_code == 403
+    body = {"start_param": asyncio.run(log_in_through_bot("user"))}
+    response = client.post("/privacy/sensitive-view", headers=headers, json=body)
+    assert response.status_code == 200
+    token = response.json()["token"]
+    response = client.get("/transactions", headers={**headers, "sensitive-view-token": token})
+    assert response.status_code == 200
+    response = client.post("/privacy/sensitive-view", headers=headers, json=body)
+    assert response.status_code == 403  # one login, one token
+
+    secret = client.post("/auth/2fa/setup", headers=headers).json()["secret"]
+    code = totp.code_at(secret, totp.time_step(time.time()))
+    assert client.post("/auth/2fa/confirm", headers=headers, params={"code": code}).is_success
+    body = {"start_param": asyncio.run(log_in_through_bot("user"))}
+    response = client.post("/privacy/sensitive-view", headers=headers, json=body)
+    assert response.status_code == 401
+    assert response.json() == {"detail": "Two-factor code required"}
+    next_code = totp.code_at(secret, totp.time_step(time.time()) + 1)
+    response = client.post("/privacy/sensitive-view", headers=headers, json={"code": next_code})
+    assert response.status_code == 200
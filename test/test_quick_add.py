@@ -0,0 +1,68 @@
+from decimal import Decimal
+
+import pytest
+from fastapi.testclient import TestClient
+
+from api.iso4217 import CURRENCIES
+from api.quick_add import QuickEntry, parse_quick_entry
+
+CATEGORIES = ["Groceries", "transport"]
+
+
+@pytest.mark.parametrize(
+    "text,expected",
+    [
+        (
+            "14.99 USD groceries lidl",
+            QuickEntry(Decimal("-14.99"), CURRENCIES["USD"], ["Groceries"], "lidl"),
+        ),
+        ("eur 3,50 coffee", QuickEntry(Decimal("-3.50"), CURRENCIES["EUR"], [], "coffee")),
+        ("$5 transport", QuickEntry(Decimal(-5), CURRENCIES["USD"], ["transport"], "transport")),
+        ("lunch 12€", QuickEntry(Decimal(-12), CURRENCIES["EUR"], [], "lunch")),
+        ("+1200 salary #work", QuickEntry(Decimal(1200), None, ["work"], "salary")),
+        ("7eleven 4.20 snacks", QuickEntry(Decimal("-4.20"), None, [], "7eleven snacks")),
+        ("10 all you can eat", QuickEntry(Decimal(-10), None, [], "all you can eat")),
+        ("10 ALL tirana taxi", QuickEntry(Decimal(-10), CURRENCIES["ALL"], [], "tirana taxi")),
+        ("2 coffees 9 EUR", QuickEntry(Decimal(-2), None, [], "coffees 9 EUR")),
+    ],
+)
+def test_parse_quick_entry(text: str, expected: QuickEntry) -> None:
+    assert parse_quick_entry(text, CATEGORIES) == expected
+
+
+@pytest.mark.parametrize("text", ["coffee", "0 EUR coffee", "¥500 ramen"])
+def test_parse_quick_entry_errors(text: str) -> None:
+    with pytest.raises(ValueError):
+        parse_quick_entry(text, CATEGORIES)
+
+
+def test_quick_add_api(client: TestClient) -> None:
+    response = client.post("/quick", json={"text": "14.99 EUR groceries"})
+    assert response.status_code == 400
+    assert response.json()["detail"] == "Default pool is not set"
+
+    pool_id = client.post(
+        "/pools", json={"display_name": "card", "balance": [{"amount": 100, "currency": "EUR"}]}
+    ).json()["id"]
+    assert client.put("/settings", json={"default_pool_id": pool_id}).status_code == 200
+    response = client.post(
+        "/transactions",
+        json={
+            "sum": {"amount": -3, "currency": "EUR"},
+            "pool_id": pool_id,
+            "description": "bread",
+            "tags": ["groceries"],
+        },
+    )
+    assert response.status_code == 200
+
+    response = client.post("/quick", json={"text": "14.99 groceries lidl"})
+    assert response.status_code == 200
+    transaction = response.json()
+    assert transaction["sum"] == {"amount": "-14.99", "currency": "EUR"}
+    assert (transaction["description"], transaction["tags"]) == ("lidl", ["groceries"])
+    assert transaction["pool_id"] == pool_id
+
+    assert client.post("/quick", json={"text": "14.99 groceries lidl"}).status_code == 409
+    assert client.post("/quick", json={"text": "groceries"}).status_code == 400
+    assert client.post("/quick", json={"text": ""}).status_code == 422
@@ -0,0 +1,75 @@
+import datetime
+from decimal import Decimal
+from test.utils import EUR, transaction
+
+from api.iso4217 import CURRENCIES
+from api.rebuild import pool_balance_at, rebuild_pool_balance
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import TransactionStatus
+
+USD = CURRENCIES["USD"]
+
+
+def test_rebuild_pool_balance() -> None:
+    pool = MoneyPool(
+        display_name="cash",
+        # drifted from the transactions
+        balance=[MoneySum(amount=Decimal("42"), currency=EUR)],
+        initial_balance=[
+            MoneySum(amount=Decimal("100"), currency=EUR),
+            MoneySum(amount=Decimal("10"), currency=USD),
+        ],
+    )
+
+    balance, initial_balance = rebuild_pool_balance(
+        pool, [transaction("-30"), transaction("-20.5"), transaction("5", USD)]
+    )
+
+    assert balance == [
+        MoneySum(amount=Decimal("49.5"), currency=EUR),
+        MoneySum(amount=Decimal("15"), currency=USD),
+    ]
+    assert initial_balance == pool.initial_balance
+
+
+def test_rebuild_pool_balance_backfills_initial_balance() -> None:
+    pool = MoneyPool(
+        display_name="cash",
+        balance=[MoneySum(amount=Decimal("49.5"), currency=EUR)],
+    )
+
+    balance, initial_balance = rebuild_pool_balance(
+        pool, [transaction("-30"), transaction("-20.5")]
+    )
+
+    assert balance == pool.balance
+    assert initial_balance == [MoneySum(amount=Decimal("100"), currency=EUR)]
+
+
+def test_rebuild_pool_balance_skips_void_transactions() -> None:
+    pool = MoneyPool(
+        display_name="cash",
+        balance=[MoneySum(amount=Decimal("70"), currency=EUR)],
+        initial_balance=[MoneySum(amount=Decimal("100"), currency=EUR)],
+    )
+    void = transaction("-50", status=TransactionStatus.VOID)
+
+    balance, _ = rebuild_pool_balance(pool, [transaction("-30"), void])
+
+    assert balance == pool.balance
+
+
+def test_pool_balance_at() -> None:
+    pool = MoneyPool(
+        display_name="cash",
+        balance=[MoneySum(amount=Decimal("49.5"), currency=EUR)],
+    )
+    day = datetime.datetime(2024, 3, 1, tzinfo=datetime.UTC)
+    rent = transaction("-30", timestamp=day)
+    groceries = transaction("-20.5", timestamp=day + datetime.timedelta(days=1))
+
+    assert pool_balance_at(pool, [rent, groceries], day) is None
+    assert pool_balance_at(pool, [rent, groceries], groceries.timestamp) == [
+        MoneySum(amount=Decimal("70"), currency=EUR)
+    ]
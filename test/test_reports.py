@@ -0,0 +1,397 @@
+import asyncio
+import datetime
+import zoneinfo
+from decimal import Decimal
+from test.utils import NoGbpExchangeRates
+
+import pydantic
+import pytest
+
+from api.exchange_rates import DumbExchangeRates
+from api.historical_rates import DatedExchangeRates
+from api.iso4217 import CURRENCIES
+from api.reports import cash_flow, net_worth, spending_by_category, tag_net_totals
+from api.storage import InmemoryStorage
+from api.types.historical_rates import DailyRates
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.settings import ReportingPeriod
+from api.types.transaction import StoredTransaction, TransactionKind, TransactionSplit
+
+
+def test_spending_by_category() -> None:
+    eur = CURRENCIES["EUR"]
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    end = start + datetime.timedelta(days=30)
+
+    def transaction(amount: float, days: int, tags: list[str], **kwargs) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{days}",
+            sum=MoneySum(amount=Decimal(amount), currency=eur),
+            pool_id="pool",
+            description="",
+            timestamp=start + datetime.timedelta(days=days),
+            amount_eur=amount,
+            tags=tags,
+            **kwargs,
+        )
+
+    transactions = [
+        transaction(-30, -20, ["food"]),
+        transaction(-60, 1, ["food"]),
+        transaction(-20, 2, ["food"]),
+        transaction(-20, 3, ["fun"]),
+        transaction(-5, 4, []),
+        transaction(-10, 4, ["food"], is_diffuse=True),
+        transaction(1000, 5, ["salary"]),
+        transaction(-100, 6, ["transfer"], transfer_id="t"),
+    ]
+
+    report = asyncio.run(
+        spending_by_category(
+            transactions,
+            exchange_rates=DumbExchangeRates(),
+            start=start,
+            end=end,
+            target_currency=eur,
+        )
+    )
+
+    assert report.previous_start == start - datetime.timedelta(days=30)
+    assert report.spent.amount == Decimal(115)
+    assert report.previous_spent.amount == Decimal(30)
+    assert [
+        (c.category, c.spent.amount, round(c.fraction, 2), c.previous_spent.amount, c.change)
+        for c in report.categories
+    ] == [
+        ("food", Decimal(80), 0.7, Decimal(30), 5 / 3),
+        ("fun", Decimal(20), 0.17, Decimal(0), None),
+        ("diffuse", Decimal(10), 0.09, Decimal(0), None),
+        (None, Decimal(5), 0.04, Decimal(0), None),
+    ]
+
+
+def test_spending_by_category_with_splits() -> None:
+    eur = CURRENCIES["EUR"]
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+    transactions = [
+        StoredTransaction(
+            id="supermarket",
+            sum=MoneySum(amount=Decimal(-50), currency=eur),
+            pool_id="pool",
+            description="",
+            timestamp=start,
+            amount_eur=-50,
+            tags=["food"],
+            splits=[
+                TransactionSplit(category="food", amount=Decimal(-35)),
+                TransactionSplit(category="household", amount=Decimal(-15)),
+            ],
+        ),
+        StoredTransaction(
+            id="cafe",
+            sum=MoneySum(amount=Decimal(-10), currency=eur),
+            pool_id="pool",
+            description="",
+            timestamp=start,
+            tags=["food"],
+        ),
+    ]
+
+    report = asyncio.run(
+        spending_by_category(
+            transactions,
+            exchange_rates=DumbExchangeRates(),
+            start=start,
+            end=start + datetime.timedelta(days=30),
+            target_currency=eur,
+        )
+    )
+
+    assert report.spent.amount == Decimal(60)
+    assert [(c.category, c.spent.amount, round(c.fraction, 2)) for c in report.categories] == [
+        ("food", Decimal(45), 0.75),
+        ("household", Decimal(15), 0.25),
+    ]
+
+
+def test_foreign_spending() -> None:
+    eur = CURRENCIES["EUR"]
+    start = datetime.datetime(year=2024, month=9, day=1, tzinfo=datetime.UTC)
+
+    def transaction(amount: str, original: tuple[str, str] | None) -> StoredTransaction:
+        return StoredTransaction(
+            id=amount,
+            sum=MoneySum(amount=Decimal(amount), currency=eur),
+            pool_id="pool",
+            description="",
+            timestamp=start,
+            original_amount=Decimal(original[0]) if original else None,
+            original_currency=CURRENCIES[original[1]] if original else None,
+        )
+
+    report = asyncio.run(
+        spending_by_category(
+            [
+                transaction("-10", ("-11", "USD")),
+                transaction("-5", ("-5.50", "USD")),
+                transaction("-20", ("-2000", "JPY")),
+                transaction("-7", None),
+            ],
+            exchange_rates=DumbExchangeRates(),
+            start=start,
+            end=start + datetime.timedelta(days=30),
+            target_currency=eur,
+        )
+    )
+
+    assert [(f.paid, f.charged.amount) for f in report.foreign_spending] == [
+        (MoneySum(amount=Decimal(2000), currency=CURRENCIES["JPY"]), Decimal(20)),
+        (MoneySum(amount=Decimal("16.50"), currency=CURRENCIES["USD"]), Decimal(15)),
+    ]
+
+
+def test_tag_net_totals_with_unavailable_rate() -> None:
+    def transaction(amount: float, currency: str, tags: list[str]) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{currency}",
+            sum=MoneySum(amount=Decimal(amount), currency=CURRENCIES[currency]),
+            pool_id="pool",
+            description="",
+            tags=tags,
+        )
+
+    totals = asyncio.run(
+        tag_net_totals(
+            [
+                transaction(-10, "USD", ["food"]),
+                transaction(-5, "GBP", ["food"]),
+                transaction(-7, "GBP", ["food"]),
+                transaction(-20, "GBP", ["fun"]),
+            ],
+            NoGbpExchangeRates(),
+            target_currency=CURRENCIES["EUR"],
+        )
+    )
+    assert [(t.tag, t.total.amount, t.unconverted) for t in totals] == [
+        ("food", Decimal("-10"), [MoneySum(amount=Decimal(-12), currency=CURRENCIES["GBP"])]),
+        ("fun", Decimal("0"), [MoneySum(amount=Decimal(-20), currency=CURRENCIES["GBP"])]),
+    ]
+
+
+def test_cash_flow() -> None:
+    eur = CURRENCIES["EUR"]
+    start = datetime.datetime(year=2024, month=11, day=15, tzinfo=datetime.UTC)
+    end = datetime.datetime(year=2025, month=1, day=10, tzinfo=datetime.UTC)
+
+    def transaction(amount: float, day: datetime.date, **kwargs) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{day}",
+            sum=MoneySum(amount=Decimal(amount), currency=eur),
+            pool_id="pool",
+            description="",
+            timestamp=datetime.datetime.combine(day, datetime.time(12), tzinfo=datetime.UTC),
+            amount_eur=amount,
+            **kwargs,
+        )
+
+    transactions = [
+        transaction(-10, datetime.date(2024, 11, 1)),  # before the period
+        transaction(-30, datetime.date(2024, 11, 20)),
+        transaction(1000, datetime.date(2024, 11, 25)),
+        transaction(-200, datetime.date(2024, 11, 26), transfer_id="t"),
+        transaction(200, datetime.date(2024, 11, 26), transfer_id="t"),
+        transaction(-50, datetime.date(2025, 1, 5)),
+    ]
+    assert [t.kind for t in transactions[2:4]] == [
+        TransactionKind.INCOME,
+        TransactionKind.TRANSFER,
+    ]
+
+    report = asyncio.run(
+        cash_flow(
+            transactions,
+            exchange_rates=DumbExchangeRates(),
+            start=start,
+            end=end,
+            target_currency=eur,
+        )
+    )
+    assert report.inflow == MoneySum(amount=Decimal(1000), currency=eur)
+    assert report.outflow == MoneySum(amount=Decimal(80), currency=eur)
+    assert report.net == MoneySum(amount=Decimal(920), currency=eur)
+    assert [(m.month, m.inflow.amount, m.outflow.amount, m.net.amount) for m in report.months] == [
+        ("2024-11", 1000, 30, 970),
+        ("2024-12", 0, 0, 0),
+        ("2025-01", 0, 50, -50),
+    ]
+
+
+def test_cash_flow_in_timezone() -> None:
+    eur = CURRENCIES["EUR"]
+    berlin = zoneinfo.ZoneInfo("Europe/Berlin")
+    transactions = [
+        StoredTransaction(
+            id="new year's eve",
+            sum=MoneySum(amount=Decimal(-40), currency=eur),
+            pool_id="pool",
+            description="",
+            # already January in Berlin
+            timestamp=datetime.datetime(2024, 12, 31, 23, 30, tzinfo=datetime.UTC),
+            amount_eur=-40,
+        )
+    ]
+
+    report = asyncio.run(
+        cash_flow(
+            transactions,
+            exchange_rates=DumbExchangeRates(),
+            start=datetime.datetime(2024, 12, 1, tzinfo=berlin),
+            end=datetime.datetime(2025, 2, 1, tzinfo=berlin),
+            target_currency=eur,
+            tz=berlin,
+        )
+    )
+    assert [(m.month, m.outflow.amount) for m in report.months] == [
+        ("2024-12", 0),
+        ("2025-01", 40),
+    ]
+
+
+def test_reporting_period() -> None:
+    def containing(period: ReportingPeriod, day: datetime.date) -> tuple[datetime.date, ...]:
+        moment = datetime.datetime.combine(day, datetime.time(12), tzinfo=datetime.UTC)
+        return tuple(dt.date() for dt in period.containing(moment))
+
+    calendar_month = ReportingPeriod()
+    assert containing(calendar_month, datetime.date(2024, 12, 31)) == (
+        datetime.date(2024, 12, 1),
+        datetime.date(2025, 1, 1),
+    )
+    payday = ReportingPeriod(start_day=25)
+    assert containing(payday, datetime.date(2024, 9, 25)) == (
+        datetime.date(2024, 9, 25),
+        datetime.date(2024, 10, 25),
+    )
+    assert containing(payday, datetime.date(2025, 1, 24)) == (
+        datetime.date(2024, 12, 25),
+        datetime.date(2025, 1, 25),
+    )
+    last_day = ReportingPeriod(start_day=31)
+    assert containing(last_day, datetime.date(2024, 3, 1)) == (
+        datetime.date(2024, 2, 29),
+        datetime.date(2024, 3, 31),
+    )
+    four_weeks = ReportingPeriod(every_weeks=4, anchor=datetime.date(2024, 9, 6))
+    assert containing(four_weeks, datetime.date(2024, 10, 3)) == (
+        datetime.date(2024, 9, 6),
+        datetime.date(2024, 10, 4),
+    )
+    assert containing(four_weeks, datetime.date(2024, 9, 5)) == (
+        datetime.date(2024, 8, 9),
+        datetime.date(2024, 9, 6),
+    )
+
+    invalid_periods = [
+        {"every_weeks": 4},
+        {"start_day": 25, "every_weeks": 2, "anchor": "2024-09-06"},
+    ]
+    for invalid in invalid_periods:
+        with pytest.raises(pydantic.ValidationError):
+            ReportingPeriod.model_validate(invalid)
+
+
+def test_cash_flow_per_pay_cycle() -> None:
+    eur = CURRENCIES["EUR"]
+
+    def transaction(amount: float, day: datetime.date) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{day}",
+            sum=MoneySum(amount=Decimal(amount), currency=eur),
+            pool_id="pool",
+            description="",
+            timestamp=datetime.datetime.combine(day, datetime.time(12), tzinfo=datetime.UTC),
+            amount_eur=amount,
+        )
+
+    report = asyncio.run(
+        cash_flow(
+            [
+                transaction(2000, datetime.date(2024, 9, 25)),
+                transaction(-100, datetime.date(2024, 10, 24)),
+                transaction(2000, datetime.date(2024, 10, 25)),
+            ],
+            exchange_rates=DumbExchangeRates(),
+            start=datetime.datetime(2024, 9, 1, tzinfo=datetime.UTC),
+            end=datetime.datetime(2024, 11, 1, tzinfo=datetime.UTC),
+            target_currency=eur,
+            reporting_period=ReportingPeriod(start_day=25),
+        )
+    )
+    assert [(m.month, m.start.day, m.net.amount) for m in report.months] == [
+        ("2024-08", 25, 0),
+        ("2024-09", 25, 1900),
+        ("2024-10", 25, 2000),
+    ]
+
+
+def test_cash_flow_at_historical_rates() -> None:
+    usd, jpy = CURRENCIES["USD"], CURRENCIES["JPY"]
+    storage = InmemoryStorage()
+    asyncio.run(
+        storage.save_historical_rates(
+            [
+                DailyRates(date=datetime.date(2024, 9, 2), rates={"USD": 1.0, "JPY": 150.0}),
+                DailyRates(date=datetime.date(2024, 10, 1), rates={"USD": 1.0, "JPY": 160.0}),
+            ]
+        )
+    )
+
+    def transaction(amount: float, day: datetime.date) -> StoredTransaction:
+        return StoredTransaction(
+            id=f"{amount}-{day}",
+            sum=MoneySum(amount=Decimal(amount), currency=usd),
+            pool_id="pool",
+            description="",
+            timestamp=datetime.datetime.combine(day, datetime.time(12), tzinfo=datetime.UTC),
+        )
+
+    report = asyncio.run(
+        cash_flow(
+            [
+                transaction(-10, datetime.date(2024, 9, 3)),
+                transaction(-10, datetime.date(2024, 10, 2)),
+            ],
+            exchange_rates=DatedExchangeRates(DumbExchangeRates(), storage),
+            start=datetime.datetime(2024, 9, 1, tzinfo=datetime.UTC),
+            end=datetime.datetime(2024, 11, 1, tzinfo=datetime.UTC),
+            target_currency=jpy,
+        )
+    )
+    assert [m.outflow.amount for m in report.months] == [Decimal(1500), Decimal(1600)]
+    assert report.outflow.amount == Decimal(3100)
+
+
+def test_net_worth() -> None:
+    def pool(*sums: tuple[int, str]) -> MoneyPool:
+        return MoneyPool(
+            display_name="pool",
+            balance=[MoneySum(amount=Decimal(a), currency=CURRENCIES[c]) for a, c in sums],
+        )
+
+    snapshot = asyncio.run(
+        net_worth(
+            [pool((100, "EUR"), (20, "USD")), pool((-30, "EUR")), pool((5, "GBP"))],
+            NoGbpExchangeRates(),
+            target_currency=CURRENCIES["EUR"],
+            date=datetime.date(2024, 9, 1),
+        )
+    )
+    assert [(s.amount, s.currency.code) for s in snapshot.balance] == [
+        (Decimal(70), "EUR"),
+        (Decimal(20), "USD"),
+        (Decimal(5), "GBP"),
+    ]
+    assert snapshot.total == MoneySum(amount=Decimal(90), currency=CURRENCIES["EUR"])
+    assert [s.currency.code for s in snapshot.unconverted] == ["GBP"]
+    assert snapshot.date == datetime.date(2024, 9, 1)
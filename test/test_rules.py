@@ -0,0 +1,43 @@
+from test.utils import transaction
+
+import pydantic
+import pytest
+
+from api.rules import categorize
+from api.types.rule import CategorizationRule, RuleMatch
+
+
+def test_rule_matching() -> None:
+    rule = CategorizationRule(pattern="lidl", category="groceries")
+    assert rule.matches(transaction("-5"), "LIDL Berlin")
+    assert not rule.matches(transaction("-5", transfer_id="t"), "LIDL Berlin")
+    assert not CategorizationRule(pattern="lidl", category="g", pool_id="other").matches(
+        transaction("-5"), "LIDL Berlin"
+    )
+    assert not CategorizationRule(
+        pattern="lidl", match=RuleMatch.STARTS_WITH, category="g"
+    ).matches(transaction("-5"), "Card payment LIDL")
+    regex = CategorizationRule(pattern=r"^uber\s*\*?trip", match=RuleMatch.REGEX, category="taxi")
+    assert regex.matches(transaction("-5"), "UBER *TRIP HELP.UBER.COM")
+
+    with pytest.raises(pydantic.ValidationError):
+        CategorizationRule(pattern="lidl")
+    with pytest.raises(pydantic.ValidationError):
+        CategorizationRule(pattern="(", match=RuleMatch.REGEX, category="g")
+
+
+def test_categorize() -> None:
+    rules = [
+        CategorizationRule(pattern="lidl", merchant="Lidl", category="groceries"),
+        CategorizationRule(pattern="lidl", merchant="LIDL GmbH", category="food"),
+        CategorizationRule(pattern="aldi", merchant="Aldi", category="groceries"),
+    ]
+    t = transaction("-5", description="LIDL BERLIN", tags=["food"])
+    update = categorize(rules, t, t.description)
+    assert update is not None
+    assert update.description == "Lidl"
+    assert update.tags == ["food", "groceries"]
+
+    t = transaction("-5", description="Lidl", tags=["groceries", "food"])
+    assert categorize(rules, t, t.description) is None
+    assert categorize(rules, transaction("-5", description="rent"), "rent") is None
@@ -0,0 +1,58 @@
+import asyncio
+import time
+
+from fastapi.testclient import TestClient
+
+from api.exchange_rates import DumbExchangeRates
+from api.sandbox import SANDBOX_USER_ID, RateLimiter, SandboxStorage, create_sandbox_app
+from api.types.money_pool import MoneyPool
+
+
+def test_sandbox() -> None:
+    client = TestClient(
+        create_sandbox_app(
+            exchange_rates=DumbExchangeRates(),
+            reset_interval_sec=3600,
+            requests_per_minute=2,
+        )
+    )
+
+    response = client.get("/pools")
+    assert response.status_code == 200
+    pools = response.json()
+    assert [p["display_name"] for p in pools] == ["Debit card", "Cash"]
+
+    response = client.get("/transactions", params={"count": 100})
+    assert response.status_code == 200
+    assert len(response.json()) == 6
+
+    response = client.get("/pools")
+    assert response.status_code == 429
+
+
+def test_sandbox_reset() -> None:
+    async def scenario() -> None:
+        storage = SandboxStorage(reset_interval_sec=0)
+        await storage.reset_if_stale()
+        await storage.add_pool(SANDBOX_USER_ID, MoneyPool(display_name="mine", balance=[]))
+        await storage.add_pool("someone", MoneyPool(display_name="other", balance=[]))
+
+        await storage.reset_if_stale()
+        pools = await storage.load_pools(SANDBOX_USER_ID)
+        assert [p.display_name for p in pools] == ["Debit card", "Cash"]
+        assert await storage.load_pools("someone") == []
+
+    asyncio.run(scenario())
+
+
+def test_rate_limiter_forgets_idle_clients() -> None:
+    limiter = RateLimiter(max_requests=1, window_sec=0.05, max_keys=2)
+    assert limiter.allow("a")
+    assert not limiter.allow("a")
+    assert limiter.allow("b")
+    assert limiter.allow("c")
+    assert len(limiter._request_times) == 2
+
+    time.sleep(0.1)
+    assert len(limiter._request_times) == 0
+    assert limiter.allow("a")
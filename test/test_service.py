@@ -0,0 +1,321 @@
+import asyncio
+import datetime
+from decimal import Decimal
+
+import pytest
+
+from api.events import EventBus
+from api.exchange_rates import DumbExchangeRates
+from api.rebuild import rebuild_pool_balance
+from api.service import (
+    ExpenseService,
+    InvalidTransaction,
+    PeriodLocked,
+    ServiceError,
+    transfer_legs,
+)
+from api.storage import InmemoryStorage, TransactionOrder
+from api.types.currency import parse_currency
+from api.types.events import BaseEvent
+from api.types.historical_rates import DailyRates
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.month_close import MonthClose, MonthCloseStatus
+from api.types.operation import OperationKind
+from api.types.rule import CategorizationRule
+from api.types.transaction import Transaction, TransactionFilter
+
+
+def make_service() -> tuple[ExpenseService, list[BaseEvent]]:
+    events = EventBus()
+    received: list[BaseEvent] = []
+
+    async def on_event(event: BaseEvent) -> None:
+        received.append(event)
+
+    events.subscribe(BaseEvent, on_event)
+    service = ExpenseService(
+        InmemoryStorage(),
+        DumbExchangeRates(),
+        events,
+        privacy=None,
+        undo_window=datetime.timedelta(minutes=5),
+        duplicate_window=datetime.timedelta(minutes=2),
+    )
+    return service, received
+
+
+def test_add_and_delete_transaction() -> None:
+    service, received = make_service()
+
+    async def scenario() -> None:
+        pool = await service.storage.add_pool(
+            "user",
+            MoneyPool(
+                display_name="card", balance=[MoneySum(amount=Decimal(100), currency="EUR")]
+            ),
+        )
+        transaction = Transaction(
+            sum=MoneySum(amount=Decimal(-10), currency="USD"),
+            pool_id=pool.id,
+            description="lunch",
+        )
+        stored, warnings = await service.add_transaction("user", transaction)
+        assert warnings == []
+        assert stored.sum.currency.code == "EUR"  # converted to the pool's currency
+        assert stored.original_currency is not None
+        assert stored.original_currency.code == "USD"
+        assert stored.amount_eur == -10
+
+        duplicate = Transaction(
+            sum=MoneySum(amount=Decimal(-10), currency="EUR"),
+            pool_id=pool.id,
+            description="lunch",
+        )
+        assert await service.find_duplicate("user", duplicate) == stored
+
+        assert await service.delete_transaction("user", stored.id)
+        assert not await service.delete_transaction("user", stored.id)
+
+        with pytest.raises(InvalidTransaction):
+            await service.add_transaction(
+                "user",
+                Transaction(
+                    sum=MoneySum(amount=Decimal(-1), currency="EUR"),
+                    pool_id="no-such-pool",
+                    description="",
+                ),
+            )
+
+    asyncio.run(scenario())
+
+    assert [e.type for e in received] == [  # type: ignore
+        "transaction_created",
+        "pool_balance_changed",
+        "transaction_deleted",
+        "pool_balance_changed",
+    ]
+
+
+def test_transfer_and_undo() -> None:
+    service, received = make_service()
+
+    async def scenario() -> None:
+        card, cash = [
+            await service.create_pool(
+                "user",
+                MoneyPool(
+                    display_name=name, balance=[MoneySum(amount=Decimal(100), currency="EUR")]
+                ),
+            )
+            for name in ("card", "cash")
+        ]
+        with pytest.raises(ServiceError):
+            transfer_legs(
+                card,
+                cash,
+                MoneySum(amount=Decimal(0), currency="EUR"),
+                "",
+                timestamp=datetime.datetime.now(tz=datetime.UTC),
+                tags=[],
+            )
+        legs = transfer_legs(
+            card,
+            cash,
+            MoneySum(amount=Decimal(30), currency="EUR"),
+            "atm",
+            timestamp=datetime.datetime.now(tz=datetime.UTC),
+            tags=[],
+        )
+        debit, credit = await service.add_transactions("user", legs)
+        assert debit.transfer_id == credit.transfer_id is not None
+        received.clear()
+
+        operation = await service.undo_last_operation("user")
+        assert operation is not None
+        assert operation.kind is OperationKind.CREATE
+        assert [t.id for t in operation.transactions] == [debit.id, credit.id]
+        for pool in (card, cash):
+            reloaded = await service.storage.load_pool("user", pool.id)
+            assert reloaded is not None
+            assert [str(s) for s in reloaded.balance] == ["100.00 EUR"]
+        assert await service.undo_last_operation("user") is None
+
+    asyncio.run(scenario())
+
+    assert [e.type for e in received] == [  # type: ignore
+        "transaction_deleted",
+        "transaction_deleted",
+        "pool_balance_changed",
+        "pool_balance_changed",
+    ]
+
+
+def test_closed_month_is_locked() -> None:
+    service, _ = make_service()
+
+    async def scenario() -> None:
+        pool = await service.storage.add_pool(
+            "user",
+            MoneyPool(display_name="cash", balance=[MoneySum(amount=Decimal(0), currency="EUR")]),
+        )
+        await service.storage.save_month_close(
+            "user", MonthClose(year=2024, month=3, status=MonthCloseStatus.CLOSED)
+        )
+        transaction = Transaction(
+            timestamp=datetime.datetime(2024, 3, 15, tzinfo=datetime.UTC),
+            sum=MoneySum(amount=Decimal(-5), currency="EUR"),
+            pool_id=pool.id,
+            description="coffee",
+        )
+        with pytest.raises(PeriodLocked, match="2024-03"):
+            await service.add_transaction("user", transaction)
+
+        transaction.timestamp = datetime.datetime(2024, 4, 1, tzinfo=datetime.UTC)
+        await service.add_transaction("user", transaction)
+
+    asyncio.run(scenario())
+
+
+def test_categorization_rules() -> None:
+    service, _ = make_service()
+
+    async def scenario() -> None:
+        pool = await service.storage.add_pool(
+            "user",
+            MoneyPool(display_name="card", balance=[MoneySum(amount=Decimal(0), currency="EUR")]),
+        )
+
+        def groceries(description: str) -> Transaction:
+            return Transaction(
+                sum=MoneySum(amount=Decimal(-20), currency="EUR"),
+                pool_id=pool.id,
+                description=description,
+            )
+
+        before, _ = await service.add_transaction("user", groceries("LIDL BERLIN 1234"))
+        rule = await service.storage.add_rule(
+            "user", CategorizationRule(pattern="lidl", category="groceries", merchant="Lidl")
+        )
+        after, _ = await service.add_transaction("user", groceries("Card payment LIDL"))
+        assert (after.description, after.tags) == ("Lidl", ["groceries"])
+
+        updated, locked = await service.apply_rule("user", rule)
+        assert [t.id for t in updated] == [before.id]
+        assert locked == 0
+        reloaded = await service.storage.load_transaction("user", before.id)
+        assert reloaded is not None
+        assert (reloaded.description, reloaded.tags) == ("Lidl", ["groceries"])
+
+        updated, _ = await service.apply_rule("user", rule)
+        assert updated == []
+
+    asyncio.run(scenario())
+
+
+def test_pool_default_category() -> None:
+    service, _ = make_service()
+
+    async def scenario() -> None:
+        pool = await service.storage.add_pool(
+            "user",
+            MoneyPool(
+                display_name="cash",
+                balance=[MoneySum(amount=Decimal(100), currency="EUR")],
+                default_category="misc",
+            ),
+        )
+        await service.storage.add_rule(
+            "user", CategorizationRule(pattern="bus", category="transport")
+        )
+
+        async def add(description: str, tags: list[str]) -> list[str]:
+            transaction = Transaction(
+                sum=MoneySum(amount=Decimal(-2), currency="EUR"),
+                pool_id=pool.id,
+                description=description,
+                tags=tags,
+            )
+            stored, _ = await service.add_transaction("user", transaction)
+            return stored.tags
+
+        assert await add("ice cream", []) == ["misc"]
+        assert await add("flowers", ["gifts"]) == ["gifts"]
+        assert await add("bus ticket", []) == ["transport"]
+
+    asyncio.run(scenario())
+
+
+def test_redenominate_pool() -> None:
+    service, _ = make_service()
+    eur, usd = parse_currency("EUR"), parse_currency("USD")
+    day = datetime.datetime(2024, 3, 1, 12, tzinfo=datetime.UTC)
+
+    async def scenario() -> None:
+        await service.storage.save_historical_rates(
+            [DailyRates(date=day.date(), rates={"USD": 1.5})]
+        )
+
+        async def add_pool_with_transactions(name: str) -> str:
+            balance = [MoneySum(amount=Decimal(100), currency=eur)]
+            pool = await service.create_pool("user", MoneyPool(display_name=name, balance=balance))
+            for amount, timestamp in ((-10, day), (-20, day + datetime.timedelta(days=30))):
+                transaction = Transaction(
+                    sum=MoneySum(amount=Decimal(amount), currency=eur),
+                    pool_id=pool.id,
+                    description="groceries",
+                    timestamp=timestamp,
+                )
+                await service.add_transaction("user", transaction)
+            return pool.id
+
+        async def rebuilds_to_itself(pool_id: str) -> bool:
+            pool = await service.storage.load_pool("user", pool_id)
+            assert pool is not None
+            transactions = await service.storage.load_transactions(
+                "user", TransactionFilter(pool_ids=[pool_id]), TransactionOrder.LATEST, 0, 100
+            )
+            return rebuild_pool_balance(pool, transactions) == (
+                pool.balance,
+                pool.initial_balance,
+            )
+
+        converted = await add_pool_with_transactions("converted")
+        with pytest.raises(ServiceError):
+            await service.redenominate_pool("user", converted, None, eur, None, True)
+        with pytest.raises(ServiceError):
+            await service.redenominate_pool("user", converted, usd, eur, None, True)
+        assert await service.redenominate_pool("user", "missing", None, usd, None, True) is None
+
+        # historical rate where there's one for the day, the current one (1.0) otherwise
+        redenomination = await service.redenominate_pool("user", converted, None, usd, None, True)
+        assert redenomination is not None
+        assert [c.rate for c in redenomination.transactions] == [1.5, 1.0]
+        pool = await service.storage.load_pool("user", converted)
+        assert pool is not None
+        assert [str(s) for s in pool.balance] == ["65.00 USD"]
+        assert pool.initial_balance is not None
+        assert [str(s) for s in pool.initial_balance] == ["100.00 USD"]
+        transactions = await service.storage.load_transactions(
+            "user", TransactionFilter(pool_ids=[pool.id]), TransactionOrder.OLDEST, 0, 100
+        )
+        assert [(str(t.sum), t.original_amount) for t in transactions] == [
+            ("-15.00 USD", Decimal(-10)),
+            ("-20.00 USD", Decimal(-20)),
+        ]
+        assert await rebuilds_to_itself(pool.id)
+
+        kept = await add_pool_with_transactions("kept")
+        await service.redenominate_pool("user", kept, eur, usd, 2.0, False)
+        pool = await service.storage.load_pool("user", kept)
+        assert pool is not None
+        assert [str(s) for s in pool.balance] == ["140.00 USD", "0.00 EUR"]
+        assert await rebuilds_to_itself(pool.id)
+        # the transactions left in the old currency can still be deleted
+        [groceries, *_] = await service.storage.load_transactions(
+            "user", TransactionFilter(pool_ids=[pool.id]), TransactionOrder.LATEST, 0, 100
+        )
+        assert await service.delete_transaction("user", groceries.id)
+        assert await rebuilds_to_itself(pool.id)
+
+    asyncio.run(scenario())
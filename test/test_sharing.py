@@ -0,0 +1,25 @@
+from decimal import Decimal
+from test.utils import eur
+
+from api.sharing import share_portions
+from api.types.api import ExpenseShare
+
+
+def test_share_portions() -> None:
+    alice = ExpenseShare(counterparty="Alice", shares=Decimal(1))
+    bob = ExpenseShare(counterparty="Bob", shares=Decimal(2))
+
+    assert share_portions(eur("100"), Decimal(1), [alice, bob]) == [eur("25"), eur("50")]
+    # the user absorbs the rounding
+    assert share_portions(eur("10"), Decimal(1), [alice, alice]) == [eur("3.33"), eur("3.33")]
+    # unless paying only for the others
+    assert share_portions(eur("10"), Decimal(0), [alice, alice, alice]) == [
+        eur("3.33"),
+        eur("3.33"),
+        eur("3.34"),
+    ]
+    percentages = [
+        ExpenseShare(counterparty="Alice", shares=Decimal(30)),
+        ExpenseShare(counterparty="Bob", shares=Decimal(20)),
+    ]
+    assert share_portions(eur("80"), Decimal(50), percentages) == [eur("24"), eur("16")]
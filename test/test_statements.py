@@ -0,0 +1,97 @@
+import datetime
+from decimal import Decimal
+
+import pytest
+
+from api.statements import (
+    StatementFormat,
+    detect_format,
+    is_imported,
+    ofx_currency,
+    parse_ofx,
+    parse_qif,
+    to_transaction,
+)
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+OFX_SGML = """OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>EUR
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20240131120000.000[-5:EST]
+<TRNAMT>-12.50
+<FITID>2024013101
+<NAME>LIDL BERLIN
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20240201
+<TRNAMT>1500,00
+<FITID>2024020101
+<MEMO>Salary
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+"""
+
+QIF = """!Type:Bank
+D01/31'24
+T-12.50
+PLIDL BERLIN
+^
+D31.01.2024
+T-1,200.00
+MRent
+^
+"""
+
+
+def test_parse_ofx() -> None:
+    assert detect_format(OFX_SGML) is StatementFormat.OFX
+    assert ofx_currency(OFX_SGML) == "EUR"
+    lidl, salary = parse_ofx(OFX_SGML)
+    assert lidl.timestamp == datetime.datetime(2024, 1, 31, 17, tzinfo=datetime.UTC)
+    assert lidl.amount == Decimal("-12.50")
+    assert (lidl.description, lidl.fitid) == ("LIDL BERLIN", "2024013101")
+    assert salary.amount == Decimal(1500)
+    assert salary.description == "Salary"
+
+    xml = "<OFX><STMTTRN><DTPOSTED>20240131</DTPOSTED><TRNAMT>-1</TRNAMT></STMTTRN></OFX>"
+    [entry] = parse_ofx(xml)
+    assert entry.fitid is None
+    with pytest.raises(ValueError, match="Transaction 1"):
+        parse_ofx("<OFX><STMTTRN><TRNAMT>-1</STMTTRN></OFX>")
+
+
+def test_parse_qif() -> None:
+    assert detect_format(QIF) is StatementFormat.QIF
+    lidl, rent = parse_qif(QIF)
+    assert lidl.timestamp.date() == rent.timestamp.date() == datetime.date(2024, 1, 31)
+    assert (lidl.amount, lidl.description) == (Decimal("-12.50"), "LIDL BERLIN")
+    assert (rent.amount, rent.description) == (Decimal(-1200), "Rent")
+    with pytest.raises(ValueError, match="line 3"):
+        parse_qif("!Type:Bank\nT-1\n^\n")
+
+
+def test_is_imported() -> None:
+    lidl, salary = parse_ofx(OFX_SGML)
+    imported = to_transaction(lidl, "pool", "EUR", StatementFormat.OFX)  # type: ignore
+    assert imported.source is not None and imported.source.external_id == lidl.fitid
+    assert is_imported(lidl, [imported])
+    assert not is_imported(salary, [imported])
+
+    entered_by_hand = Transaction(
+        sum=MoneySum(amount=Decimal(1500), currency="EUR"),  # type: ignore
+        pool_id="pool",
+        description="salary",
+        timestamp=datetime.datetime(2024, 2, 1, 18, tzinfo=datetime.UTC),
+    )
+    assert is_imported(salary, [entered_by_hand])
+    [rent] = parse_qif(QIF)[1:]
+    assert not is_imported(rent, [entered_by_hand])
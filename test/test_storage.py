@@ -0,0 +1,661 @@
+import asyncio
+import datetime
+import os
+import uuid
+from decimal import Decimal
+from pathlib import Path
+from test.utils import eur
+from typing import Awaitable, Callable
+
+import pytest
+
+from api.storage import (
+    InmemoryStorage,
+    MongoDbStorage,
+    Storage,
+    TransactionOrder,
+    VersionConflict,
+)
+from api.types.api import (
+    MoneyPoolAttributesUpdate,
+    ReportApiRouteResponse,
+    TransactionEdit,
+    TransactionUpdate,
+)
+from api.types.historical_rates import DailyRates
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.net_worth import NetWorthSnapshot
+from api.types.payee import Payee, StoredPayee
+from api.types.report_snapshot import ReportSnapshot
+from api.types.settings import UserSettings
+from api.types.suggestion import Suggestion, SuggestionField
+from api.types.sync import SyncedEntity
+from api.types.transaction import Transaction, TransactionFilter, TransactionStatus
+from api.types.two_factor import TwoFactor
+from api.wal import WriteAheadLog
+
+# contract tests, run against every backend; MongoDB ones need a replica set (for transactions)
+# at TEST_MONGODB_URL and only touch the data of the random users they create
+
+StorageScenario = Callable[[Storage, str], Awaitable[None]]
+MISSING_ID = "0" * 24  # valid as an ObjectId, so that MongoDB doesn't reject it outright
+START = datetime.datetime(2024, 1, 1, tzinfo=datetime.UTC)
+
+
+@pytest.fixture(params=["inmemory", "mongodb"])
+def run_with_storage(request: pytest.FixtureRequest) -> Callable[[StorageScenario], None]:
+    mongodb_url = os.environ.get("TEST_MONGODB_URL")
+    if request.param == "mongodb" and not mongodb_url:
+        pytest.skip("TEST_MONGODB_URL is not set")
+
+    def run(scenario: StorageScenario) -> None:
+        async def wrapper() -> None:
+            # created inside the loop, the MongoDB client binds to it
+            storage = (
+                MongoDbStorage(url=mongodb_url)
+                if request.param == "mongodb" and mongodb_url
+                else InmemoryStorage()
+            )
+            await storage.initialize()
+            user_id = f"test-{uuid.uuid4().hex}"
+            try:
+                await scenario(storage, user_id)
+            finally:
+                await storage.delete_user_data(user_id)
+                await storage.close()
+
+        asyncio.run(wrapper())
+
+    return run
+
+
+async def add_pool(storage: Storage, user_id: str, name: str = "cash", amount: int = 100) -> str:
+    pool = await storage.add_pool(user_id, MoneyPool(display_name=name, balance=[eur(amount)]))
+    return pool.id
+
+
+async def add_transaction(
+    storage: Storage, user_id: str, pool_id: str, amount: int, day: int, description: str = ""
+) -> str:
+    transaction = await storage.add_transaction(
+        user_id,
+        Transaction(
+            sum=eur(amount),
+            pool_id=pool_id,
+            description=description,
+            timestamp=START + datetime.timedelta(days=day),
+            amount_eur=amount,
+        ),
+    )
+    return transaction.id
+
+
+def test_pools(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        assert await storage.load_pools(user_id) == []
+        cash_id = await add_pool(storage, user_id, "cash")
+        card_id = await add_pool(storage, user_id, "card", amount=500)
+        assert [p.id for p in await storage.load_pools(user_id)] == [cash_id, card_id]
+        assert await storage.load_pool(user_id, MISSING_ID) is None
+        assert await storage.load_pool("someone else", cash_id) is None
+
+        update = MoneyPoolAttributesUpdate(display_name="wallet")
+        assert await storage.set_pool_attributes(user_id, cash_id, update)
+        pool = await storage.load_pool(user_id, cash_id)
+        assert pool is not None
+        assert (pool.display_name, pool.version, pool.balance) == ("wallet", 1, [eur(100)])
+        with pytest.raises(VersionConflict):
+            await storage.set_pool_attributes(user_id, cash_id, update, expected_version=0)
+        assert not await storage.set_pool_attributes(user_id, MISSING_ID, update)
+
+        await storage.set_pools_order(user_id, [card_id, cash_id])
+        assert [p.id for p in await storage.load_pools(user_id)] == [card_id, cash_id]
+
+    run_with_storage(scenario)
+
+
+def test_transactions(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        pool_id = await add_pool(storage, user_id)
+        rent_id = await add_transaction(storage, user_id, pool_id, -60, day=1, description="rent")
+        await add_transaction(storage, user_id, pool_id, -5, day=3, description="coffee")
+        await add_transaction(storage, user_id, pool_id, 20, day=2, description="refund")
+        pool = await storage.load_pool(user_id, pool_id)
+        assert pool is not None and pool.balance == [eur(55)]
+
+        async def descriptions(
+            filter: TransactionFilter | None, order: TransactionOrder = TransactionOrder.LATEST
+        ) -> list[str]:
+            loaded = await storage.load_transactions(user_id, filter, order, offset=0, count=10)
+            return [t.description for t in loaded]
+
+        assert await descriptions(None) == ["coffee", "refund", "rent"]
+        assert await descriptions(None, TransactionOrder.OLDEST) == ["rent", "refund", "coffee"]
+        assert await descriptions(None, TransactionOrder.LARGEST) == ["refund", "coffee", "rent"]
+        assert await descriptions(None, TransactionOrder.LARGEST_NEGATIVE) == [
+            "rent",
+            "coffee",
+            "refund",
+        ]
+        after_first_day = TransactionFilter(min_timestamp=START + datetime.timedelta(days=2))
+        assert await descriptions(after_first_day) == ["coffee", "refund"]
+        assert await descriptions(TransactionFilter(pool_ids=[MISSING_ID])) == []
+
+        assert await storage.update_transaction(
+            user_id, rent_id, TransactionUpdate(description="flat", tags=["home"])
+        )
+        rent = await storage.load_transaction(user_id, rent_id)
+        assert rent is not None
+        assert (rent.description, rent.tags, rent.version) == ("flat", ["home"], 1)
+        with pytest.raises(VersionConflict):
+            await storage.update_transaction(
+                user_id, rent_id, TransactionUpdate(tags=[]), expected_version=0
+            )
+        assert not await storage.update_transaction(
+            user_id, MISSING_ID, TransactionUpdate(tags=[])
+        )
+
+        assert await storage.delete_transaction(user_id, rent_id)
+        assert await storage.load_transaction(user_id, rent_id) is None
+        assert not await storage.delete_transaction(user_id, rent_id)
+        pool = await storage.load_pool(user_id, pool_id)
+        assert pool is not None and pool.balance == [eur(115)]
+        assert await storage.load_transactions(
+            "someone else", None, TransactionOrder.LATEST, offset=0, count=10
+        ) == []
+
+    run_with_storage(scenario)
+
+
+def test_transaction_changes(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        pool_id = await add_pool(storage, user_id)
+        rent_id = await add_transaction(storage, user_id, pool_id, -60, day=1, description="rent")
+        coffee_id = await add_transaction(storage, user_id, pool_id, -5, day=2)
+        tea = Transaction(sum=eur(-3), pool_id=pool_id, description="tea", timestamp=START)
+
+        async def balance() -> list[MoneySum]:
+            pool = await storage.load_pool(user_id, pool_id)
+            assert pool is not None
+            return pool.balance
+
+        rename = TransactionEdit(
+            transaction_id=rent_id,
+            expected_version=0,
+            update=TransactionUpdate(description="flat"),
+        )
+        stale = TransactionEdit(transaction_id=coffee_id, expected_version=1)
+        with pytest.raises(VersionConflict) as conflict:
+            await storage.apply_transaction_changes(user_id, [tea], [rename, stale])
+        assert conflict.value.entity_id == coffee_id
+        # nothing is applied
+        assert await balance() == [eur(35)]
+        rent = await storage.load_transaction(user_id, rent_id)
+        assert rent is not None and (rent.description, rent.version) == ("rent", 0)
+
+        delete = TransactionEdit(transaction_id=coffee_id, expected_version=0)
+        [added] = await storage.apply_transaction_changes(user_id, [tea], [rename, delete])
+        assert await balance() == [eur(37)]
+        assert await storage.load_transaction(user_id, added.id) == added
+        assert await storage.load_transaction(user_id, coffee_id) is None
+        rent = await storage.load_transaction(user_id, rent_id)
+        assert rent is not None and (rent.description, rent.version) == ("flat", 1)
+
+    run_with_storage(scenario)
+
+
+def test_transaction_status(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        pool_id = await add_pool(storage, user_id)
+        hold_id = await add_transaction(storage, user_id, pool_id, -30, day=1)
+        assert await storage.update_transaction_status(user_id, hold_id, TransactionStatus.PENDING)
+        pending = TransactionFilter(status=TransactionStatus.PENDING)
+        loaded = await storage.load_transactions(
+            user_id, pending, TransactionOrder.LATEST, offset=0, count=10
+        )
+        assert [t.id for t in loaded] == [hold_id]
+
+        assert await storage.update_transaction_status(user_id, hold_id, TransactionStatus.VOID)
+        hold = await storage.load_transaction(user_id, hold_id)
+        assert hold is not None and (hold.status, hold.version) == (TransactionStatus.VOID, 2)
+        pool = await storage.load_pool(user_id, pool_id)
+        assert pool is not None and pool.balance == [eur(100)]
+        # voiding twice doesn't revert twice
+        assert await storage.update_transaction_status(user_id, hold_id, TransactionStatus.VOID)
+        pool = await storage.load_pool(user_id, pool_id)
+        assert pool is not None and pool.balance == [eur(100)]
+
+        assert await storage.update_transaction_status(user_id, hold_id, TransactionStatus.PENDING)
+        pool = await storage.load_pool(user_id, pool_id)
+        assert pool is not None and pool.balance == [eur(70)]
+        with pytest.raises(VersionConflict):
+            await storage.update_transaction_status(
+                user_id, hold_id, TransactionStatus.CLEARED, expected_version=0
+            )
+        assert not await storage.update_transaction_status(
+            user_id, MISSING_ID, TransactionStatus.CLEARED
+        )
+
+    run_with_storage(scenario)
+
+
+def test_scheduled_transactions(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        pool_id = await add_pool(storage, user_id)
+        rent = await storage.add_transaction(
+            user_id,
+            Transaction(
+                sum=eur(-60),
+                pool_id=pool_id,
+                description="rent",
+                timestamp=START + datetime.timedelta(days=5),
+                status=TransactionStatus.SCHEDULED,
+            ),
+        )
+        pool = await storage.load_pool(user_id, pool_id)
+        assert pool is not None and pool.balance == [eur(100)]
+
+        async def due_ids(days: int) -> list[str]:
+            now = START + datetime.timedelta(days=days)
+            due = await storage.load_due_scheduled_transactions(now)
+            return [t.id for owner, t in due if owner == user_id]
+
+        assert await due_ids(4) == []
+        assert await due_ids(5) == [rent.id]
+        assert await storage.update_transaction_status(user_id, rent.id, TransactionStatus.CLEARED)
+        pool = await storage.load_pool(user_id, pool_id)
+        assert pool is not None and pool.balance == [eur(40)]
+        assert await due_ids(5) == []
+
+    run_with_storage(scenario)
+
+
+def test_payees(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        bakery = await storage.add_payee(user_id, Payee(name="Bakery"))
+        await storage.add_payee(user_id, Payee(name="Auto repair"))
+        assert [p.name for p in await storage.load_payees(user_id)] == ["Auto repair", "Bakery"]
+        assert await storage.save_payee(user_id, StoredPayee(id=bakery.id, name="Corner bakery"))
+        assert await storage.load_payee(user_id, bakery.id) == StoredPayee(
+            id=bakery.id, name="Corner bakery"
+        )
+        assert await storage.load_payee("someone else", bakery.id) is None
+
+        pool_id = await add_pool(storage, user_id)
+        bread_id = await add_transaction(storage, user_id, pool_id, -3, day=1)
+        await add_transaction(storage, user_id, pool_id, -5, day=2)
+        assert await storage.update_transaction(
+            user_id, bread_id, TransactionUpdate(payee_id=bakery.id)
+        )
+        by_payee = TransactionFilter(payee_ids=[bakery.id])
+        loaded = await storage.load_transactions(
+            user_id, by_payee, TransactionOrder.LATEST, offset=0, count=10
+        )
+        assert [t.id for t in loaded] == [bread_id]
+        assert await storage.update_transaction(user_id, bread_id, TransactionUpdate(payee_id=""))
+        bread = await storage.load_transaction(user_id, bread_id)
+        assert bread is not None and bread.payee_id is None
+
+        assert await storage.delete_payee(user_id, bakery.id)
+        assert not await storage.delete_payee(user_id, bakery.id)
+        assert [p.name for p in await storage.load_payees(user_id)] == ["Auto repair"]
+
+    run_with_storage(scenario)
+
+
+def test_suggestions(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        pool_id = await add_pool(storage, user_id)
+        bakery = await storage.add_payee(user_id, Payee(name="Bakery"))
+        for day, description, tags in [
+            (1, "Coffee", ["food"]),
+            (2, "coffee beans", ["food", "home"]),
+            (3, "Coffee", ["food"]),
+            (4, "rent", ["home"]),
+            (5, "coffee beans", []),
+        ]:
+            transaction_id = await add_transaction(storage, user_id, pool_id, -1, day, description)
+            update = TransactionUpdate(tags=tags, payee_id=bakery.id if day < 3 else None)
+            await storage.update_transaction(user_id, transaction_id, update)
+
+        async def suggest(
+            field: SuggestionField, query: str, days_since: int = 0
+        ) -> list[Suggestion]:
+            since = START + datetime.timedelta(days=days_since)
+            return await storage.load_suggestions(user_id, field, query, since, count=5)
+
+        assert await suggest(SuggestionField.DESCRIPTION, "coff") == [
+            Suggestion(value="coffee beans", count=2),  # used later than "Coffee"
+            Suggestion(value="Coffee", count=2),
+        ]
+        assert await suggest(SuggestionField.DESCRIPTION, "coff", days_since=3) == [
+            Suggestion(value="coffee beans", count=1),
+            Suggestion(value="Coffee", count=1),
+        ]
+        assert await suggest(SuggestionField.CATEGORY, "") == [
+            Suggestion(value="food", count=3),
+            Suggestion(value="home", count=2),
+        ]
+        assert await suggest(SuggestionField.PAYEE, "bak") == [
+            Suggestion(value="Bakery", payee_id=bakery.id, count=2)
+        ]
+        assert await suggest(SuggestionField.PAYEE, "shop") == []
+        assert await storage.load_suggestions(
+            "someone else", SuggestionField.DESCRIPTION, "", START, count=5
+        ) == []
+
+    run_with_storage(scenario)
+
+
+def test_change_counter(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        assert await storage.load_change_counter(user_id) == 0
+        pool_id = await add_pool(storage, user_id)
+        counters = [await storage.load_change_counter(user_id)]
+        transaction_id = await add_transaction(storage, user_id, pool_id, -5, day=1)
+        counters.append(await storage.load_change_counter(user_id))
+        await storage.update_transaction(user_id, transaction_id, TransactionUpdate(tags=["x"]))
+        counters.append(await storage.load_change_counter(user_id))
+        await storage.delete_transaction(user_id, transaction_id)
+        counters.append(await storage.load_change_counter(user_id))
+        await storage.set_pool_attributes(
+            user_id, pool_id, MoneyPoolAttributesUpdate(display_name="wallet")
+        )
+        counters.append(await storage.load_change_counter(user_id))
+        assert counters == sorted(set(counters)) and counters[0] > 0
+        await storage.save_user_settings(user_id, UserSettings(locale="de"))
+        assert await storage.load_change_counter(user_id) == counters[-1]
+        assert await storage.load_change_counter("someone else") == 0
+
+    run_with_storage(scenario)
+
+
+def test_changes(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        pool_id = await add_pool(storage, user_id)
+        coffee_id = await add_transaction(storage, user_id, pool_id, -5, day=1)
+        cursor = await storage.load_change_counter(user_id)
+        changes = await storage.load_changes(user_id, since=0)
+        assert {(c.entity, c.entity_id) for c in changes} == {
+            (SyncedEntity.POOL, pool_id),
+            (SyncedEntity.TRANSACTION, coffee_id),
+        }
+        assert changes[-1].seq == cursor
+
+        tea_id = await add_transaction(storage, user_id, pool_id, -3, day=2)
+        await storage.delete_transaction(user_id, coffee_id)
+        changes = await storage.load_changes(user_id, since=cursor)
+        assert [c.seq for c in changes] == sorted(c.seq for c in changes)
+        assert {(c.entity, c.entity_id, c.deleted) for c in changes} == {
+            (SyncedEntity.POOL, pool_id, False),
+            (SyncedEntity.TRANSACTION, tea_id, False),
+            (SyncedEntity.TRANSACTION, coffee_id, True),
+        }
+        cursor = await storage.load_change_counter(user_id)
+        assert await storage.load_changes(user_id, since=cursor) == []
+        assert await storage.load_changes("someone else", since=0) == []
+
+    run_with_storage(scenario)
+
+
+def test_user_data(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        assert await storage.load_user_settings(user_id) == UserSettings()
+        await storage.save_user_settings(user_id, UserSettings(locale="de"))
+        assert (await storage.load_user_settings(user_id)).locale == "de"
+
+        pool_id = await add_pool(storage, user_id)
+        await add_transaction(storage, user_id, pool_id, -1, day=1)
+        assert user_id in await storage.load_user_ids()
+        usage = await storage.load_storage_usage(user_id)
+        assert (usage["pools"], usage["transactions"], usage["user_settings"]) == (1, 1, 1)
+
+        assert not await storage.is_user_disabled(user_id)
+        await storage.set_user_disabled(user_id, True)
+        assert await storage.is_user_disabled(user_id)
+        await storage.set_user_disabled(user_id, False)
+        assert user_id not in await storage.load_disabled_user_ids()
+
+        await storage.delete_user_data(user_id)
+        assert await storage.load_pools(user_id) == []
+        assert await storage.load_user_settings(user_id) == UserSettings()
+        assert set((await storage.load_storage_usage(user_id)).values()) == {0}
+
+    run_with_storage(scenario)
+
+
+def test_net_worth_snapshots(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        eur = MoneySum(amount=Decimal(100), currency="EUR")
+
+        def snapshot(day: int, amount: int) -> NetWorthSnapshot:
+            total = MoneySum(amount=Decimal(amount), currency="EUR")
+            return NetWorthSnapshot(
+                date=START.date() + datetime.timedelta(days=day),
+                balance=[total],
+                total=total,
+                unconverted=[],
+            )
+
+        for day in (2, 0, 1):
+            await storage.save_net_worth_snapshot(user_id, snapshot(day, 100 + day))
+        await storage.save_net_worth_snapshot(user_id, snapshot(1, 50))  # later the same day
+
+        snapshots = await storage.load_net_worth_snapshots(
+            user_id, START.date(), START.date() + datetime.timedelta(days=1)
+        )
+        assert [(s.date.day, s.total.amount) for s in snapshots] == [(1, 100), (2, 50)]
+        assert snapshots[0].balance == [eur]
+        all_time = await storage.load_net_worth_snapshots(
+            user_id, datetime.date.min, datetime.date.max
+        )
+        assert len(all_time) == 3
+        assert not await storage.load_net_worth_snapshots("other", START.date(), START.date())
+
+        await storage.delete_user_data(user_id)
+        assert await storage.load_net_worth_snapshots(user_id, START.date(), START.date()) == []
+
+    run_with_storage(scenario)
+
+
+def test_report_snapshots(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        zero = MoneySum(amount=Decimal(0), currency="EUR")
+        report = ReportApiRouteResponse(snapshots=[], spent=zero, made=zero, tag_totals=[])
+
+        for month, day in [(1, 2), (2, 0), (3, 1), (4, 1)]:
+            await storage.add_report_snapshot(
+                user_id,
+                ReportSnapshot(
+                    year=2024,
+                    month=month,
+                    target_currency="EUR",
+                    points=0,
+                    created_at=START + datetime.timedelta(days=day),
+                    report=report,
+                ),
+            )
+
+        snapshots = await storage.load_report_snapshots(user_id)
+        assert [s.month for s in snapshots] == [1, 4, 3, 2]  # newest first, then the later added
+        assert await storage.load_report_snapshot(user_id, snapshots[0].id) == snapshots[0]
+        assert await storage.load_report_snapshots("other") == []
+
+    run_with_storage(scenario)
+
+
+def test_calendar_token(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        token = uuid.uuid4().hex
+        assert await storage.load_calendar_token(user_id) is None
+        await storage.save_calendar_token(user_id, token)
+        assert await storage.load_calendar_token(user_id) == token
+        assert await storage.load_calendar_token_owner(token) == user_id
+
+        new_token = uuid.uuid4().hex
+        await storage.save_calendar_token(user_id, new_token)
+        assert await storage.load_calendar_token_owner(token) is None
+        assert await storage.load_calendar_token_owner(new_token) == user_id
+        await storage.save_calendar_token(user_id, None)
+        assert await storage.load_calendar_token(user_id) is None
+        assert await storage.load_calendar_token_owner(new_token) is None
+
+    run_with_storage(scenario)
+
+
+def test_two_factor(run_with_storage: Callable[[StorageScenario], None]) -> None:
+    async def scenario(storage: Storage, user_id: str) -> None:
+        assert await storage.load_two_factor(user_id) is None
+        two_factor = TwoFactor(secret="JBSWY3DPEHPK3PXP", recovery_code_hashes=["a", "b"])
+        await storage.save_two_factor(user_id, two_factor)
+        assert await storage.load_two_factor(user_id) == two_factor
+
+        two_factor.confirmed = True
+        two_factor.last_used_step = 42
+        two_factor.recovery_code_hashes.remove("a")
+        await storage.save_two_factor(user_id, two_factor)
+        assert await storage.load_two_factor(user_id) == two_factor
+        await storage.save_two_factor(user_id, None)
+        assert await storage.load_two_factor(user_id) is None
+
+    run_with_storage(scenario)
+
+
+def test_inmemory_snapshot(tmp_path: Path) -> None:
+    snapshot_path = tmp_path / "storage.json"
+
+    async def scenario() -> None:
+        storage = InmemoryStorage(snapshot_path=snapshot_path)
+        await storage.initialize()
+        pool = await storage.add_pool(
+            "user",
+            MoneyPool(display_name="cash", balance=[MoneySum(amount=Decimal(50), currency="EUR")]),
+        )
+        transaction = await storage.add_transaction(
+            "user",
+            Transaction(
+                sum=MoneySum(amount=Decimal("-12.50"), currency="EUR"),
+                pool_id=pool.id,
+                description="books",
+                tags=["hobby"],
+            ),
+        )
+        await storage.save_user_settings("user", UserSettings(locale="de"))
+        await storage.save_historical_rates(
+            [DailyRates(date=datetime.date(2024, 1, 2), rates={"USD": 1.1})]
+        )
+        await storage.set_user_disabled("other", True)
+        await storage.close()
+        assert snapshot_path.exists()
+
+        restored = InmemoryStorage(snapshot_path=snapshot_path)
+        await restored.initialize()
+        assert await restored.load_pools("user") == [await storage.load_pool("user", pool.id)]
+        assert await restored.load_transaction("user", transaction.id) == transaction
+        assert (await restored.load_user_settings("user")).locale == "de"
+        rates = await restored.load_historical_rates(datetime.date(2024, 1, 3))
+        assert rates is not None and rates.rates == {"USD": 1.1}
+        assert await restored.load_disabled_user_ids() == ["other"]
+        await restored.close()
+
+    asyncio.run(scenario())
+
+
+def test_inmemory_wal(tmp_path: Path) -> None:
+    wal_path = tmp_path / "wal.jsonl"
+    snapshot_path = tmp_path / "storage.json"
+
+    async def scenario() -> None:
+        storage = InmemoryStorage(wal_path=wal_path)
+        await storage.initialize()
+        pool = await storage.add_pool(
+            "user",
+            MoneyPool(display_name="cash", balance=[MoneySum(amount=Decimal(50), currency="EUR")]),
+        )
+        transaction = await storage.add_transaction(
+            "user",
+            Transaction(
+                sum=MoneySum(amount=Decimal("-12.50"), currency="EUR"),
+                pool_id=pool.id,
+                description="books",
+            ),
+        )
+        await storage.update_transaction(
+            "user", transaction.id, TransactionUpdate(description="comics")
+        )
+        assert [r.method for r in WriteAheadLog(wal_path).read()] == [
+            "add_pool",
+            "add_transaction",
+            "update_transaction",
+        ]
+        # not closed, as if the process was killed
+
+        replayed = InmemoryStorage(wal_path=wal_path)
+        await replayed.initialize()
+        [replayed_pool] = await replayed.load_pools("user")
+        assert (replayed_pool.id, replayed_pool.balance) == (
+            pool.id,
+            [MoneySum(amount=Decimal("37.50"), currency="EUR")],
+        )
+        loaded = await replayed.load_transaction("user", transaction.id)
+        assert loaded is not None and loaded.description == "comics"
+        await replayed.close()
+
+        # records included in a snapshot are dropped from the log
+        with_snapshot = InmemoryStorage(snapshot_path=snapshot_path, wal_path=wal_path)
+        await with_snapshot.initialize()
+        await with_snapshot.save_snapshot()
+        assert WriteAheadLog(wal_path).read() == []
+        await with_snapshot.delete_transaction("user", transaction.id)
+        with wal_path.open("a") as f:
+            f.write('{"seq": 5, "meth')  # cut off by a crash
+        await with_snapshot.close()
+
+        restored = InmemoryStorage(snapshot_path=snapshot_path, wal_path=wal_path)
+        await restored.initialize()
+        assert await restored.load_transaction("user", transaction.id) is None
+        assert len(await restored.load_pools("user")) == 1
+        await restored.close()
+
+    asyncio.run(scenario())
+
+
+def test_inmemory_transaction_order() -> None:
+    storage = InmemoryStorage()
+    start = datetime.datetime(2024, 1, 1, tzinfo=datetime.UTC)
+
+    async def scenario() -> None:
+        pool = await storage.add_pool(
+            "user",
+            MoneyPool(display_name="cash", balance=[MoneySum(amount=Decimal(0), currency="EUR")]),
+        )
+        for day, description in [(3, "c"), (1, "a"), (2, "b1"), (5, "d"), (2, "b2")]:
+            await storage.add_transaction(
+                "user",
+                Transaction(
+                    sum=MoneySum(amount=Decimal(-day), currency="EUR"),
+                    pool_id=pool.id,
+                    description=description,
+                    timestamp=start + datetime.timedelta(days=day),
+                ),
+            )
+
+        def stored_descriptions() -> list[str]:
+            return [t.description for t in storage._user_transactions["user"]]
+
+        assert stored_descriptions() == ["a", "b1", "b2", "c", "d"]
+        oldest = await storage.load_transactions("user", None, TransactionOrder.OLDEST, 0, 10)
+        assert [t.description for t in oldest] == ["a", "b1", "b2", "c", "d"]
+
+        # loading in another order doesn't reorder the stored transactions
+        await storage.load_transactions("user", None, TransactionOrder.LARGEST, 0, 10)
+        assert stored_descriptions() == ["a", "b1", "b2", "c", "d"]
+
+        [a, *_] = oldest
+        await storage.update_transaction(
+            "user", a.id, TransactionUpdate(timestamp=start + datetime.timedelta(days=4))
+        )
+        assert stored_descriptions() == ["b1", "b2", "c", "a", "d"]
+
+    asyncio.run(scenario())
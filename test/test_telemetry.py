@@ -0,0 +1,61 @@
+import asyncio
+
+from fastapi.testclient import TestClient
+
+from api.app import create_app
+from api.auth import NoAuth
+from api.exchange_rates import DumbExchangeRates
+from api.storage import InmemoryStorage
+from api.telemetry import Telemetry
+
+
+class CapturingTelemetry(Telemetry):
+    def __init__(self, endpoint: str | None) -> None:
+        super().__init__(endpoint)
+        self.sent: list[str] = []
+
+    async def _post(self, endpoint: str, payload: str) -> None:
+        self.sent.append(payload)
+
+
+def test_telemetry_preview() -> None:
+    telemetry = CapturingTelemetry(endpoint="https://telemetry.example.com")
+    client = TestClient(
+        create_app(
+            storage=InmemoryStorage(),
+            auth=NoAuth(),
+            exchange_rates=DumbExchangeRates(),
+            telemetry=telemetry,
+        )
+    )
+
+    response = client.post(
+        "/pools",
+        json={"display_name": "secret", "balance": [{"amount": 100, "currency": "EUR"}]},
+    )
+    assert response.status_code == 200
+    pool_id = response.json()["id"]
+    assert client.get(f"/pools/{pool_id}").status_code == 200
+
+    response = client.get("/telemetry/preview")
+    assert response.status_code == 200
+    report = response.json()
+    assert report["backend"] == "InmemoryStorage"
+    assert report["feature_usage"] == {"GET /pools/{pool_id}": 1, "POST /pools": 1}
+    assert pool_id not in response.text
+    assert "secret" not in response.text
+
+    preview = telemetry.report(backend="InmemoryStorage", version="0.1.0")
+    assert asyncio.run(telemetry.send(preview))
+    assert telemetry.sent == [preview.model_dump_json()]
+    assert telemetry.report(backend="InmemoryStorage", version="0.1.0").feature_usage == {
+        "GET /telemetry/preview": 1
+    }
+
+
+def test_telemetry_disabled() -> None:
+    telemetry = CapturingTelemetry(endpoint=None)
+    telemetry.record("POST /transactions")
+    assert not telemetry.enabled
+    assert not asyncio.run(telemetry.send(telemetry.report(backend="b", version="v")))
+    assert telemetry.sent == []
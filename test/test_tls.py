@@ -0,0 +1,61 @@
+import datetime
+import os
+import ssl
+from pathlib import Path
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.x509.oid import NameOID
+
+from api.tls import TlsConfig, reload_if_changed, tls_config_from_env
+
+
+def write_self_signed(tls: TlsConfig, hostname: str) -> None:
+    key = ec.generate_private_key(ec.SECP256R1())
+    name = x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, hostname)])
+    now = datetime.datetime.now(tz=datetime.UTC)
+    cert = (
+        x509.CertificateBuilder()
+        .subject_name(name)
+        .issuer_name(name)
+        .public_key(key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(now)
+        .not_valid_after(now + datetime.timedelta(days=1))
+        .sign(key, hashes.SHA256())
+    )
+    tls.cert_file.write_bytes(cert.public_bytes(serialization.Encoding.PEM))
+    tls.key_file.write_bytes(
+        key.private_bytes(
+            serialization.Encoding.PEM,
+            serialization.PrivateFormat.PKCS8,
+            serialization.NoEncryption(),
+        )
+    )
+
+
+def test_tls_config_from_env() -> None:
+    assert tls_config_from_env({}) is None
+    tls = tls_config_from_env({"TLS_CERT_FILE": "cert.pem", "TLS_KEY_FILE": "key.pem"})
+    assert tls == TlsConfig(cert_file=Path("cert.pem"), key_file=Path("key.pem"))
+
+
+def test_reload_if_changed(tmp_path: Path) -> None:
+    tls = TlsConfig(cert_file=tmp_path / "cert.pem", key_file=tmp_path / "key.pem")
+    write_self_signed(tls, "old.example.com")
+    context = ssl.SSLContext(ssl.PROTOCOL_TLS_SERVER)
+    context.load_cert_chain(tls.cert_file, tls.key_file)
+    loaded_at = tls.modified_at()
+    assert reload_if_changed(context, tls, loaded_at) == loaded_at
+
+    # renewal in progress, the old certificate stays in use until the new pair is consistent
+    tls.cert_file.write_text("not a certificate")
+    os.utime(tls.cert_file, (loaded_at[0] + 10, loaded_at[0] + 10))
+    assert reload_if_changed(context, tls, loaded_at) == loaded_at
+
+    write_self_signed(tls, "new.example.com")
+    os.utime(tls.cert_file, (loaded_at[0] + 20, loaded_at[0] + 20))
+    reloaded_at = reload_if_changed(context, tls, loaded_at)
+    assert reloaded_at == tls.modified_at()
+    assert reloaded_at != loaded_at
@@ -0,0 +1,30 @@
+import base64
+
+from api import totp
+
+# RFC 6238 test vectors for SHA-1, truncated to 6 digits
+RFC_SECRET = base64.b32encode(b"12345678901234567890").decode("ascii")
+
+
+def test_code_at() -> None:
+    assert totp.code_at(RFC_SECRET, totp.time_step(59)) == "287082"
+    assert totp.code_at(RFC_SECRET, totp.time_step(1111111109)) == "081804"
+    assert totp.code_at(RFC_SECRET, totp.time_step(1234567890)) == "005924"
+    assert totp.code_at(RFC_SECRET, totp.time_step(2000000000)) == "279037"
+
+
+def test_matching_step() -> None:
+    secret = totp.generate_secret()
+    now = 1_700_000_000.0
+    step = totp.time_step(now)
+    assert totp.matching_step(secret, totp.code_at(secret, step), now) == step
+    assert totp.matching_step(secret, totp.code_at(secret, step - 1), now) == step - 1
+    assert totp.matching_step(secret, totp.code_at(secret, step - 2), now) is None
+
+
+def test_provisioning_uri() -> None:
+    uri = totp.provisioning_uri("ABCDEF", account="user", issuer="tiny-expense-tracker")
+    assert uri == (
+        "otpauth://totp/tiny-expense-tracker%3Auser"
+        + "?secret=ABCDEF&issuer=tiny-expense-tracker&digits=6&period=30"
+    )
@@ -0,0 +1,85 @@
+import asyncio
+import datetime
+from decimal import Decimal
+
+import pytest
+
+from api.events import EventBus
+from api.exchange_rates import DumbExchangeRates
+from api.iso4217 import CURRENCIES
+from api.service import ExpenseService
+from api.storage import InmemoryStorage
+from api.tui import StorageBackend, TuiError, parse_expense, pool_line, transaction_line
+from api.types.money_pool import MoneyPool, StoredMoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import StoredTransaction
+
+
+def make_pool(balance: list[MoneySum]) -> StoredMoneyPool:
+    return StoredMoneyPool(id="pool", display_name="cash", balance=balance)
+
+
+def test_parse_expense() -> None:
+    pool = make_pool([MoneySum(amount=Decimal(10), currency="EUR")])
+    transaction = parse_expense(pool, " 12,50 ", " lunch ")
+    assert transaction.sum == MoneySum(amount=Decimal("-12.50"), currency="EUR")
+    assert (transaction.pool_id, transaction.description) == ("pool", "lunch")
+    for invalid in ["", "abc", "0", "-3", "nan"]:
+        with pytest.raises(TuiError):
+            parse_expense(pool, invalid, "")
+    with pytest.raises(TuiError):
+        parse_expense(make_pool([]), "1", "")
+
+
+def test_lines() -> None:
+    pool = make_pool(
+        [
+            MoneySum(amount=Decimal("1234.5"), currency="EUR"),
+            MoneySum(amount=Decimal(-3), currency="USD"),
+        ]
+    )
+    assert pool_line(pool, "en") == "cash  €1,234.50, -$3.00"
+    transaction = StoredTransaction(
+        id="t",
+        sum=MoneySum(amount=Decimal(-5), currency="EUR"),
+        pool_id="pool",
+        description="coffee",
+        timestamp=datetime.datetime(2024, 3, 1, 9, 30).astimezone(),
+    )
+    assert transaction_line(transaction, [pool], "en") == (
+        "2024-03-01 09:30        -€5.00  cash  coffee"
+    )
+    assert "  ?  " in transaction_line(transaction, [], "en")
+    transaction.original_currency = CURRENCIES["USD"]
+    transaction.original_amount = Decimal("-5.40")
+    assert transaction_line(transaction, [pool], "en").endswith("  coffee  (-$5.40)")
+
+
+def test_storage_backend() -> None:
+    storage = InmemoryStorage()
+    service = ExpenseService(
+        storage,
+        DumbExchangeRates(),
+        EventBus(),
+        privacy=None,
+        undo_window=datetime.timedelta(minutes=5),
+        duplicate_window=datetime.timedelta(0),
+    )
+    backend = StorageBackend(service, "user", descriptions=True)
+
+    async def scenario() -> None:
+        pool = await storage.add_pool(
+            "user",
+            MoneyPool(display_name="cash", balance=[MoneySum(amount=Decimal(20), currency="EUR")]),
+        )
+        assert await backend.load_locale() == "en"
+        await backend.add_transaction(parse_expense(pool, "7", "bread"))
+        [loaded] = await backend.load_pools()
+        assert loaded.balance == [MoneySum(amount=Decimal(13), currency="EUR")]
+        assert [t.description for t in await backend.load_recent_transactions()] == ["bread"]
+
+        other_pool = make_pool([MoneySum(amount=Decimal(0), currency="EUR")])
+        with pytest.raises(TuiError):
+            await backend.add_transaction(parse_expense(other_pool, "1", ""))
+
+    asyncio.run(scenario())
@@ -0,0 +1,48 @@
+import datetime
+from decimal import Decimal
+from test.utils import EUR, transaction
+
+from api.iso4217 import CURRENCIES
+from api.types.money_pool import MoneyPool
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+from api.validation import validate_transaction
+
+USD = CURRENCIES["USD"]
+
+NOW = datetime.datetime(2024, 9, 1, 12, tzinfo=datetime.UTC)
+POOL = MoneyPool(display_name="card", balance=[MoneySum(amount=Decimal("100"), currency=EUR)])
+
+
+def ago(days: float) -> datetime.datetime:
+    return NOW - datetime.timedelta(days=days)
+
+
+def codes(new: Transaction, recent: list[Transaction]) -> list[str]:
+    return [issue.code for issue in validate_transaction(new, POOL, recent)]
+
+
+def test_unknown_pool_is_an_error() -> None:
+    issues = validate_transaction(transaction("-5", timestamp=NOW), None, [])
+    assert [(i.code, i.severity) for i in issues] == [("unknown_pool", "error")]
+
+
+def test_large_amount() -> None:
+    amounts = ["-5", "-8", "-4", "12", "-6"]  # median 6
+    recent = [transaction(a, timestamp=ago(10 + i)) for i, a in enumerate(amounts)]
+
+    assert codes(transaction("-59", timestamp=NOW), recent) == []
+    assert codes(transaction("-61", timestamp=NOW), recent) == ["large_amount"]
+    assert codes(transaction("200", timestamp=NOW), recent) == ["large_amount"]
+    # not enough samples
+    assert codes(transaction("-61", timestamp=NOW), recent[:4]) == []
+    # other currencies are not comparable
+    assert codes(transaction("-61", USD, timestamp=NOW), recent) == []
+
+
+def test_possible_duplicate() -> None:
+    recent = [transaction("-5", timestamp=ago(0.5)), transaction("-7", timestamp=ago(3))]
+
+    assert codes(transaction("-5", timestamp=NOW), recent) == ["possible_duplicate"]
+    assert codes(transaction("-7", timestamp=NOW), recent) == []
+    assert codes(transaction("-5", USD, timestamp=NOW), recent) == []
@@ -1,7 +1,27 @@
 import copy
 import datetime
+from decimal import Decimal
 from typing import Any, Callable, TypeVar
 
+from api.exchange_rates import DumbExchangeRates, ExchangeRate, ExchangeRates, RateUnavailable
+from api.iso4217 import CURRENCIES
+from api.types.currency import Currency
+from api.types.money_sum import MoneySum
+from api.types.transaction import Transaction
+
+EUR = CURRENCIES["EUR"]
+
+
+def eur(amount: int | str) -> MoneySum:
+    return MoneySum(amount=Decimal(amount), currency=EUR)
+
+
+def transaction(amount: int | str, currency: Currency = EUR, **fields: Any) -> Transaction:
+    """In the "pool" pool with an empty description, unless given among the other fields"""
+    fields.setdefault("pool_id", "pool")
+    fields.setdefault("description", "")
+    return Transaction(sum=MoneySum(amount=Decimal(amount), currency=currency), **fields)
+
 
 DataT = TypeVar("DataT")
 
@@ -64,3 +84,44 @@ def mask_ids(data: DataT) -> DataT | str:
         return masked
 
     return mask_recursively(data, predicate=is_dict_with_id, mask=mask_id_field)
+
+
+class FixedExchangeRates(ExchangeRates):
+    """Constant rates, given per EUR as in the ECB's reference rates"""
+
+    def __init__(self, eur_rates: dict[str, float]) -> None:
+        self.eur_rates = eur_rates
+
+    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate:
+        eur_rates = {"EUR": 1.0, **self.eur_rates}
+        return ExchangeRate(
+            base=base,
+            target=target,
+            rate=eur_rates[target.code] / eur_rates[base.code],
+            updated_on=datetime.datetime.now(tz=datetime.UTC),
+        )
+
+
+class DatedUsdRates(ExchangeRates):
+    """1 USD is 0.8 GBP until October, 0.75 GBP after"""
+
+    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate:
+        return await self.get_rate_on(base, target, datetime.date.today())
+
+    async def get_rate_on(
+        self, base: Currency, target: Currency, on: datetime.date
+    ) -> ExchangeRate:
+        usd_rate = 0.8 if on < datetime.date(2024, 10, 1) else 0.75
+        return ExchangeRate(
+            base=base,
+            target=target,
+            rate=usd_rate if base.code == "USD" else 1 / usd_rate,
+            updated_on=datetime.datetime.now(tz=datetime.UTC),
+        )
+
+
+class NoGbpExchangeRates(DumbExchangeRates):
+    async def get_rate(self, base: Currency, target: Currency) -> ExchangeRate:
+        if "GBP" in (base.code, target.code):
+            raise RateUnavailable(base, target)
+        return await super().get_rate(base, target)